@@ -0,0 +1,82 @@
+//! The events of a run.
+
+use serde::{Deserialize, Serialize};
+
+use devflow_core::reporter::{CheckEnd, CommandEnd, OutputStream, Skip};
+
+/// One run event, in the reporter plugin protocol: a JSON object tagged by
+/// `event`, e.g. `{"event":"command_start","command":"test:unit"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A command is about to run.
+    CommandStart { command: String },
+    /// An output line of a command, without the line ending.
+    Output {
+        command: String,
+        stream: OutputStream,
+        line: String,
+    },
+    /// A command, or one stack's share of it, was skipped.
+    Skip(Skip),
+    /// A command finished.
+    CommandEnd(CommandEnd),
+    /// A `check:<profile>` run finished.
+    CheckEnd(CheckEnd),
+}
+
+impl Event {
+    /// Parses one line of the protocol.
+    ///
+    /// # Errors
+    /// Returns an error if `line` is not a known event.
+    pub fn parse(line: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::reporter::SkipReason;
+
+    #[test]
+    fn parses_the_reporter_protocol() {
+        let lines = [
+            r#"{"event":"command_start","command":"test:unit"}"#,
+            r#"{"event":"output","command":"test:unit","stream":"stdout","line":"running 12 tests"}"#,
+            r#"{"event":"skip","command":"test:unit","stack":"node","reason":"manifest_not_found"}"#,
+            r#"{"event":"command_end","command":"test:unit","success":true,"duration_ms":1200,"error":null,"exit_code":null,"signal":null,"problems":[],"artifacts":[]}"#,
+            r#"{"event":"check_end","profile":"pr","success":true,"duration_ms":5400,"commands":4,"failed":0,"exit_code":0,"skipped":[]}"#,
+        ];
+        let events: Vec<Event> = lines
+            .iter()
+            .map(|line| Event::parse(line).unwrap())
+            .collect();
+        assert_eq!(
+            events[1],
+            Event::Output {
+                command: "test:unit".to_string(),
+                stream: OutputStream::Stdout,
+                line: "running 12 tests".to_string(),
+            }
+        );
+        assert_eq!(
+            events[2],
+            Event::Skip(Skip {
+                command: "test:unit".to_string(),
+                stack: Some("node".to_string()),
+                reason: SkipReason::ManifestNotFound,
+            })
+        );
+        for (event, line) in events.iter().zip(lines) {
+            assert_eq!(serde_json::to_string(event).unwrap(), line);
+        }
+        assert!(Event::parse(r#"{"event":"progress"}"#).is_err());
+        // Logs of a dwf from before per-command exit codes still parse.
+        assert!(Event::parse(
+            r#"{"event":"check_end","profile":"pr","success":true,"duration_ms":5400,"commands":4,"failed":0,"skipped":[]}"#
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,31 @@
+//! Run events for GUIs, such as the `examples/tauri` app.
+//!
+//! A GUI should not have to scrape `dwf` output to show how a run is going.
+//! This crate provides:
+//!
+//! - [`Event`]: the events of a run, as `dwf --events <PATH>` and reporter
+//!   plugins receive them, serializable to forward to a frontend;
+//! - [`Progress`]: the per-command state of a run, folded from its events;
+//! - [`Session`]: runs `dwf` and hands every event to a [`Subscriber`] as it
+//!   happens.
+//!
+//! ```no_run
+//! use devflow_bridge::{Event, Progress, Session};
+//!
+//! let mut progress = Progress::default();
+//! let session = Session::start("dwf", "path/to/project", ["check:pr"], move |event: &Event| {
+//!     progress.apply(event);
+//!     // e.g. `app.emit("devflow-progress", &progress)` in a Tauri command
+//! })
+//! .unwrap();
+//! let status = session.wait().unwrap();
+//! # let _ = status;
+//! ```
+
+pub mod event;
+pub mod progress;
+pub mod session;
+
+pub use event::Event;
+pub use progress::{CommandProgress, CommandStatus, Progress};
+pub use session::{Session, Subscriber};
@@ -0,0 +1,176 @@
+//! The per-command state of a run, for progress views.
+
+use serde::Serialize;
+
+use devflow_core::reporter::{CheckEnd, OutputStream};
+
+use crate::Event;
+
+/// Where a command stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandStatus {
+    Running,
+    Passed,
+    Failed,
+    /// No stack ran the command.
+    Skipped,
+}
+
+/// One command of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandProgress {
+    /// Canonical command, e.g. `test:unit`.
+    pub command: String,
+    pub status: CommandStatus,
+    /// Set once the command finished.
+    pub duration_ms: Option<u64>,
+    /// Output lines so far.
+    pub lines: usize,
+    /// The latest output line, e.g. for a status bar.
+    pub last_line: Option<String>,
+    /// Stderr lines so far.
+    pub stderr_lines: usize,
+    /// The error the command failed with.
+    pub error: Option<String>,
+    /// Diagnostics found by problem matchers.
+    pub problems: usize,
+}
+
+impl CommandProgress {
+    fn new(command: &str, status: CommandStatus) -> Self {
+        Self {
+            command: command.to_string(),
+            status,
+            duration_ms: None,
+            lines: 0,
+            last_line: None,
+            stderr_lines: 0,
+            error: None,
+            problems: 0,
+        }
+    }
+}
+
+/// The commands of a run in the order they started, folded from its events.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub commands: Vec<CommandProgress>,
+    /// Set once a `check:<profile>` run finished.
+    pub check: Option<CheckEnd>,
+}
+
+impl Progress {
+    /// Updates the run with `event`.
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::CommandStart { command } => {
+                self.commands
+                    .push(CommandProgress::new(command, CommandStatus::Running));
+            }
+            Event::Output {
+                command,
+                stream,
+                line,
+            } => {
+                if let Some(progress) = self.current(command) {
+                    progress.lines += 1;
+                    if *stream == OutputStream::Stderr {
+                        progress.stderr_lines += 1;
+                    }
+                    progress.last_line = Some(line.clone());
+                }
+            }
+            // A stack's skip leaves the command to the other stacks.
+            Event::Skip(skip) if skip.stack.is_none() => match self.current(&skip.command) {
+                Some(progress) => progress.status = CommandStatus::Skipped,
+                None => self
+                    .commands
+                    .push(CommandProgress::new(&skip.command, CommandStatus::Skipped)),
+            },
+            Event::Skip(_) => {}
+            Event::CommandEnd(end) => {
+                let progress = match self.current(&end.command) {
+                    Some(progress) => progress,
+                    None => {
+                        self.commands
+                            .push(CommandProgress::new(&end.command, CommandStatus::Running));
+                        self.commands.last_mut().expect("just pushed")
+                    }
+                };
+                progress.status = if end.success {
+                    CommandStatus::Passed
+                } else {
+                    CommandStatus::Failed
+                };
+                progress.duration_ms = Some(end.duration_ms);
+                progress.error = end.error.clone();
+                progress.problems = end.problems.len();
+            }
+            Event::CheckEnd(end) => self.check = Some(end.clone()),
+        }
+    }
+
+    /// The latest still-running entry of `command`.
+    fn current(&mut self, command: &str) -> Option<&mut CommandProgress> {
+        self.commands.iter_mut().rev().find(|progress| {
+            progress.command == command && progress.status == CommandStatus::Running
+        })
+    }
+
+    /// Commands finished (passed, failed or skipped), for a progress bar.
+    pub fn finished(&self) -> usize {
+        self.commands
+            .iter()
+            .filter(|progress| progress.status != CommandStatus::Running)
+            .count()
+    }
+
+    /// Whether every command so far passed or was skipped.
+    pub fn success(&self) -> bool {
+        self.check.as_ref().map_or_else(
+            || {
+                self.commands
+                    .iter()
+                    .all(|progress| progress.status != CommandStatus::Failed)
+            },
+            |check| check.success,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_events_into_command_progress() {
+        let mut progress = Progress::default();
+        for line in [
+            r#"{"event":"command_start","command":"fmt:check"}"#,
+            r#"{"event":"command_end","command":"fmt:check","success":true,"duration_ms":300,"error":null,"problems":[],"artifacts":[]}"#,
+            r#"{"event":"command_start","command":"test:unit"}"#,
+            r#"{"event":"output","command":"test:unit","stream":"stdout","line":"running 12 tests"}"#,
+            r#"{"event":"output","command":"test:unit","stream":"stderr","line":"test parse ... FAILED"}"#,
+            r#"{"event":"skip","command":"test:unit","stack":"node","reason":"manifest_not_found"}"#,
+            r#"{"event":"skip","command":"lint:types","stack":null,"reason":"unsupported_command"}"#,
+        ] {
+            progress.apply(&Event::parse(line).unwrap());
+        }
+        let unit = &progress.commands[1];
+        assert_eq!(unit.status, CommandStatus::Running);
+        assert_eq!((unit.lines, unit.stderr_lines), (2, 1));
+        assert_eq!(unit.last_line.as_deref(), Some("test parse ... FAILED"));
+        assert_eq!(progress.commands[2].status, CommandStatus::Skipped);
+        assert_eq!(progress.finished(), 2);
+
+        progress.apply(&Event::parse(
+            r#"{"event":"command_end","command":"test:unit","success":false,"duration_ms":1200,"error":"1 test failed","problems":[],"artifacts":[]}"#,
+        ).unwrap());
+        let unit = &progress.commands[1];
+        assert_eq!(unit.status, CommandStatus::Failed);
+        assert_eq!(unit.duration_ms, Some(1200));
+        assert_eq!(unit.error.as_deref(), Some("1 test failed"));
+        assert!(!progress.success());
+    }
+}
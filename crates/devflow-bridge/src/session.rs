@@ -0,0 +1,210 @@
+//! Runs `dwf` and follows its events.
+//!
+//! The session passes `--events <file>` to `dwf` and reads the file as `dwf`
+//! appends to it, so events arrive while the run is going, on every OS. The
+//! output of the run's commands arrives as [`Event::Output`]; `dwf`'s own
+//! stdout is discarded and its stderr is inherited.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use tempfile::TempDir;
+
+use crate::Event;
+
+/// How often the event file is checked for new lines.
+const POLL: Duration = Duration::from_millis(25);
+
+/// Receives the events of a [`Session`], on the session's reader thread.
+pub trait Subscriber: Send + 'static {
+    fn on_event(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event) + Send + 'static> Subscriber for F {
+    fn on_event(&mut self, event: &Event) {
+        self(event);
+    }
+}
+
+/// A running `dwf` invocation.
+///
+/// Dropping a session that was not waited for kills the run.
+pub struct Session {
+    child: Child,
+    exited: Arc<AtomicBool>,
+    reader: Option<JoinHandle<std::io::Result<()>>>,
+    /// Holds the event file.
+    _events: TempDir,
+}
+
+impl Session {
+    /// Runs `dwf <args>` in `dir`, handing its events to `subscriber`.
+    ///
+    /// # Errors
+    /// Returns an error if the event file cannot be created or `dwf` cannot
+    /// be started.
+    pub fn start<I, S>(
+        dwf: impl AsRef<OsStr>,
+        dir: impl AsRef<Path>,
+        args: I,
+        mut subscriber: impl Subscriber,
+    ) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let events = tempfile::tempdir().context("failed to create the event directory")?;
+        let path = events.path().join("events.jsonl");
+        // Created up front so the reader can open it before `dwf` does.
+        File::create(&path).context("failed to create the event file")?;
+        let file = File::open(&path).context("failed to open the event file")?;
+        let dwf = dwf.as_ref();
+        let child = Command::new(dwf)
+            .arg("--events")
+            .arg(&path)
+            .args(args)
+            .current_dir(dir)
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to start {}", dwf.to_string_lossy()))?;
+        let exited = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let exited = Arc::clone(&exited);
+            std::thread::spawn(move || follow(file, &exited, &mut subscriber))
+        };
+        Ok(Self {
+            child,
+            exited,
+            reader: Some(reader),
+            _events: events,
+        })
+    }
+
+    /// The process id of `dwf`.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Stops the run, e.g. for a cancel button; [`Session::wait`] still
+    /// delivers the events written so far.
+    ///
+    /// # Errors
+    /// Returns an error if the process cannot be killed.
+    pub fn kill(&mut self) -> Result<()> {
+        self.child.kill().context("failed to stop dwf")
+    }
+
+    /// Waits for `dwf` to exit and its last events to be delivered.
+    ///
+    /// # Errors
+    /// Returns an error if waiting fails or the event file cannot be read.
+    pub fn wait(mut self) -> Result<ExitStatus> {
+        let status = self.child.wait().context("failed to wait for dwf")?;
+        self.finish()?;
+        Ok(status)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.exited.store(true, Ordering::Release);
+        match self.reader.take().map(JoinHandle::join) {
+            Some(Ok(read)) => read.context("failed to read the event file"),
+            Some(Err(_)) => Err(anyhow!("an event subscriber panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.reader.is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+            let _ = self.finish();
+        }
+    }
+}
+
+/// Hands every complete line of `file` to `subscriber` until `exited` is set
+/// and the file is read to its end. Lines that are not known events, e.g.
+/// from a newer `dwf`, are skipped.
+fn follow(
+    mut file: File,
+    exited: &AtomicBool,
+    subscriber: &mut impl Subscriber,
+) -> std::io::Result<()> {
+    let mut pending = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+        // Checked before reading, so the last read sees everything written.
+        let last = exited.load(Ordering::Acquire);
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            if last {
+                return Ok(());
+            }
+            std::thread::sleep(POLL);
+            continue;
+        }
+        pending.extend_from_slice(&buf[..read]);
+        while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            if let Ok(event) = serde_json::from_slice::<Event>(&line) {
+                subscriber.on_event(&event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::mpsc;
+
+    #[test]
+    fn delivers_events_while_dwf_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let dwf = dir.path().join("dwf");
+        std::fs::write(
+            &dwf,
+            r#"#!/bin/sh
+[ "$1" = "--events" ] && [ "$3" = "check:pr" ] || exit 2
+echo '{"event":"command_start","command":"test:unit"}' >> "$2"
+sleep 0.2
+printf '{"event":"future_event"}\n{"event":"command_end","command":"test:unit",' >> "$2"
+echo '"success":false,"duration_ms":200,"error":"boom","problems":[],"artifacts":[]}' >> "$2"
+exit 1
+"#,
+        )
+        .unwrap();
+        std::fs::set_permissions(&dwf, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let (sender, events) = mpsc::channel();
+        let session = Session::start(&dwf, dir.path(), ["check:pr"], move |event: &Event| {
+            sender.send(event.clone()).unwrap();
+        })
+        .unwrap();
+        let first = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            first,
+            Event::CommandStart {
+                command: "test:unit".to_string()
+            }
+        );
+        let status = session.wait().unwrap();
+        assert_eq!(status.code(), Some(1));
+        let rest: Vec<Event> = events.try_iter().collect();
+        assert_eq!(rest.len(), 1, "{rest:?}");
+        assert!(matches!(&rest[0], Event::CommandEnd(end) if end.error.as_deref() == Some("boom")));
+    }
+}
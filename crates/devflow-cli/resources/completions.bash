@@ -0,0 +1,52 @@
+# dwf completion for bash: eval "$(dwf completions:bash)" in ~/.bashrc.
+#
+# Commands come from the project's completion cache (.devflow/completions next
+# to devflow.toml), which dwf refreshes whenever it discovers the extensions.
+# The script never runs dwf: that would run the extensions of whatever
+# checkout Tab is pressed in. Without a cache it offers devflow's own commands.
+
+_dwf_builtin_commands="@BUILTIN_COMMANDS@"
+
+_dwf_completion_file() {
+    local dir="$PWD"
+    while [ -n "$dir" ]; do
+        if [ -f "$dir/devflow.toml" ]; then
+            printf '%s\n' "$dir/.devflow/completions"
+            return 0
+        fi
+        [ "$dir" = "/" ] && return 1
+        dir="$(dirname "$dir")"
+    done
+    return 1
+}
+
+_dwf() {
+    local line="${COMP_LINE:0:COMP_POINT}"
+    local cur="${line##*[[:space:]]}"
+    local -a words positional=()
+    read -ra words <<< "${line%"$cur"}"
+    local word
+    for word in "${words[@]:1}"; do
+        [[ "$word" == -* ]] || positional+=("$word")
+    done
+    # Only the command is completed: `dwf test:u` or `dwf test u`.
+    [[ "$cur" == -* || ${#positional[@]} -gt 1 ]] && return 0
+
+    local file candidates
+    if file="$(_dwf_completion_file)" && [ -r "$file" ]; then
+        candidates="$(cat "$file")"
+    else
+        candidates="$(printf '%s\n' $_dwf_builtin_commands)"
+    fi
+    if [ ${#positional[@]} -eq 1 ]; then
+        candidates="$(sed -n "s/^${positional[0]}://p" <<< "$candidates")"
+    fi
+    COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+    # Bash splits words on ':', so drop what precedes the one being completed.
+    if [[ "$cur" == *:* ]]; then
+        local prefix="${cur%"${cur##*:}"}"
+        COMPREPLY=("${COMPREPLY[@]#"$prefix"}")
+    fi
+}
+
+complete -o default -F _dwf dwf
@@ -0,0 +1,185 @@
+//! Single-job workflow debugging with `act` (`dwf ci:debug <job>`).
+//!
+//! The profile's workflow is rendered exactly as `ci:generate --split` would
+//! write it, next to a synthetic event payload for the local commit, and
+//! `act` runs the requested job (and the jobs it needs) in a runner container.
+//! The workspace is bind-mounted so the workflow's `docker run` steps and the
+//! `.cargo-cache`/`target/ci` caches use the real checkout, and the action
+//! cache lives under the devflow cache root between runs.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+
+use devflow_core::DevflowConfig;
+use devflow_gh::jobs::{self, Revision};
+
+use crate::{changes, executor};
+
+/// Runner image `act` uses for every `runs-on` label of the workflow.
+const RUNNER_IMAGE: &str = "catthehacker/ubuntu:act-latest";
+
+/// Runs `job` of `profile`'s generated workflow locally with `act`; with
+/// `dry_run` only prints the invocation.
+///
+/// # Errors
+/// Returns an error if the profile or job is unknown, the workflow cannot be
+/// rendered, `act` is not installed, or the job fails.
+pub fn debug(cfg: &DevflowConfig, profile: &str, job: &str, dry_run: bool) -> Result<()> {
+    let jobs = jobs::profile_jobs(cfg, profile)?;
+    if !jobs.iter().any(|j| j.id == job) {
+        let ids: Vec<&str> = jobs.iter().map(|j| j.id.as_str()).collect();
+        bail!(
+            "unknown job '{}' in the {} workflow (expected one of: {})",
+            job,
+            profile,
+            ids.join(", ")
+        );
+    }
+    let workspace = match cfg.source_dir.as_deref() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => std::env::current_dir()?,
+    };
+    let dir = executor::cache_root(cfg).join("act");
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create '{}'", dir.display()))?;
+
+    let workflow = devflow_gh::render_profile_workflow(cfg, profile)?;
+    let workflow_path = dir.join(format!("{profile}.yml"));
+    fs::write(&workflow_path, workflow)
+        .with_context(|| format!("failed to write '{}'", workflow_path.display()))?;
+    let event = jobs::profile_event(profile);
+    let payload = jobs::event_payload(profile, &revision(&workspace)?);
+    let event_path = dir.join("event.json");
+    fs::write(&event_path, format!("{payload:#}\n"))
+        .with_context(|| format!("failed to write '{}'", event_path.display()))?;
+
+    let mut labels: Vec<String> = Vec::new();
+    for workflow_job in &jobs {
+        for label in workflow_job.runner.labels() {
+            if !labels.iter().any(|l| l == label) {
+                labels.push(label.to_string());
+            }
+        }
+    }
+    let args = act_args(event, &dir, &workflow_path, &event_path, job, &labels);
+    if dry_run {
+        println!("act {}", args.join(" "));
+        return Ok(());
+    }
+
+    println!(
+        "ci:debug {profile}/{job} ({event} event, {})",
+        workflow_path.display()
+    );
+    let status = match Command::new("act")
+        .args(&args)
+        .current_dir(&workspace)
+        .status()
+    {
+        Ok(status) => status,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            bail!("ci:debug needs 'act' on PATH (see https://nektosact.com), or use 'dwf ci:run'")
+        }
+        Err(e) => return Err(e).context("failed to start 'act'"),
+    };
+    if !status.success() {
+        bail!("ci:debug {profile}/{job} failed ({status})");
+    }
+    Ok(())
+}
+
+/// Describes the checked-out commit. Without a fetched base branch the pull
+/// request base is the commit itself, so change detection sees no changes.
+fn revision(workspace: &Path) -> Result<Revision> {
+    let rev_parse =
+        |rev: &str| changes::git(workspace, &["rev-parse", rev]).map(|out| out.trim().to_string());
+    let sha = rev_parse("HEAD")?;
+    let branch = changes::git(workspace, &["rev-parse", "--abbrev-ref", "HEAD"])?
+        .trim()
+        .to_string();
+    let base_branch = changes::DEFAULT_BASE_BRANCH.to_string();
+    let base_sha = rev_parse(&format!("origin/{base_branch}")).unwrap_or_else(|_| sha.clone());
+    let actor = changes::git(workspace, &["config", "user.name"])
+        .map(|out| out.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "devflow".to_string());
+    Ok(Revision {
+        sha,
+        branch,
+        base_sha,
+        base_branch,
+        actor,
+    })
+}
+
+fn act_args(
+    event: &str,
+    dir: &Path,
+    workflow: &Path,
+    event_path: &Path,
+    job: &str,
+    labels: &[String],
+) -> Vec<String> {
+    let path = |p: PathBuf| p.display().to_string();
+    let mut args = vec![
+        event.to_string(),
+        "--workflows".to_string(),
+        workflow.display().to_string(),
+        "--eventpath".to_string(),
+        event_path.display().to_string(),
+        "--job".to_string(),
+        job.to_string(),
+        "--bind".to_string(),
+        "--cache-server-path".to_string(),
+        path(dir.join("cache")),
+        "--artifact-server-path".to_string(),
+        path(dir.join("artifacts")),
+    ];
+    for label in labels {
+        args.extend(["--platform".to_string(), format!("{label}={RUNNER_IMAGE}")]);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn act_args_select_the_job_and_map_runner_labels() {
+        let dir = Path::new("/cache/act");
+        let args = act_args(
+            "pull_request",
+            dir,
+            &dir.join("pr.yml"),
+            &dir.join("event.json"),
+            "check_test_unit",
+            &["ubuntu-latest".to_string(), "ubuntu-24.04-arm".to_string()],
+        );
+        assert_eq!(
+            args,
+            [
+                "pull_request",
+                "--workflows",
+                "/cache/act/pr.yml",
+                "--eventpath",
+                "/cache/act/event.json",
+                "--job",
+                "check_test_unit",
+                "--bind",
+                "--cache-server-path",
+                "/cache/act/cache",
+                "--artifact-server-path",
+                "/cache/act/artifacts",
+                "--platform",
+                "ubuntu-latest=catthehacker/ubuntu:act-latest",
+                "--platform",
+                "ubuntu-24.04-arm=catthehacker/ubuntu:act-latest",
+            ]
+        );
+    }
+}
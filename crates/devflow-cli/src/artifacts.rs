@@ -0,0 +1,153 @@
+//! Keeps the reports of a failed test command, such as end-to-end test
+//! screenshots and videos.
+//!
+//! The runner writes them into the workspace, where the next run overwrites
+//! them. When a `test:*` command fails, the files matching its stacks'
+//! artifact patterns are copied to `.devflow/artifacts/<command>/<stack>/`,
+//! listed in the command's `command_end` event and uploaded as a job artifact
+//! by the generated workflow.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use devflow_core::artifact::ArtifactManifest;
+use devflow_core::constants::FAILURE_ARTIFACTS_DIR;
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry, PrimaryCommand};
+
+use crate::executor;
+
+/// Copies the artifacts of the failed `cmd` out of the workspace, returning
+/// their new workspace-relative paths. Failing to is only warned about, as
+/// the command's own failure is what gets reported.
+pub fn keep_failure_artifacts(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    cmd: &CommandRef,
+) -> Vec<String> {
+    if cmd.primary != PrimaryCommand::Test {
+        return Vec::new();
+    }
+    let result = std::env::current_dir()
+        .map_err(Into::into)
+        .and_then(|cwd| keep(cfg, registry, cmd, &cwd));
+    match result {
+        Ok(kept) => {
+            if !kept.is_empty() {
+                info!(
+                    target: "devflow",
+                    "kept {} failure artifact(s) of {} in {}",
+                    kept.len(),
+                    cmd,
+                    FAILURE_ARTIFACTS_DIR
+                );
+            }
+            kept
+        }
+        Err(e) => {
+            warn!("failed to keep the artifacts of {}: {:#}", cmd, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Copies the artifacts of `cmd` under `workspace`, returning their paths
+/// relative to it.
+fn keep(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    cmd: &CommandRef,
+    workspace: &Path,
+) -> Result<Vec<String>> {
+    let rel = format!(
+        "{FAILURE_ARTIFACTS_DIR}/{}",
+        cmd.canonical().replace(':', "-")
+    );
+    let dest = workspace.join(&rel);
+    if dest.exists() {
+        std::fs::remove_dir_all(&dest)
+            .with_context(|| format!("failed to clean {}", dest.display()))?;
+    }
+    let mut kept = Vec::new();
+    for planned in executor::resolve(cfg, registry, cmd)?.selected {
+        let Some(ext) = registry.get(&planned.stack) else {
+            continue;
+        };
+        // Never re-collect what earlier failures kept.
+        let mut patterns = ext.artifact_patterns(&planned.command);
+        patterns.push(format!("!{FAILURE_ARTIFACTS_DIR}/**"));
+        let manifest = ArtifactManifest::collect(workspace, &patterns)?;
+        let copied = manifest.copy_to(&dest.join(&planned.stack))?;
+        kept.extend(
+            copied
+                .entries
+                .keys()
+                .map(|path| format!("{rel}/{}/{path}", planned.stack)),
+        );
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    use devflow_core::{ExecutionAction, Extension};
+
+    #[derive(Debug)]
+    struct E2e;
+
+    impl Extension for E2e {
+        fn name(&self) -> &str {
+            "node"
+        }
+
+        fn capabilities(&self) -> HashSet<String> {
+            ["test:e2e".to_string()].into()
+        }
+
+        fn build_action(&self, _cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
+            Ok(Some(ExecutionAction {
+                program: "npx".to_string(),
+                args: vec!["playwright".to_string(), "test".to_string()],
+                env: Default::default(),
+            }))
+        }
+
+        fn artifact_patterns(&self, _cmd: &CommandRef) -> Vec<String> {
+            vec!["test-results/**".to_string()]
+        }
+    }
+
+    #[test]
+    fn keeps_the_reports_of_a_failed_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("devflow.toml");
+        std::fs::write(&path, "[project]\nname = \"demo\"\nstack = [\"node\"]\n").unwrap();
+        let cfg = DevflowConfig::load_from_file(path.to_str().unwrap()).unwrap();
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(E2e));
+        let cmd = CommandRef::from_str("test:e2e").unwrap();
+
+        std::fs::create_dir_all(dir.path().join("test-results/login")).unwrap();
+        std::fs::write(dir.path().join("test-results/login/failed.png"), "png").unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        let stale = dir.path().join(".devflow/artifacts/test-e2e/node/old.png");
+        std::fs::create_dir_all(stale.parent().unwrap()).unwrap();
+        std::fs::write(&stale, "old").unwrap();
+
+        let kept = keep(&cfg, &registry, &cmd, dir.path()).unwrap();
+        assert_eq!(
+            kept,
+            [".devflow/artifacts/test-e2e/node/test-results/login/failed.png"]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(&kept[0])).unwrap(),
+            "png"
+        );
+        assert!(!stale.exists());
+    }
+}
@@ -0,0 +1,408 @@
+//! Build provenance (`package:attest`).
+//!
+//! Builds `package:artifact`, then writes an in-toto statement with a SLSA v1
+//! provenance predicate describing the artifacts: builder identity, the
+//! environment fingerprint, the executed command log, and lockfile materials.
+//! With `--sign`, the statement is signed with `cosign sign-blob`.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use devflow_core::artifact::ArtifactManifest;
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry, PrimaryCommand};
+
+use crate::executor::{self, RunOptions};
+use crate::fingerprint;
+
+/// Workspace-relative directory receiving the provenance statement.
+const ATTEST_DIR: &str = ".devflow/attest";
+/// Workspace-relative directory used as the build output dir for attested builds.
+const OUTPUT_DIR: &str = ".devflow/out";
+/// File name of the provenance statement.
+const PROVENANCE_FILE: &str = "provenance.intoto.json";
+
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+const BUILD_TYPE: &str = "https://github.com/softmentor/devflow/package-attest/v1";
+
+#[derive(Debug, Serialize)]
+struct Statement {
+    #[serde(rename = "_type")]
+    statement_type: &'static str,
+    subject: Vec<ResourceDescriptor>,
+    #[serde(rename = "predicateType")]
+    predicate_type: &'static str,
+    predicate: Provenance,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct ResourceDescriptor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+    digest: Digest256,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct Digest256 {
+    sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Provenance {
+    build_definition: BuildDefinition,
+    run_details: RunDetails,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildDefinition {
+    build_type: &'static str,
+    external_parameters: serde_json::Value,
+    internal_parameters: serde_json::Value,
+    resolved_dependencies: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunDetails {
+    builder: Builder,
+    metadata: RunMetadata,
+    byproducts: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+struct Builder {
+    id: String,
+    version: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    invocation_id: Option<String>,
+    started_on: String,
+    finished_on: String,
+}
+
+/// Builds the package artifacts and writes (optionally signs) their provenance.
+pub fn run(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    opts: &RunOptions,
+    sign: bool,
+) -> Result<()> {
+    let build = CommandRef {
+        primary: PrimaryCommand::Package,
+        selector: Some("artifact".to_string()),
+    };
+    registry.ensure_can_run(&build)?;
+
+    let cwd = std::env::current_dir()?;
+    let stacks: Vec<&str> = cfg
+        .project
+        .stack
+        .iter()
+        .map(String::as_str)
+        .filter(|stack| registry.get(stack).is_some())
+        .collect();
+
+    let command_log: Vec<String> = executor::plan(cfg, registry, &build)?
+        .iter()
+        .map(|planned| {
+            let mut line = format!(
+                "[{}] {}: {}",
+                planned.stack, planned.command, planned.action.program
+            );
+            for arg in &planned.action.args {
+                line.push(' ');
+                line.push_str(arg);
+            }
+            line
+        })
+        .collect();
+
+    let mut build_opts = opts.clone();
+    for stack in &stacks {
+        let ext = registry.get(stack).expect("filtered above");
+        if let Some(var) = ext.output_dir_env() {
+            let path = executor::workspace_path(cfg, &format!("{OUTPUT_DIR}/{stack}"))?;
+            build_opts.env.insert(var.to_string(), path);
+        }
+    }
+
+    println!("package:attest (runtime={:?})", cfg.runtime.profile);
+    println!(" - {}", build);
+    let started_on = rfc3339(SystemTime::now());
+    executor::run(cfg, registry, &build, &build_opts)?;
+    let finished_on = rfc3339(SystemTime::now());
+
+    let mut subject = Vec::new();
+    for stack in &stacks {
+        let ext = registry.get(stack).expect("filtered above");
+        let root = match ext.output_dir_env() {
+            Some(_) => cwd.join(OUTPUT_DIR).join(stack),
+            None => cwd.clone(),
+        };
+        let manifest = ArtifactManifest::collect(&root, &ext.artifact_patterns(&build))?;
+        for (rel, entry) in &manifest.entries {
+            let path = manifest.path_of(rel);
+            subject.push(ResourceDescriptor {
+                name: Some(relative_name(&cwd, &path)),
+                uri: None,
+                digest: Digest256 {
+                    sha256: entry.sha256.clone(),
+                },
+            });
+        }
+    }
+    if subject.is_empty() {
+        bail!("package:attest found no artifacts produced by {}", build);
+    }
+
+    let inputs: Vec<String> = fingerprint::inputs(cfg, registry)
+        .into_iter()
+        .map(|(input, _)| input)
+        .collect();
+    let fingerprint = fingerprint::compute(cfg, registry, &cwd)?;
+
+    let statement = Statement {
+        statement_type: STATEMENT_TYPE,
+        subject,
+        predicate_type: PREDICATE_TYPE,
+        predicate: Provenance {
+            build_definition: BuildDefinition {
+                build_type: BUILD_TYPE,
+                external_parameters: serde_json::json!({
+                    "project": cfg.project.name,
+                    "command": build.canonical(),
+                    "stacks": stacks,
+                }),
+                internal_parameters: serde_json::json!({
+                    "fingerprint": fingerprint,
+                    "runtime": format!("{:?}", cfg.runtime.profile).to_lowercase(),
+                }),
+                resolved_dependencies: materials(&cwd, &inputs)?,
+            },
+            run_details: RunDetails {
+                builder: Builder {
+                    id: builder_id(),
+                    version: serde_json::json!({ "dwf": env!("CARGO_PKG_VERSION") }),
+                },
+                metadata: RunMetadata {
+                    invocation_id: invocation_id(),
+                    started_on,
+                    finished_on,
+                },
+                byproducts: vec![ResourceDescriptor {
+                    name: Some("command-log".to_string()),
+                    uri: None,
+                    digest: Digest256 {
+                        sha256: hex::encode(Sha256::digest(command_log.join("\n").as_bytes())),
+                    },
+                }],
+            },
+        },
+    };
+
+    let mut document = serde_json::to_value(&statement)?;
+    // Keep the log itself readable inside the statement, next to its digest.
+    document["predicate"]["runDetails"]["byproducts"][0]["content"] =
+        serde_json::json!(command_log);
+
+    let attest_dir = cwd.join(ATTEST_DIR);
+    std::fs::create_dir_all(&attest_dir)?;
+    let path = attest_dir.join(PROVENANCE_FILE);
+    std::fs::write(&path, serde_json::to_string_pretty(&document)? + "\n")
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    println!(
+        "package:attest wrote {} ({} subject(s))",
+        relative_name(&cwd, &path),
+        statement.subject.len()
+    );
+
+    if sign {
+        sign_blob(&path)?;
+    }
+    Ok(())
+}
+
+/// Hashes the lockfiles and manifests that exist among the fingerprint inputs.
+fn materials(base: &Path, inputs: &[String]) -> Result<Vec<ResourceDescriptor>> {
+    let mut out = Vec::new();
+    for input in inputs {
+        let path = base.join(input);
+        if !path.is_file() {
+            continue;
+        }
+        let content = std::fs::read(&path)
+            .with_context(|| format!("failed to read material {}", path.display()))?;
+        out.push(ResourceDescriptor {
+            name: None,
+            uri: Some(input.clone()),
+            digest: Digest256 {
+                sha256: hex::encode(Sha256::digest(&content)),
+            },
+        });
+    }
+    Ok(out)
+}
+
+/// Identifies the build platform: the workflow in GitHub Actions, `user@host` locally.
+fn builder_id() -> String {
+    let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+    if let Some(workflow_ref) = env("GITHUB_WORKFLOW_REF") {
+        return format!("https://github.com/{workflow_ref}");
+    }
+    let user = env("USER").unwrap_or_else(|| "unknown".to_string());
+    let host = env("HOSTNAME").unwrap_or_else(|| "localhost".to_string());
+    format!("local://{user}@{host}")
+}
+
+fn invocation_id() -> Option<String> {
+    let repo = std::env::var("GITHUB_REPOSITORY").ok()?;
+    let run_id = std::env::var("GITHUB_RUN_ID").ok()?;
+    Some(format!(
+        "https://github.com/{}/actions/runs/{}",
+        repo, run_id
+    ))
+}
+
+/// Signs the statement with cosign, writing a Sigstore bundle next to it.
+fn sign_blob(path: &Path) -> Result<()> {
+    let bundle = path.with_extension("sigstore.json");
+    let status = Command::new("cosign")
+        .args(["sign-blob", "--yes", "--bundle"])
+        .arg(&bundle)
+        .arg(path)
+        .status()
+        .context("failed to start cosign (is it installed?)")?;
+    if !status.success() {
+        bail!("cosign sign-blob failed with status {}", status);
+    }
+    println!("package:attest signed bundle {}", bundle.display());
+    Ok(())
+}
+
+fn relative_name(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Formats a timestamp as RFC 3339 UTC with second precision.
+pub fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        (rem % 3_600) / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_rfc3339_timestamps() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(1_709_251_199)),
+            "2024-02-29T23:59:59Z"
+        );
+    }
+
+    #[test]
+    fn materials_hash_existing_lockfiles_only() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.lock"), "lock").unwrap();
+
+        let inputs = vec!["Cargo.lock".to_string(), "package-lock.json".to_string()];
+        let out = materials(dir.path(), &inputs).unwrap();
+
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].uri.as_deref(), Some("Cargo.lock"));
+        assert_eq!(out[0].digest.sha256, hex::encode(Sha256::digest(b"lock")));
+    }
+
+    #[test]
+    fn statement_uses_slsa_field_names() {
+        let statement = Statement {
+            statement_type: STATEMENT_TYPE,
+            subject: vec![ResourceDescriptor {
+                name: Some("dist/app.js".to_string()),
+                uri: None,
+                digest: Digest256 {
+                    sha256: "ab".to_string(),
+                },
+            }],
+            predicate_type: PREDICATE_TYPE,
+            predicate: Provenance {
+                build_definition: BuildDefinition {
+                    build_type: BUILD_TYPE,
+                    external_parameters: serde_json::json!({}),
+                    internal_parameters: serde_json::json!({}),
+                    resolved_dependencies: vec![],
+                },
+                run_details: RunDetails {
+                    builder: Builder {
+                        id: "local://dev@box".to_string(),
+                        version: serde_json::json!({}),
+                    },
+                    metadata: RunMetadata {
+                        invocation_id: None,
+                        started_on: "2024-01-01T00:00:00Z".to_string(),
+                        finished_on: "2024-01-01T00:01:00Z".to_string(),
+                    },
+                    byproducts: vec![],
+                },
+            },
+        };
+
+        let value = serde_json::to_value(&statement).unwrap();
+        assert_eq!(value["_type"], STATEMENT_TYPE);
+        assert_eq!(value["predicateType"], PREDICATE_TYPE);
+        assert_eq!(value["subject"][0]["name"], "dist/app.js");
+        assert!(value["subject"][0].get("uri").is_none());
+        assert_eq!(
+            value["predicate"]["buildDefinition"]["buildType"],
+            BUILD_TYPE
+        );
+        assert_eq!(
+            value["predicate"]["runDetails"]["metadata"]["startedOn"],
+            "2024-01-01T00:00:00Z"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Status badges (`dwf badge`).
+//!
+//! Renders a flat, shields.io-style SVG from the latest recorded run: its
+//! outcome, how long it took and, given a coverage report, the line
+//! coverage. Runs recorded for the same commit count as one, so the badge
+//! of a CI job running each command as its own `dwf` call covers all of them.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use tracing::warn;
+
+use devflow_core::DevflowConfig;
+
+use crate::history::{self, RunRecord};
+
+/// Badge colors: shields.io's `brightgreen` and `red`.
+const PASSING: &str = "#4c1";
+const FAILING: &str = "#e05d44";
+
+/// Writes the badge of the latest run, of `command` if given, to `output`
+/// (stdout when unset). `coverage` overrides `[ci.badge] coverage`.
+///
+/// # Errors
+/// Returns an error if no run is recorded, a `coverage` report passed
+/// explicitly cannot be read, or `output` cannot be written.
+pub fn run(
+    cfg: &DevflowConfig,
+    command: Option<&str>,
+    coverage: Option<&str>,
+    output: Option<&str>,
+) -> Result<()> {
+    let runs = history::latest_runs(cfg, command)?;
+    if runs.is_empty() {
+        bail!(
+            "no run{} recorded yet to render a badge from",
+            command.map(|c| format!(" of '{c}'")).unwrap_or_default()
+        );
+    }
+    let configured = cfg.ci.badge.as_ref().and_then(|b| b.coverage.as_deref());
+    let percent = match (coverage, configured) {
+        (Some(path), _) => Some(read_coverage(Path::new(path))?),
+        // A failed run may not have written its report; the badge still renders.
+        (None, Some(path)) => read_coverage(Path::new(path))
+            .map_err(|e| warn!("badge without coverage: {e:#}"))
+            .ok(),
+        (None, None) => None,
+    };
+    let label = match runs.as_slice() {
+        [run] => run.invocation.clone(),
+        _ => cfg.project.name.clone(),
+    };
+    let svg = render(&label, &message(&runs, percent), color(&runs));
+    match output {
+        Some(path) => {
+            if let Some(parent) = Path::new(path).parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create '{}'", parent.display()))?;
+            }
+            std::fs::write(path, svg).with_context(|| format!("failed to write '{path}'"))?;
+            println!("badge wrote {path}");
+        }
+        None => print!("{svg}"),
+    }
+    Ok(())
+}
+
+/// The badge text: the outcome, the wall-clock time `runs` took together,
+/// and the coverage.
+fn message(runs: &[RunRecord], coverage: Option<f64>) -> String {
+    let outcome = if runs.iter().all(|run| run.success) {
+        "passing"
+    } else {
+        "failing"
+    };
+    let start = runs.iter().map(|run| run.started_at * 1000).min();
+    let end = runs
+        .iter()
+        .map(|run| run.started_at * 1000 + run.duration_ms)
+        .max();
+    let elapsed = end.zip(start).map_or(0, |(end, start)| end - start) / 1000;
+    let mut parts = vec![outcome.to_string(), format_duration(elapsed)];
+    if let Some(percent) = coverage {
+        parts.push(format!("{percent:.0}% coverage"));
+    }
+    parts.join(" · ")
+}
+
+fn color(runs: &[RunRecord]) -> &'static str {
+    if runs.iter().all(|run| run.success) {
+        PASSING
+    } else {
+        FAILING
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Reads the line coverage (0 to 100) of an lcov file (`LF:`/`LH:` totals)
+/// or an istanbul `coverage-summary.json` (`total.lines.pct`).
+fn read_coverage(path: &Path) -> Result<f64> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read coverage report '{}'", path.display()))?;
+    let invalid = || {
+        format!(
+            "'{}' is neither lcov nor coverage-summary.json",
+            path.display()
+        )
+    };
+    if path.extension().is_some_and(|ext| ext == "json") {
+        let summary: serde_json::Value = serde_json::from_str(&text).with_context(invalid)?;
+        return summary["total"]["lines"]["pct"]
+            .as_f64()
+            .with_context(invalid);
+    }
+    let (mut found, mut hit) = (0u64, 0u64);
+    for line in text.lines() {
+        if let Some(count) = line.strip_prefix("LF:") {
+            found += count.trim().parse::<u64>().with_context(invalid)?;
+        } else if let Some(count) = line.strip_prefix("LH:") {
+            hit += count.trim().parse::<u64>().with_context(invalid)?;
+        }
+    }
+    if found == 0 {
+        bail!("{}", invalid());
+    }
+    Ok(hit as f64 * 100.0 / found as f64)
+}
+
+/// Renders a flat badge; text widths are estimated for 11px Verdana.
+fn render(label: &str, message: &str, color: &str) -> String {
+    let width = |text: &str| text.chars().count() as u32 * 7 + 10;
+    let (left, right) = (width(label), width(message));
+    let total = left + right;
+    let (label, message) = (escape(label), escape(message));
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <linearGradient id="s" x2="0" y2="100%">
+    <stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
+    <stop offset="1" stop-opacity=".1"/>
+  </linearGradient>
+  <clipPath id="r"><rect width="{total}" height="20" rx="3" fill="#fff"/></clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{left}" height="20" fill="#555"/>
+    <rect x="{left}" width="{right}" height="20" fill="{color}"/>
+    <rect width="{total}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+        label_x = left / 2,
+        message_x = left + right / 2,
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(invocation: &str, started_at: u64, duration_ms: u64, success: bool) -> RunRecord {
+        RunRecord {
+            id: 0,
+            started_at,
+            invocation: invocation.to_string(),
+            git_sha: Some("abc".to_string()),
+            duration_ms,
+            success,
+        }
+    }
+
+    #[test]
+    fn renders_outcome_duration_and_coverage() {
+        let parallel = [
+            run("fmt:check", 100, 2_000, true),
+            run("test:unit", 100, 95_000, true),
+        ];
+        assert_eq!(
+            message(&parallel, Some(87.44)),
+            "passing · 1m35s · 87% coverage"
+        );
+        assert_eq!(color(&parallel), PASSING);
+        let failed = [run("check:pr", 100, 4_000, false)];
+        assert_eq!(message(&failed, None), "failing · 4s");
+        assert_eq!(color(&failed), FAILING);
+
+        let svg = render("check:pr", "passing & green", PASSING);
+        assert!(svg.contains(r#"aria-label="check:pr: passing &amp; green""#));
+        assert!(svg.contains(r##"fill="#4c1""##));
+    }
+
+    #[test]
+    fn reads_lcov_and_istanbul_summaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let lcov = dir.path().join("lcov.info");
+        std::fs::write(
+            &lcov,
+            "SF:a.rs\nLF:10\nLH:9\nend_of_record\nSF:b.rs\nLF:10\nLH:7\n",
+        )
+        .unwrap();
+        assert_eq!(read_coverage(&lcov).unwrap(), 80.0);
+        let summary = dir.path().join("coverage-summary.json");
+        std::fs::write(&summary, r#"{"total":{"lines":{"pct":91.5}}}"#).unwrap();
+        assert_eq!(read_coverage(&summary).unwrap(), 91.5);
+        std::fs::write(&lcov, "garbage").unwrap();
+        assert!(read_coverage(&lcov).is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! Output capping and log spooling for relayed command output.
+//!
+//! With `[commands.<command>] max_output`, a stream shows its first half of
+//! the budget as it arrives and keeps the last half in memory; anything in
+//! between is replaced by a truncation marker when the command ends. With
+//! `spool_logs`, the full output also goes to a gzip log under
+//! `<cache root>/logs`, and with `[logs]` to a plain [`LogFile`] of the run
+//! (see [`crate::logs`]). ANSI codes are stripped from relayed output unless
+//! it is shown somewhere that renders them (see [`keep_ansi`]). Dotenv
+//! secrets are masked everywhere (see [`mask`]). The last lines of every
+//! relayed command are kept in a [`Tail`] for `dwf explain-failure`.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Longest chunk relayed as one line; longer lines are split so a runaway
+/// command without newlines cannot exhaust memory.
+pub const MAX_LINE: u64 = 64 * 1024;
+
+/// A stream writer that keeps the head and tail of its output within a budget.
+pub struct Capped<W: Write> {
+    out: W,
+    /// Bytes passed straight through before tail retention starts.
+    head: u64,
+    /// Bytes of the most recent lines kept for the end.
+    tail: u64,
+    written: u64,
+    kept: VecDeque<Vec<u8>>,
+    kept_bytes: u64,
+    dropped_bytes: u64,
+    dropped_lines: u64,
+}
+
+impl<W: Write> Capped<W> {
+    /// Wraps `out`; without `limit` every line passes through.
+    pub fn new(out: W, limit: Option<u64>) -> Self {
+        let limit = limit.unwrap_or(u64::MAX);
+        Self {
+            out,
+            head: limit / 2,
+            tail: limit - limit / 2,
+            written: 0,
+            kept: VecDeque::new(),
+            kept_bytes: 0,
+            dropped_bytes: 0,
+            dropped_lines: 0,
+        }
+    }
+
+    /// Writes `line` or retains it for the tail.
+    pub fn line(&mut self, line: &[u8]) -> io::Result<()> {
+        let len = line.len() as u64;
+        if self.kept.is_empty() && self.written.saturating_add(len) <= self.head {
+            self.written += len;
+            self.out.write_all(line)?;
+            return self.out.flush();
+        }
+        self.kept.push_back(line.to_vec());
+        self.kept_bytes += len;
+        while self.kept_bytes > self.tail {
+            let Some(oldest) = self.kept.pop_front() else {
+                break;
+            };
+            self.kept_bytes -= oldest.len() as u64;
+            self.dropped_bytes += oldest.len() as u64;
+            self.dropped_lines += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the truncation marker, if anything was dropped, and the tail.
+    /// `note` ends the marker, e.g. with where the full log is.
+    pub fn finish(mut self, stream: &str, note: &str) -> io::Result<()> {
+        if self.dropped_bytes > 0 {
+            writeln!(
+                self.out,
+                "[dwf] ... {} ({} lines) of {} truncated{} ...",
+                format_size(self.dropped_bytes),
+                self.dropped_lines,
+                stream,
+                note
+            )?;
+        }
+        for line in &self.kept {
+            self.out.write_all(line)?;
+        }
+        self.out.flush()
+    }
+}
+
+/// A full command log, gzip-compressed, shared by the relayed streams.
+pub struct Spool {
+    path: PathBuf,
+    encoder: Mutex<GzEncoder<File>>,
+}
+
+impl Spool {
+    /// Creates `<dir>/<name>-<unix millis>.log.gz`; `name` is sanitized for
+    /// use as a file name.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn create(dir: &Path, name: &str) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create log directory '{}'", dir.display()))?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let stem: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let path = dir.join(format!("{stem}-{millis}.log.gz"));
+        let file = File::create(&path)
+            .with_context(|| format!("failed to create log '{}'", path.display()))?;
+        Ok(Self {
+            path,
+            encoder: Mutex::new(GzEncoder::new(file, Compression::fast())),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `line` to the log.
+    pub fn line(&self, line: &[u8]) -> io::Result<()> {
+        match self.encoder.lock() {
+            Ok(mut encoder) => encoder.write_all(line),
+            Err(_) => Err(io::Error::other("log writer poisoned")),
+        }
+    }
+
+    /// Completes the gzip stream and returns the log path.
+    ///
+    /// # Errors
+    /// Returns an error if the log cannot be written.
+    pub fn finish(self) -> Result<PathBuf> {
+        let encoder = self
+            .encoder
+            .into_inner()
+            .map_err(|_| anyhow::anyhow!("log writer poisoned"))?;
+        encoder
+            .finish()
+            .with_context(|| format!("failed to write log '{}'", self.path.display()))?;
+        Ok(self.path)
+    }
+}
+
+/// A plain-text command log written as the output arrives, so `dwf logs
+/// --follow` can tail it; shared by the relayed streams.
+pub struct LogFile {
+    file: Mutex<File>,
+}
+
+impl LogFile {
+    /// Creates the log at `path`, appending to it if it exists.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to create log '{}'", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends `line` to the log.
+    pub fn line(&self, line: &[u8]) -> io::Result<()> {
+        match self.file.lock() {
+            Ok(mut file) => file.write_all(line),
+            Err(_) => Err(io::Error::other("log writer poisoned")),
+        }
+    }
+}
+
+/// The last lines of a command's output, without ANSI codes, shared by the
+/// relayed streams.
+pub struct Tail {
+    limit: usize,
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl Tail {
+    /// Longest line kept; the rest of a line is dropped.
+    const MAX_CHARS: usize = 1024;
+
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            lines: Mutex::new(VecDeque::with_capacity(limit)),
+        }
+    }
+
+    /// Keeps `line`, dropping the oldest line once `limit` are kept.
+    pub fn line(&self, line: &str) {
+        let line: String = line
+            .trim_end_matches(['\r', '\n'])
+            .chars()
+            .take(Self::MAX_CHARS)
+            .collect();
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        if lines.len() == self.limit {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// The kept lines, oldest first.
+    pub fn into_lines(self) -> Vec<String> {
+        self.lines.into_inner().map(Vec::from).unwrap_or_default()
+    }
+}
+
+/// Whether relayed output keeps its ANSI codes: on a terminal or in GitHub
+/// Actions, whose log viewer renders them, unless `NO_COLOR` is set.
+pub fn keep_ansi() -> bool {
+    std::env::var_os("NO_COLOR").is_none()
+        && (io::stdout().is_terminal() || devflow_gh::log::enabled())
+}
+
+/// Removes ANSI escape sequences (CSI such as colors, OSC such as hyperlinks,
+/// charset designations such as `ESC ( B`, and two-byte escapes) from `text`.
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    if !text.contains('\u{1b}') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters, then a final byte in `@`..=`~`.
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ST (`ESC \`).
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // nF: intermediate bytes in ` `..=`/`, then a final byte.
+            Some(' '..='/') => {
+                while chars.next_if(|c| (' '..='/').contains(c)).is_some() {}
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Replaces every occurrence of a `secrets` value in `text` with `***`.
+pub fn mask<'a>(text: &'a str, secrets: &[String]) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    for secret in secrets {
+        if text.contains(secret.as_str()) {
+            text = Cow::Owned(text.replace(secret.as_str(), crate::dotenv::MASK));
+        }
+    }
+    text
+}
+
+/// Formats `bytes` with a binary unit, e.g. `1.5 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn keeps_head_and_tail_around_a_marker() {
+        let mut out = Vec::new();
+        let mut capped = Capped::new(&mut out, Some(28));
+        for i in 0..10 {
+            capped.line(format!("line {i}\n").as_bytes()).unwrap();
+        }
+        capped.finish("stdout", "").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "line 0\nline 1\n[dwf] ... 42 B (6 lines) of stdout truncated ...\nline 8\nline 9\n"
+        );
+    }
+
+    #[test]
+    fn passes_everything_without_a_limit() {
+        let mut out = Vec::new();
+        let mut capped = Capped::new(&mut out, None);
+        capped.line(b"a\n").unwrap();
+        capped.line(b"b\n").unwrap();
+        capped.finish("stderr", "").unwrap();
+        assert_eq!(out, b"a\nb\n");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(50 << 20), "50.0 MB");
+    }
+
+    #[test]
+    fn strips_ansi_sequences() {
+        assert_eq!(strip_ansi("plain"), "plain");
+        assert_eq!(
+            strip_ansi("\u{1b}[1m\u{1b}[32mtest result\u{1b}[0m: ok"),
+            "test result: ok"
+        );
+        assert_eq!(
+            strip_ansi("see \u{1b}]8;;https://d.rs\u{1b}\\docs\u{1b}]8;;\u{7} now"),
+            "see docs now"
+        );
+        // rustfmt and tput reset the charset with `ESC ( B`.
+        assert_eq!(
+            strip_ansi("\u{1b}[32m+fn main() {}\u{1b}(B\u{1b}[m"),
+            "+fn main() {}"
+        );
+        assert_eq!(strip_ansi("\u{1b}(B+fn main() {}"), "+fn main() {}");
+        assert_eq!(strip_ansi("\u{1b}#8 \u{1b}=ok"), " ok");
+    }
+
+    #[test]
+    fn masks_secret_values() {
+        let secrets = ["s3cret".to_string(), "tok".to_string()];
+        assert!(matches!(mask("nothing here", &secrets), Cow::Borrowed(_)));
+        assert_eq!(mask("login s3cret tok=tok", &secrets), "login *** ***=***");
+    }
+
+    #[test]
+    fn tail_keeps_the_last_lines() {
+        let tail = Tail::new(2);
+        for line in ["one\n", "two\r\n", &"x".repeat(2000)] {
+            tail.line(line);
+        }
+        let lines = tail.into_lines();
+        assert_eq!(lines[0], "two");
+        assert_eq!(lines[1].len(), Tail::MAX_CHARS);
+    }
+
+    #[test]
+    fn spools_the_full_log_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = Spool::create(dir.path(), "test:unit").unwrap();
+        spool.line(b"one\n").unwrap();
+        spool.line(b"two\n").unwrap();
+        let path = spool.finish().unwrap();
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("test-unit-"));
+        let mut text = String::new();
+        GzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "one\ntwo\n");
+    }
+}
@@ -0,0 +1,238 @@
+//! What the project's commands do (`dwf list`, `dwf help <command>`).
+//!
+//! Extensions describe their capabilities and the group they belong to
+//! ([`devflow_core::Extension::capability_info`]; subprocess extensions in
+//! the `capability_info` of their `--discover` manifest). `dwf list` shows
+//! every capability of the stacks that apply under its group, in lifecycle
+//! order; `dwf help test` only the `test` ones, `dwf help test:unit` one.
+//! Each line says what the command runs for one stack.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+
+use devflow_core::{CapabilityInfo, CommandRef, DevflowConfig, ExtensionRegistry, PrimaryCommand};
+
+use crate::executor;
+
+/// Shown for a capability whose extension does not describe it.
+const NO_DESCRIPTION: &str = "(no description)";
+
+/// A capability and what it does for each stack providing it, in stack
+/// order.
+#[derive(Debug, Clone)]
+struct Entry {
+    capability: String,
+    primary: Option<PrimaryCommand>,
+    stacks: Vec<(String, Option<CapabilityInfo>)>,
+}
+
+impl Entry {
+    /// The group of the first stack that names one, else the primary
+    /// command.
+    fn group(&self) -> String {
+        self.stacks
+            .iter()
+            .find_map(|(_, info)| info.as_ref()?.group.clone())
+            .unwrap_or_else(|| {
+                self.capability
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+    }
+}
+
+/// The capabilities of the applicable stacks, in lifecycle order.
+fn entries(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Vec<Entry> {
+    let mut by_capability: BTreeMap<String, Vec<(String, Option<CapabilityInfo>)>> =
+        BTreeMap::new();
+    for stack in executor::ordered_stacks(cfg) {
+        let Some(ext) = registry.get(&stack) else {
+            continue;
+        };
+        let mut info = ext.capability_info();
+        for capability in ext.capabilities() {
+            let described = info.remove(&capability);
+            by_capability
+                .entry(capability)
+                .or_default()
+                .push((stack.clone(), described));
+        }
+    }
+    let mut entries: Vec<Entry> = by_capability
+        .into_iter()
+        .map(|(capability, stacks)| Entry {
+            primary: CommandRef::from_str(&capability)
+                .ok()
+                .map(|cmd| cmd.primary),
+            capability,
+            stacks,
+        })
+        .collect();
+    entries.sort_by(|a, b| (a.primary, &a.capability).cmp(&(b.primary, &b.capability)));
+    entries
+}
+
+/// One capability with a line per stack.
+fn render_entry(out: &mut String, entry: &Entry, width: usize) {
+    out.push_str(&format!("  {}\n", entry.capability));
+    for (stack, info) in &entry.stacks {
+        let description = info
+            .as_ref()
+            .map_or(NO_DESCRIPTION, |info| info.description.as_str());
+        out.push_str(&format!("    {stack:<width$}  {description}\n"));
+    }
+}
+
+fn stack_width(entries: &[Entry]) -> usize {
+    entries
+        .iter()
+        .flat_map(|entry| &entry.stacks)
+        .map(|(stack, _)| stack.len())
+        .max()
+        .unwrap_or_default()
+}
+
+/// The grouped listing of `dwf list`.
+fn render_list(entries: &[Entry]) -> String {
+    let mut groups: Vec<(String, Vec<&Entry>)> = Vec::new();
+    for entry in entries {
+        let group = entry.group();
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, members)) => members.push(entry),
+            None => groups.push((group, vec![entry])),
+        }
+    }
+    let width = stack_width(entries);
+    let mut out = String::new();
+    for (index, (group, members)) in groups.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("{group}\n"));
+        for entry in members {
+            render_entry(&mut out, entry, width);
+        }
+    }
+    out
+}
+
+/// The capabilities `topic` names: a primary command or one capability.
+fn render_help(entries: &[Entry], topic: &str) -> Result<String> {
+    let cmd = CommandRef::from_str(topic).map_err(|e| anyhow!("invalid command '{topic}': {e}"))?;
+    let primary = cmd.primary.as_str();
+    let matching: Vec<Entry> = entries
+        .iter()
+        .filter(|entry| match &cmd.selector {
+            Some(_) => entry.capability == topic,
+            None => {
+                entry.capability == primary
+                    || entry
+                        .capability
+                        .strip_prefix(primary)
+                        .is_some_and(|rest| rest.starts_with(':'))
+            }
+        })
+        .cloned()
+        .collect();
+    if matching.is_empty() {
+        bail!("no stack of this project provides '{topic}' (see 'dwf list')");
+    }
+    let mut out = String::new();
+    if cmd.selector.is_none() {
+        out.push_str(&format!(
+            "dwf {primary} runs {primary}:{}\n\n",
+            cmd.primary.default_selector()
+        ));
+    }
+    let width = stack_width(&matching);
+    for entry in &matching {
+        render_entry(&mut out, entry, width);
+    }
+    Ok(out)
+}
+
+/// Prints the capabilities of the project's stacks by group.
+pub fn list(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<()> {
+    let entries = entries(cfg, registry);
+    if entries.is_empty() {
+        println!("no stack of this project provides any command");
+        return Ok(());
+    }
+    print!("{}", render_list(&entries));
+    Ok(())
+}
+
+/// Prints what the commands `topic` names do for each stack; `all` lists
+/// everything like [`list`].
+///
+/// # Errors
+/// Returns an error for an invalid command or one no stack provides.
+pub fn help(cfg: &DevflowConfig, registry: &ExtensionRegistry, topic: &str) -> Result<()> {
+    if topic == "all" {
+        return list(cfg, registry);
+    }
+    print!("{}", render_help(&entries(cfg, registry), topic)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_capabilities_and_describes_them_per_stack() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        let mut cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust", "node"]
+
+            [targets]
+            pr = ["test:unit"]
+            "#,
+        )
+        .unwrap();
+        cfg.source_dir = Some(dir.path().to_path_buf());
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
+        registry.register(Box::new(devflow_ext_node::NodeExtension::new()));
+
+        let entries = entries(&cfg, &registry);
+        let list = render_list(&entries);
+        assert!(list.starts_with("Setup\n  setup\n"), "{list}");
+        let quality = list.find("\nQuality\n").unwrap();
+        let build = list.find("\nBuild\n").unwrap();
+        let test = list.find("\nTest\n").unwrap();
+        assert!(quality < build && build < test, "{list}");
+        assert!(list.contains("\nSecurity\n  lint:security\n    rust  Scan the CI image"));
+
+        let help = render_help(&entries, "test").unwrap();
+        assert!(help.starts_with("dwf test runs test:unit\n\n  test:e2e\n    node  "));
+        assert!(help.contains(
+            "  test:unit\n    rust  Run the library and binary tests (cargo nextest run --lib --bins)\n    node  Run the test:unit script"
+        ));
+        assert!(!help.contains("build:"));
+
+        let one = render_help(&entries, "lint:types").unwrap();
+        assert_eq!(
+            one,
+            "  lint:types\n    node  Type-check the project (tsc --noEmit)\n"
+        );
+        let err = render_help(&entries, "test:nope").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no stack of this project provides 'test:nope'"));
+        assert!(render_help(&entries, "bogus").is_err());
+    }
+}
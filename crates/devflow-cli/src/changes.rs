@@ -0,0 +1,146 @@
+//! Change detection for path-filtered profile commands.
+//!
+//! Computes the set of files changed relative to the merge base with the
+//! target branch, so `check:<profile>` can skip commands whose path filters
+//! match nothing in the diff.
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use devflow_gh::GithubContext;
+
+/// Branch compared against when no pull request base is known.
+pub const DEFAULT_BASE_BRANCH: &str = "main";
+
+/// Resolves the git revision that changes are compared against.
+///
+/// Precedence: `base_override` (`DWF_BASE_REF`), the pull request base from
+/// the GitHub event, then `origin/main`.
+pub fn resolve_base_ref(gh: &GithubContext, base_override: Option<&str>) -> String {
+    if let Some(base) = base_override.filter(|base| !base.is_empty()) {
+        return base.to_string();
+    }
+    let branch = gh.base_ref.as_deref().unwrap_or(DEFAULT_BASE_BRANCH);
+    format!("origin/{branch}")
+}
+
+/// Lists files changed since the merge base of `base_ref` and `HEAD`.
+///
+/// Uncommitted modifications to tracked files are included so local runs reflect
+/// the working tree; with `untracked`, so are new files git does not ignore.
+pub fn changed_files(repo_dir: &Path, base_ref: &str, untracked: bool) -> Result<Vec<String>> {
+    let merge_base = git(repo_dir, &["merge-base", base_ref, "HEAD"])
+        .with_context(|| format!("unable to find merge base with '{base_ref}'"))?;
+    let mut listing = git(repo_dir, &["diff", "--name-only", merge_base.trim()])?;
+    if untracked {
+        listing.push_str(&git(
+            repo_dir,
+            &["ls-files", "--others", "--exclude-standard"],
+        )?);
+    }
+
+    Ok(listing
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+/// Runs git in `repo_dir` and returns its stdout.
+pub fn git(repo_dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_dir)
+        .output()
+        .context("failed to run git")?;
+
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args([
+                "-c",
+                "user.name=devflow",
+                "-c",
+                "user.email=devflow@example.com",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("git should run");
+        assert!(status.status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn resolve_base_ref_prefers_pull_request_base() {
+        let gh = GithubContext {
+            base_ref: Some("release/1.x".to_string()),
+            ..GithubContext::default()
+        };
+        assert_eq!(resolve_base_ref(&gh, None), "origin/release/1.x");
+        assert_eq!(resolve_base_ref(&gh, Some("")), "origin/release/1.x");
+        assert_eq!(
+            resolve_base_ref(&GithubContext::default(), None),
+            "origin/main"
+        );
+        assert_eq!(resolve_base_ref(&gh, Some("HEAD~3")), "HEAD~3");
+    }
+
+    #[test]
+    fn integration_test_changed_files_against_branch() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path();
+
+        run_git(repo, &["init", "-q", "-b", "main"]);
+        fs::write(repo.join("README.md"), "hello").unwrap();
+        run_git(repo, &["add", "."]);
+        run_git(repo, &["commit", "-q", "-m", "init"]);
+
+        run_git(repo, &["checkout", "-q", "-b", "feature"]);
+        fs::create_dir_all(repo.join("src/db")).unwrap();
+        fs::write(repo.join("src/db/pool.rs"), "// pool").unwrap();
+        run_git(repo, &["add", "."]);
+        run_git(repo, &["commit", "-q", "-m", "db"]);
+        // Uncommitted edits to tracked files are part of the change set.
+        fs::write(repo.join("README.md"), "hello again").unwrap();
+        // New files are too in local runs, unless git ignores them.
+        fs::write(repo.join(".git/info/exclude"), "*.log\n").unwrap();
+        fs::write(repo.join("src/db/migrate.rs"), "// new").unwrap();
+        fs::write(repo.join("debug.log"), "noise").unwrap();
+
+        let mut changed = changed_files(repo, "main", false).unwrap();
+        changed.sort();
+        assert_eq!(changed, vec!["README.md", "src/db/pool.rs"]);
+
+        let mut changed = changed_files(repo, "main", true).unwrap();
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec!["README.md", "src/db/migrate.rs", "src/db/pool.rs"]
+        );
+    }
+
+    #[test]
+    fn changed_files_fails_for_unknown_base() {
+        let dir = tempdir().unwrap();
+        run_git(dir.path(), &["init", "-q"]);
+        assert!(changed_files(dir.path(), "origin/does-not-exist", true).is_err());
+    }
+}
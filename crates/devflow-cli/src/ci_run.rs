@@ -0,0 +1,372 @@
+//! Local replay of a generated CI workflow (`dwf ci:run --profile <name>`).
+//!
+//! Jobs run in workflow order (prep, build, then the verify jobs) and a job
+//! whose `needs` did not pass is skipped, as on GitHub. Every job runs in the
+//! CI image tagged with the environment fingerprint, with the mounts, cache
+//! directories and environment the workflow passes to `docker run`. The
+//! single-job layout's `check` job runs `dwf check:<profile>` on the host,
+//! as it does on the runner.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use tracing::info;
+
+use devflow_core::constants::CI_DOCKERFILE;
+use devflow_core::{DevflowConfig, ExtensionRegistry, TargetEntry};
+use devflow_gh::jobs::{self, JobKind, WorkflowJob};
+
+use crate::{container, executor, fingerprint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Runs `profile`'s workflow jobs locally.
+///
+/// `changed` is the changed file set for path-filtered commands; `None` runs
+/// every command, like a push event.
+///
+/// # Errors
+/// Returns an error if the workflow builds a CI image but the project has no
+/// CI Dockerfile, the profile is unknown, or any job fails.
+pub fn run(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    profile: &str,
+    changed: Option<&[String]>,
+) -> Result<()> {
+    let jobs = jobs::profile_jobs(cfg, profile)?;
+    let workspace = workspace(cfg)?;
+    let in_image = jobs.iter().any(|job| job.kind == JobKind::Prep);
+    if in_image && !workspace.join(CI_DOCKERFILE).exists() {
+        bail!(
+            "ci:run needs {} in '{}': every workflow job runs in the CI image (run 'dwf init' to generate one)",
+            CI_DOCKERFILE,
+            workspace.display()
+        );
+    }
+    let engine = if in_image {
+        let engine = executor::container_engine(cfg)?;
+        println!("ci:run {profile} ({} jobs, engine={engine})", jobs.len());
+        engine
+    } else {
+        println!("ci:run {profile} ({} jobs, on the host)", jobs.len());
+        String::new()
+    };
+
+    let mut image = String::new();
+    let mut outcomes = BTreeMap::new();
+    for job in &jobs {
+        let outcome = if let Some(need) = blocking_need(job, &outcomes) {
+            println!("- {} skipped (needs {need})", job.id);
+            Outcome::Skipped
+        } else if job.kind == JobKind::Attest {
+            println!("- {} skipped (runs on tag pushes only)", job.id);
+            Outcome::Skipped
+        } else if job.kind == JobKind::Report {
+            println!(
+                "- {} skipped (files issues from GitHub Actions only)",
+                job.id
+            );
+            Outcome::Skipped
+        } else if job.kind == JobKind::Badge {
+            println!("- {} skipped (publishes from GitHub Actions only)", job.id);
+            Outcome::Skipped
+        } else {
+            println!("- {} ({})", job.id, job.name);
+            let result = match job.kind {
+                JobKind::Prep => {
+                    prep(cfg, registry, &engine, &workspace).map(|built| image = built)
+                }
+                JobKind::Build => in_ci_image(
+                    &engine,
+                    &workspace,
+                    &image,
+                    jobs::CONTAINER_ENV,
+                    "sh",
+                    jobs::BUILD_SCRIPT,
+                ),
+                JobKind::Verify => verify(&engine, &workspace, &image, job, changed),
+                JobKind::Check => check(&workspace, profile),
+                JobKind::Attest | JobKind::Report | JobKind::Badge => {
+                    unreachable!("skipped above")
+                }
+            };
+            match result {
+                Ok(()) => Outcome::Passed,
+                Err(e) => {
+                    println!("  {} failed: {e:#}", job.id);
+                    Outcome::Failed
+                }
+            }
+        };
+        outcomes.insert(job.id.clone(), outcome);
+    }
+
+    let failed: Vec<&str> = jobs
+        .iter()
+        .filter(|job| outcomes.get(&job.id) == Some(&Outcome::Failed))
+        .map(|job| job.id.as_str())
+        .collect();
+    if !failed.is_empty() {
+        bail!("ci:run {profile} failed: {}", failed.join(", "));
+    }
+    println!("ci:run {profile} passed");
+    Ok(())
+}
+
+fn workspace(cfg: &DevflowConfig) -> Result<PathBuf> {
+    let dir = match cfg.source_dir.as_deref() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => std::env::current_dir()?,
+    };
+    dir.canonicalize()
+        .with_context(|| format!("failed to resolve workspace '{}'", dir.display()))
+}
+
+/// The first of `job`'s needs that did not pass.
+fn blocking_need<'a>(
+    job: &'a WorkflowJob,
+    outcomes: &BTreeMap<String, Outcome>,
+) -> Option<&'a str> {
+    job.needs
+        .iter()
+        .find(|need| outcomes.get(*need) != Some(&Outcome::Passed))
+        .map(String::as_str)
+}
+
+/// Fingerprints the environment and builds the CI image unless the engine
+/// already has it; a fresh image is scanned with Trivy when it is installed.
+/// Returns the image tag.
+fn prep(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    engine: &str,
+    workspace: &Path,
+) -> Result<String> {
+    let fingerprint = fingerprint::compute(cfg, registry, workspace)?;
+    let image = jobs::ci_image(&fingerprint);
+    if container::image_present(engine, &image) {
+        println!("  {image} present (fingerprint {fingerprint})");
+        return Ok(image);
+    }
+    container::build_image(cfg, engine, &image, &[])?;
+    println!("  built {image}");
+
+    let scan = Command::new("trivy")
+        .args(["fs", ".", "--severity", "HIGH,CRITICAL", "--ignore-unfixed"])
+        .args(["--format", "table", "--exit-code", "1"])
+        .current_dir(workspace)
+        .status();
+    match scan {
+        Ok(status) if status.success() => {}
+        Ok(status) => bail!("trivy found HIGH/CRITICAL vulnerabilities ({status})"),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!("  vulnerability scan skipped (trivy not installed)");
+        }
+        Err(e) => return Err(e).context("failed to start 'trivy'"),
+    }
+    Ok(image)
+}
+
+/// Bootstraps `dwf` in the CI image, then runs the job's commands in parallel.
+fn verify(
+    engine: &str,
+    workspace: &Path,
+    image: &str,
+    job: &WorkflowJob,
+    changed: Option<&[String]>,
+) -> Result<()> {
+    let mut selected: Vec<&TargetEntry> = Vec::new();
+    for entry in &job.entries {
+        if matches_changes(entry, changed) {
+            println!("  - {}", entry.command());
+            selected.push(entry);
+        } else {
+            println!(
+                "  - {} (skipped: no changes match path filters)",
+                entry.command()
+            );
+        }
+    }
+    if selected.is_empty() {
+        return Ok(());
+    }
+    let env: Vec<_> = jobs::CONTAINER_ENV
+        .iter()
+        .chain(jobs::CHECK_ENV)
+        .copied()
+        .collect();
+    in_ci_image(
+        engine,
+        workspace,
+        image,
+        jobs::CONTAINER_ENV,
+        "/bin/bash",
+        jobs::BOOTSTRAP_SCRIPT,
+    )?;
+    in_ci_image(
+        engine,
+        workspace,
+        image,
+        &env,
+        "/bin/bash",
+        &jobs::check_script(&selected),
+    )
+}
+
+/// Runs `dwf check:<profile>` in the workspace; `check` applies the path
+/// filters itself.
+fn check(workspace: &Path, profile: &str) -> Result<()> {
+    let exe = std::env::current_exe().context("failed to locate the dwf executable")?;
+    let status = Command::new(exe)
+        .arg(format!("check:{profile}"))
+        .current_dir(workspace)
+        .status()
+        .context("failed to start 'dwf check'")?;
+    if !status.success() {
+        bail!("dwf check:{profile} failed ({status})");
+    }
+    Ok(())
+}
+
+fn matches_changes(entry: &TargetEntry, changed: Option<&[String]>) -> bool {
+    match changed {
+        Some(files) if !entry.paths().is_empty() => files
+            .iter()
+            .any(|file| devflow_core::glob::matches_any(entry.paths(), file)),
+        _ => true,
+    }
+}
+
+/// Runs `script` in `image` like a workflow `docker run` step, then hands
+/// the cache directories back to the workspace owner (the workflow's
+/// "Fix Cache Permissions" step).
+fn in_ci_image(
+    engine: &str,
+    workspace: &Path,
+    image: &str,
+    env: &[(&str, &str)],
+    shell: &str,
+    script: &str,
+) -> Result<()> {
+    for dir in jobs::CACHE_DIRS {
+        let path = workspace.join(dir);
+        fs::create_dir_all(&path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+    }
+    let args = run_args(workspace, image, env, shell, script);
+    info!(target: "devflow", "{} {}", engine, args.join(" "));
+    let status = Command::new(engine)
+        .args(&args)
+        .status()
+        .with_context(|| format!("failed to start '{} run'", engine))?;
+
+    let chown = "chown -R $(stat -c %u:%g /workspace) .cargo-cache target/ci || true";
+    let _ = Command::new(engine)
+        .args(run_args(workspace, image, &[], "sh", chown))
+        .status();
+
+    if !status.success() {
+        bail!("{shell} -c \"{script}\" failed ({status})");
+    }
+    Ok(())
+}
+
+fn run_args(
+    workspace: &Path,
+    image: &str,
+    env: &[(&str, &str)],
+    shell: &str,
+    script: &str,
+) -> Vec<String> {
+    let mut args = vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "-v".to_string(),
+        format!("{}:/workspace", workspace.display()),
+        "-w".to_string(),
+        "/workspace".to_string(),
+    ];
+    for (key, value) in env {
+        args.extend(["-e".to_string(), format!("{key}={value}")]);
+    }
+    args.extend([image, shell, "-c", script].map(String::from));
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_args_mirror_the_workflow_docker_run() {
+        let args = run_args(
+            Path::new("/src/demo"),
+            "devflow-ci:0123456789ab",
+            &[("CARGO_HOME", "/workspace/.cargo-cache")],
+            "sh",
+            "cargo fetch",
+        );
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--rm",
+                "-v",
+                "/src/demo:/workspace",
+                "-w",
+                "/workspace",
+                "-e",
+                "CARGO_HOME=/workspace/.cargo-cache",
+                "devflow-ci:0123456789ab",
+                "sh",
+                "-c",
+                "cargo fetch",
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_needs_skip_dependent_jobs() {
+        let job = WorkflowJob {
+            id: "verify".to_string(),
+            name: "Verify".to_string(),
+            kind: JobKind::Verify,
+            runner: Default::default(),
+            needs: vec!["prep".to_string(), "build".to_string()],
+            entries: Vec::new(),
+        };
+        let mut outcomes = BTreeMap::from([("prep".to_string(), Outcome::Passed)]);
+        assert_eq!(blocking_need(&job, &outcomes), Some("build"));
+        outcomes.insert("build".to_string(), Outcome::Failed);
+        assert_eq!(blocking_need(&job, &outcomes), Some("build"));
+        outcomes.insert("build".to_string(), Outcome::Passed);
+        assert_eq!(blocking_need(&job, &outcomes), None);
+    }
+
+    #[test]
+    fn path_filters_use_the_changed_files() {
+        let entry = TargetEntry::Detailed(devflow_core::config::TargetEntryOptions {
+            cmd: "test:integration".to_string(),
+            paths: vec!["migrations/**".to_string()],
+            timeout: None,
+            retries: 0,
+            stacks: Vec::new(),
+        });
+        assert!(matches_changes(&entry, None));
+        assert!(!matches_changes(&entry, Some(&["src/lib.rs".to_string()])));
+        assert!(matches_changes(
+            &entry,
+            Some(&["migrations/001.sql".to_string()])
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+//! Shell completion of the project's commands (`dwf completions:bash`,
+//! `dwf completions:zsh`).
+//!
+//! Which commands exist depends on the extensions and profiles of the
+//! project, and discovering them is too slow for a keypress. So every run
+//! that discovers the extensions also writes them, one per line, to
+//! [`COMPLETION_CACHE_FILE`] next to `devflow.toml`; the shell scripts only
+//! read that file. `completions:refresh` rewrites it. The scripts never run
+//! `dwf` themselves, since discovery runs the extensions a checkout
+//! configures; without a cache they offer the [`BUILTIN_COMMANDS`].
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::debug;
+
+use devflow_core::constants::COMPLETION_CACHE_FILE;
+use devflow_core::{DevflowConfig, ExtensionRegistry};
+
+use crate::executor;
+
+/// Commands devflow runs itself, whatever the stacks.
+const BUILTIN_COMMANDS: &[&str] = &[
+    "init",
+    "list",
+    "help",
+    "setup:tools",
+    "ci:generate",
+    "ci:check",
+    "ci:diff",
+    "ci:import",
+    "ci:run",
+    "ci:debug",
+    "ci:issue",
+    "fingerprint",
+    "env",
+    "run",
+    "ext:verify",
+    "container:pull",
+    "container:build",
+    "devcontainer:generate",
+    "devcontainer:check",
+    "cache:warm",
+    "cache:export",
+    "cache:import",
+    "config:format",
+    "config:show",
+    "config:update",
+    "stats",
+    "badge",
+    "explain-failure",
+    "explain",
+    "serve",
+    "logs",
+    "logs:list",
+    "prune:cache",
+    "prune:runs",
+    "completions:bash",
+    "completions:zsh",
+    "completions:refresh",
+];
+
+/// The completion script for `shell`, with the [`BUILTIN_COMMANDS`] it
+/// falls back to.
+///
+/// # Errors
+/// Returns an error for a shell without a script.
+pub fn script(shell: &str) -> Result<String> {
+    let template = match shell {
+        "bash" => include_str!("../resources/completions.bash"),
+        "zsh" => include_str!("../resources/completions.zsh"),
+        shell => {
+            return Err(anyhow!(
+                "no completion script for '{shell}' (expected bash or zsh)"
+            ))
+        }
+    };
+    Ok(template.replace("@BUILTIN_COMMANDS@", &BUILTIN_COMMANDS.join(" ")))
+}
+
+/// The commands of the project: the capabilities of its stacks, a
+/// `check:<profile>` per target profile, its aliases and the builtin
+/// commands, sorted.
+pub fn words(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Vec<String> {
+    let mut words: BTreeSet<String> = BUILTIN_COMMANDS.iter().map(|s| s.to_string()).collect();
+    for stack in executor::ordered_stacks(cfg) {
+        if let Some(ext) = registry.get(&stack) {
+            words.extend(ext.capabilities());
+        }
+    }
+    words.extend(
+        cfg.targets
+            .profiles
+            .keys()
+            .map(|profile| format!("check:{profile}")),
+    );
+    words.extend(cfg.aliases.keys().cloned());
+    words.into_iter().collect()
+}
+
+/// Where the completion cache of the project lives.
+pub fn path(cfg: &DevflowConfig) -> PathBuf {
+    cfg.source_dir
+        .as_deref()
+        .unwrap_or(Path::new(""))
+        .join(COMPLETION_CACHE_FILE)
+}
+
+/// Writes the completion cache unless it already lists `words`.
+///
+/// # Errors
+/// Returns an error if the cache cannot be written.
+pub fn write_cache(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<PathBuf> {
+    let path = path(cfg);
+    let mut content = words(cfg, registry).join("\n");
+    content.push('\n');
+    if fs::read_to_string(&path).is_ok_and(|current| current == content) {
+        return Ok(path);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory '{}'", parent.display()))?;
+    }
+    fs::write(&path, content).with_context(|| format!("failed to write '{}'", path.display()))?;
+    Ok(path)
+}
+
+/// Refreshes the completion cache after discovery; completion is a
+/// convenience, so a failure is only logged.
+pub fn refresh(cfg: &DevflowConfig, registry: &ExtensionRegistry) {
+    if let Err(e) = write_cache(cfg, registry) {
+        debug!("completion cache not refreshed: {:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_capabilities_profiles_and_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        let mut cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = ["test:unit"]
+            nightly = ["test:features"]
+
+            [aliases]
+            "t" = "test:unit"
+            "#,
+        )
+        .unwrap();
+        cfg.source_dir = Some(dir.path().to_path_buf());
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
+
+        let path = write_cache(&cfg, &registry).unwrap();
+        assert_eq!(path, dir.path().join(".devflow/completions"));
+        let cached = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = cached.lines().collect();
+        for word in [
+            "test:unit",
+            "test:smoke",
+            "check:pr",
+            "check:nightly",
+            "t",
+            "ci:run",
+        ] {
+            assert!(lines.contains(&word), "{word} missing from {cached}");
+        }
+        assert!(!lines.contains(&"lint:types"));
+        let mut sorted = lines.clone();
+        sorted.sort_unstable();
+        assert_eq!(lines, sorted);
+
+        assert!(script("bash")
+            .unwrap()
+            .contains("complete -o default -F _dwf dwf"));
+        assert!(script("zsh").unwrap().starts_with("#compdef dwf\n"));
+        for shell in ["bash", "zsh"] {
+            let script = script(shell).unwrap();
+            // Tab must never run dwf, and so the extensions of a checkout.
+            assert!(!script.contains("dwf completions:refresh"), "{shell}");
+            assert!(
+                script.contains("_dwf_builtin_commands=\"init list help "),
+                "{shell}"
+            );
+        }
+        assert!(script("fish").is_err());
+    }
+}
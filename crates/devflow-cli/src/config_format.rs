@@ -0,0 +1,84 @@
+//! Canonical formatting of the config file (`dwf config:format`).
+
+use std::fs;
+
+use anyhow::{bail, Context, Result};
+
+use devflow_core::format;
+
+use crate::diff;
+
+/// Rewrites the config at `path` in canonical form, or with `check` only
+/// reports whether it already is.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, parsed or written, or in
+/// check mode if it is not canonical.
+pub fn run(path: &str, check: bool) -> Result<()> {
+    if check {
+        verify(path)?;
+        println!("config:format {path} is canonical");
+        return Ok(());
+    }
+    let (actual, expected) = read(path)?;
+    if actual == expected {
+        println!("config:format {path} already canonical");
+    } else {
+        fs::write(path, &expected).with_context(|| format!("failed to write '{path}'"))?;
+        println!("config:format wrote {path}");
+    }
+    Ok(())
+}
+
+/// Fails with a diff when the config at `path` is not in canonical form.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or parsed, or is not canonical.
+pub fn verify(path: &str) -> Result<()> {
+    let (actual, expected) = read(path)?;
+    if let Some(drift) = diff::unified(
+        &expected,
+        &actual,
+        "expected (dwf config:format)",
+        path,
+        diff::stderr_color(),
+    ) {
+        eprint!("{drift}");
+        bail!("'{path}' is not canonically formatted: run 'dwf config:format' to fix");
+    }
+    Ok(())
+}
+
+/// Returns the current and the canonical text of `path`.
+fn read(path: &str) -> Result<(String, String)> {
+    let text = fs::read_to_string(path).with_context(|| format!("failed to read '{path}'"))?;
+    let canonical =
+        format::format_config(&text).with_context(|| format!("failed to format '{path}'"))?;
+    Ok((text, canonical))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn check_mode_reports_without_rewriting() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("devflow.toml");
+        let text = "[project]\nstack = [\"rust\"]\nname = \"demo\"\n";
+        fs::write(&path, text).unwrap();
+        let path = path.to_str().unwrap();
+
+        let err = run(path, true).unwrap_err();
+        assert!(err.to_string().contains("dwf config:format"));
+        assert_eq!(fs::read_to_string(path).unwrap(), text);
+
+        run(path, false).unwrap();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "[project]\nname = \"demo\"\nstack = [\"rust\"]\n"
+        );
+        run(path, true).unwrap();
+    }
+}
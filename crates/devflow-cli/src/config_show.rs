@@ -0,0 +1,172 @@
+//! The effective config and where each setting comes from (`dwf config:show`),
+//! and moving the preset lock (`dwf config:update`).
+
+use std::fs;
+
+use anyhow::{Context, Result};
+
+use devflow_core::config::LoadOptions;
+use devflow_core::constants::PRESET_LOCK_FILE;
+use devflow_core::{preset, DevflowConfig};
+
+/// Settings longer than this are not padded to align their origins.
+const ALIGN_WIDTH: usize = 60;
+
+/// Prints every setting of the config at `path` merged over its preset,
+/// each with the file it comes from.
+///
+/// # Errors
+/// Returns an error if the config or its preset cannot be loaded.
+pub fn show(path: &str, lenient: bool) -> Result<()> {
+    let (cfg, _) = DevflowConfig::load_with_options(
+        path,
+        &LoadOptions {
+            lenient,
+            ..LoadOptions::default()
+        },
+    )
+    .with_context(|| format!("unable to load config '{path}'"))?;
+    print!("{}", render(&cfg, path)?);
+    Ok(())
+}
+
+/// Resolves `extends` to the latest commit of its preset and locks it.
+///
+/// # Errors
+/// Returns an error if the config or its preset cannot be loaded.
+pub fn update(path: &str) -> Result<()> {
+    let (cfg, _) = DevflowConfig::load_with_options(
+        path,
+        &LoadOptions {
+            update_presets: true,
+            ..LoadOptions::default()
+        },
+    )
+    .with_context(|| format!("unable to load config '{path}'"))?;
+    match &cfg.preset {
+        Some(preset) if preset.commit.is_some() => {
+            println!("config:update locked {preset} in {PRESET_LOCK_FILE}");
+        }
+        Some(preset) => println!("config:update {preset} is a local file; nothing to lock"),
+        None => println!("config:update {path} extends no preset"),
+    }
+    Ok(())
+}
+
+fn render(cfg: &DevflowConfig, path: &str) -> Result<String> {
+    let text = fs::read_to_string(path).with_context(|| format!("failed to read '{path}'"))?;
+    let local: toml::Table =
+        toml::from_str(&text).with_context(|| format!("failed to parse '{path}'"))?;
+    let mut out = format!("config:show {path}\n");
+    let merged = match &cfg.preset {
+        Some(preset) => {
+            out.push_str(&format!("extends {preset}\n"));
+            let preset_text = fs::read_to_string(&preset.file)
+                .with_context(|| format!("failed to read '{}'", preset.file.display()))?;
+            let mut merged: toml::Table = toml::from_str(&preset_text)
+                .with_context(|| format!("failed to parse '{}'", preset.file.display()))?;
+            preset::merge(&mut merged, local.clone());
+            merged
+        }
+        None => local.clone(),
+    };
+    out.push('\n');
+
+    let mut lines = Vec::new();
+    leaves(&merged, Some(&local), "", &mut lines);
+    let width = lines
+        .iter()
+        .map(|(setting, _)| setting.len())
+        .filter(|len| *len <= ALIGN_WIDTH)
+        .max()
+        .unwrap_or(0);
+    let preset = cfg
+        .preset
+        .as_ref()
+        .map_or("", |preset| preset.source.as_str());
+    for (setting, from_local) in lines {
+        let origin = if from_local { path } else { preset };
+        out.push_str(&format!("{setting:width$}  # {origin}\n"));
+    }
+    Ok(out)
+}
+
+/// Flattens `table` into `key = value` lines, noting whether `local` sets
+/// each value; arrays stay whole.
+fn leaves(
+    table: &toml::Table,
+    local: Option<&toml::Table>,
+    prefix: &str,
+    out: &mut Vec<(String, bool)>,
+) {
+    for (key, value) in table {
+        let local_value = local.and_then(|local| local.get(key));
+        let name = format!("{prefix}{}", bare_key(key));
+        match value {
+            toml::Value::Table(table) => leaves(
+                table,
+                local_value.and_then(toml::Value::as_table),
+                &format!("{name}."),
+                out,
+            ),
+            value => out.push((format!("{name} = {value}"), local_value.is_some())),
+        }
+    }
+}
+
+/// `key`, quoted unless it is a bare TOML key.
+fn bare_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if bare {
+        key.to_string()
+    } else {
+        toml::Value::from(key).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn shows_the_origin_of_each_setting() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("service.toml"),
+            "[targets]\npr = [\"fmt:check\"]\nmain = [\"build:release\"]\n\n[commands.\"test:unit\"]\nmax_output = \"1MB\"\n",
+        )
+        .unwrap();
+        let path = dir.path().join("devflow.toml");
+        fs::write(
+            &path,
+            "extends = \"service.toml\"\n\n[project]\nname = \"svc\"\nstack = [\"custom\"]\n\n[targets]\npr = [\"test:unit\"]\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+        let (cfg, _) = DevflowConfig::load_with_options(path, &LoadOptions::default()).unwrap();
+        assert_eq!(cfg.targets.profiles["main"][0].command(), "build:release");
+
+        let out = render(&cfg, path).unwrap();
+        let line = |setting: &str| {
+            out.lines()
+                .find(|line| line.starts_with(setting))
+                .unwrap_or_else(|| panic!("no {setting} in {out}"))
+                .split("  # ")
+                .nth(1)
+                .unwrap()
+                .to_string()
+        };
+        assert!(out.contains("\nextends service.toml ("), "{out}");
+        assert_eq!(line("project.name = \"svc\""), path);
+        assert_eq!(line("targets.pr = [\"test:unit\"]"), path);
+        assert_eq!(line("targets.main = [\"build:release\"]"), "service.toml");
+        assert_eq!(
+            line("commands.\"test:unit\".max_output = \"1MB\""),
+            "service.toml"
+        );
+    }
+}
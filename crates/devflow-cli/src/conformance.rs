@@ -0,0 +1,295 @@
+//! `ext:verify <name>`: a conformance check for subprocess extensions.
+//!
+//! Runs the extension's `--discover`, checks the manifest against the
+//! configured version requirement and the protocol's `api_version`, asks for
+//! an action for every advertised capability on every target OS and checks
+//! that each action is well-formed. Meant for extension authors; it needs no
+//! project, but honours `[extensions.<name>]` when `devflow.toml` exists.
+
+use std::process::Command;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+
+use devflow_core::constants::EXTENSION_API_VERSION;
+use devflow_core::extension::subprocess::SubprocessExtension;
+use devflow_core::{
+    ActionContext, CommandRef, DevflowConfig, ExecutionAction, Extension, TargetOs,
+};
+
+use crate::discovery;
+
+const TARGETS: [TargetOs; 3] = [TargetOs::Linux, TargetOs::Macos, TargetOs::Windows];
+
+/// The result of one check: `Err` holds why it failed.
+struct Check {
+    name: String,
+    outcome: Result<String, String>,
+}
+
+/// Runs `ext:verify:<name>` and prints one line per check.
+///
+/// # Errors
+/// Returns an error if no extension is named or any check fails.
+pub fn run(cfg: Option<&DevflowConfig>, selector: Option<&str>) -> Result<()> {
+    let name = selector
+        .and_then(|selector| selector.strip_prefix("verify:"))
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| {
+            anyhow!("ext:verify requires an extension name (e.g. `dwf ext:verify python`)")
+        })?;
+    let binary = discovery::binary_for(cfg, name);
+    println!("ext:verify {name} ({binary})");
+    let ext_cfg = cfg
+        .and_then(|cfg| cfg.extensions.as_ref())
+        .and_then(|extensions| extensions.get(name));
+    let checks = verify(
+        name,
+        &binary,
+        ext_cfg.and_then(|ext_cfg| ext_cfg.version.as_deref()),
+        ext_cfg.and_then(|ext_cfg| ext_cfg.api_version),
+    );
+    let mut failed = 0;
+    for check in &checks {
+        match &check.outcome {
+            Ok(detail) => println!(" ok   {}: {}", check.name, detail),
+            Err(reason) => {
+                failed += 1;
+                println!(" FAIL {}: {}", check.name, reason);
+            }
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "extension '{}' failed {} of {} checks",
+            name,
+            failed,
+            checks.len()
+        );
+    }
+    Ok(())
+}
+
+/// Runs every check against `binary`; a failed `--discover` ends the run.
+fn verify(
+    name: &str,
+    binary: &str,
+    required_version: Option<&str>,
+    declared_api_version: Option<u32>,
+) -> Vec<Check> {
+    let check = |name: &str, outcome| Check {
+        name: name.to_string(),
+        outcome,
+    };
+    let manifest = match discover(binary) {
+        Ok(manifest) => manifest,
+        Err(reason) => return vec![check("discover", Err(reason))],
+    };
+    let mut checks = vec![check(
+        "discover",
+        Ok(format!("{} capabilities", manifest.capabilities.len())),
+    )];
+    checks.push(check(
+        "version",
+        check_version(name, required_version, manifest.version.as_deref()),
+    ));
+    checks.push(check(
+        "api_version",
+        check_api_version(declared_api_version, manifest.api_version),
+    ));
+
+    let mut capabilities: Vec<&String> = manifest.capabilities.iter().collect();
+    capabilities.sort();
+    if capabilities.is_empty() {
+        checks.push(check("capabilities", Err("none advertised".to_string())));
+    }
+    let ext = SubprocessExtension::new(
+        name.to_string(),
+        binary.to_string(),
+        manifest.capabilities.clone(),
+        false,
+    );
+    for capability in capabilities {
+        let outcome = match CommandRef::from_str(capability) {
+            Ok(cmd) => check_actions(&ext, &cmd),
+            Err(e) => Err(e.to_string()),
+        };
+        checks.push(check(&format!("action {capability}"), outcome));
+    }
+    checks
+}
+
+fn discover(binary: &str) -> Result<discovery::Manifest, String> {
+    let output = Command::new(binary)
+        .arg("--discover")
+        .output()
+        .map_err(|e| format!("failed to run '{binary}': {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "'{binary} --discover' failed with {}",
+            output.status
+        ));
+    }
+    discovery::parse_manifest(&output.stdout)
+        .map_err(|e| format!("'{binary} --discover' printed invalid capabilities: {e}"))
+}
+
+fn check_version(
+    name: &str,
+    required: Option<&str>,
+    reported: Option<&str>,
+) -> Result<String, String> {
+    if let Some(required) = required {
+        discovery::check_version(name, required, reported).map_err(|e| e.to_string())?;
+    }
+    match reported {
+        Some(version) => semver::Version::parse(version)
+            .map(|_| version.to_string())
+            .map_err(|e| format!("'{version}' is not a semantic version: {e}")),
+        None => Ok("not reported".to_string()),
+    }
+}
+
+fn check_api_version(declared: Option<u32>, reported: Option<u32>) -> Result<String, String> {
+    if let (Some(declared), Some(reported)) = (declared, reported) {
+        if declared != reported {
+            return Err(format!(
+                "devflow.toml declares api_version {declared}, the extension reports {reported}"
+            ));
+        }
+    }
+    let version = reported.or(declared).unwrap_or(EXTENSION_API_VERSION);
+    if version != EXTENSION_API_VERSION {
+        return Err(format!(
+            "api_version {version} is not supported (this dwf speaks {EXTENSION_API_VERSION})"
+        ));
+    }
+    Ok(match reported {
+        Some(_) => version.to_string(),
+        None => format!("not reported, assuming {EXTENSION_API_VERSION}"),
+    })
+}
+
+/// Builds `cmd` for every target OS; declining some is fine, declining all
+/// of an advertised capability is not.
+fn check_actions(ext: &SubprocessExtension, cmd: &CommandRef) -> Result<String, String> {
+    let mut built = Vec::new();
+    for os in TARGETS {
+        match ext.build_action_for(cmd, &ActionContext { os }) {
+            Ok(Some(action)) => {
+                check_action(&action).map_err(|reason| format!("{}: {reason}", os.as_str()))?;
+                built.push(os.as_str());
+            }
+            Ok(None) => {}
+            Err(e) => return Err(format!("{}: {e}", os.as_str())),
+        }
+    }
+    if built.is_empty() {
+        return Err("advertised but declined on every OS".to_string());
+    }
+    Ok(built.join(", "))
+}
+
+/// Checks that `action` can be spawned as given.
+fn check_action(action: &ExecutionAction) -> Result<(), String> {
+    if action.program.trim().is_empty() {
+        return Err("program is empty".to_string());
+    }
+    if action.program.contains('\0') || action.args.iter().any(|arg| arg.contains('\0')) {
+        return Err("program or argument contains a NUL byte".to_string());
+    }
+    let mut keys: Vec<&String> = action.env.keys().collect();
+    keys.sort();
+    for key in keys {
+        if key.is_empty() || key.contains(['=', '\0']) {
+            return Err(format!("invalid environment variable name '{key}'"));
+        }
+        if action.env[key].contains('\0') {
+            return Err(format!("environment variable {key} contains a NUL byte"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(dir: &std::path::Path, body: &str) -> String {
+        let path = dir.join("devflow-ext-demo");
+        fs::write(&path, format!("#!/bin/sh\n{body}")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn verifies_discovery_and_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = script(
+            dir.path(),
+            r#"if [ "$1" = "--discover" ]; then
+    echo '{"version": "0.2.0", "api_version": 1, "capabilities": ["test", "lint", "fmt"]}'
+    exit 0
+fi
+request=$(cat)
+case "$request" in
+    *'"primary":"test"'*'"os":"windows"'*) exit 1 ;;
+    *'"primary":"test"'*) echo '{"program": "pytest", "args": []}' ;;
+    *'"primary":"lint"'*) echo '{"program": "", "args": []}' ;;
+    *) exit 1 ;;
+esac
+"#,
+        );
+
+        let checks = verify("demo", &binary, Some(">=0.3"), None);
+        let outcome = |name: &str| {
+            checks
+                .iter()
+                .find(|check| check.name == name)
+                .map(|check| check.outcome.clone())
+                .unwrap()
+        };
+        assert_eq!(outcome("discover"), Ok("3 capabilities".to_string()));
+        assert!(outcome("version").unwrap_err().contains(">=0.3"));
+        assert_eq!(outcome("api_version"), Ok("1".to_string()));
+        assert_eq!(outcome("action test"), Ok("linux, macos".to_string()));
+        assert_eq!(
+            outcome("action lint"),
+            Err("linux: program is empty".to_string())
+        );
+        assert_eq!(
+            outcome("action fmt"),
+            Err("advertised but declined on every OS".to_string())
+        );
+
+        let checks = verify("demo", &binary, None, Some(2));
+        assert!(checks[2]
+            .outcome
+            .as_ref()
+            .unwrap_err()
+            .contains("declares api_version 2"));
+
+        let checks = verify("demo", "/nonexistent/devflow-ext-demo", None, None);
+        assert_eq!(checks.len(), 1);
+        assert!(checks[0].outcome.is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_environments() {
+        let action = |key: &str| ExecutionAction {
+            program: "pytest".to_string(),
+            args: Vec::new(),
+            env: [(key.to_string(), "1".to_string())].into(),
+        };
+        check_action(&action("PYTHONDONTWRITEBYTECODE")).unwrap();
+        assert!(check_action(&action("A=B")).is_err());
+        assert!(check_action(&action("")).is_err());
+        assert!(check_api_version(None, Some(2)).is_err());
+        assert_eq!(
+            check_api_version(None, None),
+            Ok("not reported, assuming 1".to_string())
+        );
+    }
+}
@@ -0,0 +1,472 @@
+//! Container image management (`container:pull`, `container:build`).
+//!
+//! Missing images are pulled explicitly before `docker run`/`podman run`, so the
+//! pull reports layer progress and an ETA instead of stalling silently. Builds go
+//! through BuildKit so `--mount=type=cache` steps and `[container.buildkit]`
+//! registry caches are reused across rebuilds.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, IsTerminal, Read};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use tracing::{info, warn};
+
+use devflow_core::config::BuildkitConfig;
+use devflow_core::constants::CI_DOCKERFILE;
+use devflow_core::DevflowConfig;
+
+use crate::executor;
+
+/// Output lines kept for the error message when a pull fails.
+const ERROR_TAIL: usize = 20;
+
+/// Pulls the configured image (`container:pull`), e.g. to pre-warm a machine.
+pub fn pull_configured(cfg: &DevflowConfig) -> Result<()> {
+    let engine = executor::container_engine(cfg)?;
+    let image = executor::container_image(cfg)?;
+    pull(&engine, &image, executor::run_platform(cfg).as_deref())?;
+    println!("container:pull {} ready ({})", image, engine);
+    Ok(())
+}
+
+/// Builds the CI image from the project's Dockerfile (`container:build`).
+///
+/// # Errors
+/// Returns an error if the Dockerfile is missing or the engine build fails.
+pub fn build(cfg: &DevflowConfig) -> Result<()> {
+    let engine = executor::container_engine(cfg)?;
+    let image = executor::container_image(cfg)?;
+    let platforms = cfg
+        .container
+        .as_ref()
+        .map(|c| c.platforms.clone())
+        .unwrap_or_default();
+    build_image(cfg, &engine, &image, &platforms)?;
+    println!("container:build {} ready ({})", image, engine);
+    Ok(())
+}
+
+/// Builds the `ci` stage of the project's Dockerfile as `image` for `platforms`
+/// (the host's platform when empty).
+///
+/// # Errors
+/// Returns an error if the Dockerfile is missing or the engine build fails.
+pub fn build_image(
+    cfg: &DevflowConfig,
+    engine: &str,
+    image: &str,
+    platforms: &[String],
+) -> Result<()> {
+    let context = cfg
+        .source_dir
+        .clone()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| std::path::PathBuf::from("."));
+    if !context.join(CI_DOCKERFILE).exists() {
+        bail!(
+            "{} not found in '{}' (run 'dwf init' to generate one)",
+            CI_DOCKERFILE,
+            context.display()
+        );
+    }
+    let buildkit = cfg
+        .container
+        .as_ref()
+        .map(|c| c.buildkit.clone())
+        .unwrap_or_default();
+
+    let args = build_args(engine, &buildkit, platforms, image);
+    info!(target: "devflow", "{} {}", engine, args.join(" "));
+    let status = Command::new(engine)
+        .args(&args)
+        .current_dir(&context)
+        .env("DOCKER_BUILDKIT", "1")
+        .status()
+        .with_context(|| format!("failed to start '{} build'", engine))?;
+    if !status.success() {
+        bail!("failed to build image '{}' ({})", image, status);
+    }
+    Ok(())
+}
+
+/// Build argv for `engine`: `docker buildx build` with every configured cache
+/// spec, or `podman build --layers` with the registry cache only.
+///
+/// Several `platforms` make a multi-arch build: Docker's local image store
+/// cannot load a manifest list, so buildx pushes it instead of `--load`;
+/// Podman assembles it as a local manifest.
+fn build_args(
+    engine: &str,
+    buildkit: &BuildkitConfig,
+    platforms: &[String],
+    image: &str,
+) -> Vec<String> {
+    let multi_arch = platforms.len() > 1;
+    let mut args: Vec<String> = Vec::new();
+    if engine == "podman" {
+        args.extend(["build", "--layers"].map(String::from));
+        if let Some(cache_ref) = &buildkit.cache_ref {
+            args.extend(["--cache-from".to_string(), cache_ref.clone()]);
+            args.extend(["--cache-to".to_string(), cache_ref.clone()]);
+        }
+        if !buildkit.cache_from.is_empty() || !buildkit.cache_to.is_empty() {
+            warn!(
+                target: "devflow",
+                "podman only supports the [container.buildkit] cache_ref; ignoring cache_from/cache_to"
+            );
+        }
+    } else {
+        let output = if multi_arch { "--push" } else { "--load" };
+        args.extend(["buildx", "build", output].map(String::from));
+        for spec in buildkit.cache_from_specs() {
+            args.extend(["--cache-from".to_string(), spec]);
+        }
+        for spec in buildkit.cache_to_specs() {
+            args.extend(["--cache-to".to_string(), spec]);
+        }
+    }
+    if !platforms.is_empty() {
+        args.extend(["--platform".to_string(), platforms.join(",")]);
+    }
+    let tag = if multi_arch && engine == "podman" {
+        "--manifest"
+    } else {
+        "--tag"
+    };
+    args.extend(["--file", CI_DOCKERFILE, "--target", "ci", tag, image, "."].map(String::from));
+    args
+}
+
+/// Pulls `image` (for `platform`, if given) unless the engine already has it.
+/// Returns whether it was already present.
+pub fn ensure_image(engine: &str, image: &str, platform: Option<&str>) -> Result<bool> {
+    if image_present(engine, image) {
+        return Ok(true);
+    }
+    info!(target: "devflow", "image {} not present locally, pulling", image);
+    pull(engine, image, platform)?;
+    Ok(false)
+}
+
+/// Whether the engine's local store has `image`.
+pub fn image_present(engine: &str, image: &str) -> bool {
+    Command::new(engine)
+        .args(["image", "inspect", image])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Runs `<engine> pull <image>`, rendering layer progress on stderr.
+///
+/// On a terminal the status line is redrawn in place; otherwise a line is
+/// printed each time a layer finishes.
+pub fn pull(engine: &str, image: &str, platform: Option<&str>) -> Result<()> {
+    let mut command = Command::new(engine);
+    command.arg("pull");
+    if let Some(platform) = platform {
+        command.args(["--platform", platform]);
+    }
+    let mut child = command
+        .arg(image)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start '{} pull {}'", engine, image))?;
+
+    // Docker reports progress on stdout, Podman on stderr.
+    let (tx, rx) = mpsc::channel();
+    let streams: [Option<Box<dyn Read + Send>>; 2] = [
+        child
+            .stdout
+            .take()
+            .map(|s| Box::new(s) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|s| Box::new(s) as Box<dyn Read + Send>),
+    ];
+    for stream in streams.into_iter().flatten() {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let interactive = std::io::stderr().is_terminal();
+    let started = Instant::now();
+    let mut progress = PullProgress::default();
+    let mut tail: Vec<String> = Vec::new();
+    for line in rx {
+        let finished_before = progress.finished();
+        if progress.observe(&line) && (interactive || progress.finished() != finished_before) {
+            let status = format!("pulling {}: {}", image, progress.render(started.elapsed()));
+            if interactive {
+                eprint!("\r{}\x1b[K", status);
+            } else {
+                eprintln!("{}", status);
+            }
+        }
+        tail.push(line);
+        if tail.len() > ERROR_TAIL {
+            tail.remove(0);
+        }
+    }
+    if interactive && progress.total() > 0 {
+        eprintln!();
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        bail!(
+            "failed to pull image '{}' ({}):\n{}",
+            image,
+            status,
+            tail.join("\n")
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LayerState {
+    Pending,
+    Downloaded,
+    Done,
+}
+
+/// Per-layer pull state parsed from `docker pull` / `podman pull` output.
+#[derive(Debug, Default)]
+struct PullProgress {
+    layers: BTreeMap<String, LayerState>,
+}
+
+impl PullProgress {
+    /// Records one output line; returns whether any layer changed state.
+    fn observe(&mut self, line: &str) -> bool {
+        let Some((layer, state)) = parse_line(line) else {
+            return false;
+        };
+        match self.layers.get_mut(layer) {
+            Some(current) if *current >= state => false,
+            Some(current) => {
+                *current = state;
+                true
+            }
+            None => {
+                self.layers.insert(layer.to_string(), state);
+                true
+            }
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn finished(&self) -> usize {
+        self.layers
+            .values()
+            .filter(|s| **s == LayerState::Done)
+            .count()
+    }
+
+    /// Completion in `0.0..=1.0`; a downloaded (not yet extracted) layer counts half.
+    fn fraction(&self) -> f64 {
+        if self.layers.is_empty() {
+            return 0.0;
+        }
+        let units: usize = self
+            .layers
+            .values()
+            .map(|s| match s {
+                LayerState::Pending => 0,
+                LayerState::Downloaded => 1,
+                LayerState::Done => 2,
+            })
+            .sum();
+        units as f64 / (2 * self.layers.len()) as f64
+    }
+
+    /// Renders e.g. `3/7 layers (42%), ETA 1m20s`.
+    fn render(&self, elapsed: Duration) -> String {
+        let fraction = self.fraction();
+        let mut out = format!(
+            "{}/{} layers ({:.0}%)",
+            self.finished(),
+            self.total(),
+            fraction * 100.0
+        );
+        if fraction > 0.0 && fraction < 1.0 {
+            let remaining = elapsed.as_secs_f64() * (1.0 - fraction) / fraction;
+            out.push_str(&format!(
+                ", ETA {}",
+                format_duration(remaining.round() as u64)
+            ));
+        }
+        out
+    }
+}
+
+/// Extracts `(layer, state)` from a Docker (`<id>: Pull complete`) or Podman
+/// (`Copying blob <id> done`) progress line.
+fn parse_line(line: &str) -> Option<(&str, LayerState)> {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("Copying blob ") {
+        let mut parts = rest.splitn(2, ' ');
+        let layer = parts.next()?;
+        let state = match parts.next().map(str::trim) {
+            Some(status) if status.starts_with("done") || status.starts_with("skipped") => {
+                LayerState::Done
+            }
+            _ => LayerState::Pending,
+        };
+        return Some((layer, state));
+    }
+
+    let (layer, status) = line.split_once(": ")?;
+    if layer.len() < 12 || !layer.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let state = if status.starts_with("Pull complete") || status.starts_with("Already exists") {
+        LayerState::Done
+    } else if status.starts_with("Download complete") || status.starts_with("Extracting") {
+        LayerState::Downloaded
+    } else {
+        LayerState::Pending
+    };
+    Some((layer, state))
+}
+
+fn format_duration(secs: u64) -> String {
+    if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_docker_layer_progress() {
+        let mut progress = PullProgress::default();
+        for line in [
+            "latest: Pulling from softmentor/devflow-ci",
+            "a1b2c3d4e5f6: Pulling fs layer",
+            "0f9e8d7c6b5a: Pulling fs layer",
+            "a1b2c3d4e5f6: Downloading  12.3MB/40.1MB",
+            "a1b2c3d4e5f6: Download complete",
+            "0f9e8d7c6b5a: Already exists",
+        ] {
+            progress.observe(line);
+        }
+        assert_eq!(progress.total(), 2);
+        assert_eq!(progress.finished(), 1);
+        assert_eq!(
+            progress.render(Duration::from_secs(30)),
+            "1/2 layers (75%), ETA 10s"
+        );
+
+        assert!(progress.observe("a1b2c3d4e5f6: Pull complete"));
+        // Late or repeated lines never move a layer backwards.
+        assert!(!progress.observe("a1b2c3d4e5f6: Downloading  40.1MB/40.1MB"));
+        assert_eq!(
+            progress.render(Duration::from_secs(40)),
+            "2/2 layers (100%)"
+        );
+    }
+
+    #[test]
+    fn tracks_podman_blob_progress() {
+        let mut progress = PullProgress::default();
+        progress.observe("Trying to pull ghcr.io/softmentor/devflow-ci:latest...");
+        progress.observe("Copying blob 5b5fe70539cd done");
+        progress.observe("Copying blob sha256:9c1b6dd6c1e6");
+        progress.observe("Copying blob 7d63c13c1a5e skipped: already exists");
+        progress.observe("Copying config 2ad3a1b5f9e0 done");
+        assert_eq!(progress.total(), 3);
+        assert_eq!(progress.finished(), 2);
+    }
+
+    #[test]
+    fn docker_builds_pass_registry_cache_specs() {
+        let buildkit = BuildkitConfig {
+            cache_ref: Some("ghcr.io/acme/app-ci:buildcache".to_string()),
+            cache_from: vec!["type=gha".to_string()],
+            cache_to: Vec::new(),
+        };
+        let args = build_args("docker", &buildkit, &[], "app-ci:latest").join(" ");
+        assert_eq!(
+            args,
+            "buildx build --load \
+             --cache-from type=registry,ref=ghcr.io/acme/app-ci:buildcache \
+             --cache-from type=gha \
+             --cache-to type=registry,ref=ghcr.io/acme/app-ci:buildcache,mode=max \
+             --file Dockerfile.devflow --target ci --tag app-ci:latest ."
+        );
+    }
+
+    #[test]
+    fn podman_builds_use_the_cache_ref_directly() {
+        let buildkit = BuildkitConfig {
+            cache_ref: Some("quay.io/acme/cache".to_string()),
+            ..BuildkitConfig::default()
+        };
+        let args = build_args("podman", &buildkit, &[], "app-ci:latest");
+        assert_eq!(
+            args[..6],
+            [
+                "build",
+                "--layers",
+                "--cache-from",
+                "quay.io/acme/cache",
+                "--cache-to",
+                "quay.io/acme/cache"
+            ]
+        );
+        let plain = build_args("podman", &BuildkitConfig::default(), &[], "app-ci:latest");
+        assert!(!plain.iter().any(|a| a.starts_with("--cache")));
+    }
+
+    #[test]
+    fn multi_arch_builds_push_a_manifest_list() {
+        let platforms = vec!["linux/amd64".to_string(), "linux/arm64".to_string()];
+        let docker =
+            build_args("docker", &BuildkitConfig::default(), &platforms, "app-ci").join(" ");
+        assert_eq!(
+            docker,
+            "buildx build --push --platform linux/amd64,linux/arm64 \
+             --file Dockerfile.devflow --target ci --tag app-ci ."
+        );
+        let podman =
+            build_args("podman", &BuildkitConfig::default(), &platforms, "app-ci").join(" ");
+        assert!(podman.ends_with("--platform linux/amd64,linux/arm64 --file Dockerfile.devflow --target ci --manifest app-ci ."));
+
+        let single = build_args(
+            "docker",
+            &BuildkitConfig::default(),
+            &platforms[1..],
+            "app-ci",
+        );
+        assert_eq!(single[2], "--load");
+    }
+
+    #[test]
+    fn formats_eta_durations() {
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(80), "1m20s");
+    }
+}
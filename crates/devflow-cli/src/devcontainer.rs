@@ -0,0 +1,210 @@
+//! `.devcontainer/devcontainer.json` from the container runtime profile
+//! (`dwf devcontainer:generate`, `dwf devcontainer:check`).
+//!
+//! The generated definition uses the image containerized commands run in
+//! (`[container] image`, else the default CI image), the workspace at
+//! `/workspace`, the stacks' cache mounts under the cache root, their merged
+//! environment and the `[container]` run arguments, so a dev container sees
+//! what `dwf` and CI see. `devcontainer:check` reports drift like `ci:check`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+
+use devflow_core::constants::{DEFAULT_CACHE_ROOT, DEVCONTAINER_FILE};
+use devflow_core::{DevflowConfig, ExtensionRegistry};
+
+use crate::{diff, executor};
+
+/// First line of the generated file; `devcontainer.json` accepts comments.
+const HEADER: &str =
+    "// Generated by 'dwf devcontainer:generate'; 'dwf devcontainer:check' reports drift.";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Devcontainer {
+    name: String,
+    image: String,
+    workspace_mount: String,
+    workspace_folder: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mounts: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    container_env: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    run_args: Vec<String>,
+}
+
+/// Renders the `devcontainer.json` matching the container runtime profile.
+///
+/// # Errors
+/// Returns an error if the stacks' environments conflict under
+/// `[runtime] env_conflict = "error"`.
+pub fn render(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<String> {
+    let root = cfg
+        .cache
+        .as_ref()
+        .and_then(|cache| cache.root.as_deref())
+        .unwrap_or(DEFAULT_CACHE_ROOT)
+        .trim_end_matches('/');
+    let root = if Path::new(root).is_absolute() {
+        root.to_string()
+    } else {
+        format!("${{localWorkspaceFolder}}/{root}")
+    };
+    let mut mounts: Vec<String> = executor::ordered_stacks(cfg)
+        .iter()
+        .filter_map(|stack| registry.get(stack))
+        .flat_map(|ext| ext.cache_mounts())
+        .filter_map(|mount| {
+            let (host_rel, container_abs) = executor::parse_mount(&mount)?;
+            Some(format!(
+                "source={root}/{host_rel},target={container_abs},type=bind"
+            ))
+        })
+        .collect();
+    mounts.sort();
+    mounts.dedup();
+    let container_env = executor::merged_env(cfg, registry)?
+        .vars
+        .into_iter()
+        .map(|(key, var)| (key, var.value))
+        .collect();
+    let spec = Devcontainer {
+        name: cfg.project.name.clone(),
+        image: executor::configured_image(cfg),
+        workspace_mount: format!(
+            "source=${{localWorkspaceFolder}},target={},type=bind",
+            executor::CONTAINER_WORKSPACE
+        ),
+        workspace_folder: executor::CONTAINER_WORKSPACE,
+        mounts,
+        container_env,
+        run_args: cfg
+            .container
+            .as_ref()
+            .map(executor::configured_run_args)
+            .unwrap_or_default(),
+    };
+    let json = serde_json::to_string_pretty(&spec).context("failed to render devcontainer.json")?;
+    Ok(format!("{HEADER}\n{json}\n"))
+}
+
+/// Where the definition lives: under the source directory.
+pub fn path(cfg: &DevflowConfig) -> PathBuf {
+    cfg.source_dir
+        .as_deref()
+        .unwrap_or(Path::new(""))
+        .join(DEVCONTAINER_FILE)
+}
+
+/// Writes the rendered definition, or prints it with `stdout`.
+///
+/// # Errors
+/// Returns an error if it cannot be rendered or written.
+pub fn generate(cfg: &DevflowConfig, registry: &ExtensionRegistry, stdout: bool) -> Result<()> {
+    let rendered = render(cfg, registry)?;
+    if stdout {
+        print!("{rendered}");
+        return Ok(());
+    }
+    let path = path(cfg);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory '{}'", parent.display()))?;
+    }
+    fs::write(&path, rendered).with_context(|| format!("failed to write '{}'", path.display()))?;
+    println!("devcontainer:generate wrote {}", path.display());
+    Ok(())
+}
+
+/// Fails with a diff on stderr if the definition on disk is not what
+/// `generate` would write.
+///
+/// # Errors
+/// Returns an error if the file is missing or has drifted.
+pub fn check(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<()> {
+    let expected = render(cfg, registry)?;
+    let path = path(cfg);
+    let label = path.display().to_string();
+    let actual = fs::read_to_string(&path).with_context(|| {
+        format!("failed to read '{label}': run 'dwf devcontainer:generate' to create it")
+    })?;
+    if let Some(drift) = diff::unified(
+        &expected,
+        &actual,
+        "expected (dwf devcontainer:generate)",
+        &label,
+        diff::stderr_color(),
+    ) {
+        eprint!("{drift}");
+        return Err(anyhow!(
+            "devcontainer drift detected in '{label}': run 'dwf devcontainer:generate' to resync"
+        ));
+    }
+    println!("devcontainer:check passed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::runtime::devcontainer_image;
+
+    #[test]
+    fn renders_the_container_runtime_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        let mut cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = ["fmt:check"]
+
+            [cache]
+            root = ".cache/dwf"
+
+            [container]
+            image = "ghcr.io/acme/ci:1.4"
+            extra_args = ["--shm-size=1g"]
+            "#,
+        )
+        .unwrap();
+        cfg.source_dir = Some(dir.path().to_path_buf());
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
+
+        let rendered = render(&cfg, &registry).unwrap();
+        assert!(rendered.starts_with(&format!("{HEADER}\n{{\n  \"name\": \"demo\",\n")));
+        assert!(rendered.contains("\"image\": \"ghcr.io/acme/ci:1.4\""));
+        assert!(rendered.contains(
+            "\"source=${localWorkspaceFolder}/.cache/dwf/rust/cargo,target=/workspace/.cargo-cache,type=bind\""
+        ));
+        assert!(rendered.contains("\"runArgs\": [\n    \"--shm-size=1g\"\n  ]"));
+
+        let err = check(&cfg, &registry).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("run 'dwf devcontainer:generate' to create it"));
+        generate(&cfg, &registry, false).unwrap();
+        check(&cfg, &registry).unwrap();
+        assert_eq!(
+            devcontainer_image(dir.path()).unwrap(),
+            "ghcr.io/acme/ci:1.4"
+        );
+
+        fs::write(path(&cfg), rendered.replace("1.4", "1.3")).unwrap();
+        let err = check(&cfg, &registry).unwrap_err();
+        assert!(err.to_string().starts_with("devcontainer drift detected"));
+    }
+}
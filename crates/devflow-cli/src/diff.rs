@@ -0,0 +1,195 @@
+//! Line-based unified diffs for `ci:check`, `ci:diff` and
+//! `config:format --check` drift reports.
+
+use std::io::IsTerminal;
+
+use crate::styles;
+
+/// Lines of unchanged context shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Renders a unified diff from `expected` to `actual`, or `None` if they match.
+///
+/// Lines are colored (red removals, green additions) when `color` is set.
+pub fn unified(
+    expected: &str,
+    actual: &str,
+    expected_label: &str,
+    actual_label: &str,
+    color: bool,
+) -> Option<String> {
+    let ops = line_ops(expected, actual);
+    if ops.iter().all(|op| matches!(op, Op::Same(_))) {
+        return None;
+    }
+
+    let paint = |style: anstyle::Style, text: String| {
+        if color {
+            format!("{}{}{}", style.render(), text, style.render_reset())
+        } else {
+            text
+        }
+    };
+
+    let mut out = String::new();
+    out.push_str(&paint(styles::DIFF_HEADER, format!("--- {expected_label}")));
+    out.push('\n');
+    out.push_str(&paint(styles::DIFF_HEADER, format!("+++ {actual_label}")));
+    out.push('\n');
+
+    for (start, end) in hunks(&ops) {
+        let (mut old_line, mut new_line) = (1, 1);
+        for op in &ops[..start] {
+            match op {
+                Op::Same(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                Op::Removed(_) => old_line += 1,
+                Op::Added(_) => new_line += 1,
+            }
+        }
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|op| !matches!(op, Op::Added(_))).count();
+        let new_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Removed(_)))
+            .count();
+        out.push_str(&paint(
+            styles::DIFF_HUNK,
+            format!("@@ -{old_line},{old_len} +{new_line},{new_len} @@"),
+        ));
+        out.push('\n');
+
+        for op in hunk {
+            let line = match op {
+                Op::Same(text) => format!(" {text}"),
+                Op::Removed(text) => paint(styles::DIFF_REMOVED, format!("-{text}")),
+                Op::Added(text) => paint(styles::DIFF_ADDED, format!("+{text}")),
+            };
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    Some(out)
+}
+
+/// Whether diffs on stderr should be colored (a terminal, and `NO_COLOR` unset).
+pub fn stderr_color() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Whether diffs on stdout should be colored (a terminal, and `NO_COLOR` unset).
+pub fn stdout_color() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Computes the line edit script via a longest-common-subsequence table.
+fn line_ops<'a>(expected: &'a str, actual: &'a str) -> Vec<Op<'a>> {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(Op::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(Op::Removed(old[i]));
+            i += 1;
+        } else {
+            ops.push(Op::Added(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| Op::Removed(line)));
+    ops.extend(new[j..].iter().map(|line| Op::Added(line)));
+    ops
+}
+
+/// Groups changes into `[start, end)` op ranges padded with context, merging overlaps.
+fn hunks(ops: &[Op<'_>]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (idx, op) in ops.iter().enumerate() {
+        if matches!(op, Op::Same(_)) {
+            continue;
+        }
+        let start = idx.saturating_sub(CONTEXT);
+        let end = (idx + 1 + CONTEXT).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_inputs_have_no_diff() {
+        assert!(unified("a\nb\n", "a\nb\n", "expected", "actual", false).is_none());
+    }
+
+    #[test]
+    fn renders_hunk_with_context() {
+        let expected = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let actual = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        let out = unified(expected, actual, "generated", "ci.yml", false).unwrap();
+        assert_eq!(
+            out,
+            "--- generated\n+++ ci.yml\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn separates_distant_changes_into_hunks() {
+        let expected: String = (1..=20).map(|n| format!("{n}\n")).collect();
+        let actual: String = (1..=20)
+            .filter(|n| *n != 19)
+            .map(|n| {
+                if n == 2 {
+                    "two\n".to_string()
+                } else {
+                    format!("{n}\n")
+                }
+            })
+            .collect();
+        let out = unified(&expected, &actual, "a", "b", false).unwrap();
+        assert_eq!(out.matches("@@ -").count(), 2);
+        assert!(out.contains("-19\n"));
+        assert!(out.contains("+two\n"));
+    }
+
+    #[test]
+    fn colors_changed_lines() {
+        let out = unified("old\n", "new\n", "a", "b", true).unwrap();
+        assert!(out.contains(&format!(
+            "{}-old{}",
+            styles::DIFF_REMOVED.render(),
+            styles::DIFF_REMOVED.render_reset()
+        )));
+    }
+}
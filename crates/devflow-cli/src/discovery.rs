@@ -3,32 +3,169 @@
 //! This module implements both implicit (by convention) and explicit (by config)
 //! discovery of subprocess-based extensions.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::process::Command;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
 use tracing::{debug, warn};
 
+use devflow_core::diagnostic::ConfigDiagnostic;
 use devflow_core::extension::subprocess::SubprocessExtension;
-use devflow_core::{DevflowConfig, ExtensionRegistry};
+use devflow_core::problem::ProblemMatcher;
+use devflow_core::tool::ToolRequirement;
+use devflow_core::{CapabilityInfo, DevflowConfig, ExtensionRegistry, ResourceClass};
 
 /// The naming convention prefix for Devflow subprocess extensions.
 const EXTENSION_PREFIX: &str = "devflow-ext-";
 
-/// Probes a potential subprocess extension for its capabilities.
+/// The `--discover` response: a bare capability list, or an object that also
+/// reports the extension version, its problem matchers, its tools, the
+/// resource classes of its commands and their descriptions.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DiscoverResponse {
+    Capabilities(HashSet<String>),
+    Detailed {
+        version: Option<String>,
+        api_version: Option<u32>,
+        capabilities: HashSet<String>,
+        #[serde(default)]
+        problem_matchers: Vec<ProblemMatcher>,
+        #[serde(default)]
+        tools: Vec<ToolRequirement>,
+        #[serde(default)]
+        resources: BTreeMap<String, ResourceClass>,
+        #[serde(default)]
+        capability_info: BTreeMap<String, CapabilityInfo>,
+    },
+}
+
+/// What an extension reported from `--discover`.
+#[derive(Debug, Default)]
+pub struct Manifest {
+    pub version: Option<String>,
+    pub api_version: Option<u32>,
+    pub capabilities: HashSet<String>,
+    pub problem_matchers: Vec<ProblemMatcher>,
+    pub tools: Vec<ToolRequirement>,
+    pub resources: BTreeMap<String, ResourceClass>,
+    pub capability_info: BTreeMap<String, CapabilityInfo>,
+}
+
+/// Parses the `--discover` output of an extension.
+///
+/// # Errors
+/// Returns an error if `stdout` is neither a capability list nor a manifest
+/// object.
+pub fn parse_manifest(stdout: &[u8]) -> serde_json::Result<Manifest> {
+    Ok(match serde_json::from_slice(stdout)? {
+        DiscoverResponse::Capabilities(capabilities) => Manifest {
+            capabilities,
+            ..Manifest::default()
+        },
+        DiscoverResponse::Detailed {
+            version,
+            api_version,
+            capabilities,
+            problem_matchers,
+            tools,
+            resources,
+            capability_info,
+        } => Manifest {
+            version,
+            api_version,
+            capabilities,
+            problem_matchers,
+            tools,
+            resources,
+            capability_info,
+        },
+    })
+}
+
+/// The binary of the subprocess extension `name`: its configured `path`, or
+/// `devflow-ext-<name>` on `PATH`.
+pub fn binary_for(cfg: Option<&DevflowConfig>, name: &str) -> String {
+    cfg.and_then(|cfg| cfg.extensions.as_ref())
+        .and_then(|extensions| extensions.get(name))
+        .and_then(|ext_cfg| ext_cfg.path.as_ref())
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("{}{}", EXTENSION_PREFIX, name))
+}
+
+/// Options controlling subprocess discovery.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiscoveryOptions {
+    /// Downgrade extension version mismatches to warnings.
+    pub ignore_version_mismatch: bool,
+}
+
+/// Checks a reported extension version against the configured requirement.
+pub fn check_version(ext_name: &str, required: &str, reported: Option<&str>) -> Result<()> {
+    let req = semver::VersionReq::parse(required).map_err(|e| {
+        anyhow!(
+            "invalid version requirement '{}' for extension '{}': {}",
+            required,
+            ext_name,
+            e
+        )
+    })?;
+    let reported = reported.ok_or_else(|| {
+        anyhow!(
+            "extension '{}' does not report a version, but '{}' is required",
+            ext_name,
+            required
+        )
+    })?;
+    let version = semver::Version::parse(reported).map_err(|e| {
+        anyhow!(
+            "extension '{}' reported invalid version '{}': {}",
+            ext_name,
+            reported,
+            e
+        )
+    })?;
+    if !req.matches(&version) {
+        return Err(anyhow!(
+            "extension '{}' version {} does not satisfy requirement '{}'",
+            ext_name,
+            version,
+            required
+        ));
+    }
+    Ok(())
+}
+
+/// A subprocess extension that was probed but not registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveryFailure {
+    pub name: String,
+    /// Why the probe failed, e.g. the binary is missing.
+    pub reason: String,
+}
+
+/// Probes a potential subprocess extension for its capabilities, returning
+/// why it was not registered if the probe failed.
+///
+/// # Errors
+/// Returns an error if the extension's version does not satisfy the configured
+/// requirement and mismatches are not ignored; probe failures are only logged.
 fn discover_and_register(
     ext_name: String,
     binary_name: String,
     registry: &mut ExtensionRegistry,
     is_trusted: bool,
-) {
+    required_version: Option<&str>,
+    opts: DiscoveryOptions,
+) -> Result<Option<String>> {
     debug!("probing for subprocess extension: {}", ext_name);
 
     let output = match Command::new(&binary_name).arg("--discover").output() {
         Ok(out) => out,
         Err(e) => {
             debug!("failed to find or execute extension '{}': {}", ext_name, e);
-            return;
+            return Ok(Some(format!("failed to run '{}': {}", binary_name, e)));
         }
     };
 
@@ -37,28 +174,48 @@ fn discover_and_register(
             "extension '{}' --discover failed with status {}",
             ext_name, output.status
         );
-        return;
+        return Ok(Some(format!(
+            "'{} --discover' failed with {}",
+            binary_name, output.status
+        )));
     }
 
-    let capabilities: HashSet<String> = match serde_json::from_slice(&output.stdout) {
-        Ok(caps) => caps,
+    let manifest = match parse_manifest(&output.stdout) {
+        Ok(manifest) => manifest,
         Err(e) => {
             warn!(
                 "failed to parse capabilities for extension '{}': {}",
                 ext_name, e
             );
-            return;
+            return Ok(Some(format!(
+                "'{} --discover' printed invalid capabilities: {}",
+                binary_name, e
+            )));
         }
     };
 
+    if let Some(required) = required_version {
+        if let Err(e) = check_version(&ext_name, required, manifest.version.as_deref()) {
+            if !opts.ignore_version_mismatch {
+                return Err(e.context("pass --ignore-version-mismatch to continue anyway"));
+            }
+            warn!("{:#}", e);
+        }
+    }
+
     debug!(
         "discovered subprocess extension '{}' with {} capabilities",
         ext_name,
-        capabilities.len()
+        manifest.capabilities.len()
     );
 
-    let ext = SubprocessExtension::new(ext_name, binary_name, capabilities, is_trusted);
+    let ext = SubprocessExtension::new(ext_name, binary_name, manifest.capabilities, is_trusted)
+        .with_problem_matchers(manifest.problem_matchers)
+        .with_tools(manifest.tools)
+        .with_resources(manifest.resources)
+        .with_capability_info(manifest.capability_info);
     registry.register(Box::new(ext));
+    Ok(None)
 }
 
 /// Scans for available extensions based on the project configuration.
@@ -66,43 +223,136 @@ fn discover_and_register(
 /// This covers:
 /// 1. Implicit stacks (e.g., if "python" is in stack, it probes for `devflow-ext-python`).
 /// 2. Explicitly configured path-based extensions in `devflow.toml`.
+///
+/// Returns the probed extensions that could not be registered.
 pub fn discover_subprocess_extensions(
     cfg: &DevflowConfig,
     registry: &mut ExtensionRegistry,
-) -> Result<()> {
+    opts: DiscoveryOptions,
+) -> Result<Vec<DiscoveryFailure>> {
+    let mut failures = Vec::new();
     // 1. Implicit discovery from stack labels
     for stack in &cfg.project.stack {
         // Skip built-in extensions we already registered explicitly and the custom stack logic
-        if stack == "rust" || stack == "node" || stack == "custom" {
+        if stack == "rust" || stack == "node" || stack == "terraform" || stack == "custom" {
             continue;
         }
         let binary_name = format!("{}{}", EXTENSION_PREFIX, stack);
         // Security default: implicitly discovered extensions are untrusted unless
         // explicitly opted in via `[extensions.<name>] trusted = true`.
-        let is_trusted = cfg
+        let ext_cfg = cfg
             .extensions
             .as_ref()
-            .and_then(|extensions| extensions.get(stack))
-            .map(|ext_cfg| ext_cfg.trusted)
-            .unwrap_or(false);
-        discover_and_register(stack.clone(), binary_name, registry, is_trusted);
+            .and_then(|extensions| extensions.get(stack));
+        let is_trusted = ext_cfg.map(|ext_cfg| ext_cfg.trusted).unwrap_or(false);
+        let required_version = ext_cfg.and_then(|ext_cfg| ext_cfg.version.as_deref());
+        if let Some(reason) = discover_and_register(
+            stack.clone(),
+            binary_name,
+            registry,
+            is_trusted,
+            required_version,
+            opts,
+        )? {
+            failures.push(DiscoveryFailure {
+                name: stack.clone(),
+                reason,
+            });
+        }
     }
 
     // 2. Explicit discovery from extension config
     if let Some(extensions) = &cfg.extensions {
         for (ext_name, ext_cfg) in extensions {
             if let devflow_core::config::ExtensionSource::Path = ext_cfg.source {
-                let binary_name = ext_cfg
-                    .path
-                    .as_ref()
-                    .map(|p| p.to_string_lossy().into_owned())
-                    .unwrap_or_else(|| format!("{}{}", EXTENSION_PREFIX, ext_name));
-
-                discover_and_register(ext_name.clone(), binary_name, registry, ext_cfg.trusted);
+                if let Some(reason) = discover_and_register(
+                    ext_name.clone(),
+                    binary_for(Some(cfg), ext_name),
+                    registry,
+                    ext_cfg.trusted,
+                    ext_cfg.version.as_deref(),
+                    opts,
+                )? {
+                    failures.push(DiscoveryFailure {
+                        name: ext_name.clone(),
+                        reason,
+                    });
+                }
             }
         }
     }
 
+    Ok(failures)
+}
+
+/// Fails on `[extensions.<name>] required = true` extensions that are not
+/// registered, with the reason from `failures` when discovery probed them.
+pub fn check_required_extensions(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    failures: &[DiscoveryFailure],
+) -> Result<()> {
+    let Some(extensions) = &cfg.extensions else {
+        return Ok(());
+    };
+    let mut required: Vec<&String> = extensions
+        .iter()
+        .filter(|(name, ext_cfg)| ext_cfg.required && registry.get(name).is_none())
+        .map(|(name, _)| name)
+        .collect();
+    required.sort();
+    let Some(name) = required.first() else {
+        return Ok(());
+    };
+    let reason = failures
+        .iter()
+        .find(|failure| &&failure.name == name)
+        .map_or_else(
+            || "no extension by that name is built in or configured".to_string(),
+            |failure| failure.reason.clone(),
+        );
+    Err(ConfigDiagnostic::new(format!(
+        "required extension '{}' is not available: {}",
+        name, reason
+    ))
+    .at_key(format!("extensions.{}.required", name))
+    .with_hint(format!(
+        "install {}{} on PATH or set [extensions.{}] path; set required = false to run without it",
+        EXTENSION_PREFIX, name, name
+    ))
+    .into())
+}
+
+/// Fails on `[project] stack` entries that no registered extension handles.
+///
+/// Auto-detected stacks only warn: a manifest was found, but the stack was
+/// never asked for.
+pub fn check_stack_support(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<()> {
+    let mut supported = registry.names();
+    supported.push("custom");
+    supported.sort_unstable();
+    for (index, stack) in cfg.project.stack.iter().enumerate() {
+        if supported.contains(&stack.as_str()) {
+            continue;
+        }
+        if cfg.project.detected_stacks.contains(stack) {
+            warn!(
+                "detected a {} project, but no extension handles it (install {}{})",
+                stack, EXTENSION_PREFIX, stack
+            );
+            continue;
+        }
+        return Err(ConfigDiagnostic::new(format!("unsupported stack '{}'", stack))
+            .at_key(format!("project.stack[{}]", index))
+            .with_hint(format!(
+                "supported: {}; did you mean to add a subprocess extension ({}{} on PATH, or [extensions.{}] with source = \"path\")?",
+                supported.join(", "),
+                EXTENSION_PREFIX,
+                stack,
+                stack
+            ))
+            .into());
+    }
     Ok(())
 }
 
@@ -151,22 +401,40 @@ exit 1
         create_mock_binary(dir.path(), "python", r#"["test", "fmt"]"#);
 
         let cfg = DevflowConfig {
+            schema_version: None,
             project: ProjectConfig {
                 name: "test-proj".to_string(),
                 stack: vec!["python".to_string()],
+                detected_stacks: Vec::new(),
             },
             runtime: RuntimeConfig {
                 profile: devflow_core::runtime::RuntimeProfile::default(),
+                ..RuntimeConfig::default()
             },
             cache: Default::default(),
+            extends: None,
+            preset: None,
+            concurrency: None,
             container: Default::default(),
             extensions: Default::default(),
             targets: Default::default(),
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
             source_dir: None,
         };
 
         let mut registry = ExtensionRegistry::default();
-        let result = discover_subprocess_extensions(&cfg, &mut registry);
+        let result =
+            discover_subprocess_extensions(&cfg, &mut registry, DiscoveryOptions::default());
 
         // Reset PATH immediately
         std::env::set_var("PATH", old_path);
@@ -188,22 +456,40 @@ exit 1
     #[test]
     fn discover_subprocess_extensions_ignores_builtin() {
         let cfg = DevflowConfig {
+            schema_version: None,
             project: ProjectConfig {
                 name: "test-proj".to_string(),
                 stack: vec!["rust".to_string(), "node".to_string(), "custom".to_string()],
+                detected_stacks: Vec::new(),
             },
             runtime: RuntimeConfig {
                 profile: devflow_core::runtime::RuntimeProfile::default(),
+                ..RuntimeConfig::default()
             },
             cache: Default::default(),
+            extends: None,
+            preset: None,
+            concurrency: None,
             container: Default::default(),
             extensions: Default::default(),
             targets: Default::default(),
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
             source_dir: None,
         };
 
         let mut registry = ExtensionRegistry::default();
-        let result = discover_subprocess_extensions(&cfg, &mut registry);
+        let result =
+            discover_subprocess_extensions(&cfg, &mut registry, DiscoveryOptions::default());
         assert!(result.is_ok());
 
         // Ensure no extensions were actually added for builtins
@@ -211,8 +497,171 @@ exit 1
         // Since the registry is empty, `ensure_can_run` might return Ok(()) to allow bootstrap,
         // but `build_action` for a specific named builtin extension like "rust" or an unregistered one will be None
         assert!(registry
-            .build_action("rust", &cmd)
+            .build_action("rust", &cmd, &devflow_core::ActionContext::host())
             .expect("registry lookup should not error")
             .is_none());
     }
+
+    #[test]
+    fn manifest_reports_capability_info() {
+        let manifest = parse_manifest(
+            br#"{"capabilities": ["test:unit", "lint"],
+                 "capability_info": {"test:unit": {"description": "Run pytest", "group": "Test"},
+                                     "lint": {"description": "Run ruff"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.capability_info["test:unit"],
+            CapabilityInfo::new("Test", "Run pytest")
+        );
+        assert_eq!(manifest.capability_info["lint"].group, None);
+        assert!(parse_manifest(br#"["test"]"#)
+            .unwrap()
+            .capability_info
+            .is_empty());
+    }
+
+    #[test]
+    fn check_version_enforces_requirement() {
+        check_version("python", ">=0.3", Some("0.3.1")).expect("0.3.1 satisfies >=0.3");
+
+        let err = check_version("python", ">=0.3", Some("0.2.0")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("extension 'python' version 0.2.0 does not satisfy requirement '>=0.3'"));
+
+        let err = check_version("python", ">=0.3", None).unwrap_err();
+        assert!(err.to_string().contains("does not report a version"));
+    }
+
+    #[test]
+    fn version_mismatch_fails_unless_ignored() {
+        let dir = tempdir().unwrap();
+        create_mock_binary(
+            dir.path(),
+            "python",
+            r#"{"version": "0.2.0", "capabilities": ["test"]}"#,
+        );
+        let binary = dir
+            .path()
+            .join("devflow-ext-python")
+            .to_string_lossy()
+            .into_owned();
+
+        let mut registry = ExtensionRegistry::default();
+        let err = discover_and_register(
+            "python".to_string(),
+            binary.clone(),
+            &mut registry,
+            false,
+            Some(">=0.3"),
+            DiscoveryOptions::default(),
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("--ignore-version-mismatch"));
+        assert!(registry.get("python").is_none());
+
+        discover_and_register(
+            "python".to_string(),
+            binary,
+            &mut registry,
+            false,
+            Some(">=0.3"),
+            DiscoveryOptions {
+                ignore_version_mismatch: true,
+            },
+        )
+        .expect("mismatch should only warn when ignored");
+        assert!(registry.get("python").is_some());
+    }
+
+    #[test]
+    fn rejects_stacks_without_an_extension() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
+        let mut cfg = DevflowConfig {
+            schema_version: None,
+            project: ProjectConfig {
+                name: "test-proj".to_string(),
+                stack: vec!["rust".to_string(), "custom".to_string(), "go".to_string()],
+                detected_stacks: vec!["go".to_string()],
+            },
+            runtime: RuntimeConfig::default(),
+            cache: Default::default(),
+            extends: None,
+            preset: None,
+            concurrency: None,
+            container: Default::default(),
+            extensions: Default::default(),
+            targets: Default::default(),
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
+            source_dir: None,
+        };
+        check_stack_support(&cfg, &registry).expect("detected stacks only warn");
+
+        cfg.project.stack.push("ruby".to_string());
+        let err = check_stack_support(&cfg, &registry).unwrap_err();
+        let diagnostic = err.downcast::<ConfigDiagnostic>().unwrap();
+        assert_eq!(diagnostic.message, "unsupported stack 'ruby'");
+        assert_eq!(diagnostic.key.as_deref(), Some("project.stack[3]"));
+        assert!(diagnostic
+            .hint
+            .unwrap()
+            .starts_with("supported: custom, rust; did you mean to add a subprocess extension"));
+    }
+
+    #[test]
+    fn required_extensions_must_register() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("devflow.toml");
+        fs::write(
+            &path,
+            r#"
+            [project]
+            name = "test-proj"
+            stack = ["rust"]
+
+            [extensions.lint]
+            source = "path"
+            path = "/nonexistent/devflow-ext-lint"
+            required = true
+
+            [extensions.docs]
+            source = "path"
+            path = "/nonexistent/devflow-ext-docs"
+            "#,
+        )
+        .unwrap();
+        let mut cfg = DevflowConfig::load_from_file(path.to_str().unwrap()).unwrap();
+
+        let mut registry = ExtensionRegistry::default();
+        let failures =
+            discover_subprocess_extensions(&cfg, &mut registry, DiscoveryOptions::default())
+                .unwrap();
+        assert_eq!(failures.len(), 2);
+        let err = check_required_extensions(&cfg, &registry, &failures).unwrap_err();
+        let diagnostic = err.downcast::<ConfigDiagnostic>().unwrap();
+        assert!(
+            diagnostic.message.starts_with(
+                "required extension 'lint' is not available: failed to run '/nonexistent/devflow-ext-lint'"
+            ),
+            "{}",
+            diagnostic.message
+        );
+        assert_eq!(diagnostic.key.as_deref(), Some("extensions.lint.required"));
+
+        let extensions = cfg.extensions.as_mut().unwrap();
+        extensions.get_mut("lint").unwrap().required = false;
+        check_required_extensions(&cfg, &registry, &failures).expect("docs is optional");
+    }
 }
@@ -0,0 +1,262 @@
+//! Dotenv files (`[env_files]`) for the execution environment.
+//!
+//! Each listed file that exists is parsed as `KEY=VALUE` lines; later files
+//! override earlier ones, and the `ci` files only load when `CI` is set. The
+//! variables sit between the inherited environment and the configured one:
+//! extension and `--env` variables win over them. Values of keys matching a
+//! `mask` glob are replaced by `***` in relayed output and `dwf env`, and
+//! registered with `::add-mask::` in GitHub Actions.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use tracing::debug;
+
+use devflow_core::{DevflowConfig, ExecutionAction};
+
+/// What replaces a masked value.
+pub const MASK: &str = "***";
+
+/// The variables loaded from a project's dotenv files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DotEnv {
+    pub vars: BTreeMap<String, DotEnvVar>,
+}
+
+/// One loaded variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotEnvVar {
+    pub value: String,
+    /// The file it was read from, as configured.
+    pub file: String,
+    /// Whether its key matches a `mask` glob.
+    pub secret: bool,
+}
+
+impl DotEnv {
+    /// Loads the `[env_files]` of `cfg`, relative to its config file; without
+    /// the section nothing is loaded.
+    ///
+    /// # Errors
+    /// Returns an error if a file exists but cannot be read or parsed.
+    pub fn load(cfg: &DevflowConfig) -> Result<Self> {
+        let Some(env_files) = &cfg.env_files else {
+            return Ok(Self::default());
+        };
+        let base = cfg.source_dir.clone().unwrap_or_default();
+        let ci = std::env::var_os("CI").is_some_and(|v| !v.is_empty());
+        let files = env_files
+            .files
+            .iter()
+            .chain(env_files.ci.iter().filter(|_| ci));
+        let mut dotenv = Self::default();
+        for file in files {
+            let path = base.join(file);
+            if !path.is_file() {
+                debug!("no env file {}", path.display());
+                continue;
+            }
+            for (key, value) in read(&path)? {
+                let secret = env_files.masks(&key);
+                dotenv.vars.insert(
+                    key,
+                    DotEnvVar {
+                        value,
+                        file: file.clone(),
+                        secret,
+                    },
+                );
+            }
+        }
+        if devflow_gh::log::enabled() {
+            for secret in dotenv.secrets() {
+                println!("{}", devflow_gh::log::add_mask(&secret));
+            }
+        }
+        Ok(dotenv)
+    }
+
+    /// Adds the variables `action` does not already set.
+    pub fn apply(&self, mut action: ExecutionAction) -> ExecutionAction {
+        for (key, var) in &self.vars {
+            action
+                .env
+                .entry(key.clone())
+                .or_insert_with(|| var.value.clone());
+        }
+        action
+    }
+
+    /// The non-empty values to mask in output.
+    pub fn secrets(&self) -> Vec<String> {
+        self.vars
+            .values()
+            .filter(|var| var.secret && !var.value.is_empty())
+            .map(|var| var.value.clone())
+            .collect()
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<(String, String)>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read env file '{}'", path.display()))?;
+    parse(&text).with_context(|| format!("invalid env file '{}'", path.display()))
+}
+
+/// Parses dotenv text: `KEY=VALUE` lines with an optional `export ` prefix,
+/// `#` comments and blank lines. Double-quoted values may span lines and
+/// know `\n`, `\t`, `\"` and `\\`; single-quoted values are literal; unquoted
+/// values end at ` #`. Variables are not expanded.
+///
+/// # Errors
+/// Returns an error naming the line of a malformed entry.
+pub fn parse(text: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let number = index + 1;
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {}: expected KEY=VALUE", number);
+        };
+        let key = key.trim();
+        if key.is_empty()
+            || key.starts_with(|c: char| c.is_ascii_digit())
+            || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            bail!("line {}: invalid variable name '{}'", number, key);
+        }
+        let value = value.trim_start();
+        let value = if let Some(rest) = value.strip_prefix('"') {
+            let mut quoted = rest.to_string();
+            loop {
+                if let Some(value) = double_quoted(&quoted) {
+                    break value;
+                }
+                let Some((_, next)) = lines.next() else {
+                    bail!("line {}: unterminated double quote", number);
+                };
+                quoted.push('\n');
+                quoted.push_str(next);
+            }
+        } else if let Some(rest) = value.strip_prefix('\'') {
+            match rest.split_once('\'') {
+                Some((value, _)) => value.to_string(),
+                None => bail!("line {}: unterminated single quote", number),
+            }
+        } else {
+            let value = match value.find(" #") {
+                Some(comment) => &value[..comment],
+                None => value,
+            };
+            value.trim_end().to_string()
+        };
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+/// Unescapes `text` up to its closing double quote, if it has one.
+fn double_quoted(text: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                other @ ('"' | '\\' | '$') => value.push(other),
+                other => {
+                    value.push('\\');
+                    value.push(other);
+                }
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::config::EnvFilesConfig;
+
+    #[test]
+    fn parses_dotenv_syntax() {
+        let vars = parse(
+            "# local settings\n\
+             \n\
+             export DATABASE_URL=postgres://localhost/dev # dev db\n\
+             GREETING=\"hello\\n\\\"world\\\"\"\n\
+             LITERAL='$HOME \\n'\n\
+             MULTI=\"one\n\
+             two\"\n\
+             EMPTY=\n\
+             HASH=a#b\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vars,
+            [
+                ("DATABASE_URL", "postgres://localhost/dev"),
+                ("GREETING", "hello\n\"world\""),
+                ("LITERAL", "$HOME \\n"),
+                ("MULTI", "one\ntwo"),
+                ("EMPTY", ""),
+                ("HASH", "a#b"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+
+        let err = parse("OK=1\nnot a variable\n").unwrap_err().to_string();
+        assert_eq!(err, "line 2: expected KEY=VALUE");
+        assert!(parse("1KEY=x").is_err());
+        assert!(parse("KEY=\"open").is_err());
+    }
+
+    #[test]
+    fn loads_files_in_order_and_marks_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".env"), "API_TOKEN=abc123\nMODE=dev\n").unwrap();
+        fs::write(dir.path().join(".env.local"), "MODE=local\n").unwrap();
+        let mut cfg = DevflowConfig {
+            env_files: Some(EnvFilesConfig {
+                files: vec![
+                    ".env".to_string(),
+                    ".env.local".to_string(),
+                    ".env.missing".to_string(),
+                ],
+                ci: Vec::new(),
+                ..EnvFilesConfig::default()
+            }),
+            source_dir: Some(dir.path().to_path_buf()),
+            ..DevflowConfig::default()
+        };
+        let dotenv = DotEnv::load(&cfg).unwrap();
+        assert_eq!(dotenv.vars["MODE"].value, "local");
+        assert_eq!(dotenv.vars["MODE"].file, ".env.local");
+        assert!(dotenv.vars["API_TOKEN"].secret);
+        assert_eq!(dotenv.secrets(), ["abc123"]);
+
+        let mut action = ExecutionAction {
+            program: "true".to_string(),
+            args: Vec::new(),
+            env: [("MODE".to_string(), "config".to_string())].into(),
+        };
+        action = dotenv.apply(action);
+        assert_eq!(action.env["MODE"], "config");
+        assert_eq!(action.env["API_TOKEN"], "abc123");
+
+        cfg.env_files = None;
+        assert_eq!(DotEnv::load(&cfg).unwrap(), DotEnv::default());
+    }
+}
@@ -0,0 +1,122 @@
+//! Effective extension environment reporting (`dwf env`).
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use devflow_core::extension::{is_secret_env, MergedEnvVar};
+use devflow_core::{DevflowConfig, ExtensionRegistry};
+
+use crate::dotenv::{self, DotEnv};
+use crate::executor;
+
+/// Prints each dispatched stack followed by its effective environment,
+/// masking credential variables.
+pub fn run(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<()> {
+    let dotenv = DotEnv::load(cfg)?;
+    for (stack, mut vars) in executor::stack_environments(cfg, registry)? {
+        let declared = registry
+            .get(&stack)
+            .map(|ext| ext.secret_env())
+            .unwrap_or_default();
+        for (key, var) in vars.iter_mut() {
+            if is_secret_env(key) || declared.contains(key) {
+                var.value = dotenv::MASK.to_string();
+            }
+        }
+        add_dotenv(&mut vars, &dotenv);
+        print!("{}", render(&stack, &vars));
+    }
+    Ok(())
+}
+
+/// Adds the dotenv variables the extensions do not set, noting their file
+/// and masking secrets.
+fn add_dotenv(vars: &mut BTreeMap<String, MergedEnvVar>, dotenv: &DotEnv) {
+    for (key, var) in &dotenv.vars {
+        vars.entry(key.clone()).or_insert_with(|| MergedEnvVar {
+            value: if var.secret {
+                dotenv::MASK.to_string()
+            } else {
+                var.value.clone()
+            },
+            source: var.file.clone(),
+        });
+    }
+}
+
+/// Renders one stack's variables, noting those inherited from another extension.
+fn render(stack: &str, vars: &BTreeMap<String, MergedEnvVar>) -> String {
+    let mut out = format!("{stack}:\n");
+    if vars.is_empty() {
+        out.push_str("  (no variables)\n");
+    }
+    for (key, var) in vars {
+        out.push_str(&format!("  {key}={}", var.value));
+        if var.source != stack {
+            out.push_str(&format!("  (from {})", var.source));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_marks_inherited_variables() {
+        let vars = BTreeMap::from([
+            (
+                "CARGO_HOME".to_string(),
+                MergedEnvVar {
+                    value: "/workspace/.cargo-cache".to_string(),
+                    source: "rust".to_string(),
+                },
+            ),
+            (
+                "NPM_CONFIG_CACHE".to_string(),
+                MergedEnvVar {
+                    value: "/root/.npm".to_string(),
+                    source: "node".to_string(),
+                },
+            ),
+        ]);
+        assert_eq!(
+            render("rust", &vars),
+            "rust:\n  CARGO_HOME=/workspace/.cargo-cache\n  NPM_CONFIG_CACHE=/root/.npm  (from node)\n"
+        );
+        assert_eq!(
+            render("custom", &BTreeMap::new()),
+            "custom:\n  (no variables)\n"
+        );
+    }
+
+    #[test]
+    fn dotenv_variables_rank_below_extensions_and_mask_secrets() {
+        let mut vars = BTreeMap::from([(
+            "MODE".to_string(),
+            MergedEnvVar {
+                value: "ci".to_string(),
+                source: "rust".to_string(),
+            },
+        )]);
+        let var = |value: &str, secret| dotenv::DotEnvVar {
+            value: value.to_string(),
+            file: ".env".to_string(),
+            secret,
+        };
+        let dotenv = DotEnv {
+            vars: BTreeMap::from([
+                ("API_TOKEN".to_string(), var("abc123", true)),
+                ("MODE".to_string(), var("dev", false)),
+            ]),
+        };
+        add_dotenv(&mut vars, &dotenv);
+        assert_eq!(
+            render("rust", &vars),
+            "rust:\n  API_TOKEN=***  (from .env)\n  MODE=ci\n"
+        );
+    }
+}
@@ -4,116 +4,1100 @@
 //! extensions. It also provides the "container proxy" implementation that
 //! wraps host commands in Docker/Podman `run` calls with transparent volume mounting.
 
+use std::borrow::Cow;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
 
+use crate::capture::{self, Capped, LogFile, Spool, Tail};
+use crate::dotenv::DotEnv;
+use crate::fixtures::Fixtures;
+use crate::history::{ActionRecord, TestCounts};
+use crate::logs::RunLog;
+use crate::nested::{self, HostPaths};
+use crate::{container, hermetic, host_path, remote, tools, volumes, waits};
+use devflow_core::reporter::{OutputStream, Reporter, Reporters, Skip, SkipReason};
 use devflow_core::{
-    config::ContainerEngine, runtime::RuntimeProfile, CommandRef, DevflowConfig, ExecutionAction,
-    ExtensionRegistry, PrimaryCommand,
+    config::{
+        CommandConfig, ConflictPolicy, ContainerConfig, ContainerEngine, EnvConflictPolicy,
+        NestedStrategy,
+    },
+    constants::DEFAULT_CACHE_ROOT,
+    extension::{is_secret_env, MergedEnv, MergedEnvVar},
+    problem::{Matcher, Problem, Scanner},
+    runtime::RuntimeEnv,
+    runtime::RuntimeProfile,
+    ActionContext, Affected, CommandRef, DevflowConfig, ExecutionAction, ExtensionRegistry,
+    PrimaryCommand, TargetOs,
 };
 use tracing::{info, instrument, warn};
 
 /// Default image used for containerized execution if none specified.
 const DEFAULT_CI_IMAGE: &str = "ghcr.io/softmentor/devflow-ci:latest";
-/// Default host directory for the Devflow cache.
-const DEFAULT_CACHE_ROOT: &str = ".cache/devflow";
 /// The internal container path where the project is mounted.
-const CONTAINER_WORKSPACE: &str = "/workspace";
+pub(crate) const CONTAINER_WORKSPACE: &str = "/workspace";
 /// The internal container path where the host `dwf` binary is mapped.
 const CONTAINER_DWF_BIN: &str = "/usr/local/bin/dwf";
+/// How long a timed-out command may take to exit after SIGTERM.
+const STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// Per-invocation execution options derived from CLI flags.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Run containerized commands without network access and with a read-only
+    /// workspace, then verify that only declared outputs changed.
+    pub hermetic: bool,
+    /// Environment overrides applied on top of the extension-provided environment.
+    pub env: std::collections::HashMap<String, String>,
+    /// Leave `[fixtures]` databases running after `test:integration`.
+    pub keep_fixtures: bool,
+    /// Network containerized commands join (the fixture network).
+    pub network: Option<String>,
+    /// Stops each stack's command after this long (not on remote hosts).
+    pub timeout: Option<Duration>,
+    /// How often the caller retries the command after a failure.
+    pub retries: u32,
+    /// Only dispatch to these stacks (empty means all).
+    pub stacks: Vec<String>,
+    /// The check run's shared container, if `[container] reuse` is set.
+    pub session: Option<Arc<ContainerSession>>,
+    /// Mount the workspace read-only in containers, except `[container]
+    /// outputs` and these paths (`[commands] read_only`).
+    pub read_only: Option<Writable>,
+    /// Fail instead of skipping a stack whose manifest is missing or that
+    /// does not support the command.
+    pub strict_skips: bool,
+    /// The image of containerized runs instead of `[container] image`
+    /// (`[commands] image`).
+    pub image: Option<String>,
+    /// Narrow each stack's command to what these changed files affect
+    /// (`--affected`).
+    pub affected: Option<Vec<String>>,
+    /// Keep dwf's `PATH` for host runs under `[runtime] path = "hermetic"`
+    /// (`--inherit-path`).
+    pub inherit_path: bool,
+    /// The run's command logs (`[logs]`).
+    pub run_log: Option<Arc<RunLog>>,
+}
+
+/// Workspace directories a read-only containerized run may write.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Writable {
+    /// Bind-mounted from the host, so results such as coverage reports stay.
+    pub host: Vec<String>,
+    /// On a tmpfs discarded after the run.
+    pub tmpfs: Vec<String>,
+}
+
+/// What a command run observed, for the run history.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandStats {
+    /// Whether the container image was already present locally (containerized
+    /// runs only).
+    pub cache_hit: Option<bool>,
+    /// Counts parsed from the summary lines of `test:*` output.
+    pub tests: Option<TestCounts>,
+    /// Diagnostics found by the stacks' problem matchers, without duplicates.
+    pub problems: Vec<Problem>,
+    /// The last lines of output of a failed run, for `dwf explain-failure`.
+    pub output: Vec<String>,
+    /// The full log of a failed run (`[commands] spool_logs`).
+    pub log: Option<PathBuf>,
+    /// The actions the command resolved to, one per stack, for `dwf explain`.
+    pub actions: Vec<ActionRecord>,
+}
+
+/// Output lines a failed command keeps in [`CommandStats::output`].
+const FAILURE_TAIL_LINES: usize = 200;
 
 /// Runs a Devflow command by dispatching it to applicable stacks.
-#[instrument(skip(cfg, registry), fields(command = %command))]
-pub fn run(cfg: &DevflowConfig, registry: &ExtensionRegistry, command: &CommandRef) -> Result<()> {
+pub fn run(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    command: &CommandRef,
+    opts: &RunOptions,
+) -> Result<()> {
+    run_with_stats(
+        cfg,
+        registry,
+        command,
+        opts,
+        &mut CommandStats::default(),
+        &Reporters::default(),
+    )
+}
+
+/// Like [`run`], filling `stats` as the command runs (also when it fails)
+/// and passing output lines to `reporter` when it wants them.
+#[instrument(name = "run", skip(cfg, registry, opts, stats, reporter), fields(command = %command))]
+pub fn run_with_stats(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    command: &CommandRef,
+    opts: &RunOptions,
+    stats: &mut CommandStats,
+    reporter: &dyn Reporter,
+) -> Result<()> {
     let mut attempted = false;
 
-    let mut requested_stacks = Vec::new();
-    for stack in &cfg.project.stack {
-        if stack_is_applicable(cfg, stack) {
-            requested_stacks.push(stack.clone());
-        } else {
-            info!(target: "devflow", "skip {}: manifest not found", stack);
+    if opts.hermetic && !uses_container_proxy(cfg) {
+        bail!("--hermetic requires a containerized runtime (container profile or devcontainer env) outside of a container");
+    }
+
+    let effective = with_default_selector(command);
+    let skip = |stack: &str, reason| Skip {
+        command: effective.canonical(),
+        stack: Some(stack.to_string()),
+        reason,
+    };
+    let mut skips: Vec<Skip> = cfg
+        .project
+        .stack
+        .iter()
+        .filter(|stack| opts.stacks.is_empty() || opts.stacks.contains(stack))
+        .filter(|stack| !stack_is_applicable(cfg, stack))
+        .map(|stack| skip(stack, SkipReason::ManifestNotFound))
+        .collect();
+    let stacks = only_stacks(ordered_stacks(cfg), &opts.stacks)?;
+    let candidates = candidate_actions(cfg, registry, &effective, &stacks, true)?;
+    for stack in &stacks {
+        if !candidates.iter().any(|c| &c.stack == stack) {
+            skips.push(skip(stack, SkipReason::UnsupportedCommand));
         }
     }
+    let mut resolution = resolve_conflicts(cfg, &effective, candidates)?;
+    for shadowed in &resolution.shadowed {
+        skips.push(skip(&shadowed.stack, SkipReason::Shadowed));
+    }
+    if let Some(changed) = &opts.affected {
+        let mut unaffected = Vec::new();
+        resolution.selected = narrow_to_affected(registry, resolution.selected, changed, |stack| {
+            unaffected.push(skip(stack, SkipReason::Unaffected));
+        });
+        skips.extend(unaffected);
+    }
+    for skip in &skips {
+        info!(target: "devflow", "skip {}", skip);
+        reporter.on_skip(skip);
+    }
+    let unexpected: Vec<String> = skips
+        .iter()
+        .filter(|skip| !skip.reason.is_expected())
+        .map(ToString::to_string)
+        .collect();
+    if opts.strict_skips && !unexpected.is_empty() {
+        bail!(
+            "unexpected skips (--strict-skips):\n  {}",
+            unexpected.join("\n  ")
+        );
+    }
 
-    if let Some(extensions) = &cfg.extensions {
-        for ext_name in extensions.keys() {
-            if !requested_stacks.contains(ext_name) {
-                // Explicitly declared subprocess extensions assume implicit applicability
-                requested_stacks.push(ext_name.clone());
+    // Services must be up before integration tests start; remote hosts reach
+    // their own services. Fixtures live until the command returns.
+    let integration = effective.primary == PrimaryCommand::Test
+        && effective.selector.as_deref() == Some("integration");
+    let prepare = integration && !resolution.selected.is_empty() && !uses_remote_runtime(cfg);
+    let mut fixtures = None;
+    let fixture_opts;
+    let opts = if prepare && !cfg.fixtures.is_empty() {
+        let containerized = uses_container_proxy(cfg);
+        let started = fixtures.insert(Fixtures::start(
+            cfg,
+            &container_engine(cfg)?,
+            containerized,
+            opts.keep_fixtures,
+        )?);
+        fixture_opts = RunOptions {
+            env: started
+                .env(containerized)
+                .into_iter()
+                .chain(opts.env.clone())
+                .collect(),
+            network: started.network().map(str::to_string),
+            ..opts.clone()
+        };
+        &fixture_opts
+    } else {
+        opts
+    };
+    if prepare {
+        waits::wait_all(cfg)?;
+    }
+
+    let (merged_env, dotenv) = if resolution.selected.is_empty() {
+        (MergedEnv::default(), DotEnv::default())
+    } else {
+        (merged_env(cfg, registry)?, DotEnv::load(cfg)?)
+    };
+    let secrets = dotenv.secrets();
+    let tool_dirs = tools::installed_dirs(cfg, registry);
+
+    let settings = cfg.command_config(&effective);
+    let isolation = settings.filter(|command| command.clear_env);
+    let max_output = match settings {
+        Some(command) => command.max_output_bytes()?,
+        None => None,
+    };
+    let read_only_opts;
+    let opts = match settings.filter(|command| command.read_only && !opts.hermetic) {
+        Some(command) => {
+            if !uses_container_proxy(cfg) {
+                info!(target: "devflow",
+                    "{} is read_only, but the workspace is only read-only in containerized runs",
+                    effective
+                );
+            }
+            read_only_opts = RunOptions {
+                read_only: Some(Writable {
+                    host: command.writable.clone(),
+                    tmpfs: command.tmpfs.clone(),
+                }),
+                ..opts.clone()
+            };
+            &read_only_opts
+        }
+        None => opts,
+    };
+    let image_opts;
+    let opts = match settings.and_then(|command| command.image.as_ref()) {
+        Some(image) => {
+            if !uses_container_proxy(cfg) {
+                info!(target: "devflow",
+                    "{} has an image, but it is only used in containerized runs",
+                    effective
+                );
+            }
+            image_opts = RunOptions {
+                image: Some(image.clone()),
+                ..opts.clone()
+            };
+            &image_opts
+        }
+        None => opts,
+    };
+
+    for PlannedAction { stack, action, .. } in resolution.selected {
+        attempted = true;
+        let mut action = dotenv.apply(with_merged_env(action, &merged_env));
+        if let Some(isolation) = isolation {
+            action.env.retain(|key, _| isolation.allows(key));
+        }
+        stats.actions.push(action_record(
+            cfg, registry, &stack, &action, opts, &secrets,
+        ));
+        // Values that must not appear in output or error messages, which
+        // embed the command line and with it `-e KEY=VALUE` arguments.
+        let masked = action_secrets(
+            registry,
+            &stack,
+            &with_env_overrides(action.clone(), opts),
+            &secrets,
+        );
+
+        if uses_remote_runtime(cfg) {
+            let remote_action = with_env_overrides(sanitize_host_env(action), opts);
+            info!(target: "devflow", "run {} on {} (remote)", effective, stack);
+            run_remote(cfg, &remote_action, &masked)
+                .with_context(|| failure_context(registry, &stack, &effective))?;
+            continue;
+        }
+
+        // When Devflow already runs in a container (e.g., inside a GHA native
+        // `container:` job) and `[container] nested` is "skip", the docker-run
+        // proxy is skipped even if profile is "container".
+        let final_action = if uses_container_proxy(cfg) {
+            let action = with_env_overrides(action, opts);
+            match opts
+                .session
+                .as_deref()
+                .filter(|_| ContainerSession::serves(opts))
+            {
+                Some(session) => {
+                    stats.cache_hit = Some(session.cache_hit);
+                    session.exec(&action)
+                }
+                None => build_container_proxy(
+                    cfg,
+                    registry,
+                    std::slice::from_ref(&stack),
+                    registry.cache_mounts_for(&effective),
+                    &action,
+                    opts,
+                    stats,
+                )?,
+            }
+        } else {
+            let workspace = std::env::current_dir()?;
+            let mut host_action =
+                with_env_overrides(host_env(cfg, registry, action, &workspace), opts);
+            if let Some(isolation) = isolation {
+                inherit_allowed(&mut host_action, isolation);
+            }
+            host_path::apply(&mut host_action, cfg, registry, &tool_dirs, opts)?;
+            if runtime_env(cfg) == RuntimeEnv::Nix {
+                wrap_nix_develop(host_action)
+            } else {
+                host_action
+            }
+        };
+
+        info!(target: "devflow", "run {} on {}", effective, stack);
+        let tests = (effective.primary == PrimaryCommand::Test).then_some(&mut stats.tests);
+        let canonical = effective.canonical();
+        let spool = settings.filter(|command| command.spool_logs).and_then(|_| {
+            Spool::create(
+                &cache_root(cfg).join("logs"),
+                &format!("{canonical} {stack}"),
+            )
+            .map_err(|e| warn!("not spooling the {} log: {:#}", canonical, e))
+            .ok()
+        });
+        let log = opts.run_log.as_ref().and_then(|run| {
+            run.file(&canonical, &stack)
+                .map_err(|e| warn!("not logging {} for dwf logs: {:#}", canonical, e))
+                .ok()
+        });
+        let matchers = problem_matchers(registry, &stack, &effective);
+        let tail = Tail::new(FAILURE_TAIL_LINES);
+        let capture = Capture {
+            reporter: reporter
+                .wants_output()
+                .then_some((reporter, canonical.as_str())),
+            max_output,
+            spool: spool.as_ref(),
+            log: log.as_ref(),
+            tail: Some(&tail),
+            strip_ansi: !capture::keep_ansi(),
+            matchers: &matchers,
+            secrets: &masked,
+        };
+        let mut problems = Vec::new();
+        // The engine itself keeps the host environment; a containerized
+        // command only sees what is passed with `-e`.
+        let clear_env = isolation.is_some() && !uses_container_proxy(cfg);
+        if opts.hermetic {
+            let workspace = std::env::current_dir()?;
+            let allowed = hermetic_allowed_paths(cfg, registry, &effective, &workspace);
+            let before = hermetic::Snapshot::capture(&workspace, &allowed)?;
+            let result = run_relayed(
+                &final_action,
+                tests,
+                &mut problems,
+                capture,
+                clear_env,
+                opts.timeout,
+            );
+            let log = finish_spool(spool);
+            add_problems(&mut stats.problems, problems);
+            if result.is_err() {
+                stats.output = tail.into_lines();
+                stats.log = log;
+            }
+            result.with_context(|| failure_context(registry, &stack, &effective))?;
+            let after = hermetic::Snapshot::capture(&workspace, &allowed)?;
+            hermetic::verify_unchanged(&before, &after)
+                .with_context(|| format!("{} is not hermetic", effective.canonical()))?;
+        } else {
+            let result = run_relayed(
+                &final_action,
+                tests,
+                &mut problems,
+                capture,
+                clear_env,
+                opts.timeout,
+            );
+            let log = finish_spool(spool);
+            add_problems(&mut stats.problems, problems);
+            if result.is_err() {
+                stats.output = tail.into_lines();
+                stats.log = log;
             }
+            result.with_context(|| failure_context(registry, &stack, &effective))?;
         }
     }
 
-    for stack in &requested_stacks {
-        let effective = with_default_selector(command);
+    if !attempted {
+        bail!(
+            "command '{}' did not match any runnable stack",
+            command.canonical()
+        );
+    }
+
+    Ok(())
+}
+
+/// Runs `argv` in the execution environment of `stack`: its extension
+/// environment and, depending on the runtime profile, its container (with
+/// cache mounts), nix shell or remote host. Without `stack`, the project
+/// must have a single stack. Standard streams are inherited; in a container
+/// they stay interactive when stdin is a terminal.
+///
+/// # Errors
+/// Returns an error if `stack` is not one of the project's stacks, the
+/// environment cannot be prepared, or the command fails.
+pub fn run_raw(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    stack: Option<&str>,
+    argv: &[String],
+    opts: &RunOptions,
+) -> Result<()> {
+    let Some((program, args)) = argv.split_first() else {
+        bail!("nothing to run: pass the command after `--`, e.g. `dwf run -- cargo expand`");
+    };
+    if opts.hermetic {
+        bail!("--hermetic is not supported for `dwf run`");
+    }
+    let stacks = requested_stacks(cfg);
+    let stack = match (stack, stacks.as_slice()) {
+        (Some(stack), _) => stack,
+        (None, [only]) => only.as_str(),
+        (None, _) => bail!(
+            "choose the stack to run in with --stack (project stacks: {})",
+            stacks.join(", ")
+        ),
+    };
+    let Some(extension) = stacks
+        .iter()
+        .find(|s| s.as_str() == stack)
+        .and_then(|s| registry.get(s))
+    else {
+        bail!(
+            "stack '{}' is not available (project stacks: {})",
+            stack,
+            stacks.join(", ")
+        );
+    };
 
-        let is_already_in_container = std::env::var("IS_CONTAINER")
-            .map(|v| v == "true")
-            .unwrap_or(false);
+    let merged = registry.merged_env_vars(&[stack.to_string()]);
+    let dotenv = DotEnv::load(cfg)?;
+    let action = dotenv.apply(with_merged_env(
+        ExecutionAction {
+            program: program.clone(),
+            args: args.to_vec(),
+            env: std::collections::HashMap::new(),
+        },
+        &merged,
+    ));
+    let masked = action_secrets(
+        registry,
+        stack,
+        &with_env_overrides(action.clone(), opts),
+        &dotenv.secrets(),
+    );
+
+    if uses_remote_runtime(cfg) {
+        info!(target: "devflow", "run {} on {} (remote)", program, stack);
+        return run_remote(
+            cfg,
+            &with_env_overrides(sanitize_host_env(action), opts),
+            &masked,
+        );
+    }
+    let final_action = if uses_container_proxy(cfg) {
+        let mut proxy = build_container_proxy(
+            cfg,
+            registry,
+            &[stack.to_string()],
+            extension.cache_mounts(),
+            &with_env_overrides(action, opts),
+            opts,
+            &mut CommandStats::default(),
+        )?;
+        if std::io::stdin().is_terminal() {
+            proxy.args.insert(1, "-it".to_string());
+        }
+        proxy
+    } else {
+        let workspace = std::env::current_dir()?;
+        let mut host_action = with_env_overrides(host_env(cfg, registry, action, &workspace), opts);
+        let tool_dirs = tools::installed_dirs(cfg, registry);
+        host_path::apply(&mut host_action, cfg, registry, &tool_dirs, opts)?;
+        if runtime_env(cfg) == RuntimeEnv::Nix {
+            wrap_nix_develop(host_action)
+        } else {
+            host_action
+        }
+    };
+    info!(target: "devflow", "run {} on {}", program, stack);
+    run_isolated(&final_action, false, &masked)
+}
 
-        let action_result = if cfg.runtime.profile == RuntimeProfile::Container
-            && !is_already_in_container
-        {
-            // If we are in container mode, check if the extension is trusted to run on host
+/// A command mapped to the concrete action a stack would execute.
+#[derive(Debug, Clone)]
+pub struct PlannedAction {
+    /// The stack (extension) that handles the command.
+    pub stack: String,
+    /// The command with default selectors applied.
+    pub command: CommandRef,
+    /// The action as produced by the extension, before container or host wrapping.
+    pub action: ExecutionAction,
+}
+
+/// The stacks selected to run a command, and those shadowed under `conflict = "first"`.
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    /// Actions that run, in dispatch order.
+    pub selected: Vec<PlannedAction>,
+    /// Actions a higher-precedence stack won over.
+    pub shadowed: Vec<PlannedAction>,
+}
+
+/// Resolves the actions `command` would execute without running anything.
+pub fn plan(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    command: &CommandRef,
+) -> Result<Vec<PlannedAction>> {
+    Ok(resolve(cfg, registry, command)?.selected)
+}
+
+/// Resolves which stacks handle `command` under `[runtime] conflict`.
+///
+/// # Errors
+/// Returns an error if an extension fails to map the command, or if several
+/// stacks claim it under `conflict = "error"`.
+pub fn resolve(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    command: &CommandRef,
+) -> Result<Resolution> {
+    let effective = with_default_selector(command);
+    let candidates = candidate_actions(cfg, registry, &effective, &ordered_stacks(cfg), false)?;
+    resolve_conflicts(cfg, &effective, candidates)
+}
+
+/// Maps `command` on each of `stacks`, keeping those that produce an action.
+///
+/// With `enforce_trust`, untrusted extensions are refused when negotiation would
+/// otherwise happen on the host in container mode.
+fn candidate_actions(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    command: &CommandRef,
+    stacks: &[String],
+    enforce_trust: bool,
+) -> Result<Vec<PlannedAction>> {
+    let mut candidates = Vec::new();
+    for stack in stacks {
+        if enforce_trust && uses_container_proxy(cfg) {
             if let Some(ext) = registry.get(stack) {
-                if ext.is_trusted() {
-                    map_command(stack, &effective, registry)
-                } else {
+                if !ext.is_trusted() {
                     // UNTRUSTED negotiation: we must run build_action inside a container
                     // For now, we bail with a helpful message until the full Pre-Flight container is wired
                     bail!("untrusted extension '{}' cannot negotiate on host in container mode. Move to trusted = true or wait for Pre-Flight jail support.", stack);
                 }
-            } else {
-                map_command(stack, &effective, registry)
             }
-        } else {
-            map_command(stack, &effective, registry)
-        };
+        }
+
+        if let Some(action) = map_command(stack, command, registry, &action_context(cfg))? {
+            candidates.push(PlannedAction {
+                stack: stack.clone(),
+                command: command.clone(),
+                action,
+            });
+        }
+    }
+    Ok(candidates)
+}
+
+/// Lets each stack narrow its action to what the `changed` files affect,
+/// dropping (and reporting through `unaffected`) those they do not affect.
+fn narrow_to_affected(
+    registry: &ExtensionRegistry,
+    selected: Vec<PlannedAction>,
+    changed: &[String],
+    mut unaffected: impl FnMut(&str),
+) -> Vec<PlannedAction> {
+    selected
+        .into_iter()
+        .filter_map(|mut planned| {
+            let Some(ext) = registry.get(&planned.stack) else {
+                return Some(planned);
+            };
+            match ext.affected(&planned.command, &planned.action, changed) {
+                Affected::All => {}
+                Affected::Action(action) => {
+                    info!(target: "devflow",
+                        "{} on {}: running what the changes affect ({})",
+                        planned.command,
+                        planned.stack,
+                        action.args.join(" ")
+                    );
+                    planned.action = action;
+                }
+                Affected::Nothing => {
+                    unaffected(&planned.stack);
+                    return None;
+                }
+            }
+            Some(planned)
+        })
+        .collect()
+}
+
+/// Applies `[runtime] conflict` to the stacks that produced an action.
+fn resolve_conflicts(
+    cfg: &DevflowConfig,
+    command: &CommandRef,
+    candidates: Vec<PlannedAction>,
+) -> Result<Resolution> {
+    match cfg.runtime.conflict {
+        ConflictPolicy::All => Ok(Resolution {
+            selected: candidates,
+            shadowed: Vec::new(),
+        }),
+        ConflictPolicy::First => {
+            let mut candidates = candidates.into_iter();
+            Ok(Resolution {
+                selected: candidates.next().into_iter().collect(),
+                shadowed: candidates.collect(),
+            })
+        }
+        ConflictPolicy::Error if candidates.len() > 1 => {
+            let stacks: Vec<&str> = candidates.iter().map(|c| c.stack.as_str()).collect();
+            bail!(
+                "command '{}' is claimed by multiple stacks ({}); set [runtime] conflict = \"first\" or \"all\"",
+                command.canonical(),
+                stacks.join(", ")
+            )
+        }
+        ConflictPolicy::Error => Ok(Resolution {
+            selected: candidates,
+            shadowed: Vec::new(),
+        }),
+    }
+}
 
-        let Some(action) = action_result? else {
-            info!(target: "devflow",
-                "skip {}: unsupported command {}",
-                stack,
-                effective.canonical()
+/// Merges the environment of every dispatched stack under `[runtime] env_conflict`.
+///
+/// # Errors
+/// Returns an error on a conflicting variable when the policy is `error`.
+pub fn merged_env(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<MergedEnv> {
+    let merged = registry.merged_env_vars(&ordered_stacks(cfg));
+    for conflict in &merged.conflicts {
+        if cfg.runtime.env_conflict == EnvConflictPolicy::Error {
+            bail!(
+                "environment variable '{}' is set to '{}' by {} and '{}' by {}; set [runtime] env_conflict = \"warn\" to keep the {} value",
+                conflict.key,
+                conflict.kept.value,
+                conflict.kept.source,
+                conflict.ignored.value,
+                conflict.ignored.source,
+                conflict.kept.source
             );
-            continue;
-        };
+        }
+        warn!(
+            "environment variable '{}': using '{}' from {} over '{}' from {}",
+            conflict.key,
+            conflict.kept.value,
+            conflict.kept.source,
+            conflict.ignored.value,
+            conflict.ignored.source
+        );
+    }
+    Ok(merged)
+}
 
-        attempted = true;
+/// Returns each dispatched stack with its effective environment: the merged
+/// environment overlaid by the stack's own extension variables, remapped onto
+/// the host (see [`host_env`]) when actions run there.
+pub fn stack_environments(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+) -> Result<Vec<(String, std::collections::BTreeMap<String, MergedEnvVar>)>> {
+    let merged = merged_env(cfg, registry)?;
+    let on_host = !uses_container_proxy(cfg) && !uses_remote_runtime(cfg);
+    let workspace = std::env::current_dir()?;
+    Ok(ordered_stacks(cfg)
+        .into_iter()
+        .map(|stack| {
+            let mut vars = merged.vars.clone();
+            if let Some(ext) = registry.get(&stack) {
+                for (key, value) in ext.env_vars() {
+                    vars.insert(
+                        key,
+                        MergedEnvVar {
+                            value,
+                            source: stack.clone(),
+                        },
+                    );
+                }
+            }
+            if on_host {
+                let action = ExecutionAction {
+                    program: String::new(),
+                    args: Vec::new(),
+                    env: vars
+                        .iter()
+                        .map(|(key, var)| (key.clone(), var.value.clone()))
+                        .collect(),
+                };
+                let mut env = host_env(cfg, registry, action, &workspace).env;
+                vars.retain(|key, var| match env.remove(key) {
+                    Some(value) => {
+                        var.value = value;
+                        true
+                    }
+                    None => false,
+                });
+            }
+            (stack, vars)
+        })
+        .collect())
+}
 
-        // When IS_CONTAINER=true (e.g., inside GHA native container: job),
-        // skip the docker-run proxy even if profile is "container".
-        // This enables GHA native container jobs to run dwf commands directly.
-        let is_already_in_container = std::env::var("IS_CONTAINER")
-            .map(|v| v == "true")
-            .unwrap_or(false);
-
-        let use_container_proxy =
-            cfg.runtime.profile == RuntimeProfile::Container && !is_already_in_container;
-        let final_action = if use_container_proxy {
-            build_container_proxy(cfg, registry, &action)?
-        } else {
-            sanitize_host_env(action)
-        };
+/// Layers the merged extension environment beneath the action's own variables.
+fn with_merged_env(mut action: ExecutionAction, merged: &MergedEnv) -> ExecutionAction {
+    let mut env: std::collections::HashMap<String, String> = merged
+        .vars
+        .iter()
+        .map(|(key, var)| (key.clone(), var.value.clone()))
+        .collect();
+    env.extend(action.env);
+    action.env = env;
+    action
+}
 
-        info!(target: "devflow", "run {} on {}", effective, stack);
-        run_action(&final_action)
-            .with_context(|| format!("{} failed for {}", effective.canonical(), stack))?;
+/// Lists the stacks a command is dispatched to, in precedence order.
+fn requested_stacks(cfg: &DevflowConfig) -> Vec<String> {
+    for stack in &cfg.project.stack {
+        if !stack_is_applicable(cfg, stack) {
+            info!(target: "devflow", "skip {}: manifest not found", stack);
+        }
     }
+    ordered_stacks(cfg)
+}
 
-    if !attempted {
+/// Keeps the stacks in `only`, if any, failing on one that is not requested.
+fn only_stacks(stacks: Vec<String>, only: &[String]) -> Result<Vec<String>> {
+    if let Some(missing) = only.iter().find(|stack| !stacks.contains(stack)) {
         bail!(
-            "command '{}' did not match any runnable stack",
-            command.canonical()
+            "stack '{}' is not available (project stacks: {})",
+            missing,
+            stacks.join(", ")
         );
     }
+    Ok(stacks
+        .into_iter()
+        .filter(|stack| only.is_empty() || only.contains(stack))
+        .collect())
+}
+
+/// Orders applicable stacks by `priority` (highest first), then `project.stack`
+/// position, then name for extensions only declared under `[extensions]`.
+pub fn ordered_stacks(cfg: &DevflowConfig) -> Vec<String> {
+    let mut stacks: Vec<String> = cfg
+        .project
+        .stack
+        .iter()
+        .filter(|stack| stack_is_applicable(cfg, stack))
+        .cloned()
+        .collect();
+
+    if let Some(extensions) = &cfg.extensions {
+        // Explicitly declared subprocess extensions assume implicit applicability
+        let mut declared: Vec<&String> = extensions
+            .keys()
+            .filter(|name| !stacks.contains(name))
+            .collect();
+        declared.sort();
+        stacks.extend(declared.into_iter().cloned());
+    }
+
+    stacks.sort_by_key(|stack| std::cmp::Reverse(stack_priority(cfg, stack)));
+    stacks
+}
+
+fn stack_priority(cfg: &DevflowConfig, stack: &str) -> i32 {
+    cfg.extensions
+        .as_ref()
+        .and_then(|extensions| extensions.get(stack))
+        .map(|ext| ext.priority)
+        .unwrap_or(0)
+}
+
+/// Returns true when Devflow itself already runs inside a container and
+/// `[container] nested = "skip"` runs actions there directly.
+fn skips_nested_proxy(cfg: &DevflowConfig) -> bool {
+    nested::strategy(cfg) == Some(NestedStrategy::Skip)
+}
+
+/// Resolves the development environment actions run in (never `Auto`).
+fn runtime_env(cfg: &DevflowConfig) -> RuntimeEnv {
+    resolved_env(cfg, skips_nested_proxy(cfg))
+}
 
+/// [`runtime_env`], with `skip_nested` in place of the container detection.
+fn resolved_env(cfg: &DevflowConfig, skip_nested: bool) -> RuntimeEnv {
+    if skip_nested {
+        return RuntimeEnv::None;
+    }
+    let base = cfg.source_dir.as_deref().unwrap_or(Path::new(""));
+    cfg.runtime.env.resolve(cfg.runtime.profile, base)
+}
+
+/// Returns true when actions are wrapped in a `docker run`/`podman run` proxy.
+///
+/// A devcontainer env always uses the proxy (with the devcontainer image); a Nix
+/// env replaces it with `nix develop` on the host.
+pub fn uses_container_proxy(cfg: &DevflowConfig) -> bool {
+    proxies_actions(cfg, skips_nested_proxy(cfg))
+}
+
+/// [`uses_container_proxy`], with `skip_nested` in place of the container
+/// detection.
+fn proxies_actions(cfg: &DevflowConfig, skip_nested: bool) -> bool {
+    match resolved_env(cfg, skip_nested) {
+        RuntimeEnv::Devcontainer => true,
+        RuntimeEnv::Nix => false,
+        RuntimeEnv::None | RuntimeEnv::Auto => {
+            cfg.runtime.profile == RuntimeProfile::Container && !skip_nested
+        }
+    }
+}
+
+/// Returns true when actions run on the `[runtime.remote]` build machine.
+fn uses_remote_runtime(cfg: &DevflowConfig) -> bool {
+    cfg.runtime.profile == RuntimeProfile::Remote && !skips_nested_proxy(cfg)
+}
+
+/// Syncs the workspace to the remote machine, runs `action` there, and pulls artifacts back.
+/// `secrets` are masked in errors.
+fn run_remote(cfg: &DevflowConfig, action: &ExecutionAction, secrets: &[String]) -> Result<()> {
+    let remote = cfg
+        .runtime
+        .remote
+        .as_ref()
+        .context("runtime profile 'remote' requires [runtime.remote]")?;
+    let plan = remote::plan(remote, &cfg.project.name, action);
+
+    run_isolated(&plan.sync_up, false, secrets).context("failed to sync workspace to remote")?;
+    run_isolated(&plan.exec, false, secrets)?;
+    if let Some(sync_back) = &plan.sync_back {
+        run_isolated(sync_back, false, secrets).context("failed to sync artifacts from remote")?;
+    }
     Ok(())
 }
 
+/// Wraps a host action so it runs inside the project's Nix development shell.
+fn wrap_nix_develop(action: ExecutionAction) -> ExecutionAction {
+    let mut args = vec![
+        "develop".to_string(),
+        "--command".to_string(),
+        action.program,
+    ];
+    args.extend(action.args);
+    ExecutionAction {
+        program: "nix".to_string(),
+        args,
+        env: action.env,
+    }
+}
+
+/// Resolves a workspace-relative path as seen by the executed command.
+///
+/// Container proxies mount the workspace at `/workspace`, remote runs execute
+/// from the synced workspace root, and host runs use the current directory.
+pub fn workspace_path(cfg: &DevflowConfig, rel: &str) -> Result<String> {
+    if uses_remote_runtime(cfg) {
+        Ok(rel.to_string())
+    } else if uses_container_proxy(cfg) {
+        Ok(format!("{}/{}", CONTAINER_WORKSPACE, rel))
+    } else {
+        Ok(std::env::current_dir()?
+            .join(rel)
+            .to_string_lossy()
+            .into_owned())
+    }
+}
+
+/// Adds the allowlisted variables `action` does not set from the inherited
+/// environment, for a host run with `clear_env`.
+fn inherit_allowed(action: &mut ExecutionAction, isolation: &CommandConfig) {
+    for key in &isolation.allow {
+        if let Some(value) = std::env::var_os(key) {
+            action
+                .env
+                .entry(key.clone())
+                .or_insert_with(|| value.to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// `secrets` plus the values of the credential variables of `action` (see
+/// [`devflow_core::Extension::secret_env`]).
+fn action_secrets(
+    registry: &ExtensionRegistry,
+    stack: &str,
+    action: &ExecutionAction,
+    secrets: &[String],
+) -> Vec<String> {
+    let declared = registry
+        .get(stack)
+        .map(|ext| ext.secret_env())
+        .unwrap_or_default();
+    let mut secrets = secrets.to_vec();
+    secrets.extend(
+        action
+            .env
+            .iter()
+            .filter(|(key, value)| {
+                !value.is_empty() && (is_secret_env(key) || declared.contains(key))
+            })
+            .map(|(_, value)| value.clone()),
+    );
+    secrets
+}
+
+/// `action` of `stack` as the run history keeps it: with the overrides of
+/// `opts`, the image it runs in, and `secrets` masked, as are the values of
+/// credential variables (see [`devflow_core::Extension::secret_env`]) wherever they appear.
+fn action_record(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    stack: &str,
+    action: &ExecutionAction,
+    opts: &RunOptions,
+    secrets: &[String],
+) -> ActionRecord {
+    let action = with_env_overrides(action.clone(), opts);
+    let secrets = action_secrets(registry, stack, &action, secrets);
+    let mask = |text: &str| capture::mask(text, &secrets).into_owned();
+    let image = (uses_container_proxy(cfg) && !uses_remote_runtime(cfg))
+        .then(|| opts.image.clone().or_else(|| container_image(cfg).ok()))
+        .flatten();
+    ActionRecord {
+        stack: stack.to_string(),
+        program: action.program,
+        args: action.args.iter().map(|arg| mask(arg)).collect(),
+        env: action
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), mask(value)))
+            .collect(),
+        image,
+    }
+}
+
+fn with_env_overrides(mut action: ExecutionAction, opts: &RunOptions) -> ExecutionAction {
+    action
+        .env
+        .extend(opts.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+    action
+}
+
+/// Workspace-relative paths a hermetic run is allowed to modify: declared outputs,
+/// the cache root (when it lives inside the workspace), and the command's cache mount points.
+fn hermetic_allowed_paths(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    command: &CommandRef,
+    workspace: &Path,
+) -> Vec<String> {
+    let mut allowed = hermetic_outputs(cfg).to_vec();
+
+    let cache_root = cache_root(cfg);
+    if let Ok(rel) = cache_root.strip_prefix(workspace) {
+        allowed.push(rel.to_string_lossy().into_owned());
+    }
+
+    for mount in registry.cache_mounts_for(command) {
+        if let Some((_, container_abs)) = parse_mount(&mount) {
+            if let Some(rel) = workspace_relative(container_abs) {
+                allowed.push(rel.to_string());
+            }
+        }
+    }
+
+    allowed
+}
+
+fn hermetic_outputs(cfg: &DevflowConfig) -> &[String] {
+    cfg.container
+        .as_ref()
+        .map(|c| c.outputs.as_slice())
+        .unwrap_or_default()
+}
+
+/// Maps a container path under the workspace mount to its workspace-relative form.
+fn workspace_relative(container_path: &str) -> Option<&str> {
+    container_path
+        .strip_prefix(CONTAINER_WORKSPACE)?
+        .strip_prefix('/')
+        .filter(|rel| !rel.is_empty())
+}
+
+fn cache_root_setting(cfg: &DevflowConfig) -> String {
+    std::env::var("DWF_CACHE_ROOT")
+        .ok()
+        .or_else(|| cfg.cache.as_ref().and_then(|c| c.root.clone()))
+        .unwrap_or_else(|| DEFAULT_CACHE_ROOT.to_string())
+}
+
+/// Maps container-bound env values onto the host for a host run, so host and
+/// container runs of a command see the same variables.
+///
+/// A value under an extension cache mount's container path points at the
+/// mount's host directory under the cache root, any other `/workspace` value
+/// into `workspace`. Other `/root` values have no host counterpart and are
+/// dropped, as are a `RUSTC_WRAPPER` that is not installed and the variables
+/// in `[runtime] host_env_exclude`.
+fn host_env(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    mut action: ExecutionAction,
+    workspace: &Path,
+) -> ExecutionAction {
+    let root = cache_root(cfg);
+    let mut mounts: Vec<(String, String)> = ordered_stacks(cfg)
+        .iter()
+        .filter_map(|stack| registry.get(stack))
+        .flat_map(|ext| ext.cache_mounts())
+        .filter_map(|mount| {
+            let (host_rel, container_abs) = parse_mount(&mount)?;
+            Some((
+                container_abs.trim_end_matches('/').to_string(),
+                root.join(host_rel).to_string_lossy().into_owned(),
+            ))
+        })
+        .collect();
+    // The most specific mount wins.
+    mounts.sort_by_key(|(container_abs, _)| std::cmp::Reverse(container_abs.len()));
+    let workspace = workspace.to_string_lossy().into_owned();
+    let under = |value: &str, dir: &str| -> Option<String> {
+        let rest = value.strip_prefix(dir)?;
+        (rest.is_empty() || rest.starts_with('/')).then(|| rest.to_string())
+    };
+
+    let exclude = &cfg.runtime.host_env_exclude;
+    action.env = std::mem::take(&mut action.env)
+        .into_iter()
+        .filter(|(key, _)| !exclude.contains(key))
+        .filter(|(key, value)| key != "RUSTC_WRAPPER" || command_exists(value))
+        .filter_map(|(key, value)| {
+            let mapped = mounts
+                .iter()
+                .find_map(|(container_abs, host_abs)| {
+                    under(&value, container_abs).map(|rest| format!("{host_abs}{rest}"))
+                })
+                .or_else(|| {
+                    under(&value, CONTAINER_WORKSPACE).map(|rest| format!("{workspace}{rest}"))
+                });
+            match mapped {
+                Some(mapped) => Some((key, mapped)),
+                None if under(&value, "/root").is_some() => None,
+                None => Some((key, value)),
+            }
+        })
+        .collect();
+    action
+}
+
 /// Removes container-bound env values when running directly on host.
 ///
 /// Extensions may return envs like `/workspace/...` or `/root/...` for container parity.
@@ -162,10 +1146,23 @@ fn map_command(
     stack: &str,
     cmd: &CommandRef,
     registry: &ExtensionRegistry,
+    ctx: &ActionContext,
 ) -> Result<Option<ExecutionAction>> {
     match stack {
         "custom" => Ok(map_custom(cmd)),
-        _ => registry.build_action(stack, cmd),
+        _ => registry.build_action(stack, cmd, ctx),
+    }
+}
+
+/// Where actions run: containers are Linux, as are remote build machines;
+/// otherwise the host.
+fn action_context(cfg: &DevflowConfig) -> ActionContext {
+    if uses_container_proxy(cfg) || uses_remote_runtime(cfg) {
+        ActionContext {
+            os: TargetOs::Linux,
+        }
+    } else {
+        ActionContext::host()
     }
 }
 
@@ -199,56 +1196,469 @@ fn map_custom(cmd: &CommandRef) -> Option<ExecutionAction> {
 }
 
 /// Executes a process on the host system.
+#[cfg(test)]
 fn run_action(action: &ExecutionAction) -> Result<()> {
-    let status = Command::new(&action.program)
-        .args(&action.args)
-        .envs(action.env.iter())
-        .status()
-        .with_context(|| {
-            format!(
-                "failed to start command '{} {}'",
-                action.program,
-                action.args.join(" ")
-            )
-        })?;
+    run_isolated(action, false, &[])
+}
+
+/// Runs `action`; with `clear_env` it gets only `action.env`. `secrets` are
+/// masked in errors.
+fn run_isolated(action: &ExecutionAction, clear_env: bool, secrets: &[String]) -> Result<()> {
+    let status = command_for(action, clear_env).status().with_context(|| {
+        format!(
+            "failed to start command '{}'",
+            command_line(action, secrets)
+        )
+    })?;
 
     if !status.success() {
+        return Err(CommandFailed::new(status, action, secrets).into());
+    }
+
+    Ok(())
+}
+
+/// The program and arguments of `action`, with `secrets` masked.
+fn command_line(action: &ExecutionAction, secrets: &[String]) -> String {
+    let line = format!("{} {}", action.program, action.args.join(" "));
+    capture::mask(&line, secrets).into_owned()
+}
+
+/// What happens to relayed output besides reaching the terminal.
+#[derive(Clone, Copy, Default)]
+struct Capture<'a> {
+    /// Reporter receiving each line, with the canonical command.
+    reporter: Option<(&'a dyn Reporter, &'a str)>,
+    /// `[commands] max_output`: terminal budget per stream.
+    max_output: Option<u64>,
+    /// `[commands] spool_logs`: the full log.
+    spool: Option<&'a Spool>,
+    /// `[logs]`: the command's log of the run, without ANSI codes.
+    log: Option<&'a LogFile>,
+    /// The last lines, kept for failure triage (`dwf explain-failure`).
+    tail: Option<&'a Tail>,
+    /// Remove ANSI codes before output reaches the terminal (the spool keeps
+    /// them).
+    strip_ansi: bool,
+    /// Problem matchers run over each stream.
+    matchers: &'a [Matcher],
+    /// Dotenv secret values masked in every output.
+    secrets: &'a [String],
+}
+
+impl Capture<'_> {
+    fn is_passthrough(&self) -> bool {
+        self.reporter.is_none()
+            && self.max_output.is_none()
+            && self.spool.is_none()
+            && self.log.is_none()
+            && self.tail.is_none()
+            && !self.strip_ansi
+            && self.matchers.is_empty()
+            && self.secrets.is_empty()
+    }
+}
+
+/// Compiles the problem matchers `stack` declares for `command`, skipping
+/// invalid ones with a warning.
+pub(crate) fn problem_matchers(
+    registry: &ExtensionRegistry,
+    stack: &str,
+    command: &CommandRef,
+) -> Vec<Matcher> {
+    registry
+        .problem_matchers_for(stack, command)
+        .iter()
+        .filter_map(|matcher| {
+            Matcher::compile(matcher)
+                .map_err(|e| warn!("ignoring problem matcher of {}: {:#}", stack, e))
+                .ok()
+        })
+        .collect()
+}
+
+/// The error context when `command` fails for `stack`, with the extension's
+/// hint on how to fix it.
+fn failure_context(registry: &ExtensionRegistry, stack: &str, command: &CommandRef) -> String {
+    let mut context = format!("{} failed for {}", command.canonical(), stack);
+    if let Some(hint) = registry
+        .get(stack)
+        .and_then(|ext| ext.failure_hint(command))
+    {
+        context.push_str(&format!("\n  hint: {hint}"));
+    }
+    context
+}
+
+/// Appends `found` to `problems`; tools such as cargo repeat a diagnostic
+/// for every target that compiles the same file.
+fn add_problems(problems: &mut Vec<Problem>, found: Vec<Problem>) {
+    for problem in found {
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+}
+
+fn finish_spool(spool: Option<Spool>) -> Option<PathBuf> {
+    match spool.map(Spool::finish) {
+        Some(Ok(path)) => {
+            info!(target: "devflow", "full log: {}", path.display());
+            Some(path)
+        }
+        Some(Err(e)) => {
+            warn!("{:#}", e);
+            None
+        }
+        None => None,
+    }
+}
+
+fn command_for(action: &ExecutionAction, clear_env: bool) -> Command {
+    let mut command = Command::new(&action.program);
+    if clear_env {
+        command.env_clear();
+    }
+    command.args(&action.args).envs(action.env.iter());
+    command
+}
+
+/// Runs `action` like [`run_isolated`]; with `tests`, a `timeout` or a
+/// `capture` that is not a passthrough, its output is relayed line by line,
+/// test summary lines are added to `tests` and matched diagnostics to
+/// `problems`. Colors stay on where they are rendered. After `timeout` the
+/// command is stopped (see [`wait_within`]) and fails.
+fn run_relayed(
+    action: &ExecutionAction,
+    tests: Option<&mut Option<TestCounts>>,
+    problems: &mut Vec<Problem>,
+    capture: Capture,
+    clear_env: bool,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    if tests.is_none() && capture.is_passthrough() && timeout.is_none() {
+        return run_isolated(action, clear_env, capture.secrets);
+    }
+    let mut command = command_for(action, clear_env);
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // Its own process group, so a timeout stops the whole process tree.
+    #[cfg(unix)]
+    if timeout.is_some() {
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    }
+    if !capture.strip_ansi {
+        for (key, value) in [("CARGO_TERM_COLOR", "always"), ("FORCE_COLOR", "1")] {
+            if std::env::var_os(key).is_none() && !action.env.contains_key(key) {
+                command.env(key, value);
+            }
+        }
+    }
+    let start_error = || {
+        format!(
+            "failed to start command '{}'",
+            command_line(action, capture.secrets)
+        )
+    };
+    let mut child = command.spawn().with_context(start_error)?;
+    // nextest reports on stderr, cargo test and JS runners on stdout.
+    let (found, status) = std::thread::scope(|scope| -> Result<_> {
+        let stderr = child.stderr.take().map(|stderr| {
+            scope.spawn(move || {
+                relay(
+                    BufReader::new(stderr),
+                    std::io::stderr(),
+                    OutputStream::Stderr,
+                    capture,
+                )
+            })
+        });
+        let stdout = child.stdout.take().map(|stdout| {
+            scope.spawn(move || {
+                relay(
+                    BufReader::new(stdout),
+                    std::io::stdout(),
+                    OutputStream::Stdout,
+                    capture,
+                )
+            })
+        });
+        let status = wait_within(&mut child, timeout).with_context(start_error)?;
+        let mut found = None;
+        if let Some(handle) = stdout {
+            let (counts, matched) = handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("stdout relay panicked"))??;
+            problems.extend(matched);
+            found = counts;
+        }
+        if let Some(Ok(Ok((counts, matched)))) = stderr.map(|handle| handle.join()) {
+            problems.extend(matched);
+            found = merge(found, counts);
+        }
+        Ok((found, status))
+    })?;
+    if let Some(tests) = tests {
+        *tests = merge(tests.take(), found);
+    }
+
+    let Some(status) = status else {
         bail!(
-            "command failed with status {}: {} {}",
-            status,
-            action.program,
-            action.args.join(" ")
+            "command timed out after {}: {}",
+            waits::format_duration(timeout.unwrap_or_default()),
+            command_line(action, capture.secrets)
         );
+    };
+    if !status.success() {
+        return Err(CommandFailed::new(status, action, capture.secrets).into());
     }
-
     Ok(())
 }
 
+/// A process that exited unsuccessfully, with its exit status.
+#[derive(Debug)]
+pub struct CommandFailed {
+    pub status: ExitStatus,
+    /// The program and its arguments, with secrets masked.
+    pub command: String,
+}
+
+impl CommandFailed {
+    fn new(status: ExitStatus, action: &ExecutionAction, secrets: &[String]) -> Self {
+        Self {
+            status,
+            command: command_line(action, secrets),
+        }
+    }
+
+    /// The process's exit code; `None` when a signal killed it.
+    pub fn code(&self) -> Option<i32> {
+        self.status.code()
+    }
+
+    /// The signal that killed the process.
+    pub fn signal(&self) -> Option<i32> {
+        #[cfg(unix)]
+        return std::os::unix::process::ExitStatusExt::signal(&self.status);
+        #[cfg(not(unix))]
+        None
+    }
+
+    /// The failed process in `error`'s chain, if a process failed it.
+    pub fn find(error: &anyhow::Error) -> Option<&Self> {
+        error.chain().find_map(|cause| cause.downcast_ref::<Self>())
+    }
+}
+
+impl std::fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "command failed with status {}: {}",
+            self.status, self.command
+        )
+    }
+}
+
+impl std::error::Error for CommandFailed {}
+
+/// The code `dwf` exits with after `error`: the exit code of the process
+/// that failed it, `128 + N` when signal `N` killed that process, and `1`
+/// for failures without a process exit (timeouts, devflow errors).
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    let Some(failed) = CommandFailed::find(error) else {
+        return 1;
+    };
+    let code = match (failed.code(), failed.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    };
+    u8::try_from(code)
+        .ok()
+        .filter(|code| *code != 0)
+        .unwrap_or(1)
+}
+
+/// Waits for `child`; once `timeout` passes, its process group gets SIGTERM
+/// (an engine client forwards it to the container) and, if still running
+/// after [`STOP_GRACE`], SIGKILL. Returns `None` when the command timed out.
+fn wait_within(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+    let poll = |child: &mut Child, until: Instant| -> std::io::Result<Option<ExitStatus>> {
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+            if Instant::now() >= until {
+                return Ok(None);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    };
+    if let Some(status) = poll(child, Instant::now() + timeout)? {
+        return Ok(Some(status));
+    }
+    warn!(
+        "command timed out after {}, stopping it",
+        waits::format_duration(timeout)
+    );
+    signal_group(child, "TERM");
+    if poll(child, Instant::now() + STOP_GRACE)?.is_none() {
+        signal_group(child, "KILL");
+        let _ = child.kill();
+        child.wait()?;
+    }
+    Ok(None)
+}
+
+/// Sends `signal` to the process group led by `child`.
+fn signal_group(child: &Child, signal: &str) {
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .arg(format!("-{signal}"))
+        .arg("--")
+        .arg(format!("-{}", child.id()))
+        .stderr(Stdio::null())
+        .status();
+    #[cfg(not(unix))]
+    let _ = (child, signal);
+}
+
+/// Copies `reader` to `out` line by line within the `capture` budget, summing
+/// the test summaries seen, matching problems and passing each line to the
+/// reporter and spool, with secrets masked.
+fn relay(
+    mut reader: impl BufRead,
+    out: impl Write,
+    stream: OutputStream,
+    capture: Capture,
+) -> std::io::Result<(Option<TestCounts>, Vec<Problem>)> {
+    let mut out = Capped::new(out, capture.max_output);
+    let mut scanner = Scanner::new(capture.matchers);
+    let mut problems = Vec::new();
+    let mut counts = None;
+    let mut line = Vec::new();
+    while reader
+        .by_ref()
+        .take(capture::MAX_LINE)
+        .read_until(b'\n', &mut line)?
+        > 0
+    {
+        let raw = String::from_utf8_lossy(&line);
+        let text = capture::mask(&raw, capture.secrets);
+        let bytes = match &text {
+            Cow::Owned(masked) if !capture.secrets.is_empty() => masked.as_bytes(),
+            _ => &line,
+        };
+        counts = merge(counts, TestCounts::parse(&text));
+        if let Some((reporter, command)) = capture.reporter {
+            reporter.on_output(command, stream, text.trim_end_matches(['\r', '\n']));
+        }
+        if let Some(spool) = capture.spool {
+            spool.line(bytes)?;
+        }
+        let plain = capture::strip_ansi(&text);
+        if let Some(log) = capture.log {
+            log.line(plain.as_bytes())?;
+        }
+        problems.extend(scanner.line(&plain));
+        if let Some(tail) = capture.tail {
+            tail.line(&plain);
+        }
+        match plain {
+            Cow::Owned(plain) if capture.strip_ansi => out.line(plain.as_bytes())?,
+            _ => out.line(bytes)?,
+        }
+        line.clear();
+    }
+    let note = match capture.spool {
+        Some(spool) => format!("; full log: {}", spool.path().display()),
+        None => String::new(),
+    };
+    out.finish(stream.as_str(), &note)?;
+    Ok((counts, problems))
+}
+
+fn merge(a: Option<TestCounts>, b: Option<TestCounts>) -> Option<TestCounts> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
 /// Transforms a host execution action into a containerized proxy action.
 ///
 /// This involves:
 /// 1. Detecting an available container engine (Docker/Podman).
-/// 2. Resolving the appropriate container image.
+/// 2. Resolving the appropriate container image, pulling it with progress if missing.
 /// 3. Injecting the host `dwf` binary into the container to ensure version parity.
-/// 4. Mounting the workspace and any extension-defined cache volumes.
+/// 4. Mounting the workspace and the extension-defined cache volumes in `mounts`.
+///
+/// In hermetic mode the container has no network, the workspace is mounted
+/// read-only, and only declared outputs are re-mounted writable.
 fn build_container_proxy(
     cfg: &DevflowConfig,
     registry: &ExtensionRegistry,
+    stacks: &[String],
+    mounts: Vec<String>,
     action: &ExecutionAction,
+    opts: &RunOptions,
+    stats: &mut CommandStats,
 ) -> Result<ExecutionAction> {
-    let container_config = cfg.container.as_ref();
-    let engine_cfg = container_config.map(|c| c.engine).unwrap_or_default();
+    let (engine_cmd, image, mut args) =
+        container_run_args(cfg, registry, stacks, mounts, opts, stats)?;
+    for (key, value) in &action.env {
+        args.push("-e".to_string());
+        args.push(format!("{}={}", key, value));
+    }
 
-    let engine_cmd = resolve_engine(engine_cfg)?;
+    args.push(image);
+    args.push(action.program.clone());
+    args.extend(action.args.clone());
 
-    let image = container_config
-        .and_then(|c| c.image.clone())
-        .unwrap_or_else(|| DEFAULT_CI_IMAGE.to_string());
+    Ok(ExecutionAction {
+        program: engine_cmd,
+        args,
+        env: action.env.clone(),
+    })
+}
 
-    let dwf_cache_root = std::env::var("DWF_CACHE_ROOT")
-        .ok()
-        .or_else(|| cfg.cache.as_ref().and_then(|c| c.root.clone()))
-        .unwrap_or_else(|| DEFAULT_CACHE_ROOT.to_string());
+/// The engine, the image (ensured to be present) and the `run` arguments
+/// before the environment and image: workspace, `dwf`, cache and `stacks`'
+/// volume mounts, platform and network.
+fn container_run_args(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    stacks: &[String],
+    mounts: Vec<String>,
+    opts: &RunOptions,
+    stats: &mut CommandStats,
+) -> Result<(String, String, Vec<String>)> {
+    let engine_cmd = container_engine(cfg)?;
+    let image = match &opts.image {
+        Some(image) => image.clone(),
+        None => container_image(cfg)?,
+    };
+    let platform = run_platform(cfg);
+    if let Some(platform) = &platform {
+        warn!(
+            "host platform {} is not in [container] platforms; running {} under emulation, expect it to be considerably slower",
+            devflow_core::runtime::host_platform(),
+            platform
+        );
+    }
+    stats.cache_hit = Some(container::ensure_image(
+        &engine_cmd,
+        &image,
+        platform.as_deref(),
+    )?);
+
+    let dwf_cache_root = cache_root_setting(cfg);
 
     let cwd = std::env::current_dir()?;
     let cwd_str = cwd.to_string_lossy();
@@ -259,21 +1669,52 @@ fn build_container_proxy(
     let host_dwf_path = std::env::current_exe()?;
     let host_dwf_str = host_dwf_path.to_string_lossy();
 
+    let read_only = opts.hermetic || opts.read_only.is_some();
+    let workspace_mode = if read_only { ":ro" } else { "" };
+    let nested = nested::strategy(cfg);
+    let host_paths = match nested {
+        Some(NestedStrategy::Socket) => Some(HostPaths::discover(&engine_cmd)?),
+        _ => None,
+    };
     let mut args = vec![
         "run".to_string(),
         "--rm".to_string(),
         "-v".to_string(),
-        format!("{}:{}", cwd_str, CONTAINER_WORKSPACE),
-        "-v".to_string(),
-        format!("{}:{}:ro", host_dwf_str, CONTAINER_DWF_BIN),
-        "-w".to_string(),
-        CONTAINER_WORKSPACE.to_string(),
+        format!("{}:{}{}", cwd_str, CONTAINER_WORKSPACE, workspace_mode),
     ];
+    // The host's engine can only mount the invoking `dwf` if it is on a mount
+    // of this container; it is left out otherwise.
+    let dwf_visible = host_paths
+        .as_ref()
+        .is_none_or(|paths| paths.translate(&host_dwf_path).is_some());
+    if dwf_visible {
+        args.push("-v".to_string());
+        args.push(format!("{}:{}:ro", host_dwf_str, CONTAINER_DWF_BIN));
+    }
+    args.push("-w".to_string());
+    args.push(CONTAINER_WORKSPACE.to_string());
+    if let Some(platform) = platform {
+        args.push("--platform".to_string());
+        args.push(platform);
+    }
+
+    if opts.hermetic {
+        args.push("--network".to_string());
+        args.push("none".to_string());
+    } else if let Some(network) = &opts.network {
+        args.push("--network".to_string());
+        args.push(network.clone());
+    }
+    if read_only {
+        args.extend(writable_mounts(cfg, opts, &cwd)?);
+    }
+    if let Some(container) = &cfg.container {
+        args.extend(configured_run_args(container));
+    }
 
     // Cache redirection: extensions define relative paths (e.g. ".cargo") which
     // we anchor to the unified `DWF_CACHE_ROOT` on the host.
     let abs_cache_root = resolve_cache_root(cfg, &dwf_cache_root);
-    let mounts = registry.all_cache_mounts();
 
     for mount in mounts {
         if let Some((host_rel, container_abs)) = parse_mount(&mount) {
@@ -287,6 +1728,14 @@ fn build_container_proxy(
                 );
             }
 
+            // A read-only workspace cannot receive new mount points, so nested
+            // cache mounts need their directories to exist up front.
+            if read_only {
+                if let Some(rel) = workspace_relative(container_abs) {
+                    let _ = std::fs::create_dir_all(cwd.join(rel));
+                }
+            }
+
             args.push("-v".to_string());
             args.push(format!("{}:{}", host_abs.display(), container_abs));
         } else {
@@ -294,20 +1743,226 @@ fn build_container_proxy(
         }
     }
 
-    for (key, value) in &action.env {
-        args.push("-e".to_string());
-        args.push(format!("{}={}", key, value));
+    for (volume, path) in volumes::prepare(cfg, registry, stacks, &engine_cmd, &image, &cwd)? {
+        if read_only {
+            let _ = std::fs::create_dir_all(cwd.join(&path));
+        }
+        args.push("-v".to_string());
+        args.push(format!("{}:{}/{}", volume, CONTAINER_WORKSPACE, path));
+    }
+    if let Some(paths) = host_paths {
+        // The host's engine resolves bind mounts on the host.
+        args = paths.translate_args(args)?;
     }
+    Ok((engine_cmd, image, args))
+}
 
-    args.push(image);
-    args.push(action.program.clone());
-    args.extend(action.args.clone());
+/// The `[container]` entrypoint, labels and extra args.
+pub(crate) fn configured_run_args(container: &ContainerConfig) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(entrypoint) = &container.entrypoint {
+        args.push("--entrypoint".to_string());
+        args.push(entrypoint.clone());
+    }
+    for (key, value) in &container.labels {
+        args.push("--label".to_string());
+        args.push(format!("{key}={value}"));
+    }
+    args.extend(container.extra_args.iter().cloned());
+    args
+}
 
-    Ok(ExecutionAction {
-        program: engine_cmd,
-        args,
-        env: action.env.clone(),
-    })
+/// The mounts that keep `[container] outputs` and the read-only paths of
+/// `opts` writable over a read-only workspace, creating their directories.
+fn writable_mounts(cfg: &DevflowConfig, opts: &RunOptions, cwd: &Path) -> Result<Vec<String>> {
+    let writable = opts.read_only.as_ref();
+    let mut args = Vec::new();
+    for output in hermetic_outputs(cfg)
+        .iter()
+        .chain(writable.iter().flat_map(|w| &w.host))
+    {
+        let host_abs = cwd.join(output);
+        std::fs::create_dir_all(&host_abs)
+            .with_context(|| format!("failed to create output directory {}", host_abs.display()))?;
+        args.push("-v".to_string());
+        args.push(format!(
+            "{}:{}/{}",
+            host_abs.display(),
+            CONTAINER_WORKSPACE,
+            output.trim_end_matches('/')
+        ));
+    }
+    for path in writable.iter().flat_map(|w| &w.tmpfs) {
+        // The mount point must exist in the read-only workspace.
+        let _ = std::fs::create_dir_all(cwd.join(path));
+        args.push("--tmpfs".to_string());
+        args.push(format!(
+            "{}/{}",
+            CONTAINER_WORKSPACE,
+            path.trim_end_matches('/')
+        ));
+    }
+    Ok(args)
+}
+
+/// A long-lived container that the containerized commands of one check run
+/// `exec` into (`[container] reuse = true`), instead of each paying for its
+/// own `run` and cache warm-up.
+///
+/// The container's main process waits on a pipe held by dwf, so it stops
+/// (and `--rm` removes it) when the session is dropped, or when dwf exits on
+/// a signal.
+#[derive(Debug)]
+pub struct ContainerSession {
+    engine: String,
+    name: String,
+    cache_hit: bool,
+    child: Child,
+}
+
+impl ContainerSession {
+    /// Starts the session container for `commands` if `[container] reuse` is
+    /// set and they run containerized, with every cache mount any of them
+    /// needs.
+    ///
+    /// # Errors
+    /// Returns an error if the engine or image is unavailable or the
+    /// container does not start.
+    pub fn start(
+        cfg: &DevflowConfig,
+        registry: &ExtensionRegistry,
+        commands: &[CommandRef],
+        opts: &RunOptions,
+    ) -> Result<Option<Self>> {
+        let reuse = cfg.container.as_ref().is_some_and(|c| c.reuse);
+        if !reuse || opts.hermetic || !uses_container_proxy(cfg) || uses_remote_runtime(cfg) {
+            return Ok(None);
+        }
+        let mut mounts: Vec<String> = commands
+            .iter()
+            .flat_map(|cmd| registry.cache_mounts_for(&with_default_selector(cmd)))
+            .collect();
+        mounts.sort();
+        mounts.dedup();
+        let mut stats = CommandStats::default();
+        let (engine, image, mut args) = container_run_args(
+            cfg,
+            registry,
+            &requested_stacks(cfg),
+            mounts,
+            &RunOptions::default(),
+            &mut stats,
+        )?;
+        let name = format!(
+            "dwf-{}-{}",
+            volumes::engine_name(&cfg.project.name),
+            std::process::id()
+        );
+        args.extend(
+            ["-i", "--name", &name, &image, "sh", "-c"]
+                .iter()
+                .map(|arg| arg.to_string()),
+        );
+        args.push("echo ready && exec cat >/dev/null".to_string());
+
+        let mut child = Command::new(&engine)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start session container with {}", engine))?;
+        let mut ready = String::new();
+        if let Some(stdout) = child.stdout.take() {
+            BufReader::new(stdout).read_line(&mut ready)?;
+        }
+        if ready.trim() != "ready" {
+            drop(child.stdin.take());
+            let status = child.wait()?;
+            bail!("session container {} did not start ({})", name, status);
+        }
+        info!(target: "devflow", "started session container {}", name);
+        Ok(Some(Self {
+            engine,
+            name,
+            cache_hit: stats.cache_hit.unwrap_or_default(),
+            child,
+        }))
+    }
+
+    /// Whether a command run with `opts` can use the session: hermetic runs,
+    /// fixture networks and timeouts (which must stop the command with its
+    /// container) need a container of their own.
+    fn serves(opts: &RunOptions) -> bool {
+        !opts.hermetic
+            && opts.read_only.is_none()
+            && opts.network.is_none()
+            && opts.timeout.is_none()
+            && opts.image.is_none()
+    }
+
+    /// `action` as an `exec` in the session container, with exactly the
+    /// action's environment.
+    fn exec(&self, action: &ExecutionAction) -> ExecutionAction {
+        let mut args = vec![
+            "exec".to_string(),
+            "-w".to_string(),
+            CONTAINER_WORKSPACE.to_string(),
+        ];
+        for (key, value) in &action.env {
+            args.push("-e".to_string());
+            args.push(format!("{}={}", key, value));
+        }
+        args.push(self.name.clone());
+        args.push(action.program.clone());
+        args.extend(action.args.clone());
+        ExecutionAction {
+            program: self.engine.clone(),
+            args,
+            env: action.env.clone(),
+        }
+    }
+}
+
+impl Drop for ContainerSession {
+    fn drop(&mut self) {
+        drop(self.child.stdin.take());
+        if let Err(e) = self.child.wait() {
+            warn!("failed to stop session container {}: {}", self.name, e);
+        }
+    }
+}
+
+/// Resolves the container engine binary (`docker` or `podman`) for `cfg`.
+pub fn container_engine(cfg: &DevflowConfig) -> Result<String> {
+    resolve_engine(cfg.container.as_ref().map(|c| c.engine).unwrap_or_default())
+}
+
+/// The `--platform` containerized commands must request, if the host's own
+/// platform is not among `[container] platforms`.
+pub fn run_platform(cfg: &DevflowConfig) -> Option<String> {
+    let platforms = cfg.container.as_ref().map(|c| c.platforms.as_slice())?;
+    devflow_core::runtime::emulated_platform(platforms, &devflow_core::runtime::host_platform())
+        .map(str::to_string)
+}
+
+/// Resolves the image containerized commands run in.
+///
+/// A devcontainer env uses the devcontainer image; otherwise `[container] image`,
+/// falling back to the default CI image.
+pub fn container_image(cfg: &DevflowConfig) -> Result<String> {
+    if runtime_env(cfg) == RuntimeEnv::Devcontainer {
+        let base = cfg.source_dir.as_deref().unwrap_or(Path::new(""));
+        return devflow_core::runtime::devcontainer_image(base);
+    }
+    Ok(configured_image(cfg))
+}
+
+/// `[container] image`, or the default CI image.
+pub(crate) fn configured_image(cfg: &DevflowConfig) -> String {
+    cfg.container
+        .as_ref()
+        .and_then(|c| c.image.clone())
+        .unwrap_or_else(|| DEFAULT_CI_IMAGE.to_string())
 }
 
 fn resolve_engine(engine_cfg: ContainerEngine) -> Result<String> {
@@ -352,6 +2007,14 @@ fn is_engine_healthy(name: &str) -> bool {
         .unwrap_or(false)
 }
 
+/// Returns the absolute host directory cache mounts are anchored to.
+///
+/// `DWF_CACHE_ROOT` overrides `[cache] root`; relative roots resolve against the
+/// directory containing `devflow.toml`.
+pub fn cache_root(cfg: &DevflowConfig) -> PathBuf {
+    resolve_cache_root(cfg, &cache_root_setting(cfg))
+}
+
 fn resolve_cache_root(cfg: &DevflowConfig, root: &str) -> PathBuf {
     let path = PathBuf::from(root);
     if path.is_absolute() {
@@ -368,7 +2031,8 @@ fn resolve_cache_root(cfg: &DevflowConfig, root: &str) -> PathBuf {
     abs_source.join(root)
 }
 
-fn parse_mount(mount: &str) -> Option<(&str, &str)> {
+/// Splits a `host_relative_dir:container_absolute_dir` cache mount.
+pub fn parse_mount(mount: &str) -> Option<(&str, &str)> {
     let parts: Vec<&str> = mount.split(':').collect();
     if parts.len() == 2 {
         Some((parts[0], parts[1]))
@@ -422,40 +2086,308 @@ mod tests {
     }
 
     #[test]
-    fn integration_test_run_action_success() {
+    fn integration_test_run_action_success() {
+        let action = ExecutionAction {
+            program: "echo".to_string(),
+            args: vec!["hello".to_string(), "world".to_string()],
+            env: std::collections::HashMap::new(),
+        };
+        // Should succeed without error
+        assert!(run_action(&action).is_ok());
+    }
+
+    #[test]
+    fn integration_test_run_action_failure() {
+        let action = ExecutionAction {
+            program: "false".to_string(), // Typical unix command that always fails
+            args: vec![],
+            env: std::collections::HashMap::new(),
+        };
+        let result = run_action(&action);
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("command failed with status"));
+        assert_eq!(CommandFailed::find(&err).unwrap().code(), Some(1));
+        assert_eq!(exit_code(&err), 1);
+    }
+
+    #[test]
+    fn integration_test_exit_codes_follow_the_failed_process() {
+        let sh = |script: &str| ExecutionAction {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: std::collections::HashMap::new(),
+        };
+        let err = run_action(&sh("exit 3"))
+            .context("test:unit failed")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 3);
+        let err = run_action(&sh("kill -9 $$")).unwrap_err();
+        let failed = CommandFailed::find(&err).unwrap();
+        assert_eq!((failed.code(), failed.signal()), (None, Some(9)));
+        assert_eq!(exit_code(&err), 137);
+        assert_eq!(exit_code(&anyhow::anyhow!("no stack")), 1);
+    }
+
+    #[test]
+    fn integration_test_errors_mask_secrets_in_the_command_line() {
+        let secrets = ["hunter2".to_string()];
+        let proxy = ExecutionAction {
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "exit 1".to_string(),
+                "-e".to_string(),
+                "API_TOKEN=hunter2".to_string(),
+            ],
+            env: std::collections::HashMap::new(),
+        };
+        let err = run_isolated(&proxy, false, &secrets).unwrap_err();
+        assert!(format!("{err:#}").ends_with("-e API_TOKEN=***"), "{err:#}");
+        let err = run_relayed(
+            &proxy,
+            None,
+            &mut Vec::new(),
+            Capture {
+                secrets: &secrets,
+                ..Capture::default()
+            },
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(!format!("{err:#}").contains("hunter2"), "{err:#}");
+
+        let missing = ExecutionAction {
+            program: "devflow-no-such-program".to_string(),
+            ..proxy
+        };
+        let err = run_isolated(&missing, false, &secrets).unwrap_err();
+        assert!(!format!("{err:#}").contains("hunter2"), "{err:#}");
+
+        let secret = ExecutionAction {
+            env: [("API_TOKEN".to_string(), "s3cret".to_string())].into(),
+            ..missing
+        };
+        let masked = action_secrets(&ExtensionRegistry::default(), "rust", &secret, &secrets);
+        assert_eq!(masked, ["hunter2", "s3cret"]);
+    }
+
+    #[test]
+    fn integration_test_run_action_invalid_program() {
+        let action = ExecutionAction {
+            program: "this-program-definitely-does-not-exist-123".to_string(),
+            args: vec![],
+            env: std::collections::HashMap::new(),
+        };
+        let result = run_action(&action);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn timeout_stops_the_whole_process_tree() {
+        let action = ExecutionAction {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), "sleep 30 & sleep 30".to_string()],
+            env: std::collections::HashMap::new(),
+        };
+        let started = Instant::now();
+        let err = run_relayed(
+            &action,
+            None,
+            &mut Vec::new(),
+            Capture::default(),
+            false,
+            Some(Duration::from_millis(200)),
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().starts_with("command timed out after 200ms"),
+            "{err}"
+        );
+        // The background sleep held the output pipes open until it was stopped too.
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let quick = ExecutionAction {
+            args: vec!["-c".to_string(), "true".to_string()],
+            ..action
+        };
+        run_relayed(
+            &quick,
+            None,
+            &mut Vec::new(),
+            Capture::default(),
+            false,
+            Some(Duration::from_secs(5)),
+        )
+        .unwrap();
+        assert_eq!(
+            only_stacks(
+                vec!["rust".to_string(), "node".to_string()],
+                &["node".to_string()]
+            )
+            .unwrap(),
+            ["node"]
+        );
+        assert!(only_stacks(vec!["rust".to_string()], &["go".to_string()]).is_err());
+    }
+
+    #[test]
+    fn relay_matches_problems_in_colored_output() {
+        let matcher = devflow_core::problem::ProblemMatcher {
+            owner: "tsc".to_string(),
+            severity: devflow_core::problem::Severity::Error,
+            pattern: vec![devflow_core::problem::ProblemPattern {
+                regexp: r"^(.+?)\((\d+),(\d+)\): (.+)$".to_string(),
+                file: Some(1),
+                line: Some(2),
+                column: Some(3),
+                message: Some(4),
+                ..Default::default()
+            }],
+        };
+        let matchers = [Matcher::compile(&matcher).unwrap()];
+        let capture = Capture {
+            matchers: &matchers,
+            ..Capture::default()
+        };
+        let input = "building\n\u{1b}[31msrc/a.ts(3,7): Type 'x' is not assignable\u{1b}[0m\n";
+        let mut out = Vec::new();
+        let (_, problems) =
+            relay(input.as_bytes(), &mut out, OutputStream::Stdout, capture).unwrap();
+        assert_eq!(out, input.as_bytes());
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].file.as_deref(), Some("src/a.ts"));
+        assert_eq!(problems[0].message, "Type 'x' is not assignable");
+
+        let mut all = problems.clone();
+        add_problems(&mut all, problems);
+        assert_eq!(all.len(), 1);
+    }
+
+    #[test]
+    fn integration_test_failure_tail_is_kept_without_other_capture() {
+        let tail = Tail::new(FAILURE_TAIL_LINES);
+        let capture = Capture {
+            tail: Some(&tail),
+            ..Capture::default()
+        };
+        assert!(!capture.is_passthrough());
+        let stripped = Capture {
+            strip_ansi: true,
+            ..Capture::default()
+        };
+        assert!(!stripped.is_passthrough());
+        assert!(Capture::default().is_passthrough());
+
         let action = ExecutionAction {
-            program: "echo".to_string(),
-            args: vec!["hello".to_string(), "world".to_string()],
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "echo 'src/main.rs: not formatted'; exit 1".to_string(),
+            ],
             env: std::collections::HashMap::new(),
         };
-        // Should succeed without error
-        assert!(run_action(&action).is_ok());
+        assert!(run_relayed(&action, None, &mut Vec::new(), capture, false, None).is_err());
+        assert!(tail
+            .into_lines()
+            .iter()
+            .any(|line| line.contains("src/main.rs: not formatted")));
     }
 
     #[test]
-    fn integration_test_run_action_failure() {
-        let action = ExecutionAction {
-            program: "false".to_string(), // Typical unix command that always fails
-            args: vec![],
-            env: std::collections::HashMap::new(),
+    fn relay_masks_dotenv_secrets() {
+        let secrets = ["hunter2".to_string()];
+        let capture = Capture {
+            secrets: &secrets,
+            ..Capture::default()
         };
-        let result = run_action(&action);
-        assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .to_string()
-            .contains("command failed with status"));
+        assert!(!capture.is_passthrough());
+        let mut out = Vec::new();
+        relay(
+            "login with hunter2\nok\n".as_bytes(),
+            &mut out,
+            OutputStream::Stdout,
+            capture,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "login with ***\nok\n");
     }
 
     #[test]
-    fn integration_test_run_action_invalid_program() {
+    fn action_records_mask_credential_variables() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_node::NodeExtension::new()));
+        // What `build:debug` runs in a Turborepo with a remote cache.
         let action = ExecutionAction {
-            program: "this-program-definitely-does-not-exist-123".to_string(),
-            args: vec![],
-            env: std::collections::HashMap::new(),
+            program: "npx".to_string(),
+            args: [
+                "--no",
+                "--",
+                "turbo",
+                "run",
+                "build",
+                "--token=supersecret123",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+            env: [
+                ("TURBO_TOKEN", "supersecret123"),
+                ("NX_SELF_HOSTED_REMOTE_CACHE_ACCESS_TOKEN", "nxsecret"),
+                ("TURBO_TEAM", "web"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
         };
-        let result = run_action(&action);
-        assert!(result.is_err());
+        let opts = RunOptions {
+            env: [("DEPLOY_PASSWORD".to_string(), "hunter2".to_string())].into(),
+            ..RunOptions::default()
+        };
+        let record = action_record(
+            &DevflowConfig::default(),
+            &registry,
+            "node",
+            &action,
+            &opts,
+            &[],
+        );
+        assert_eq!(record.env["TURBO_TOKEN"], "***");
+        assert_eq!(
+            record.env["NX_SELF_HOSTED_REMOTE_CACHE_ACCESS_TOKEN"],
+            "***"
+        );
+        assert_eq!(record.env["DEPLOY_PASSWORD"], "***");
+        assert_eq!(record.env["TURBO_TEAM"], "web");
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(
+            !json.contains("supersecret123") && !json.contains("hunter2"),
+            "{json}"
+        );
+    }
+
+    #[test]
+    fn clear_env_keeps_only_the_allowlisted_environment() {
+        let isolation = CommandConfig {
+            clear_env: true,
+            allow: vec!["HOME".to_string(), "DWF_TEST_NEVER_SET".to_string()],
+            ..CommandConfig::default()
+        };
+        let mut action = ExecutionAction {
+            program: "/bin/sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "test -n \"$HOME\" && test -z \"$CARGO_PKG_NAME\" && test \"$KEEP\" = 1"
+                    .to_string(),
+            ],
+            env: std::collections::HashMap::from([("KEEP".to_string(), "1".to_string())]),
+        };
+        inherit_allowed(&mut action, &isolation);
+        assert!(action.env.contains_key("HOME"));
+        assert!(!action.env.contains_key("DWF_TEST_NEVER_SET"));
+        assert!(run_isolated(&action, true, &[]).is_ok());
+        assert!(run_isolated(&action, false, &[]).is_err());
     }
 
     #[test]
@@ -504,17 +2436,33 @@ mod tests {
     #[test]
     fn resolve_cache_root_absolute_passthrough() {
         let cfg = DevflowConfig {
+            schema_version: None,
             project: devflow_core::config::ProjectConfig {
                 name: "test".to_string(),
                 stack: vec![],
+                detected_stacks: Vec::new(),
             },
             runtime: devflow_core::config::RuntimeConfig::default(),
             targets: devflow_core::config::TargetsConfig {
-                profiles: std::collections::HashMap::new(),
+                profiles: Default::default(),
             },
             extensions: None,
             container: None,
             cache: None,
+            extends: None,
+            preset: None,
+            concurrency: None,
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
             source_dir: None,
         };
         let result = resolve_cache_root(&cfg, "/absolute/path");
@@ -524,17 +2472,33 @@ mod tests {
     #[test]
     fn resolve_cache_root_relative_anchored_to_source_dir() {
         let cfg = DevflowConfig {
+            schema_version: None,
             project: devflow_core::config::ProjectConfig {
                 name: "test".to_string(),
                 stack: vec![],
+                detected_stacks: Vec::new(),
             },
             runtime: devflow_core::config::RuntimeConfig::default(),
             targets: devflow_core::config::TargetsConfig {
-                profiles: std::collections::HashMap::new(),
+                profiles: Default::default(),
             },
             extensions: None,
             container: None,
             cache: None,
+            extends: None,
+            preset: None,
+            concurrency: None,
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
             source_dir: Some(PathBuf::from("/project")),
         };
         let result = resolve_cache_root(&cfg, ".cache/devflow");
@@ -580,23 +2544,52 @@ mod tests {
                 capabilities: vec![],
                 required: false,
                 trusted: false,
+                priority: 0,
+                fingerprint_strategy: None,
+                mount_type: Default::default(),
+                feature_matrix: None,
+                wasm_targets: vec![],
+                no_std_targets: vec![],
+                visual: None,
+                remote_cache: None,
             },
         );
 
         let cfg = DevflowConfig {
+            schema_version: None,
             project: devflow_core::config::ProjectConfig {
                 name: "trust-test".to_string(),
                 stack: vec![],
+                detected_stacks: Vec::new(),
             },
             runtime: devflow_core::config::RuntimeConfig {
                 profile: RuntimeProfile::Container,
+                ..devflow_core::config::RuntimeConfig::default()
             },
             targets: devflow_core::config::TargetsConfig {
-                profiles: std::collections::HashMap::new(),
+                profiles: Default::default(),
             },
             extensions: Some(extensions),
-            container: None,
+            // Keeps the proxy when the tests themselves run in a container.
+            container: Some(ContainerConfig {
+                nested: NestedStrategy::Dind,
+                ..Default::default()
+            }),
             cache: None,
+            extends: None,
+            preset: None,
+            concurrency: None,
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
             source_dir: None,
         };
 
@@ -604,7 +2597,7 @@ mod tests {
         registry.register(Box::new(UntrustedMockExtension));
 
         let command = cmd(PrimaryCommand::Test, Some("unit"));
-        let result = run(&cfg, &registry, &command);
+        let result = run(&cfg, &registry, &command, &RunOptions::default());
         assert!(result.is_err());
         assert!(
             result
@@ -615,6 +2608,222 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hermetic_requires_container_profile() {
+        let cfg = DevflowConfig {
+            schema_version: None,
+            project: devflow_core::config::ProjectConfig {
+                name: "hermetic-test".to_string(),
+                stack: vec![],
+                detected_stacks: Vec::new(),
+            },
+            runtime: devflow_core::config::RuntimeConfig::default(),
+            targets: devflow_core::config::TargetsConfig {
+                profiles: Default::default(),
+            },
+            extensions: None,
+            container: None,
+            cache: None,
+            extends: None,
+            preset: None,
+            concurrency: None,
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
+            source_dir: None,
+        };
+
+        let opts = RunOptions {
+            hermetic: true,
+            ..RunOptions::default()
+        };
+        let err = run(
+            &cfg,
+            &ExtensionRegistry::default(),
+            &cmd(PrimaryCommand::Build, Some("release")),
+            &opts,
+        )
+        .expect_err("hermetic on host must fail");
+        assert!(err.to_string().contains("--hermetic requires"));
+    }
+
+    #[test]
+    fn env_overrides_replace_extension_values() {
+        let mut env = std::collections::HashMap::new();
+        env.insert("CARGO_TARGET_DIR".to_string(), "target/ci".to_string());
+        let action = ExecutionAction {
+            program: "cargo".to_string(),
+            args: vec![],
+            env,
+        };
+
+        let mut opts = RunOptions::default();
+        opts.env
+            .insert("CARGO_TARGET_DIR".to_string(), "/tmp/run-1".to_string());
+        let out = with_env_overrides(action, &opts);
+        assert_eq!(
+            out.env.get("CARGO_TARGET_DIR").map(String::as_str),
+            Some("/tmp/run-1")
+        );
+    }
+
+    #[test]
+    fn nix_env_wraps_action_in_develop_shell() {
+        let out = wrap_nix_develop(ExecutionAction {
+            program: "cargo".to_string(),
+            args: vec!["build".to_string(), "--release".to_string()],
+            env: std::collections::HashMap::new(),
+        });
+        assert_eq!(out.program, "nix");
+        assert_eq!(
+            out.args,
+            vec!["develop", "--command", "cargo", "build", "--release"]
+        );
+    }
+
+    #[test]
+    fn explicit_nix_env_replaces_container_proxy() {
+        let cfg = DevflowConfig {
+            schema_version: None,
+            project: devflow_core::config::ProjectConfig {
+                name: "nix-test".to_string(),
+                stack: vec![],
+                detected_stacks: Vec::new(),
+            },
+            runtime: devflow_core::config::RuntimeConfig {
+                profile: RuntimeProfile::Container,
+                env: RuntimeEnv::Nix,
+                remote: None,
+                conflict: Default::default(),
+                env_conflict: Default::default(),
+                host_env_exclude: Vec::new(),
+                path: Default::default(),
+                path_dirs: Vec::new(),
+            },
+            targets: devflow_core::config::TargetsConfig {
+                profiles: Default::default(),
+            },
+            extensions: None,
+            container: None,
+            cache: None,
+            extends: None,
+            preset: None,
+            concurrency: None,
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
+            source_dir: None,
+        };
+        // Nix runs on the host whether or not devflow itself is nested.
+        assert!(!proxies_actions(&cfg, false));
+        assert!(!proxies_actions(&cfg, true));
+
+        let mut plain = cfg;
+        plain.runtime.env = RuntimeEnv::None;
+        assert!(proxies_actions(&plain, false));
+        assert!(!proxies_actions(&plain, true));
+    }
+
+    #[test]
+    fn extensions_map_containerized_commands_for_linux() {
+        let mut cfg = DevflowConfig::default();
+        assert_eq!(action_context(&cfg), ActionContext::host());
+        cfg.runtime.env = RuntimeEnv::Devcontainer;
+        assert_eq!(action_context(&cfg).os, TargetOs::Linux);
+    }
+
+    #[test]
+    fn workspace_relative_strips_container_mount() {
+        assert_eq!(
+            workspace_relative("/workspace/target/ci"),
+            Some("target/ci")
+        );
+        assert_eq!(workspace_relative("/workspace"), None);
+        assert_eq!(workspace_relative("/workspaces/other"), None);
+        assert_eq!(workspace_relative("/root/.npm"), None);
+    }
+
+    #[test]
+    fn host_env_maps_container_paths_onto_the_host() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        let cfg = DevflowConfig {
+            project: devflow_core::config::ProjectConfig {
+                name: "demo".to_string(),
+                stack: vec!["node".to_string()],
+                detected_stacks: Vec::new(),
+            },
+            runtime: devflow_core::config::RuntimeConfig {
+                host_env_exclude: vec!["SKIPPED".to_string()],
+                ..Default::default()
+            },
+            cache: Some(devflow_core::config::CacheConfig {
+                root: Some("/cache".to_string()),
+                ..Default::default()
+            }),
+            source_dir: Some(dir.path().to_path_buf()),
+            ..DevflowConfig::default()
+        };
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_node::NodeExtension::new()));
+        let env = [
+            ("NPM_CONFIG_CACHE", "/root/.npm"),
+            ("NPM_LOGS", "/root/.npm/_logs"),
+            ("DIST", "/workspace/dist"),
+            ("HISTFILE", "/root/.history"),
+            ("RUSTC_WRAPPER", "no-such-wrapper-xyz"),
+            ("SKIPPED", "1"),
+            ("PLAIN", "/rootless"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let action = ExecutionAction {
+            program: "npm".to_string(),
+            args: vec![],
+            env,
+        };
+
+        let out = host_env(&cfg, &registry, action, Path::new("/src/demo"));
+        let mut env: Vec<(&str, &str)> = out
+            .env
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        env.sort();
+        assert_eq!(
+            env,
+            [
+                ("DIST", "/src/demo/dist"),
+                ("NPM_CONFIG_CACHE", "/cache/node/npm"),
+                ("NPM_LOGS", "/cache/node/npm/_logs"),
+                ("PLAIN", "/rootless"),
+            ]
+        );
+
+        // `dwf env` shows the environment host runs get.
+        assert!(!uses_container_proxy(&cfg));
+        let envs = stack_environments(&cfg, &registry).unwrap();
+        assert_eq!(envs[0].1["NPM_CONFIG_CACHE"].value, "/cache/node/npm");
+        assert_eq!(envs[0].1["NPM_CONFIG_CACHE"].source, "node");
+    }
+
     #[test]
     fn sanitize_host_env_drops_workspace_and_root_paths() {
         let mut env = std::collections::HashMap::new();
@@ -632,4 +2841,457 @@ mod tests {
         assert!(!out.env.contains_key("OTHER"));
         assert_eq!(out.env.get("GOOD").map(String::as_str), Some("/home/user"));
     }
+
+    #[derive(Debug)]
+    struct EchoExtension(String);
+
+    impl devflow_core::Extension for EchoExtension {
+        fn name(&self) -> &str {
+            &self.0
+        }
+        fn capabilities(&self) -> std::collections::HashSet<String> {
+            std::collections::HashSet::from(["test:unit".to_string()])
+        }
+        fn build_action(&self, _cmd: &CommandRef) -> anyhow::Result<Option<ExecutionAction>> {
+            Ok(Some(ExecutionAction {
+                program: "echo".to_string(),
+                args: vec![self.0.clone()],
+                env: std::collections::HashMap::new(),
+            }))
+        }
+    }
+
+    fn conflict_setup(
+        priorities: &[(&str, i32)],
+        conflict: ConflictPolicy,
+    ) -> (DevflowConfig, ExtensionRegistry) {
+        use devflow_core::config::{ExtensionConfig, ExtensionSource};
+
+        let mut extensions = std::collections::HashMap::new();
+        let mut registry = ExtensionRegistry::default();
+        for (name, priority) in priorities {
+            extensions.insert(
+                name.to_string(),
+                ExtensionConfig {
+                    source: ExtensionSource::Path,
+                    path: None,
+                    version: None,
+                    api_version: None,
+                    capabilities: vec![],
+                    required: false,
+                    trusted: true,
+                    priority: *priority,
+                    fingerprint_strategy: None,
+                    mount_type: Default::default(),
+                    feature_matrix: None,
+                    wasm_targets: vec![],
+                    no_std_targets: vec![],
+                    visual: None,
+                    remote_cache: None,
+                },
+            );
+            registry.register(Box::new(EchoExtension(name.to_string())));
+        }
+        let cfg = DevflowConfig {
+            schema_version: None,
+            project: devflow_core::config::ProjectConfig {
+                name: "conflict-test".to_string(),
+                stack: vec![],
+                detected_stacks: Vec::new(),
+            },
+            runtime: devflow_core::config::RuntimeConfig {
+                conflict,
+                ..devflow_core::config::RuntimeConfig::default()
+            },
+            extensions: Some(extensions),
+            ..DevflowConfig::default()
+        };
+        (cfg, registry)
+    }
+
+    #[test]
+    fn run_raw_needs_a_command_and_a_known_stack() {
+        let (mut cfg, registry) =
+            conflict_setup(&[("rust", 0), ("python", 0)], ConflictPolicy::All);
+        cfg.runtime.profile = RuntimeProfile::Host;
+        let argv = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let opts = RunOptions::default();
+
+        let err = run_raw(&cfg, &registry, Some("rust"), &[], &opts).unwrap_err();
+        assert!(err.to_string().starts_with("nothing to run"));
+        let err = run_raw(&cfg, &registry, None, &argv(&["true"]), &opts).unwrap_err();
+        assert!(err.to_string().contains("--stack"), "{err}");
+        let err = run_raw(&cfg, &registry, Some("go"), &argv(&["true"]), &opts).unwrap_err();
+        assert!(err.to_string().starts_with("stack 'go' is not available"));
+
+        run_raw(&cfg, &registry, Some("rust"), &argv(&["true"]), &opts).unwrap();
+        assert!(run_raw(&cfg, &registry, Some("python"), &argv(&["false"]), &opts).is_err());
+    }
+
+    #[test]
+    fn reports_skips_and_fails_unexpected_ones_when_strict() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut cfg, registry) =
+            conflict_setup(&[("alpha", 10), ("beta", 0)], ConflictPolicy::First);
+        cfg.runtime.profile = RuntimeProfile::Host;
+        cfg.project.stack = vec!["go".to_string()];
+        cfg.source_dir = Some(dir.path().to_path_buf());
+        let unit = cmd(PrimaryCommand::Test, Some("unit"));
+        let inner = Reporters::default();
+        let recorder = crate::reporter::SkipRecorder::new(&inner);
+
+        let opts = RunOptions::default();
+        run_with_stats(
+            &cfg,
+            &registry,
+            &unit,
+            &opts,
+            &mut CommandStats::default(),
+            &recorder,
+        )
+        .unwrap();
+        let skips: Vec<String> = recorder.skips().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            skips,
+            [
+                "test:unit on go: manifest not found",
+                "test:unit on beta: handled by higher-precedence stack (conflict = first)"
+            ]
+        );
+
+        let strict = RunOptions {
+            strict_skips: true,
+            ..RunOptions::default()
+        };
+        let err = run_with_stats(
+            &cfg,
+            &registry,
+            &unit,
+            &strict,
+            &mut CommandStats::default(),
+            &inner,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected skips (--strict-skips):\n  test:unit on go: manifest not found"
+        );
+        cfg.project.stack.clear();
+        run_with_stats(
+            &cfg,
+            &registry,
+            &unit,
+            &strict,
+            &mut CommandStats::default(),
+            &inner,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn stacks_are_ordered_by_priority_then_name() {
+        let (cfg, _) = conflict_setup(
+            &[("zeta", 0), ("alpha", 0), ("beta", 5)],
+            ConflictPolicy::All,
+        );
+        assert_eq!(ordered_stacks(&cfg), vec!["beta", "alpha", "zeta"]);
+    }
+
+    #[test]
+    fn conflict_first_selects_highest_priority_stack() {
+        let (cfg, registry) = conflict_setup(&[("rust", 0), ("python", 10)], ConflictPolicy::First);
+        let resolution =
+            resolve(&cfg, &registry, &cmd(PrimaryCommand::Test, Some("unit"))).unwrap();
+        let selected: Vec<&str> = resolution
+            .selected
+            .iter()
+            .map(|p| p.stack.as_str())
+            .collect();
+        let shadowed: Vec<&str> = resolution
+            .shadowed
+            .iter()
+            .map(|p| p.stack.as_str())
+            .collect();
+        assert_eq!(selected, vec!["python"]);
+        assert_eq!(shadowed, vec!["rust"]);
+
+        let (cfg, registry) = conflict_setup(&[("rust", 0), ("python", 10)], ConflictPolicy::All);
+        let planned = plan(&cfg, &registry, &cmd(PrimaryCommand::Test, Some("unit"))).unwrap();
+        assert_eq!(planned.len(), 2);
+    }
+
+    #[test]
+    fn conflict_error_rejects_ambiguous_commands() {
+        let (cfg, registry) = conflict_setup(&[("rust", 0), ("python", 0)], ConflictPolicy::Error);
+        let err = resolve(&cfg, &registry, &cmd(PrimaryCommand::Test, Some("unit"))).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("command 'test:unit' is claimed by multiple stacks (python, rust)"));
+
+        let (cfg, registry) = conflict_setup(&[("rust", 0)], ConflictPolicy::Error);
+        assert!(resolve(&cfg, &registry, &cmd(PrimaryCommand::Test, Some("unit"))).is_ok());
+    }
+
+    #[derive(Debug)]
+    struct EnvExtension(&'static str, &'static str);
+
+    impl devflow_core::Extension for EnvExtension {
+        fn name(&self) -> &str {
+            self.0
+        }
+        fn capabilities(&self) -> std::collections::HashSet<String> {
+            std::collections::HashSet::new()
+        }
+        fn build_action(&self, _cmd: &CommandRef) -> anyhow::Result<Option<ExecutionAction>> {
+            Ok(None)
+        }
+        fn env_vars(&self) -> std::collections::HashMap<String, String> {
+            std::collections::HashMap::from([
+                ("CARGO_TARGET_DIR".to_string(), self.1.to_string()),
+                (format!("{}_ONLY", self.0.to_uppercase()), "1".to_string()),
+            ])
+        }
+    }
+
+    #[test]
+    fn env_conflicts_follow_precedence_or_fail() {
+        let (mut cfg, _) = conflict_setup(&[("rust", 0), ("tauri", 5)], ConflictPolicy::All);
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(EnvExtension("rust", "target/ci")));
+        registry.register(Box::new(EnvExtension("tauri", "target/tauri")));
+
+        let merged = merged_env(&cfg, &registry).expect("warn policy keeps going");
+        assert_eq!(merged.vars["CARGO_TARGET_DIR"].value, "target/tauri");
+
+        let envs = stack_environments(&cfg, &registry).unwrap();
+        let (stack, rust_env) = &envs[1];
+        assert_eq!(stack, "rust");
+        // A stack's own variables override the merged environment.
+        assert_eq!(rust_env["CARGO_TARGET_DIR"].value, "target/ci");
+        assert_eq!(rust_env["TAURI_ONLY"].source, "tauri");
+
+        cfg.runtime.env_conflict = EnvConflictPolicy::Error;
+        let err = merged_env(&cfg, &registry).unwrap_err();
+        assert!(err.to_string().contains(
+            "environment variable 'CARGO_TARGET_DIR' is set to 'target/tauri' by tauri and 'target/ci' by rust"
+        ));
+    }
+
+    #[test]
+    fn merged_env_sits_beneath_action_env() {
+        let mut merged = MergedEnv::default();
+        merged.vars.insert(
+            "CI".to_string(),
+            MergedEnvVar {
+                value: "true".to_string(),
+                source: "node".to_string(),
+            },
+        );
+        merged.vars.insert(
+            "CARGO_HOME".to_string(),
+            MergedEnvVar {
+                value: "/merged".to_string(),
+                source: "node".to_string(),
+            },
+        );
+        let action = ExecutionAction {
+            program: "cargo".to_string(),
+            args: vec![],
+            env: std::collections::HashMap::from([("CARGO_HOME".to_string(), "/own".to_string())]),
+        };
+        let out = with_merged_env(action, &merged);
+        assert_eq!(out.env["CARGO_HOME"], "/own");
+        assert_eq!(out.env["CI"], "true");
+    }
+
+    #[test]
+    fn passes_configured_entrypoint_labels_and_extra_args() {
+        let container = ContainerConfig {
+            entrypoint: Some(String::new()),
+            extra_args: vec![
+                "--privileged".to_string(),
+                "--add-host=db:10.0.0.5".to_string(),
+            ],
+            labels: [("team".to_string(), "platform".to_string())].into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            configured_run_args(&container),
+            [
+                "--entrypoint",
+                "",
+                "--label",
+                "team=platform",
+                "--privileged",
+                "--add-host=db:10.0.0.5"
+            ]
+        );
+        assert!(configured_run_args(&ContainerConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn read_only_runs_keep_outputs_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = DevflowConfig {
+            container: Some(devflow_core::config::ContainerConfig {
+                outputs: vec!["target/ci".to_string()],
+                ..Default::default()
+            }),
+            ..DevflowConfig::default()
+        };
+        let opts = RunOptions {
+            read_only: Some(Writable {
+                host: vec!["coverage/".to_string()],
+                tmpfs: vec![".pytest_cache".to_string()],
+            }),
+            ..RunOptions::default()
+        };
+        let args = writable_mounts(&cfg, &opts, dir.path()).unwrap();
+        let cwd = dir.path().display();
+        assert_eq!(
+            args,
+            [
+                "-v".to_string(),
+                format!("{cwd}/target/ci:/workspace/target/ci"),
+                "-v".to_string(),
+                format!("{cwd}/coverage/:/workspace/coverage"),
+                "--tmpfs".to_string(),
+                "/workspace/.pytest_cache".to_string(),
+            ]
+        );
+        assert!(dir.path().join(".pytest_cache").is_dir());
+        assert!(!ContainerSession::serves(&opts));
+    }
+
+    #[test]
+    fn session_execs_commands_and_stops_on_drop() {
+        let child = Command::new("cat").stdin(Stdio::piped()).spawn().unwrap();
+        let session = ContainerSession {
+            engine: "docker".to_string(),
+            name: "dwf-demo-1".to_string(),
+            cache_hit: true,
+            child,
+        };
+        let action = ExecutionAction {
+            program: "cargo".to_string(),
+            args: vec!["test".to_string()],
+            env: std::collections::HashMap::from([("CI".to_string(), "true".to_string())]),
+        };
+        let exec = session.exec(&action);
+        assert_eq!(exec.program, "docker");
+        assert_eq!(
+            exec.args,
+            [
+                "exec",
+                "-w",
+                "/workspace",
+                "-e",
+                "CI=true",
+                "dwf-demo-1",
+                "cargo",
+                "test"
+            ]
+        );
+
+        assert!(ContainerSession::serves(&RunOptions::default()));
+        let timed = RunOptions {
+            timeout: Some(Duration::from_secs(60)),
+            ..RunOptions::default()
+        };
+        assert!(!ContainerSession::serves(&timed));
+        let networked = RunOptions {
+            network: Some("dwf-fixtures".to_string()),
+            ..RunOptions::default()
+        };
+        assert!(!ContainerSession::serves(&networked));
+        // Closing the pipe ends `cat`, so dropping must not hang.
+        drop(session);
+    }
+
+    #[test]
+    fn failure_context_includes_the_extension_hint() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
+        let lockfiles = cmd(PrimaryCommand::Check, Some("lockfiles"));
+        let context = failure_context(&registry, "rust", &lockfiles);
+        assert!(
+            context
+                .starts_with("check:lockfiles failed for rust\n  hint: Cargo.lock is out of sync"),
+            "{context}"
+        );
+        let test = cmd(PrimaryCommand::Test, Some("unit"));
+        assert_eq!(
+            failure_context(&registry, "rust", &test),
+            "test:unit failed for rust"
+        );
+    }
+
+    /// Affects only changes under `src/`.
+    #[derive(Debug)]
+    struct AffectedMock(&'static str);
+
+    impl devflow_core::Extension for AffectedMock {
+        fn name(&self) -> &str {
+            self.0
+        }
+        fn capabilities(&self) -> std::collections::HashSet<String> {
+            std::collections::HashSet::from(["test:unit".to_string()])
+        }
+        fn build_action(&self, _cmd: &CommandRef) -> anyhow::Result<Option<ExecutionAction>> {
+            Ok(Some(ExecutionAction {
+                program: "echo".to_string(),
+                args: vec![self.0.to_string()],
+                env: std::collections::HashMap::new(),
+            }))
+        }
+        fn affected(
+            &self,
+            _cmd: &CommandRef,
+            action: &ExecutionAction,
+            changed: &[String],
+        ) -> Affected {
+            if !changed.iter().any(|file| file.starts_with("src/")) {
+                return Affected::Nothing;
+            }
+            let mut action = action.clone();
+            action.args.push("--affected-only".to_string());
+            Affected::Action(action)
+        }
+    }
+
+    #[test]
+    fn affected_runs_narrow_or_skip_stacks() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(AffectedMock("rust")));
+        let unit = cmd(PrimaryCommand::Test, Some("unit"));
+        let planned = |stack: &str| PlannedAction {
+            stack: stack.to_string(),
+            command: unit.clone(),
+            action: ExecutionAction {
+                program: "echo".to_string(),
+                args: vec![stack.to_string()],
+                env: std::collections::HashMap::new(),
+            },
+        };
+
+        let mut skipped = Vec::new();
+        let kept = narrow_to_affected(
+            &registry,
+            vec![planned("rust"), planned("custom")],
+            &["src/lib.rs".to_string()],
+            |stack| skipped.push(stack.to_string()),
+        );
+        assert_eq!(kept[0].action.args, ["rust", "--affected-only"]);
+        assert_eq!(kept[1].action.args, ["custom"]);
+        assert!(skipped.is_empty());
+
+        let kept = narrow_to_affected(
+            &registry,
+            vec![planned("rust")],
+            &["README.md".to_string()],
+            |stack| skipped.push(stack.to_string()),
+        );
+        assert!(kept.is_empty());
+        assert_eq!(skipped, ["rust"]);
+    }
 }
@@ -0,0 +1,329 @@
+//! Failure triage (`dwf explain-failure`).
+//!
+//! Reads the failures of the latest failed runs from the run history (the
+//! commands of one `check` run, or the separate `dwf` calls of a CI job),
+//! runs the stacks' problem matchers over each failed command's kept output
+//! and prints the errors they find, falling back to error-looking lines and
+//! then to the last lines. The view ends with where the full logs are: the
+//! spooled log, and the workflow run in GitHub Actions. `--format markdown`
+//! suits `$GITHUB_STEP_SUMMARY`.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+use devflow_core::problem::{Problem, Scanner};
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry};
+
+use crate::executor;
+use crate::history::{self, FailureRecord, RunRecord};
+
+/// Lines shown per failed command.
+const SHOWN: usize = 10;
+
+/// What a failed command's triage view shows.
+#[derive(Debug, PartialEq, Eq)]
+enum Excerpt {
+    /// Problems found by the problem matchers, errors first.
+    Problems(Vec<Problem>, usize),
+    /// Output lines mentioning an error, or else the last lines.
+    Lines(Vec<String>),
+    /// Nothing was kept, e.g. for a command whose output was not relayed.
+    Missing,
+}
+
+/// Prints the triage view of the latest failed runs in `format` (`text` or
+/// `markdown`).
+///
+/// # Errors
+/// Returns an error for an unknown format or if the history cannot be read.
+pub fn run(cfg: &DevflowConfig, registry: &ExtensionRegistry, format: &str) -> Result<()> {
+    if !matches!(format, "text" | "markdown") {
+        bail!("unknown explain-failure format '{format}' (expected text or markdown)");
+    }
+    let runs = history::latest_failures(cfg)?;
+    let run_url = crate::get_gha_target_url();
+    let failures: Vec<(&FailureRecord, Excerpt)> = runs
+        .iter()
+        .flat_map(|(_, failures)| failures)
+        .map(|failure| (failure, excerpt(cfg, registry, failure)))
+        .collect();
+    let view = if format == "markdown" {
+        markdown(&runs, &failures, run_url.as_deref())
+    } else {
+        text(&runs, &failures, run_url.as_deref())
+    };
+    print!("{view}");
+    Ok(())
+}
+
+/// The excerpt of `failure`'s output, scanned with the problem matchers the
+/// project's stacks declare for its command.
+fn excerpt(cfg: &DevflowConfig, registry: &ExtensionRegistry, failure: &FailureRecord) -> Excerpt {
+    let matchers: Vec<_> = CommandRef::from_str(&failure.command)
+        .map(|command| {
+            cfg.project
+                .stack
+                .iter()
+                .flat_map(|stack| executor::problem_matchers(registry, stack, &command))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut scanner = Scanner::new(&matchers);
+    let mut problems: Vec<Problem> = Vec::new();
+    for line in &failure.output {
+        for problem in scanner.line(line) {
+            if !problems.contains(&problem) {
+                problems.push(problem);
+            }
+        }
+    }
+    lines_or_problems(&failure.output, problems)
+}
+
+fn lines_or_problems(output: &[String], mut problems: Vec<Problem>) -> Excerpt {
+    if !problems.is_empty() {
+        problems.sort_by_key(|problem| problem.severity);
+        let total = problems.len();
+        problems.truncate(SHOWN);
+        return Excerpt::Problems(problems, total);
+    }
+    if output.is_empty() {
+        return Excerpt::Missing;
+    }
+    let mut lines: Vec<String> = output
+        .iter()
+        .filter(|line| mentions_error(line))
+        .cloned()
+        .collect();
+    lines.dedup();
+    if lines.is_empty() {
+        lines = output.to_vec();
+    }
+    let skip = lines.len().saturating_sub(SHOWN);
+    Excerpt::Lines(lines.split_off(skip))
+}
+
+/// Whether `line` has a word such as `error`, `FAILED` or `panicked`.
+fn mentions_error(line: &str) -> bool {
+    line.split(|c: char| !c.is_ascii_alphabetic()).any(|word| {
+        ERROR_WORDS
+            .iter()
+            .any(|error| word.eq_ignore_ascii_case(error))
+    })
+}
+
+const ERROR_WORDS: &[&str] = &[
+    "error", "errors", "failed", "failure", "failures", "fatal", "panicked",
+];
+
+/// `path:line:column` of a problem, as far as it is known.
+fn location(problem: &Problem) -> Option<String> {
+    let file = problem.file.as_deref()?;
+    Some(match (problem.line, problem.column) {
+        (Some(line), Some(column)) => format!("{file}:{line}:{column}"),
+        (Some(line), None) => format!("{file}:{line}"),
+        _ => file.to_string(),
+    })
+}
+
+fn problem_line(problem: &Problem) -> String {
+    let severity = problem.severity.as_str();
+    match location(problem) {
+        Some(location) => format!("{location}: {severity}: {}", problem.message),
+        None => format!("{severity}: {}", problem.message),
+    }
+}
+
+fn heading(runs: &[(RunRecord, Vec<FailureRecord>)]) -> Option<String> {
+    let invocations: Vec<&str> = runs
+        .iter()
+        .map(|(run, _)| run.invocation.as_str())
+        .collect();
+    let (first, _) = runs.first()?;
+    let commit = first
+        .git_sha
+        .as_deref()
+        .map(|sha| format!(" on {}", &sha[..sha.len().min(12)]))
+        .unwrap_or_default();
+    Some(format!("{}{commit}", invocations.join(", ")))
+}
+
+fn text(
+    runs: &[(RunRecord, Vec<FailureRecord>)],
+    failures: &[(&FailureRecord, Excerpt)],
+    run_url: Option<&str>,
+) -> String {
+    let Some(heading) = heading(runs) else {
+        return "no failed run recorded\n".to_string();
+    };
+    let mut out = format!("failed: {heading}\n");
+    if failures.is_empty() {
+        out.push_str("\nno command output was recorded for the failure\n");
+    }
+    for (failure, excerpt) in failures {
+        out.push_str(&format!("\n{}\n", failure.command));
+        for line in failure.error.lines() {
+            out.push_str(&format!("  {line}\n"));
+        }
+        match excerpt {
+            Excerpt::Problems(problems, total) => {
+                for problem in problems {
+                    out.push_str(&format!("  > {}\n", problem_line(problem)));
+                }
+                if *total > problems.len() {
+                    out.push_str(&format!("  ... and {} more\n", total - problems.len()));
+                }
+            }
+            Excerpt::Lines(lines) => {
+                for line in lines {
+                    out.push_str(&format!("  | {line}\n"));
+                }
+            }
+            Excerpt::Missing => out.push_str("  (no output was captured)\n"),
+        }
+        if let Some(log) = &failure.log {
+            out.push_str(&format!("  full log: {log}\n"));
+        }
+    }
+    if let Some(url) = run_url {
+        out.push_str(&format!("\nworkflow run: {url}\n"));
+    }
+    out
+}
+
+fn markdown(
+    runs: &[(RunRecord, Vec<FailureRecord>)],
+    failures: &[(&FailureRecord, Excerpt)],
+    run_url: Option<&str>,
+) -> String {
+    let Some(heading) = heading(runs) else {
+        return "No failed run recorded.\n".to_string();
+    };
+    let mut out = format!("## Failed: {heading}\n");
+    for (failure, excerpt) in failures {
+        out.push_str(&format!(
+            "\n### `{}`\n\n```text\n{}\n```\n",
+            failure.command, failure.error
+        ));
+        match excerpt {
+            Excerpt::Problems(problems, total) => {
+                out.push('\n');
+                for problem in problems {
+                    let message = match location(problem) {
+                        Some(location) => format!("`{location}` {}", problem.message),
+                        None => problem.message.clone(),
+                    };
+                    out.push_str(&format!("- **{}** {message}\n", problem.severity.as_str()));
+                }
+                if *total > problems.len() {
+                    out.push_str(&format!("- ... and {} more\n", total - problems.len()));
+                }
+            }
+            Excerpt::Lines(lines) => {
+                out.push_str(&format!("\n```text\n{}\n```\n", lines.join("\n")));
+            }
+            Excerpt::Missing => out.push_str("\nNo output was captured.\n"),
+        }
+        if let Some(log) = &failure.log {
+            out.push_str(&format!("\nFull log: `{log}`\n"));
+        }
+    }
+    if let Some(url) = run_url {
+        out.push_str(&format!("\n[Workflow run]({url})\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::problem::Severity;
+
+    fn run_record() -> RunRecord {
+        RunRecord {
+            id: 1,
+            started_at: 0,
+            invocation: "check:pr".to_string(),
+            git_sha: Some("0123456789abcdef".to_string()),
+            duration_ms: 1_000,
+            success: false,
+        }
+    }
+
+    fn failure(output: &[&str]) -> FailureRecord {
+        FailureRecord {
+            command: "test:unit".to_string(),
+            error: "test:unit failed for rust".to_string(),
+            output: output.iter().map(|line| line.to_string()).collect(),
+            log: Some(".cache/devflow/logs/test-unit.log.gz".to_string()),
+        }
+    }
+
+    fn problem(severity: Severity, message: &str) -> Problem {
+        Problem {
+            owner: "rustc".to_string(),
+            severity,
+            message: message.to_string(),
+            file: Some("src/lib.rs".to_string()),
+            line: Some(3),
+            column: Some(9),
+        }
+    }
+
+    #[test]
+    fn prefers_problems_then_error_lines_then_the_tail() {
+        let problems = lines_or_problems(
+            &[],
+            vec![
+                problem(Severity::Warning, "unused variable"),
+                problem(Severity::Error, "mismatched types"),
+            ],
+        );
+        let Excerpt::Problems(found, 2) = &problems else {
+            panic!("{problems:?}");
+        };
+        assert_eq!(found[0].message, "mismatched types");
+
+        let output: Vec<String> = [
+            "running 2 tests",
+            "test a ... ok",
+            "test b ... FAILED",
+            "done",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(
+            lines_or_problems(&output, Vec::new()),
+            Excerpt::Lines(vec!["test b ... FAILED".to_string()])
+        );
+        let quiet: Vec<String> = (0..15).map(|i| format!("line {i}")).collect();
+        let Excerpt::Lines(tail) = lines_or_problems(&quiet, Vec::new()) else {
+            panic!("expected lines");
+        };
+        assert_eq!(tail.len(), SHOWN);
+        assert_eq!(tail[0], "line 5");
+        assert_eq!(lines_or_problems(&[], Vec::new()), Excerpt::Missing);
+    }
+
+    #[test]
+    fn renders_text_and_markdown_views() {
+        let record = run_record();
+        let failed = failure(&["test b ... FAILED"]);
+        let runs = vec![(record.clone(), vec![failed.clone()])];
+        let failures = vec![(
+            &failed,
+            Excerpt::Problems(vec![problem(Severity::Error, "mismatched types")], 1),
+        )];
+        let url = Some("https://github.com/o/r/actions/runs/1");
+        assert_eq!(
+            text(&runs, &failures, url),
+            "failed: check:pr on 0123456789ab\n\ntest:unit\n  test:unit failed for rust\n  > src/lib.rs:3:9: error: mismatched types\n  full log: .cache/devflow/logs/test-unit.log.gz\n\nworkflow run: https://github.com/o/r/actions/runs/1\n"
+        );
+        assert_eq!(
+            markdown(&runs, &failures, url),
+            "## Failed: check:pr on 0123456789ab\n\n### `test:unit`\n\n```text\ntest:unit failed for rust\n```\n\n- **error** `src/lib.rs:3:9` mismatched types\n\nFull log: `.cache/devflow/logs/test-unit.log.gz`\n\n[Workflow run](https://github.com/o/r/actions/runs/1)\n"
+        );
+        assert_eq!(text(&[], &[], None), "no failed run recorded\n");
+    }
+}
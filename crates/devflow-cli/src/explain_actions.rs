@@ -0,0 +1,315 @@
+//! Recorded execution actions (`dwf explain`).
+//!
+//! Every command run keeps, per stack, the action it resolved to (program,
+//! arguments, environment and image) and its hash in the run history.
+//! `dwf explain test:unit` shows those of the latest run; `--diff` compares
+//! them with the last run that passed before it (or the previous run when
+//! none did), so a sudden failure can be traced to e.g. a bumped image tag
+//! or a new linter flag. `last` stands for the command recorded last.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use devflow_core::{CommandRef, DevflowConfig};
+
+use crate::history::{self, ActionRecord, ActionRun};
+use crate::remote::shell_quote;
+
+/// Hash characters shown.
+const SHORT_HASH: usize = 12;
+
+/// Prints the recorded actions of `command`, or with `diff` what changed in
+/// them since it last passed.
+///
+/// # Errors
+/// Returns an error for an invalid command or if the history cannot be read.
+pub fn run(cfg: &DevflowConfig, command: &str, diff: bool) -> Result<()> {
+    let name = if command == "last" {
+        match history::last_action_command(cfg)? {
+            Some(name) => name,
+            None => {
+                println!("no command run recorded");
+                return Ok(());
+            }
+        }
+    } else {
+        let cmd = CommandRef::from_str(command)
+            .map_err(|e| anyhow!("invalid command '{command}': {e}"))?;
+        let selector = cmd
+            .selector
+            .unwrap_or_else(|| cmd.primary.default_selector().to_string());
+        format!("{}:{}", cmd.primary.as_str(), selector)
+    };
+    let runs = history::action_runs(cfg, &name)?;
+    let view = match (runs.first(), diff) {
+        (None, _) => format!("no recorded run of {name}\n"),
+        (Some(latest), false) => show(&name, latest),
+        (Some(latest), true) => match baseline(&runs) {
+            Some(base) => changes(&name, latest, base),
+            None => format!("{name} has only one recorded run, nothing to compare\n"),
+        },
+    };
+    print!("{view}");
+    Ok(())
+}
+
+/// The run `runs[0]` is compared with: the newest earlier run that passed,
+/// or else the one right before it.
+fn baseline(runs: &[ActionRun]) -> Option<&ActionRun> {
+    let earlier = runs.get(1..)?;
+    earlier
+        .iter()
+        .find(|run| run.success)
+        .or_else(|| earlier.first())
+}
+
+fn describe(run: &ActionRun) -> String {
+    let outcome = if run.success { "passed" } else { "failed" };
+    let commit = run
+        .run
+        .git_sha
+        .as_deref()
+        .map(|sha| format!(" on {}", &sha[..sha.len().min(SHORT_HASH)]))
+        .unwrap_or_default();
+    format!(
+        "{outcome} run {} ({}{commit})",
+        run.run.id, run.run.invocation
+    )
+}
+
+fn short(action: &ActionRecord) -> String {
+    action.hash()[..SHORT_HASH].to_string()
+}
+
+fn command_line(action: &ActionRecord) -> String {
+    std::iter::once(&action.program)
+        .chain(&action.args)
+        .map(|word| shell_quote(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn show(name: &str, latest: &ActionRun) -> String {
+    let mut out = format!("{name}: {}\n", describe(latest));
+    for action in &latest.actions {
+        out.push_str(&format!("\n{} [{}]\n", action.stack, short(action)));
+        out.push_str(&format!("  run: {}\n", command_line(action)));
+        if let Some(image) = &action.image {
+            out.push_str(&format!("  image: {image}\n"));
+        }
+        for (key, value) in &action.env {
+            out.push_str(&format!("  env: {key}={value}\n"));
+        }
+    }
+    out
+}
+
+fn changes(name: &str, latest: &ActionRun, base: &ActionRun) -> String {
+    let mut out = format!("{name}: {} since {}\n", describe(latest), describe(base));
+    let by_stack = |run: &ActionRun| -> BTreeMap<String, ActionRecord> {
+        run.actions
+            .iter()
+            .map(|action| (action.stack.clone(), action.clone()))
+            .collect()
+    };
+    let (before, after) = (by_stack(base), by_stack(latest));
+    let mut changed = false;
+    for stack in before
+        .keys()
+        .chain(after.keys().filter(|s| !before.contains_key(*s)))
+    {
+        match (before.get(stack), after.get(stack)) {
+            (Some(old), Some(new)) if old.hash() == new.hash() => {
+                out.push_str(&format!("\n{stack}: unchanged [{}]\n", short(new)));
+            }
+            (Some(old), Some(new)) => {
+                changed = true;
+                out.push_str(&format!(
+                    "\n{stack}: changed [{} -> {}]\n",
+                    short(old),
+                    short(new)
+                ));
+                for line in action_changes(old, new) {
+                    out.push_str(&format!("  {line}\n"));
+                }
+            }
+            (None, Some(new)) => {
+                changed = true;
+                out.push_str(&format!("\n{stack}: added\n  run: {}\n", command_line(new)));
+            }
+            (Some(_), None) => {
+                changed = true;
+                out.push_str(&format!("\n{stack}: no longer runs\n"));
+            }
+            (None, None) => {}
+        }
+    }
+    if !changed {
+        out.push_str("\nnothing devflow runs changed; look at the sources and tools instead\n");
+    }
+    out
+}
+
+/// What differs between `old` and `new`, one line each.
+fn action_changes(old: &ActionRecord, new: &ActionRecord) -> Vec<String> {
+    let mut lines = Vec::new();
+    if old.image != new.image {
+        let image = |image: &Option<String>| image.clone().unwrap_or_else(|| "(host)".into());
+        lines.push(format!(
+            "image: {} -> {}",
+            image(&old.image),
+            image(&new.image)
+        ));
+    }
+    if old.program != new.program {
+        lines.push(format!("program: {} -> {}", old.program, new.program));
+    }
+    let removed = missing_from(&old.args, &new.args);
+    let added = missing_from(&new.args, &old.args);
+    for arg in &removed {
+        lines.push(format!("args: - {}", shell_quote(arg)));
+    }
+    for arg in &added {
+        lines.push(format!("args: + {}", shell_quote(arg)));
+    }
+    if removed.is_empty() && added.is_empty() && old.args != new.args {
+        lines.push(format!(
+            "args reordered: {} -> {}",
+            command_line(old),
+            command_line(new)
+        ));
+    }
+    for (key, value) in &old.env {
+        match new.env.get(key) {
+            None => lines.push(format!("env: - {key}={value}")),
+            Some(new_value) if new_value != value => {
+                lines.push(format!("env: {key}: {value} -> {new_value}"));
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, value) in &new.env {
+        if !old.env.contains_key(key) {
+            lines.push(format!("env: + {key}={value}"));
+        }
+    }
+    lines
+}
+
+/// The entries of `items` that `other` lacks, counting repeats.
+fn missing_from(items: &[String], other: &[String]) -> Vec<String> {
+    let mut rest: Vec<&String> = other.iter().collect();
+    items
+        .iter()
+        .filter(
+            |item| match rest.iter().position(|candidate| candidate == item) {
+                Some(index) => {
+                    rest.remove(index);
+                    false
+                }
+                None => true,
+            },
+        )
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::RunRecord;
+
+    fn action(args: &[&str], env: &[(&str, &str)], image: &str) -> ActionRecord {
+        ActionRecord {
+            stack: "rust".to_string(),
+            program: "cargo".to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: env
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            image: Some(image.to_string()),
+        }
+    }
+
+    fn action_run(id: i64, success: bool, actions: Vec<ActionRecord>) -> ActionRun {
+        ActionRun {
+            run: RunRecord {
+                id,
+                started_at: id as u64,
+                invocation: "check:pr".to_string(),
+                git_sha: Some(format!("{id:0>16}")),
+                duration_ms: 1_000,
+                success,
+            },
+            success,
+            actions,
+        }
+    }
+
+    #[test]
+    fn compares_with_the_last_passing_run() {
+        let old = action(
+            &["clippy", "--", "-D", "warnings"],
+            &[("RUSTFLAGS", "-Cdebuginfo=0"), ("CI", "true")],
+            "ghcr.io/devflow/ci:1.2",
+        );
+        let new = action(
+            &["clippy", "--", "-D", "warnings", "-W", "clippy::pedantic"],
+            &[("RUSTFLAGS", "-Cdebuginfo=1"), ("RUST_LOG", "debug")],
+            "ghcr.io/devflow/ci:1.3",
+        );
+        let runs = [
+            action_run(3, false, vec![new.clone()]),
+            action_run(2, false, vec![new.clone()]),
+            action_run(1, true, vec![old.clone()]),
+        ];
+        let base = baseline(&runs).unwrap();
+        assert_eq!(base.run.id, 1);
+        assert_eq!(
+            action_changes(&old, &new),
+            [
+                "image: ghcr.io/devflow/ci:1.2 -> ghcr.io/devflow/ci:1.3",
+                "args: + -W",
+                "args: + clippy::pedantic",
+                "env: - CI=true",
+                "env: RUSTFLAGS: -Cdebuginfo=0 -> -Cdebuginfo=1",
+                "env: + RUST_LOG=debug",
+            ]
+        );
+        let text = changes("lint:static", &runs[0], base);
+        assert!(text.starts_with(
+            "lint:static: failed run 3 (check:pr on 000000000000) since passed run 1 (check:pr on 000000000000)\n"
+        ), "{text}");
+        assert!(text.contains(&format!(
+            "\nrust: changed [{} -> {}]\n  image: ",
+            short(&old),
+            short(&new)
+        )));
+
+        let same = changes("lint:static", &runs[0], &runs[1]);
+        assert!(same.contains("\nrust: unchanged ["));
+        assert!(
+            same.ends_with("nothing devflow runs changed; look at the sources and tools instead\n")
+        );
+        assert!(baseline(&runs[..1]).is_none());
+    }
+
+    #[test]
+    fn shows_the_latest_actions() {
+        let latest = action_run(
+            7,
+            true,
+            vec![action(&["test", "--workspace"], &[("CI", "true")], "ci:1")],
+        );
+        let text = show("test:unit", &latest);
+        assert!(text.starts_with("test:unit: passed run 7 (check:pr on 000000000000)\n\nrust ["));
+        assert!(text.ends_with("]\n  run: cargo test --workspace\n  image: ci:1\n  env: CI=true\n"));
+        assert_eq!(
+            missing_from(&["-v".to_string(), "-v".to_string()], &["-v".to_string()]),
+            ["-v"]
+        );
+    }
+}
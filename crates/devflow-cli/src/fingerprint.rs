@@ -0,0 +1,174 @@
+//! Environment fingerprint reporting (`dwf fingerprint`).
+//!
+//! The fingerprint keys the CI container image: it covers every extension's
+//! fingerprint inputs, `[container] fingerprint_inputs`, and the CI Dockerfile.
+//! Each extension's inputs are hashed with its `fingerprint_strategy`; the
+//! others with `[cache] fingerprint_strategy`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use devflow_core::constants::CI_DOCKERFILE;
+use devflow_core::fingerprint::{compute_fingerprint_with, FingerprintStrategy};
+use devflow_core::{DevflowConfig, ExtensionRegistry};
+
+/// Returns the sorted, de-duplicated files hashed into the project
+/// fingerprint, with their strategy. A file declared with both strategies is
+/// hashed by content.
+pub fn inputs(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+) -> Vec<(String, FingerprintStrategy)> {
+    let mut inputs = BTreeMap::new();
+    let mut add = |input: String, strategy| {
+        inputs
+            .entry(input)
+            .and_modify(|kept| {
+                if strategy == FingerprintStrategy::Content {
+                    *kept = strategy;
+                }
+            })
+            .or_insert(strategy);
+    };
+    for name in registry.names() {
+        let strategy = cfg.fingerprint_strategy(Some(name));
+        for input in registry
+            .get(name)
+            .map(|ext| ext.fingerprint_inputs())
+            .unwrap_or_default()
+        {
+            add(input, strategy);
+        }
+    }
+    let strategy = cfg.fingerprint_strategy(None);
+    if let Some(container) = &cfg.container {
+        for input in &container.fingerprint_inputs {
+            add(input.clone(), strategy);
+        }
+    }
+    add(CI_DOCKERFILE.to_string(), strategy);
+    inputs.into_iter().collect()
+}
+
+/// Computes the project fingerprint of the files under `dir`.
+///
+/// # Errors
+/// Returns an error if an input exists but cannot be read.
+pub fn compute(cfg: &DevflowConfig, registry: &ExtensionRegistry, dir: &Path) -> Result<String> {
+    compute_fingerprint_with(dir, &inputs(cfg, registry))
+}
+
+/// Options for `dwf fingerprint`.
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintOptions {
+    /// List the hashed and missing input files.
+    pub show_inputs: bool,
+    /// Expected aggregate fingerprint; a mismatch is an error.
+    pub check: Option<String>,
+}
+
+/// Prints the aggregate fingerprint, then one line per extension.
+///
+/// The aggregate stays alone on the first line so scripts can read it with
+/// `head -n 1`.
+pub fn run(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    opts: &FingerprintOptions,
+) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let inputs = inputs(cfg, registry);
+    let aggregate = compute_fingerprint_with(&cwd, &inputs)?;
+
+    if let Some(expected) = &opts.check {
+        verify(expected, &aggregate)?;
+    }
+
+    println!("{aggregate}");
+    for stack in &cfg.project.stack {
+        if let Some(ext) = registry.get(stack) {
+            let strategy = cfg.fingerprint_strategy(Some(stack));
+            let inputs: Vec<(String, FingerprintStrategy)> = ext
+                .fingerprint_inputs()
+                .into_iter()
+                .map(|input| (input, strategy))
+                .collect();
+            let hash = compute_fingerprint_with(&cwd, &inputs)?;
+            println!("{stack:<12} {hash}");
+        }
+    }
+
+    if opts.show_inputs {
+        println!("inputs:");
+        for (input, strategy) in &inputs {
+            let status = match (cwd.join(input).is_file(), strategy) {
+                (false, _) => "missing",
+                (true, FingerprintStrategy::Git) => "git",
+                (true, FingerprintStrategy::Content) => "hashed",
+            };
+            println!("  {status:<8} {input}");
+        }
+    }
+    Ok(())
+}
+
+/// Fails unless `expected` (case-insensitive, surrounding whitespace ignored) equals `actual`.
+fn verify(expected: &str, actual: &str) -> Result<()> {
+    let expected = expected.trim().to_ascii_lowercase();
+    if expected != actual {
+        bail!(
+            "fingerprint mismatch: expected {}, computed {}",
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::config::{ContainerConfig, ProjectConfig};
+
+    #[test]
+    fn inputs_include_container_inputs_and_ci_dockerfile() {
+        let cfg = DevflowConfig {
+            schema_version: None,
+            project: ProjectConfig {
+                name: "demo".to_string(),
+                stack: vec![],
+                detected_stacks: Vec::new(),
+            },
+            container: Some(ContainerConfig {
+                fingerprint_inputs: vec![
+                    "rust-toolchain.toml".to_string(),
+                    CI_DOCKERFILE.to_string(),
+                ],
+                ..ContainerConfig::default()
+            }),
+            ..DevflowConfig::default()
+        };
+
+        assert_eq!(
+            inputs(&cfg, &ExtensionRegistry::default()),
+            vec![
+                (CI_DOCKERFILE.to_string(), FingerprintStrategy::Content),
+                (
+                    "rust-toolchain.toml".to_string(),
+                    FingerprintStrategy::Content
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_accepts_matching_hash_only() {
+        verify(" ABC123\n", "abc123").expect("normalized hash should match");
+        let err = verify("abc", "def").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("fingerprint mismatch: expected abc, computed def"));
+    }
+}
@@ -0,0 +1,214 @@
+//! `ci:plan --format dot|mermaid`: a profile's job and command graph.
+//!
+//! Nodes are the jobs of the generated workflow, linked by `needs`, and the
+//! commands each verify job runs in parallel, labelled with the stacks that
+//! handle them.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry};
+use devflow_gh::jobs::profile_jobs;
+
+use crate::executor;
+
+/// A graph description language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz DOT.
+    Dot,
+    /// A Mermaid flowchart.
+    Mermaid,
+}
+
+impl GraphFormat {
+    /// Parses `dot` or `mermaid`; any other format is not a graph.
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "dot" => Some(Self::Dot),
+            "mermaid" => Some(Self::Mermaid),
+            _ => None,
+        }
+    }
+}
+
+struct Node {
+    id: String,
+    label: String,
+    command: bool,
+}
+
+/// Renders the graph of `profile` in `format`.
+///
+/// # Errors
+/// Returns an error if `profile` is unknown or a command cannot be resolved.
+pub fn render(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    profile: &str,
+    format: GraphFormat,
+) -> Result<String> {
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for job in profile_jobs(cfg, profile)? {
+        for need in &job.needs {
+            edges.push((need.clone(), job.id.clone()));
+        }
+        for entry in &job.entries {
+            let cmd = CommandRef::from_str(entry.command()).map_err(|e| anyhow!(e))?;
+            let id = format!("{}_{}", job.id, entry.command().replace([':', '-'], "_"));
+            let mut label = format!("{} ({})", cmd, stacks(cfg, registry, &cmd)?);
+            if !entry.paths().is_empty() {
+                label.push_str(&format!(" if {} changed", entry.paths().join(", ")));
+            }
+            edges.push((job.id.clone(), id.clone()));
+            nodes.push(Node {
+                id,
+                label,
+                command: true,
+            });
+        }
+        nodes.push(Node {
+            id: job.id,
+            label: job.name,
+            command: false,
+        });
+    }
+    // Jobs before their commands, in workflow order.
+    nodes.sort_by_key(|node| node.command);
+    Ok(match format {
+        GraphFormat::Dot => dot(profile, &nodes, &edges),
+        GraphFormat::Mermaid => mermaid(&nodes, &edges),
+    })
+}
+
+/// The stacks handling `cmd`, or `no stack`.
+fn stacks(cfg: &DevflowConfig, registry: &ExtensionRegistry, cmd: &CommandRef) -> Result<String> {
+    let resolution = executor::resolve(cfg, registry, cmd)?;
+    if resolution.selected.is_empty() {
+        return Ok("no stack".to_string());
+    }
+    Ok(resolution
+        .selected
+        .iter()
+        .map(|p| p.stack.as_str())
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+fn dot(profile: &str, nodes: &[Node], edges: &[(String, String)]) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut out = format!(
+        "digraph {} {{\n    rankdir=LR;\n    node [shape=box];\n",
+        quote(profile)
+    );
+    for node in nodes {
+        let style = if node.command { ", style=rounded" } else { "" };
+        out.push_str(&format!(
+            "    {} [label={}{style}];\n",
+            node.id,
+            quote(&node.label)
+        ));
+    }
+    for (from, to) in edges {
+        out.push_str(&format!("    {from} -> {to};\n"));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn mermaid(nodes: &[Node], edges: &[(String, String)]) -> String {
+    let mut out = String::from("flowchart LR\n");
+    for node in nodes {
+        let label = node.label.replace('"', "#quot;");
+        let (open, close) = if node.command { ("(", ")") } else { ("[", "]") };
+        out.push_str(&format!("    {}{open}\"{label}\"{close}\n", node.id));
+    }
+    for (from, to) in edges {
+        out.push_str(&format!("    {from} --> {to}\n"));
+    }
+    out
+}
+
+/// Checks `format` for `ci:plan`: `text` or a [`GraphFormat`].
+///
+/// # Errors
+/// Returns an error for any other format.
+pub fn plan_format(format: &str) -> Result<Option<GraphFormat>> {
+    match (format, GraphFormat::parse(format)) {
+        ("text", _) => Ok(None),
+        (_, Some(graph)) => Ok(Some(graph)),
+        _ => bail!(
+            "unknown ci:plan format '{}' (expected text, dot or mermaid)",
+            format
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DevflowConfig {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("devflow.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = ["fmt:check", { cmd = "test:integration", paths = ["src/db/**"] }]
+
+            [ci.runners]
+            "test:integration" = "ubuntu-24.04-arm"
+            "#,
+        )
+        .unwrap();
+        DevflowConfig::load_from_file(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn renders_jobs_and_commands() {
+        let cfg = config();
+        let registry = ExtensionRegistry::default();
+
+        let dot = render(&cfg, &registry, "pr", GraphFormat::Dot).unwrap();
+        assert!(dot.starts_with("digraph \"pr\" {\n"), "{dot}");
+        assert!(dot.contains("    prep [label=\"Prep\"];\n"), "{dot}");
+        assert!(
+            dot.contains("    verify_fmt_check [label=\"fmt:check (no stack)\", style=rounded];\n")
+        );
+        assert!(
+            dot.contains("    prep -> build;\n    prep -> verify;\n    build -> verify;\n"),
+            "{dot}"
+        );
+        assert!(dot
+            .contains("    check_test_integration -> check_test_integration_test_integration;\n"));
+
+        let mermaid = render(&cfg, &registry, "pr", GraphFormat::Mermaid).unwrap();
+        assert!(
+            mermaid.starts_with("flowchart LR\n    prep[\"Prep\"]\n"),
+            "{mermaid}"
+        );
+        assert!(mermaid.contains(
+            "    check_test_integration_test_integration(\"test:integration (no stack) if src/db/** changed\")\n"
+        ));
+        assert!(
+            mermaid.contains("    verify --> verify_fmt_check\n"),
+            "{mermaid}"
+        );
+
+        assert!(render(&cfg, &registry, "nightly", GraphFormat::Dot).is_err());
+    }
+
+    #[test]
+    fn accepts_text_and_graph_formats() {
+        assert_eq!(plan_format("text").unwrap(), None);
+        assert_eq!(plan_format("mermaid").unwrap(), Some(GraphFormat::Mermaid));
+        assert!(plan_format("csv").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Git state guards run before commands that rewrite the tree or ship it.
+//!
+//! Each command has a policy from its `[commands.<command>]` entry:
+//! `require_clean` refuses to run with uncommitted changes to tracked files
+//! (on by default for `fmt:fix`, which would mix its rewrite into them, and
+//! `release:*`), and `protected_branches` refuses to run on those branches.
+//! `--allow-dirty` skips both. Outside a git work tree nothing is checked.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use tracing::debug;
+
+use devflow_core::{CommandRef, DevflowConfig, PrimaryCommand};
+
+use crate::changes;
+
+/// What is checked before a command runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    pub require_clean: bool,
+    pub protected_branches: Vec<String>,
+}
+
+impl Policy {
+    /// The policy of `cmd`: its `[commands]` entry over the defaults.
+    pub fn of(cfg: &DevflowConfig, cmd: &CommandRef) -> Self {
+        let settings = cfg.command_config(cmd);
+        let rewrites = cmd.primary == PrimaryCommand::Fmt
+            && cmd
+                .selector
+                .as_deref()
+                .unwrap_or(cmd.primary.default_selector())
+                == "fix";
+        Self {
+            require_clean: settings
+                .and_then(|settings| settings.require_clean)
+                .unwrap_or(rewrites || cmd.primary == PrimaryCommand::Release),
+            protected_branches: settings
+                .map(|settings| settings.protected_branches.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.require_clean && self.protected_branches.is_empty()
+    }
+}
+
+/// The directory git runs in: the source directory, else the current one.
+///
+/// # Errors
+/// Returns an error if the current directory cannot be read.
+pub fn workspace(cfg: &DevflowConfig) -> Result<PathBuf> {
+    Ok(match cfg.source_dir.as_deref() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => std::env::current_dir()?,
+    })
+}
+
+/// Tracked files with uncommitted changes, staged or not.
+///
+/// # Errors
+/// Returns an error if git cannot tell.
+pub fn dirty_files(workspace: &Path) -> Result<Vec<String>> {
+    let status = changes::git(
+        workspace,
+        &["status", "--porcelain", "--untracked-files=no"],
+    )?;
+    Ok(status
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(str::to_string)
+        .collect())
+}
+
+/// The checked-out branch, or `None` for a detached `HEAD`.
+///
+/// # Errors
+/// Returns an error if git cannot tell.
+pub fn current_branch(workspace: &Path) -> Result<Option<String>> {
+    let branch = changes::git(workspace, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    Ok(Some(branch.trim().to_string()).filter(|branch| branch != "HEAD"))
+}
+
+/// What keeps `policy` from letting a command run in `workspace`, one line
+/// each.
+///
+/// # Errors
+/// Returns an error if git cannot tell.
+pub fn issues(workspace: &Path, policy: &Policy) -> Result<Vec<String>> {
+    let mut issues = Vec::new();
+    if policy.require_clean {
+        let files = dirty_files(workspace)?;
+        if !files.is_empty() {
+            issues.push(format!(
+                "the working tree has uncommitted changes: {}",
+                files.join(", ")
+            ));
+        }
+    }
+    if !policy.protected_branches.is_empty() {
+        if let Some(branch) = current_branch(workspace)? {
+            if policy.protected_branches.contains(&branch) {
+                issues.push(format!("'{branch}' is a protected branch"));
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// Refuses `cmd` if its policy does not hold, unless `allow_dirty`.
+///
+/// # Errors
+/// Returns an error naming what to fix if a guard fails.
+pub fn check(cfg: &DevflowConfig, cmd: &CommandRef, allow_dirty: bool) -> Result<()> {
+    let policy = Policy::of(cfg, cmd);
+    if allow_dirty || policy.is_empty() {
+        return Ok(());
+    }
+    let workspace = workspace(cfg)?;
+    if changes::git(&workspace, &["rev-parse", "--is-inside-work-tree"]).is_err() {
+        debug!("{cmd}: not in a git work tree, skipping the git guards");
+        return Ok(());
+    }
+    let issues = issues(&workspace, &policy)?;
+    if !issues.is_empty() {
+        bail!(
+            "refusing to run {}:\n  - {}\ncommit or stash the changes, or pass --allow-dirty",
+            cmd.canonical(),
+            issues.join("\n  - ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::str::FromStr;
+
+    fn git(dir: &Path, args: &[&str]) {
+        changes::git(dir, args).unwrap();
+    }
+
+    #[test]
+    fn refuses_dirty_trees_and_protected_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.email", "dev@example.com"]);
+        git(path, &["config", "user.name", "dev"]);
+        fs::write(path.join("lib.rs"), "fn main() {}\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "init"]);
+        let mut cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [commands.fmt]
+            protected_branches = ["main"]
+
+            [commands."release:verify"]
+            require_clean = false
+            "#,
+        )
+        .unwrap();
+        cfg.source_dir = Some(path.to_path_buf());
+        let cmd = |text: &str| CommandRef::from_str(text).unwrap();
+
+        assert_eq!(
+            Policy::of(&cfg, &cmd("fmt:fix")),
+            Policy {
+                require_clean: true,
+                protected_branches: vec!["main".to_string()],
+            }
+        );
+        assert!(!Policy::of(&cfg, &cmd("fmt:check")).require_clean);
+        assert!(Policy::of(&cfg, &cmd("release")).require_clean);
+        assert!(!Policy::of(&cfg, &cmd("release:verify")).require_clean);
+
+        fs::write(path.join("lib.rs"), "fn main() { }\n").unwrap();
+        fs::write(path.join("notes.txt"), "untracked\n").unwrap();
+        let err = check(&cfg, &cmd("fmt:fix"), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "refusing to run fmt:fix:\n  - the working tree has uncommitted changes: lib.rs\n  \
+             - 'main' is a protected branch\ncommit or stash the changes, or pass --allow-dirty"
+        );
+        check(&cfg, &cmd("fmt:fix"), true).unwrap();
+        check(&cfg, &cmd("test:unit"), false).unwrap();
+
+        git(path, &["checkout", "-q", "-b", "feature"]);
+        git(path, &["commit", "-q", "-am", "wip"]);
+        check(&cfg, &cmd("fmt:fix"), false).unwrap();
+        check(&cfg, &cmd("release:candidate"), false).unwrap();
+
+        let outside = tempfile::tempdir().unwrap();
+        cfg.source_dir = Some(outside.path().to_path_buf());
+        check(&cfg, &cmd("fmt:fix"), false).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+//! Workspace escape detection for hermetic runs.
+//!
+//! In hermetic mode the workspace is mounted read-only except for declared output
+//! directories. As a second line of defence, Devflow snapshots the workspace
+//! before and after each command and fails if anything outside the allowed
+//! paths was created, modified, or removed.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+
+/// Paths that are never considered part of the hermetic surface.
+const ALWAYS_EXCLUDED: &[&str] = &[".git"];
+
+/// File metadata captured per workspace path (size, modification time).
+type Entry = (u64, Option<SystemTime>);
+
+/// A point-in-time view of the files in a workspace.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Snapshot {
+    /// Walks `root`, skipping any path under one of the `allowed` prefixes.
+    ///
+    /// # Errors
+    /// Returns an error if a directory in the workspace cannot be read.
+    pub fn capture(root: &Path, allowed: &[String]) -> Result<Self> {
+        let mut snapshot = Self::default();
+        snapshot.walk(root, root, allowed)?;
+        Ok(snapshot)
+    }
+
+    fn walk(&mut self, root: &Path, dir: &Path, allowed: &[String]) -> Result<()> {
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("failed to read directory {}", dir.display()))?;
+
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let rel = relative_path(root, &path);
+            if is_allowed(&rel, allowed) {
+                continue;
+            }
+
+            let meta = entry.metadata()?;
+            if meta.is_dir() {
+                self.walk(root, &path, allowed)?;
+            } else {
+                self.entries.insert(rel, (meta.len(), meta.modified().ok()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lists paths that differ between `self` (before) and `after`.
+    pub fn changed_paths(&self, after: &Snapshot) -> Vec<String> {
+        let mut changed: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(path, entry)| after.entries.get(*path) != Some(entry))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        changed.extend(
+            after
+                .entries
+                .keys()
+                .filter(|path| !self.entries.contains_key(*path))
+                .cloned(),
+        );
+        changed.sort();
+        changed
+    }
+}
+
+/// Fails if the workspace changed outside the allowed output paths.
+pub fn verify_unchanged(before: &Snapshot, after: &Snapshot) -> Result<()> {
+    let changed = before.changed_paths(after);
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    const SHOWN: usize = 10;
+    let mut listing = changed
+        .iter()
+        .take(SHOWN)
+        .map(|p| format!("  - {p}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if changed.len() > SHOWN {
+        listing.push_str(&format!("\n  ... and {} more", changed.len() - SHOWN));
+    }
+
+    bail!(
+        "hermetic violation: {} path(s) changed outside declared outputs:\n{}\n\
+         declare writable directories in [container] outputs",
+        changed.len(),
+        listing
+    );
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_allowed(rel: &str, allowed: &[String]) -> bool {
+    ALWAYS_EXCLUDED
+        .iter()
+        .copied()
+        .chain(allowed.iter().map(String::as_str))
+        .map(|prefix| prefix.trim_end_matches('/'))
+        .any(|prefix| rel == prefix || rel.starts_with(&format!("{prefix}/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn detects_created_and_removed_files() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("keep.txt"), "a").unwrap();
+        fs::write(dir.path().join("gone.txt"), "b").unwrap();
+
+        let before = Snapshot::capture(dir.path(), &[]).unwrap();
+        fs::remove_file(dir.path().join("gone.txt")).unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/new.rs"), "c").unwrap();
+        let after = Snapshot::capture(dir.path(), &[]).unwrap();
+
+        assert_eq!(before.changed_paths(&after), vec!["gone.txt", "src/new.rs"]);
+        assert!(verify_unchanged(&before, &after)
+            .unwrap_err()
+            .to_string()
+            .contains("hermetic violation: 2 path(s)"));
+    }
+
+    #[test]
+    fn ignores_declared_outputs_and_git() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+
+        let outputs = vec!["target/".to_string()];
+        let before = Snapshot::capture(dir.path(), &outputs).unwrap();
+        fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        fs::write(dir.path().join("target/debug/app"), "bin").unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".git/HEAD"), "ref").unwrap();
+        let after = Snapshot::capture(dir.path(), &outputs).unwrap();
+
+        assert!(verify_unchanged(&before, &after).is_ok());
+    }
+
+    #[test]
+    fn allowed_prefix_matches_whole_segments() {
+        let allowed = vec!["target".to_string()];
+        assert!(is_allowed("target", &allowed));
+        assert!(is_allowed("target/ci/debug", &allowed));
+        assert!(!is_allowed("targets.toml", &allowed));
+    }
+}
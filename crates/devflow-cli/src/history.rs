@@ -0,0 +1,902 @@
+//! Run history in an embedded SQLite database (`dwf stats`).
+//!
+//! Every invocation that executes commands is stored in
+//! `<cache root>/history.db`: one `runs` row per `dwf` call and one
+//! `commands` row per executed command, with its duration, outcome, whether
+//! the CI image was already cached, and the test counts parsed from test
+//! output. A failed command also keeps its error and the last lines of its
+//! output in `failures`, for `dwf explain-failure`, and every command keeps
+//! the actions it resolved to in `actions`, for `dwf explain`. Recording
+//! never fails a command; a broken database only warns.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use devflow_core::{CommandRef, DevflowConfig};
+
+use crate::capture::strip_ansi;
+use crate::executor::{self, CommandStats};
+use crate::logs::RunLog;
+
+/// File name of the history database under the cache root.
+const DATABASE: &str = "history.db";
+
+/// Version of the schema below, kept in `PRAGMA user_version`.
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    invocation TEXT NOT NULL,
+    git_sha TEXT,
+    duration_ms INTEGER NOT NULL,
+    success INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS commands (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    started_at INTEGER NOT NULL,
+    command TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    success INTEGER NOT NULL,
+    cache_hit INTEGER,
+    tests_passed INTEGER,
+    tests_failed INTEGER
+);
+CREATE INDEX IF NOT EXISTS commands_by_name ON commands (command, started_at);
+CREATE TABLE IF NOT EXISTS failures (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    command TEXT NOT NULL,
+    error TEXT NOT NULL,
+    output TEXT NOT NULL,
+    log TEXT
+);
+CREATE TABLE IF NOT EXISTS actions (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    command TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    action TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS actions_by_run ON actions (run_id, command);
+";
+
+/// Test results from the summary lines of a test command's output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestCounts {
+    pub passed: u64,
+    pub failed: u64,
+}
+
+impl TestCounts {
+    /// Parses a summary line: cargo's `test result:`, nextest's `Summary`,
+    /// Jest's `Tests:` and Vitest's `Tests`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = strip_ansi(line);
+        let line = line.trim();
+        let rest = ["test result:", "Summary [", "Tests:", "Tests "]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))?;
+        let words: Vec<&str> = rest
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '|'))
+            .filter(|word| !word.is_empty())
+            .collect();
+        let mut counts: Option<Self> = None;
+        for pair in words.windows(2) {
+            let Ok(count) = pair[0].parse::<u64>() else {
+                continue;
+            };
+            match pair[1] {
+                "passed" => counts.get_or_insert_default().passed += count,
+                "failed" => counts.get_or_insert_default().failed += count,
+                _ => {}
+            }
+        }
+        counts
+    }
+}
+
+impl std::ops::Add for TestCounts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            passed: self.passed + other.passed,
+            failed: self.failed + other.failed,
+        }
+    }
+}
+
+/// One executed command, as stored and exported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandRecord {
+    pub run_id: i64,
+    /// Unix seconds.
+    pub started_at: u64,
+    pub command: String,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub cache_hit: Option<bool>,
+    pub tests_passed: Option<u64>,
+    pub tests_failed: Option<u64>,
+}
+
+/// One stored invocation, e.g. a `check:pr` run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunRecord {
+    pub id: i64,
+    /// Unix seconds.
+    pub started_at: u64,
+    pub invocation: String,
+    pub git_sha: Option<String>,
+    pub duration_ms: u64,
+    pub success: bool,
+}
+
+/// A failed command's error and the end of its output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureRecord {
+    pub command: String,
+    pub error: String,
+    /// The last output lines, oldest first.
+    pub output: Vec<String>,
+    /// The spooled full log, if any.
+    pub log: Option<String>,
+}
+
+/// What a stack ran for a command: the resolved action before the container
+/// proxy or host environment wraps it, with secrets masked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionRecord {
+    pub stack: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// The container image, for containerized runs.
+    pub image: Option<String>,
+}
+
+impl ActionRecord {
+    /// SHA-256 of the record, which changes with anything it holds.
+    pub fn hash(&self) -> String {
+        let json = serde_json::to_vec(self).expect("action records serialize");
+        hex::encode(Sha256::digest(json))
+    }
+}
+
+/// The actions one recorded run of a command resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionRun {
+    pub run: RunRecord,
+    /// Whether the command itself succeeded.
+    pub success: bool,
+    pub actions: Vec<ActionRecord>,
+}
+
+/// Collects the commands of one invocation until it is saved.
+pub struct Recorder {
+    invocation: String,
+    started: SystemTime,
+    clock: Instant,
+    commands: Mutex<Vec<CommandRecord>>,
+    failures: Mutex<Vec<FailureRecord>>,
+    actions: Mutex<Vec<(String, ActionRecord)>>,
+    run_log: Option<Arc<RunLog>>,
+}
+
+impl Recorder {
+    pub fn new(invocation: &CommandRef) -> Self {
+        Self {
+            invocation: invocation.canonical(),
+            started: SystemTime::now(),
+            clock: Instant::now(),
+            commands: Mutex::new(Vec::new()),
+            failures: Mutex::new(Vec::new()),
+            actions: Mutex::new(Vec::new()),
+            run_log: None,
+        }
+    }
+
+    /// Writes the output of the commands of the run to `run_log` too.
+    pub fn with_run_log(mut self, run_log: Option<RunLog>) -> Self {
+        self.run_log = run_log.map(Arc::new);
+        self
+    }
+
+    /// The command logs of the run, with `[logs]` enabled.
+    pub fn run_log(&self) -> Option<Arc<RunLog>> {
+        self.run_log.clone()
+    }
+
+    /// Records why `command` failed, with the output `stats` kept.
+    pub fn failure(&self, command: &str, error: &anyhow::Error, stats: &CommandStats) {
+        self.failures.lock().unwrap().push(FailureRecord {
+            command: command.to_string(),
+            error: format!("{error:#}"),
+            output: stats.output.clone(),
+            log: stats.log.as_ref().map(|log| log.display().to_string()),
+        });
+    }
+
+    /// Records a command that took `duration` and ended with `success`.
+    pub fn command(&self, command: &str, duration: Duration, success: bool, stats: CommandStats) {
+        let started_at = unix_secs(SystemTime::now().checked_sub(duration));
+        self.commands.lock().unwrap().push(CommandRecord {
+            run_id: 0,
+            started_at,
+            command: command.to_string(),
+            duration_ms: millis(duration),
+            success,
+            cache_hit: stats.cache_hit,
+            tests_passed: stats.tests.map(|t| t.passed),
+            tests_failed: stats.tests.map(|t| t.failed),
+        });
+        self.actions.lock().unwrap().extend(
+            stats
+                .actions
+                .into_iter()
+                .map(|action| (command.to_string(), action)),
+        );
+    }
+
+    /// The invoked command, e.g. `check:pr`.
+    pub fn invocation(&self) -> &str {
+        &self.invocation
+    }
+
+    /// The commands recorded so far.
+    pub fn commands(&self) -> Vec<CommandRecord> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed()
+    }
+
+    /// Stores the run if it executed any command; errors only warn.
+    pub fn save(&self, cfg: &DevflowConfig, success: bool) {
+        if self.commands.lock().unwrap().is_empty() {
+            return;
+        }
+        let path = database_path(cfg);
+        if let Err(e) = self.insert(&path, cfg, success) {
+            warn!(
+                "failed to record run history in {}: {:#}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    fn insert(&self, path: &Path, cfg: &DevflowConfig, success: bool) -> Result<()> {
+        let mut conn = open(path)?;
+        let git_sha = cfg
+            .source_dir
+            .as_deref()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map_or_else(std::env::current_dir, |dir| Ok(dir.to_path_buf()))
+            .ok()
+            .and_then(|dir| crate::changes::git(&dir, &["rev-parse", "HEAD"]).ok())
+            .map(|sha| sha.trim().to_string());
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO runs (started_at, invocation, git_sha, duration_ms, success)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                unix_secs(Some(self.started)),
+                self.invocation,
+                git_sha,
+                millis(self.clock.elapsed()),
+                success
+            ],
+        )?;
+        let run_id = tx.last_insert_rowid();
+        for record in self.commands.lock().unwrap().iter() {
+            tx.execute(
+                "INSERT INTO commands (run_id, started_at, command, duration_ms, success,
+                                       cache_hit, tests_passed, tests_failed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    run_id,
+                    record.started_at,
+                    record.command,
+                    record.duration_ms,
+                    record.success,
+                    record.cache_hit,
+                    record.tests_passed,
+                    record.tests_failed
+                ],
+            )?;
+        }
+        for failure in self.failures.lock().unwrap().iter() {
+            tx.execute(
+                "INSERT INTO failures (run_id, command, error, output, log)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    run_id,
+                    failure.command,
+                    failure.error,
+                    failure.output.join("\n"),
+                    failure.log
+                ],
+            )?;
+        }
+        for (command, action) in self.actions.lock().unwrap().iter() {
+            tx.execute(
+                "INSERT INTO actions (run_id, command, hash, action) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    run_id,
+                    command,
+                    action.hash(),
+                    serde_json::to_string(action)?
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn database_path(cfg: &DevflowConfig) -> PathBuf {
+    executor::cache_root(cfg).join(DATABASE)
+}
+
+/// Opens (creating if needed) the history database at `path`.
+fn open(path: &Path) -> Result<Connection> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create '{}'", parent.display()))?;
+    }
+    let conn =
+        Connection::open(path).with_context(|| format!("failed to open '{}'", path.display()))?;
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > SCHEMA_VERSION {
+        bail!(
+            "'{}' has schema version {} (this dwf supports {})",
+            path.display(),
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    conn.execute_batch(SCHEMA)?;
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(conn)
+}
+
+/// Commands started at or after `since` (Unix seconds), optionally only
+/// `command`, oldest first.
+fn query(conn: &Connection, since: u64, command: Option<&str>) -> Result<Vec<CommandRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT run_id, started_at, command, duration_ms, success,
+                cache_hit, tests_passed, tests_failed
+         FROM commands
+         WHERE started_at >= ?1 AND (?2 IS NULL OR command = ?2)
+         ORDER BY started_at, rowid",
+    )?;
+    let rows = stmt.query_map(params![since, command], |row| {
+        Ok(CommandRecord {
+            run_id: row.get(0)?,
+            started_at: row.get(1)?,
+            command: row.get(2)?,
+            duration_ms: row.get(3)?,
+            success: row.get(4)?,
+            cache_hit: row.get(5)?,
+            tests_passed: row.get(6)?,
+            tests_failed: row.get(7)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Prints the recorded commands of the last `last` (e.g. `30d`, all time
+/// when unset), optionally filtered to `command`, as a per-command summary
+/// (`text`) or as raw rows (`csv`, `json`).
+///
+/// # Errors
+/// Returns an error for an invalid window, command or format, or if the
+/// database cannot be read.
+pub fn report(
+    cfg: &DevflowConfig,
+    last: Option<&str>,
+    command: Option<&str>,
+    format: &str,
+) -> Result<()> {
+    let records = records(cfg, last, command)?;
+    match format {
+        "text" => print!("{}", summary(&records, last)),
+        "csv" => print!("{}", to_csv(&records)),
+        "json" => println!("{}", serde_json::to_string_pretty(&records)?),
+        other => bail!(
+            "unknown stats format '{}' (expected text, csv or json)",
+            other
+        ),
+    }
+    Ok(())
+}
+
+/// The recorded commands of the last `last` (all time when unset),
+/// optionally only `command`, oldest first.
+///
+/// # Errors
+/// Returns an error for an invalid window or command, or if the database
+/// cannot be read.
+pub fn records(
+    cfg: &DevflowConfig,
+    last: Option<&str>,
+    command: Option<&str>,
+) -> Result<Vec<CommandRecord>> {
+    let since = match last {
+        Some(window) => unix_secs(SystemTime::now().checked_sub(parse_window(window)?)),
+        None => 0,
+    };
+    let command = command.map(full_command).transpose()?;
+    let path = database_path(cfg);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    query(&open(&path)?, since, command.as_deref())
+}
+
+/// `text` with its default selector, as commands are recorded (`test` is
+/// `test:unit`).
+fn full_command(text: &str) -> Result<String> {
+    CommandRef::from_str(text)
+        .map(|cmd| {
+            let selector = cmd
+                .selector
+                .unwrap_or_else(|| cmd.primary.default_selector().to_string());
+            format!("{}:{}", cmd.primary.as_str(), selector)
+        })
+        .map_err(|e| anyhow!("invalid --command '{}': {}", text, e))
+}
+
+/// The latest run, of `invocation` if given, together with the latest run
+/// of every other invocation recorded for the same commit, e.g. the
+/// commands a CI job runs as separate `dwf` calls. Empty without history.
+///
+/// # Errors
+/// Returns an error for an invalid invocation, or if the database cannot be
+/// read.
+pub fn latest_runs(cfg: &DevflowConfig, invocation: Option<&str>) -> Result<Vec<RunRecord>> {
+    let invocation = invocation.map(full_command).transpose()?;
+    let path = database_path(cfg);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    latest(&open(&path)?, invocation.as_deref())
+}
+
+fn latest(conn: &Connection, invocation: Option<&str>) -> Result<Vec<RunRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, started_at, invocation, git_sha, duration_ms, success
+         FROM runs
+         WHERE (?1 IS NULL OR invocation = ?1)
+         ORDER BY started_at DESC, id DESC",
+    )?;
+    let runs = stmt
+        .query_map(params![invocation], |row| {
+            Ok(RunRecord {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                invocation: row.get(2)?,
+                git_sha: row.get(3)?,
+                duration_ms: row.get(4)?,
+                success: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let Some(newest) = runs.first() else {
+        return Ok(Vec::new());
+    };
+    let Some(sha) = newest.git_sha.clone() else {
+        return Ok(vec![newest.clone()]);
+    };
+    let mut commit: Vec<RunRecord> = Vec::new();
+    for run in runs {
+        let seen = commit.iter().any(|kept| kept.invocation == run.invocation);
+        if run.git_sha.as_deref() == Some(&sha) && !seen {
+            commit.push(run);
+        }
+    }
+    commit.reverse();
+    Ok(commit)
+}
+
+/// The failed runs among [`latest_runs`], each with its recorded failures.
+/// Empty without history.
+///
+/// # Errors
+/// Returns an error if the database cannot be read.
+pub fn latest_failures(cfg: &DevflowConfig) -> Result<Vec<(RunRecord, Vec<FailureRecord>)>> {
+    let path = database_path(cfg);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = open(&path)?;
+    latest(&conn, None)?
+        .into_iter()
+        .filter(|run| !run.success)
+        .map(|run| {
+            let failures = failures(&conn, run.id)?;
+            Ok((run, failures))
+        })
+        .collect()
+}
+
+fn failures(conn: &Connection, run_id: i64) -> Result<Vec<FailureRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT command, error, output, log FROM failures WHERE run_id = ?1 ORDER BY rowid",
+    )?;
+    let rows = stmt.query_map(params![run_id], |row| {
+        let output: String = row.get(2)?;
+        Ok(FailureRecord {
+            command: row.get(0)?,
+            error: row.get(1)?,
+            output: output.lines().map(String::from).collect(),
+            log: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// The recorded runs of `command` (e.g. `test:unit`) with the actions they
+/// resolved to, newest first. Empty without history.
+///
+/// # Errors
+/// Returns an error if the database cannot be read.
+pub fn action_runs(cfg: &DevflowConfig, command: &str) -> Result<Vec<ActionRun>> {
+    let path = database_path(cfg);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = open(&path)?;
+    let mut stmt = conn.prepare(
+        "SELECT runs.id, runs.started_at, runs.invocation, runs.git_sha, runs.duration_ms,
+                runs.success, MIN(commands.success)
+         FROM runs JOIN commands ON commands.run_id = runs.id
+         WHERE commands.command = ?1
+           AND EXISTS (SELECT 1 FROM actions
+                       WHERE actions.run_id = runs.id AND actions.command = ?1)
+         GROUP BY runs.id
+         ORDER BY runs.started_at DESC, runs.id DESC",
+    )?;
+    let runs = stmt
+        .query_map(params![command], |row| {
+            Ok((
+                RunRecord {
+                    id: row.get(0)?,
+                    started_at: row.get(1)?,
+                    invocation: row.get(2)?,
+                    git_sha: row.get(3)?,
+                    duration_ms: row.get(4)?,
+                    success: row.get(5)?,
+                },
+                row.get::<_, bool>(6)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut actions = conn
+        .prepare("SELECT action FROM actions WHERE run_id = ?1 AND command = ?2 ORDER BY rowid")?;
+    runs.into_iter()
+        .map(|(run, success)| {
+            let actions = actions
+                .query_map(params![run.id, command], |row| row.get::<_, String>(0))?
+                .map(|json| Ok(serde_json::from_str(&json?)?))
+                .collect::<Result<Vec<ActionRecord>>>()?;
+            Ok(ActionRun {
+                run,
+                success,
+                actions,
+            })
+        })
+        .collect()
+}
+
+/// The command whose actions were recorded last, if any.
+///
+/// # Errors
+/// Returns an error if the database cannot be read.
+pub fn last_action_command(cfg: &DevflowConfig) -> Result<Option<String>> {
+    let path = database_path(cfg);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let command = open(&path)?
+        .query_row(
+            "SELECT command FROM actions ORDER BY run_id DESC, rowid DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(command)
+}
+
+/// Parses a window such as `90m`, `12h`, `30d` or `2w`.
+fn parse_window(window: &str) -> Result<Duration> {
+    let invalid = || anyhow!("invalid --last '{}' (expected e.g. 12h, 30d or 2w)", window);
+    let split = window.len().checked_sub(1).ok_or_else(invalid)?;
+    let (count, unit) = window.split_at(split);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit {
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    Ok(Duration::from_secs(count * unit_secs))
+}
+
+fn summary(records: &[CommandRecord], last: Option<&str>) -> String {
+    let window = last.map_or_else(|| "all time".to_string(), |w| format!("last {w}"));
+    let mut out = format!("stats ({window}): {} command runs\n", records.len());
+    if records.is_empty() {
+        return out;
+    }
+    let mut commands: Vec<&str> = records.iter().map(|r| r.command.as_str()).collect();
+    commands.sort_unstable();
+    commands.dedup();
+
+    let mut rows = vec![[
+        "COMMAND".to_string(),
+        "RUNS".to_string(),
+        "FAILED".to_string(),
+        "AVG".to_string(),
+        "MAX".to_string(),
+        "CACHE HITS".to_string(),
+        "TESTS (LATEST)".to_string(),
+    ]];
+    for command in commands {
+        let runs: Vec<&CommandRecord> = records.iter().filter(|r| r.command == command).collect();
+        let total: u64 = runs.iter().map(|r| r.duration_ms).sum();
+        let max = runs.iter().map(|r| r.duration_ms).max().unwrap_or_default();
+        let cached: Vec<bool> = runs.iter().filter_map(|r| r.cache_hit).collect();
+        let cache_hits = if cached.is_empty() {
+            "-".to_string()
+        } else {
+            format!(
+                "{}/{}",
+                cached.iter().filter(|hit| **hit).count(),
+                cached.len()
+            )
+        };
+        let tests = runs
+            .iter()
+            .rev()
+            .find_map(|r| Some((r.tests_passed?, r.tests_failed?)))
+            .map_or_else(
+                || "-".to_string(),
+                |(passed, failed)| format!("{passed} passed, {failed} failed"),
+            );
+        rows.push([
+            command.to_string(),
+            runs.len().to_string(),
+            runs.iter().filter(|r| !r.success).count().to_string(),
+            seconds(total / runs.len() as u64),
+            seconds(max),
+            cache_hits,
+            tests,
+        ]);
+    }
+
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn to_csv(records: &[CommandRecord]) -> String {
+    let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let mut out = String::from(
+        "run_id,started_at,command,duration_ms,success,cache_hit,tests_passed,tests_failed\n",
+    );
+    for r in records {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            r.run_id,
+            crate::attest::rfc3339(UNIX_EPOCH + Duration::from_secs(r.started_at)),
+            r.command,
+            r.duration_ms,
+            r.success,
+            r.cache_hit.map(|hit| hit.to_string()).unwrap_or_default(),
+            optional(r.tests_passed),
+            optional(r.tests_failed)
+        ));
+    }
+    out
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1_000.0)
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn unix_secs(time: Option<SystemTime>) -> u64 {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_test_summaries() {
+        let counts = |passed, failed| Some(TestCounts { passed, failed });
+        assert_eq!(
+            TestCounts::parse(
+                "test result: \u{1b}[32mok\u{1b}[0m. 12 passed; 0 failed; 1 ignored; 0 measured"
+            ),
+            counts(12, 0)
+        );
+        assert_eq!(
+            TestCounts::parse(
+                "     Summary [   0.012s] 6 tests run: 4 passed, 2 failed, 0 skipped"
+            ),
+            counts(4, 2)
+        );
+        assert_eq!(
+            TestCounts::parse("Tests:       1 failed, 5 passed, 6 total"),
+            counts(5, 1)
+        );
+        assert_eq!(TestCounts::parse(" Tests  4 passed (4)"), counts(4, 0));
+        assert_eq!(TestCounts::parse("running 12 tests"), None);
+        assert_eq!(TestCounts::parse("Tests that flake: 3"), None);
+    }
+
+    #[test]
+    fn records_and_queries_runs() {
+        let dir = tempdir().unwrap();
+        let cfg = DevflowConfig {
+            source_dir: Some(dir.path().to_path_buf()),
+            ..DevflowConfig::default()
+        };
+        let recorder = Recorder::new(&CommandRef::from_str("check:pr").unwrap());
+        recorder.command(
+            "fmt:check",
+            Duration::from_millis(1_200),
+            true,
+            CommandStats::default(),
+        );
+        recorder.command(
+            "test:unit",
+            Duration::from_millis(4_000),
+            false,
+            CommandStats {
+                cache_hit: Some(true),
+                tests: Some(TestCounts {
+                    passed: 10,
+                    failed: 1,
+                }),
+                actions: vec![ActionRecord {
+                    stack: "rust".to_string(),
+                    program: "cargo".to_string(),
+                    args: vec!["test".to_string()],
+                    ..ActionRecord::default()
+                }],
+                ..CommandStats::default()
+            },
+        );
+        recorder.failure(
+            "test:unit",
+            &anyhow!("test:unit failed for rust"),
+            &CommandStats {
+                output: vec!["test it_works ... FAILED".to_string(), "done".to_string()],
+                ..CommandStats::default()
+            },
+        );
+        recorder.save(&cfg, false);
+
+        let failed = latest_failures(&cfg).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0.invocation, "check:pr");
+        assert_eq!(
+            failed[0].1,
+            [FailureRecord {
+                command: "test:unit".to_string(),
+                error: "test:unit failed for rust".to_string(),
+                output: vec!["test it_works ... FAILED".to_string(), "done".to_string()],
+                log: None,
+            }]
+        );
+
+        let actions = action_runs(&cfg, "test:unit").unwrap();
+        assert_eq!(actions.len(), 1);
+        assert!(!actions[0].success);
+        assert_eq!(actions[0].actions[0].args, ["test"]);
+        assert!(action_runs(&cfg, "fmt:check").unwrap().is_empty());
+        assert_eq!(
+            last_action_command(&cfg).unwrap().as_deref(),
+            Some("test:unit")
+        );
+
+        let conn = open(&database_path(&cfg)).unwrap();
+        let all = query(&conn, 0, None).unwrap();
+        assert_eq!(all.len(), 2);
+        let unit = query(&conn, 0, Some("test:unit")).unwrap();
+        assert_eq!(unit.len(), 1);
+        assert_eq!(unit[0].tests_failed, Some(1));
+        assert_eq!(unit[0].cache_hit, Some(true));
+        assert!(query(&conn, u64::MAX >> 1, None).unwrap().is_empty());
+
+        let text = summary(&all, Some("30d"));
+        assert!(text.starts_with("stats (last 30d): 2 command runs\n"));
+        assert!(
+            text.contains("test:unit  1     1       4.0s  4.0s  1/1         10 passed, 1 failed")
+        );
+        let csv = to_csv(&unit);
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .contains(",test:unit,4000,false,true,10,1"));
+    }
+
+    #[test]
+    fn latest_runs_cover_the_newest_commit() {
+        let dir = tempdir().unwrap();
+        let conn = open(&dir.path().join(DATABASE)).unwrap();
+        for (started_at, invocation, sha, success) in [
+            (10, "test:unit", "old", false),
+            (20, "fmt:check", "new", true),
+            (30, "test:unit", "new", false),
+            (40, "test:unit", "new", true),
+        ] {
+            conn.execute(
+                "INSERT INTO runs (started_at, invocation, git_sha, duration_ms, success)
+                 VALUES (?1, ?2, ?3, 1000, ?4)",
+                params![started_at, invocation, sha, success],
+            )
+            .unwrap();
+        }
+        let runs = latest(&conn, None).unwrap();
+        let summary: Vec<(&str, bool)> = runs
+            .iter()
+            .map(|run| (run.invocation.as_str(), run.success))
+            .collect();
+        assert_eq!(summary, [("fmt:check", true), ("test:unit", true)]);
+        let fmt = latest(&conn, Some("fmt:check")).unwrap();
+        assert_eq!(fmt.len(), 1);
+        assert_eq!(fmt[0].started_at, 20);
+        assert_eq!(full_command("test").unwrap(), "test:unit");
+    }
+
+    #[test]
+    fn parses_time_windows() {
+        assert_eq!(
+            parse_window("30d").unwrap(),
+            Duration::from_secs(30 * 86_400)
+        );
+        assert_eq!(
+            parse_window("12h").unwrap(),
+            Duration::from_secs(12 * 3_600)
+        );
+        assert!(parse_window("30").is_err());
+        assert!(parse_window("").is_err());
+    }
+}
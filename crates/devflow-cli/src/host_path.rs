@@ -0,0 +1,331 @@
+//! The `PATH` of host runs (`[runtime] path`, `--inherit-path`).
+//!
+//! Host runs inherit dwf's `PATH` by default, after the installed pinned
+//! tools (see [`crate::tools`]), so a tool one developer has earlier on
+//! their `PATH` than another makes the same command behave differently.
+//! With `path = "hermetic"` the `PATH` is built instead, in order: the
+//! installed pinned tools, `[runtime] path_dirs`, the stacks' toolchain
+//! directories ([`devflow_core::Extension::toolchain_dirs`]) and the system
+//! directories. `--inherit-path`, or an explicit `--env PATH=...`, keeps the
+//! inherited one for a run.
+//!
+//! `setup:doctor` reports the programs the stacks run that one directory of
+//! the `PATH` shadows in another and, under `hermetic`, those the hermetic
+//! `PATH` resolves differently or not at all.
+
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+
+use devflow_core::config::PathMode;
+use devflow_core::{CommandRef, DevflowConfig, ExecutionAction, ExtensionRegistry};
+
+use crate::executor::{self, RunOptions};
+use crate::tools;
+
+/// The system directories at the end of a hermetic `PATH`.
+pub const SYSTEM_DIRS: &[&str] = &[
+    "/usr/local/bin",
+    "/usr/bin",
+    "/bin",
+    "/usr/local/sbin",
+    "/usr/sbin",
+    "/sbin",
+];
+
+/// The directories of a hermetic `PATH`, in lookup order.
+pub fn hermetic_dirs(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    tool_dirs: &[PathBuf],
+) -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let base = cfg.source_dir.clone().unwrap_or_default();
+    let declared = cfg
+        .runtime
+        .path_dirs
+        .iter()
+        .map(|dir| match (dir.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => base.join(dir),
+        });
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in tool_dirs
+        .iter()
+        .cloned()
+        .chain(declared)
+        .chain(registry.toolchain_dirs_for(&executor::ordered_stacks(cfg)))
+        .chain(SYSTEM_DIRS.iter().map(PathBuf::from))
+    {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Sets the `PATH` of the host run `action` under `[runtime] path`.
+///
+/// # Errors
+/// Returns an error if a directory contains the path separator.
+pub fn apply(
+    action: &mut ExecutionAction,
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    tool_dirs: &[PathBuf],
+    opts: &RunOptions,
+) -> Result<()> {
+    if cfg.runtime.path != PathMode::Hermetic || opts.inherit_path || opts.env.contains_key("PATH")
+    {
+        return tools::add_to_path(action, tool_dirs);
+    }
+    let path = std::env::join_paths(hermetic_dirs(cfg, registry, tool_dirs))
+        .context("[runtime] path_dirs cannot be put on PATH")?;
+    action
+        .env
+        .insert("PATH".to_string(), path.to_string_lossy().into_owned());
+    Ok(())
+}
+
+/// Every executable named `program` on `path`, in lookup order; one reached
+/// again through a symlink (e.g. `/bin` to `/usr/bin`) is listed once.
+fn resolve_all(program: &str, path: &OsStr) -> Vec<PathBuf> {
+    let name = format!("{program}{}", std::env::consts::EXE_SUFFIX);
+    let mut seen = BTreeSet::new();
+    std::env::split_paths(path)
+        .map(|dir| dir.join(&name))
+        .filter(|candidate| is_executable(candidate))
+        .filter(|candidate| seen.insert(candidate.canonicalize().unwrap_or(candidate.clone())))
+        .collect()
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b
+        || a.canonicalize()
+            .ok()
+            .is_some_and(|a| b.canonicalize().ok() == Some(a))
+}
+
+/// The programs the stacks' commands run, looked up on `PATH`.
+fn programs(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> BTreeSet<String> {
+    let mut programs = BTreeSet::new();
+    for stack in executor::ordered_stacks(cfg) {
+        let Some(ext) = registry.get(&stack) else {
+            continue;
+        };
+        for capability in ext.capabilities() {
+            let Ok(cmd) = CommandRef::from_str(&capability) else {
+                continue;
+            };
+            for planned in executor::plan(cfg, registry, &cmd).unwrap_or_default() {
+                if !planned.action.program.contains(['/', '\\']) {
+                    programs.insert(planned.action.program);
+                }
+            }
+        }
+    }
+    programs
+}
+
+/// The findings of `setup:doctor` for `programs`: shadowed on `inherited`,
+/// and resolved differently or not at all on `hermetic` when it is used.
+fn findings(
+    programs: &BTreeSet<String>,
+    inherited: &OsStr,
+    hermetic: Option<&OsStr>,
+) -> (Vec<String>, Vec<String>) {
+    let mut notes = Vec::new();
+    let mut missing = Vec::new();
+    for program in programs {
+        let found = resolve_all(program, inherited);
+        if let [first, shadowed @ ..] = found.as_slice() {
+            if !shadowed.is_empty() {
+                let others: Vec<String> = shadowed
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                notes.push(format!(
+                    "{program}: {} shadows {}",
+                    first.display(),
+                    others.join(", ")
+                ));
+            }
+        }
+        let Some(hermetic) = hermetic else {
+            continue;
+        };
+        match (resolve_all(program, hermetic).first(), found.first()) {
+            (None, _) => missing.push(format!(
+                "{program}: not on the hermetic PATH (add its directory to [runtime] path_dirs)"
+            )),
+            (Some(pinned), Some(first)) if !same_file(pinned, first) => notes.push(format!(
+                "{program}: the hermetic PATH runs {}, your PATH {}",
+                pinned.display(),
+                first.display()
+            )),
+            _ => {}
+        }
+    }
+    (notes, missing)
+}
+
+/// Reports the programs the stacks run that are shadowed on `PATH` and, under
+/// `path = "hermetic"`, resolved differently by it.
+///
+/// # Errors
+/// Returns an error if a program is not on the hermetic `PATH`.
+pub fn doctor(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<()> {
+    let tool_dirs = tools::installed_dirs(cfg, registry);
+    let inherited = std::env::var_os("PATH").unwrap_or_default();
+    let inherited = std::env::join_paths(
+        tool_dirs
+            .iter()
+            .cloned()
+            .chain(std::env::split_paths(&inherited)),
+    )
+    .unwrap_or(inherited);
+    let hermetic = (cfg.runtime.path == PathMode::Hermetic)
+        .then(|| std::env::join_paths(hermetic_dirs(cfg, registry, &tool_dirs)))
+        .transpose()
+        .context("[runtime] path_dirs cannot be put on PATH")?;
+    let mode = match cfg.runtime.path {
+        PathMode::Inherit => "inherit",
+        PathMode::Hermetic => "hermetic",
+    };
+    let (notes, missing) = findings(&programs(cfg, registry), &inherited, hermetic.as_deref());
+    if notes.is_empty() && missing.is_empty() {
+        println!("PATH ({mode}): no shadowed tools");
+        return Ok(());
+    }
+    println!("PATH ({mode}):");
+    for line in notes.iter().chain(&missing) {
+        println!(" - {line}");
+    }
+    if !missing.is_empty() {
+        bail!(
+            "{} program(s) the stacks run are not on the hermetic PATH",
+            missing.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn install(dir: &Path, program: &str) {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(program);
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn builds_the_hermetic_path_and_flags_shadowed_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [runtime]
+            path = "hermetic"
+            path_dirs = ["tools/bin", "/opt/bin"]
+            "#,
+        )
+        .unwrap();
+        cfg.source_dir = Some(dir.path().to_path_buf());
+        let registry = ExtensionRegistry::default();
+        let pinned = dir.path().join("cache/tools/nextest/0.9");
+        let dirs = hermetic_dirs(&cfg, &registry, std::slice::from_ref(&pinned));
+        assert_eq!(
+            dirs[..3],
+            [pinned, dir.path().join("tools/bin"), "/opt/bin".into()]
+        );
+        assert_eq!(
+            dirs[3..],
+            SYSTEM_DIRS.iter().map(PathBuf::from).collect::<Vec<_>>()[..]
+        );
+
+        let mut action = ExecutionAction {
+            program: "cargo".to_string(),
+            args: Vec::new(),
+            env: Default::default(),
+        };
+        apply(&mut action, &cfg, &registry, &[], &RunOptions::default()).unwrap();
+        assert!(action.env["PATH"].starts_with(&format!(
+            "{}:/opt/bin:/usr/local/bin:",
+            dir.path().join("tools/bin").display()
+        )));
+        let escape = RunOptions {
+            inherit_path: true,
+            ..RunOptions::default()
+        };
+        let mut inherited = ExecutionAction {
+            env: Default::default(),
+            ..action.clone()
+        };
+        apply(&mut inherited, &cfg, &registry, &[], &escape).unwrap();
+        assert!(!inherited.env.contains_key("PATH"));
+
+        let user = dir.path().join("home/.cargo/bin");
+        let system = dir.path().join("usr/bin");
+        install(&user, "cargo");
+        install(&system, "cargo");
+        install(&system, "npm");
+        fs::write(system.join("make"), "not executable").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&system, dir.path().join("bin")).unwrap();
+        let inherited = std::env::join_paths([&user, &system, &dir.path().join("bin")]).unwrap();
+        let hermetic = std::env::join_paths([&system]).unwrap();
+        let programs: BTreeSet<String> = ["cargo", "make", "npm"].map(String::from).into();
+
+        let (notes, missing) = findings(&programs, &inherited, None);
+        assert_eq!(
+            notes,
+            [format!(
+                "cargo: {} shadows {}",
+                user.join("cargo").display(),
+                system.join("cargo").display()
+            )]
+        );
+        assert!(missing.is_empty());
+
+        let (notes, missing) = findings(&programs, &inherited, Some(&hermetic));
+        assert_eq!(
+            notes[1],
+            format!(
+                "cargo: the hermetic PATH runs {}, your PATH {}",
+                system.join("cargo").display(),
+                user.join("cargo").display()
+            )
+        );
+        assert_eq!(
+            missing,
+            ["make: not on the hermetic PATH (add its directory to [runtime] path_dirs)"]
+        );
+    }
+}
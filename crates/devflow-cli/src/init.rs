@@ -1,11 +1,99 @@
-use std::{fs, path::Path};
+use std::io::{BufRead, IsTerminal, Write};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 
 use crate::Cli;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
+
+/// Stacks the wizard offers.
+const STACKS: [&str; 3] = ["rust", "node", "custom"];
+/// Runtime profiles the wizard offers.
+const RUNTIMES: [&str; 3] = ["auto", "host", "container"];
+/// CI providers the wizard offers; `none` writes no workflow.
+const CI_PROVIDERS: [&str; 2] = ["github", "none"];
+/// Target profiles the wizard offers; `pr` is always included.
+const PROFILES: [&str; 4] = ["pr", "main", "release", "security"];
+/// Directories never scanned for sub-projects.
+const SKIPPED_DIRS: [&str; 5] = ["node_modules", "target", "vendor", "dist", "build"];
+/// How deep below the root sub-projects are looked for.
+const PROJECT_DEPTH: usize = 2;
+/// What dwf writes into a project and `.gitignore` keeps out of git: the
+/// completion cache and failure artifacts, and the default cache root.
+const IGNORED_STATE: [&str; 2] = [".devflow/", ".cache/devflow/"];
+
+/// Which checks go into the target profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Preset {
+    /// Formatting, lints and unit tests; no debug build on PRs.
+    Minimal,
+    /// Adds the debug build on PRs and integration tests on main.
+    Standard,
+    /// Adds integration tests on PRs and provenance and a reproducibility
+    /// check on release.
+    Strict,
+}
+
+impl Preset {
+    const NAMES: [&'static str; 3] = ["minimal", "standard", "strict"];
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "minimal" => Ok(Self::Minimal),
+            "standard" => Ok(Self::Standard),
+            "strict" => Ok(Self::Strict),
+            other => Err(anyhow!(
+                "unknown preset '{}' (supported: {})",
+                other,
+                Self::NAMES.join(",")
+            )),
+        }
+    }
+
+    /// The commands of target `profile`, in lifecycle order.
+    fn commands(self, profile: &str) -> &'static [&'static str] {
+        match (self, profile) {
+            (Self::Minimal, "pr") => &["fmt:check", "lint:static", "test:unit"],
+            (Self::Minimal, "main") => &["fmt:check", "lint:static", "build:release", "test:unit"],
+            (Self::Standard, "pr") => &["fmt:check", "lint:static", "build:debug", "test:unit"],
+            (Self::Strict, "pr") => &[
+                "fmt:check",
+                "lint:static",
+                "build:debug",
+                "test:unit",
+                "test:integration",
+            ],
+            (Self::Standard | Self::Strict, "main") => &[
+                "fmt:check",
+                "lint:static",
+                "build:release",
+                "test:unit",
+                "test:integration",
+            ],
+            (Self::Minimal | Self::Standard, "release") => &["build:release", "package:artifact"],
+            (Self::Strict, "release") => &[
+                "build:release",
+                "test:integration",
+                "package:artifact",
+                "package:attest",
+                "check:repro",
+            ],
+            (_, "security") => &["check:security"],
+            _ => &[],
+        }
+    }
+}
 
 /// Runs the `init` command to bootstrap a new Devflow project.
+///
+/// Without a template, a directory with no manifest of its own but projects
+/// in its subdirectories gets a config in each project. Otherwise `--stack`,
+/// `--runtime`, `--preset`, `--profiles` or `--ci` compose the config from
+/// their answers, or the template is detected from the project files, and
+/// when that fails on a terminal the wizard asks.
 #[instrument(skip(cli))]
 pub fn run(cli: &Cli, template_selector: Option<&str>) -> Result<()> {
     let config_path = Path::new(&cli.config);
@@ -21,43 +109,181 @@ pub fn run(cli: &Cli, template_selector: Option<&str>) -> Result<()> {
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| "devflow-project".to_string());
 
-    let template = match template_selector {
-        Some(value) => InitTemplate::from_str(value)?,
-        None => detect_template(&target_dir)?,
+    if template_selector.is_some() && cli.preset.is_some() {
+        bail!("--preset cannot be combined with a template; use --stack instead");
+    }
+    let detected = detect_template(&target_dir);
+    if template_selector.is_none() && cli.stack.is_empty() && detected.is_err() {
+        let projects = detect_projects(&target_dir)?;
+        if !projects.is_empty() {
+            return init_projects(cli, &target_dir, &projects);
+        }
+    }
+
+    let flagged = !cli.stack.is_empty()
+        || cli.runtime.is_some()
+        || cli.preset.is_some()
+        || !cli.profiles.is_empty()
+        || cli.ci.is_some();
+    let (template, answers) = match template_selector {
+        Some(value) => (Some(InitTemplate::from_str(value)?), None),
+        None if flagged => (None, Some(Answers::from_flags(cli, &target_dir)?)),
+        None => match detected {
+            Ok(template) => (Some(template), None),
+            Err(_) if std::io::stdin().is_terminal() => {
+                let stdin = std::io::stdin();
+                let answers = Answers::ask(&mut stdin.lock(), &mut std::io::stdout())?;
+                (None, Some(answers))
+            }
+            Err(e) => return Err(e),
+        },
     };
 
-    let config_content = template.render_config(&project_name);
+    let (config_content, dockerfile, template_name) = match (template, &answers) {
+        (Some(template), _) => (
+            template.render_config(&project_name),
+            template.render_dockerfile(),
+            template.as_str(),
+        ),
+        (None, Some(answers)) => (
+            answers.render_config(&project_name),
+            answers.render_dockerfile(),
+            "answers",
+        ),
+        (None, None) => unreachable!("init has a template or answers"),
+    };
     write_if_absent(&cli.config, &config_content, cli.force)
         .with_context(|| format!("failed to write '{}'", cli.config))?;
+    ignore_state(&target_dir)?;
 
-    if let Some(dockerfile_content) = template.render_dockerfile() {
-        let dockerfile_path = target_dir.join("Dockerfile.devflow");
+    if let Some(dockerfile_content) = dockerfile {
+        let dockerfile_path = target_dir.join(devflow_core::constants::CI_DOCKERFILE);
         let df_str = dockerfile_path.to_str().unwrap();
         // Ignore failures if the Dockerfile already exists, this is non-critical scaffolding
         let _ = write_if_absent(df_str, dockerfile_content, cli.force);
     }
 
-    let cfg = devflow_core::DevflowConfig::load_from_file(&cli.config)?;
-    let workflow = devflow_gh::render_workflow(&cfg)?;
-
-    if cli.stdout {
-        println!("{workflow}");
-    } else {
-        write_if_absent(&cli.ci_output, &workflow, cli.force)
-            .with_context(|| format!("failed to write '{}'", cli.ci_output))?;
+    let github = match &answers {
+        Some(answers) => answers.github,
+        None => cli.ci.as_deref() != Some("none"),
+    };
+    if github {
+        let cfg = devflow_core::DevflowConfig::load_from_file(&cli.config)?;
+        let workflow = devflow_gh::render_workflow(&cfg)?;
+
+        if cli.stdout {
+            println!("{workflow}");
+        } else {
+            write_if_absent(&cli.ci_output, &workflow, cli.force)
+                .with_context(|| format!("failed to write '{}'", cli.ci_output))?;
+        }
     }
 
     info!(
         "init complete: template={}, config={}, ci={}",
-        template.as_str(),
+        template_name,
         cli.config,
-        cli.ci_output
+        if github {
+            cli.ci_output.as_str()
+        } else {
+            "none"
+        }
     );
     println!("next: run 'dwf check:pr'");
 
     Ok(())
 }
 
+/// Writes a config and Dockerfile from the flags into each of `projects`,
+/// with the stacks detected there. No workflow is written: each project is
+/// run from its own directory.
+fn init_projects(cli: &Cli, root: &Path, projects: &[PathBuf]) -> Result<()> {
+    let file_name = Path::new(&cli.config)
+        .file_name()
+        .unwrap_or(std::ffi::OsStr::new(devflow_core::constants::CONFIG_FILE));
+    if !cli.force {
+        if let Some(existing) = projects
+            .iter()
+            .map(|dir| dir.join(file_name))
+            .find(|path| path.exists())
+        {
+            bail!(
+                "'{}' already exists. Re-run with --force to overwrite",
+                existing.display()
+            );
+        }
+    }
+    for dir in projects {
+        let answers = Answers::from_flags(cli, dir)?;
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "devflow-project".to_string());
+        let config = dir.join(file_name);
+        fs::write(&config, answers.render_config(&name))
+            .with_context(|| format!("failed to write '{}'", config.display()))?;
+        ignore_state(dir)?;
+        if let Some(dockerfile) = answers.render_dockerfile() {
+            let path = dir.join(devflow_core::constants::CI_DOCKERFILE);
+            // Non-critical scaffolding, as for a single project
+            let _ = write_if_absent(&path.to_string_lossy(), dockerfile, cli.force);
+        }
+        println!(
+            "initialized {} ({})",
+            config.strip_prefix(root).unwrap_or(&config).display(),
+            answers.stacks.join(", ")
+        );
+    }
+    if cli.ci.as_deref() == Some("github") {
+        warn!("no workflow written for a multi-project layout; run 'dwf ci:generate' in a project");
+    }
+    info!(
+        "init complete: {} projects, preset={}",
+        projects.len(),
+        cli.preset.as_deref().unwrap_or("standard")
+    );
+    println!("next: run 'dwf check:pr' in each project directory");
+    Ok(())
+}
+
+/// Finds the project directories below `root`: subdirectories, up to
+/// [`PROJECT_DEPTH`] deep, with a rust or node manifest. Hidden and
+/// [`SKIPPED_DIRS`] directories and the inside of projects are not scanned.
+fn detect_projects(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut projects = Vec::new();
+    collect_projects(root, PROJECT_DEPTH, &mut projects)?;
+    Ok(projects)
+}
+
+fn collect_projects(dir: &Path, depth: usize, projects: &mut Vec<PathBuf>) -> Result<()> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("failed to read '{}'", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    for path in dirs {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+        if !detected_stacks(&path).is_empty() {
+            projects.push(path);
+        } else if depth > 1 {
+            collect_projects(&path, depth - 1, projects)?;
+        }
+    }
+    Ok(())
+}
+
+/// The wizard's stacks whose manifest is in `dir`.
+fn detected_stacks(dir: &Path) -> Vec<String> {
+    devflow_core::project::detect_stacks(dir)
+        .into_iter()
+        .filter(|stack| STACKS.contains(&stack.as_str()))
+        .collect()
+}
+
 /// Supported project templates for initialization.
 #[derive(Debug, Clone, Copy)]
 enum InitTemplate {
@@ -118,6 +344,238 @@ impl InitTemplate {
     }
 }
 
+/// What the wizard or the init flags chose.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Answers {
+    stacks: Vec<String>,
+    runtime: String,
+    /// Write a GitHub Actions workflow.
+    github: bool,
+    preset: Preset,
+    /// Target profiles, in [`PROFILES`] order.
+    profiles: Vec<String>,
+}
+
+impl Answers {
+    /// Takes the answers from `--stack`, `--runtime`, `--preset`, `--profiles`
+    /// and `--ci`; the stacks default to those detected in `dir`, else
+    /// `custom`.
+    fn from_flags(cli: &Cli, dir: &Path) -> Result<Self> {
+        let stacks = match cli.stack.as_slice() {
+            [] => match detected_stacks(dir) {
+                stacks if stacks.is_empty() => vec!["custom".to_string()],
+                stacks => stacks,
+            },
+            stacks => stacks.to_vec(),
+        };
+        for stack in &stacks {
+            if !STACKS.contains(&stack.as_str()) {
+                bail!(
+                    "unknown stack '{}' for init (supported: {})",
+                    stack,
+                    STACKS.join(",")
+                );
+            }
+        }
+        for profile in &cli.profiles {
+            if !PROFILES.contains(&profile.as_str()) {
+                bail!(
+                    "unknown target profile '{}' for init (supported: {})",
+                    profile,
+                    PROFILES.join(",")
+                );
+            }
+        }
+        Ok(Self::new(
+            stacks,
+            cli.runtime.clone().unwrap_or_else(|| "auto".to_string()),
+            cli.ci.as_deref() != Some("none"),
+            Preset::from_str(cli.preset.as_deref().unwrap_or("standard"))?,
+            &cli.profiles,
+        ))
+    }
+
+    /// Asks for every answer on `output`, reading replies from `input`.
+    fn ask(input: &mut impl BufRead, output: &mut impl Write) -> Result<Self> {
+        writeln!(
+            output,
+            "No project template detected; answer a few questions to create devflow.toml."
+        )?;
+        let stacks = choose_many(input, output, "Stacks", &STACKS, &["custom"])?;
+        let runtime = choose(input, output, "Runtime profile", &RUNTIMES, "auto")?;
+        let ci = choose(input, output, "CI provider", &CI_PROVIDERS, "github")?;
+        let preset = choose(input, output, "Preset", &Preset::NAMES, "standard")?;
+        let profiles = choose_many(
+            input,
+            output,
+            "Target profiles (pr is always included)",
+            &PROFILES,
+            &["pr", "main", "security"],
+        )?;
+        Ok(Self::new(
+            stacks,
+            runtime,
+            ci != "none",
+            Preset::from_str(&preset)?,
+            &profiles,
+        ))
+    }
+
+    fn new(
+        stacks: Vec<String>,
+        runtime: String,
+        github: bool,
+        preset: Preset,
+        profiles: &[String],
+    ) -> Self {
+        let profiles = PROFILES
+            .iter()
+            .filter(|name| **name == "pr" || profiles.iter().any(|p| p == *name))
+            .map(|name| name.to_string())
+            .collect();
+        Self {
+            stacks,
+            runtime,
+            github,
+            preset,
+            profiles,
+        }
+    }
+
+    /// Renders a canonical `devflow.toml` for the answers.
+    fn render_config(&self, project_name: &str) -> String {
+        let quoted = |items: &[&str]| {
+            items
+                .iter()
+                .map(|item| format!("\"{item}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let stacks: Vec<&str> = self.stacks.iter().map(String::as_str).collect();
+        let mut out = format!(
+            "[project]\nname = \"{project_name}\"\nstack = [{}]\n\n[runtime]\nprofile = \"{}\"\n\n[targets]\n",
+            quoted(&stacks),
+            self.runtime
+        );
+        let mut profiles: Vec<&str> = self.profiles.iter().map(String::as_str).collect();
+        profiles.sort_unstable();
+        for name in profiles {
+            out.push_str(&format!(
+                "{name} = [{}]\n",
+                quoted(self.preset.commands(name))
+            ));
+        }
+        let mut builtins: Vec<&str> = stacks
+            .iter()
+            .copied()
+            .filter(|stack| *stack != "custom")
+            .collect();
+        builtins.sort_unstable();
+        for stack in builtins {
+            out.push_str(&format!(
+                "\n[extensions.{stack}]\nsource = \"builtin\"\nrequired = true\n"
+            ));
+        }
+        if self.runtime != "host" {
+            out.push_str(&format!(
+                "\n[container]\n# You can customize this base image via Dockerfile.devflow\nimage = \"{project_name}-ci:latest\"\nengine = \"auto\"\n"
+            ));
+        }
+        if stacks.contains(&"custom") {
+            out.push_str(
+                "\n# Custom stack delegates canonical selectors to just/make targets.\n# Implement matching targets in justfile or Makefile, e.g. fmt-check, build-debug\n",
+            );
+        }
+        out
+    }
+
+    /// The Dockerfile of the first stack that has one, unless commands run on
+    /// the host.
+    fn render_dockerfile(&self) -> Option<&'static str> {
+        if self.runtime == "host" {
+            return None;
+        }
+        self.stacks.iter().find_map(|stack| match stack.as_str() {
+            "rust" => InitTemplate::Rust.render_dockerfile(),
+            "node" => InitTemplate::Node.render_dockerfile(),
+            _ => None,
+        })
+    }
+}
+
+/// Asks for one of `options`; an empty reply picks `default`.
+fn choose(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    options: &[&str],
+    default: &str,
+) -> Result<String> {
+    loop {
+        let reply = prompt(input, output, question, options, default)?;
+        if options.contains(&reply.as_str()) {
+            return Ok(reply);
+        }
+        writeln!(
+            output,
+            "unknown choice '{}' (choose from {})",
+            reply,
+            options.join(", ")
+        )?;
+    }
+}
+
+/// Asks for one or more comma-separated `options`; an empty reply picks
+/// `defaults`.
+fn choose_many(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    options: &[&str],
+    defaults: &[&str],
+) -> Result<Vec<String>> {
+    'ask: loop {
+        let reply = prompt(input, output, question, options, &defaults.join(","))?;
+        let mut chosen: Vec<String> = Vec::new();
+        for choice in reply.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            if !options.contains(&choice) {
+                writeln!(
+                    output,
+                    "unknown choice '{}' (choose from {})",
+                    choice,
+                    options.join(", ")
+                )?;
+                continue 'ask;
+            }
+            if !chosen.iter().any(|c| c == choice) {
+                chosen.push(choice.to_string());
+            }
+        }
+        if !chosen.is_empty() {
+            return Ok(chosen);
+        }
+    }
+}
+
+/// Prints `question` with its options and default and reads the trimmed
+/// reply, or `default` for an empty one.
+fn prompt(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    options: &[&str],
+    default: &str,
+) -> Result<String> {
+    write!(output, "{question} [{}] ({default}): ", options.join("/"))?;
+    output.flush()?;
+    let mut reply = String::new();
+    if input.read_line(&mut reply)? == 0 {
+        bail!("init aborted: no answer for '{}'", question);
+    }
+    let reply = reply.trim();
+    Ok(if reply.is_empty() { default } else { reply }.to_string())
+}
+
 fn detect_template(base_path: &Path) -> Result<InitTemplate> {
     use devflow_core::constants::{MANIFEST_NODE, MANIFEST_RUST, MANIFEST_TSC};
 
@@ -134,10 +592,41 @@ fn detect_template(base_path: &Path) -> Result<InitTemplate> {
     }
 
     Err(anyhow!(
-        "unable to auto-detect template. Run: dwf init <rust|node|tsc|kotlin>"
+        "unable to auto-detect template. Run: dwf init <rust|node|tsc|kotlin>, or pass --stack"
     ))
 }
 
+/// Adds the [`IGNORED_STATE`] entries `dir/.gitignore` lacks, creating it
+/// if needed.
+fn ignore_state(dir: &Path) -> Result<()> {
+    let path = dir.join(".gitignore");
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read '{}'", path.display())),
+    };
+    let missing: Vec<&str> = IGNORED_STATE
+        .into_iter()
+        .filter(|entry| {
+            !text.lines().any(|line| {
+                let line = line.trim().trim_start_matches('/');
+                line == *entry || line == entry.trim_end_matches('/')
+            })
+        })
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    for entry in missing {
+        text.push_str(entry);
+        text.push('\n');
+    }
+    fs::write(&path, text).with_context(|| format!("failed to write '{}'", path.display()))
+}
+
 fn write_if_absent(path: &str, content: &str, force: bool) -> Result<()> {
     let output = Path::new(path);
 
@@ -166,6 +655,8 @@ mod tests {
         Cli {
             command: Some("init".to_string()),
             selector: None,
+            detail: None,
+            configs: vec![],
             config: dir.join("devflow.toml").to_str().unwrap().to_string(),
             stdout: false,
             ci_output: dir
@@ -178,6 +669,42 @@ mod tests {
             local: false,
             gh: false,
             all: false,
+            dry_run: false,
+            hermetic: false,
+            sign: false,
+            inputs: false,
+            diff: false,
+            list: false,
+            follow: false,
+            allow_dirty: false,
+            inherit_path: false,
+            check: None,
+            split: false,
+            ignore_version_mismatch: false,
+            lenient_config: false,
+            profile: "pr".to_string(),
+            last: None,
+            stats_command: None,
+            coverage: None,
+            output: None,
+            format: "text".to_string(),
+            listen: "127.0.0.1:8787".to_string(),
+            notify: false,
+            sarif: None,
+            events: None,
+            affected: false,
+            keep: false,
+            strict_skips: false,
+            no_fail_fast: false,
+            stack: Vec::new(),
+            timeout: None,
+            retries: 0,
+            env: Vec::new(),
+            runtime: None,
+            ci: None,
+            preset: None,
+            profiles: Vec::new(),
+            argv: Vec::new(),
         }
     }
 
@@ -198,6 +725,208 @@ mod tests {
         assert!(matches!(detect_template(base).unwrap(), InitTemplate::Rust));
     }
 
+    #[test]
+    fn unit_test_dockerfiles_enable_buildkit_cache_mounts() {
+        for template in [InitTemplate::Rust, InitTemplate::Node] {
+            let dockerfile = template.render_dockerfile().unwrap();
+            assert!(dockerfile.starts_with("# syntax=docker/dockerfile:1\n"));
+            assert!(dockerfile.contains("--mount=type=cache"));
+        }
+    }
+
+    #[test]
+    fn unit_test_templates_are_canonically_formatted() {
+        for template in [
+            InitTemplate::Rust,
+            InitTemplate::Node,
+            InitTemplate::Tsc,
+            InitTemplate::Kotlin,
+        ] {
+            let config = template.render_config("demo");
+            assert!(
+                devflow_core::format::is_canonical(&config).unwrap(),
+                "{} template is not canonical",
+                template.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn unit_test_wizard_asks_until_answers_are_valid() {
+        let mut input =
+            "rust, node\ncloud\ncontainer\n\nstrict\nrelease,nightly\nrelease\n".as_bytes();
+        let mut output = Vec::new();
+        let answers = Answers::ask(&mut input, &mut output).unwrap();
+        assert_eq!(
+            answers,
+            Answers {
+                stacks: vec!["rust".to_string(), "node".to_string()],
+                runtime: "container".to_string(),
+                github: true,
+                preset: Preset::Strict,
+                profiles: vec!["pr".to_string(), "release".to_string()],
+            }
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Stacks [rust/node/custom] (custom): "));
+        assert!(output.contains("unknown choice 'cloud' (choose from auto, host, container)"));
+        assert!(output.contains("unknown choice 'nightly'"));
+
+        let err = Answers::ask(&mut "rust\n".as_bytes(), &mut Vec::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "init aborted: no answer for 'Runtime profile'"
+        );
+    }
+
+    #[test]
+    fn unit_test_answers_render_a_valid_canonical_config() {
+        let answers = Answers::new(
+            vec!["rust".to_string(), "custom".to_string(), "node".to_string()],
+            "container".to_string(),
+            true,
+            Preset::Standard,
+            &["security".to_string(), "main".to_string()],
+        );
+        let config = answers.render_config("demo");
+        assert!(
+            devflow_core::format::is_canonical(&config).unwrap(),
+            "{config}"
+        );
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("devflow.toml");
+        fs::write(&path, &config).unwrap();
+        let cfg = devflow_core::DevflowConfig::load_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(cfg.project.stack, ["rust", "custom", "node"]);
+        let profiles: Vec<&String> = cfg.targets.profiles.keys().collect();
+        assert_eq!(profiles, ["main", "pr", "security"]);
+        assert_eq!(
+            cfg.extensions
+                .unwrap()
+                .keys()
+                .collect::<std::collections::BTreeSet<_>>(),
+            [&"node".to_string(), &"rust".to_string()].into()
+        );
+        assert_eq!(
+            cfg.container.unwrap().image.as_deref(),
+            Some("demo-ci:latest")
+        );
+        assert!(answers.render_dockerfile().unwrap().contains("Rust"));
+
+        let host = Answers::new(
+            vec!["custom".to_string()],
+            "host".to_string(),
+            false,
+            Preset::Minimal,
+            &[],
+        );
+        let config = host.render_config("demo");
+        assert!(
+            devflow_core::format::is_canonical(&config).unwrap(),
+            "{config}"
+        );
+        assert!(!config.contains("[container]"));
+        assert!(host.render_dockerfile().is_none());
+    }
+
+    #[test]
+    fn unit_test_presets_differ_in_their_checks() {
+        let pr = |preset: Preset| preset.commands("pr");
+        assert!(!pr(Preset::Minimal).contains(&"build:debug"));
+        assert!(pr(Preset::Standard).contains(&"build:debug"));
+        assert!(pr(Preset::Strict).contains(&"test:integration"));
+        assert!(!Preset::Minimal
+            .commands("main")
+            .contains(&"test:integration"));
+        assert!(Preset::Strict.commands("release").contains(&"check:repro"));
+        for preset in Preset::NAMES {
+            let preset = Preset::from_str(preset).unwrap();
+            for profile in PROFILES {
+                assert!(!preset.commands(profile).is_empty());
+            }
+        }
+        assert!(Preset::from_str("lax").is_err());
+    }
+
+    #[test]
+    fn integration_test_init_scaffolds_each_project_of_a_monorepo() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        for (path, manifest) in [
+            ("services/api", "Cargo.toml"),
+            ("services/api/crates/core", "Cargo.toml"),
+            ("web", "package.json"),
+            ("web/node_modules/dep", "package.json"),
+            (".cache/tool", "Cargo.toml"),
+            ("a/b/c", "Cargo.toml"),
+        ] {
+            fs::create_dir_all(root.join(path)).unwrap();
+            fs::write(root.join(path).join(manifest), "").unwrap();
+        }
+        assert_eq!(
+            detect_projects(root).unwrap(),
+            [root.join("services/api"), root.join("web")]
+        );
+
+        let mut cli = test_cli(root);
+        cli.preset = Some("strict".to_string());
+        cli.profiles = vec!["release".to_string()];
+        run(&cli, None).unwrap();
+        assert!(!Path::new(&cli.config).exists());
+        assert!(!Path::new(&cli.ci_output).exists());
+        let api = fs::read_to_string(root.join("services/api/devflow.toml")).unwrap();
+        assert!(api.contains("name = \"api\"\nstack = [\"rust\"]"), "{api}");
+        assert!(api.contains("\"check:repro\""), "{api}");
+        let web = fs::read_to_string(root.join("web/devflow.toml")).unwrap();
+        assert!(web.contains("stack = [\"node\"]"), "{web}");
+        assert!(root.join("web/Dockerfile.devflow").exists());
+
+        let err = run(&cli, None).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        let err = run(&cli, Some("rust")).unwrap_err();
+        assert!(
+            err.to_string().contains("--preset cannot be combined"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn integration_test_init_from_flags_without_ci() {
+        let dir = tempdir().unwrap();
+        let mut cli = test_cli(dir.path());
+        cli.stack = vec!["node".to_string()];
+        cli.runtime = Some("host".to_string());
+        cli.profiles = vec!["release".to_string()];
+        cli.ci = Some("none".to_string());
+        run(&cli, None).unwrap();
+        let config = fs::read_to_string(&cli.config).unwrap();
+        assert!(config.contains("stack = [\"node\"]"));
+        assert!(config.contains("release = [\"build:release\", \"package:artifact\"]"));
+        assert!(!Path::new(&cli.ci_output).exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(".gitignore")).unwrap(),
+            ".devflow/\n.cache/devflow/\n"
+        );
+
+        cli.force = true;
+        cli.stack = vec!["python".to_string()];
+        let err = run(&cli, None).unwrap_err();
+        assert!(err.to_string().contains("unknown stack 'python'"), "{err}");
+    }
+
+    #[test]
+    fn unit_test_ignore_state_appends_missing_entries_once() {
+        let dir = tempdir().unwrap();
+        let gitignore = dir.path().join(".gitignore");
+        fs::write(&gitignore, "target/\n/.devflow").unwrap();
+        ignore_state(dir.path()).unwrap();
+        ignore_state(dir.path()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&gitignore).unwrap(),
+            "target/\n/.devflow\n.cache/devflow/\n"
+        );
+    }
+
     #[test]
     fn unit_test_write_if_absent() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,323 @@
+//! `ci:issue`: tracks the results of a scheduled maintenance run in a GitHub
+//! issue.
+//!
+//! Every maintenance command reports a commit status (`dwf --report`). The
+//! generated `maintenance_report` job reads them back for the run's commit
+//! and opens an issue labelled `devflow-maintenance` listing the failed
+//! commands, or updates the one already open. Once every command passes, the
+//! open issue is closed.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::debug;
+
+use devflow_core::{DevflowConfig, TargetEntry};
+use devflow_gh::{GithubContext, MAINTENANCE_LABEL};
+
+const API: &str = "https://api.github.com";
+
+/// A commit status as the GitHub API returns it.
+#[derive(Debug, Clone, Deserialize)]
+struct Status {
+    context: String,
+    state: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    target_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    number: u64,
+}
+
+/// The outcome of one profile command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Finding {
+    command: String,
+    /// `success`, `failure`, `error`, `pending`, or `missing` without a status.
+    state: String,
+    description: String,
+    url: Option<String>,
+}
+
+impl Finding {
+    fn failed(&self) -> bool {
+        self.state != "success"
+    }
+}
+
+/// Runs `ci:issue` for `profile` with `token`.
+///
+/// # Errors
+/// Returns an error if the profile is unknown, the run's repository or commit
+/// is unknown, or a GitHub API call fails.
+pub fn run(
+    cfg: &DevflowConfig,
+    gh: &GithubContext,
+    token: Option<&str>,
+    profile: &str,
+    run_url: Option<&str>,
+) -> Result<()> {
+    let entries = cfg
+        .targets
+        .profiles
+        .get(profile)
+        .ok_or_else(|| anyhow!("unknown targets profile '{}'", profile))?;
+    let token = token.context("ci:issue needs a GitHub token (GITHUB_TOKEN)")?;
+    let repo = gh
+        .repository
+        .as_deref()
+        .context("ci:issue needs GITHUB_REPOSITORY")?;
+    let sha = gh
+        .head_sha
+        .as_deref()
+        .context("unable to resolve commit SHA from GitHub event payload or GITHUB_SHA")?;
+    let client = Client { token };
+
+    let statuses: Vec<Status> = client.get(&format!(
+        "/repos/{repo}/commits/{sha}/statuses?per_page=100"
+    ))?;
+    let findings = findings(entries, &statuses);
+    let open: Vec<Issue> = client.get(&format!(
+        "/repos/{repo}/issues?labels={MAINTENANCE_LABEL}&state=open&per_page=1"
+    ))?;
+    let open = open.first().map(|issue| issue.number);
+    let failed = findings.iter().filter(|f| f.failed()).count();
+
+    match (failed, open) {
+        (0, None) => println!("ci:issue {profile}: all {} checks passed", findings.len()),
+        (0, Some(number)) => {
+            client.send::<Value>(
+                "POST",
+                &format!("/repos/{repo}/issues/{number}/comments"),
+                json!({ "body": format!("All `{profile}` checks passed{}; closing.", run_link(run_url)) }),
+            )?;
+            client.send::<Value>(
+                "PATCH",
+                &format!("/repos/{repo}/issues/{number}"),
+                json!({ "state": "closed" }),
+            )?;
+            println!("ci:issue {profile}: all checks passed, closed #{number}");
+        }
+        (_, open) => {
+            let issue = json!({
+                "title": title(profile, failed),
+                "body": body(profile, &findings, sha, run_url),
+                "labels": [MAINTENANCE_LABEL],
+            });
+            match open {
+                Some(number) => {
+                    client.send::<Value>(
+                        "PATCH",
+                        &format!("/repos/{repo}/issues/{number}"),
+                        issue,
+                    )?;
+                    println!("ci:issue {profile}: {failed} failing, updated #{number}");
+                }
+                None => {
+                    let created: Issue =
+                        client.send("POST", &format!("/repos/{repo}/issues"), issue)?;
+                    println!(
+                        "ci:issue {profile}: {failed} failing, opened #{}",
+                        created.number
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The latest status of each entry's `--report` context, or the first
+/// failing one of a matrix job's `<context>-<shard>` contexts; statuses are
+/// listed newest first.
+fn findings(entries: &[TargetEntry], statuses: &[Status]) -> Vec<Finding> {
+    entries
+        .iter()
+        .map(|entry| {
+            let command = entry.command().to_string();
+            let context = command.replace(':', "-");
+            let mut latest: Vec<&Status> = Vec::new();
+            for status in statuses {
+                let shard = status
+                    .context
+                    .strip_prefix(&context)
+                    .is_some_and(|rest| rest.starts_with('-'));
+                if shard && !latest.iter().any(|seen| seen.context == status.context) {
+                    latest.push(status);
+                }
+            }
+            let matrix = latest
+                .iter()
+                .find(|status| status.state != "success")
+                .or(latest.first())
+                .copied();
+            let status = statuses
+                .iter()
+                .find(|status| status.context == context)
+                .or(matrix);
+            match status {
+                Some(status) => Finding {
+                    command,
+                    state: status.state.clone(),
+                    description: status.description.clone().unwrap_or_default(),
+                    url: status.target_url.clone(),
+                },
+                None => Finding {
+                    command,
+                    state: "missing".to_string(),
+                    description: "no status reported (the job did not run?)".to_string(),
+                    url: None,
+                },
+            }
+        })
+        .collect()
+}
+
+fn title(profile: &str, failed: usize) -> String {
+    let checks = if failed == 1 { "check" } else { "checks" };
+    format!("{profile}: {failed} {checks} failing")
+}
+
+fn run_link(run_url: Option<&str>) -> String {
+    run_url
+        .map(|url| format!(" ([run]({url}))"))
+        .unwrap_or_default()
+}
+
+fn body(profile: &str, findings: &[Finding], sha: &str, run_url: Option<&str>) -> String {
+    let mut body = format!(
+        "The scheduled `{profile}` run on `{}` found problems{}.\n\n| Command | Result | Details |\n| --- | --- | --- |\n",
+        &sha[..sha.len().min(12)],
+        run_link(run_url)
+    );
+    for finding in findings {
+        let result = if finding.failed() {
+            format!("❌ {}", finding.state)
+        } else {
+            "✅ success".to_string()
+        };
+        let details = match &finding.url {
+            Some(url) => format!("[{}]({url})", finding.description),
+            None => finding.description.clone(),
+        };
+        body.push_str(&format!(
+            "| `{}` | {result} | {details} |\n",
+            finding.command
+        ));
+    }
+    body.push_str(&format!(
+        "\nThis issue is updated by `dwf ci:issue` on every `{profile}` run and closed once all checks pass.\n"
+    ));
+    body
+}
+
+/// A minimal client for the GitHub REST API.
+struct Client<'a> {
+    token: &'a str,
+}
+
+impl Client<'_> {
+    fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T> {
+        debug!("GET {}", path);
+        ureq::get(&format!("{API}{path}"))
+            .header("Authorization", &format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .call()
+            .with_context(|| format!("GitHub request GET {path} failed"))?
+            .body_mut()
+            .read_json()
+            .with_context(|| format!("unexpected response to GET {path}"))
+    }
+
+    fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        body: Value,
+    ) -> Result<T> {
+        debug!("{} {}", method, path);
+        let url = format!("{API}{path}");
+        let request = match method {
+            "PATCH" => ureq::patch(&url),
+            _ => ureq::post(&url),
+        };
+        request
+            .header("Authorization", &format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send_json(body)
+            .with_context(|| format!("GitHub request {method} {path} failed"))?
+            .body_mut()
+            .read_json()
+            .with_context(|| format!("unexpected response to {method} {path}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(context: &str, state: &str) -> Status {
+        Status {
+            context: context.to_string(),
+            state: state.to_string(),
+            description: Some(format!("{context} {state}")),
+            target_url: None,
+        }
+    }
+
+    #[test]
+    fn summarizes_the_latest_status_of_each_command() {
+        let entries = vec![
+            TargetEntry::Command("check:security".to_string()),
+            TargetEntry::Command("test:integration".to_string()),
+            TargetEntry::Command("build:release".to_string()),
+        ];
+        let statuses = [
+            status("check-security", "failure"),
+            status("test-integration", "success"),
+            status("check-security", "success"),
+        ];
+        let findings = findings(&entries, &statuses);
+        assert_eq!(findings[0].state, "failure");
+        assert_eq!(findings[1].state, "success");
+        assert_eq!(findings[2].state, "missing");
+        assert_eq!(findings.iter().filter(|f| f.failed()).count(), 2);
+
+        let matrix = [
+            status("test-features-serde", "success"),
+            status("test-features-none", "failure"),
+            status("test-features-serde", "failure"),
+        ];
+        let features = super::findings(
+            &[TargetEntry::Command("test:features".to_string())],
+            &matrix,
+        );
+        assert_eq!(features[0].state, "failure");
+        assert_eq!(features[0].description, "test-features-none failure");
+
+        assert_eq!(title("maintenance", 2), "maintenance: 2 checks failing");
+        let body = body(
+            "maintenance",
+            &findings,
+            "0123456789abcdef",
+            Some("https://github.com/o/r/actions/runs/1"),
+        );
+        assert!(body.starts_with(
+            "The scheduled `maintenance` run on `0123456789ab` found problems ([run](https://github.com/o/r/actions/runs/1))."
+        ), "{body}");
+        assert!(
+            body.contains("| `check:security` | ❌ failure | check-security failure |\n"),
+            "{body}"
+        );
+        assert!(
+            body.contains("| `test:integration` | ✅ success |"),
+            "{body}"
+        );
+    }
+}
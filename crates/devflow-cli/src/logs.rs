@@ -0,0 +1,508 @@
+//! Per-run command logs (`[logs]`, `dwf logs`).
+//!
+//! With `[logs] enabled`, each command a run executes writes its output,
+//! without ANSI codes, to `<cache root>/runs/<run id>/` as it arrives, one
+//! file per command and stack. The run id is the start time in UTC
+//! (`20261014-130819`), and `run.json` lists the invocation, the logs in the
+//! order they started and, once the run ended, whether it passed. Only the
+//! last `[logs] keep` runs are kept.
+//!
+//! `dwf logs` prints the logs of the latest run, `dwf logs <run id>
+//! [<command>]` those of one run, or of one command of it (`dwf logs
+//! test:unit` in the latest run), and `dwf logs:list` the runs. `--follow`
+//! tails the run as its commands write, until it ends.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use devflow_core::{CommandRef, DevflowConfig};
+
+use crate::attest::rfc3339;
+use crate::capture::LogFile;
+use crate::executor;
+
+/// Where the runs live under the cache root.
+const RUNS_DIR: &str = "runs";
+
+/// The description of a run in its directory.
+const META_FILE: &str = "run.json";
+
+/// How often `--follow` looks for new output.
+const POLL: Duration = Duration::from_millis(200);
+
+/// A command log of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    file: String,
+    command: String,
+    stack: String,
+}
+
+impl Entry {
+    fn label(&self) -> String {
+        format!("{} ({})", self.command, self.stack)
+    }
+
+    /// Whether `command` names this log: its canonical command, or its
+    /// primary command without a selector.
+    fn matches(&self, command: &str) -> bool {
+        self.command == command
+            || self
+                .command
+                .strip_prefix(command)
+                .is_some_and(|rest| rest.starts_with(':'))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Meta {
+    invocation: String,
+    started_at: String,
+    #[serde(default)]
+    success: Option<bool>,
+    #[serde(default)]
+    logs: Vec<Entry>,
+}
+
+impl Meta {
+    fn status(&self) -> &'static str {
+        match self.success {
+            None => "running",
+            Some(true) => "passed",
+            Some(false) => "failed",
+        }
+    }
+}
+
+/// The run's directory and description, once its first log is created.
+#[derive(Debug)]
+struct Started {
+    dir: PathBuf,
+    meta: Meta,
+}
+
+/// The command logs of the current run.
+#[derive(Debug)]
+pub struct RunLog {
+    root: PathBuf,
+    keep: usize,
+    invocation: String,
+    started: Mutex<Option<Started>>,
+}
+
+impl RunLog {
+    /// The logs of a run of `invocation`, if `[logs]` is enabled. Its
+    /// directory is created with the first log, so runs that execute no
+    /// command leave none behind.
+    pub fn new(cfg: &DevflowConfig, invocation: &CommandRef) -> Option<Self> {
+        cfg.logs.enabled.then(|| Self {
+            root: runs_dir(cfg),
+            keep: cfg.logs.keep,
+            invocation: invocation.canonical(),
+            started: Mutex::new(None),
+        })
+    }
+
+    /// Creates the log of `command` on `stack`.
+    ///
+    /// # Errors
+    /// Returns an error if the run directory or the log cannot be written.
+    pub fn file(&self, command: &str, stack: &str) -> Result<LogFile> {
+        let mut started = self
+            .started
+            .lock()
+            .map_err(|_| anyhow!("run log poisoned"))?;
+        if started.is_none() {
+            *started = Some(self.start()?);
+        }
+        let Some(started) = started.as_mut() else {
+            unreachable!("the run was just started");
+        };
+        let file = format!(
+            "{:02}-{}-{}.log",
+            started.meta.logs.len() + 1,
+            file_stem(command),
+            file_stem(stack)
+        );
+        let log = LogFile::create(&started.dir.join(&file))?;
+        started.meta.logs.push(Entry {
+            file,
+            command: command.to_string(),
+            stack: stack.to_string(),
+        });
+        write_meta(&started.dir, &started.meta)?;
+        Ok(log)
+    }
+
+    /// Records the outcome of the run, if it logged anything; errors only
+    /// warn.
+    pub fn finish(&self, success: bool) {
+        let Ok(mut started) = self.started.lock() else {
+            return;
+        };
+        if let Some(started) = started.as_mut() {
+            started.meta.success = Some(success);
+            if let Err(e) = write_meta(&started.dir, &started.meta) {
+                warn!("failed to finish the run log: {:#}", e);
+            }
+        }
+    }
+
+    /// Creates the directory of this run, then removes the oldest runs
+    /// beyond `keep`.
+    fn start(&self) -> Result<Started> {
+        fs::create_dir_all(&self.root)
+            .with_context(|| format!("failed to create '{}'", self.root.display()))?;
+        let now = SystemTime::now();
+        let stamp: String = rfc3339(now)
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == 'T')
+            .map(|c| if c == 'T' { '-' } else { c })
+            .collect();
+        let mut id = stamp.clone();
+        let mut attempt = 1;
+        let dir = loop {
+            let dir = self.root.join(&id);
+            match fs::create_dir(&dir) {
+                Ok(()) => break dir,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    attempt += 1;
+                    id = format!("{stamp}-{attempt}");
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("failed to create '{}'", dir.display()))
+                }
+            }
+        };
+        let meta = Meta {
+            invocation: self.invocation.clone(),
+            started_at: rfc3339(now),
+            success: None,
+            logs: Vec::new(),
+        };
+        write_meta(&dir, &meta)?;
+        let runs = run_ids(&self.root)?;
+        for old in &runs[..runs.len().saturating_sub(self.keep)] {
+            let _ = fs::remove_dir_all(self.root.join(old));
+        }
+        Ok(Started { dir, meta })
+    }
+}
+
+fn runs_dir(cfg: &DevflowConfig) -> PathBuf {
+    executor::cache_root(cfg).join(RUNS_DIR)
+}
+
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Writes `run.json` through a rename, so a follower never reads half of it.
+fn write_meta(dir: &Path, meta: &Meta) -> Result<()> {
+    let partial = dir.join(format!("{META_FILE}.partial"));
+    fs::write(&partial, serde_json::to_vec_pretty(meta)?)
+        .with_context(|| format!("failed to write '{}'", partial.display()))?;
+    fs::rename(&partial, dir.join(META_FILE))
+        .with_context(|| format!("failed to write '{}'", dir.join(META_FILE).display()))
+}
+
+fn read_meta(dir: &Path) -> Result<Meta> {
+    let path = dir.join(META_FILE);
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("invalid run log '{}'", path.display()))
+}
+
+/// The ids of the runs under `root`, oldest first.
+fn run_ids(root: &Path) -> Result<Vec<String>> {
+    let mut ids: Vec<String> = match fs::read_dir(root) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join(META_FILE).is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read '{}'", root.display())),
+    };
+    ids.sort();
+    Ok(ids)
+}
+
+/// The directory of the run `selector` names: `last` or a run id.
+fn find_run(cfg: &DevflowConfig, selector: &str) -> Result<(String, PathBuf)> {
+    let root = runs_dir(cfg);
+    let ids = run_ids(&root)?;
+    let id = match selector {
+        "last" => ids.last().cloned().ok_or_else(|| {
+            if cfg.logs.enabled {
+                anyhow!("no run has written logs yet")
+            } else {
+                anyhow!("no run logs: set [logs] enabled = true in devflow.toml")
+            }
+        })?,
+        id if ids.iter().any(|known| known == id) => id.to_string(),
+        id => bail!("no run '{id}' with logs (see 'dwf logs:list')"),
+    };
+    let dir = root.join(&id);
+    Ok((id, dir))
+}
+
+/// The logs of `meta` that `command` names, or all of them.
+fn selected<'a>(meta: &'a Meta, command: Option<&str>) -> Vec<&'a Entry> {
+    meta.logs
+        .iter()
+        .filter(|entry| command.is_none_or(|command| entry.matches(command)))
+        .collect()
+}
+
+/// Lists the runs with logs, newest first.
+fn render_list(cfg: &DevflowConfig) -> Result<String> {
+    let root = runs_dir(cfg);
+    let mut out = String::new();
+    for id in run_ids(&root)?.iter().rev() {
+        let meta = read_meta(&root.join(id))?;
+        let commands: Vec<&str> = meta
+            .logs
+            .iter()
+            .map(|entry| entry.command.as_str())
+            .collect();
+        out.push_str(&format!(
+            "{id}  {:<7}  {}  ({})\n",
+            meta.status(),
+            meta.invocation,
+            commands.join(", ")
+        ));
+    }
+    Ok(out)
+}
+
+/// Prints the logs `command` names in the run `dir`.
+fn print_logs(id: &str, dir: &Path, command: Option<&str>, out: &mut impl Write) -> Result<()> {
+    let meta = read_meta(dir)?;
+    let entries = selected(&meta, command);
+    if entries.is_empty() {
+        let logged: Vec<&str> = meta
+            .logs
+            .iter()
+            .map(|entry| entry.command.as_str())
+            .collect();
+        bail!(
+            "run {id} has no log for '{}' (it logged: {})",
+            command.unwrap_or_default(),
+            logged.join(", ")
+        );
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "==> {} <==", entry.label())?;
+        let path = dir.join(&entry.file);
+        let text =
+            fs::read(&path).with_context(|| format!("failed to read '{}'", path.display()))?;
+        out.write_all(&text)?;
+    }
+    Ok(())
+}
+
+/// Prints what the logs `command` names in the run `dir` gained since
+/// `offsets`, with a header whenever the log changes from `current`.
+/// Returns whether anything was printed.
+fn print_new(
+    dir: &Path,
+    entries: &[&Entry],
+    offsets: &mut HashMap<String, u64>,
+    current: &mut Option<String>,
+    out: &mut impl Write,
+) -> Result<bool> {
+    let mut printed = false;
+    for entry in entries {
+        let offset = offsets.entry(entry.file.clone()).or_default();
+        let Ok(mut file) = fs::File::open(dir.join(&entry.file)) else {
+            continue;
+        };
+        file.seek(SeekFrom::Start(*offset))?;
+        let mut new = Vec::new();
+        file.read_to_end(&mut new)?;
+        if new.is_empty() {
+            continue;
+        }
+        if current.as_deref() != Some(entry.file.as_str()) {
+            if current.is_some() {
+                writeln!(out)?;
+            }
+            writeln!(out, "==> {} <==", entry.label())?;
+            *current = Some(entry.file.clone());
+        }
+        out.write_all(&new)?;
+        *offset += new.len() as u64;
+        printed = true;
+    }
+    out.flush()?;
+    Ok(printed)
+}
+
+/// Tails the logs `command` names in the run `dir` until the run ends.
+fn follow(id: &str, dir: &Path, command: Option<&str>, out: &mut impl Write) -> Result<()> {
+    let mut offsets = HashMap::new();
+    let mut current = None;
+    loop {
+        let meta = read_meta(dir)?;
+        let entries = selected(&meta, command);
+        let printed = print_new(dir, &entries, &mut offsets, &mut current, out)?;
+        if meta.success.is_some() && !printed {
+            writeln!(out, "==> run {id} {} <==", meta.status())?;
+            return Ok(());
+        }
+        std::thread::sleep(POLL);
+    }
+}
+
+/// The run and the command `target` names: `last` or a run id, then
+/// optionally `:<command>`; a bare command is looked up in the latest run.
+fn parse_target(target: &str) -> (&str, Option<&str>) {
+    let (run, command) = match target.split_once(':') {
+        Some((run, command)) => (run, Some(command)),
+        None => (target, None),
+    };
+    if run == "last" || run.starts_with(|c: char| c.is_ascii_digit()) {
+        (run, command)
+    } else {
+        ("last", Some(target))
+    }
+}
+
+/// `dwf logs`: lists the runs (`list`), or prints or follows the logs of the
+/// run `target` names, or of one command of it.
+///
+/// # Errors
+/// Returns an error if the run or the command has no logs.
+pub fn run(cfg: &DevflowConfig, target: &str, follow_run: bool) -> Result<()> {
+    if target == "list" {
+        let list = render_list(cfg)?;
+        if list.is_empty() {
+            find_run(cfg, "last")?;
+        }
+        print!("{list}");
+        return Ok(());
+    }
+    let (selector, command) = parse_target(target);
+    let (id, dir) = find_run(cfg, selector)?;
+    let mut out = io::stdout().lock();
+    if follow_run {
+        follow(&id, &dir, command, &mut out)
+    } else {
+        print_logs(&id, &dir, command, &mut out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn logs_commands_per_run_and_prints_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cfg: DevflowConfig = toml::from_str(&format!(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [cache]
+            root = "{}"
+
+            [logs]
+            enabled = true
+            keep = 2
+            "#,
+            dir.path().display()
+        ))
+        .unwrap();
+        let check = CommandRef::from_str("check:pr").unwrap();
+
+        let run = RunLog::new(&cfg, &check).unwrap();
+        run.file("fmt:check", "rust")
+            .unwrap()
+            .line(b"formatted\n")
+            .unwrap();
+        let unit = run.file("test:unit", "rust").unwrap();
+        unit.line(b"running 2 tests\n").unwrap();
+        let (id, run_dir) = find_run(&cfg, "last").unwrap();
+        let meta = read_meta(&run_dir).unwrap();
+        assert_eq!(meta.status(), "running");
+        assert_eq!(
+            meta.logs
+                .iter()
+                .map(|e| e.file.as_str())
+                .collect::<Vec<_>>(),
+            ["01-fmt-check-rust.log", "02-test-unit-rust.log"]
+        );
+
+        let mut offsets = HashMap::new();
+        let mut current = None;
+        let mut out = Vec::new();
+        let entries = selected(&meta, None);
+        assert!(print_new(&run_dir, &entries, &mut offsets, &mut current, &mut out).unwrap());
+        unit.line(b"test result: ok\n").unwrap();
+        assert!(print_new(&run_dir, &entries, &mut offsets, &mut current, &mut out).unwrap());
+        assert!(!print_new(&run_dir, &entries, &mut offsets, &mut current, &mut out).unwrap());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "==> fmt:check (rust) <==\nformatted\n\n==> test:unit (rust) <==\nrunning 2 tests\ntest result: ok\n"
+        );
+
+        run.finish(false);
+        let mut out = Vec::new();
+        follow(&id, &run_dir, Some("test"), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("==> test:unit (rust) <==\nrunning 2 tests\ntest result: ok\n==> run {id} failed <==\n")
+        );
+        let err = print_logs(&id, &run_dir, Some("lint"), &mut Vec::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("run {id} has no log for 'lint' (it logged: fmt:check, test:unit)")
+        );
+
+        // A second run in the same second gets its own id; the third
+        // removes the first.
+        let second = RunLog::new(&cfg, &check).unwrap();
+        second.file("test:unit", "rust").unwrap();
+        second.finish(true);
+        let ids = run_ids(&runs_dir(&cfg)).unwrap();
+        assert_eq!(ids.len(), 2);
+        let third = RunLog::new(&cfg, &check).unwrap();
+        third.file("lint:static", "rust").unwrap();
+        assert!(!run_ids(&runs_dir(&cfg)).unwrap().contains(&id));
+        assert!(render_list(&cfg)
+            .unwrap()
+            .contains("running  check:pr  (lint:static)"));
+
+        assert_eq!(parse_target("last"), ("last", None));
+        assert_eq!(parse_target("test:unit"), ("last", Some("test:unit")));
+        assert_eq!(
+            parse_target("20261014-130819:test"),
+            ("20261014-130819", Some("test"))
+        );
+
+        cfg.logs.enabled = false;
+        assert!(RunLog::new(&cfg, &check).is_none());
+        let err = find_run(&cfg, "nope").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no run 'nope' with logs (see 'dwf logs:list')"
+        );
+    }
+}
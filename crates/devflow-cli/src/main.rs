@@ -1,17 +1,70 @@
+use std::io::IsTerminal;
 use std::str::FromStr;
-use std::{fs, path::Path};
+use std::time::{Duration, Instant};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
 
-use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry, PrimaryCommand};
-use tracing::debug;
+use devflow_core::config::LoadOptions;
+use devflow_core::constants::CONFIG_FILE;
+use devflow_core::diagnostic;
+use devflow_core::reporter::{CheckEnd, CommandEnd, Reporter, Skip, SkipReason};
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry, PrimaryCommand, TargetEntry};
+use devflow_gh::GithubContext;
+use tracing::{debug, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+mod act;
+mod artifacts;
+mod attest;
+mod badge;
+mod capture;
+mod catalog;
+mod changes;
+mod ci_run;
+mod completions;
+mod config_format;
+mod config_show;
+mod conformance;
+mod container;
+mod devcontainer;
+mod diff;
 mod discovery;
+mod dotenv;
+mod env;
 mod executor;
+mod explain;
+mod explain_actions;
+mod fingerprint;
+mod fixtures;
+mod graph;
+mod guard;
+mod hermetic;
+mod history;
+mod host_path;
 mod init;
+mod issue;
+mod logs;
+mod nested;
+mod notify;
+mod prune;
+mod release;
+mod remote;
+mod reporter;
+mod repro;
+mod sarif;
+mod schedule;
+mod serve;
+mod snapshot;
 mod styles;
+mod tools;
+mod umbrella;
+mod volumes;
+mod waits;
 
 use serde_json::json;
 
@@ -39,25 +92,76 @@ Options:
 Commands (by Lifecycle):
   Project Setup
     init                       Bootstrap project from templates
-    setup:doctor               Verify toolchains and environment
+    setup:doctor               Verify toolchains and environment, flagging shadowed tools
     setup:deps                 Fetch and cache dependencies
+    setup:tools                Install the pinned tools extensions need
+    setup:browsers             Install the browsers end-to-end tests run in
 
   Development Loop (Frequent)
     check:pr                   Run standard PR verification (fmt, lint, build, test)
-    fmt:fix                    Automatically apply formatting fixes
+    check --list               List the target profiles and their commands
+    fmt:fix                    Automatically apply formatting fixes (clean tree, or --allow-dirty)
     test:unit                  Run unit tests
     build:debug                Incremental debug build
+    build:wasm                 Build for the configured wasm targets
 
   Security & Infrastructure
     check:security             Run local vulnerability scan
+    check:repro                Build release twice and compare artifacts
+    check:lockfiles            Verify lockfiles are in sync with their manifests
+    check:msrv                 Check the crate builds with its rust-version
+    check:no-std               Check the crate builds for targets without std
+    test:features              Test each feature combination (feature_matrix)
+    test:e2e                   Run Playwright or Cypress tests, keeping failure reports
+    test:visual                Run visual regression tests, keeping image diffs
+    package:attest             Write SLSA provenance for package artifacts
+    release:verify             Check the release guard rails and run the release profile
+    release:candidate          Verify, then build the release candidate
+    release:tag                ...then tag the project version (--dry-run to preview)
+    release:publish            ...then publish and push the tag (--dry-run to preview)
     lint:static                Run static analyzers
+    lint:types                 Type-check TypeScript without emitting (tsc --noEmit)
     ci:generate                Sync GitHub Actions workflow
+    ci:diff                    Show what ci:generate would change (fails on changes)
+    ci:import                  Map a hand-written ci.yml to profiles and user-managed steps
+    ci:run                     Replay a profile's CI jobs locally (--profile pr)
+    ci:debug <JOB>             Run one generated workflow job locally with act
+    ci:issue                   File failed scheduled checks as a GitHub issue (--profile maintenance)
+    fingerprint                Print the environment fingerprint (CI image key)
+    env                        Show the merged extension environment per stack
+    run -- <ARGV>              Run any command in a stack's environment (--stack rust)
+    ext:verify <NAME>          Check a subprocess extension against the protocol
+    container:pull             Pre-pull the container image with progress
+    container:build            Build the CI image with BuildKit layer caching
+    devcontainer:generate      Write .devcontainer/devcontainer.json from the container profile
+    devcontainer:check         Fail if devcontainer.json drifted from the container profile
+    cache:warm                 Seed the caches (dependencies, CI image, compiler cache)
+    cache:export <FILE>        Snapshot the caches to a tarball (--stack to filter)
+    cache:import <FILE>        Restore a cache snapshot (--stack to filter)
+    config:format              Rewrite devflow.toml in canonical form (--check to verify)
+    config:show                Print the effective config and where each setting comes from
+    config:update              Lock the latest commit of the `extends` preset
+    stats                      Summarize run history (--last 30d --command test:unit)
+    badge                      Render an SVG status badge of the latest run (--output)
+    explain-failure            Condense why the latest run failed (--format markdown)
+    explain <COMMAND>          Show what a command ran; --diff: what changed since it passed
+    logs [<RUN>] [<COMMAND>]   Print the command logs of the latest run, or one ([logs]; --follow)
+    logs:list                  List the runs with command logs
+    list                       List the commands of the project's stacks, grouped
+    help <COMMAND>             Describe what a command runs for each stack (help test)
+    completions:bash           Print the bash completion script (also completions:zsh)
+    completions:refresh        Rewrite the project's completion cache
+    serve                      Serve capabilities, runs and history over HTTP (--listen)
     prune:cache                Cleanup local/GH caches
 
 Examples:
   dwf init                     # Bootstrap project
   dwf check pr                 # Run all PR checks
   dwf check security           # Run vulnerability scan
+  dwf check pr --notify        # Desktop notification when the run finishes
+  dwf all check:pr             # Run PR checks for every devflow.toml below here
+  dwf build release --hermetic # Offline build, verify no workspace escapes
+  dwf package attest --sign    # Attest and sign package artifacts
   dwf prune:cache --all        # Prune all caches
 
 Documentation: https://github.com/softmentor/devflow
@@ -68,10 +172,18 @@ pub(crate) struct Cli {
     command: Option<String>,
     /// Optional selector (supports `dwf test unit` style)
     selector: Option<String>,
-    /// Path to devflow config file.
-    #[arg(long, default_value = "devflow.toml")]
+    /// The command whose logs `dwf logs <RUN>` prints, e.g. `test:unit`.
+    #[arg(value_name = "COMMAND")]
+    detail: Option<String>,
+    /// Path to devflow config file. Repeat it to run the command for each
+    /// project in turn, with a combined summary.
+    #[arg(long = "config", value_name = "PATH")]
+    configs: Vec<String>,
+    /// The config this invocation loads: the first `--config`, if any.
+    #[arg(skip)]
     config: String,
-    /// Print generated CI workflow to stdout instead of writing to file.
+    /// Print the generated CI workflow (or devcontainer.json) to stdout
+    /// instead of writing to file.
     #[arg(long, default_value_t = false)]
     stdout: bool,
     /// Output path for `ci:generate` when writing files.
@@ -93,9 +205,167 @@ pub(crate) struct Cli {
     /// Prune everything (local and GH).
     #[arg(long, default_value_t = false)]
     all: bool,
+    /// Show what `prune` would delete (or the `act` call `ci:debug` would
+    /// make, or the tag and publish of `release:*`) without doing it; other
+    /// commands reject it.
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+    /// Run containerized commands without network and with a read-only workspace,
+    /// failing if anything outside `[container] outputs` changed.
+    #[arg(long, default_value_t = false)]
+    hermetic: bool,
+    /// Sign the `package:attest` provenance with cosign (keyless via Sigstore).
+    #[arg(long, default_value_t = false)]
+    sign: bool,
+    /// List the files hashed (and missing) for `fingerprint`.
+    #[arg(long, default_value_t = false)]
+    inputs: bool,
+    /// Show what changed in `explain`'s command since it last passed.
+    #[arg(long, default_value_t = false)]
+    diff: bool,
+    /// List the target profiles `check` can run, with their commands.
+    #[arg(long, default_value_t = false)]
+    list: bool,
+    /// Keep printing the output of `logs` as the run writes it, until it ends.
+    #[arg(long, default_value_t = false)]
+    follow: bool,
+    /// Run `fmt:fix`, `release:*` and other guarded commands despite
+    /// uncommitted changes or a protected branch (`[commands]`
+    /// `require_clean`, `protected_branches`).
+    #[arg(long, default_value_t = false)]
+    allow_dirty: bool,
+    /// Run host commands with dwf's own `PATH` under `[runtime] path =
+    /// "hermetic"`.
+    #[arg(long, default_value_t = false)]
+    inherit_path: bool,
+    /// Fail `fingerprint` unless the aggregate fingerprint equals HASH; with
+    /// `config:format`, report drift instead of rewriting the file.
+    #[arg(long, value_name = "HASH", num_args = 0..=1, default_missing_value = "")]
+    check: Option<String>,
+    /// Generate/check one workflow per target profile (paths from `[ci.outputs]`).
+    #[arg(long, default_value_t = false)]
+    split: bool,
+    /// Warn instead of failing when an extension's version does not satisfy
+    /// `[extensions.<name>] version`.
+    #[arg(long, default_value_t = false)]
+    ignore_version_mismatch: bool,
+    /// Ignore unknown config keys with a warning (e.g. a config written for a newer dwf).
+    #[arg(long, default_value_t = false)]
+    lenient_config: bool,
+    /// Target profile whose workflow `ci:run` replays (or `ci:debug` runs a job
+    /// of, `ci:plan --format dot|mermaid` draws, or `ci:issue` reports on).
+    #[arg(long, default_value = "pr")]
+    profile: String,
+    /// Limit `stats` to runs within DURATION (e.g. `12h`, `30d`, `2w`).
+    #[arg(long, value_name = "DURATION")]
+    last: Option<String>,
+    /// Limit `stats` to one command (e.g. `test:unit`); the run `badge` renders.
+    #[arg(long = "command", value_name = "COMMAND")]
+    stats_command: Option<String>,
+    /// Coverage report (lcov or `coverage-summary.json`) `badge` shows;
+    /// defaults to `[ci.badge] coverage`.
+    #[arg(long, value_name = "PATH")]
+    coverage: Option<String>,
+    /// Write the `badge` SVG to PATH instead of stdout.
+    #[arg(long, value_name = "PATH")]
+    output: Option<String>,
+    /// Output format: `text`, `csv` or `json` for `stats`; `text`, `dot` or
+    /// `mermaid` (the `--profile` graph) for `ci:plan`; `text` or `markdown`
+    /// for `explain-failure`.
+    #[arg(long, default_value = "text")]
+    format: String,
+    /// Address `serve` listens on.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8787")]
+    listen: String,
+    /// Send a desktop notification when the run finishes.
+    #[arg(long, default_value_t = false)]
+    notify: bool,
+    /// Write the problems found in command output to PATH as SARIF.
+    #[arg(long, value_name = "PATH")]
+    sarif: Option<PathBuf>,
+    /// Narrow commands to what the files changed since the base branch affect,
+    /// e.g. the cargo workspace packages depending on them (`DWF_BASE_REF`).
+    #[arg(long, default_value_t = false)]
+    affected: bool,
+    /// Write run events, output included, to PATH as JSON lines (the reporter
+    /// protocol), for GUIs.
+    #[arg(long, value_name = "PATH")]
+    events: Option<PathBuf>,
+    /// Keep `[fixtures]` databases running after `test:integration`, for debugging.
+    #[arg(long, default_value_t = false)]
+    keep: bool,
+    /// Fail when a stack is skipped because its manifest is missing or it
+    /// does not support the command (conflict and path-filter skips still pass).
+    #[arg(long, default_value_t = false)]
+    strict_skips: bool,
+    /// Run every command of a check profile even after one failed.
+    #[arg(long, default_value_t = false)]
+    no_fail_fast: bool,
+    /// Only run the command on this stack (repeatable, e.g. `--stack rust`);
+    /// `run` takes one stack whose environment it uses, `init` sets up these
+    /// stacks, `cache:export`/`cache:import` only cover their caches.
+    #[arg(long)]
+    stack: Vec<String>,
+    /// Stop each stack's command after DURATION (e.g. `5m`).
+    #[arg(long, value_name = "DURATION", value_parser = devflow_core::config::parse_duration)]
+    timeout: Option<std::time::Duration>,
+    /// Retry a failed command up to N times.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+    /// Set a variable for every command this invocation runs, on the host and
+    /// in containers (repeatable, e.g. `--env RUST_LOG=debug`).
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    env: Vec<(String, String)>,
+    /// Runtime profile `init` writes.
+    #[arg(long, value_name = "PROFILE", value_parser = ["auto", "host", "container"])]
+    runtime: Option<String>,
+    /// CI provider `init` writes a workflow for (`none` writes none).
+    #[arg(long, value_name = "PROVIDER", value_parser = ["github", "none"])]
+    ci: Option<String>,
+    /// Which checks `init` puts into the target profiles.
+    #[arg(long, value_name = "PRESET", value_parser = ["minimal", "standard", "strict"])]
+    preset: Option<String>,
+    /// Target profiles `init` writes besides `pr` (e.g. `main,release,security`).
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    profiles: Vec<String>,
+    /// Command line for `run`, after `--` (e.g. `dwf run -- cargo expand`).
+    #[arg(last = true, value_name = "ARGV")]
+    argv: Vec<String>,
+}
+
+/// Runs the command for several projects: each `--config`, or every
+/// `devflow.toml` under the current directory for `dwf all <command>`.
+fn run_umbrella(cli: &Cli, command_name: &str) -> Result<()> {
+    let raw: Vec<std::ffi::OsString> = std::env::args_os().skip(1).collect();
+    if command_name != "all" {
+        let configs: Vec<PathBuf> = cli.configs.iter().map(PathBuf::from).collect();
+        let command = match &cli.selector {
+            Some(selector) => format!("{command_name}:{selector}"),
+            None => command_name.to_string(),
+        };
+        return umbrella::run(&configs, &command, &umbrella::child_args(&raw, false));
+    }
+    let Some(command) = &cli.selector else {
+        bail!("'dwf all' needs a command to run, for example: dwf all check:pr");
+    };
+    if !cli.configs.is_empty() {
+        bail!("'dwf all' discovers its configs; it cannot be combined with --config");
+    }
+    let configs = umbrella::discover(&std::env::current_dir()?)?;
+    umbrella::run(&configs, command, &umbrella::child_args(&raw, true))
+}
+
+fn main() -> std::process::ExitCode {
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            std::process::ExitCode::from(executor::exit_code(&e))
+        }
+    }
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     let format = fmt::format()
         .with_target(false)
         .with_level(true)
@@ -111,7 +381,12 @@ fn main() -> Result<()> {
         .with(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
         .init();
 
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    cli.config = cli
+        .configs
+        .first()
+        .cloned()
+        .unwrap_or_else(|| CONFIG_FILE.to_string());
     debug!("parsed cli arguments: {:?}", cli);
 
     let command_name = match &cli.command {
@@ -124,6 +399,10 @@ fn main() -> Result<()> {
         }
     };
 
+    if command_name == "all" || cli.configs.len() > 1 {
+        return run_umbrella(&cli, command_name);
+    }
+
     let command_text = match &cli.selector {
         Some(selector) => format!("{}:{}", command_name, selector),
         None => command_name.clone(),
@@ -131,53 +410,205 @@ fn main() -> Result<()> {
 
     let command = CommandRef::from_str(&command_text)
         .map_err(|e| anyhow!("failed to parse command '{}': {e}", command_text))?;
+    if let Some(detail) = cli.detail.as_deref() {
+        if command.primary != PrimaryCommand::Logs {
+            bail!("unexpected argument '{detail}' for '{command_text}'");
+        }
+    }
 
     if command.primary == PrimaryCommand::Init {
         return init::run(&cli, command.selector.as_deref());
     }
+    if command.primary == PrimaryCommand::Config {
+        return match command.selector.as_deref().unwrap_or("format") {
+            "format" => config_format::run(&cli.config, cli.check.is_some()),
+            "show" => config_show::show(&cli.config, cli.lenient_config),
+            "update" => config_show::update(&cli.config),
+            selector => Err(anyhow!("unknown config selector '{}'", selector)),
+        };
+    }
+    if command.primary == PrimaryCommand::Completions
+        && command.selector.as_deref() != Some("refresh")
+    {
+        print!(
+            "{}",
+            completions::script(command.selector.as_deref().unwrap_or("bash"))?
+        );
+        return Ok(());
+    }
+    if command.primary == PrimaryCommand::Ext {
+        // Extension authors verify their binary with or without a project.
+        let cfg = if Path::new(&cli.config).exists() {
+            Some(
+                DevflowConfig::load_from_file(&cli.config)
+                    .with_context(|| format!("unable to load config '{}'", cli.config))?,
+            )
+        } else {
+            None
+        };
+        return conformance::run(cfg.as_ref(), command.selector.as_deref());
+    }
 
-    let cfg = DevflowConfig::load_from_file(&cli.config)
-        .with_context(|| format!("unable to load config '{}'", cli.config))?;
+    let (cfg, warnings) = DevflowConfig::load_with_options(
+        &cli.config,
+        &LoadOptions {
+            lenient: cli.lenient_config,
+            ..LoadOptions::default()
+        },
+    )
+    .with_context(|| format!("unable to load config '{}'", cli.config))?;
+    for warning in &warnings {
+        warn!("{}: {}", cli.config, warning);
+    }
+    let (command, alias) = cfg
+        .aliases()
+        .parse(&command_text)
+        .map_err(|e| anyhow!("failed to parse command '{}': {e}", command_text))?;
+    if let Some(alias) = alias.filter(|alias| alias.deprecated) {
+        warn!("{}", alias);
+    }
+    if cli.dry_run && !supports_dry_run(&command) {
+        bail!(
+            "--dry-run is not supported for '{}' (only prune, ci:debug and release:*)",
+            command.canonical()
+        );
+    }
+    if command.primary == PrimaryCommand::Check && cli.list {
+        print!("{}", list_profiles(&cfg));
+        return Ok(());
+    }
+    if !cfg.project.detected_stacks.is_empty() {
+        eprintln!(
+            "detected stacks: {} (from project manifests)",
+            cfg.project.detected_stacks.join(", ")
+        );
+    }
     let mut registry = ExtensionRegistry::discover(&cfg)?;
 
     // Phase 1 Wiring: Explicitly compile in the required trait implementations
-    registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
-    registry.register(Box::new(devflow_ext_node::NodeExtension::new()));
+    registry.register(Box::new(devflow_ext_rust::RustExtension::from_config(&cfg)));
+    registry.register(Box::new(devflow_ext_node::NodeExtension::from_config(&cfg)));
+    registry.register(Box::new(devflow_ext_terraform::TerraformExtension::new()));
 
     // Phase 2 Wiring: Runtime discovery of Subprocess Extensions
-    discovery::discover_subprocess_extensions(&cfg, &mut registry)?;
-
-    registry.validate_target_support(&cfg)?;
+    let failures = discovery::discover_subprocess_extensions(
+        &cfg,
+        &mut registry,
+        discovery::DiscoveryOptions {
+            ignore_version_mismatch: cli.ignore_version_mismatch,
+        },
+    )?;
+
+    discovery::check_required_extensions(&cfg, &registry, &failures)
+        .and_then(|()| discovery::check_stack_support(&cfg, &registry))
+        .and_then(|()| registry.validate_target_support(&cfg))
+        .map_err(|e| diagnostic::locate_in_file(e, &cli.config))?;
+    completions::refresh(&cfg, &registry);
+    if command.primary == PrimaryCommand::Check {
+        // Fail before setup, not when the profile is reached.
+        let selector = command.selector.as_deref().unwrap_or("pr");
+        if !is_stack_check(&cfg, &registry, selector) {
+            devflow_policy::resolve_policy_entries(&cfg, selector)?;
+        }
+    }
 
     execute(&cli, &cfg, &registry, &command)
 }
 
+/// Whether `command` honours `--dry-run`.
+fn supports_dry_run(command: &CommandRef) -> bool {
+    match command.primary {
+        PrimaryCommand::Prune | PrimaryCommand::Release => true,
+        PrimaryCommand::Ci => command
+            .selector
+            .as_deref()
+            .is_some_and(|s| s == "debug" || s.starts_with("debug:")),
+        _ => false,
+    }
+}
+
+/// Whether `check:<selector>` names a stack command rather than a
+/// `[targets]` profile: a configured profile wins, otherwise one of the
+/// project's stacks must advertise the command.
+fn is_stack_check(cfg: &DevflowConfig, registry: &ExtensionRegistry, selector: &str) -> bool {
+    if cfg.targets.profiles.contains_key(selector) {
+        return false;
+    }
+    let capability = format!("check:{selector}");
+    executor::ordered_stacks(cfg)
+        .iter()
+        .filter_map(|stack| registry.get(stack))
+        .any(|ext| ext.capabilities().contains(&capability))
+}
+
+/// The `[targets]` profiles and their commands, one per line (`check --list`).
+fn list_profiles(cfg: &DevflowConfig) -> String {
+    let profiles = &cfg.targets.profiles;
+    if profiles.is_empty() {
+        return "no [targets] profiles are configured\n".to_string();
+    }
+    let width = profiles.keys().map(String::len).max().unwrap_or_default();
+    profiles
+        .iter()
+        .map(|(name, entries)| {
+            let commands: Vec<&str> = entries.iter().map(TargetEntry::command).collect();
+            format!("{name:<width$}  {}\n", commands.join(", "))
+        })
+        .collect()
+}
+
+/// Resolves a GitHub token through the credential provider chain.
+///
+/// Failures are logged rather than propagated: GitHub integration is best-effort.
+fn github_token(cfg: &DevflowConfig) -> Option<devflow_gh::Credential> {
+    match devflow_gh::resolve_github_token(cfg.ci.credential_helper.as_deref()) {
+        Ok(Some(credential)) => {
+            debug!("using GitHub token from {}", credential.source);
+            Some(credential)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("failed to resolve GitHub token: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Makes a token from the keychain or credential helper visible to `gh` subprocesses.
+fn export_gh_token(cfg: &DevflowConfig) {
+    if let Some(credential) = github_token(cfg) {
+        if matches!(
+            credential.source,
+            devflow_gh::CredentialSource::Keychain | devflow_gh::CredentialSource::Helper
+        ) {
+            std::env::set_var("GH_TOKEN", credential.token);
+        }
+    }
+}
+
 /// Reports a GitHub status update.
 fn report_status(
+    gh: &GithubContext,
+    token: Option<&str>,
     context: &str,
     state: &str,
     description: &str,
     target_url: Option<&str>,
 ) -> Result<()> {
-    let token = match std::env::var("GITHUB_TOKEN") {
-        Ok(t) => t,
-        Err(_) => {
-            debug!("GITHUB_TOKEN not set, skipping status reporting");
-            return Ok(());
-        }
+    let Some(token) = token else {
+        debug!("no GitHub token available, skipping status reporting");
+        return Ok(());
     };
 
-    let repo = match std::env::var("GITHUB_REPOSITORY") {
-        Ok(r) => r,
-        Err(_) => {
-            debug!("GITHUB_REPOSITORY not set, skipping status reporting");
-            return Ok(());
-        }
+    let Some(repo) = gh.repository.as_deref() else {
+        debug!("GITHUB_REPOSITORY not set, skipping status reporting");
+        return Ok(());
     };
 
-    let sha = std::env::var("GITHUB_HEAD_SHA")
-        .or_else(|_| std::env::var("GITHUB_SHA"))
-        .context("niether GITHUB_HEAD_SHA nor GITHUB_SHA is set")?;
+    let sha = gh
+        .head_sha
+        .as_deref()
+        .context("unable to resolve commit SHA from GitHub event payload or GITHUB_SHA")?;
 
     let url = format!("https://api.github.com/repos/{}/statuses/{}", repo, sha);
 
@@ -205,12 +636,74 @@ fn report_status(
         Err(e) => {
             // We don't want to fail the whole command just because reporting failed,
             // but we should log it.
-            tracing::warn!("failed to report status to GitHub: {}", e);
+            warn!("failed to report status to GitHub: {}", e);
             Ok(())
         }
     }
 }
 
+/// Whether the actor of the event may push to the repository. Unknown
+/// actors and failed lookups may not.
+fn actor_can_write(gh: &GithubContext, token: Option<&str>) -> bool {
+    let (Some(token), Some(repo), Some(actor)) =
+        (token, gh.repository.as_deref(), gh.actor.as_deref())
+    else {
+        return false;
+    };
+    let url = format!("https://api.github.com/repos/{repo}/collaborators/{actor}/permission");
+    let response = ureq::get(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .call()
+        .and_then(|mut resp| resp.body_mut().read_json::<serde_json::Value>());
+    match response {
+        Ok(body) => matches!(
+            body["permission"].as_str(),
+            Some("admin" | "maintain" | "write")
+        ),
+        Err(e) => {
+            warn!("failed to look up the permission of {}: {}", actor, e);
+            false
+        }
+    }
+}
+
+/// Honors the skip label; returns whether the run is skipped.
+///
+/// Only `check` profiles and reported commands are skipped, and only when the
+/// actor may push to the repository, since anyone allowed to label a pull
+/// request could otherwise skip its checks. A skipped context is reported
+/// `pending`, never `success`, so the label cannot satisfy a required status
+/// check.
+fn skip_labeled_run(
+    gh: &GithubContext,
+    command: &CommandRef,
+    report: Option<&str>,
+    actor_can_write: impl FnOnce() -> bool,
+    post_status: impl FnOnce(&str, &str, &str) -> Result<()>,
+) -> Result<bool> {
+    let skippable = command.primary == PrimaryCommand::Check || report.is_some();
+    if !skippable || !gh.skip_requested() {
+        return Ok(false);
+    }
+    let label = devflow_gh::context::SKIP_CI_LABEL;
+    if !actor_can_write() {
+        warn!(
+            "ignoring the '{}' label: {} cannot push to the repository",
+            label,
+            gh.actor.as_deref().unwrap_or("the actor")
+        );
+        return Ok(false);
+    }
+    println!("{} skipped: pull request is labeled '{}'", command, label);
+    if let Some(context) = report {
+        let description = format!("skipped by the '{label}' label; run without it to report");
+        post_status(context, "pending", &description)?;
+    }
+    Ok(true)
+}
+
 fn get_gha_target_url() -> Option<String> {
     let repo = std::env::var("GITHUB_REPOSITORY").ok()?;
     let run_id = std::env::var("GITHUB_RUN_ID").ok()?;
@@ -227,27 +720,285 @@ fn execute(
     registry: &ExtensionRegistry,
     command: &CommandRef,
 ) -> Result<()> {
+    let gh = GithubContext::from_env().unwrap_or_else(|e| {
+        warn!("ignoring unreadable GitHub event payload: {:#}", e);
+        GithubContext::default()
+    });
+
+    let token = cli
+        .report
+        .as_ref()
+        .and_then(|_| github_token(cfg))
+        .map(|credential| credential.token);
+
+    let skipped = skip_labeled_run(
+        &gh,
+        command,
+        cli.report.as_deref(),
+        || {
+            let token = token.clone().or_else(|| github_token(cfg).map(|c| c.token));
+            actor_can_write(&gh, token.as_deref())
+        },
+        |context, state, description| {
+            let target_url = get_gha_target_url();
+            report_status(
+                &gh,
+                token.as_deref(),
+                context,
+                state,
+                description,
+                target_url.as_deref(),
+            )
+        },
+    )?;
+    if skipped {
+        return Ok(());
+    }
+
+    let history = history::Recorder::new(command).with_run_log(
+        (command.primary != PrimaryCommand::Logs)
+            .then(|| logs::RunLog::new(cfg, command))
+            .flatten(),
+    );
+    let mut reporters = reporter::from_config(cfg);
+    if let Some(path) = &cli.sarif {
+        reporters.push(Box::new(sarif::SarifReporter::new(path.clone())));
+    }
+    if let Some(path) = &cli.events {
+        reporters.push(Box::new(reporter::EventLog::create(path)?));
+    }
+    let reporters = reporter::SkipRecorder::new(&reporters);
     if let Some(context) = &cli.report {
         let target_url = get_gha_target_url();
         report_status(
+            &gh,
+            token.as_deref(),
             context,
             "pending",
             &format!("Running {}...", context),
             target_url.as_deref(),
         )?;
 
-        let result = execute_inner(cli, cfg, registry, command);
+        let result = execute_inner(cli, cfg, registry, &gh, command, &history, &reporters);
+        finish_run(cli, cfg, &history, &result);
+        print_skips(&reporters.skips());
 
         let (state, desc) = match &result {
             Ok(_) => ("success", format!("{} passed", context)),
             Err(_) => ("failure", format!("{} failed", context)),
         };
 
-        report_status(context, state, &desc, target_url.as_deref())?;
+        report_status(
+            &gh,
+            token.as_deref(),
+            context,
+            state,
+            &desc,
+            target_url.as_deref(),
+        )?;
+        result
+    } else {
+        let result = execute_inner(cli, cfg, registry, &gh, command, &history, &reporters);
+        finish_run(cli, cfg, &history, &result);
+        print_skips(&reporters.skips());
         result
+    }
+}
+
+/// Lists what the run skipped and why.
+fn print_skips(skips: &[Skip]) {
+    if skips.is_empty() {
+        return;
+    }
+    println!("skipped:");
+    for skip in skips {
+        println!(" - {}", skip);
+    }
+}
+
+/// Records a run that executed commands and sends its notifications.
+fn finish_run(cli: &Cli, cfg: &DevflowConfig, history: &history::Recorder, result: &Result<()>) {
+    let commands = history.commands();
+    if commands.is_empty() {
+        return;
+    }
+    history.save(cfg, result.is_ok());
+    if let Some(run_log) = history.run_log() {
+        run_log.finish(result.is_ok());
+    }
+    let summary = notify::RunSummary {
+        project: cfg.project.name.clone(),
+        invocation: history.invocation().to_string(),
+        success: result.is_ok(),
+        duration: history.elapsed(),
+        commands,
+        error: result.as_ref().err().map(|e| format!("{e:#}")),
+        url: get_gha_target_url(),
+    };
+    notify::send(&notify::notifiers(cfg, cli.notify), &summary);
+}
+
+/// Describes which stacks handle `cmd`, e.g. `test:unit -> custom (shadowed: rust)`.
+fn plan_line(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    cmd: &CommandRef,
+) -> Result<String> {
+    let resolution = executor::resolve(cfg, registry, cmd)?;
+    let stacks = |planned: &[executor::PlannedAction]| {
+        planned
+            .iter()
+            .map(|p| p.stack.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut line = format!("{} -> ", cmd);
+    if resolution.selected.is_empty() {
+        line.push_str("(no stack)");
     } else {
-        execute_inner(cli, cfg, registry, command)
+        line.push_str(&stacks(&resolution.selected));
+    }
+    if !resolution.shadowed.is_empty() {
+        line.push_str(&format!(" (shadowed: {})", stacks(&resolution.shadowed)));
+    }
+    Ok(line)
+}
+
+/// Builds executor options from CLI flags.
+fn run_options(cli: &Cli) -> executor::RunOptions {
+    executor::RunOptions {
+        hermetic: cli.hermetic,
+        keep_fixtures: cli.keep,
+        timeout: cli.timeout,
+        retries: cli.retries,
+        stacks: cli.stack.clone(),
+        strict_skips: cli.strict_skips,
+        env: cli.env.iter().cloned().collect(),
+        inherit_path: cli.inherit_path,
+        ..executor::RunOptions::default()
+    }
+}
+
+/// Parses a `--env KEY=VALUE` pair.
+fn parse_env_var(text: &str) -> Result<(String, String)> {
+    let (key, value) = text
+        .split_once('=')
+        .ok_or_else(|| anyhow!("invalid --env '{text}' (expected KEY=VALUE)"))?;
+    let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("invalid --env variable name '{key}'");
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// The run options for a profile entry: its own timeout, retries and stacks
+/// take precedence over the command line.
+fn entry_options(cli: &Cli, entry: &devflow_policy::PolicyCommand) -> executor::RunOptions {
+    let mut opts = run_options(cli);
+    if entry.timeout.is_some() {
+        opts.timeout = entry.timeout;
+    }
+    if entry.retries > 0 {
+        opts.retries = entry.retries;
+    }
+    if !entry.stacks.is_empty() {
+        opts.stacks = entry.stacks.clone();
+    }
+    opts
+}
+
+/// Computes the changed file set for path-filtered commands.
+///
+/// Returns `None` when changes cannot be determined, in which case every
+/// command runs (fail open).
+fn detect_changes(gh: &GithubContext) -> Option<Vec<String>> {
+    let base = changes::resolve_base_ref(gh, std::env::var("DWF_BASE_REF").ok().as_deref());
+    let cwd = std::env::current_dir().ok()?;
+    // CI checkouts are clean; locally new files are part of the change.
+    match changes::changed_files(&cwd, &base, !notify::in_ci()) {
+        Ok(files) => {
+            debug!("{} file(s) changed relative to {}", files.len(), base);
+            Some(files)
+        }
+        Err(e) => {
+            warn!("change detection failed, running all commands: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Runs `test:features` as one `test:features:<shard>` per feature set or
+/// partition, all of them even when one fails.
+fn run_feature_matrix(
+    cli: &Cli,
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    shards: &[String],
+    opts: &executor::RunOptions,
+    history: &history::Recorder,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let mut failed = Vec::new();
+    for shard in shards {
+        let cmd = CommandRef {
+            primary: PrimaryCommand::Test,
+            selector: Some(format!("features:{shard}")),
+        };
+        if let Err(e) = run_command(cli, cfg, registry, &cmd, opts, history, reporter) {
+            warn!("{:#}", e);
+            failed.push(shard.as_str());
+        }
+    }
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "test:features failed for {} of {} feature sets: {}",
+            failed.len(),
+            shards.len(),
+            failed.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// The outcome of a profile run from its entries' `results`, in profile
+/// order: a single failure as it is, several (under `--no-fail-fast` or
+/// with `[concurrency]`) as one error listing each command's exit, caused
+/// by the first failure.
+fn profile_result(
+    selector: &str,
+    entries: &[devflow_policy::PolicyCommand],
+    results: Vec<Option<Result<()>>>,
+) -> Result<()> {
+    let mut failures: Vec<(String, anyhow::Error)> = entries
+        .iter()
+        .zip(results)
+        .filter_map(|(entry, result)| match result {
+            Some(Err(e)) => Some((entry.command.canonical(), e)),
+            _ => None,
+        })
+        .collect();
+    if failures.len() <= 1 {
+        return failures.pop().map_or(Ok(()), |(_, e)| Err(e));
     }
+    let exits = failures
+        .iter()
+        .map(|(command, e)| match executor::CommandFailed::find(e) {
+            Some(failed) => match (failed.code(), failed.signal()) {
+                (Some(code), _) => format!("{command} (exit code {code})"),
+                (None, Some(signal)) => format!("{command} (signal {signal})"),
+                (None, None) => command.clone(),
+            },
+            None => command.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let count = failures.len();
+    let (_, first) = failures.remove(0);
+    Err(first.context(format!(
+        "check:{selector} failed for {count} of {} commands: {exits}",
+        entries.len()
+    )))
 }
 
 /// Internal execution logic.
@@ -255,91 +1006,404 @@ fn execute_inner(
     cli: &Cli,
     cfg: &DevflowConfig,
     registry: &ExtensionRegistry,
+    gh: &GithubContext,
     command: &CommandRef,
+    history: &history::Recorder,
+    reporter: &reporter::SkipRecorder,
 ) -> Result<()> {
+    guard::check(cfg, command, cli.allow_dirty)?;
     match command.primary {
         PrimaryCommand::Check => {
             let selector = command.selector.as_deref().unwrap_or("pr");
-            let resolved = devflow_policy::resolve_policy_commands(cfg, selector)?;
+            let stack_check = is_stack_check(cfg, registry, selector);
+            if selector == "repro" && stack_check {
+                return repro::run(cfg, registry, &run_options(cli));
+            }
+            // Stack commands, not profiles.
+            if stack_check {
+                registry.ensure_can_run(command)?;
+                return run_command(
+                    cli,
+                    cfg,
+                    registry,
+                    command,
+                    &run_options(cli),
+                    history,
+                    reporter,
+                );
+            }
+            let resolved = devflow_policy::resolve_policy_entries(cfg, selector)?;
             println!("check:{selector} (runtime={:?})", cfg.runtime.profile);
-            for cmd in resolved {
-                registry.ensure_can_run(&cmd)?;
+            let changed = if cli.affected || resolved.iter().any(|entry| !entry.paths.is_empty()) {
+                detect_changes(gh)
+            } else {
+                None
+            };
+            let commands: Vec<CommandRef> =
+                resolved.iter().map(|entry| entry.command.clone()).collect();
+            let session =
+                executor::ContainerSession::start(cfg, registry, &commands, &run_options(cli))?
+                    .map(std::sync::Arc::new);
+            let started = std::time::Instant::now();
+            let earlier = history.commands().len();
+            let earlier_skips = reporter.skips().len();
+            let run_entry = |entry: &devflow_policy::PolicyCommand| {
+                let cmd = &entry.command;
+                registry.ensure_can_run(cmd)?;
+                guard::check(cfg, cmd, cli.allow_dirty)?;
+                if let Some(changed) = &changed {
+                    if !entry.matches_changes(changed) {
+                        let skip = Skip {
+                            command: cmd.canonical(),
+                            stack: None,
+                            reason: SkipReason::PathFilter,
+                        };
+                        println!(" - {} (skipped: {})", cmd, skip.reason.as_str());
+                        reporter.on_skip(&skip);
+                        return Ok(());
+                    }
+                }
                 println!(" - {}", cmd);
-                executor::run(cfg, registry, &cmd)?;
-            }
-            Ok(())
+                let opts = executor::RunOptions {
+                    session: session.clone(),
+                    affected: changed.clone().filter(|_| cli.affected),
+                    ..entry_options(cli, entry)
+                };
+                run_command(cli, cfg, registry, cmd, &opts, history, reporter)
+            };
+            let fail_fast = !cli.no_fail_fast;
+            let results = match &cfg.concurrency {
+                Some(limits) => schedule::run_limited(
+                    &resolved,
+                    |entry| schedule::resource_class(cfg, registry, &entry.command),
+                    limits,
+                    fail_fast,
+                    run_entry,
+                ),
+                None => {
+                    let mut results = Vec::new();
+                    for entry in &resolved {
+                        let result = run_entry(entry);
+                        let failed = result.is_err();
+                        results.push(Some(result));
+                        if failed && fail_fast {
+                            break;
+                        }
+                    }
+                    results
+                }
+            };
+            let result = profile_result(selector, &resolved, results);
+            let ran = &history.commands()[earlier..];
+            reporter.on_check_end(&CheckEnd {
+                profile: selector.to_string(),
+                success: result.is_ok(),
+                duration_ms: millis(started.elapsed()),
+                commands: ran.len(),
+                failed: ran.iter().filter(|c| !c.success).count(),
+                exit_code: result
+                    .as_ref()
+                    .err()
+                    .map_or(0, |e| executor::exit_code(e).into()),
+                skipped: reporter.skips().split_off(earlier_skips),
+            });
+            result
+        }
+        PrimaryCommand::Package if command.selector.as_deref() == Some("attest") => {
+            attest::run(cfg, registry, &run_options(cli), cli.sign)
         }
         PrimaryCommand::Ci if command.selector.as_deref() == Some("generate") => {
-            let workflow = devflow_gh::render_workflow(cfg)?;
-            if cli.stdout {
-                println!("{workflow}");
-            } else {
-                write_ci_workflow(&cli.ci_output, &workflow)?;
-                println!("ci:generate wrote {}", cli.ci_output);
+            let workflows = ci_workflows(cli, cfg)?;
+            let split = workflows.len() > 1;
+            for (_, path, workflow) in &workflows {
+                if cli.stdout {
+                    if split {
+                        println!("# {path}");
+                    }
+                    println!("{workflow}");
+                } else {
+                    write_ci_workflow(path, workflow)?;
+                    println!("ci:generate wrote {}", path);
+                }
             }
             Ok(())
         }
         PrimaryCommand::Ci if command.selector.as_deref() == Some("check") => {
-            let expected = devflow_gh::render_workflow(cfg)?;
-            let actual = read_ci_workflow(&cli.ci_output)?;
-            devflow_gh::check_workflow(cfg, &actual)?;
-            if actual != expected {
-                return Err(anyhow!(
-                    "ci workflow drift detected in '{}': run 'dwf ci:generate' to resync",
-                    cli.ci_output
-                ));
+            let mut failures = Vec::new();
+            for (profile, path, expected) in ci_workflows(cli, cfg)? {
+                let actual = match read_ci_workflow(&path) {
+                    Ok(actual) => actual,
+                    Err(e) => {
+                        failures.push(format!("{e:#}"));
+                        continue;
+                    }
+                };
+                let drift = diff::unified(
+                    &expected,
+                    &actual,
+                    "expected (dwf ci:generate)",
+                    &path,
+                    diff::stderr_color(),
+                );
+                if let Some(drift) = &drift {
+                    eprint!("{drift}");
+                }
+                if let Err(e) = devflow_gh::check_profile_workflow(cfg, &profile, &actual) {
+                    failures.push(format!("{path}: {e}"));
+                } else if drift.is_some() {
+                    failures.push(format!(
+                        "ci workflow drift detected in '{}': run 'dwf ci:generate' to resync",
+                        path
+                    ));
+                }
+            }
+            if !failures.is_empty() {
+                return Err(anyhow!(failures.join("\n")));
             }
             println!("ci:check passed");
             Ok(())
         }
+        PrimaryCommand::Ci if command.selector.as_deref() == Some("diff") => {
+            let mut changed = Vec::new();
+            for (_, path, rendered) in ci_workflows(cli, cfg)? {
+                let (current, label) = if Path::new(&path).exists() {
+                    (read_ci_workflow(&path)?, path.clone())
+                } else {
+                    (String::new(), "/dev/null".to_string())
+                };
+                if let Some(diff) = diff::unified(
+                    &current,
+                    &rendered,
+                    &label,
+                    &format!("{path} (dwf ci:generate)"),
+                    diff::stdout_color(),
+                ) {
+                    print!("{diff}");
+                    changed.push(path);
+                }
+            }
+            if !changed.is_empty() {
+                return Err(anyhow!("ci:generate would change {}", changed.join(", ")));
+            }
+            println!("ci:diff: workflows are up to date");
+            Ok(())
+        }
+        PrimaryCommand::Ci if command.selector.as_deref() == Some("import") => {
+            import_ci_workflow(cli, cfg)
+        }
+        PrimaryCommand::Ci if command.selector.as_deref() == Some("run") => {
+            let entries = cfg.targets.profiles.get(&cli.profile);
+            let changed = if entries.is_some_and(|e| e.iter().any(|e| !e.paths().is_empty())) {
+                detect_changes(gh)
+            } else {
+                None
+            };
+            ci_run::run(cfg, registry, &cli.profile, changed.as_deref())
+        }
+        PrimaryCommand::Ci
+            if command
+                .selector
+                .as_deref()
+                .is_some_and(|s| s == "debug" || s.starts_with("debug:")) =>
+        {
+            let selector = command.selector.as_deref().unwrap_or_default();
+            let job = selector.strip_prefix("debug:").unwrap_or("verify");
+            act::debug(cfg, &cli.profile, job, cli.dry_run)
+        }
+        PrimaryCommand::Ci if command.selector.as_deref() == Some("issue") => {
+            let token = github_token(cfg).map(|credential| credential.token);
+            issue::run(
+                cfg,
+                gh,
+                token.as_deref(),
+                &cli.profile,
+                get_gha_target_url().as_deref(),
+            )
+        }
         PrimaryCommand::Ci if command.selector.as_deref() == Some("plan") => {
-            let profiles = cfg
-                .targets
-                .profiles
-                .keys()
-                .cloned()
-                .collect::<Vec<_>>()
-                .join(", ");
-            println!("ci:plan profiles=[{}]", profiles);
+            if let Some(format) = graph::plan_format(&cli.format)? {
+                print!("{}", graph::render(cfg, registry, &cli.profile, format)?);
+                return Ok(());
+            }
+            let profiles = cfg.targets.profiles.keys().cloned().collect::<Vec<_>>();
+            println!("ci:plan profiles=[{}]", profiles.join(", "));
+            if !cli.env.is_empty() {
+                let env: Vec<String> = cli.env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+                println!("env: {}", env.join(" "));
+            }
+            for profile in &profiles {
+                println!("{profile}:");
+                for entry in devflow_policy::resolve_policy_entries(cfg, profile)? {
+                    println!(" - {}", plan_line(cfg, registry, &entry.command)?);
+                }
+            }
+            Ok(())
+        }
+        PrimaryCommand::Fingerprint => {
+            if cli.check.as_deref() == Some("") {
+                return Err(anyhow!("fingerprint --check requires the expected HASH"));
+            }
+            fingerprint::run(
+                cfg,
+                registry,
+                &fingerprint::FingerprintOptions {
+                    show_inputs: cli.inputs,
+                    check: cli.check.clone(),
+                },
+            )
+        }
+        PrimaryCommand::Env => env::run(cfg, registry),
+        PrimaryCommand::Setup if command.selector.as_deref() == Some("tools") => {
+            tools::setup(cfg, registry)
+        }
+        PrimaryCommand::Setup
+            if command
+                .selector
+                .as_deref()
+                .unwrap_or(command.primary.default_selector())
+                == "doctor" =>
+        {
+            registry.ensure_can_run(command)?;
+            run_command(cli, cfg, registry, command, &run_options(cli), history, reporter)?;
+            host_path::doctor(cfg, registry)
+        }
+        PrimaryCommand::Run => {
+            let stack = match cli.stack.as_slice() {
+                [] => None,
+                [stack] => Some(stack.as_str()),
+                _ => return Err(anyhow!("`run` takes a single --stack")),
+            };
+            executor::run_raw(cfg, registry, stack, &cli.argv, &run_options(cli))
+        }
+        PrimaryCommand::Stats => match command.selector.as_deref().unwrap_or("show") {
+            "show" => history::report(
+                cfg,
+                cli.last.as_deref(),
+                cli.stats_command.as_deref(),
+                &cli.format,
+            ),
+            selector => Err(anyhow!("unknown stats selector '{}'", selector)),
+        },
+        PrimaryCommand::Badge => match command.selector.as_deref().unwrap_or("svg") {
+            "svg" => badge::run(
+                cfg,
+                cli.stats_command.as_deref(),
+                cli.coverage.as_deref(),
+                cli.output.as_deref(),
+            ),
+            selector => Err(anyhow!("unknown badge selector '{}'", selector)),
+        },
+        PrimaryCommand::ExplainFailure => match command.selector.as_deref().unwrap_or("last") {
+            "last" => explain::run(cfg, registry, &cli.format),
+            selector => Err(anyhow!("unknown explain-failure selector '{}'", selector)),
+        },
+        PrimaryCommand::Explain => {
+            explain_actions::run(cfg, command.selector.as_deref().unwrap_or("last"), cli.diff)
+        }
+        PrimaryCommand::Logs => {
+            let run = command.selector.as_deref().unwrap_or("last");
+            let target = match &cli.detail {
+                Some(detail) => format!("{run}:{detail}"),
+                None => run.to_string(),
+            };
+            logs::run(cfg, &target, cli.follow)
+        }
+        PrimaryCommand::Release => {
+            let step = |cmd: &CommandRef| {
+                if cmd.primary == PrimaryCommand::Check {
+                    return execute_inner(cli, cfg, registry, gh, cmd, history, reporter);
+                }
+                registry.ensure_can_run(cmd)?;
+                run_command(cli, cfg, registry, cmd, &run_options(cli), history, reporter)
+            };
+            release::run(
+                cfg,
+                registry,
+                command.selector.as_deref().unwrap_or("candidate"),
+                cli.dry_run,
+                &step,
+            )
+        }
+        PrimaryCommand::List => catalog::list(cfg, registry),
+        PrimaryCommand::Completions => {
+            let path = completions::write_cache(cfg, registry)?;
+            println!("completions:refresh wrote {}", path.display());
             Ok(())
         }
+        PrimaryCommand::Help => {
+            catalog::help(cfg, registry, command.selector.as_deref().unwrap_or("all"))
+        }
+        PrimaryCommand::Serve => serve::run(cfg, registry, &cli.config, &cli.listen),
+        PrimaryCommand::Container => match command.selector.as_deref().unwrap_or("pull") {
+            "pull" => container::pull_configured(cfg),
+            "build" => container::build(cfg),
+            selector => Err(anyhow!("unknown container selector '{}'", selector)),
+        },
+        PrimaryCommand::Devcontainer => match command.selector.as_deref().unwrap_or("check") {
+            "generate" => devcontainer::generate(cfg, registry, cli.stdout),
+            "check" => devcontainer::check(cfg, registry),
+            selector => Err(anyhow!("unknown devcontainer selector '{}'", selector)),
+        },
+        PrimaryCommand::Cache => match command.selector.as_deref().unwrap_or("warm") {
+            "warm" => warm_caches(cli, cfg, registry, history, reporter),
+            selector @ ("export" | "import") => Err(anyhow!(
+                "cache:{selector} needs the snapshot file, e.g. 'dwf cache:{selector} cache.tar.zst'"
+            )),
+            selector => match selector.split_once(':') {
+                Some(("export", file)) => snapshot::export(cfg, registry, file, &cli.stack),
+                Some(("import", file)) => snapshot::import(cfg, registry, file, &cli.stack),
+                _ => Err(anyhow!("unknown cache selector '{}'", selector)),
+            },
+        },
         PrimaryCommand::Prune => {
             let selector = command.selector.as_deref().unwrap_or("cache");
+            if cli.gh || cli.all {
+                export_gh_token(cfg);
+            }
             match selector {
                 "cache" => {
                     if cli.local || cli.all {
-                        let cache_dir = cfg
-                            .cache
-                            .as_ref()
-                            .and_then(|c| c.root.as_ref())
-                            .map(Path::new)
-                            .unwrap_or(Path::new(".cargo-cache"));
-                        let target_ci = Path::new("target/ci");
-
-                        let before_size = get_dir_size(cache_dir) + get_dir_size(target_ci);
-                        println!(
-                            "🧹 Pruning local caches (Current size: {} MB)...",
-                            before_size / 1024 / 1024
-                        );
-
-                        if cache_dir.exists() {
-                            fs::remove_dir_all(cache_dir).with_context(|| {
-                                format!("failed to remove cache dir '{}'", cache_dir.display())
-                            })?;
+                        let targets = prune::local_targets(cfg, registry)?;
+                        let sizes: Vec<u64> = targets.iter().map(|t| get_dir_size(t)).collect();
+                        let before_size: u64 = sizes.iter().sum();
+                        if cli.dry_run {
+                            println!(
+                                "🔎 Would prune {} local cache directories ({} MB):",
+                                targets.len(),
+                                before_size / 1024 / 1024
+                            );
+                            for (target, size) in targets.iter().zip(&sizes) {
+                                println!("  {} ({} MB)", target.display(), size / 1024 / 1024);
+                            }
+                        } else {
+                            println!(
+                                "🧹 Pruning local caches (Current size: {} MB)...",
+                                before_size / 1024 / 1024
+                            );
+                            for target in &targets {
+                                fs::remove_dir_all(target).with_context(|| {
+                                    format!("failed to remove cache dir '{}'", target.display())
+                                })?;
+                            }
+                            let after_size: u64 = targets.iter().map(|t| get_dir_size(t)).sum();
+                            println!(
+                                "✨ Local cache pruned. (New size: {} MB, Reclaimed: {} MB)",
+                                after_size / 1024 / 1024,
+                                (before_size.saturating_sub(after_size)) / 1024 / 1024
+                            );
                         }
-                        if target_ci.exists() {
-                            fs::remove_dir_all(target_ci)
-                                .with_context(|| "failed to remove target/ci")?;
-                        }
-
-                        let after_size = get_dir_size(cache_dir) + get_dir_size(target_ci);
+                    }
+                    if (cli.gh || cli.all) && cli.dry_run {
                         println!(
-                            "✨ Local cache pruned. (New size: {} MB, Reclaimed: {} MB)",
-                            after_size / 1024 / 1024,
-                            (before_size.saturating_sub(after_size)) / 1024 / 1024
+                            "🔎 Would {} GitHub Actions caches (Current: {} MB)",
+                            if cli.force {
+                                "delete ALL"
+                            } else {
+                                "prune stale"
+                            },
+                            get_gh_cache_size().unwrap_or(0) / 1024 / 1024
                         );
-                    }
-                    if (cli.gh || cli.all) && cli.force {
+                    } else if (cli.gh || cli.all) && cli.force {
                         let before_size = get_gh_cache_size().unwrap_or(0);
                         println!(
                             "🔥 Force-pruning ALL GitHub Actions caches (Current: {} MB)...",
@@ -367,7 +1431,12 @@ fn execute_inner(
                     }
                 }
                 "runs" => {
-                    if cli.gh || cli.all {
+                    if (cli.gh || cli.all) && cli.dry_run {
+                        println!(
+                            "🔎 Would prune failed, cancelled, and old GitHub Actions runs (Current: {} runs)",
+                            get_gh_run_count().unwrap_or(0)
+                        );
+                    } else if cli.gh || cli.all {
                         let before_count = get_gh_run_count().unwrap_or(0);
                         println!(
                             "🧹 Pruning GitHub Actions workflow runs (Current: {} runs)...",
@@ -388,9 +1457,149 @@ fn execute_inner(
         }
         _ => {
             registry.ensure_can_run(command)?;
-            executor::run(cfg, registry, command)
+            let opts = executor::RunOptions {
+                affected: cli.affected.then(|| detect_changes(gh)).flatten(),
+                ..run_options(cli)
+            };
+            run_command(cli, cfg, registry, command, &opts, history, reporter)
+        }
+    }
+}
+
+/// Runs `cmd` through the executor, records it in the run history and
+/// reports its start and end; `fmt:check` also requires the config file
+/// itself to be canonically formatted (see `config:format`). A failed run is
+/// retried `opts.retries` times; only the last attempt is recorded, and a
+/// failed test keeps its reports (see [`artifacts`]). In GitHub Actions the
+/// command's output is a collapsible log group, and a failure is annotated.
+fn run_command(
+    cli: &Cli,
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    cmd: &CommandRef,
+    opts: &executor::RunOptions,
+    history: &history::Recorder,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    if cmd.canonical() == "test:features" {
+        let shards = cfg.feature_matrix().shards();
+        if !shards.is_empty() {
+            return run_feature_matrix(cli, cfg, registry, &shards, opts, history, reporter);
+        }
+    }
+    let selector = cmd
+        .selector
+        .as_deref()
+        .unwrap_or_else(|| cmd.primary.default_selector());
+    let name = format!("{}:{}", cmd.primary.as_str(), selector);
+    let logged_opts;
+    let opts = match history.run_log() {
+        Some(run_log) if opts.run_log.is_none() => {
+            logged_opts = executor::RunOptions {
+                run_log: Some(run_log),
+                ..opts.clone()
+            };
+            &logged_opts
+        }
+        _ => opts,
+    };
+    let grouped = devflow_gh::log::enabled();
+    if grouped {
+        println!("{}", devflow_gh::log::group(&name));
+    }
+    reporter.on_command_start(&name);
+    let started = std::time::Instant::now();
+    let mut stats = executor::CommandStats::default();
+    let mut attempt = 0;
+    let mut result = loop {
+        let result = executor::run_with_stats(cfg, registry, cmd, opts, &mut stats, reporter);
+        match result {
+            Err(e) if attempt < opts.retries => {
+                attempt += 1;
+                warn!(
+                    "{} failed, retrying ({}/{}): {:#}",
+                    name, attempt, opts.retries, e
+                );
+                stats = executor::CommandStats::default();
+            }
+            result => break result,
+        }
+    };
+    if result.is_ok() && cmd.primary == PrimaryCommand::Fmt && selector == "check" {
+        result = config_format::verify(&cli.config);
+    }
+    let artifacts = if result.is_err() {
+        artifacts::keep_failure_artifacts(cfg, registry, cmd)
+    } else {
+        Vec::new()
+    };
+    let duration = started.elapsed();
+    if grouped {
+        println!("{}", devflow_gh::log::end_group());
+        for problem in &stats.problems {
+            println!("{}", devflow_gh::log::problem(problem));
+        }
+        if let Err(e) = &result {
+            println!(
+                "{}",
+                devflow_gh::log::error(&format!("{name} failed"), &format!("{e:#}"))
+            );
+        }
+    }
+    let problems = std::mem::take(&mut stats.problems);
+    if let Err(e) = &result {
+        history.failure(&name, e, &stats);
+    }
+    history.command(&name, duration, result.is_ok(), stats);
+    let failed = result
+        .as_ref()
+        .err()
+        .and_then(executor::CommandFailed::find);
+    reporter.on_command_end(&CommandEnd {
+        command: name,
+        success: result.is_ok(),
+        duration_ms: millis(duration),
+        error: result.as_ref().err().map(|e| format!("{e:#}")),
+        exit_code: failed.and_then(executor::CommandFailed::code),
+        signal: failed.and_then(executor::CommandFailed::signal),
+        problems,
+        artifacts,
+    });
+    result
+}
+
+/// Seeds the caches (`cache:warm`): pulls the CI image of containerized
+/// runs, then runs each stack's warm commands, e.g. `cargo fetch` and a
+/// debug build priming sccache, so they fill the same cache mounts.
+fn warm_caches(
+    cli: &Cli,
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    history: &history::Recorder,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    if executor::uses_container_proxy(cfg) {
+        container::pull_configured(cfg)?;
+    }
+    for stack in executor::ordered_stacks(cfg) {
+        for cmd in registry.warm_commands(&stack) {
+            println!("cache:warm {stack}: {cmd}");
+            let opts = executor::RunOptions {
+                stacks: vec![stack.clone()],
+                ..run_options(cli)
+            };
+            run_command(cli, cfg, registry, &cmd, &opts, history, reporter)?;
         }
     }
+    println!(
+        "cache:warm caches ready in {}",
+        executor::cache_root(cfg).display()
+    );
+    Ok(())
+}
+
+fn millis(duration: std::time::Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
 }
 
 fn run_gh_prune_cache(force: bool) -> Result<()> {
@@ -450,21 +1659,31 @@ fn run_gh_prune_runs() -> Result<()> {
     Ok(())
 }
 
+/// The size of `path`, with a running file count on an interactive stderr.
 fn get_dir_size(path: &Path) -> u64 {
-    if !path.exists() {
-        return 0;
-    }
-    if path.is_file() {
-        return path.metadata().map(|m| m.len()).unwrap_or(0);
-    }
-    fs::read_dir(path)
-        .map(|entries| {
-            entries
-                .filter_map(|e| e.ok())
-                .map(|e| get_dir_size(&e.path()))
-                .sum()
-        })
-        .unwrap_or(0)
+    if !std::io::stderr().is_terminal() {
+        return devflow_core::walk::size(path);
+    }
+    // When the status line was last drawn, if ever
+    let shown = std::sync::Mutex::new((Instant::now(), false));
+    let size = devflow_core::walk::size_with_progress(path, |progress| {
+        let Ok(mut shown) = shown.try_lock() else {
+            return;
+        };
+        if shown.0.elapsed() >= Duration::from_millis(100) {
+            *shown = (Instant::now(), true);
+            eprint!(
+                "\rsizing {}: {} files, {} MB\x1b[K",
+                path.display(),
+                progress.files,
+                progress.bytes / 1024 / 1024
+            );
+        }
+    });
+    if shown.into_inner().is_ok_and(|(_, drawn)| drawn) {
+        eprint!("\r\x1b[K");
+    }
+    size
 }
 
 fn get_gh_cache_size() -> Result<u64> {
@@ -489,6 +1708,24 @@ fn get_gh_run_count() -> Result<u64> {
         .map_err(|e| anyhow!("failed to parse run count: {}", e))
 }
 
+/// Renders every managed workflow as `(profile, path, content)`.
+///
+/// Split mode (`--split`, or any `[ci.outputs]` entry) renders one workflow per
+/// target profile; otherwise a single combined workflow covers `targets.pr`.
+fn ci_workflows(cli: &Cli, cfg: &DevflowConfig) -> Result<Vec<(String, String, String)>> {
+    if !cli.split && cfg.ci.outputs.is_empty() {
+        let workflow = devflow_gh::render_workflow(cfg)?;
+        return Ok(vec![("pr".to_string(), cli.ci_output.clone(), workflow)]);
+    }
+    devflow_gh::split_outputs(cfg, &cli.ci_output)
+        .into_iter()
+        .map(|(profile, path)| {
+            let workflow = devflow_gh::render_profile_workflow(cfg, &profile)?;
+            Ok((profile, path, workflow))
+        })
+        .collect()
+}
+
 fn write_ci_workflow(path: &str, content: &str) -> Result<()> {
     let output = Path::new(path);
     if let Some(parent) = output.parent() {
@@ -502,6 +1739,51 @@ fn read_ci_workflow(path: &str) -> Result<String> {
     fs::read_to_string(path).with_context(|| format!("failed to read '{}'", path))
 }
 
+/// Maps the hand-written workflow at `--ci-output` to target profiles and
+/// user-managed `[ci.jobs]` steps (`ci:import`, see
+/// [`devflow_gh::import`]) and merges them into the config, or prints them
+/// with `--stdout`. Settings the config already has are only replaced with
+/// `--force`.
+fn import_ci_workflow(cli: &Cli, cfg: &DevflowConfig) -> Result<()> {
+    let source = &cli.ci_output;
+    let import = devflow_gh::import::import_workflow(&read_ci_workflow(source)?)
+        .with_context(|| format!("failed to import '{source}'"))?;
+    let job = devflow_gh::import::managed_job(cfg);
+    let proposal = import.render(source, job);
+    if cli.stdout {
+        print!("{proposal}");
+        return Ok(());
+    }
+    let text = fs::read_to_string(&cli.config)
+        .with_context(|| format!("failed to read '{}'", cli.config))?;
+    let (merged, replaced) = devflow_core::format::merge_config(&text, &proposal)?;
+    if !replaced.is_empty() && !cli.force {
+        bail!(
+            "{} already sets {}; pass --force to replace them, or --stdout to print the proposal",
+            cli.config,
+            replaced.join(", ")
+        );
+    }
+    fs::write(&cli.config, merged).with_context(|| format!("failed to write '{}'", cli.config))?;
+    println!(
+        "ci:import {} -> {}",
+        import.profiles.join(", "),
+        import.commands.join(", ")
+    );
+    let kept = import.pre_steps.len() + import.post_steps.len();
+    if kept > 0 {
+        println!("ci:import kept {kept} step(s) as user-managed [ci.jobs.{job}] steps");
+    }
+    for (skipped, why) in &import.skipped {
+        println!("ci:import skipped job '{skipped}': {why}; move it to a workflow of its own");
+    }
+    println!(
+        "ci:import wrote {}; review it, then run 'dwf ci:diff'",
+        cli.config
+    );
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -509,19 +1791,36 @@ mod tests {
     use tempfile::tempdir;
 
     fn test_cfg() -> DevflowConfig {
-        let mut profiles = std::collections::HashMap::new();
-        profiles.insert("pr".to_string(), vec!["test:unit".to_string()]);
+        let profiles = [("pr".to_string(), vec!["test:unit".into()])]
+            .into_iter()
+            .collect();
 
         DevflowConfig {
+            schema_version: None,
             project: ProjectConfig {
                 name: "test-main".to_string(),
                 stack: vec!["rust".to_string()],
+                detected_stacks: Vec::new(),
             },
             runtime: RuntimeConfig::default(),
             targets: devflow_core::config::TargetsConfig { profiles },
             extensions: None,
             container: None,
             cache: None,
+            extends: None,
+            preset: None,
+            concurrency: None,
+            ci: Default::default(),
+            release: Default::default(),
+            logs: Default::default(),
+            serve: Default::default(),
+            notifications: Default::default(),
+            reporters: Default::default(),
+            commands: Default::default(),
+            waits: Default::default(),
+            fixtures: Default::default(),
+            env_files: None,
+            aliases: Default::default(),
             source_dir: None,
         }
     }
@@ -530,6 +1829,8 @@ mod tests {
         Cli {
             command: Some("ci".to_string()),
             selector: None,
+            detail: None,
+            configs: vec![],
             config: "devflow.toml".to_string(),
             stdout: true,
             ci_output: ci_output.to_string(),
@@ -538,6 +1839,42 @@ mod tests {
             local: false,
             gh: false,
             all: false,
+            dry_run: false,
+            hermetic: false,
+            sign: false,
+            inputs: false,
+            diff: false,
+            list: false,
+            follow: false,
+            allow_dirty: false,
+            inherit_path: false,
+            check: None,
+            split: false,
+            ignore_version_mismatch: false,
+            lenient_config: false,
+            profile: "pr".to_string(),
+            last: None,
+            stats_command: None,
+            coverage: None,
+            output: None,
+            format: "text".to_string(),
+            listen: "127.0.0.1:8787".to_string(),
+            notify: false,
+            sarif: None,
+            events: None,
+            affected: false,
+            keep: false,
+            strict_skips: false,
+            no_fail_fast: false,
+            stack: Vec::new(),
+            timeout: None,
+            retries: 0,
+            env: Vec::new(),
+            runtime: None,
+            ci: None,
+            preset: None,
+            profiles: Vec::new(),
+            argv: Vec::new(),
         }
     }
 
@@ -552,6 +1889,93 @@ mod tests {
         assert!(execute(&cli, &cfg, &registry, &cmd).is_ok());
     }
 
+    #[test]
+    fn env_flags_reach_the_run_options() {
+        let cli = Cli::try_parse_from([
+            "dwf",
+            "test:unit",
+            "--env",
+            "RUST_LOG=debug",
+            "--env",
+            "ARGS=a=b",
+        ])
+        .unwrap();
+        let opts = run_options(&cli);
+        assert_eq!(opts.env["RUST_LOG"], "debug");
+        assert_eq!(opts.env["ARGS"], "a=b");
+        for invalid in ["RUST_LOG", "=x", "1X=y", "A-B=c"] {
+            assert!(
+                Cli::try_parse_from(["dwf", "--env", invalid]).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn lists_the_target_profiles() {
+        let mut cfg = test_cfg();
+        cfg.targets = toml::from_str(
+            r#"
+            pr = ["fmt:check", { cmd = "test:unit", retries = 1 }]
+            nightly = ["test:features"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            list_profiles(&cfg),
+            "pr       fmt:check, test:unit\nnightly  test:features\n"
+        );
+    }
+
+    #[test]
+    fn stack_checks_come_from_the_project_stacks_unless_a_profile_is_configured() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        let mut cfg = test_cfg();
+        cfg.project.stack = vec!["rust".to_string(), "terraform".to_string()];
+        cfg.source_dir = Some(dir.path().to_path_buf());
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
+        registry.register(Box::new(devflow_ext_terraform::TerraformExtension::new()));
+
+        assert!(is_stack_check(&cfg, &registry, "msrv"));
+        assert!(!is_stack_check(&cfg, &registry, "nightly"));
+        // No Terraform files, so `check:plan` is not a stack command.
+        assert!(!is_stack_check(&cfg, &registry, "plan"));
+        std::fs::write(dir.path().join("main.tf"), "").unwrap();
+        assert!(is_stack_check(&cfg, &registry, "plan"));
+
+        cfg.targets = toml::from_str("plan = [\"build:debug\"]\nmsrv = [\"test:unit\"]").unwrap();
+        assert!(!is_stack_check(&cfg, &registry, "plan"));
+        assert!(!is_stack_check(&cfg, &registry, "msrv"));
+    }
+
+    #[test]
+    fn dry_run_is_limited_to_the_commands_honouring_it() {
+        for supported in [
+            "prune",
+            "prune:cache",
+            "ci:debug",
+            "ci:debug:verify",
+            "release:tag",
+        ] {
+            assert!(
+                supports_dry_run(&CommandRef::from_str(supported).unwrap()),
+                "{supported}"
+            );
+        }
+        for unsupported in ["check:pr", "fmt:fix", "ci:generate", "test:unit"] {
+            assert!(
+                !supports_dry_run(&CommandRef::from_str(unsupported).unwrap()),
+                "{unsupported}"
+            );
+        }
+    }
+
     #[test]
     fn smoke_test_execute_ci_generate_stdout() {
         let cfg = test_cfg();
@@ -583,6 +2007,57 @@ mod tests {
         assert!(content.contains("test:unit"));
     }
 
+    #[test]
+    fn split_generate_and_check_cover_every_profile() {
+        let dir = tempdir().unwrap();
+        let ci_path = dir.path().join("ci.yml");
+        let release_path = dir.path().join("release.yml");
+
+        let mut cfg = test_cfg();
+        cfg.targets
+            .profiles
+            .insert("release".to_string(), vec!["package:artifact".into()]);
+        cfg.ci.outputs.insert(
+            "release".to_string(),
+            release_path.to_str().unwrap().to_string(),
+        );
+        let registry = ExtensionRegistry::default();
+
+        let mut cli = test_cli(ci_path.to_str().unwrap());
+        cli.stdout = false;
+        let generate = CommandRef::from_str("ci:generate").unwrap();
+        execute(&cli, &cfg, &registry, &generate).expect("split generate failed");
+
+        let release = fs::read_to_string(&release_path).unwrap();
+        assert!(release.starts_with("name: release\n"));
+        assert!(release.contains("dwf --report package-artifact package:artifact &"));
+        assert!(fs::read_to_string(&ci_path).unwrap().contains("test:unit"));
+
+        let check = CommandRef::from_str("ci:check").unwrap();
+        execute(&cli, &cfg, &registry, &check).expect("split check should pass");
+
+        fs::write(
+            &release_path,
+            release.replace("package:artifact", "build:release"),
+        )
+        .unwrap();
+        let err = execute(&cli, &cfg, &registry, &check).expect_err("drift must fail");
+        assert!(err.to_string().contains("drift detected in"));
+        assert!(err.to_string().contains("release.yml"));
+
+        let diff = CommandRef::from_str("ci:diff").unwrap();
+        let err = execute(&cli, &cfg, &registry, &diff).expect_err("a pending change must fail");
+        assert_eq!(
+            err.to_string(),
+            format!("ci:generate would change {}", release_path.display())
+        );
+        fs::remove_file(&release_path).unwrap();
+        assert!(execute(&cli, &cfg, &registry, &diff).is_err());
+        execute(&cli, &cfg, &registry, &generate).unwrap();
+        execute(&cli, &cfg, &registry, &diff).expect("no changes after generate");
+        assert!(release_path.exists());
+    }
+
     #[test]
     fn get_dir_size_nonexistent_returns_zero() {
         assert_eq!(
@@ -603,6 +2078,10 @@ mod tests {
 
         let size = get_dir_size(dir.path());
         assert_eq!(size, 11);
+
+        // Ignored files are not counted
+        fs::write(dir.path().join(".devflowignore"), "sub/\n").unwrap(); // 5 bytes
+        assert_eq!(get_dir_size(dir.path()), 10);
     }
 
     #[test]
@@ -628,4 +2107,43 @@ mod tests {
             .to_string()
             .contains("unknown prune selector"));
     }
+
+    #[test]
+    fn skip_label_never_reports_success() {
+        let gh = GithubContext {
+            labels: vec![devflow_gh::context::SKIP_CI_LABEL.to_string()],
+            actor: Some("octocat".to_string()),
+            ..GithubContext::default()
+        };
+        let check = CommandRef::from_str("check:pr").unwrap();
+
+        let mut posted = Vec::new();
+        let skipped = skip_labeled_run(
+            &gh,
+            &check,
+            Some("ci/pr"),
+            || true,
+            |ctx, state, _| {
+                posted.push((ctx.to_string(), state.to_string()));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert!(skipped);
+        assert_eq!(posted, [("ci/pr".to_string(), "pending".to_string())]);
+
+        // Without push access the label is ignored and the checks run.
+        let skipped = skip_labeled_run(
+            &gh,
+            &check,
+            Some("ci/pr"),
+            || false,
+            |_, state, _| panic!("posted {state}"),
+        )
+        .unwrap();
+        assert!(!skipped);
+
+        let unlabeled = GithubContext::default();
+        assert!(!skip_labeled_run(&unlabeled, &check, None, || true, |_, _, _| Ok(())).unwrap());
+    }
 }
@@ -0,0 +1,234 @@
+//! Running devflow inside a container (`[container] nested`).
+//!
+//! Devflow counts as nested when `IS_CONTAINER=true` (the generated CI jobs
+//! set it), or, unless `IS_CONTAINER=false`, when the container runtimes'
+//! markers are present: `/.dockerenv`, podman's `/run/.containerenv`, or a
+//! container cgroup for PID 1. Detection runs once per process.
+//!
+//! With the `socket` strategy the engine is the host's, so bind mounts must
+//! name host paths: the current container's own mounts (from
+//! `<engine> inspect`) map each path back to its source.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use devflow_core::config::NestedStrategy;
+use devflow_core::DevflowConfig;
+
+/// cgroup path segments of containerized processes.
+const CONTAINER_CGROUPS: &[&str] = &["docker", "kubepods", "containerd", "libpod", "lxc"];
+
+/// Whether devflow runs inside a container.
+pub fn detected() -> bool {
+    static DETECTED: OnceLock<bool> = OnceLock::new();
+    *DETECTED.get_or_init(|| {
+        detect(
+            std::env::var("IS_CONTAINER").ok().as_deref(),
+            Path::new("/"),
+        )
+    })
+}
+
+/// Detection against the filesystem under `root`; `is_container` is the
+/// `IS_CONTAINER` value.
+fn detect(is_container: Option<&str>, root: &Path) -> bool {
+    match is_container {
+        Some("true") => return true,
+        Some("false") => return false,
+        _ => {}
+    }
+    if root.join(".dockerenv").exists() || root.join("run/.containerenv").exists() {
+        return true;
+    }
+    std::fs::read_to_string(root.join("proc/1/cgroup")).is_ok_and(|cgroups| {
+        cgroups.lines().any(|line| {
+            let path = line.splitn(3, ':').nth(2).unwrap_or_default();
+            path.split(['/', '-', '.'])
+                .any(|segment| CONTAINER_CGROUPS.contains(&segment))
+        })
+    })
+}
+
+/// The configured strategy when devflow runs nested, `None` otherwise.
+pub fn strategy(cfg: &DevflowConfig) -> Option<NestedStrategy> {
+    detected().then(|| {
+        cfg.container
+            .as_ref()
+            .map(|container| container.nested)
+            .unwrap_or_default()
+    })
+}
+
+/// One mount of the current container, as `inspect` reports it.
+#[derive(Debug, Deserialize)]
+struct Mount {
+    #[serde(rename = "Source")]
+    source: PathBuf,
+    #[serde(rename = "Destination")]
+    destination: PathBuf,
+}
+
+/// Maps paths of the current container to the host paths they are mounted
+/// from.
+#[derive(Debug)]
+pub struct HostPaths {
+    /// `(destination, source)`, longest destination first.
+    mounts: Vec<(PathBuf, PathBuf)>,
+}
+
+impl HostPaths {
+    /// Inspects the current container (named by its hostname) with `engine`.
+    ///
+    /// # Errors
+    /// Returns an error if the container cannot be inspected.
+    pub fn discover(engine: &str) -> Result<Self> {
+        let id = std::fs::read_to_string("/etc/hostname")
+            .ok()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .context("cannot tell the current container's id from its hostname")?;
+        let output = Command::new(engine)
+            .args(["inspect", "--format", "{{json .Mounts}}", &id])
+            .output()
+            .with_context(|| format!("failed to run '{engine} inspect'"))?;
+        if !output.status.success() {
+            bail!(
+                "'{engine} inspect {id}' failed ([container] nested = \"socket\" needs the host's engine to know this container): {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parses the `.Mounts` JSON of `inspect`.
+    ///
+    /// # Errors
+    /// Returns an error if `json` is not a list of mounts.
+    pub fn parse(json: &str) -> Result<Self> {
+        let mounts: Vec<Mount> =
+            serde_json::from_str(json.trim()).context("unexpected inspect output")?;
+        let mut mounts: Vec<(PathBuf, PathBuf)> = mounts
+            .into_iter()
+            .map(|mount| (mount.destination, mount.source))
+            .collect();
+        mounts.sort_by_key(|(destination, _)| std::cmp::Reverse(destination.as_os_str().len()));
+        Ok(Self { mounts })
+    }
+
+    /// The host path `path` is mounted from.
+    pub fn translate(&self, path: &Path) -> Option<PathBuf> {
+        self.mounts.iter().find_map(|(destination, source)| {
+            let rest = path.strip_prefix(destination).ok()?;
+            Some(if rest.as_os_str().is_empty() {
+                source.clone()
+            } else {
+                source.join(rest)
+            })
+        })
+    }
+
+    /// `args` of a `run` with the source of every bind mount (`-v` with an
+    /// absolute path) translated to the host.
+    ///
+    /// # Errors
+    /// Returns an error naming a source that is not on a mount of this
+    /// container.
+    pub fn translate_args(&self, args: Vec<String>) -> Result<Vec<String>> {
+        let mut out = Vec::with_capacity(args.len());
+        let mut volume = false;
+        for arg in args {
+            if !std::mem::replace(&mut volume, arg == "-v") || !arg.starts_with('/') {
+                out.push(arg);
+                continue;
+            }
+            let (source, target) = arg.split_once(':').unwrap_or((&arg, ""));
+            let host = self.translate(Path::new(source)).ok_or_else(|| {
+                anyhow!(
+                    "'{source}' is not on a mount of this container, so the host's engine cannot mount it ([container] nested = \"socket\")"
+                )
+            })?;
+            out.push(format!("{}:{target}", host.display()));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_containers_from_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        assert!(!detect(None, root));
+        assert!(detect(Some("true"), root));
+
+        std::fs::create_dir_all(root.join("proc/1")).unwrap();
+        std::fs::write(root.join("proc/1/cgroup"), "0::/\n").unwrap();
+        assert!(!detect(None, root));
+        std::fs::write(
+            root.join("proc/1/cgroup"),
+            "0::/system.slice/docker-4a1b.scope\n",
+        )
+        .unwrap();
+        assert!(detect(None, root));
+        assert!(!detect(Some("false"), root));
+
+        let podman = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(podman.path().join("run")).unwrap();
+        std::fs::write(podman.path().join("run/.containerenv"), "").unwrap();
+        assert!(detect(None, podman.path()));
+    }
+
+    #[test]
+    fn translates_bind_mounts_to_host_paths() {
+        let paths = HostPaths::parse(
+            r#"[{"Type":"bind","Source":"/home/dev/app","Destination":"/workspaces/app"},
+                {"Type":"bind","Source":"/var/cache/dwf","Destination":"/workspaces/app/.cache"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            paths.translate(Path::new("/workspaces/app/src")),
+            Some(PathBuf::from("/home/dev/app/src"))
+        );
+        assert_eq!(
+            paths.translate(Path::new("/workspaces/app/.cache/cargo")),
+            Some(PathBuf::from("/var/cache/dwf/cargo"))
+        );
+        assert_eq!(paths.translate(Path::new("/usr/local/bin/dwf")), None);
+
+        let args: Vec<String> = [
+            "run",
+            "-v",
+            "/workspaces/app:/workspace:ro",
+            "-v",
+            "cargo-registry:/workspace/.cargo",
+            "-w",
+            "/workspace",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(
+            paths.translate_args(args).unwrap(),
+            [
+                "run",
+                "-v",
+                "/home/dev/app:/workspace:ro",
+                "-v",
+                "cargo-registry:/workspace/.cargo",
+                "-w",
+                "/workspace"
+            ]
+        );
+        let err = paths
+            .translate_args(vec!["-v".to_string(), "/opt/x:/x".to_string()])
+            .unwrap_err();
+        assert!(err.to_string().contains("'/opt/x' is not on a mount"));
+    }
+}
@@ -0,0 +1,265 @@
+//! Completion notifications (`--notify`, `[notifications]`).
+//!
+//! A [`RunSummary`] is handed to every active [`Notifier`] when a run that
+//! executed commands finishes: the desktop notifier when `--notify` is given,
+//! the webhook when `[notifications.webhook]` is configured and the run is in
+//! CI. A failing notifier only warns.
+
+use std::io::ErrorKind;
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use devflow_core::config::{NotifyOn, WebhookConfig};
+use devflow_core::DevflowConfig;
+
+use crate::history::CommandRecord;
+
+/// The outcome of one `dwf` invocation.
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub project: String,
+    /// The invoked command, e.g. `check:pr`.
+    pub invocation: String,
+    pub success: bool,
+    pub duration: Duration,
+    pub commands: Vec<CommandRecord>,
+    /// The error the run failed with.
+    pub error: Option<String>,
+    /// Link to the CI run, when known.
+    pub url: Option<String>,
+}
+
+impl RunSummary {
+    /// One-line outcome, e.g. `check:pr passed (4 commands, 42.1s)`.
+    pub fn headline(&self) -> String {
+        let outcome = if self.success { "passed" } else { "failed" };
+        let noun = if self.commands.len() == 1 {
+            "command"
+        } else {
+            "commands"
+        };
+        format!(
+            "{} {} ({} {}, {:.1}s)",
+            self.invocation,
+            outcome,
+            self.commands.len(),
+            noun,
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+/// A destination for run summaries.
+pub trait Notifier {
+    /// Short name used in log messages, e.g. `desktop`.
+    fn name(&self) -> &'static str;
+
+    /// Delivers `summary`.
+    ///
+    /// # Errors
+    /// Returns an error if the notification could not be delivered.
+    fn notify(&self, summary: &RunSummary) -> Result<()>;
+}
+
+/// Returns the notifiers active for this run.
+pub fn notifiers(cfg: &DevflowConfig, desktop: bool) -> Vec<Box<dyn Notifier>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    if desktop {
+        notifiers.push(Box::new(Desktop));
+    }
+    if let Some(webhook) = &cfg.notifications.webhook {
+        if in_ci() {
+            notifiers.push(Box::new(Webhook(webhook.clone())));
+        } else {
+            debug!("webhook notification skipped: not running in CI");
+        }
+    }
+    notifiers
+}
+
+/// Sends `summary` to every notifier; failures only warn.
+pub fn send(notifiers: &[Box<dyn Notifier>], summary: &RunSummary) {
+    for notifier in notifiers {
+        if let Err(e) = notifier.notify(summary) {
+            warn!("{} notification failed: {:#}", notifier.name(), e);
+        }
+    }
+}
+
+/// Whether this run is in CI (`CI` is set and not `false`).
+pub(crate) fn in_ci() -> bool {
+    std::env::var("CI").is_ok_and(|v| !v.is_empty() && v != "false")
+}
+
+/// Desktop notification via `notify-send` (Linux) or `osascript` (macOS).
+struct Desktop;
+
+impl Notifier for Desktop {
+    fn name(&self) -> &'static str {
+        "desktop"
+    }
+
+    fn notify(&self, summary: &RunSummary) -> Result<()> {
+        let title = format!("dwf {}", summary.project);
+        let body = summary.headline();
+        let (program, args) = if cfg!(target_os = "macos") {
+            let script = format!(
+                "display notification {} with title {}",
+                applescript_string(&body),
+                applescript_string(&title)
+            );
+            ("osascript", vec!["-e".to_string(), script])
+        } else {
+            ("notify-send", vec![title, body])
+        };
+        match Command::new(program).args(&args).status() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => bail!("'{}' exited with {}", program, status),
+            Err(e) if e.kind() == ErrorKind::NotFound => bail!("'{}' not found", program),
+            Err(e) => Err(e).with_context(|| format!("failed to start '{}'", program)),
+        }
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// JSON summary posted to `[notifications.webhook] url`.
+struct Webhook(WebhookConfig);
+
+impl Notifier for Webhook {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn notify(&self, summary: &RunSummary) -> Result<()> {
+        let outcome = if summary.success {
+            NotifyOn::Success
+        } else {
+            NotifyOn::Failure
+        };
+        if !self.0.on.contains(&outcome) {
+            return Ok(());
+        }
+        let url = expand_env(&self.0.url)?;
+        ureq::post(&url)
+            .send_json(payload(summary))
+            .context("webhook request failed")?;
+        Ok(())
+    }
+}
+
+/// Expands `${VAR}` references from the environment.
+fn expand_env(text: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + len];
+        let value = std::env::var(name)
+            .with_context(|| format!("webhook url references unset variable '{}'", name))?;
+        out.push_str(&rest[..start]);
+        out.push_str(&value);
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// The webhook body: Slack-compatible `text` plus structured fields.
+fn payload(summary: &RunSummary) -> Value {
+    let mut text = format!("{}: {}", summary.project, summary.headline());
+    if let Some(url) = &summary.url {
+        text.push_str(&format!(" <{url}|details>"));
+    }
+    let commands: Vec<Value> = summary
+        .commands
+        .iter()
+        .map(|c| {
+            json!({
+                "command": c.command,
+                "success": c.success,
+                "duration_ms": c.duration_ms,
+                "tests_passed": c.tests_passed,
+                "tests_failed": c.tests_failed,
+            })
+        })
+        .collect();
+    json!({
+        "text": text,
+        "project": summary.project,
+        "command": summary.invocation,
+        "success": summary.success,
+        "duration_ms": u64::try_from(summary.duration.as_millis()).unwrap_or(u64::MAX),
+        "commands": commands,
+        "error": summary.error,
+        "url": summary.url,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(success: bool) -> RunSummary {
+        RunSummary {
+            project: "demo".to_string(),
+            invocation: "check:pr".to_string(),
+            success,
+            duration: Duration::from_millis(42_100),
+            commands: vec![CommandRecord {
+                run_id: 0,
+                started_at: 0,
+                command: "test:unit".to_string(),
+                duration_ms: 40_000,
+                success,
+                cache_hit: None,
+                tests_passed: Some(12),
+                tests_failed: Some(u64::from(!success)),
+            }],
+            error: (!success).then(|| "test:unit failed for rust".to_string()),
+            url: Some("https://github.com/o/r/actions/runs/1".to_string()),
+        }
+    }
+
+    #[test]
+    fn payload_carries_slack_text_and_structured_fields() {
+        let body = payload(&summary(false));
+        assert_eq!(
+            body["text"],
+            "demo: check:pr failed (1 command, 42.1s) <https://github.com/o/r/actions/runs/1|details>"
+        );
+        assert_eq!(body["success"], false);
+        assert_eq!(body["commands"][0]["command"], "test:unit");
+        assert_eq!(body["commands"][0]["tests_failed"], 1);
+        assert_eq!(body["error"], "test:unit failed for rust");
+    }
+
+    #[test]
+    fn webhook_skips_outcomes_it_is_not_configured_for() {
+        let webhook = Webhook(WebhookConfig {
+            url: "http://127.0.0.1:9/unreachable".to_string(),
+            on: vec![NotifyOn::Failure],
+        });
+        assert!(webhook.notify(&summary(true)).is_ok());
+        assert!(webhook.notify(&summary(false)).is_err());
+    }
+
+    #[test]
+    fn expands_environment_references() {
+        std::env::set_var("DWF_TEST_WEBHOOK_HOST", "hooks.example.com");
+        assert_eq!(
+            expand_env("https://${DWF_TEST_WEBHOOK_HOST}/x").unwrap(),
+            "https://hooks.example.com/x"
+        );
+        assert!(expand_env("${DWF_TEST_UNSET_WEBHOOK}").is_err());
+        assert_eq!(applescript_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+}
@@ -0,0 +1,138 @@
+//! Local cache pruning (`prune:cache --local`).
+//!
+//! Only the directories extensions mount from the resolved cache root are
+//! removed. Anything that resolves outside the root, or contains a `.git`, is
+//! refused before a single file is deleted.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use devflow_core::{DevflowConfig, ExtensionRegistry};
+
+use crate::executor;
+
+/// Returns the cache directories `prune:cache --local` would remove, sorted.
+///
+/// # Errors
+/// Returns an error if any mount directory escapes the cache root or contains
+/// a `.git` entry.
+pub fn local_targets(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<Vec<PathBuf>> {
+    safe_targets(&executor::cache_root(cfg), &registry.all_cache_mounts())
+}
+
+/// Resolves each mount's host directory under `cache_root`, keeping existing ones.
+fn safe_targets(cache_root: &Path, mounts: &[String]) -> Result<Vec<PathBuf>> {
+    if !cache_root.exists() {
+        return Ok(Vec::new());
+    }
+    let root = cache_root
+        .canonicalize()
+        .with_context(|| format!("failed to resolve cache root '{}'", cache_root.display()))?;
+
+    let mut targets = Vec::new();
+    for mount in mounts {
+        let Some((host_rel, _)) = executor::parse_mount(mount) else {
+            continue;
+        };
+        let candidate = root.join(host_rel);
+        if !candidate.exists() {
+            continue;
+        }
+        let path = candidate
+            .canonicalize()
+            .with_context(|| format!("failed to resolve '{}'", candidate.display()))?;
+        if path == root || !path.starts_with(&root) {
+            bail!(
+                "refusing to prune '{}': not inside the cache root '{}'",
+                path.display(),
+                root.display()
+            );
+        }
+        if contains_git(&path) {
+            bail!(
+                "refusing to prune '{}': it contains a .git directory",
+                path.display()
+            );
+        }
+        targets.push(path);
+    }
+    targets.sort();
+    targets.dedup();
+    Ok(targets)
+}
+
+/// Whether `path` is, or contains anywhere beneath it, a `.git` entry.
+///
+/// Symlinks are not followed.
+fn contains_git(path: &Path) -> bool {
+    if path.file_name().is_some_and(|name| name == ".git") {
+        return true;
+    }
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return false;
+    };
+    if !meta.is_dir() {
+        return false;
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .any(|e| contains_git(&e.path()))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn targets_are_mount_directories_under_the_root() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("cache");
+        fs::create_dir_all(root.join("rust/cargo")).unwrap();
+        fs::create_dir_all(root.join("keep")).unwrap();
+
+        let mounts = vec![
+            "rust/cargo:/workspace/.cargo-cache".to_string(),
+            "rust/target:/workspace/target/ci".to_string(),
+        ];
+        let targets = safe_targets(&root, &mounts).unwrap();
+        assert_eq!(
+            targets,
+            vec![root.canonicalize().unwrap().join("rust/cargo")]
+        );
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_root() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("cache");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(dir.path().join("project")).unwrap();
+
+        let err = safe_targets(&root, &["../project:/workspace".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("not inside the cache root"));
+    }
+
+    #[test]
+    fn refuses_paths_containing_git() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("cache");
+        fs::create_dir_all(root.join("node/npm/checkout/.git")).unwrap();
+
+        let err = safe_targets(&root, &["node/npm:/root/.npm".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("contains a .git directory"));
+    }
+
+    #[test]
+    fn missing_cache_root_has_no_targets() {
+        let dir = tempdir().unwrap();
+        let targets = safe_targets(&dir.path().join("absent"), &["a:/b".to_string()]).unwrap();
+        assert!(targets.is_empty());
+    }
+}
@@ -0,0 +1,368 @@
+//! The release pipeline (`dwf release:<stage>`, `[release]`).
+//!
+//! The stages run in order: `verify`, `candidate`, `tag`, `publish`, and
+//! `release:<stage>` runs the ones before it first, so a publish always
+//! ships a verified build of the tagged tree. `release` is
+//! `release:candidate`.
+//!
+//! - `verify` checks the guard rails (`[release] branch` checked out and not
+//!   behind its upstream as of the last fetch, or a detached `HEAD` it
+//!   contains), runs the `[release] profile`, then the stacks'
+//!   `release:verify` (e.g. `cargo package`). A clean tree is required by
+//!   the [`crate::guard`] policy of `release:*`.
+//! - `candidate` runs the stacks' `release:candidate` (e.g. `cargo build
+//!   --release`).
+//! - `tag` creates the annotated `[release] tag` for the version the stacks
+//!   report ([`devflow_core::Extension::release_version`]), which must agree.
+//!   A tag already on `HEAD` is kept; elsewhere it is an error.
+//! - `publish` runs the stacks' `release:publish` (e.g. `cargo publish`) and
+//!   pushes the tag.
+//!
+//! With `--dry-run`, `tag` and `publish` only say what they would do.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry, PrimaryCommand};
+
+use crate::{changes, executor, guard};
+
+/// The remote `publish` pushes the tag to.
+const REMOTE: &str = "origin";
+
+/// A stage of the pipeline, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Verify,
+    Candidate,
+    Tag,
+    Publish,
+}
+
+impl Stage {
+    const ALL: [Self; 4] = [Self::Verify, Self::Candidate, Self::Tag, Self::Publish];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Verify => "verify",
+            Self::Candidate => "candidate",
+            Self::Tag => "tag",
+            Self::Publish => "publish",
+        }
+    }
+
+    /// The stage `selector` names.
+    ///
+    /// # Errors
+    /// Returns an error for a selector that is not a stage.
+    pub fn parse(selector: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|stage| stage.as_str() == selector)
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown release selector '{selector}' (expected one of: {})",
+                    Self::ALL.map(Self::as_str).join(", ")
+                )
+            })
+    }
+
+    fn command(self) -> CommandRef {
+        CommandRef {
+            primary: PrimaryCommand::Release,
+            selector: Some(self.as_str().to_string()),
+        }
+    }
+}
+
+/// Runs the pipeline up to the stage `selector` names; `step` runs a
+/// command (the stacks' stage commands and the `check:<profile>`).
+///
+/// # Errors
+/// Returns an error if a guard rail or a step fails.
+pub fn run(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    selector: &str,
+    dry_run: bool,
+    step: &dyn Fn(&CommandRef) -> Result<()>,
+) -> Result<()> {
+    let last = Stage::parse(selector)?;
+    let workspace = guard::workspace(cfg)?;
+    let workspace = workspace.as_path();
+    for stage in Stage::ALL.into_iter().take_while(|stage| *stage <= last) {
+        println!("release:{}", stage.as_str());
+        match stage {
+            Stage::Verify => {
+                let issues = guard_issues(cfg, workspace)?;
+                if !issues.is_empty() {
+                    bail!("release guard rails failed:\n  - {}", issues.join("\n  - "));
+                }
+                if let Some(profile) = cfg.release.profile(&cfg.targets) {
+                    step(&CommandRef {
+                        primary: PrimaryCommand::Check,
+                        selector: Some(profile.to_string()),
+                    })?;
+                }
+                step(&stage.command())?;
+            }
+            Stage::Candidate => step(&stage.command())?,
+            Stage::Tag => {
+                let tag = cfg.release.tag_name(&version(cfg, registry)?);
+                tag_head(workspace, &tag, dry_run)?;
+            }
+            Stage::Publish => {
+                let tag = cfg.release.tag_name(&version(cfg, registry)?);
+                if dry_run {
+                    println!(" - would run release:publish and push {tag} to {REMOTE}");
+                    continue;
+                }
+                step(&stage.command())?;
+                changes::git(workspace, &["push", REMOTE, &tag])?;
+                println!(" - pushed {tag} to {REMOTE}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What keeps the tree in `workspace` from being released, one line each.
+///
+/// # Errors
+/// Returns an error if git cannot tell.
+fn guard_issues(cfg: &DevflowConfig, workspace: &Path) -> Result<Vec<String>> {
+    let git = |args: &[&str]| changes::git(workspace, args).map(|out| out.trim().to_string());
+    let branch = &cfg.release.branch;
+    let issue = match guard::current_branch(workspace)? {
+        None => {
+            let contained = [format!("{REMOTE}/{branch}"), branch.clone()]
+                .iter()
+                .any(|base| git(&["merge-base", "--is-ancestor", "HEAD", base]).is_ok());
+            (!contained)
+                .then(|| format!("the detached HEAD is not on '{branch}' ([release] branch)"))
+        }
+        Some(current) if current == *branch => {
+            match git(&["rev-list", "--count", "HEAD..@{upstream}"]) {
+                Ok(count) if count != "0" => Some(format!(
+                    "'{branch}' is {count} commit(s) behind its upstream; pull first"
+                )),
+                Ok(_) => None,
+                Err(_) => Some(format!(
+                    "'{branch}' has no upstream, so it cannot be checked to be up to date"
+                )),
+            }
+        }
+        Some(current) => Some(format!(
+            "releases are cut from '{branch}' ([release] branch), not '{current}'"
+        )),
+    };
+    Ok(issue.into_iter().collect())
+}
+
+/// The version the stacks release; they must agree.
+///
+/// # Errors
+/// Returns an error if no stack reports one, or they differ.
+fn version(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<String> {
+    let mut versions = Vec::new();
+    for stack in executor::ordered_stacks(cfg) {
+        if let Some(version) = registry
+            .get(&stack)
+            .map(|ext| ext.release_version())
+            .transpose()?
+            .flatten()
+        {
+            versions.push((stack, version));
+        }
+    }
+    let Some((_, first)) = versions.first() else {
+        bail!("no stack reports a version to tag (e.g. version in Cargo.toml or package.json)");
+    };
+    if versions.iter().any(|(_, version)| version != first) {
+        let listed: Vec<String> = versions
+            .iter()
+            .map(|(stack, version)| format!("{stack} {version}"))
+            .collect();
+        bail!(
+            "the stacks report different versions ({}); align them before tagging",
+            listed.join(", ")
+        );
+    }
+    Ok(first.clone())
+}
+
+/// Creates the annotated `tag` on `HEAD`, unless it is already there.
+///
+/// # Errors
+/// Returns an error if `tag` exists on another commit or cannot be created.
+fn tag_head(workspace: &Path, tag: &str, dry_run: bool) -> Result<()> {
+    let git = |args: &[&str]| changes::git(workspace, args).map(|out| out.trim().to_string());
+    if let Ok(tagged) = git(&[
+        "rev-parse",
+        "--verify",
+        &format!("refs/tags/{tag}^{{commit}}"),
+    ]) {
+        if tagged != git(&["rev-parse", "HEAD"])? {
+            bail!("tag '{tag}' already exists on another commit ({tagged})");
+        }
+        println!(" - {tag} already tags HEAD");
+        return Ok(());
+    }
+    if dry_run {
+        println!(" - would tag HEAD as {tag}");
+        return Ok(());
+    }
+    git(&["tag", "-a", tag, "-m", &format!("Release {tag}")])?;
+    println!(" - tagged HEAD as {tag}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::{ExecutionAction, Extension};
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::fs;
+
+    /// A stack releasing a fixed version.
+    #[derive(Debug)]
+    struct Versioned(&'static str, &'static str);
+
+    impl Extension for Versioned {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn capabilities(&self) -> HashSet<String> {
+            HashSet::from(["release".to_string()])
+        }
+
+        fn build_action(&self, _cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
+            Ok(None)
+        }
+
+        fn release_version(&self) -> Result<Option<String>> {
+            Ok(Some(self.1.to_string()))
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        changes::git(dir, args).unwrap().trim().to_string()
+    }
+
+    fn repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.email", "dev@example.com"]);
+        git(path, &["config", "user.name", "dev"]);
+        fs::write(
+            path.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"1.4.0\"\n",
+        )
+        .unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "init"]);
+        dir
+    }
+
+    fn config(dir: &Path) -> DevflowConfig {
+        let mut cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            release = ["test:unit"]
+            "#,
+        )
+        .unwrap();
+        cfg.source_dir = Some(dir.to_path_buf());
+        cfg
+    }
+
+    #[test]
+    fn guard_rails_need_the_release_branch() {
+        let dir = repo();
+        let path = dir.path();
+        let cfg = config(path);
+        assert_eq!(
+            guard_issues(&cfg, path).unwrap(),
+            ["'main' has no upstream, so it cannot be checked to be up to date"]
+        );
+
+        git(path, &["checkout", "-q", "-b", "feature"]);
+        assert_eq!(
+            guard_issues(&cfg, path).unwrap(),
+            ["releases are cut from 'main' ([release] branch), not 'feature'"]
+        );
+
+        git(path, &["checkout", "-q", "--detach", "main"]);
+        assert!(guard_issues(&cfg, path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn stages_run_in_order_and_tag_head_once() {
+        let dir = repo();
+        let path = dir.path();
+        git(path, &["checkout", "-q", "--detach"]);
+        let cfg = config(path);
+        let ran = RefCell::new(Vec::new());
+        let step = |cmd: &CommandRef| -> Result<()> {
+            ran.borrow_mut().push(cmd.canonical());
+            Ok(())
+        };
+        // Without a registered stack there is no version to tag.
+        let registry = ExtensionRegistry::default();
+        run(&cfg, &registry, "candidate", false, &step).unwrap();
+        assert_eq!(
+            *ran.borrow(),
+            ["check:release", "release:verify", "release:candidate"]
+        );
+        let err = run(&cfg, &registry, "tag", false, &step).unwrap_err();
+        assert!(err.to_string().starts_with("no stack reports a version"));
+        assert!(Stage::parse("ship").is_err());
+
+        // The tag is named after the version the stacks report.
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(Versioned("rust", "1.4.0")));
+        run(&cfg, &registry, "tag", false, &step).unwrap();
+        assert_eq!(git(path, &["tag", "--points-at", "HEAD"]), "v1.4.0");
+        git(path, &["tag", "-d", "v1.4.0"]);
+
+        tag_head(path, "v1.4.0", true).unwrap();
+        assert!(git(path, &["tag"]).is_empty());
+        tag_head(path, "v1.4.0", false).unwrap();
+        tag_head(path, "v1.4.0", false).unwrap();
+        assert_eq!(git(path, &["tag"]), "v1.4.0");
+        fs::write(path.join("CHANGELOG.md"), "1.4.0\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "changelog"]);
+        let err = tag_head(path, "v1.4.0", false).unwrap_err();
+        assert!(err.to_string().contains("already exists on another commit"));
+    }
+
+    #[test]
+    fn stacks_must_agree_on_the_version() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        let mut cfg = config(dir.path());
+        cfg.project.stack.push("node".to_string());
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(Versioned("rust", "1.4.0")));
+        registry.register(Box::new(Versioned("node", "1.4.0")));
+        assert_eq!(version(&cfg, &registry).unwrap(), "1.4.0");
+        assert_eq!(cfg.release.tag_name("1.4.0"), "v1.4.0");
+
+        registry.register(Box::new(Versioned("node", "1.5.0")));
+        let err = version(&cfg, &registry).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the stacks report different versions (rust 1.4.0, node 1.5.0); align them before tagging"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! SSH remote execution for the `remote` runtime profile.
+//!
+//! A remote run is three steps: rsync the workspace to the build machine
+//! (honoring `.gitignore`), execute the action there over SSH, then rsync the
+//! declared artifacts back into the local workspace.
+
+use devflow_core::config::RemoteConfig;
+use devflow_core::ExecutionAction;
+
+/// Remote directory used when `[runtime.remote] path` is not set.
+const DEFAULT_REMOTE_ROOT: &str = ".devflow/workspaces";
+
+/// The ordered actions that make up one remote command execution.
+#[derive(Debug, Clone)]
+pub struct RemotePlan {
+    /// Pushes the local workspace to the remote directory.
+    pub sync_up: ExecutionAction,
+    /// Runs the command on the remote machine.
+    pub exec: ExecutionAction,
+    /// Pulls declared artifacts back, if any are configured.
+    pub sync_back: Option<ExecutionAction>,
+}
+
+/// Builds the remote plan for `action` in project `project`.
+pub fn plan(remote: &RemoteConfig, project: &str, action: &ExecutionAction) -> RemotePlan {
+    let dir = remote_dir(remote, project);
+    let target = destination(remote);
+
+    let mut sync_up = rsync_base(remote);
+    sync_up.args.extend([
+        // Create the remote directory on first use.
+        format!("--rsync-path=mkdir -p {} && rsync", shell_quote(&dir)),
+        "--delete".to_string(),
+        "--filter=:- .gitignore".to_string(),
+        "--exclude=.git".to_string(),
+        "--".to_string(),
+        "./".to_string(),
+        format!("{target}:{dir}/"),
+    ]);
+
+    let sync_back = (!remote.artifacts.is_empty()).then(|| {
+        let mut back = rsync_base(remote);
+        back.args.push("--relative".to_string());
+        back.args.push("--".to_string());
+        back.args.extend(
+            remote
+                .artifacts
+                .iter()
+                .map(|artifact| format!("{target}:{dir}/./{}", artifact.trim_end_matches('/'))),
+        );
+        back.args.push("./".to_string());
+        back
+    });
+
+    RemotePlan {
+        sync_up,
+        exec: ssh_exec(remote, &dir, action),
+        sync_back,
+    }
+}
+
+fn remote_dir(remote: &RemoteConfig, project: &str) -> String {
+    remote
+        .path
+        .clone()
+        .unwrap_or_else(|| format!("{DEFAULT_REMOTE_ROOT}/{project}"))
+}
+
+fn destination(remote: &RemoteConfig) -> String {
+    match &remote.user {
+        Some(user) => format!("{user}@{}", remote.host),
+        None => remote.host.clone(),
+    }
+}
+
+fn rsync_base(remote: &RemoteConfig) -> ExecutionAction {
+    let mut args = vec!["-az".to_string()];
+    if let Some(port) = remote.port {
+        args.push("-e".to_string());
+        args.push(format!("ssh -p {port}"));
+    }
+    ExecutionAction {
+        program: "rsync".to_string(),
+        args,
+        env: std::collections::HashMap::new(),
+    }
+}
+
+fn ssh_exec(remote: &RemoteConfig, dir: &str, action: &ExecutionAction) -> ExecutionAction {
+    let mut env: Vec<(&String, &String)> = action.env.iter().collect();
+    env.sort();
+
+    let mut script = format!("cd {} && env", shell_quote(dir));
+    for (key, value) in env {
+        script.push(' ');
+        script.push_str(&shell_quote(&format!("{key}={value}")));
+    }
+    script.push(' ');
+    script.push_str(&shell_quote(&action.program));
+    for arg in &action.args {
+        script.push(' ');
+        script.push_str(&shell_quote(arg));
+    }
+
+    let mut args = Vec::new();
+    if let Some(port) = remote.port {
+        args.push("-p".to_string());
+        args.push(port.to_string());
+    }
+    args.push("--".to_string());
+    args.push(destination(remote));
+    args.push(script);
+
+    ExecutionAction {
+        program: "ssh".to_string(),
+        args,
+        env: std::collections::HashMap::new(),
+    }
+}
+
+/// Quotes a value for a POSIX shell.
+pub(crate) fn shell_quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c))
+    {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote() -> RemoteConfig {
+        RemoteConfig {
+            host: "builder01".to_string(),
+            user: Some("ci".to_string()),
+            port: Some(2222),
+            path: None,
+            artifacts: vec!["target/release/".to_string()],
+        }
+    }
+
+    fn cargo_build() -> ExecutionAction {
+        let mut env = std::collections::HashMap::new();
+        env.insert("RUSTFLAGS".to_string(), "-C opt-level=3".to_string());
+        ExecutionAction {
+            program: "cargo".to_string(),
+            args: vec!["build".to_string(), "--release".to_string()],
+            env,
+        }
+    }
+
+    #[test]
+    fn plans_sync_exec_and_sync_back() {
+        let plan = plan(&remote(), "demo", &cargo_build());
+
+        assert_eq!(plan.sync_up.program, "rsync");
+        assert_eq!(
+            plan.sync_up.args,
+            vec![
+                "-az",
+                "-e",
+                "ssh -p 2222",
+                "--rsync-path=mkdir -p .devflow/workspaces/demo && rsync",
+                "--delete",
+                "--filter=:- .gitignore",
+                "--exclude=.git",
+                "--",
+                "./",
+                "ci@builder01:.devflow/workspaces/demo/",
+            ]
+        );
+
+        assert_eq!(plan.exec.program, "ssh");
+        assert_eq!(
+            plan.exec.args,
+            vec![
+                "-p",
+                "2222",
+                "--",
+                "ci@builder01",
+                "cd .devflow/workspaces/demo && env 'RUSTFLAGS=-C opt-level=3' cargo build --release",
+            ]
+        );
+
+        let back = plan.sync_back.expect("artifacts are declared");
+        assert_eq!(
+            &back.args[3..],
+            [
+                "--relative",
+                "--",
+                "ci@builder01:.devflow/workspaces/demo/./target/release",
+                "./"
+            ]
+        );
+    }
+
+    #[test]
+    fn omits_sync_back_without_artifacts() {
+        let cfg = RemoteConfig {
+            host: "builder01".to_string(),
+            path: Some("/srv/build".to_string()),
+            ..RemoteConfig::default()
+        };
+        let plan = plan(&cfg, "demo", &cargo_build());
+        assert!(plan.sync_back.is_none());
+        assert_eq!(plan.exec.args[..2], ["--", "builder01"]);
+        assert!(plan.exec.args[2].starts_with("cd /srv/build && "));
+    }
+
+    #[test]
+    fn shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("plain-arg"), "plain-arg");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,419 @@
+//! Subprocess reporter plugins (`[reporters.<name>]`).
+//!
+//! A plugin is started on the first event of a run and receives one JSON
+//! object per line on stdin:
+//!
+//! ```text
+//! {"event":"command_start","command":"test:unit"}
+//! {"event":"output","command":"test:unit","stream":"stdout","line":"running 12 tests"}
+//! {"event":"skip","command":"test:unit","stack":"node","reason":"manifest_not_found"}
+//! {"event":"command_end","command":"test:unit","success":true,"duration_ms":1200,"error":null,"exit_code":null,"signal":null,"problems":[],"artifacts":[]}
+//! {"event":"check_end","profile":"pr","success":true,"duration_ms":5400,"commands":4,"failed":0,"exit_code":0,"skipped":[]}
+//! ```
+//!
+//! A failed command's `exit_code` and `signal` are those of the process that
+//! failed it; `check_end` has the code `dwf` exits with. `problems` lists the diagnostics found by problem matchers, each with
+//! `owner`, `severity`, `message`, `file`, `line` and `column`; `artifacts`
+//! the workspace-relative reports a failed test kept, such as image diffs. A
+//! skip has a `null` stack when the whole command was skipped; `check_end`
+//! repeats the run's skips in `skipped`. `output` events are only sent to plugins
+//! configured with `output = true`.
+//! When the run ends, the plugin's stdin is closed and dwf waits for it to
+//! exit. A plugin that cannot be started or stops reading is disabled with a
+//! warning; it never fails the run.
+//!
+//! `--events <PATH>` writes the same lines, output included, to a file, for
+//! GUIs following the run through `devflow-bridge`.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+
+use serde::Serialize;
+use tracing::warn;
+
+use devflow_core::config::ReporterConfig;
+use devflow_core::reporter::{CheckEnd, CommandEnd, OutputStream, Reporter, Reporters, Skip};
+use devflow_core::DevflowConfig;
+
+/// One line of the plugin protocol.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    CommandStart {
+        command: &'a str,
+    },
+    Output {
+        command: &'a str,
+        stream: OutputStream,
+        line: &'a str,
+    },
+    Skip(&'a Skip),
+    CommandEnd(&'a CommandEnd),
+    CheckEnd(&'a CheckEnd),
+}
+
+enum State {
+    Idle,
+    Running(Child),
+    Disabled,
+}
+
+/// A reporter plugin fed over stdin.
+pub struct SubprocessReporter {
+    name: String,
+    config: ReporterConfig,
+    state: Mutex<State>,
+}
+
+impl SubprocessReporter {
+    pub fn new(name: &str, config: ReporterConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+            state: Mutex::new(State::Idle),
+        }
+    }
+
+    fn send(&self, event: &Event) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if matches!(*state, State::Idle) {
+            *state = match self.spawn() {
+                Ok(child) => State::Running(child),
+                Err(e) => {
+                    warn!(
+                        "reporter '{}' disabled: failed to start '{}': {}",
+                        self.name, self.config.command, e
+                    );
+                    State::Disabled
+                }
+            };
+        }
+        let State::Running(child) = &mut *state else {
+            return;
+        };
+        let line = serde_json::to_string(event).expect("events serialize");
+        let written = child
+            .stdin
+            .as_mut()
+            .map(|stdin| writeln!(stdin, "{line}").and_then(|()| stdin.flush()));
+        if let Some(Err(e)) = written {
+            warn!("reporter '{}' disabled: {}", self.name, e);
+            if let State::Running(mut child) = std::mem::replace(&mut *state, State::Disabled) {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+
+    fn spawn(&self) -> std::io::Result<Child> {
+        Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+    }
+}
+
+impl Reporter for SubprocessReporter {
+    fn on_command_start(&self, command: &str) {
+        self.send(&Event::CommandStart { command });
+    }
+
+    fn on_output(&self, command: &str, stream: OutputStream, line: &str) {
+        self.send(&Event::Output {
+            command,
+            stream,
+            line,
+        });
+    }
+
+    fn on_skip(&self, skip: &Skip) {
+        self.send(&Event::Skip(skip));
+    }
+
+    fn on_command_end(&self, end: &CommandEnd) {
+        self.send(&Event::CommandEnd(end));
+    }
+
+    fn on_check_end(&self, end: &CheckEnd) {
+        self.send(&Event::CheckEnd(end));
+    }
+
+    fn wants_output(&self) -> bool {
+        self.config.output
+    }
+}
+
+impl Drop for SubprocessReporter {
+    fn drop(&mut self) {
+        let Ok(state) = self.state.get_mut() else {
+            return;
+        };
+        if let State::Running(child) = state {
+            drop(child.stdin.take());
+            match child.wait() {
+                Ok(status) if !status.success() => {
+                    warn!("reporter '{}' exited with {}", self.name, status);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("reporter '{}' did not exit cleanly: {}", self.name, e),
+            }
+        }
+    }
+}
+
+/// Writes every event to a file (`--events`), one flushed line at a time so
+/// a reader can follow the run.
+pub struct EventLog {
+    file: Mutex<Option<File>>,
+}
+
+impl EventLog {
+    /// Creates (or truncates) `path`.
+    ///
+    /// # Errors
+    /// Returns an error if `path` cannot be created.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create event log {}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(Some(file)),
+        })
+    }
+
+    fn send(&self, event: &Event) {
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        let Some(out) = file.as_mut() else {
+            return;
+        };
+        let line = serde_json::to_string(event).expect("events serialize");
+        if let Err(e) = writeln!(out, "{line}").and_then(|()| out.flush()) {
+            warn!("event log disabled: {}", e);
+            *file = None;
+        }
+    }
+}
+
+impl Reporter for EventLog {
+    fn on_command_start(&self, command: &str) {
+        self.send(&Event::CommandStart { command });
+    }
+
+    fn on_output(&self, command: &str, stream: OutputStream, line: &str) {
+        self.send(&Event::Output {
+            command,
+            stream,
+            line,
+        });
+    }
+
+    fn on_skip(&self, skip: &Skip) {
+        self.send(&Event::Skip(skip));
+    }
+
+    fn on_command_end(&self, end: &CommandEnd) {
+        self.send(&Event::CommandEnd(end));
+    }
+
+    fn on_check_end(&self, end: &CheckEnd) {
+        self.send(&Event::CheckEnd(end));
+    }
+
+    fn wants_output(&self) -> bool {
+        true
+    }
+}
+
+/// Passes every event on to `inner`, keeping the skips for the run summary.
+pub struct SkipRecorder<'a> {
+    inner: &'a dyn Reporter,
+    skips: Mutex<Vec<Skip>>,
+}
+
+impl<'a> SkipRecorder<'a> {
+    pub fn new(inner: &'a dyn Reporter) -> Self {
+        Self {
+            inner,
+            skips: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The skips so far, in order.
+    pub fn skips(&self) -> Vec<Skip> {
+        self.skips
+            .lock()
+            .map(|skips| skips.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Reporter for SkipRecorder<'_> {
+    fn on_command_start(&self, command: &str) {
+        self.inner.on_command_start(command);
+    }
+
+    fn on_output(&self, command: &str, stream: OutputStream, line: &str) {
+        self.inner.on_output(command, stream, line);
+    }
+
+    fn on_skip(&self, skip: &Skip) {
+        if let Ok(mut skips) = self.skips.lock() {
+            skips.push(skip.clone());
+        }
+        self.inner.on_skip(skip);
+    }
+
+    fn on_command_end(&self, end: &CommandEnd) {
+        self.inner.on_command_end(end);
+    }
+
+    fn on_check_end(&self, end: &CheckEnd) {
+        self.inner.on_check_end(end);
+    }
+
+    fn wants_output(&self) -> bool {
+        self.inner.wants_output()
+    }
+}
+
+/// The reporters declared in `[reporters]`.
+pub fn from_config(cfg: &DevflowConfig) -> Reporters {
+    Reporters::new(
+        cfg.reporters
+            .iter()
+            .map(|(name, config)| {
+                Box::new(SubprocessReporter::new(name, config.clone())) as Box<dyn Reporter>
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::problem::{Problem, Severity};
+    use devflow_core::reporter::SkipReason;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn events_use_the_documented_protocol() {
+        let end = CommandEnd {
+            command: "test:unit".to_string(),
+            success: false,
+            duration_ms: 1200,
+            error: Some("boom".to_string()),
+            exit_code: Some(101),
+            signal: None,
+            problems: vec![Problem {
+                owner: "rustc".to_string(),
+                severity: Severity::Error,
+                message: "mismatched types".to_string(),
+                file: Some("src/lib.rs".to_string()),
+                line: Some(3),
+                column: None,
+            }],
+            artifacts: vec![".devflow/artifacts/test-unit/rust/diff.png".to_string()],
+        };
+        assert_eq!(
+            serde_json::to_string(&Event::CommandEnd(&end)).unwrap(),
+            r#"{"event":"command_end","command":"test:unit","success":false,"duration_ms":1200,"error":"boom","exit_code":101,"signal":null,"problems":[{"owner":"rustc","severity":"error","message":"mismatched types","file":"src/lib.rs","line":3,"column":null}],"artifacts":[".devflow/artifacts/test-unit/rust/diff.png"]}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Event::Output {
+                command: "test:unit",
+                stream: OutputStream::Stderr,
+                line: "warning",
+            })
+            .unwrap(),
+            r#"{"event":"output","command":"test:unit","stream":"stderr","line":"warning"}"#
+        );
+        let skip = Skip {
+            command: "test:unit".to_string(),
+            stack: Some("node".to_string()),
+            reason: SkipReason::ManifestNotFound,
+        };
+        assert_eq!(
+            serde_json::to_string(&Event::Skip(&skip)).unwrap(),
+            r#"{"event":"skip","command":"test:unit","stack":"node","reason":"manifest_not_found"}"#
+        );
+
+        let inner = Reporters::default();
+        let recorder = SkipRecorder::new(&inner);
+        recorder.on_skip(&skip);
+        assert_eq!(recorder.skips(), [skip]);
+    }
+
+    #[test]
+    fn plugin_receives_events_until_the_run_ends() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("events.jsonl");
+        let reporter = SubprocessReporter::new(
+            "log",
+            ReporterConfig {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), format!("cat > {}", log.display())],
+                output: false,
+            },
+        );
+        reporter.on_command_start("fmt:check");
+        reporter.on_check_end(&CheckEnd {
+            profile: "pr".to_string(),
+            success: true,
+            duration_ms: 10,
+            commands: 1,
+            failed: 0,
+            exit_code: 0,
+            skipped: Vec::new(),
+        });
+        drop(reporter);
+
+        let events = fs::read_to_string(&log).unwrap();
+        let lines: Vec<&str> = events.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            r#"{"event":"command_start","command":"fmt:check"}"#
+        );
+        assert!(lines[1].starts_with(r#"{"event":"check_end","profile":"pr""#));
+    }
+
+    #[test]
+    fn event_log_receives_output() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let log = EventLog::create(&path).unwrap();
+        assert!(log.wants_output());
+        log.on_command_start("test:unit");
+        log.on_output("test:unit", OutputStream::Stdout, "running 12 tests");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"event\":\"command_start\",\"command\":\"test:unit\"}\n{\"event\":\"output\",\"command\":\"test:unit\",\"stream\":\"stdout\",\"line\":\"running 12 tests\"}\n"
+        );
+        assert!(EventLog::create(&dir.path().join("missing/events.jsonl")).is_err());
+    }
+
+    #[test]
+    fn missing_plugin_is_disabled_without_failing() {
+        let reporter = SubprocessReporter::new(
+            "missing",
+            ReporterConfig {
+                command: "devflow-no-such-reporter".to_string(),
+                args: Vec::new(),
+                output: true,
+            },
+        );
+        reporter.on_command_start("test:unit");
+        reporter.on_command_start("test:unit");
+        assert!(matches!(*reporter.state.lock().unwrap(), State::Disabled));
+    }
+}
@@ -0,0 +1,171 @@
+//! Reproducibility verification (`check:repro`).
+//!
+//! Runs `build:release` twice into fresh output directories, collects an
+//! [`ArtifactManifest`] per stack after each run, and reports every artifact
+//! whose content differs between the two builds.
+
+use anyhow::{bail, Context, Result};
+use devflow_core::artifact::{ArtifactDifference, ArtifactManifest, DifferenceKind};
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry, PrimaryCommand};
+
+use crate::executor::{self, RunOptions};
+
+/// Workspace-relative directory holding the per-run build outputs.
+const REPRO_DIR: &str = ".devflow/repro";
+/// Number of builds compared.
+const RUNS: usize = 2;
+
+/// Builds the project twice and fails if any artifact differs.
+pub fn run(cfg: &DevflowConfig, registry: &ExtensionRegistry, opts: &RunOptions) -> Result<()> {
+    let build = CommandRef {
+        primary: PrimaryCommand::Build,
+        selector: Some("release".to_string()),
+    };
+    registry.ensure_can_run(&build)?;
+
+    let cwd = std::env::current_dir()?;
+    let stacks: Vec<&str> = cfg
+        .project
+        .stack
+        .iter()
+        .map(String::as_str)
+        .filter(|stack| registry.get(stack).is_some())
+        .collect();
+
+    println!("check:repro (runtime={:?})", cfg.runtime.profile);
+
+    let mut runs: Vec<Vec<(String, ArtifactManifest)>> = Vec::with_capacity(RUNS);
+    for run in 1..=RUNS {
+        let run_rel = format!("{REPRO_DIR}/run-{run}");
+        let run_dir = cwd.join(&run_rel);
+        if run_dir.exists() {
+            std::fs::remove_dir_all(&run_dir)
+                .with_context(|| format!("failed to clean {}", run_dir.display()))?;
+        }
+        std::fs::create_dir_all(&run_dir)?;
+
+        let mut run_opts = opts.clone();
+        for stack in &stacks {
+            let ext = registry.get(stack).expect("filtered above");
+            if let Some(var) = ext.output_dir_env() {
+                let path = executor::workspace_path(cfg, &format!("{run_rel}/{stack}"))?;
+                run_opts.env.insert(var.to_string(), path);
+            }
+        }
+
+        println!(" - {} (run {}/{})", build, run, RUNS);
+        executor::run(cfg, registry, &build, &run_opts)?;
+
+        let mut manifests = Vec::new();
+        for stack in &stacks {
+            let ext = registry.get(stack).expect("filtered above");
+            let patterns = ext.artifact_patterns(&build);
+            let stack_dir = run_dir.join(stack);
+            let manifest = if ext.output_dir_env().is_some() {
+                ArtifactManifest::collect(&stack_dir, &patterns)?
+            } else {
+                // Outputs land in the workspace; copy them aside so the next run
+                // cannot overwrite the files being compared.
+                let in_place = ArtifactManifest::collect(&cwd, &patterns)?;
+                in_place.copy_to(&stack_dir)?
+            };
+            manifests.push((stack.to_string(), manifest));
+        }
+        runs.push(manifests);
+    }
+
+    let total: usize = runs[0].iter().map(|(_, m)| m.entries.len()).sum();
+    if total == 0 {
+        bail!("check:repro found no artifacts produced by {}", build);
+    }
+
+    let mut failures = 0;
+    for ((stack, first), (_, second)) in runs[0].iter().zip(&runs[1]) {
+        for diff in first.compare(second) {
+            failures += 1;
+            println!(" ✗ [{}] {}", stack, describe(first, second, &diff));
+        }
+    }
+
+    if failures > 0 {
+        bail!(
+            "check:repro failed: {} of {} artifact(s) are not reproducible (outputs kept in {})",
+            failures,
+            total,
+            REPRO_DIR
+        );
+    }
+
+    println!("check:repro passed: {} artifact(s) reproducible", total);
+    Ok(())
+}
+
+/// Renders a human-readable description of a single artifact difference.
+fn describe(
+    first: &ArtifactManifest,
+    second: &ArtifactManifest,
+    diff: &ArtifactDifference,
+) -> String {
+    match diff.kind {
+        DifferenceKind::Missing => format!("{} only produced by run 1", diff.path),
+        DifferenceKind::Added => format!("{} only produced by run 2", diff.path),
+        DifferenceKind::Changed => {
+            let a = std::fs::read(first.path_of(&diff.path)).unwrap_or_default();
+            let b = std::fs::read(second.path_of(&diff.path)).unwrap_or_default();
+            format!("{} differs: {}", diff.path, content_diff(&a, &b))
+        }
+    }
+}
+
+/// Summarizes where two artifact contents diverge.
+///
+/// Text files report the first differing line; binaries report sizes and the
+/// offset of the first differing byte.
+fn content_diff(a: &[u8], b: &[u8]) -> String {
+    if let (Ok(text_a), Ok(text_b)) = (std::str::from_utf8(a), std::str::from_utf8(b)) {
+        let mut lines_b = text_b.lines();
+        for (idx, line_a) in text_a.lines().enumerate() {
+            let line_b = lines_b.next();
+            if Some(line_a) != line_b {
+                return format!(
+                    "line {}: -{:?} +{:?}",
+                    idx + 1,
+                    line_a,
+                    line_b.unwrap_or("")
+                );
+            }
+        }
+        if let Some(extra) = lines_b.next() {
+            return format!("line {}: +{:?}", text_a.lines().count() + 1, extra);
+        }
+    }
+
+    let offset = a
+        .iter()
+        .zip(b)
+        .position(|(x, y)| x != y)
+        .unwrap_or_else(|| a.len().min(b.len()));
+    format!(
+        "{} -> {} bytes, first difference at byte {:#x}",
+        a.len(),
+        b.len(),
+        offset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_diff_reports_first_differing_line() {
+        let out = content_diff(b"a\nbuilt=1\nc\n", b"a\nbuilt=2\nc\n");
+        assert_eq!(out, r#"line 2: -"built=1" +"built=2""#);
+    }
+
+    #[test]
+    fn content_diff_reports_byte_offset_for_binaries() {
+        let out = content_diff(&[0xff, 0x00, 0x01], &[0xff, 0x00, 0x02, 0x03]);
+        assert_eq!(out, "3 -> 4 bytes, first difference at byte 0x2");
+    }
+}
@@ -0,0 +1,151 @@
+//! SARIF output of matched problems (`--sarif <path>`).
+//!
+//! The reporter collects the problems of every finished command and writes a
+//! SARIF 2.1.0 log when the run ends, with one run per matcher owner (e.g.
+//! `rustc`, `tsc`), ready for GitHub code scanning upload.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+use tracing::warn;
+
+use devflow_core::problem::{Problem, Severity};
+use devflow_core::reporter::{CommandEnd, Reporter};
+
+/// Writes the problems of a run to a SARIF file.
+pub struct SarifReporter {
+    path: PathBuf,
+    problems: Mutex<Vec<Problem>>,
+}
+
+impl SarifReporter {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            problems: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Reporter for SarifReporter {
+    fn on_command_end(&self, end: &CommandEnd) {
+        if let Ok(mut problems) = self.problems.lock() {
+            problems.extend(end.problems.iter().cloned());
+        }
+    }
+}
+
+impl Drop for SarifReporter {
+    fn drop(&mut self) {
+        let problems = self
+            .problems
+            .get_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        let log = serde_json::to_string_pretty(&sarif_log(&problems)).expect("SARIF serializes");
+        if let Err(e) = fs::write(&self.path, log + "\n") {
+            warn!("failed to write SARIF log '{}': {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Builds a SARIF log with one run per problem owner.
+fn sarif_log(problems: &[Problem]) -> Value {
+    let mut by_owner: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    for problem in problems {
+        by_owner
+            .entry(problem.owner.as_str())
+            .or_default()
+            .push(result(problem));
+    }
+    let runs: Vec<Value> = by_owner
+        .into_iter()
+        .map(|(owner, results)| {
+            json!({
+                "tool": { "driver": { "name": owner } },
+                "results": results,
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": runs,
+    })
+}
+
+fn result(problem: &Problem) -> Value {
+    let level = match problem.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Notice => "note",
+    };
+    let mut result = json!({
+        "level": level,
+        "message": { "text": problem.message },
+    });
+    if let Some(file) = &problem.file {
+        let mut location = json!({
+            "artifactLocation": { "uri": file.strip_prefix("./").unwrap_or(file) },
+        });
+        if let Some(line) = problem.line.filter(|&line| line > 0) {
+            let mut region = json!({ "startLine": line });
+            if let Some(column) = problem.column.filter(|&column| column > 0) {
+                region["startColumn"] = json!(column);
+            }
+            location["region"] = region;
+        }
+        result["locations"] = json!([{ "physicalLocation": location }]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn problem(owner: &str, file: Option<&str>) -> Problem {
+        Problem {
+            owner: owner.to_string(),
+            severity: Severity::Warning,
+            message: "unused variable: `x`".to_string(),
+            file: file.map(str::to_string),
+            line: Some(4),
+            column: Some(9),
+        }
+    }
+
+    #[test]
+    fn writes_one_run_per_owner_when_the_run_ends() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("problems.sarif");
+        let reporter = SarifReporter::new(path.clone());
+        reporter.on_command_end(&CommandEnd {
+            command: "lint:static".to_string(),
+            success: false,
+            duration_ms: 10,
+            error: None,
+            exit_code: Some(1),
+            signal: None,
+            problems: vec![problem("rustc", Some("./src/lib.rs")), problem("tsc", None)],
+            artifacts: Vec::new(),
+        });
+        drop(reporter);
+
+        let log: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let runs = log["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0]["tool"]["driver"]["name"], "rustc");
+        let location = &runs[0]["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/lib.rs");
+        assert_eq!(location["region"]["startLine"], 4);
+        assert_eq!(location["region"]["startColumn"], 9);
+        assert_eq!(runs[1]["results"][0]["level"], "warning");
+        assert!(runs[1]["results"][0].get("locations").is_none());
+    }
+}
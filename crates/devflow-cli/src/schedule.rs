@@ -0,0 +1,130 @@
+//! Parallel check runs, limited per resource class (`[concurrency]`).
+//!
+//! Commands start in profile order as soon as their class has a free slot,
+//! so IO-bound linters run next to a CPU-bound build instead of queueing
+//! behind it. Output of commands running at once interleaves by line.
+
+use std::collections::BTreeMap;
+use std::sync::{Condvar, Mutex};
+
+use anyhow::Result;
+
+use devflow_core::config::ConcurrencyConfig;
+use devflow_core::{CommandRef, DevflowConfig, ExtensionRegistry, ResourceClass};
+
+/// The resource class `cmd` counts against: its `[commands] resource`, or
+/// what its extensions declare.
+pub fn resource_class(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    cmd: &CommandRef,
+) -> ResourceClass {
+    cfg.command_config(cmd)
+        .and_then(|command| command.resource)
+        .unwrap_or_else(|| registry.resource_class(cmd))
+}
+
+/// Commands running per class, and whether one has failed.
+#[derive(Default)]
+struct Slots {
+    running: BTreeMap<ResourceClass, usize>,
+    failed: bool,
+}
+
+/// Runs `job` for each of `items`, with at most `limits` of each class at
+/// once, and returns each item's result in item order. With `fail_fast`, no
+/// further job starts after a failure; the items left out have no result.
+pub fn run_limited<T: Sync>(
+    items: &[T],
+    class: impl Fn(&T) -> ResourceClass,
+    limits: &ConcurrencyConfig,
+    fail_fast: bool,
+    job: impl Fn(&T) -> Result<()> + Sync,
+) -> Vec<Option<Result<()>>> {
+    let classes: Vec<ResourceClass> = items.iter().map(class).collect();
+    let results: Vec<Mutex<Option<Result<()>>>> = items.iter().map(|_| Mutex::new(None)).collect();
+    let slots = Mutex::new(Slots::default());
+    let freed = Condvar::new();
+    let mut pending: Vec<usize> = (0..items.len()).collect();
+    std::thread::scope(|scope| {
+        let mut state = slots.lock().unwrap();
+        while !pending.is_empty() && (!fail_fast || !state.failed) {
+            let free = |index: &usize| {
+                let class = classes[*index];
+                state.running.get(&class).copied().unwrap_or(0) < limits.limit(class)
+            };
+            let Some(position) = pending.iter().position(free) else {
+                state = freed.wait(state).unwrap();
+                continue;
+            };
+            let index = pending.remove(position);
+            *state.running.entry(classes[index]).or_default() += 1;
+            let (job, slots, freed, results) = (&job, &slots, &freed, &results);
+            let class = classes[index];
+            scope.spawn(move || {
+                let result = job(&items[index]);
+                let mut state = slots.lock().unwrap();
+                state.failed |= result.is_err();
+                *state.running.get_mut(&class).unwrap() -= 1;
+                *results[index].lock().unwrap() = Some(result);
+                freed.notify_all();
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn limits_each_class_and_stops_after_a_failure_when_failing_fast() {
+        let limits = ConcurrencyConfig { cpu: 1, io: 2 };
+        let class = |item: &(ResourceClass, bool)| item.0;
+        let running = [AtomicUsize::new(0), AtomicUsize::new(0)];
+        let peak = [AtomicUsize::new(0), AtomicUsize::new(0)];
+        let ran = AtomicUsize::new(0);
+        let job = |item: &(ResourceClass, bool)| {
+            let slot = item.0 as usize;
+            let now = running[slot].fetch_add(1, Ordering::SeqCst) + 1;
+            peak[slot].fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            running[slot].fetch_sub(1, Ordering::SeqCst);
+            ran.fetch_add(1, Ordering::SeqCst);
+            if item.1 {
+                Err(anyhow!("failed"))
+            } else {
+                Ok(())
+            }
+        };
+
+        let (io, cpu) = ((ResourceClass::Io, false), (ResourceClass::Cpu, false));
+        let items = [cpu, io, io, cpu, io, io, cpu];
+        let results = run_limited(&items, class, &limits, true, job);
+        assert!(results.iter().all(|result| matches!(result, Some(Ok(())))));
+        assert_eq!(ran.load(Ordering::SeqCst), items.len());
+        assert_eq!(peak[ResourceClass::Cpu as usize].load(Ordering::SeqCst), 1);
+        assert_eq!(peak[ResourceClass::Io as usize].load(Ordering::SeqCst), 2);
+
+        ran.store(0, Ordering::SeqCst);
+        let items = [(ResourceClass::Cpu, true), cpu, cpu];
+        let results = run_limited(&items, class, &limits, true, job);
+        assert!(matches!(results.as_slice(), [Some(Err(_)), None, None]));
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+
+        ran.store(0, Ordering::SeqCst);
+        let results = run_limited(&items, class, &limits, false, job);
+        assert!(matches!(
+            results.as_slice(),
+            [Some(Err(_)), Some(Ok(())), Some(Ok(()))]
+        ));
+        assert_eq!(ran.load(Ordering::SeqCst), 3);
+    }
+}
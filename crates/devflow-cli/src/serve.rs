@@ -0,0 +1,835 @@
+//! `serve`: devflow over HTTP, for internal platforms.
+//!
+//! `dwf serve --listen 127.0.0.1:8787` answers JSON requests:
+//!
+//! - `GET /capabilities`: the capabilities of every stack, and the target profiles
+//! - `POST /runs` with `{"command": "test:unit"}` or `{"profile": "pr"}`: starts a run
+//! - `GET /runs` and `GET /runs/<id>`: the runs started by this server
+//! - `GET /runs/<id>/logs`: the run's output as Server-Sent Events, following it
+//!   until it ends
+//! - `GET /history?last=30d&command=test:unit`: the run history of `dwf stats`
+//!
+//! Each run is a child `dwf` process in the server's working directory, so it
+//! records its history and notifies like any other; only one runs at a time,
+//! as runs share the workspace. The output of the last [`MAX_RUNS`] runs is
+//! kept in memory.
+//!
+//! Every request needs `Authorization: Bearer <token>`, with the token of
+//! `DEVFLOW_SERVE_TOKEN` or one generated and printed at startup. Requests
+//! from browsers are refused: those with an `Origin`, a `Host` other than the
+//! listen address (DNS rebinding), or a `POST` body that is not
+//! `application/json`. `POST /runs` only starts the commands `[serve] allow`
+//! matches.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use devflow_core::{DevflowConfig, ExtensionRegistry, PrimaryCommand};
+
+use crate::capture::strip_ansi;
+use crate::history;
+
+/// Environment variable holding the bearer token requests must carry.
+const TOKEN_ENV: &str = "DEVFLOW_SERVE_TOKEN";
+/// Finished runs kept, with their output, before the oldest is dropped.
+const MAX_RUNS: usize = 50;
+/// Largest request head and body accepted.
+const MAX_REQUEST: usize = 64 * 1024;
+/// How long reading a request may take.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Interval of the comments that keep an idle log stream open.
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// How a run stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RunState {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// One output line of a run.
+#[derive(Debug, Clone, Serialize)]
+struct LogLine {
+    stream: &'static str,
+    line: String,
+}
+
+/// A `dwf` invocation started over HTTP.
+#[derive(Debug)]
+struct Run {
+    id: u64,
+    invocation: String,
+    /// Unix seconds.
+    started_at: u64,
+    state: RunState,
+    exit_code: Option<i32>,
+    log: Vec<LogLine>,
+}
+
+impl Run {
+    fn summary(&self) -> Value {
+        json!({
+            "id": self.id,
+            "invocation": self.invocation,
+            "started_at": self.started_at,
+            "state": self.state,
+            "exit_code": self.exit_code,
+            "lines": self.log.len(),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Runs {
+    next_id: u64,
+    list: VecDeque<Run>,
+}
+
+impl Runs {
+    fn get(&self, id: u64) -> Option<&Run> {
+        self.list.iter().find(|run| run.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut Run> {
+        self.list.iter_mut().find(|run| run.id == id)
+    }
+}
+
+/// The runs, shared with the threads following their processes.
+#[derive(Debug, Default)]
+struct Log {
+    runs: Mutex<Runs>,
+    /// Signalled when a run logs a line or ends.
+    changed: Condvar,
+}
+
+impl Log {
+    fn runs(&self) -> MutexGuard<'_, Runs> {
+        self.runs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// What request handlers share.
+struct State<'a> {
+    cfg: &'a DevflowConfig,
+    /// `GET /capabilities`, fixed once the extensions are registered.
+    capabilities: Value,
+    /// The program runs start, `dwf` itself outside of tests.
+    program: PathBuf,
+    config_path: String,
+    token: String,
+    /// The `Host` values requests may carry; any when listening on all
+    /// interfaces.
+    hosts: Vec<String>,
+    log: Arc<Log>,
+}
+
+/// Serves `cfg` on `listen` until the process is stopped.
+///
+/// # Errors
+/// Returns an error if `listen` cannot be bound or `dwf` itself cannot be
+/// located.
+pub fn run(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    config_path: &str,
+    listen: &str,
+) -> Result<()> {
+    let listener =
+        TcpListener::bind(listen).with_context(|| format!("failed to listen on {listen}"))?;
+    let addr = listener.local_addr()?;
+    let token = match std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+    {
+        Some(token) => token,
+        None => {
+            let token = generate_token()?;
+            println!("{TOKEN_ENV} is not set; requests need 'Authorization: Bearer {token}'");
+            token
+        }
+    };
+    let state = State {
+        cfg,
+        capabilities: capabilities(cfg, registry),
+        program: std::env::current_exe().context("failed to locate the dwf binary")?,
+        config_path: config_path.to_string(),
+        token,
+        hosts: allowed_hosts(addr),
+        log: Arc::default(),
+    };
+    info!(target: "devflow", "serving devflow on http://{}", addr);
+    accept(&state, &listener);
+    Ok(())
+}
+
+/// A random token of 32 bytes, hex-encoded.
+fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("failed to generate a token: {e}"))?;
+    Ok(hex::encode(bytes))
+}
+
+/// The `Host` values of requests to `addr`; empty, so any, for the
+/// unspecified address, whose clients may use any of the host's names.
+fn allowed_hosts(addr: SocketAddr) -> Vec<String> {
+    if addr.ip().is_unspecified() {
+        return Vec::new();
+    }
+    let mut hosts = vec![addr.to_string()];
+    if addr.ip().is_loopback() {
+        hosts.push(format!("localhost:{}", addr.port()));
+    }
+    hosts
+}
+
+/// Compares `a` and `b` in time that depends only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Handles every connection of `listener` on its own thread.
+fn accept(state: &State, listener: &TcpListener) {
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    scope.spawn(move || {
+                        if let Err(e) = handle(state, stream) {
+                            debug!("connection failed: {:#}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("failed to accept a connection: {}", e),
+            }
+        }
+    });
+}
+
+fn handle(state: &State, stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut stream = stream;
+    let response = match read_request(&mut reader) {
+        Ok(request) => {
+            debug!("{} {}", request.method, request.path);
+            route(state, &request)
+        }
+        Err(e) => Response::error(400, format!("{e:#}")),
+    };
+    match response {
+        Response::Json(status, body) => write_json(&mut stream, status, &body)?,
+        Response::Logs(id) => stream_logs(&state.log, id, &mut stream)?,
+    }
+    Ok(())
+}
+
+/// The capabilities of every registered stack, and the target profiles.
+fn capabilities(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Value {
+    let stacks: BTreeMap<&str, Vec<String>> = registry
+        .names()
+        .into_iter()
+        .filter_map(|name| {
+            let mut capabilities: Vec<String> =
+                registry.get(name)?.capabilities().into_iter().collect();
+            capabilities.sort();
+            Some((name, capabilities))
+        })
+        .collect();
+    let profiles: Vec<&String> = cfg.targets.profiles.keys().collect();
+    json!({ "stacks": stacks, "profiles": profiles })
+}
+
+/// A parsed HTTP request.
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads one HTTP/1.1 request from `reader`.
+fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let mut head = 0;
+    let mut next_line = |reader: &mut dyn BufRead| -> Result<String> {
+        let mut line = String::new();
+        head += reader
+            .take((MAX_REQUEST - head.min(MAX_REQUEST)) as u64)
+            .read_line(&mut line)?;
+        if !line.ends_with('\n') {
+            bail!("request head is incomplete or larger than {MAX_REQUEST} bytes");
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    };
+
+    let request_line = next_line(reader)?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("malformed request line '{request_line}'");
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(key), percent_decode(value))
+            })
+            .collect(),
+        ..Request::default()
+    };
+    loop {
+        let line = next_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("malformed header '{line}'"))?;
+        request
+            .headers
+            .push((name.trim().to_string(), value.trim().to_string()));
+    }
+    let length: usize = match request.header("content-length") {
+        Some(length) => length
+            .parse()
+            .map_err(|_| anyhow!("invalid Content-Length '{length}'"))?,
+        None => 0,
+    };
+    if length > MAX_REQUEST {
+        bail!("request body is larger than {MAX_REQUEST} bytes");
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(request)
+}
+
+/// Decodes `%XX` escapes and `+` of a query component.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match std::str::from_utf8(&bytes[i + 1..bytes.len().min(i + 3)])
+                .ok()
+                .filter(|hex| hex.len() == 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    out.push(byte);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// What a request is answered with.
+#[derive(Debug)]
+enum Response {
+    Json(u16, Value),
+    /// The log stream of a run.
+    Logs(u64),
+}
+
+impl Response {
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::Json(status, json!({ "error": message.into() }))
+    }
+}
+
+/// Refuses `request` unless it carries the token and does not come from a
+/// browser page.
+fn check_access(state: &State, request: &Request) -> Option<Response> {
+    if request.header("origin").is_some() {
+        return Some(Response::error(403, "cross-origin requests are refused"));
+    }
+    let host = request.header("host").unwrap_or_default();
+    if !state.hosts.is_empty() && !state.hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) {
+        return Some(Response::error(
+            403,
+            format!(
+                "unexpected Host '{host}' (expected {})",
+                state.hosts.join(" or ")
+            ),
+        ));
+    }
+    let token = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !constant_time_eq(token.as_bytes(), state.token.as_bytes()) {
+        return Some(Response::error(
+            401,
+            format!("missing or wrong bearer token ({TOKEN_ENV})"),
+        ));
+    }
+    let media_type = request
+        .header("content-type")
+        .and_then(|value| value.split(';').next())
+        .map(str::trim);
+    if request.method == "POST"
+        && !media_type.is_some_and(|media| media.eq_ignore_ascii_case("application/json"))
+    {
+        return Some(Response::error(
+            415,
+            "expected Content-Type: application/json",
+        ));
+    }
+    None
+}
+
+fn route(state: &State, request: &Request) -> Response {
+    if let Some(refused) = check_access(state, request) {
+        return refused;
+    }
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let method = request.method.as_str();
+    match (method, segments.as_slice()) {
+        ("GET", ["capabilities"]) => Response::Json(200, state.capabilities.clone()),
+        ("GET", ["runs"]) => {
+            let runs: Vec<Value> = state.log.runs().list.iter().map(Run::summary).collect();
+            Response::Json(200, Value::Array(runs))
+        }
+        ("POST", ["runs"]) => {
+            match invocation(state.cfg, &request.body).and_then(|inv| start(state, inv)) {
+                Ok(summary) => Response::Json(202, summary),
+                Err((status, message)) => Response::error(status, message),
+            }
+        }
+        ("GET", ["runs", id]) => match id
+            .parse()
+            .ok()
+            .and_then(|id| state.log.runs().get(id).map(Run::summary))
+        {
+            Some(summary) => Response::Json(200, summary),
+            None => Response::error(404, format!("no run '{id}'")),
+        },
+        ("GET", ["runs", id, "logs"]) => match id.parse() {
+            Ok(id) if state.log.runs().get(id).is_some() => Response::Logs(id),
+            _ => Response::error(404, format!("no run '{id}'")),
+        },
+        ("GET", ["history"]) => {
+            match history::records(state.cfg, request.param("last"), request.param("command")) {
+                Ok(records) => Response::Json(200, json!(records)),
+                Err(e) => Response::error(400, format!("{e:#}")),
+            }
+        }
+        (_, ["capabilities" | "history"] | ["runs", ..]) => {
+            Response::error(405, format!("{method} is not allowed on {}", request.path))
+        }
+        _ => Response::error(404, format!("no such endpoint {}", request.path)),
+    }
+}
+
+/// The `dwf` invocation a `POST /runs` body asks for, if `[serve] allow`
+/// lets it run.
+fn invocation(cfg: &DevflowConfig, body: &[u8]) -> Result<String, (u16, String)> {
+    let (invocation, canonical) = requested(cfg, body).map_err(|e| (400, format!("{e:#}")))?;
+    if !cfg.serve.allows(&canonical) {
+        return Err((
+            403,
+            format!("{canonical} may not be started over HTTP (see [serve] allow)"),
+        ));
+    }
+    Ok(invocation)
+}
+
+/// The invocation a `POST /runs` body asks for and the canonical command it
+/// resolves to.
+fn requested(cfg: &DevflowConfig, body: &[u8]) -> Result<(String, String)> {
+    let body: Value = serde_json::from_slice(body).context("invalid JSON body")?;
+    let text = |key: &str| body.get(key).and_then(Value::as_str);
+    match (text("command"), text("profile")) {
+        (Some(command), None) => {
+            let (cmd, _) = cfg
+                .aliases()
+                .parse(command)
+                .map_err(|e| anyhow!("invalid command '{}': {}", command, e))?;
+            if cmd.primary == PrimaryCommand::Serve {
+                bail!("serve cannot be started over HTTP");
+            }
+            Ok((command.to_string(), cmd.canonical()))
+        }
+        (None, Some(profile)) => {
+            if !cfg.targets.profiles.contains_key(profile) {
+                bail!("unknown targets profile '{}'", profile);
+            }
+            let invocation = format!("check:{profile}");
+            Ok((invocation.clone(), invocation))
+        }
+        _ => bail!("expected exactly one of \"command\" or \"profile\""),
+    }
+}
+
+/// Starts `invocation` as a child `dwf`, unless a run is in progress.
+fn start(state: &State, invocation: String) -> Result<Value, (u16, String)> {
+    let mut runs = state.log.runs();
+    if let Some(running) = runs.list.iter().find(|run| run.state == RunState::Running) {
+        return Err((409, format!("run {} is still running", running.id)));
+    }
+    let mut child = Command::new(&state.program)
+        .args(["--config", &state.config_path, &invocation])
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| (500, format!("failed to start {invocation}: {e}")))?;
+
+    runs.next_id += 1;
+    let id = runs.next_id;
+    let run = Run {
+        id,
+        invocation,
+        started_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default(),
+        state: RunState::Running,
+        exit_code: None,
+        log: Vec::new(),
+    };
+    let summary = run.summary();
+    runs.list.push_back(run);
+    while runs.list.len() > MAX_RUNS {
+        runs.list.pop_front();
+    }
+    drop(runs);
+
+    let readers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|out| (Box::new(out) as Box<dyn Read + Send>, "stdout")),
+        child
+            .stderr
+            .take()
+            .map(|err| (Box::new(err) as Box<dyn Read + Send>, "stderr")),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(pipe, stream)| {
+        let log = Arc::clone(&state.log);
+        std::thread::spawn(move || {
+            for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                if let Some(run) = log.runs().get_mut(id) {
+                    run.log.push(LogLine {
+                        stream,
+                        line: strip_ansi(&line).into_owned(),
+                    });
+                }
+                log.changed.notify_all();
+            }
+        })
+    })
+    .collect();
+    let log = Arc::clone(&state.log);
+    std::thread::spawn(move || {
+        for reader in readers {
+            let _ = reader.join();
+        }
+        let status = child.wait();
+        if let Some(run) = log.runs().get_mut(id) {
+            let code = status.as_ref().ok().and_then(|status| status.code());
+            run.exit_code = code;
+            run.state = if code == Some(0) {
+                RunState::Succeeded
+            } else {
+                RunState::Failed
+            };
+        }
+        log.changed.notify_all();
+    });
+    Ok(summary)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    }
+}
+
+fn write_json(out: &mut impl Write, status: u16, body: &Value) -> std::io::Result<()> {
+    let body = body.to_string();
+    write!(
+        out,
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason(status),
+        body.len()
+    )?;
+    out.flush()
+}
+
+/// Sends run `id`'s output as `output` events, then an `end` event with its
+/// summary once it finished.
+fn stream_logs(log: &Log, id: u64, out: &mut impl Write) -> std::io::Result<()> {
+    out.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+    )?;
+    let mut sent = 0;
+    loop {
+        let mut runs = log.runs();
+        let idle = runs
+            .get(id)
+            .is_some_and(|run| run.log.len() == sent && run.state == RunState::Running);
+        if idle {
+            let (guard, wait) = log
+                .changed
+                .wait_timeout(runs, KEEPALIVE)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            runs = guard;
+            if wait.timed_out() {
+                drop(runs);
+                out.write_all(b": keepalive\n\n")?;
+                out.flush()?;
+                continue;
+            }
+        }
+        let Some(run) = runs.get(id) else {
+            return Ok(());
+        };
+        let lines: Vec<LogLine> = run.log[sent..].to_vec();
+        let end = (run.state != RunState::Running).then(|| run.summary());
+        drop(runs);
+
+        for line in &lines {
+            let data = serde_json::to_string(line).unwrap_or_default();
+            write!(out, "event: output\ndata: {data}\n\n")?;
+        }
+        sent += lines.len();
+        if let Some(summary) = end {
+            write!(out, "event: end\ndata: {summary}\n\n")?;
+            return out.flush();
+        }
+        out.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &std::path::Path) -> DevflowConfig {
+        let path = dir.join("devflow.toml");
+        std::fs::write(
+            &path,
+            "[project]\nname = \"demo\"\nstack = [\"custom\"]\n\n[targets]\npr = [\"test:unit\"]\n",
+        )
+        .unwrap();
+        DevflowConfig::load_from_file(path.to_str().unwrap()).unwrap()
+    }
+
+    fn state(cfg: &DevflowConfig) -> State<'_> {
+        State {
+            cfg,
+            capabilities: json!({}),
+            // Runs `echo --config devflow.toml <invocation>`.
+            program: PathBuf::from("echo"),
+            config_path: "devflow.toml".to_string(),
+            token: "secret".to_string(),
+            hosts: allowed_hosts("127.0.0.1:8787".parse().unwrap()),
+            log: Arc::default(),
+        }
+    }
+
+    /// An authorized request, as a script sends it.
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        let raw = format!(
+            "{method} {path} HTTP/1.1\r\nHost: localhost:8787\r\nAuthorization: Bearer secret\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        read_request(&mut raw.as_bytes()).unwrap()
+    }
+
+    fn json_of(response: Response) -> (u16, Value) {
+        match response {
+            Response::Json(status, body) => (status, body),
+            Response::Logs(id) => panic!("unexpected log stream of run {id}"),
+        }
+    }
+
+    #[test]
+    fn parses_requests() {
+        let request = request("POST", "/history?last=30d&command=test%3Aunit", "{}");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/history");
+        assert_eq!(request.param("command"), Some("test:unit"));
+        assert_eq!(request.header("content-length"), Some("2"));
+        assert_eq!(request.body, b"{}");
+        assert_eq!(percent_decode("a+b%2"), "a b%2");
+
+        assert!(read_request(&mut "GET /\r\n\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "GET / HTTP/1.1\r\nHost".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn validates_what_runs_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = config(dir.path());
+        let inv = |body: &str| invocation(&cfg, body.as_bytes()).map_err(|(_, e)| e);
+        assert_eq!(
+            inv(r#"{"command": "test:unit"}"#),
+            Ok("test:unit".to_string())
+        );
+        assert_eq!(inv(r#"{"profile": "pr"}"#), Ok("check:pr".to_string()));
+        assert!(inv(r#"{"profile": "nightly"}"#)
+            .unwrap_err()
+            .contains("unknown targets profile"));
+        assert!(inv(r#"{"command": "serve"}"#).is_err());
+        assert!(inv(r#"{"command": "test:unit", "profile": "pr"}"#).is_err());
+        assert!(inv("not json").is_err());
+
+        // Commands that rewrite the workspace or publish are not allowed by default.
+        for command in ["fmt:fix", "release:publish", "prune:cache"] {
+            let body = format!(r#"{{"command": "{command}"}}"#);
+            let err = invocation(&cfg, body.as_bytes()).unwrap_err();
+            assert_eq!(err.0, 403, "{command}: {}", err.1);
+        }
+    }
+
+    #[test]
+    fn runs_and_streams_a_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = config(dir.path());
+        let state = state(&cfg);
+
+        let (status, run) = json_of(route(
+            &state,
+            &request("POST", "/runs", r#"{"command": "test:unit"}"#),
+        ));
+        assert_eq!(status, 202, "{run}");
+        assert_eq!(run["id"], 1);
+        let Response::Logs(id) = route(&state, &request("GET", "/runs/1/logs", "")) else {
+            panic!("expected a log stream");
+        };
+        let mut events = Vec::new();
+        stream_logs(&state.log, id, &mut events).unwrap();
+        let events = String::from_utf8(events).unwrap();
+        assert!(events.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream"));
+        assert!(
+            events.contains(
+                "event: output\ndata: {\"stream\":\"stdout\",\"line\":\"--config devflow.toml test:unit\"}\n\n"
+            ),
+            "{events}"
+        );
+        assert!(events.contains("event: end\ndata: {"), "{events}");
+
+        let (_, run) = json_of(route(&state, &request("GET", "/runs/1", "")));
+        assert_eq!(run["state"], "succeeded");
+        assert_eq!(run["exit_code"], 0);
+        assert_eq!(
+            json_of(route(&state, &request("GET", "/runs/7", ""))).0,
+            404
+        );
+        assert_eq!(
+            json_of(route(&state, &request("DELETE", "/runs", ""))).0,
+            405
+        );
+        assert_eq!(json_of(route(&state, &request("GET", "/", ""))).0, 404);
+
+        state.log.runs().list[0].state = RunState::Running;
+        let (status, body) = json_of(route(
+            &state,
+            &request("POST", "/runs", r#"{"profile": "pr"}"#),
+        ));
+        assert_eq!(status, 409, "{body}");
+    }
+
+    #[test]
+    fn refuses_requests_without_the_token_or_from_browsers() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = config(dir.path());
+        let state = state(&cfg);
+        let status = |request: &Request| json_of(route(&state, request)).0;
+        let without = |name: &str, request: Request| Request {
+            headers: request
+                .headers
+                .into_iter()
+                .filter(|(key, _)| !key.eq_ignore_ascii_case(name))
+                .collect(),
+            ..request
+        };
+        let with = |name: &str, value: &str, request: Request| {
+            let mut request = without(name, request);
+            request.headers.push((name.to_string(), value.to_string()));
+            request
+        };
+        let post = || request("POST", "/runs", r#"{"command": "test:unit"}"#);
+
+        assert_eq!(status(&request("GET", "/capabilities", "")), 200);
+        assert_eq!(
+            status(&without(
+                "Authorization",
+                request("GET", "/capabilities", "")
+            )),
+            401
+        );
+        assert_eq!(status(&with("Authorization", "Bearer secreT", post())), 401);
+        // A page the developer visits posting a CORS-simple request.
+        assert_eq!(status(&with("Content-Type", "text/plain", post())), 415);
+        assert_eq!(status(&with("Origin", "https://evil.example", post())), 403);
+        // DNS rebinding: the page's own name resolves to the loopback address.
+        assert_eq!(status(&with("Host", "evil.example:8787", post())), 403);
+        assert_eq!(
+            status(&with("Host", "127.0.0.1:8787", request("GET", "/runs", ""))),
+            200
+        );
+
+        assert!(allowed_hosts("0.0.0.0:8787".parse().unwrap()).is_empty());
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secrets"));
+        assert_eq!(generate_token().unwrap().len(), 64);
+    }
+}
@@ -0,0 +1,239 @@
+//! Cache snapshots (`cache:export`, `cache:import`).
+//!
+//! A snapshot is a tarball of the extensions' cache mounts under the cache
+//! root (plus the pinned tools), so an air-gapped or ephemeral runner can
+//! start from a warm cache. `tar` picks the compression from the file name
+//! (`.tar.zst` needs `zstd` on `PATH`, `.tar.gz`, `.tar`); run logs and the
+//! history database are never included, and an import extracts nothing but
+//! the cache directories, whatever else the tarball holds.
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use devflow_core::{DevflowConfig, ExtensionRegistry};
+
+use crate::executor;
+
+/// Cache directories a full snapshot includes besides the cache mounts.
+const SHARED_DIRS: &[&str] = &["tools"];
+
+/// Writes the cache directories of `stacks` (every extension when empty) to
+/// `file` (`cache:export`).
+///
+/// # Errors
+/// Returns an error if a stack has no extension, no cache directory exists
+/// yet, or `tar` fails.
+pub fn export(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    file: &str,
+    stacks: &[String],
+) -> Result<()> {
+    let root = executor::cache_root(cfg);
+    let dirs: Vec<String> = snapshot_dirs(registry, stacks)?
+        .into_iter()
+        .filter(|dir| root.join(dir).is_dir())
+        .collect();
+    if dirs.is_empty() {
+        bail!(
+            "nothing to export: no cache directories under '{}' (run 'dwf cache:warm' first)",
+            root.display()
+        );
+    }
+    let mut tar = Command::new("tar");
+    tar.arg("-caf").arg(file).arg("-C").arg(&root).args(&dirs);
+    if let Err(e) = run_tar(tar, "export") {
+        let _ = std::fs::remove_file(file);
+        return Err(e);
+    }
+    let size = std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+    println!(
+        "cache:export wrote {} ({}; {} MB)",
+        file,
+        dirs.join(", "),
+        size / 1024 / 1024
+    );
+    Ok(())
+}
+
+/// Restores the cache directories of `stacks` (every extension's plus
+/// [`SHARED_DIRS`] when empty) from a snapshot into the cache root
+/// (`cache:import`). Files are owned by the importing user.
+///
+/// # Errors
+/// Returns an error if `file` does not exist, a stack has no extension, the
+/// snapshot holds none of the directories, or `tar` fails, e.g. because the
+/// snapshot lacks a requested directory.
+pub fn import(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    file: &str,
+    stacks: &[String],
+) -> Result<()> {
+    if !Path::new(file).is_file() {
+        bail!("cache snapshot '{}' not found", file);
+    }
+    let root = executor::cache_root(cfg);
+    std::fs::create_dir_all(&root)
+        .with_context(|| format!("failed to create cache root '{}'", root.display()))?;
+    let mut dirs = snapshot_dirs(registry, stacks)?;
+    if stacks.is_empty() {
+        // A full snapshot holds the directories that existed at export.
+        let mut list = Command::new("tar");
+        list.arg("-tf").arg(file);
+        let members = run_tar(list, "import")?;
+        dirs.retain(|dir| {
+            members.lines().any(|member| {
+                member.trim_end_matches('/') == dir || member.starts_with(&format!("{dir}/"))
+            })
+        });
+        if dirs.is_empty() {
+            bail!("'{}' holds no cache directories", file);
+        }
+    }
+    let mut tar = Command::new("tar");
+    tar.arg("-xf")
+        .arg(file)
+        .arg("--no-same-owner")
+        .arg("-C")
+        .arg(&root)
+        .args(&dirs);
+    run_tar(tar, "import")?;
+    println!("cache:import restored {} into {}", file, root.display());
+    Ok(())
+}
+
+/// The cache-root-relative directories a snapshot of `stacks` covers: their
+/// cache mounts, or every extension's plus [`SHARED_DIRS`] when empty.
+fn snapshot_dirs(registry: &ExtensionRegistry, stacks: &[String]) -> Result<Vec<String>> {
+    let mounts = if stacks.is_empty() {
+        registry.all_cache_mounts()
+    } else {
+        let mut mounts = Vec::new();
+        for stack in stacks {
+            let ext = registry
+                .get(stack)
+                .ok_or_else(|| anyhow!("no extension '{}' is registered", stack))?;
+            mounts.extend(ext.cache_mounts());
+        }
+        mounts
+    };
+    let mut dirs: Vec<String> = mounts
+        .iter()
+        .filter_map(|mount| executor::parse_mount(mount))
+        .map(|(host, _)| host.to_string())
+        .collect();
+    if stacks.is_empty() {
+        dirs.extend(SHARED_DIRS.iter().map(|dir| dir.to_string()));
+    }
+    dirs.sort();
+    dirs.dedup();
+    Ok(dirs)
+}
+
+/// Runs `tar`, returning what it printed.
+fn run_tar(mut tar: Command, action: &str) -> Result<String> {
+    let output = tar
+        .output()
+        .with_context(|| format!("failed to run tar for cache:{action}"))?;
+    if !output.status.success() {
+        bail!(
+            "cache:{} failed: tar exited with {}: {}",
+            action,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::config::CacheConfig;
+
+    fn config(root: &Path) -> DevflowConfig {
+        DevflowConfig {
+            cache: Some(CacheConfig {
+                root: Some(root.display().to_string()),
+                ..Default::default()
+            }),
+            ..DevflowConfig::default()
+        }
+    }
+
+    #[test]
+    fn exports_and_imports_cache_directories() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(devflow_ext_node::NodeExtension::new()));
+        registry.register(Box::new(devflow_ext_rust::RustExtension::new()));
+        let node = ["node".to_string()];
+        assert_eq!(snapshot_dirs(&registry, &node).unwrap(), ["node/npm"]);
+        assert!(snapshot_dirs(&registry, &[])
+            .unwrap()
+            .contains(&"tools".to_string()));
+        assert!(snapshot_dirs(&registry, &["go".to_string()]).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        for file in [
+            "node/npm/_cacache/index",
+            "rust/cargo/registry/crate",
+            "logs/run.gz",
+        ] {
+            let path = source.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+        let archive = dir.path().join("cache.tar");
+        let archive = archive.to_str().unwrap();
+        export(&config(&source), &registry, archive, &[]).unwrap();
+
+        let target = dir.path().join("target");
+        import(&config(&target), &registry, archive, &node).unwrap();
+        assert!(target.join("node/npm/_cacache/index").is_file());
+        assert!(!target.join("rust").exists());
+
+        import(&config(&target), &registry, archive, &[]).unwrap();
+        assert!(target.join("rust/cargo/registry/crate").is_file());
+        assert!(!target.join("logs").exists());
+
+        // Nothing outside the cache directories is restored, even when the
+        // snapshot holds it.
+        std::fs::write(source.join("history.db"), "forged").unwrap();
+        let forged = dir.path().join("forged.tar");
+        let status = Command::new("tar")
+            .arg("-cf")
+            .arg(&forged)
+            .arg("-C")
+            .arg(&source)
+            .args(["history.db", "logs", "node"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let target = dir.path().join("forged-target");
+        import(&config(&target), &registry, forged.to_str().unwrap(), &[]).unwrap();
+        assert!(target.join("node/npm/_cacache/index").is_file());
+        assert!(!target.join("history.db").exists());
+        assert!(!target.join("logs").exists());
+
+        std::fs::write(dir.path().join("notes"), "x").unwrap();
+        let empty = dir.path().join("empty.tar");
+        let status = Command::new("tar")
+            .arg("-cf")
+            .arg(&empty)
+            .arg("-C")
+            .arg(dir.path())
+            .arg("notes")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let err = import(&config(&target), &registry, empty.to_str().unwrap(), &[]).unwrap_err();
+        assert!(
+            err.to_string().contains("holds no cache directories"),
+            "{err}"
+        );
+    }
+}
@@ -25,6 +25,14 @@ pub const ERROR: Style = Style::new()
     .fg_color(Some(Color::Ansi(AnsiColor::Red)))
     .bold();
 
+pub const DIFF_HEADER: Style = Style::new().bold();
+
+pub const DIFF_HUNK: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Cyan)));
+
+pub const DIFF_REMOVED: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Red)));
+
+pub const DIFF_ADDED: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Green)));
+
 pub const PLACEHOLDER: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Cyan)));
 
 pub fn get_clap_styles() -> clap::builder::Styles {
@@ -0,0 +1,275 @@
+//! Pinned tool provisioning (`dwf setup:tools`).
+//!
+//! Extensions declare the tools their commands use (see
+//! [`devflow_core::tool`]). `setup:tools` downloads each pinned release for
+//! the host into `<cache root>/tools/<name>/<version>`, and host runs put the
+//! installed directories first on `PATH`, so every run uses the same versions
+//! without installing anything globally. Containerized runs use the tools of
+//! their image.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Context, Result};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use devflow_core::tool::{self, ToolRequirement};
+use devflow_core::{DevflowConfig, ExecutionAction, ExtensionRegistry};
+
+use crate::executor;
+
+/// Largest download accepted.
+const MAX_DOWNLOAD: u64 = 512 * 1024 * 1024;
+
+/// Installs the tools of every dispatched stack that are not installed yet.
+///
+/// # Errors
+/// Returns an error if the host platform has no releases, or a download,
+/// checksum or unpack fails.
+pub fn setup(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Result<()> {
+    let tools = registry.tools_for(&executor::ordered_stacks(cfg));
+    if tools.is_empty() {
+        println!("no tools required");
+        return Ok(());
+    }
+    let target = tool::host_target().ok_or_else(|| {
+        anyhow!(
+            "no tool releases for {}-{}",
+            std::env::consts::ARCH,
+            std::env::consts::OS
+        )
+    })?;
+    for tool in &tools {
+        let dir = tool_dir(cfg, tool);
+        if dir.join(bin_name(tool)).is_file() {
+            println!("{} {} already installed", tool.name, tool.version);
+            continue;
+        }
+        let url = tool.url_for(target);
+        info!(target: "devflow", "download {} {} from {}", tool.name, tool.version, url);
+        let bytes = download(&url)?;
+        if let Some(expected) = tool.sha256.get(target) {
+            let actual = hex::encode(Sha256::digest(&bytes));
+            if !actual.eq_ignore_ascii_case(expected) {
+                bail!(
+                    "checksum mismatch for {} {}: expected {}, got {}",
+                    tool.name,
+                    tool.version,
+                    expected,
+                    actual
+                );
+            }
+        }
+        unpack(&bytes, &url, &bin_name(tool), &dir)
+            .with_context(|| format!("failed to install {} {}", tool.name, tool.version))?;
+        println!(
+            "installed {} {} ({})",
+            tool.name,
+            tool.version,
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// The installed tool directories of the dispatched stacks, in name order.
+pub fn installed_dirs(cfg: &DevflowConfig, registry: &ExtensionRegistry) -> Vec<PathBuf> {
+    registry
+        .tools_for(&executor::ordered_stacks(cfg))
+        .iter()
+        .filter(|tool| tool_dir(cfg, tool).join(bin_name(tool)).is_file())
+        .map(|tool| tool_dir(cfg, tool))
+        .collect()
+}
+
+/// Puts `dirs` in front of the `PATH` of `action`, or of the inherited one.
+///
+/// # Errors
+/// Returns an error if a directory contains the path separator.
+pub fn add_to_path(action: &mut ExecutionAction, dirs: &[PathBuf]) -> Result<()> {
+    if dirs.is_empty() {
+        return Ok(());
+    }
+    let base = action
+        .env
+        .get("PATH")
+        .cloned()
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+    let path = std::env::join_paths(dirs.iter().cloned().chain(std::env::split_paths(&base)))
+        .context("tool directory cannot be put on PATH")?;
+    action
+        .env
+        .insert("PATH".to_string(), path.to_string_lossy().into_owned());
+    Ok(())
+}
+
+fn tool_dir(cfg: &DevflowConfig, tool: &ToolRequirement) -> PathBuf {
+    executor::cache_root(cfg)
+        .join("tools")
+        .join(&tool.name)
+        .join(&tool.version)
+}
+
+fn bin_name(tool: &ToolRequirement) -> String {
+    format!("{}{}", tool.bin(), std::env::consts::EXE_SUFFIX)
+}
+
+fn download(url: &str) -> Result<Vec<u8>> {
+    let mut response = ureq::get(url)
+        .call()
+        .with_context(|| format!("failed to download {url}"))?;
+    let mut bytes = Vec::new();
+    response
+        .body_mut()
+        .as_reader()
+        .take(MAX_DOWNLOAD)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("failed to download {url}"))?;
+    Ok(bytes)
+}
+
+/// Installs `bin` from the downloaded `bytes` of `url` (an archive or the
+/// binary itself) into `dir`, through a staging directory so an interrupted
+/// install leaves nothing behind.
+fn unpack(bytes: &[u8], url: &str, bin: &str, dir: &Path) -> Result<()> {
+    let parent = dir.parent().context("tool directory has no parent")?;
+    let staging = parent.join(format!(
+        ".partial-{}",
+        dir.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)
+        .with_context(|| format!("failed to create '{}'", staging.display()))?;
+    let result = unpack_into(bytes, url, bin, &staging, dir);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn unpack_into(bytes: &[u8], url: &str, bin: &str, staging: &Path, dir: &Path) -> Result<()> {
+    let name = url.rsplit('/').next().unwrap_or(url);
+    let archive = staging.join(name);
+    fs::write(&archive, bytes)?;
+    let extract = if name.ends_with(".zip") {
+        let mut unzip = Command::new("unzip");
+        unzip.arg("-q").arg(&archive).arg("-d").arg(staging);
+        Some(unzip)
+    } else if [".tar.gz", ".tgz", ".tar.xz", ".tar.bz2", ".tar"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+    {
+        let mut tar = Command::new("tar");
+        tar.arg("-xf").arg(&archive).arg("-C").arg(staging);
+        Some(tar)
+    } else {
+        None
+    };
+    let found = match extract {
+        Some(mut extract) => {
+            let status = extract
+                .status()
+                .with_context(|| format!("failed to unpack {name}"))?;
+            if !status.success() {
+                bail!("unpacking {} failed with {}", name, status);
+            }
+            find_file(staging, bin)?.with_context(|| format!("{name} does not contain {bin}"))?
+        }
+        None => archive,
+    };
+    fs::create_dir_all(dir).with_context(|| format!("failed to create '{}'", dir.display()))?;
+    let installed = dir.join(bin);
+    fs::copy(&found, &installed)
+        .with_context(|| format!("failed to install '{}'", installed.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&installed, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Finds the file named `name` anywhere below `dir`.
+fn find_file(dir: &Path, name: &str) -> Result<Option<PathBuf>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name)? {
+                return Ok(Some(found));
+            }
+        } else if path.file_name().is_some_and(|file| file == name) {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpacks_the_binary_from_a_nested_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let release = dir.path().join("release");
+        fs::create_dir_all(release.join("sccache-v0.7.7")).unwrap();
+        fs::write(release.join("sccache-v0.7.7/sccache"), "#!/bin/sh\n").unwrap();
+        fs::write(release.join("sccache-v0.7.7/README.md"), "docs").unwrap();
+        let archive = dir.path().join("sccache.tar.gz");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&release)
+            .arg("sccache-v0.7.7")
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let install = dir.path().join("tools/sccache/0.7.7");
+        unpack(
+            &fs::read(&archive).unwrap(),
+            "https://example.com/sccache.tar.gz",
+            "sccache",
+            &install,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(install.join("sccache")).unwrap(),
+            "#!/bin/sh\n"
+        );
+        assert!(!dir.path().join("tools/sccache/.partial-0.7.7").exists());
+
+        let err = unpack(
+            &fs::read(&archive).unwrap(),
+            "https://example.com/sccache.tar.gz",
+            "nextest",
+            &dir.path().join("tools/nextest/1.0"),
+        )
+        .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("does not contain nextest"),
+            "{err:#}"
+        );
+
+        unpack(b"binary", "https://example.com/tool", "tool", &install).unwrap();
+        assert_eq!(fs::read(install.join("tool")).unwrap(), b"binary");
+    }
+
+    #[test]
+    fn tool_directories_go_first_on_path() {
+        let mut action = ExecutionAction {
+            program: "cargo".to_string(),
+            args: Vec::new(),
+            env: [("PATH".to_string(), "/usr/bin".to_string())].into(),
+        };
+        add_to_path(&mut action, &[]).unwrap();
+        assert_eq!(action.env["PATH"], "/usr/bin");
+        add_to_path(&mut action, &[PathBuf::from("/tools/sccache/0.7.7")]).unwrap();
+        assert_eq!(action.env["PATH"], "/tools/sccache/0.7.7:/usr/bin");
+    }
+}
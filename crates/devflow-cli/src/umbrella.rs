@@ -0,0 +1,199 @@
+//! One command across several projects (`--config a --config b`, `dwf all`).
+//!
+//! Umbrella repositories without a root config run each project's `dwf` in
+//! the project's directory, one after the other, and end with a combined
+//! summary. Every project runs even after one failed.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use devflow_core::constants::CONFIG_FILE;
+use devflow_core::walk;
+
+/// The `devflow.toml` files under `root` that are not ignored, sorted.
+///
+/// # Errors
+/// Returns an error if the tree cannot be walked or holds no config.
+pub fn discover(root: &Path) -> Result<Vec<PathBuf>> {
+    let configs: Vec<PathBuf> = walk::files(root)?
+        .into_iter()
+        .filter(|path| path.file_name().is_some_and(|name| name == CONFIG_FILE))
+        .map(|path| {
+            path.strip_prefix(root)
+                .map(Path::to_path_buf)
+                .unwrap_or(path)
+        })
+        .collect();
+    if configs.is_empty() {
+        bail!("no {CONFIG_FILE} found under '{}'", root.display());
+    }
+    Ok(configs)
+}
+
+/// How one project's run ended.
+struct Outcome {
+    config: PathBuf,
+    success: bool,
+    duration: Duration,
+}
+
+/// Runs `command` (for the summary) once per config by re-invoking `dwf`
+/// with `args` (the invocation's arguments without `--config`) in the
+/// config's directory.
+///
+/// # Errors
+/// Returns an error naming the projects whose run failed.
+pub fn run(configs: &[PathBuf], command: &str, args: &[OsString]) -> Result<()> {
+    let exe = std::env::current_exe().context("failed to locate the dwf binary")?;
+    let mut outcomes = Vec::new();
+    for config in configs {
+        let dir = match config.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let file = config.file_name().unwrap_or(config.as_os_str());
+        println!("==> {} ({command})", config.display());
+        let started = Instant::now();
+        let status = Command::new(&exe)
+            .args(args)
+            .arg("--config")
+            .arg(file)
+            .current_dir(dir)
+            .status()
+            .with_context(|| format!("failed to run dwf for '{}'", config.display()))?;
+        outcomes.push(Outcome {
+            config: config.clone(),
+            success: status.success(),
+            duration: started.elapsed(),
+        });
+    }
+    print!("{}", summary(command, &outcomes));
+    let failed: Vec<String> = outcomes
+        .iter()
+        .filter(|outcome| !outcome.success)
+        .map(|outcome| outcome.config.display().to_string())
+        .collect();
+    if !failed.is_empty() {
+        bail!(
+            "{command} failed for {} of {} projects: {}",
+            failed.len(),
+            outcomes.len(),
+            failed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn summary(command: &str, outcomes: &[Outcome]) -> String {
+    let width = outcomes
+        .iter()
+        .map(|outcome| outcome.config.display().to_string().len())
+        .max()
+        .unwrap_or(0);
+    let mut out = format!("\n{command}: {} projects\n", outcomes.len());
+    for outcome in outcomes {
+        out.push_str(&format!(
+            "  {} {:width$}  {:.1}s\n",
+            if outcome.success { "ok    " } else { "FAILED" },
+            outcome.config.display().to_string(),
+            outcome.duration.as_secs_f64(),
+        ));
+    }
+    out
+}
+
+/// `raw` (the arguments after the program name) without its `--config`
+/// options and, for `dwf all`, without the `all` in front of the command.
+pub fn child_args(raw: &[OsString], all: bool) -> Vec<OsString> {
+    let mut args = Vec::new();
+    let mut iter = raw.iter();
+    let mut dropped_all = !all;
+    while let Some(arg) = iter.next() {
+        let text = arg.to_string_lossy();
+        if text == "--" {
+            args.push(arg.clone());
+            args.extend(iter.cloned());
+            break;
+        }
+        if text == "--config" {
+            iter.next();
+        } else if text.starts_with("--config=") {
+        } else if !dropped_all && text == "all" {
+            dropped_all = true;
+        } else {
+            args.push(arg.clone());
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn discovers_configs_and_rewrites_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in [
+            "services/api/devflow.toml",
+            "web/devflow.toml",
+            "vendor/lib/devflow.toml",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(dir.path().join(".gitignore"), "vendor/\n").unwrap();
+        assert_eq!(
+            discover(dir.path()).unwrap(),
+            [
+                PathBuf::from("services/api/devflow.toml"),
+                PathBuf::from("web/devflow.toml")
+            ]
+        );
+        assert!(discover(&dir.path().join("services/api/src")).is_err());
+
+        assert_eq!(
+            child_args(
+                &os(&[
+                    "--config",
+                    "a/devflow.toml",
+                    "check:pr",
+                    "--config=b/devflow.toml",
+                    "-v"
+                ]),
+                false
+            ),
+            os(&["check:pr", "-v"])
+        );
+        assert_eq!(
+            child_args(&os(&["all", "run", "--stack", "rust", "--", "all"]), true),
+            os(&["run", "--stack", "rust", "--", "all"])
+        );
+
+        let outcomes = [
+            Outcome {
+                config: PathBuf::from("services/api/devflow.toml"),
+                success: true,
+                duration: Duration::from_millis(1_250),
+            },
+            Outcome {
+                config: PathBuf::from("web/devflow.toml"),
+                success: false,
+                duration: Duration::from_secs(3),
+            },
+        ];
+        assert_eq!(
+            summary("check:pr", &outcomes),
+            "\ncheck:pr: 2 projects\n  ok     services/api/devflow.toml  1.2s\n  FAILED web/devflow.toml           3.0s\n"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Named engine volumes for extension directories such as `node_modules`
+//! (`[extensions.<name>] mount_type = "volume"`).
+//!
+//! A bind-mounted `node_modules` is slow on Docker Desktop and Podman
+//! machines. A volume is named after the project, the extension, the
+//! directory and a key hashed from the extension's key inputs and the image,
+//! so a lockfile or image change mounts a fresh, empty volume. Volumes of
+//! older keys are removed when a new key is first used.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use devflow_core::config::MountType;
+use devflow_core::fingerprint::compute_fingerprint;
+use devflow_core::{DevflowConfig, ExtensionRegistry};
+
+/// Characters of the key kept in volume names.
+const KEY_LEN: usize = 12;
+
+/// Returns the `(volume, workspace-relative path)` pairs to mount for
+/// `stacks`, removing volumes of stale keys.
+///
+/// # Errors
+/// Returns an error if a key input exists but cannot be read.
+pub fn prepare(
+    cfg: &DevflowConfig,
+    registry: &ExtensionRegistry,
+    stacks: &[String],
+    engine: &str,
+    image: &str,
+    workspace: &Path,
+) -> Result<Vec<(String, String)>> {
+    let mut volumes = Vec::new();
+    for stack in stacks {
+        if cfg.mount_type(stack) != MountType::Volume {
+            continue;
+        }
+        let Some(ext) = registry.get(stack) else {
+            continue;
+        };
+        for mount in ext.volume_mounts() {
+            let fingerprint = compute_fingerprint(workspace, &mount.key_inputs)?;
+            let prefix = volume_prefix(&cfg.project.name, stack, &mount.name);
+            let volume = format!("{prefix}{}", key(&fingerprint, image));
+            if !exists(engine, &volume) {
+                for old in stale(&prefix, &volume, &list(engine, &prefix)) {
+                    info!(target: "devflow", "removing stale volume {}", old);
+                    remove(engine, old);
+                }
+            }
+            volumes.push((volume, mount.path.trim_end_matches('/').to_string()));
+        }
+    }
+    Ok(volumes)
+}
+
+/// `text` with every character an engine rejects in names replaced by `-`.
+pub fn engine_name(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// The name shared by every key's volume of one mount.
+fn volume_prefix(project: &str, stack: &str, mount: &str) -> String {
+    format!(
+        "dwf-{}-{}-{}-",
+        engine_name(project),
+        engine_name(stack),
+        engine_name(mount)
+    )
+}
+
+/// The volume key for the key inputs' `fingerprint` in `image`.
+fn key(fingerprint: &str, image: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(fingerprint.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(image.as_bytes());
+    hex::encode(hasher.finalize())[..KEY_LEN].to_string()
+}
+
+/// The volumes in `listing` of an older key than `current`.
+fn stale<'a>(prefix: &str, current: &str, listing: &'a str) -> Vec<&'a str> {
+    listing
+        .lines()
+        .map(str::trim)
+        .filter(|name| {
+            name.strip_prefix(prefix)
+                .is_some_and(|key| key.len() == KEY_LEN && *name != current)
+        })
+        .collect()
+}
+
+fn exists(engine: &str, volume: &str) -> bool {
+    Command::new(engine)
+        .args(["volume", "inspect", volume])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn list(engine: &str, prefix: &str) -> String {
+    Command::new(engine)
+        .args(["volume", "ls", "-q", "--filter", &format!("name={prefix}")])
+        .stderr(Stdio::null())
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+        .unwrap_or_default()
+}
+
+fn remove(engine: &str, volume: &str) {
+    let removed = Command::new(engine)
+        .args(["volume", "rm", volume])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !removed {
+        debug!("could not remove volume {} (still in use?)", volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_volumes_by_fingerprint_and_image() {
+        let prefix = volume_prefix("my app", "node", "node_modules");
+        assert_eq!(prefix, "dwf-my-app-node-node_modules-");
+        let current = format!("{prefix}{}", key("abc", "node:20"));
+        assert_eq!(current.len(), prefix.len() + KEY_LEN);
+        assert_ne!(key("abc", "node:20"), key("abc", "node:22"));
+        assert_ne!(key("abc", "node:20"), key("abd", "node:20"));
+
+        let old = format!("{prefix}{}", key("old", "node:20"));
+        let listing = format!("{current}\n{old}\n{prefix}cache-extra\n");
+        assert_eq!(stale(&prefix, &current, &listing), [old.as_str()]);
+    }
+}
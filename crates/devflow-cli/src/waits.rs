@@ -0,0 +1,179 @@
+//! Service readiness waits (`[waits.<name>]`).
+//!
+//! Before `test:integration` runs, every configured probe is retried until it
+//! succeeds or its timeout passes, so tests do not race a database or API
+//! that is still starting. Probes run on the host running dwf.
+
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use tracing::{debug, info};
+
+use devflow_core::config::{Probe, WaitConfig};
+use devflow_core::DevflowConfig;
+
+/// Waits for every `[waits]` entry in name order.
+///
+/// # Errors
+/// Returns an error naming the first service that is not ready in time.
+pub fn wait_all(cfg: &DevflowConfig) -> Result<()> {
+    for (name, wait) in &cfg.waits {
+        wait_for(name, wait)?;
+    }
+    Ok(())
+}
+
+fn wait_for(name: &str, wait: &WaitConfig) -> Result<()> {
+    poll(name, wait.probe()?, wait.timeout()?, wait.interval()?)
+}
+
+/// Retries `probe` every `interval` until it succeeds or `timeout` passes.
+///
+/// # Errors
+/// Returns an error with the last probe error if `name` is not ready in time.
+pub fn poll(name: &str, probe: Probe, timeout: Duration, interval: Duration) -> Result<()> {
+    info!(target: "devflow", "waiting for {} ({})", name, describe(probe));
+    let started = Instant::now();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        // An attempt may use what is left of the timeout, but no less than
+        // a moment so the last one can still succeed.
+        let budget = timeout
+            .saturating_sub(started.elapsed())
+            .max(Duration::from_millis(100));
+        let error = match check(probe, budget) {
+            Ok(()) => {
+                info!(target: "devflow",
+                    "{} ready after {:.1}s",
+                    name,
+                    started.elapsed().as_secs_f64()
+                );
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+        if started.elapsed() + interval >= timeout {
+            bail!(
+                "{} not ready after {} ({} attempts): {:#}",
+                name,
+                format_duration(timeout),
+                attempts,
+                error
+            );
+        }
+        debug!("{} not ready yet: {:#}", name, error);
+        std::thread::sleep(interval);
+    }
+}
+
+/// Formats `duration` the way it is configured, e.g. `30s` or `500ms`.
+pub fn format_duration(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+fn describe(probe: Probe) -> String {
+    match probe {
+        Probe::Tcp(addr) => format!("tcp {addr}"),
+        Probe::Http(url) => format!("http {url}"),
+        Probe::Command(command) => format!("command `{command}`"),
+    }
+}
+
+/// Runs one attempt of `probe` within `budget`.
+fn check(probe: Probe, budget: Duration) -> Result<()> {
+    match probe {
+        Probe::Tcp(addr) => {
+            let mut last = anyhow!("'{}' did not resolve", addr);
+            for socket in addr
+                .to_socket_addrs()
+                .with_context(|| format!("failed to resolve '{addr}'"))?
+            {
+                match TcpStream::connect_timeout(&socket, budget) {
+                    Ok(_) => return Ok(()),
+                    Err(e) => last = anyhow!("connecting to {} failed: {}", socket, e),
+                }
+            }
+            Err(last)
+        }
+        Probe::Http(url) => {
+            let agent = ureq::Agent::new_with_config(
+                ureq::Agent::config_builder()
+                    .timeout_global(Some(budget))
+                    .build(),
+            );
+            agent.get(url).call().map(drop).map_err(Into::into)
+        }
+        Probe::Command(command) => {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .context("failed to start probe command")?;
+            let deadline = Instant::now() + budget;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    return if status.success() {
+                        Ok(())
+                    } else {
+                        Err(anyhow!("probe command exited with {}", status))
+                    };
+                }
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    bail!("probe command timed out");
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn tcp_wait_succeeds_once_the_port_listens() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let wait = WaitConfig {
+            tcp: Some(listener.local_addr().unwrap().to_string()),
+            timeout: Some("2s".to_string()),
+            ..WaitConfig::default()
+        };
+        wait_for("db", &wait).unwrap();
+    }
+
+    #[test]
+    fn failing_probe_times_out_with_the_last_error() {
+        let wait = WaitConfig {
+            command: Some("exit 3".to_string()),
+            timeout: Some("300ms".to_string()),
+            interval: Some("100ms".to_string()),
+            ..WaitConfig::default()
+        };
+        let err = wait_for("api", &wait).unwrap_err().to_string();
+        assert!(err.starts_with("api not ready after 300ms ("), "{err}");
+        assert!(
+            err.contains("probe command exited with exit status: 3"),
+            "{err}"
+        );
+
+        let ok = WaitConfig {
+            command: Some("true".to_string()),
+            ..WaitConfig::default()
+        };
+        wait_for("api", &ok).unwrap();
+    }
+}
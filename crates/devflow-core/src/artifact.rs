@@ -0,0 +1,299 @@
+//! Artifact manifests.
+//!
+//! An [`ArtifactManifest`] records the SHA256 digest and size of every build
+//! output matching a set of glob patterns. Manifests can be compared to detect
+//! nondeterministic outputs between builds.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::glob;
+
+/// Identity of a single artifact file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactEntry {
+    /// Hex-encoded SHA256 digest of the file content.
+    pub sha256: String,
+    /// File size in bytes.
+    pub size: u64,
+}
+
+/// The set of artifacts produced by a build, keyed by `/`-separated path relative to the root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactManifest {
+    /// Directory the artifact paths are relative to.
+    pub root: PathBuf,
+    /// Artifact entries keyed by relative path.
+    pub entries: BTreeMap<String, ArtifactEntry>,
+}
+
+/// How an artifact differs between two manifests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DifferenceKind {
+    /// Present in both manifests with different content.
+    Changed,
+    /// Present only in the first manifest.
+    Missing,
+    /// Present only in the second manifest.
+    Added,
+}
+
+/// An artifact that differs between two manifests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactDifference {
+    /// Relative path of the artifact.
+    pub path: String,
+    /// The kind of difference.
+    pub kind: DifferenceKind,
+}
+
+impl ArtifactManifest {
+    /// Collects every file under `root` matching `patterns`.
+    ///
+    /// Patterns prefixed with `!` exclude matching files (e.g. `!release/*.d`).
+    /// A missing `root` yields an empty manifest. `.git`, symlinked
+    /// directories and directories no include pattern can match below are
+    /// not entered.
+    ///
+    /// # Errors
+    /// Returns an error if a directory or matched file cannot be read.
+    pub fn collect(root: &Path, patterns: &[String]) -> Result<Self> {
+        let (exclude, include): (Vec<String>, Vec<String>) = patterns
+            .iter()
+            .cloned()
+            .partition(|pattern| pattern.starts_with('!'));
+        let exclude: Vec<String> = exclude
+            .into_iter()
+            .map(|pattern| pattern[1..].to_string())
+            .collect();
+
+        let mut manifest = Self {
+            root: root.to_path_buf(),
+            entries: BTreeMap::new(),
+        };
+        if root.is_dir() && !include.is_empty() {
+            manifest.walk(root, &include, &exclude)?;
+        }
+        Ok(manifest)
+    }
+
+    fn walk(&mut self, dir: &Path, include: &[String], exclude: &[String]) -> Result<()> {
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("failed to read directory {}", dir.display()))?;
+
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let rel = relative_path(&self.root, &path);
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                let prune = entry.file_name() == ".git"
+                    || !include
+                        .iter()
+                        .any(|pattern| glob::may_match_below(pattern, &rel));
+                if !prune {
+                    self.walk(&path, include, exclude)?;
+                }
+                continue;
+            }
+            // A symlinked directory may point back up the tree.
+            if file_type.is_symlink() && path.is_dir() {
+                continue;
+            }
+
+            if glob::matches_any(include, &rel) && !glob::matches_any(exclude, &rel) {
+                let content = std::fs::read(&path)
+                    .with_context(|| format!("failed to read artifact: {}", path.display()))?;
+                self.entries.insert(
+                    rel,
+                    ArtifactEntry {
+                        sha256: hex::encode(Sha256::digest(&content)),
+                        size: content.len() as u64,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the absolute path of an artifact in this manifest.
+    pub fn path_of(&self, rel: &str) -> PathBuf {
+        self.root.join(rel)
+    }
+
+    /// Copies every artifact into `dest`, returning the relocated manifest.
+    ///
+    /// # Errors
+    /// Returns an error if a directory cannot be created or an artifact copied.
+    pub fn copy_to(&self, dest: &Path) -> Result<Self> {
+        for rel in self.entries.keys() {
+            let target = dest.join(rel);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(self.path_of(rel), &target)
+                .with_context(|| format!("failed to copy artifact {}", rel))?;
+        }
+        Ok(Self {
+            root: dest.to_path_buf(),
+            entries: self.entries.clone(),
+        })
+    }
+
+    /// Lists the artifacts that differ between `self` and `other`, sorted by path.
+    pub fn compare(&self, other: &ArtifactManifest) -> Vec<ArtifactDifference> {
+        let mut diffs = Vec::new();
+
+        for (path, entry) in &self.entries {
+            match other.entries.get(path) {
+                Some(theirs) if theirs == entry => {}
+                Some(_) => diffs.push(ArtifactDifference {
+                    path: path.clone(),
+                    kind: DifferenceKind::Changed,
+                }),
+                None => diffs.push(ArtifactDifference {
+                    path: path.clone(),
+                    kind: DifferenceKind::Missing,
+                }),
+            }
+        }
+
+        for path in other.entries.keys() {
+            if !self.entries.contains_key(path) {
+                diffs.push(ArtifactDifference {
+                    path: path.clone(),
+                    kind: DifferenceKind::Added,
+                });
+            }
+        }
+
+        diffs.sort_by(|a, b| a.path.cmp(&b.path));
+        diffs
+    }
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn collects_matching_files_with_exclusions() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("release/deps")).unwrap();
+        fs::write(dir.path().join("release/app"), b"binary").unwrap();
+        fs::write(dir.path().join("release/app.d"), b"dep-info").unwrap();
+        fs::write(dir.path().join("release/deps/libx.rlib"), b"dep").unwrap();
+
+        let manifest =
+            ArtifactManifest::collect(dir.path(), &patterns(&["release/*", "!release/*.d"]))
+                .unwrap();
+
+        assert_eq!(
+            manifest.entries.keys().collect::<Vec<_>>(),
+            vec!["release/app"]
+        );
+        let entry = &manifest.entries["release/app"];
+        assert_eq!(entry.size, 6);
+        assert_eq!(entry.sha256.len(), 64);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinked_directories_and_unmatched_subtrees() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("dist")).unwrap();
+        fs::write(dir.path().join("dist/app.js"), "app").unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("dist/loop")).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/x")).unwrap();
+        fs::write(dir.path().join("node_modules/x/index.js"), "x").unwrap();
+
+        let manifest = ArtifactManifest::collect(dir.path(), &patterns(&["**/*.js"])).unwrap();
+        assert_eq!(
+            manifest.entries.keys().collect::<Vec<_>>(),
+            vec!["dist/app.js", "node_modules/x/index.js"]
+        );
+
+        assert!(!glob::may_match_below("dist/*", "node_modules"));
+        let manifest = ArtifactManifest::collect(dir.path(), &patterns(&["dist/*"])).unwrap();
+        assert_eq!(
+            manifest.entries.keys().collect::<Vec<_>>(),
+            vec!["dist/app.js"]
+        );
+    }
+
+    #[test]
+    fn missing_root_yields_empty_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest =
+            ArtifactManifest::collect(&dir.path().join("absent"), &patterns(&["**"])).unwrap();
+        assert!(manifest.entries.is_empty());
+    }
+
+    #[test]
+    fn copy_to_relocates_artifacts() {
+        let workspace = tempfile::tempdir().unwrap();
+        fs::create_dir_all(workspace.path().join("dist")).unwrap();
+        fs::write(workspace.path().join("dist/app.js"), "v1").unwrap();
+
+        let manifest =
+            ArtifactManifest::collect(workspace.path(), &patterns(&["dist/**"])).unwrap();
+        let dest = workspace.path().join(".devflow/repro/run-1/node");
+        let kept = manifest.copy_to(&dest).unwrap();
+
+        fs::write(workspace.path().join("dist/app.js"), "v2").unwrap();
+        assert_eq!(
+            fs::read_to_string(kept.path_of("dist/app.js")).unwrap(),
+            "v1"
+        );
+        assert_eq!(kept.entries, manifest.entries);
+    }
+
+    #[test]
+    fn compare_reports_changed_missing_and_added() {
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        fs::write(a.path().join("same"), b"1").unwrap();
+        fs::write(b.path().join("same"), b"1").unwrap();
+        fs::write(a.path().join("changed"), b"a").unwrap();
+        fs::write(b.path().join("changed"), b"b").unwrap();
+        fs::write(a.path().join("only-a"), b"x").unwrap();
+        fs::write(b.path().join("only-b"), b"y").unwrap();
+
+        let all = patterns(&["*"]);
+        let first = ArtifactManifest::collect(a.path(), &all).unwrap();
+        let second = ArtifactManifest::collect(b.path(), &all).unwrap();
+
+        let diffs = first.compare(&second);
+        let summary: Vec<(&str, DifferenceKind)> =
+            diffs.iter().map(|d| (d.path.as_str(), d.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("changed", DifferenceKind::Changed),
+                ("only-a", DifferenceKind::Missing),
+                ("only-b", DifferenceKind::Added),
+            ]
+        );
+        assert!(first.compare(&first).is_empty());
+    }
+}
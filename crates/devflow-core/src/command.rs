@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
@@ -5,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// The primary categories of commands supported by Devflow.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Variants are declared in lifecycle order, which `Ord` follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PrimaryCommand {
     /// Initialize a new Devflow project.
@@ -30,6 +33,40 @@ pub enum PrimaryCommand {
     Ci,
     /// Prune redundant caches or runs.
     Prune,
+    /// Report the environment fingerprint.
+    Fingerprint,
+    /// Show the effective extension environment.
+    Env,
+    /// Manage the container image (e.g., pre-pulling it).
+    Container,
+    /// Maintain `devflow.toml` itself (e.g., canonical formatting).
+    Config,
+    /// Query the run history (durations, cache hits, test counts).
+    Stats,
+    /// Run an arbitrary command in a stack's execution environment.
+    Run,
+    /// Serve devflow over HTTP (capabilities, runs and their logs, history).
+    Serve,
+    /// Work on extensions themselves (e.g., a protocol conformance check).
+    Ext,
+    /// Manage the devflow cache (e.g., seeding it ahead of time).
+    Cache,
+    /// Render a status badge from the run history.
+    Badge,
+    /// Summarize why the latest run failed, from the run history.
+    ExplainFailure,
+    /// Show the recorded actions of a command and what changed between runs.
+    Explain,
+    /// Generate or check `.devcontainer/devcontainer.json`.
+    Devcontainer,
+    /// List the capabilities of the project's stacks, grouped.
+    List,
+    /// Describe what a command does for each stack.
+    Help,
+    /// Shell completion of the project's commands.
+    Completions,
+    /// Show or follow the command logs of a run.
+    Logs,
 }
 
 impl PrimaryCommand {
@@ -47,6 +84,23 @@ impl PrimaryCommand {
             Self::Release => "release",
             Self::Ci => "ci",
             Self::Prune => "prune",
+            Self::Fingerprint => "fingerprint",
+            Self::Env => "env",
+            Self::Container => "container",
+            Self::Cache => "cache",
+            Self::Config => "config",
+            Self::Stats => "stats",
+            Self::Run => "run",
+            Self::Serve => "serve",
+            Self::Ext => "ext",
+            Self::Badge => "badge",
+            Self::ExplainFailure => "explain-failure",
+            Self::Explain => "explain",
+            Self::Devcontainer => "devcontainer",
+            Self::List => "list",
+            Self::Help => "help",
+            Self::Completions => "completions",
+            Self::Logs => "logs",
         }
     }
 
@@ -64,6 +118,56 @@ impl PrimaryCommand {
             Self::Ci => "check",
             Self::Init => "rust",
             Self::Prune => "cache",
+            Self::Fingerprint => "show",
+            Self::Env => "show",
+            Self::Container => "pull",
+            Self::Cache => "warm",
+            Self::Config => "format",
+            Self::Stats => "show",
+            Self::Run => "command",
+            Self::Serve => "api",
+            Self::Ext => "verify",
+            Self::Badge => "svg",
+            Self::ExplainFailure => "last",
+            Self::Explain => "last",
+            Self::Devcontainer => "check",
+            Self::List => "all",
+            Self::Help => "all",
+            Self::Completions => "bash",
+            Self::Logs => "last",
+        }
+    }
+}
+
+/// The machine resource a command mostly uses. Parallel check runs cap how
+/// many commands of each class run at once (`[concurrency]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceClass {
+    /// Waits on disk or network, e.g. formatting checks and most linters.
+    Io,
+    /// Keeps the cores busy, e.g. compiling and running tests.
+    Cpu,
+}
+
+impl ResourceClass {
+    /// Returns the `[concurrency]` key of the class.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Io => "io",
+            Self::Cpu => "cpu",
+        }
+    }
+
+    /// The class of `cmd` unless an extension says otherwise: building,
+    /// testing and packaging are CPU-bound, everything else IO-bound.
+    pub fn default_for(cmd: &CommandRef) -> Self {
+        match cmd.primary {
+            PrimaryCommand::Build
+            | PrimaryCommand::Test
+            | PrimaryCommand::Package
+            | PrimaryCommand::Release => Self::Cpu,
+            _ => Self::Io,
         }
     }
 }
@@ -121,6 +225,23 @@ impl FromStr for CommandRef {
             "release" => PrimaryCommand::Release,
             "ci" => PrimaryCommand::Ci,
             "prune" => PrimaryCommand::Prune,
+            "fingerprint" => PrimaryCommand::Fingerprint,
+            "env" => PrimaryCommand::Env,
+            "container" => PrimaryCommand::Container,
+            "cache" => PrimaryCommand::Cache,
+            "config" => PrimaryCommand::Config,
+            "stats" => PrimaryCommand::Stats,
+            "run" => PrimaryCommand::Run,
+            "serve" => PrimaryCommand::Serve,
+            "ext" => PrimaryCommand::Ext,
+            "badge" => PrimaryCommand::Badge,
+            "explain-failure" => PrimaryCommand::ExplainFailure,
+            "explain" => PrimaryCommand::Explain,
+            "devcontainer" => PrimaryCommand::Devcontainer,
+            "list" => PrimaryCommand::List,
+            "help" => PrimaryCommand::Help,
+            "completions" => PrimaryCommand::Completions,
+            "logs" => PrimaryCommand::Logs,
             _ => return Err(CommandParseError::UnknownPrimary(primary_text.to_string())),
         };
 
@@ -128,6 +249,115 @@ impl FromStr for CommandRef {
     }
 }
 
+/// Built-in aliases as `(alias, command, deprecated)`: shorthands, and
+/// selectors that were renamed.
+const BUILTIN_ALIASES: &[(&str, &str, bool)] = &[
+    ("lint", "lint:static", false),
+    ("test:it", "test:integration", true),
+];
+
+/// What an alias stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alias {
+    /// The command it is replaced with.
+    pub command: String,
+    /// Whether using it warns.
+    pub deprecated: bool,
+}
+
+/// An alias that was replaced while parsing a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasUse {
+    /// The command text as given.
+    pub alias: String,
+    /// The command it was replaced with.
+    pub command: String,
+    /// Whether using the alias warns.
+    pub deprecated: bool,
+}
+
+impl Display for AliasUse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' is deprecated, use '{}' instead",
+            self.alias, self.command
+        )
+    }
+}
+
+/// The alias table applied while parsing commands: the built-in aliases,
+/// extended or overridden by a config's `[aliases]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aliases {
+    entries: BTreeMap<String, Alias>,
+}
+
+impl Aliases {
+    /// The built-in aliases.
+    pub fn builtin() -> Self {
+        Self {
+            entries: BUILTIN_ALIASES
+                .iter()
+                .map(|(alias, command, deprecated)| {
+                    (
+                        alias.to_string(),
+                        Alias {
+                            command: command.to_string(),
+                            deprecated: *deprecated,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds configured aliases, which are all deprecated, over the current
+    /// ones.
+    pub fn with_config(mut self, aliases: &BTreeMap<String, String>) -> Self {
+        for (alias, command) in aliases {
+            self.entries.insert(
+                alias.clone(),
+                Alias {
+                    command: command.clone(),
+                    deprecated: true,
+                },
+            );
+        }
+        self
+    }
+
+    /// What `alias` stands for, if it is one.
+    pub fn get(&self, alias: &str) -> Option<&Alias> {
+        self.entries.get(alias)
+    }
+
+    /// Parses `value`, replacing it first if it is an alias. Aliases are
+    /// resolved once; the command an alias stands for is not looked up again.
+    ///
+    /// # Errors
+    /// Returns an error if the (replaced) command does not parse.
+    pub fn parse(&self, value: &str) -> Result<(CommandRef, Option<AliasUse>), CommandParseError> {
+        match self.entries.get(value) {
+            Some(alias) => Ok((
+                CommandRef::from_str(&alias.command)?,
+                Some(AliasUse {
+                    alias: value.to_string(),
+                    command: alias.command.clone(),
+                    deprecated: alias.deprecated,
+                }),
+            )),
+            None => Ok((CommandRef::from_str(value)?, None)),
+        }
+    }
+}
+
+impl Default for Aliases {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,4 +414,125 @@ mod tests {
     fn prune_as_str_returns_prune() {
         assert_eq!(PrimaryCommand::Prune.as_str(), "prune");
     }
+
+    #[test]
+    fn parses_fingerprint_command() {
+        let cmd = CommandRef::from_str("fingerprint").expect("fingerprint should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Fingerprint);
+        assert_eq!(cmd.selector, None);
+        assert_eq!(PrimaryCommand::Fingerprint.as_str(), "fingerprint");
+    }
+
+    #[test]
+    fn parses_env_command() {
+        let cmd = CommandRef::from_str("env").expect("env should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Env);
+        assert_eq!(PrimaryCommand::Env.as_str(), "env");
+    }
+
+    #[test]
+    fn parses_container_pull_command() {
+        let cmd = CommandRef::from_str("container:pull").expect("container:pull should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Container);
+        assert_eq!(cmd.selector.as_deref(), Some("pull"));
+        assert_eq!(PrimaryCommand::Container.default_selector(), "pull");
+    }
+
+    #[test]
+    fn parses_config_format_command() {
+        let cmd = CommandRef::from_str("config:format").expect("config:format should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Config);
+        assert_eq!(PrimaryCommand::Config.default_selector(), "format");
+        assert!(PrimaryCommand::Fmt < PrimaryCommand::Lint);
+        assert!(PrimaryCommand::Build < PrimaryCommand::Test);
+    }
+
+    #[test]
+    fn parses_stats_command() {
+        let cmd = CommandRef::from_str("stats").expect("stats should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Stats);
+        assert_eq!(PrimaryCommand::Stats.as_str(), "stats");
+        assert_eq!(PrimaryCommand::Stats.default_selector(), "show");
+    }
+
+    #[test]
+    fn parses_badge_command() {
+        let cmd = CommandRef::from_str("badge").expect("badge should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Badge);
+        assert_eq!(PrimaryCommand::Badge.as_str(), "badge");
+        assert_eq!(PrimaryCommand::Badge.default_selector(), "svg");
+    }
+
+    #[test]
+    fn parses_explain_failure_command() {
+        let cmd = CommandRef::from_str("explain-failure").expect("explain-failure should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::ExplainFailure);
+        assert_eq!(PrimaryCommand::ExplainFailure.as_str(), "explain-failure");
+
+        let cmd = CommandRef::from_str("explain:test:unit").expect("explain should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Explain);
+        assert_eq!(cmd.selector.as_deref(), Some("test:unit"));
+
+        let cmd = CommandRef::from_str("devcontainer:generate").expect("devcontainer should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Devcontainer);
+
+        let cmd = CommandRef::from_str("help:test:unit").expect("help should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Help);
+        assert_eq!(cmd.selector.as_deref(), Some("test:unit"));
+        assert_eq!(PrimaryCommand::List.default_selector(), "all");
+
+        let cmd = CommandRef::from_str("completions:zsh").expect("completions should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Completions);
+
+        let cmd = CommandRef::from_str("logs:20261014-130819").expect("logs should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Logs);
+    }
+
+    #[test]
+    fn aliases_replace_shorthands_and_renamed_selectors() {
+        let aliases = Aliases::builtin().with_config(&BTreeMap::from([(
+            "check:sec".to_string(),
+            "check:security".to_string(),
+        )]));
+        let (cmd, alias) = aliases.parse("lint").unwrap();
+        assert_eq!(cmd.canonical(), "lint:static");
+        assert!(!alias.unwrap().deprecated);
+
+        let (cmd, alias) = aliases.parse("test:it").unwrap();
+        assert_eq!(cmd.canonical(), "test:integration");
+        assert_eq!(
+            alias.unwrap().to_string(),
+            "'test:it' is deprecated, use 'test:integration' instead"
+        );
+
+        let (cmd, alias) = aliases.parse("check:sec").unwrap();
+        assert_eq!(cmd.canonical(), "check:security");
+        assert!(alias.unwrap().deprecated);
+
+        assert_eq!(aliases.parse("test:unit").unwrap().1, None);
+        assert!(aliases.parse("tset").is_err());
+    }
+
+    #[test]
+    fn parses_run_command() {
+        let cmd = CommandRef::from_str("run").expect("run should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Run);
+        assert_eq!(PrimaryCommand::Run.as_str(), "run");
+    }
+
+    #[test]
+    fn parses_ext_command() {
+        let cmd = CommandRef::from_str("ext:verify:python").expect("ext:verify should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Ext);
+        assert_eq!(cmd.selector.as_deref(), Some("verify:python"));
+        assert_eq!(PrimaryCommand::Ext.default_selector(), "verify");
+    }
+
+    #[test]
+    fn parses_cache_command() {
+        let cmd = CommandRef::from_str("cache").expect("cache should parse");
+        assert_eq!(cmd.primary, PrimaryCommand::Cache);
+        assert_eq!(PrimaryCommand::Cache.as_str(), "cache");
+        assert_eq!(PrimaryCommand::Cache.default_selector(), "warm");
+    }
 }
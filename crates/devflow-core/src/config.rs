@@ -1,19 +1,30 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
+use indexmap::IndexMap;
 use serde::Deserialize;
 
-use crate::command::CommandRef;
-use crate::runtime::RuntimeProfile;
+use crate::command::{Aliases, CommandRef, ResourceClass};
+use crate::constants::{CONFIG_SCHEMA_VERSION, STACK_AUTO};
+use crate::diagnostic::{self, ConfigDiagnostic};
+use crate::fingerprint::FingerprintStrategy;
+use crate::preset::{self, Preset};
+use crate::runtime::{RuntimeEnv, RuntimeProfile};
 
 /// The root configuration structure for a Devflow project.
 ///
 /// This structure is typically deserialized from a `devflow.toml` file.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
 pub struct DevflowConfig {
+    /// Schema version the config was written for (see `CONFIG_SCHEMA_VERSION`).
+    pub schema_version: Option<u32>,
+    /// Preset this config is merged over (see [`crate::preset`]), e.g.
+    /// `github:myorg/devflow-presets#rust-service`.
+    pub extends: Option<String>,
     /// Basic project metadata.
     pub project: ProjectConfig,
     /// Runtime settings (e.g., local, CI).
@@ -22,6 +33,22 @@ pub struct DevflowConfig {
     /// Custom target profiles (e.g., `pr`, `main`, `release`).
     #[serde(default)]
     pub targets: TargetsConfig,
+    /// Per-command execution settings keyed by canonical command (`test:unit`)
+    /// or primary command (`test`).
+    #[serde(default)]
+    pub commands: BTreeMap<String, CommandConfig>,
+    /// Deprecated command names and the commands replacing them, on top of
+    /// the built-in aliases.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Readiness probes run before `test:integration`, keyed by service name.
+    #[serde(default)]
+    pub waits: BTreeMap<String, WaitConfig>,
+    /// Ephemeral databases created for `test:integration`, keyed by name.
+    #[serde(default)]
+    pub fixtures: BTreeMap<String, FixtureConfig>,
+    /// Dotenv files loaded into the execution environment.
+    pub env_files: Option<EnvFilesConfig>,
     /// Optional extension configurations.
     pub extensions: Option<HashMap<String, ExtensionConfig>>,
     /// Container configuration for execution proxies.
@@ -30,9 +57,33 @@ pub struct DevflowConfig {
     /// Cache configuration for build artifact management.
     #[serde(default)]
     pub cache: Option<CacheConfig>,
+    /// Runs check profiles in parallel, with at most this many commands of
+    /// each resource class at once.
+    pub concurrency: Option<ConcurrencyConfig>,
+    /// CI integration settings (workflow generation, GitHub access).
+    #[serde(default)]
+    pub ci: CiConfig,
+    /// The `release:*` pipeline: guard rails and the tag it creates.
+    #[serde(default)]
+    pub release: ReleaseConfig,
+    /// Per-run command logs for `dwf logs`.
+    #[serde(default)]
+    pub logs: LogsConfig,
+    /// What `dwf serve` lets HTTP clients run.
+    #[serde(default)]
+    pub serve: ServeConfig,
+    /// Notifications sent when a run finishes.
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// Subprocess reporter plugins receiving run events, keyed by name.
+    #[serde(default)]
+    pub reporters: BTreeMap<String, ReporterConfig>,
     /// Path to the directory containing this config file, used to anchor relative paths.
     #[serde(skip)]
     pub source_dir: Option<PathBuf>,
+    /// The preset `extends` resolved to when the config was loaded.
+    #[serde(skip)]
+    pub preset: Option<Preset>,
 }
 
 impl DevflowConfig {
@@ -42,19 +93,244 @@ impl DevflowConfig {
     /// Returns an error if the file cannot be read, the TOML is invalid,
     /// or the configuration fails validation.
     pub fn load_from_file(path: &str) -> Result<Self> {
+        let (cfg, warnings) = Self::load_with_options(path, &LoadOptions::default())?;
+        for warning in warnings {
+            tracing::warn!("{path}: {warning}");
+        }
+        Ok(cfg)
+    }
+
+    /// Loads a `DevflowConfig`, returning unknown keys ignored in lenient mode.
+    ///
+    /// Lenient mode applies when `opts.lenient` is set or the file declares a
+    /// `schema_version` newer than [`CONFIG_SCHEMA_VERSION`]. A config with
+    /// `extends` is merged over its preset first.
+    ///
+    /// # Errors
+    /// As [`Self::load_from_file`]; in lenient mode an unknown key that looks
+    /// like a typo of a known key is still an error. Also fails if the preset
+    /// cannot be resolved.
+    pub fn load_with_options(path: &str, opts: &LoadOptions) -> Result<(Self, Vec<ConfigWarning>)> {
         let text = std::fs::read_to_string(path)
             .with_context(|| format!("failed to read config file: {path}"))?;
-        let mut cfg = toml::from_str::<Self>(&text)
-            .with_context(|| format!("failed to parse TOML config: {path}"))?;
-
-        cfg.source_dir = Some(
-            PathBuf::from(path)
-                .parent()
-                .unwrap_or(std::path::Path::new(""))
-                .to_path_buf(),
-        );
-        cfg.validate()?;
-        Ok(cfg)
+        let source_dir = PathBuf::from(path)
+            .parent()
+            .unwrap_or(std::path::Path::new(""))
+            .to_path_buf();
+        let preset = preset::declared(&text)
+            .map(|extends| preset::resolve(&extends, &source_dir, opts.update_presets))
+            .transpose()
+            .with_context(|| format!("failed to resolve the preset of {path}"))?;
+        // Errors in the merged text name both files.
+        let (merged, label) = match &preset {
+            Some(preset) => (
+                preset::merged_text(preset, path, &text)?,
+                format!("{path} (merged with {})", preset.source),
+            ),
+            None => (text.clone(), path.to_string()),
+        };
+        let lenient =
+            opts.lenient || declared_schema_version(&merged) > Some(CONFIG_SCHEMA_VERSION);
+        let (mut cfg, warnings) = if lenient {
+            crate::lenient::from_str::<Self>(&label, &merged)
+        } else {
+            toml::from_str::<Self>(&merged)
+                .map(|cfg| (cfg, Vec::new()))
+                .map_err(|e| diagnostic::from_toml_error(&e, &label, &merged).into())
+        }
+        .with_context(|| format!("failed to parse TOML config: {path}"))?;
+
+        cfg.source_dir = Some(source_dir);
+        cfg.preset = preset;
+        let base = cfg.source_dir.clone().unwrap_or_default();
+        cfg.resolve_auto_stacks(&base)
+            .and_then(|()| cfg.resolve_aliases(path))
+            .and_then(|()| cfg.validate())
+            .map_err(|e| match e.downcast::<ConfigDiagnostic>() {
+                Ok(diagnostic) => cfg.locate(diagnostic, path, &text).into(),
+                Err(e) => e,
+            })?;
+        Ok((cfg, warnings))
+    }
+
+    /// Locates `diagnostic` in the config file, or in the preset file when
+    /// its key comes from the preset.
+    fn locate(&self, diagnostic: ConfigDiagnostic, path: &str, text: &str) -> ConfigDiagnostic {
+        let inherited = |preset: &Preset| {
+            let key = diagnostic.key.as_deref()?;
+            let local = preset::table(path, text).ok()?;
+            if preset::defines(&local, key) {
+                return None;
+            }
+            std::fs::read_to_string(&preset.file).ok()
+        };
+        match self.preset.as_ref().and_then(|p| Some((p, inherited(p)?))) {
+            Some((preset, preset_text)) => {
+                diagnostic.locate(&preset.file.display().to_string(), &preset_text)
+            }
+            None => diagnostic.locate(path, text),
+        }
+    }
+
+    /// Replaces an omitted `[project] stack`, or an `"auto"` entry in it, with
+    /// the stacks detected under `base_path`; explicit entries keep their order.
+    ///
+    /// # Errors
+    /// Returns an error if detection was requested but found no stack at all.
+    pub fn resolve_auto_stacks(&mut self, base_path: &std::path::Path) -> Result<()> {
+        let project = &mut self.project;
+        if !project.stack.is_empty() && !project.stack.iter().any(|s| s == STACK_AUTO) {
+            return Ok(());
+        }
+        let explicit: Vec<String> = project
+            .stack
+            .drain(..)
+            .filter(|s| s != STACK_AUTO)
+            .collect();
+        project.detected_stacks = crate::project::detect_stacks(base_path)
+            .into_iter()
+            .filter(|s| !explicit.contains(s))
+            .collect();
+        project.stack = explicit;
+        project
+            .stack
+            .extend(project.detected_stacks.iter().cloned());
+        if project.stack.is_empty() {
+            return Err(ConfigDiagnostic::new(format!(
+                "no stacks detected in '{}' (looked for {})",
+                base_path.display(),
+                crate::project::detectable_manifests().join(", ")
+            ))
+            .at_key("project")
+            .with_hint("set [project] stack explicitly")
+            .into());
+        }
+        Ok(())
+    }
+
+    /// The alias table of this config: the built-in aliases and `[aliases]`.
+    pub fn aliases(&self) -> Aliases {
+        Aliases::builtin().with_config(&self.aliases)
+    }
+
+    /// Replaces aliased commands in `[targets]`, and deprecated ones in
+    /// `[commands]` keys, with the commands they stand for, warning about
+    /// deprecated ones. Shorthand `[commands]` keys such as `lint` keep
+    /// applying to every selector of their primary command.
+    ///
+    /// # Errors
+    /// Returns an error if an `[aliases]` entry is not a command, or an
+    /// aliased `[commands]` key clashes with the key it is replaced by.
+    pub fn resolve_aliases(&mut self, path: &str) -> Result<()> {
+        for (alias, command) in &self.aliases {
+            for text in [alias, command] {
+                CommandRef::from_str(text).map_err(|e| {
+                    ConfigDiagnostic::new(format!("invalid command '{}' in [aliases]: {}", text, e))
+                        .at_key(format!("aliases.{alias}"))
+                })?;
+            }
+            if alias == command {
+                return Err(ConfigDiagnostic::new(format!(
+                    "alias '{}' stands for itself in [aliases]",
+                    alias
+                ))
+                .at_key(format!("aliases.{alias}"))
+                .into());
+            }
+        }
+        let aliases = self.aliases();
+        let replace = |text: &str, key: &str| {
+            let alias = aliases.parse(text).ok()?.1?;
+            if alias.deprecated {
+                tracing::warn!("{path}: {key}: {alias}");
+            }
+            Some(alias)
+        };
+        for (profile, entries) in &mut self.targets.profiles {
+            for (index, entry) in entries.iter_mut().enumerate() {
+                let key = format!("targets.{profile}[{index}]");
+                if let Some(alias) = replace(entry.command(), &key) {
+                    match entry {
+                        TargetEntry::Command(cmd) => *cmd = alias.command,
+                        TargetEntry::Detailed(options) => options.cmd = alias.command,
+                    }
+                }
+            }
+        }
+        let keys: Vec<String> = self
+            .commands
+            .keys()
+            .filter(|key| aliases.get(key).is_some_and(|alias| alias.deprecated))
+            .cloned()
+            .collect();
+        for key in keys {
+            let Some(command) = replace(&key, &format!("commands.{key}")).map(|a| a.command) else {
+                continue;
+            };
+            if self.commands.contains_key(&command) {
+                return Err(ConfigDiagnostic::new(format!(
+                    "[commands] has both '{}' and '{}', which it stands for",
+                    key, command
+                ))
+                .at_key(format!("commands.{key}"))
+                .into());
+            }
+            let config = self.commands.remove(&key).unwrap_or_default();
+            self.commands.insert(command, config);
+        }
+        Ok(())
+    }
+
+    /// How the fingerprint inputs of extension `name` are hashed, or those of
+    /// no extension when `name` is `None`.
+    pub fn fingerprint_strategy(&self, name: Option<&str>) -> FingerprintStrategy {
+        name.and_then(|name| self.extensions.as_ref()?.get(name)?.fingerprint_strategy)
+            .or_else(|| self.cache.as_ref()?.fingerprint_strategy)
+            .unwrap_or_default()
+    }
+
+    /// Returns `[extensions.rust.feature_matrix]`, or the default matrix.
+    pub fn feature_matrix(&self) -> FeatureMatrix {
+        self.extensions
+            .as_ref()
+            .and_then(|extensions| extensions.get("rust"))
+            .and_then(|ext| ext.feature_matrix.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns `[extensions.node.visual]`, or the default suite.
+    pub fn visual(&self) -> VisualConfig {
+        self.extension("node")
+            .and_then(|ext| ext.visual.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns `[extensions.node.remote_cache]`, if configured.
+    pub fn remote_cache(&self) -> Option<RemoteCacheConfig> {
+        self.extension("node")
+            .and_then(|ext| ext.remote_cache.clone())
+    }
+
+    /// Returns the settings of extension `name`, if configured.
+    pub fn extension(&self, name: &str) -> Option<&ExtensionConfig> {
+        self.extensions.as_ref()?.get(name)
+    }
+
+    /// Returns how extension `name`'s volume mounts are provided.
+    pub fn mount_type(&self, name: &str) -> MountType {
+        self.extensions
+            .as_ref()
+            .and_then(|extensions| extensions.get(name))
+            .map(|ext| ext.mount_type)
+            .unwrap_or_default()
+    }
+
+    /// Returns the `[commands]` settings for `command`: the entry for its
+    /// canonical form, else the one for its primary command.
+    pub fn command_config(&self, command: &CommandRef) -> Option<&CommandConfig> {
+        self.commands
+            .get(&command.canonical())
+            .or_else(|| self.commands.get(command.primary.as_str()))
     }
 
     /// Validates the configuration for logical consistency.
@@ -62,31 +338,544 @@ impl DevflowConfig {
         // Devflow Core is stack-agnostic. We allow any stack name here, as long as
         // an extension (builtin or subprocess) registers to handle it during runtime execution.
 
-        for (profile, commands) in &self.targets.profiles {
-            for raw in commands {
+        for (profile, entries) in &self.targets.profiles {
+            for (index, entry) in entries.iter().enumerate() {
+                let raw = entry.command();
+                let key = format!("targets.{profile}[{index}]");
                 CommandRef::from_str(raw).map_err(|e| {
-                    anyhow!(
+                    ConfigDiagnostic::new(format!(
                         "invalid command '{}' in targets profile '{}': {}",
-                        raw,
-                        profile,
-                        e
+                        raw, profile, e
+                    ))
+                    .at_key(&key)
+                })?;
+                if entry.paths().iter().any(|p| p.trim().is_empty()) {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "empty path filter for '{}' in targets profile '{}'",
+                        raw, profile
+                    ))
+                    .at_key(format!("{key}.paths"))
+                    .into());
+                }
+                if let Some(timeout) = entry.timeout() {
+                    parse_duration(timeout).map_err(|e| {
+                        ConfigDiagnostic::new(format!(
+                            "{} for '{}' in targets profile '{}'",
+                            e, raw, profile
+                        ))
+                        .at_key(format!("{key}.timeout"))
+                    })?;
+                }
+                let declared = |stack: &String| {
+                    self.project.stack.contains(stack)
+                        || self
+                            .extensions
+                            .as_ref()
+                            .is_some_and(|extensions| extensions.contains_key(stack))
+                };
+                if let Some(stack) = entry.stacks().iter().find(|stack| !declared(stack)) {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "stack '{}' for '{}' in targets profile '{}' is not a project stack",
+                        stack, raw, profile
+                    ))
+                    .at_key(format!("{key}.stacks"))
+                    .with_hint(format!("project stacks: {}", self.project.stack.join(", ")))
+                    .into());
+                }
+            }
+        }
+
+        if self.runtime.profile == RuntimeProfile::Remote {
+            match &self.runtime.remote {
+                Some(remote) if !remote.host.trim().is_empty() => {}
+                _ => {
+                    return Err(ConfigDiagnostic::new(
+                        "runtime profile 'remote' requires [runtime.remote] with a host",
                     )
+                    .at_key("runtime.remote.host")
+                    .with_hint("add `[runtime.remote]` with `host = \"build-box\"`")
+                    .into())
+                }
+            }
+        }
+
+        if let Some(remote) = &self.runtime.remote {
+            let fields = [("host", Some(&remote.host)), ("user", remote.user.as_ref())];
+            for (field, value) in fields {
+                let Some(value) = value.map(|value| value.trim()) else {
+                    continue;
+                };
+                if value.starts_with('-') || value.contains('@') {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "runtime.remote.{field} '{value}' must not start with '-' or contain '@'"
+                    ))
+                    .at_key(format!("runtime.remote.{field}"))
+                    .with_hint("set the login name with `user = \"ci\"` and keep `host` a bare hostname or ssh alias")
+                    .into());
+                }
+            }
+        }
+
+        if let Some(index) = self
+            .runtime
+            .path_dirs
+            .iter()
+            .position(|dir| dir.trim().is_empty() || dir.contains(':'))
+        {
+            return Err(ConfigDiagnostic::new(format!(
+                "invalid [runtime] path_dirs entry '{}': must be a non-empty directory without ':'",
+                self.runtime.path_dirs[index]
+            ))
+            .at_key(format!("runtime.path_dirs[{index}]"))
+            .into());
+        }
+
+        for (name, ext) in self.extensions.iter().flatten() {
+            if let Some(req) = &ext.version {
+                semver::VersionReq::parse(req).map_err(|e| {
+                    ConfigDiagnostic::new(format!(
+                        "invalid version requirement '{}' for extension '{}': {}",
+                        req, name, e
+                    ))
+                    .at_key(format!("extensions.{name}.version"))
+                    .with_hint("use a semver requirement such as \"^1.2\"")
+                })?;
+            }
+            let scoped = [
+                ("rust", "feature_matrix", ext.feature_matrix.is_some()),
+                ("rust", "wasm_targets", !ext.wasm_targets.is_empty()),
+                ("rust", "no_std_targets", !ext.no_std_targets.is_empty()),
+                ("node", "visual", ext.visual.is_some()),
+                ("node", "remote_cache", ext.remote_cache.is_some()),
+            ];
+            if let Some((owner, field, _)) =
+                scoped.iter().find(|(owner, _, set)| *set && name != owner)
+            {
+                return Err(ConfigDiagnostic::new(format!(
+                    "{} is not supported by extension '{}'",
+                    field, name
+                ))
+                .at_key(format!("extensions.{name}.{field}"))
+                .with_hint(format!(
+                    "{field} is only available under [extensions.{owner}]"
+                ))
+                .into());
+            }
+            for (field, targets) in [
+                ("wasm_targets", &ext.wasm_targets),
+                ("no_std_targets", &ext.no_std_targets),
+            ] {
+                if let Some((index, target)) = targets.iter().enumerate().find(|(_, target)| {
+                    target.is_empty()
+                        || !target
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                }) {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "invalid target '{}' in {}",
+                        target, field
+                    ))
+                    .at_key(format!("extensions.{name}.{field}[{index}]"))
+                    .with_hint("use a target triple such as \"wasm32-unknown-unknown\"")
+                    .into());
+                }
+            }
+            if let Some(matrix) = &ext.feature_matrix {
+                matrix.validate().map_err(|e| {
+                    ConfigDiagnostic::new(format!("invalid [extensions.rust.feature_matrix]: {e}"))
+                        .at_key("extensions.rust.feature_matrix")
                 })?;
             }
+            if let Some(visual) = &ext.visual {
+                visual.validate().map_err(|e| {
+                    ConfigDiagnostic::new(format!("invalid [extensions.node.visual]: {e}"))
+                        .at_key("extensions.node.visual")
+                })?;
+            }
+            if let Some(cache) = &ext.remote_cache {
+                cache.validate().map_err(|e| {
+                    ConfigDiagnostic::new(format!("invalid [extensions.node.remote_cache]: {e}"))
+                        .at_key("extensions.node.remote_cache")
+                })?;
+            }
+        }
+
+        self.ci.permissions.validate()?;
+        self.ci.runners.validate()?;
+        if let Some(badge) = &self.ci.badge {
+            badge.validate().map_err(|e| {
+                ConfigDiagnostic::new(format!("invalid [ci.badge]: {e}")).at_key("ci.badge")
+            })?;
+        }
+        for (action, version) in &self.ci.actions {
+            let repository = action.split_once('/').is_some_and(|(owner, repo)| {
+                !owner.is_empty() && !repo.is_empty() && !repo.contains('/')
+            });
+            let message = if !repository {
+                format!("[ci.actions] key '{action}' must be an action repository like 'actions/checkout'")
+            } else if version.is_empty()
+                || version.contains(|c: char| c == '@' || c.is_whitespace())
+            {
+                format!(
+                    "[ci.actions] '{action}' must pin a tag, branch or commit SHA, not '{version}'"
+                )
+            } else {
+                continue;
+            };
+            return Err(ConfigDiagnostic::new(message)
+                .at_key(format!("ci.actions.{action}"))
+                .into());
+        }
+        for (job, steps) in &self.ci.jobs {
+            let listed = [
+                ("pre_steps", &steps.pre_steps),
+                ("post_steps", &steps.post_steps),
+            ];
+            for (list, steps) in listed {
+                if let Some(index) = steps
+                    .iter()
+                    .position(|step| step.uses.is_some() == step.run.is_some())
+                {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "[ci.jobs.{job}] {list}[{index}] needs exactly one of 'uses' and 'run'"
+                    ))
+                    .at_key(format!("ci.jobs.{job}.{list}"))
+                    .into());
+                }
+            }
+        }
+        if let Some(profile) = self
+            .ci
+            .outputs
+            .keys()
+            .find(|profile| !self.targets.profiles.contains_key(*profile))
+        {
+            return Err(ConfigDiagnostic::new(format!(
+                "[ci.outputs] references unknown targets profile '{}'",
+                profile
+            ))
+            .at_key(format!("ci.outputs.{profile}"))
+            .into());
+        }
+        if let Some(profile) = self
+            .release
+            .profile
+            .as_ref()
+            .filter(|profile| !self.targets.profiles.contains_key(*profile))
+        {
+            return Err(ConfigDiagnostic::new(format!(
+                "[release] profile references unknown targets profile '{profile}'"
+            ))
+            .at_key("release.profile")
+            .into());
+        }
+        if !self.release.tag.contains("{version}") {
+            return Err(ConfigDiagnostic::new(format!(
+                "[release] tag '{}' must contain {{version}}",
+                self.release.tag
+            ))
+            .at_key("release.tag")
+            .into());
+        }
+        if self.logs.keep == 0 {
+            return Err(ConfigDiagnostic::new("[logs] keep must be at least 1")
+                .at_key("logs.keep")
+                .into());
+        }
+        for (profile, condition) in &self.ci.conditions {
+            let key = format!("ci.conditions.{profile}");
+            if !self.targets.profiles.contains_key(profile) {
+                return Err(ConfigDiagnostic::new(format!(
+                    "[ci.conditions] references unknown targets profile '{}'",
+                    profile
+                ))
+                .at_key(key)
+                .into());
+            }
+            if profile == "pr" {
+                return Err(ConfigDiagnostic::new(
+                    "[ci.conditions] cannot condition the 'pr' profile, whose jobs always run",
+                )
+                .at_key(key)
+                .into());
+            }
+            if condition.trim().is_empty() {
+                return Err(ConfigDiagnostic::new(format!(
+                    "empty condition for profile '{}' in [ci.conditions]",
+                    profile
+                ))
+                .at_key(key)
+                .into());
+            }
+        }
+
+        for (key, command) in &self.commands {
+            CommandRef::from_str(key).map_err(|e| {
+                ConfigDiagnostic::new(format!("invalid command '{}' in [commands]: {}", key, e))
+                    .at_key(format!("commands.{key}"))
+            })?;
+            if let Some(var) = command
+                .allow
+                .iter()
+                .find(|var| var.trim().is_empty() || var.contains('='))
+            {
+                return Err(ConfigDiagnostic::new(format!(
+                    "invalid variable name '{}' in allow list for '{}'",
+                    var, key
+                ))
+                .at_key(format!("commands.{key}.allow"))
+                .with_hint("list variable names only, e.g. allow = [\"PATH\", \"HOME\"]")
+                .into());
+            }
+            command.max_output_bytes().map_err(|e| {
+                ConfigDiagnostic::new(format!("{} for '{}'", e, key))
+                    .at_key(format!("commands.{key}.max_output"))
+            })?;
+            for (field, paths) in [("writable", &command.writable), ("tmpfs", &command.tmpfs)] {
+                if let Some((index, path)) = paths
+                    .iter()
+                    .enumerate()
+                    .find(|(_, path)| !is_workspace_relative(path))
+                {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "invalid {} path '{}' for '{}': must be a path relative to the workspace",
+                        field, path, key
+                    ))
+                    .at_key(format!("commands.{key}.{field}[{index}]"))
+                    .into());
+                }
+            }
+            if command
+                .image
+                .as_deref()
+                .is_some_and(|image| image.trim().is_empty())
+            {
+                return Err(ConfigDiagnostic::new(format!("empty image for '{}'", key))
+                    .at_key(format!("commands.{key}.image"))
+                    .into());
+            }
+            if let Some(index) = command
+                .protected_branches
+                .iter()
+                .position(|branch| branch.trim().is_empty())
+            {
+                return Err(
+                    ConfigDiagnostic::new(format!("empty protected branch for '{}'", key))
+                        .at_key(format!("commands.{key}.protected_branches[{index}]"))
+                        .into(),
+                );
+            }
+        }
+
+        if let Some(concurrency) = &self.concurrency {
+            for class in [ResourceClass::Cpu, ResourceClass::Io] {
+                if concurrency.limit(class) == 0 {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "[concurrency] {} must be at least 1",
+                        class.as_str()
+                    ))
+                    .at_key(format!("concurrency.{}", class.as_str()))
+                    .into());
+                }
+            }
+        }
+
+        for (name, wait) in &self.waits {
+            wait.probe().map_err(|e| {
+                ConfigDiagnostic::new(format!("{} in [waits.{}]", e, name))
+                    .at_key(format!("waits.{name}"))
+                    .with_hint("set exactly one of tcp = \"host:port\", http = \"http://...\" or command = \"...\"")
+            })?;
+            wait.timeout().and(wait.interval()).map_err(|e| {
+                ConfigDiagnostic::new(format!("{} in [waits.{}]", e, name))
+                    .at_key(format!("waits.{name}"))
+            })?;
+        }
+
+        for (name, fixture) in &self.fixtures {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return Err(ConfigDiagnostic::new(format!("invalid fixture name '{}'", name))
+                    .at_key(format!("fixtures.{name}"))
+                    .with_hint("fixture names become container host names: use letters, digits and '-'")
+                    .into());
+            }
+            let env = fixture.env_var();
+            if env.is_empty() || env.contains(['=', ' ']) {
+                return Err(ConfigDiagnostic::new(format!(
+                    "invalid variable name '{}' for fixture '{}'",
+                    env, name
+                ))
+                .at_key(format!("fixtures.{name}.env"))
+                .into());
+            }
+            if fixture
+                .migrate
+                .iter()
+                .any(|command| command.trim().is_empty())
+            {
+                return Err(ConfigDiagnostic::new(format!(
+                    "empty migrate command for fixture '{}'",
+                    name
+                ))
+                .at_key(format!("fixtures.{name}.migrate"))
+                .into());
+            }
+            fixture.timeout().map_err(|e| {
+                ConfigDiagnostic::new(format!("{} for fixture '{}'", e, name))
+                    .at_key(format!("fixtures.{name}.timeout"))
+            })?;
+        }
+
+        if let Some(env_files) = &self.env_files {
+            for (field, values) in [
+                ("files", &env_files.files),
+                ("ci", &env_files.ci),
+                ("mask", &env_files.mask),
+            ] {
+                if values.iter().any(|value| value.trim().is_empty()) {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "empty entry in [env_files] {}",
+                        field
+                    ))
+                    .at_key(format!("env_files.{field}"))
+                    .into());
+                }
+            }
+        }
+
+        if let Some(name) = self
+            .reporters
+            .iter()
+            .find_map(|(name, reporter)| reporter.command.trim().is_empty().then_some(name))
+        {
+            return Err(
+                ConfigDiagnostic::new(format!("empty command for reporter '{}'", name))
+                    .at_key(format!("reporters.{name}.command"))
+                    .into(),
+            );
+        }
+
+        if let Some(webhook) = &self.notifications.webhook {
+            let url = webhook.url.trim();
+            if !(url.starts_with("https://") || url.starts_with("http://") || url.starts_with("${"))
+            {
+                return Err(ConfigDiagnostic::new(format!(
+                    "invalid webhook url '{}': expected an http(s) URL",
+                    webhook.url
+                ))
+                .at_key("notifications.webhook.url")
+                .with_hint("use `url = \"${DEVFLOW_WEBHOOK_URL}\"` to read a secret URL from the environment")
+                .into());
+            }
+        }
+
+        if let Some(container) = &self.container {
+            for (index, output) in container.outputs.iter().enumerate() {
+                if !is_workspace_relative(output) {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "invalid container output '{}': must be a path relative to the workspace",
+                        output
+                    ))
+                    .at_key(format!("container.outputs[{index}]"))
+                    .into());
+                }
+            }
+            for (index, platform) in container.platforms.iter().enumerate() {
+                let parts: Vec<&str> = platform.split('/').collect();
+                if !(2..=3).contains(&parts.len()) || parts.iter().any(|p| p.trim().is_empty()) {
+                    return Err(ConfigDiagnostic::new(format!(
+                        "invalid container platform '{}': expected os/arch[/variant] (e.g. linux/arm64)",
+                        platform
+                    ))
+                    .at_key(format!("container.platforms[{index}]"))
+                    .into());
+                }
+            }
+            for (index, arg) in container.extra_args.iter().enumerate() {
+                let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+                if MANAGED_RUN_FLAGS.contains(&flag) {
+                    let hint = match flag {
+                        "--entrypoint" => "use `[container] entrypoint` instead",
+                        "-l" | "--label" => "use `[container.labels]` instead",
+                        _ => "Devflow sets this flag itself; remove it from extra_args",
+                    };
+                    return Err(ConfigDiagnostic::new(format!(
+                        "container extra arg '{}' conflicts with a flag managed by Devflow",
+                        arg
+                    ))
+                    .at_key(format!("container.extra_args[{index}]"))
+                    .with_hint(hint)
+                    .into());
+                }
+            }
+            if let Some(key) = container
+                .labels
+                .keys()
+                .find(|key| key.is_empty() || key.contains('='))
+            {
+                return Err(ConfigDiagnostic::new(format!(
+                    "invalid container label '{}': keys must be non-empty and contain no '='",
+                    key
+                ))
+                .at_key("container.labels")
+                .into());
+            }
         }
 
         Ok(())
     }
 }
 
+/// Options for [`DevflowConfig::load_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Ignore unknown keys (with a warning) instead of rejecting them.
+    pub lenient: bool,
+    /// Resolve `extends` to the latest commit instead of the locked one.
+    pub update_presets: bool,
+}
+
+/// An unknown key skipped by lenient loading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigWarning {
+    /// Dotted key path, e.g. `runtime.sandbox`.
+    pub key: String,
+    /// 1-based line of the key in the config file.
+    pub line: usize,
+    /// 1-based column of the key in the config file.
+    pub column: usize,
+}
+
+impl std::fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ignoring unknown key '{}' at line {}, column {}",
+            self.key, self.line, self.column
+        )
+    }
+}
+
+/// Reads the top-level `schema_version` without deserializing the rest.
+fn declared_schema_version(text: &str) -> Option<u32> {
+    let doc = toml_edit::Document::parse(text).ok()?;
+    let version = doc.as_table().get("schema_version")?.as_integer()?;
+    u32::try_from(version).ok()
+}
+
 /// Metadata about the project.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
 pub struct ProjectConfig {
     /// Name of the project.
     pub name: String,
     /// Technology stacks used in the project (e.g., "rust", "node").
+    /// Omitted, or containing `"auto"`, means detect them from project manifests.
+    #[serde(default)]
     pub stack: Vec<String>,
+    /// Stacks added to `stack` by auto-detection when the config was loaded.
+    #[serde(skip)]
+    pub detected_stacks: Vec<String>,
 }
 
 /// Configuration for the Devflow runtime.
@@ -96,6 +885,86 @@ pub struct RuntimeConfig {
     /// The current runtime profile.
     #[serde(default)]
     pub profile: RuntimeProfile,
+    /// Development environment integration (`auto`, `none`, `nix`, `devcontainer`).
+    #[serde(default)]
+    pub env: RuntimeEnv,
+    /// Remote build machine used by the `remote` profile.
+    pub remote: Option<RemoteConfig>,
+    /// How a command claimed by several stacks is dispatched.
+    #[serde(default)]
+    pub conflict: ConflictPolicy,
+    /// How extensions setting the same environment variable differently are handled.
+    #[serde(default)]
+    pub env_conflict: EnvConflictPolicy,
+    /// Extension variables not passed to commands running on the host.
+    #[serde(default)]
+    pub host_env_exclude: Vec<String>,
+    /// The `PATH` of commands running on the host (`inherit`, `hermetic`).
+    #[serde(default)]
+    pub path: PathMode,
+    /// Toolchain directories put on a `hermetic` `PATH` before the stacks'
+    /// own; `~/` is the home directory.
+    #[serde(default)]
+    pub path_dirs: Vec<String>,
+}
+
+/// Where commands running on the host look up their programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PathMode {
+    /// dwf's own `PATH`, after the installed pinned tools.
+    #[default]
+    Inherit,
+    /// Only the installed pinned tools, `path_dirs`, the stacks' toolchain
+    /// directories and the system directories.
+    Hermetic,
+}
+
+/// Dispatch policy when more than one stack produces an action for a command.
+///
+/// Stacks are ordered by `[extensions.<name>] priority` (highest first), then by
+/// their position in `project.stack`, then by extension name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Run the command on every stack that supports it.
+    #[default]
+    All,
+    /// Run the command only on the highest-precedence stack.
+    First,
+    /// Fail when more than one stack supports the command.
+    Error,
+}
+
+/// Policy for extensions that set the same environment variable to different values.
+///
+/// Precedence follows the stack dispatch order (see [`ConflictPolicy`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvConflictPolicy {
+    /// Keep the highest-precedence value and log a warning.
+    #[default]
+    Warn,
+    /// Fail before running anything.
+    Error,
+}
+
+/// SSH target for the `remote` runtime profile.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// Hostname or SSH config alias of the build machine.
+    pub host: String,
+    /// Login user; defaults to the SSH client configuration.
+    pub user: Option<String>,
+    /// SSH port; defaults to the SSH client configuration.
+    pub port: Option<u16>,
+    /// Remote directory the workspace is synced into.
+    /// Defaults to `.devflow/workspaces/<project>` under the remote user's home.
+    pub path: Option<String>,
+    /// Workspace-relative paths copied back after each command (e.g. `target/release`).
+    #[serde(default)]
+    pub artifacts: Vec<String>,
 }
 
 /// Supported container proxy engines.
@@ -121,134 +990,2741 @@ pub struct ContainerConfig {
     /// List of file paths to include in the container's fingerprint calculation.
     #[serde(default)]
     pub fingerprint_inputs: Vec<String>,
+    /// Workspace directories that stay writable in hermetic mode (e.g. `target`).
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Target platforms (e.g. `["linux/amd64", "linux/arm64"]`); empty means the host's.
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    /// BuildKit layer cache settings for `container:build` and generated CI.
+    #[serde(default)]
+    pub buildkit: BuildkitConfig,
+    /// Run the containerized commands of a check in one long-lived container.
+    #[serde(default)]
+    pub reuse: bool,
+    /// Overrides the image entrypoint (`""` clears it).
+    pub entrypoint: Option<String>,
+    /// Extra `run` flags (e.g. `--privileged`, `--add-host=db:10.0.0.5`).
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// Labels set on every container (`[container.labels]`).
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// How containerized commands run when devflow itself runs in a container.
+    #[serde(default)]
+    pub nested: NestedStrategy,
 }
 
-/// Configuration for build artifact and dependency caching.
-#[derive(Debug, Clone, Deserialize, Default)]
+/// How devflow runs containerized commands from inside a container
+/// (`[container] nested`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NestedStrategy {
+    /// Run commands directly in the current container, without the proxy.
+    #[default]
+    Skip,
+    /// Use a Docker daemon that shares this container's filesystem
+    /// (docker-in-docker); paths are mounted as they are.
+    Dind,
+    /// Use the host's engine through its mounted socket; mounts are
+    /// translated to the host paths they come from.
+    Socket,
+}
+
+/// `run` flags Devflow sets itself, which `[container] extra_args` may not
+/// repeat.
+const MANAGED_RUN_FLAGS: &[&str] = &[
+    "--rm",
+    "-i",
+    "--interactive",
+    "-d",
+    "--detach",
+    "--name",
+    "-v",
+    "--volume",
+    "--mount",
+    "--tmpfs",
+    "-w",
+    "--workdir",
+    "-e",
+    "--env",
+    "--env-file",
+    "--platform",
+    "--network",
+    "--net",
+    "--entrypoint",
+    "-l",
+    "--label",
+];
+
+/// BuildKit cache import/export settings (`[container.buildkit]`).
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
-pub struct CacheConfig {
-    /// The root directory for the Devflow cache (relative to source dir or absolute).
-    pub root: Option<String>,
-    /// Reserved for future cache strategy selection (e.g., "local", "gha").
-    pub strategy: Option<String>,
+pub struct BuildkitConfig {
+    /// Registry reference used as a shared layer cache (e.g. `ghcr.io/org/app-ci:buildcache`).
+    pub cache_ref: Option<String>,
+    /// Extra raw `--cache-from` specs (e.g. `type=gha`).
+    #[serde(default)]
+    pub cache_from: Vec<String>,
+    /// Extra raw `--cache-to` specs (e.g. `type=gha,mode=max`).
+    #[serde(default)]
+    pub cache_to: Vec<String>,
 }
 
-/// Configuration for target profiles.
-///
-/// Maps profile names (e.g., "pr") to a list of command strings.
-#[derive(Debug, Deserialize, Default)]
-pub struct TargetsConfig {
-    /// A map of profile names to command lists.
-    #[serde(flatten, default)]
-    pub profiles: HashMap<String, Vec<String>>,
+impl BuildkitConfig {
+    /// Whether any cache import or export is configured.
+    pub fn is_configured(&self) -> bool {
+        self.cache_ref.is_some() || !self.cache_from.is_empty() || !self.cache_to.is_empty()
+    }
+
+    /// `--cache-from` specs: the registry cache first, then the raw entries.
+    pub fn cache_from_specs(&self) -> Vec<String> {
+        let mut specs: Vec<String> = self
+            .cache_ref
+            .iter()
+            .map(|r| format!("type=registry,ref={r}"))
+            .collect();
+        specs.extend(self.cache_from.iter().cloned());
+        specs
+    }
+
+    /// `--cache-to` specs: the registry cache (all layers) first, then the raw entries.
+    pub fn cache_to_specs(&self) -> Vec<String> {
+        let mut specs: Vec<String> = self
+            .cache_ref
+            .iter()
+            .map(|r| format!("type=registry,ref={r},mode=max"))
+            .collect();
+        specs.extend(self.cache_to.iter().cloned());
+        specs
+    }
 }
 
-/// Configuration for an individual extension.
-#[derive(Debug, Clone, Deserialize)]
+/// Configuration for CI integration.
+#[derive(Debug, Clone, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
-pub struct ExtensionConfig {
-    /// Where the extension is sourced from (builtin or path).
-    pub source: ExtensionSource,
-    /// Optional path for path-sourced extensions.
-    pub path: Option<PathBuf>,
-    /// Optional version string.
-    pub version: Option<String>,
-    /// The API version the extension expects.
-    pub api_version: Option<u32>,
-    /// List of capabilities exposed by the extension.
+pub struct CiConfig {
+    /// Shell command printing a GitHub token on stdout (e.g. `op read op://ci/github/token`).
+    /// Consulted after `GITHUB_TOKEN`, `gh auth token`, and the OS keychain.
+    pub credential_helper: Option<String>,
+    /// The job graph of generated workflows.
     #[serde(default)]
-    pub capabilities: Vec<String>,
-    /// Whether this extension is required for project operations.
+    pub layout: CiLayout,
+    /// `GITHUB_TOKEN` permissions for generated workflows.
     #[serde(default)]
-    pub required: bool,
-    /// Whether this extension is trusted to run on the host during negotiation.
+    pub permissions: PermissionsConfig,
+    /// Runner selection for generated workflow jobs.
     #[serde(default)]
-    pub trusted: bool,
+    pub runners: RunnersConfig,
+    /// Per-profile workflow paths for `ci:generate --split`; setting any enables split mode.
+    #[serde(default)]
+    pub outputs: BTreeMap<String, String>,
+    /// Per-profile `if:` expressions for the profile's jobs in the combined
+    /// workflow (`main` and `release` have defaults).
+    #[serde(default)]
+    pub conditions: BTreeMap<String, String>,
+    /// Per-job caches of the generated verify jobs.
+    #[serde(default)]
+    pub cache: CiCacheConfig,
+    /// Status badge the generated workflow publishes on pushes to `main`.
+    pub badge: Option<BadgeConfig>,
+    /// Versions generated workflows use, per action repository
+    /// (`"actions/checkout" = "v4.2.2"`, or a full commit SHA).
+    #[serde(default)]
+    pub actions: BTreeMap<String, String>,
+    /// Extra steps of generated jobs, keyed by job id (`check_test_unit`).
+    #[serde(default)]
+    pub jobs: BTreeMap<String, CiJobConfig>,
 }
 
-/// Source types for extensions.
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum ExtensionSource {
-    /// A builtin extension bundled with the Devflow binary.
-    Builtin,
-    /// An extension loaded from a local directory.
-    Path,
+/// How generated workflows split a profile into jobs (`[ci] layout`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CiLayout {
+    /// Prep, build and verify jobs running the commands in the CI image.
+    #[default]
+    MultiJob,
+    /// One `check` job running `dwf check:<profile>` on the runner, for
+    /// small projects.
+    SingleJob,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl CiLayout {
+    /// The `[ci] layout` value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MultiJob => "multi-job",
+            Self::SingleJob => "single-job",
+        }
+    }
+}
 
-    #[test]
-    fn rejects_unknown_top_level_key() {
-        // Ensures that the configuration parser rejects unknown keys at the top level
-        // to prevent silent errors from typos.
-        let text = r#"
-        random_top = "not-allowed"
+/// Extra steps of one generated job.
+///
+/// ```toml
+/// [ci.jobs.check_test_unit]
+/// pre_steps = [{ uses = "actions/setup-java@v4", with = { java-version = "21" } }]
+/// post_steps = [{ name = "Upload logs", run = "./scripts/upload-logs.sh", if = "always()" }]
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CiJobConfig {
+    /// Steps run right after the job's checkout (or first, without one).
+    #[serde(default)]
+    pub pre_steps: Vec<CiStep>,
+    /// Steps run after the job's own steps.
+    #[serde(default)]
+    pub post_steps: Vec<CiStep>,
+}
 
-        [project]
-        name = "demo"
-        stack = ["rust"]
+/// A workflow step added to a generated job, with GitHub's step keys.
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CiStep {
+    pub name: Option<String>,
+    pub id: Option<String>,
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+    /// The action to run; exactly one of `uses` and `run` is required.
+    pub uses: Option<String>,
+    /// The script to run.
+    pub run: Option<String>,
+    pub shell: Option<String>,
+    pub working_directory: Option<String>,
+    /// Action inputs.
+    #[serde(default)]
+    pub with: BTreeMap<String, String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    pub continue_on_error: Option<bool>,
+    pub timeout_minutes: Option<u32>,
+}
 
-        [targets]
-        pr = ["fmt:check"]
-        "#;
+/// Per-job caching in generated verify jobs.
+///
+/// ```toml
+/// [ci.cache]
+/// strategy = "restore-pr-save-main"
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CiCacheConfig {
+    /// When jobs restore and save their cache.
+    #[serde(default)]
+    pub strategy: CiCacheStrategy,
+}
+
+/// When a verify job restores and saves its own cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CiCacheStrategy {
+    /// Jobs only restore the shared build cache.
+    #[default]
+    None,
+    /// Restore and save on every run.
+    RestoreSave,
+    /// Restore on every run, save only on pushes to `main`, so pull
+    /// requests never evict the caches main builds rely on.
+    RestorePrSaveMain,
+}
+
+/// The status badge of generated workflows (`dwf badge`).
+///
+/// ```toml
+/// [ci.badge]
+/// publish = "gh-pages"
+/// coverage = "coverage/lcov.info"
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BadgeConfig {
+    /// Where the workflow publishes the badge.
+    #[serde(default)]
+    pub publish: BadgePublish,
+    /// The gist updated with `publish = "gist"`.
+    pub gist: Option<String>,
+    /// Coverage report shown on the badge: an lcov file or an istanbul
+    /// `coverage-summary.json`, relative to the project root.
+    pub coverage: Option<String>,
+}
+
+impl BadgeConfig {
+    fn validate(&self) -> Result<(), String> {
+        match (self.publish, self.gist.as_deref()) {
+            (BadgePublish::Gist, None) => return Err("publish = \"gist\" requires gist".into()),
+            (BadgePublish::GhPages, Some(_)) => {
+                return Err("gist is only used with publish = \"gist\"".into())
+            }
+            (BadgePublish::Gist, Some(gist))
+                if gist.is_empty() || !gist.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                return Err(format!("gist '{gist}' is not a gist id"))
+            }
+            _ => {}
+        }
+        match &self.coverage {
+            Some(path) if !is_workspace_relative(path) => Err(format!(
+                "coverage '{path}' must be a path relative to the workspace"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where generated workflows publish the status badge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BadgePublish {
+    /// Commit it to the `gh-pages` branch.
+    #[default]
+    GhPages,
+    /// Update a file of a gist, with the `GIST_TOKEN` secret.
+    Gist,
+}
+
+/// Execution settings for one command.
+///
+/// ```toml
+/// [commands."test:integration"]
+/// clear_env = true
+/// allow = ["PATH", "HOME"]
+/// max_output = "50MB"
+/// spool_logs = true
+/// read_only = true
+/// writable = ["coverage"]
+/// tmpfs = [".pytest_cache"]
+/// image = "rust:1.75"
+/// resource = "cpu"
+/// require_clean = true
+/// protected_branches = ["main"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    /// Run the command with an empty environment plus `allow`, instead of
+    /// the inherited one.
+    #[serde(default)]
+    pub clear_env: bool,
+    /// Variables kept under `clear_env`. Extension and config environment is
+    /// filtered by the same list.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Terminal output budget per stream (e.g. `"50MB"`); beyond it only the
+    /// head and tail are shown.
+    pub max_output: Option<String>,
+    /// Also write the full output to a compressed log in the cache.
+    #[serde(default)]
+    pub spool_logs: bool,
+    /// Mount the workspace read-only in containerized runs, except
+    /// `[container] outputs`, `writable` and `tmpfs`.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Workspace directories that stay writable under `read_only`, kept on
+    /// the host.
+    #[serde(default)]
+    pub writable: Vec<String>,
+    /// Workspace directories that stay writable under `read_only`, on a tmpfs
+    /// discarded after the run.
+    #[serde(default)]
+    pub tmpfs: Vec<String>,
+    /// Run containerized in this image instead of `[container] image`, e.g. a
+    /// toolchain pinned for `check:msrv`.
+    pub image: Option<String>,
+    /// The resource class `[concurrency]` counts the command against,
+    /// instead of the one its extensions declare.
+    pub resource: Option<ResourceClass>,
+    /// Refuse to run with uncommitted changes to tracked files, unless
+    /// `--allow-dirty`; unset, only `fmt:fix` and `release` do.
+    pub require_clean: Option<bool>,
+    /// Branches the command refuses to run on, unless `--allow-dirty`.
+    #[serde(default)]
+    pub protected_branches: Vec<String>,
+}
+
+impl CommandConfig {
+    /// Whether `clear_env` keeps the variable `key`.
+    pub fn allows(&self, key: &str) -> bool {
+        self.allow.iter().any(|var| var == key)
+    }
+
+    /// Returns `max_output` in bytes.
+    ///
+    /// # Errors
+    /// Returns an error if `max_output` is not a valid size.
+    pub fn max_output_bytes(&self) -> Result<Option<u64>> {
+        self.max_output.as_deref().map(parse_size).transpose()
+    }
+}
+
+/// Whether `path` is a non-empty path inside the workspace.
+fn is_workspace_relative(path: &str) -> bool {
+    let parsed = std::path::Path::new(path);
+    !path.trim().is_empty()
+        && !parsed.is_absolute()
+        && !parsed
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+}
+
+/// Parses a byte size such as `512KB`, `50MB`, `1GB` or `4096` (bytes); units
+/// are powers of 1024 and case-insensitive.
+///
+/// # Errors
+/// Returns an error if `text` is not a positive size.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let invalid = || anyhow!("invalid size '{}' (expected e.g. 512KB, 50MB or 1GB)", text);
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (count, unit) = trimmed.split_at(split);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        _ => return Err(invalid()),
+    };
+    count
+        .checked_mul(1 << shift)
+        .filter(|bytes| *bytes > 0)
+        .ok_or_else(invalid)
+}
+
+/// A readiness probe for a service that integration tests depend on,
+/// retried until it succeeds or `timeout` passes.
+///
+/// ```toml
+/// [waits.postgres]
+/// tcp = "localhost:5432"
+/// timeout = "30s"
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WaitConfig {
+    /// `host:port` that must accept a TCP connection.
+    pub tcp: Option<String>,
+    /// URL that must answer a GET with a 2xx status.
+    pub http: Option<String>,
+    /// Shell command that must exit successfully.
+    pub command: Option<String>,
+    /// How long to keep retrying (default `30s`).
+    pub timeout: Option<String>,
+    /// Pause between attempts (default `1s`).
+    pub interval: Option<String>,
+}
+
+/// What a [`WaitConfig`] checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe<'a> {
+    Tcp(&'a str),
+    Http(&'a str),
+    Command(&'a str),
+}
+
+impl WaitConfig {
+    /// Returns the configured probe.
+    ///
+    /// # Errors
+    /// Returns an error unless exactly one valid probe is set.
+    pub fn probe(&self) -> Result<Probe<'_>> {
+        let probes: Vec<Probe> = [
+            self.tcp.as_deref().map(Probe::Tcp),
+            self.http.as_deref().map(Probe::Http),
+            self.command.as_deref().map(Probe::Command),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        match probes.as_slice() {
+            [Probe::Tcp(addr)]
+                if addr
+                    .rsplit_once(':')
+                    .is_none_or(|(_, port)| port.parse::<u16>().is_err()) =>
+            {
+                Err(anyhow!(
+                    "invalid tcp address '{}' (expected host:port)",
+                    addr
+                ))
+            }
+            [Probe::Http(url)] if !(url.starts_with("http://") || url.starts_with("https://")) => {
+                Err(anyhow!("invalid http url '{}'", url))
+            }
+            [Probe::Command(command)] if command.trim().is_empty() => Err(anyhow!("empty command")),
+            [probe] => Ok(*probe),
+            [] => Err(anyhow!("no probe")),
+            _ => Err(anyhow!("more than one probe")),
+        }
+    }
+
+    /// Returns `timeout`, 30 seconds by default.
+    ///
+    /// # Errors
+    /// Returns an error if `timeout` is not a valid duration.
+    pub fn timeout(&self) -> Result<Duration> {
+        self.timeout
+            .as_deref()
+            .map_or(Ok(Duration::from_secs(30)), parse_duration)
+    }
+
+    /// Returns `interval`, one second by default.
+    ///
+    /// # Errors
+    /// Returns an error if `interval` is not a valid duration.
+    pub fn interval(&self) -> Result<Duration> {
+        self.interval
+            .as_deref()
+            .map_or(Ok(Duration::from_secs(1)), parse_duration)
+    }
+}
+
+/// An ephemeral database started in a container for `test:integration` and
+/// removed afterwards.
+///
+/// ```toml
+/// [fixtures.db]
+/// engine = "postgres"
+/// migrate = ["sqlx migrate run"]
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureConfig {
+    /// Database server to run.
+    pub engine: FixtureEngine,
+    /// Image to run instead of the engine default.
+    pub image: Option<String>,
+    /// Variable receiving the connection string (default `DATABASE_URL`).
+    pub env: Option<String>,
+    /// Shell commands run on the host once the database accepts connections.
+    #[serde(default)]
+    pub migrate: Vec<String>,
+    /// How long to wait for the database to start (default `60s`).
+    pub timeout: Option<String>,
+}
+
+/// Database servers available as fixtures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FixtureEngine {
+    Postgres,
+    Mysql,
+}
+
+impl FixtureConfig {
+    /// Returns `env`, `DATABASE_URL` by default.
+    pub fn env_var(&self) -> &str {
+        self.env.as_deref().unwrap_or("DATABASE_URL")
+    }
+
+    /// Returns `image`, or the engine's default image.
+    pub fn image(&self) -> &str {
+        self.image.as_deref().unwrap_or(match self.engine {
+            FixtureEngine::Postgres => "postgres:16-alpine",
+            FixtureEngine::Mysql => "mysql:8",
+        })
+    }
+
+    /// Returns `timeout`, 60 seconds by default.
+    ///
+    /// # Errors
+    /// Returns an error if `timeout` is not a valid duration.
+    pub fn timeout(&self) -> Result<Duration> {
+        self.timeout
+            .as_deref()
+            .map_or(Ok(Duration::from_secs(60)), parse_duration)
+    }
+}
+
+/// Dotenv files loaded into the execution environment of every command.
+///
+/// ```toml
+/// [env_files]
+/// files = [".env"]
+/// ci = [".env.ci"]
+/// mask = ["*TOKEN*", "*SECRET*"]
+/// ```
+///
+/// Their variables override the inherited environment; extension and
+/// command-line variables override them.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct EnvFilesConfig {
+    /// Files loaded in order, relative to the config file; missing ones are
+    /// skipped.
+    #[serde(default = "EnvFilesConfig::default_files")]
+    pub files: Vec<String>,
+    /// Files loaded after `files` when `CI` is set.
+    #[serde(default = "EnvFilesConfig::default_ci")]
+    pub ci: Vec<String>,
+    /// Key globs whose values are masked in output.
+    #[serde(default = "EnvFilesConfig::default_mask")]
+    pub mask: Vec<String>,
+}
+
+impl Default for EnvFilesConfig {
+    fn default() -> Self {
+        Self {
+            files: Self::default_files(),
+            ci: Self::default_ci(),
+            mask: Self::default_mask(),
+        }
+    }
+}
+
+impl EnvFilesConfig {
+    fn default_files() -> Vec<String> {
+        vec![".env".to_string()]
+    }
+
+    fn default_ci() -> Vec<String> {
+        vec![".env.ci".to_string()]
+    }
+
+    fn default_mask() -> Vec<String> {
+        [
+            "*TOKEN*",
+            "*SECRET*",
+            "*PASSWORD*",
+            "*PASSWD*",
+            "*CREDENTIAL*",
+            "*_KEY",
+        ]
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect()
+    }
+
+    /// Whether the value of `key` is masked (patterns match case-insensitively).
+    pub fn masks(&self, key: &str) -> bool {
+        let key = key.to_ascii_uppercase();
+        self.mask
+            .iter()
+            .any(|pattern| crate::glob::matches(&pattern.to_ascii_uppercase(), &key))
+    }
+}
+
+/// Parses a duration such as `500ms`, `30s`, `2m` or `1h`; a bare number is
+/// seconds.
+///
+/// # Errors
+/// Returns an error if `text` is not a positive duration.
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let invalid = || {
+        anyhow!(
+            "invalid duration '{}' (expected e.g. 500ms, 30s or 2m)",
+            text
+        )
+    };
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (count, unit) = trimmed.split_at(split);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let duration = match unit.trim() {
+        "ms" => Duration::from_millis(count),
+        "" | "s" => Duration::from_secs(count),
+        "m" => Duration::from_secs(count.saturating_mul(60)),
+        "h" => Duration::from_secs(count.saturating_mul(3_600)),
+        _ => return Err(invalid()),
+    };
+    if duration.is_zero() {
+        return Err(invalid());
+    }
+    Ok(duration)
+}
+
+/// A subprocess reporter plugin.
+///
+/// The command is started once per run and receives one JSON event per line
+/// on stdin (see `devflow_core::reporter`).
+///
+/// ```toml
+/// [reporters.audit]
+/// command = "./scripts/devflow-audit"
+/// args = ["--team", "platform"]
+/// output = true
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ReporterConfig {
+    /// Program to start, looked up on `PATH` unless it contains a `/`.
+    pub command: String,
+    /// Arguments passed to the program.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Also send every output line of every command (pipes command output
+    /// through devflow).
+    #[serde(default)]
+    pub output: bool,
+}
+
+/// The `release:*` pipeline.
+///
+/// ```toml
+/// [release]
+/// branch = "main"
+/// profile = "release"
+/// tag = "v{version}"
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct ReleaseConfig {
+    /// The branch releases are cut from; `release:verify` requires it to be
+    /// checked out and not behind its upstream.
+    pub branch: String,
+    /// The target profile that must pass first; defaults to `release` when
+    /// `[targets]` defines it.
+    pub profile: Option<String>,
+    /// The tag `release:tag` creates, where `{version}` is the project
+    /// version.
+    pub tag: String,
+}
+
+impl Default for ReleaseConfig {
+    fn default() -> Self {
+        Self {
+            branch: "main".to_string(),
+            profile: None,
+            tag: "v{version}".to_string(),
+        }
+    }
+}
+
+impl ReleaseConfig {
+    /// The profile `release:verify` runs, if any.
+    pub fn profile<'a>(&'a self, targets: &TargetsConfig) -> Option<&'a str> {
+        match &self.profile {
+            Some(profile) => Some(profile),
+            None => targets
+                .profiles
+                .contains_key("release")
+                .then_some("release"),
+        }
+    }
+
+    /// The tag name for `version`.
+    pub fn tag_name(&self, version: &str) -> String {
+        self.tag.replace("{version}", version)
+    }
+}
+
+/// Per-run command logs (`dwf logs`).
+///
+/// ```toml
+/// [logs]
+/// enabled = true
+/// keep = 20
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct LogsConfig {
+    /// Write each command's output to a log under `<cache root>/runs`.
+    pub enabled: bool,
+    /// How many runs keep their logs; older ones are removed.
+    pub keep: usize,
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep: 20,
+        }
+    }
+}
+
+/// What `dwf serve` lets HTTP clients run.
+///
+/// ```toml
+/// [serve]
+/// allow = ["test:*", "check:pr"]
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct ServeConfig {
+    /// Globs of the commands `POST /runs` may start, matched against the
+    /// canonical command an alias or profile resolves to.
+    pub allow: Vec<String>,
+}
+
+impl ServeConfig {
+    /// The default `allow`: commands that neither rewrite the workspace
+    /// nor publish, so not `fmt:fix`, `release:*` or `prune:*`.
+    pub const DEFAULT_ALLOW: &'static [&'static str] = &[
+        "fmt:check",
+        "lint:*",
+        "build:*",
+        "test:*",
+        "check:*",
+        "ci:check",
+    ];
+
+    /// Whether `POST /runs` may start the canonical `command`.
+    pub fn allows(&self, command: &str) -> bool {
+        crate::glob::matches_any(&self.allow, command)
+    }
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            allow: Self::DEFAULT_ALLOW.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// Notifications sent when a run finishes.
+///
+/// ```toml
+/// [notifications.webhook]
+/// url = "${DEVFLOW_WEBHOOK_URL}"
+/// on = ["failure"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// JSON summary posted when a run finishes in CI.
+    pub webhook: Option<WebhookConfig>,
+}
+
+/// A webhook receiving run summaries (e.g. a Slack incoming webhook).
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Endpoint to post to; `${VAR}` references are expanded from the environment.
+    pub url: String,
+    /// Outcomes that trigger a post (default: both).
+    #[serde(default = "NotifyOn::all")]
+    pub on: Vec<NotifyOn>,
+}
+
+/// Run outcome a notification is sent for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    Success,
+    Failure,
+}
+
+impl NotifyOn {
+    fn all() -> Vec<Self> {
+        vec![Self::Success, Self::Failure]
+    }
+}
+
+/// A GitHub Actions `runs-on` value: one runner label or a set of labels.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum RunnerSpec {
+    /// A single label (e.g. `ubuntu-latest`).
+    Label(String),
+    /// Labels that must all match (e.g. `["self-hosted", "linux", "x64"]`).
+    Labels(Vec<String>),
+}
+
+impl RunnerSpec {
+    /// Returns the configured labels.
+    pub fn labels(&self) -> Vec<&str> {
+        match self {
+            Self::Label(label) => vec![label.as_str()],
+            Self::Labels(labels) => labels.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+impl Default for RunnerSpec {
+    fn default() -> Self {
+        Self::Label("ubuntu-latest".to_string())
+    }
+}
+
+/// Runner selection for generated workflows.
+///
+/// ```toml
+/// [ci.runners]
+/// default = "ubuntu-latest"
+/// "test:integration" = ["self-hosted", "linux", "x64"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+pub struct RunnersConfig {
+    /// Runner for every job without an override; `ubuntu-latest` when unset.
+    #[serde(default)]
+    pub default: RunnerSpec,
+    /// Per-command runner overrides keyed by canonical command.
+    #[serde(flatten)]
+    pub commands: BTreeMap<String, RunnerSpec>,
+}
+
+impl RunnersConfig {
+    /// Returns the override for `command`, if one is configured.
+    pub fn for_command(&self, command: &str) -> Option<&RunnerSpec> {
+        self.commands.get(command)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (key, spec) in std::iter::once(("default", &self.default))
+            .chain(self.commands.iter().map(|(k, v)| (k.as_str(), v)))
+        {
+            if key != "default" {
+                CommandRef::from_str(key)
+                    .map_err(|e| anyhow!("invalid command '{}' in [ci.runners]: {}", key, e))?;
+            }
+            let labels = spec.labels();
+            if labels.is_empty() || labels.iter().any(|label| label.trim().is_empty()) {
+                return Err(anyhow!("empty runner label for '{}' in [ci.runners]", key));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `GITHUB_TOKEN` scopes accepted in `[ci.permissions]`.
+pub const PERMISSION_SCOPES: &[&str] = &[
+    "actions",
+    "attestations",
+    "checks",
+    "contents",
+    "deployments",
+    "discussions",
+    "id-token",
+    "issues",
+    "packages",
+    "pages",
+    "pull-requests",
+    "repository-projects",
+    "security-events",
+    "statuses",
+];
+
+/// Workflow and per-job `GITHUB_TOKEN` permissions.
+///
+/// ```toml
+/// [ci.permissions]
+/// contents = "read"
+///
+/// [ci.permissions.jobs]
+/// verify = { checks = "write" }
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+pub struct PermissionsConfig {
+    /// Workflow-level scopes; `contents = "read"` when none are given.
+    #[serde(flatten)]
+    pub workflow: BTreeMap<String, String>,
+    /// Per-job scopes merged over the workflow-level scopes.
+    #[serde(default)]
+    pub jobs: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl PermissionsConfig {
+    /// Returns the workflow-level scopes, applying the `contents: read` default.
+    pub fn workflow_scopes(&self) -> BTreeMap<String, String> {
+        if self.workflow.is_empty() {
+            BTreeMap::from([("contents".to_string(), "read".to_string())])
+        } else {
+            self.workflow.clone()
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let job_scopes = self.jobs.values().flatten();
+        for (scope, level) in self.workflow.iter().chain(job_scopes) {
+            if !PERMISSION_SCOPES.contains(&scope.as_str()) {
+                return Err(anyhow!(
+                    "unknown permission scope '{}' in [ci.permissions]",
+                    scope
+                ));
+            }
+            let allowed: &[&str] = if scope == "id-token" {
+                &["write", "none"]
+            } else {
+                &["read", "write", "none"]
+            };
+            if !allowed.contains(&level.as_str()) {
+                return Err(anyhow!(
+                    "invalid permission '{} = \"{}\"' in [ci.permissions]: expected one of {}",
+                    scope,
+                    level,
+                    allowed.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Configuration for build artifact and dependency caching.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// The root directory for the Devflow cache (relative to source dir or absolute).
+    pub root: Option<String>,
+    /// Reserved for future cache strategy selection (e.g., "local", "gha").
+    pub strategy: Option<String>,
+    /// How fingerprint inputs are hashed, unless an extension overrides it.
+    pub fingerprint_strategy: Option<FingerprintStrategy>,
+}
+
+/// Limits for running the commands of a check profile in parallel.
+///
+/// ```toml
+/// [concurrency]
+/// cpu = 1
+/// io = 4
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    /// CPU-bound commands (builds, tests) running at once.
+    #[serde(default = "ConcurrencyConfig::default_cpu")]
+    pub cpu: usize,
+    /// IO-bound commands (formatting checks, linters) running at once.
+    #[serde(default = "ConcurrencyConfig::default_io")]
+    pub io: usize,
+}
+
+impl ConcurrencyConfig {
+    fn default_cpu() -> usize {
+        1
+    }
+
+    fn default_io() -> usize {
+        4
+    }
+
+    /// How many commands of `class` may run at once.
+    pub fn limit(&self, class: ResourceClass) -> usize {
+        match class {
+            ResourceClass::Cpu => self.cpu,
+            ResourceClass::Io => self.io,
+        }
+    }
+}
+
+/// Configuration for target profiles.
+///
+/// Maps profile names (e.g., "pr") to a list of command entries, in the
+/// order the config file declares them.
+#[derive(Debug, Deserialize, Default)]
+pub struct TargetsConfig {
+    /// A map of profile names to command lists.
+    #[serde(flatten, default)]
+    pub profiles: IndexMap<String, Vec<TargetEntry>>,
+}
+
+/// A single command entry in a target profile.
+///
+/// Entries are either a plain command string (`"test:unit"`) or a table with
+/// per-command options (`{ cmd = "test:unit", timeout = "5m", retries = 2 }`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum TargetEntry {
+    /// A bare canonical command string.
+    Command(String),
+    /// A command with additional options.
+    Detailed(TargetEntryOptions),
+}
+
+/// Per-command options for a target profile entry.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetEntryOptions {
+    /// The canonical command (e.g., "test:integration").
+    pub cmd: String,
+    /// Path globs; the command only runs when a changed file matches one of them.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Stops the command after this long per stack (e.g. "5m").
+    pub timeout: Option<String>,
+    /// How often the command is retried after failing.
+    #[serde(default)]
+    pub retries: u32,
+    /// Only these project stacks run the command (empty means all).
+    #[serde(default)]
+    pub stacks: Vec<String>,
+}
+
+impl TargetEntry {
+    /// Returns the canonical command string of this entry.
+    pub fn command(&self) -> &str {
+        match self {
+            Self::Command(cmd) => cmd,
+            Self::Detailed(options) => &options.cmd,
+        }
+    }
+
+    /// Returns the path filters of this entry (empty means "always run").
+    pub fn paths(&self) -> &[String] {
+        match self {
+            Self::Command(_) => &[],
+            Self::Detailed(options) => &options.paths,
+        }
+    }
+
+    /// Returns the timeout as configured (e.g. "5m"), if any.
+    pub fn timeout(&self) -> Option<&str> {
+        match self {
+            Self::Command(_) => None,
+            Self::Detailed(options) => options.timeout.as_deref(),
+        }
+    }
+
+    /// Returns how often the command is retried after failing.
+    pub fn retries(&self) -> u32 {
+        match self {
+            Self::Command(_) => 0,
+            Self::Detailed(options) => options.retries,
+        }
+    }
+
+    /// Returns the stacks the command is restricted to (empty means all).
+    pub fn stacks(&self) -> &[String] {
+        match self {
+            Self::Command(_) => &[],
+            Self::Detailed(options) => &options.stacks,
+        }
+    }
+}
+
+impl From<&str> for TargetEntry {
+    fn from(value: &str) -> Self {
+        Self::Command(value.to_string())
+    }
+}
+
+/// Configuration for an individual extension.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtensionConfig {
+    /// Where the extension is sourced from (builtin or path).
+    pub source: ExtensionSource,
+    /// Optional path for path-sourced extensions.
+    pub path: Option<PathBuf>,
+    /// Semver requirement the extension's reported version must satisfy
+    /// (for example `">=0.3"`).
+    pub version: Option<String>,
+    /// The API version the extension expects.
+    pub api_version: Option<u32>,
+    /// List of capabilities exposed by the extension.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Whether this extension is required for project operations.
+    #[serde(default)]
+    pub required: bool,
+    /// Whether this extension is trusted to run on the host during negotiation.
+    #[serde(default)]
+    pub trusted: bool,
+    /// Dispatch precedence; higher values run (or win a conflict) first.
+    #[serde(default)]
+    pub priority: i32,
+    /// How this extension's fingerprint inputs are hashed, over
+    /// `[cache] fingerprint_strategy`.
+    pub fingerprint_strategy: Option<FingerprintStrategy>,
+    /// How the extension's volume mounts are provided in containers.
+    #[serde(default)]
+    pub mount_type: MountType,
+    /// The feature combinations `test:features` covers (Rust only).
+    pub feature_matrix: Option<FeatureMatrix>,
+    /// Targets `build:wasm` builds for (Rust only).
+    #[serde(default)]
+    pub wasm_targets: Vec<String>,
+    /// Targets without `std` that `check:no-std` checks against (Rust only).
+    #[serde(default)]
+    pub no_std_targets: Vec<String>,
+    /// How `test:visual` runs the visual regression suite (Node only).
+    pub visual: Option<VisualConfig>,
+    /// The remote cache of a Turborepo or Nx monorepo (Node only).
+    pub remote_cache: Option<RemoteCacheConfig>,
+}
+
+/// The remote cache the task runner of a Node monorepo (Turborepo or Nx)
+/// shares build and test results through.
+///
+/// ```toml
+/// [extensions.node.remote_cache]
+/// url = "https://cache.example.com"
+/// team = "platform"
+/// token_env = "DEVFLOW_REMOTE_CACHE_TOKEN"
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteCacheConfig {
+    /// The cache server.
+    pub url: String,
+    /// The team or namespace the cache entries belong to (Turborepo only).
+    pub team: Option<String>,
+    /// The environment variable holding the cache token; the token itself
+    /// never goes in `devflow.toml`.
+    #[serde(default = "RemoteCacheConfig::default_token_env")]
+    pub token_env: String,
+}
+
+impl RemoteCacheConfig {
+    fn default_token_env() -> String {
+        "DEVFLOW_REMOTE_CACHE_TOKEN".to_string()
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if !(self.url.starts_with("https://") || self.url.starts_with("http://")) {
+            return Err(format!("url '{}' must be an http(s) URL", self.url));
+        }
+        if self.token_env.trim().is_empty() {
+            return Err("token_env must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The visual regression suite of `test:visual`, e.g. Storybook snapshots.
+///
+/// Devflow runs the script and keeps the image diffs it writes when it fails;
+/// comparing images is left to the suite, which reads the tolerated
+/// difference from `DEVFLOW_VISUAL_THRESHOLD`.
+///
+/// ```toml
+/// [extensions.node.visual]
+/// script = "test-storybook"
+/// threshold = 0.01
+/// diffs = ["**/__diff_output__/**"]
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VisualConfig {
+    /// The `package.json` script to run.
+    #[serde(default = "VisualConfig::default_script")]
+    pub script: String,
+    /// The share of pixels (0 to 1) a snapshot may differ in before it fails.
+    pub threshold: Option<f64>,
+    /// Globs matching the image diffs the suite writes.
+    #[serde(default = "VisualConfig::default_diffs")]
+    pub diffs: Vec<String>,
+}
+
+impl Default for VisualConfig {
+    fn default() -> Self {
+        Self {
+            script: Self::default_script(),
+            threshold: None,
+            diffs: Self::default_diffs(),
+        }
+    }
+}
+
+impl VisualConfig {
+    fn default_script() -> String {
+        "test:visual".to_string()
+    }
+
+    /// The diff outputs of jest-image-snapshot (used by the Storybook test
+    /// runner) and of Playwright's `toHaveScreenshot`.
+    fn default_diffs() -> Vec<String> {
+        vec![
+            "**/__diff_output__/**".to_string(),
+            "test-results/**/*-diff.png".to_string(),
+        ]
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.script.trim().is_empty() {
+            return Err("script must not be empty".to_string());
+        }
+        match self.threshold {
+            Some(threshold) if !(0.0..=1.0).contains(&threshold) => {
+                Err(format!("threshold {threshold} must be between 0 and 1"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The feature combinations of `test:features`.
+///
+/// Without `sets`, cargo-hack generates the combinations of every crate's
+/// features; with them, each set runs on its own with
+/// `--no-default-features`.
+///
+/// ```toml
+/// [extensions.rust.feature_matrix]
+/// strategy = "powerset"
+/// exclude = ["nightly"]
+/// depth = 2
+/// partitions = 4
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FeatureMatrix {
+    /// How combinations are generated; unused with `sets`.
+    #[serde(default)]
+    pub strategy: FeatureStrategy,
+    /// Explicit feature combinations, instead of generated ones.
+    #[serde(default)]
+    pub sets: Vec<Vec<String>>,
+    /// Features left out of generated combinations.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// The most features a generated combination has.
+    pub depth: Option<u32>,
+    /// Splits the generated combinations into this many runs, one CI job
+    /// each.
+    pub partitions: Option<u32>,
+    /// Whether each combination is tested or only checked.
+    #[serde(default)]
+    pub command: FeatureCommand,
+}
+
+/// How `test:features` generates feature combinations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeatureStrategy {
+    /// Every combination of the features (`cargo hack --feature-powerset`).
+    #[default]
+    Powerset,
+    /// Each feature on its own (`cargo hack --each-feature`).
+    EachFeature,
+}
+
+/// The cargo command `test:features` runs for each combination.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureCommand {
+    /// `cargo test`.
+    #[default]
+    Test,
+    /// `cargo check`, for crates whose tests are too slow to repeat.
+    Check,
+}
+
+impl FeatureMatrix {
+    /// The runs `test:features` is split into, as the part of the selector
+    /// after `features:`: one per set (`serde+tokio`, `none` for the empty
+    /// set) or per partition (`1/4`). Empty when it is a single run.
+    pub fn shards(&self) -> Vec<String> {
+        if !self.sets.is_empty() {
+            return self.sets.iter().map(|set| feature_set_label(set)).collect();
+        }
+        match self.partitions {
+            Some(count) if count > 1 => (1..=count).map(|i| format!("{i}/{count}")).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The set named by `label` (see [`FeatureMatrix::shards`]).
+    pub fn set(&self, label: &str) -> Option<&[String]> {
+        self.sets
+            .iter()
+            .find(|set| feature_set_label(set) == label)
+            .map(Vec::as_slice)
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.partitions == Some(0) || self.depth == Some(0) {
+            return Err("depth and partitions must be at least 1".to_string());
+        }
+        if !self.sets.is_empty() && self.partitions.is_some() {
+            return Err(
+                "partitions cannot be combined with sets; each set is its own run".to_string(),
+            );
+        }
+        if let Some(feature) = self
+            .sets
+            .iter()
+            .flatten()
+            .chain(&self.exclude)
+            .find(|feature| feature.is_empty() || feature.contains(['+', ',', ' ']))
+        {
+            return Err(format!("invalid feature name '{feature}'"));
+        }
+        let mut labels = self.shards();
+        labels.sort();
+        if let Some(pair) = labels.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("feature set '{}' is listed twice", pair[0]));
+        }
+        Ok(())
+    }
+}
+
+/// The label of a feature set in selectors and job names: its features
+/// joined by `+`, or `none`.
+pub fn feature_set_label(set: &[String]) -> String {
+    if set.is_empty() {
+        "none".to_string()
+    } else {
+        set.join("+")
+    }
+}
+
+/// How an extension directory such as `node_modules` is provided to
+/// containerized commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MountType {
+    /// Part of the workspace bind mount, shared with the host.
+    #[default]
+    Bind,
+    /// A named engine volume keyed by the extension's fingerprint.
+    Volume,
+}
+
+/// Source types for extensions.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtensionSource {
+    /// A builtin extension bundled with the Devflow binary.
+    Builtin,
+    /// An extension loaded from a local directory.
+    Path,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unknown_top_level_key() {
+        // Ensures that the configuration parser rejects unknown keys at the top level
+        // to prevent silent errors from typos.
+        let text = r#"
+        random_top = "not-allowed"
+
+        [project]
+        name = "demo"
+        stack = ["rust"]
+
+        [targets]
+        pr = ["fmt:check"]
+        "#;
+
+        let err = toml::from_str::<DevflowConfig>(text).expect_err("must reject unknown key");
+        assert!(err.to_string().contains("random_top"));
+    }
+
+    #[test]
+    fn rejects_unknown_project_key() {
+        // Ensures that the [project] section does not allow unauthorized keys.
+        let text = r#"
+        [project]
+        name = "demo"
+        stack = ["rust"]
+        owner = "team"
+
+        [targets]
+        pr = ["fmt:check"]
+        "#;
+
+        let err =
+            toml::from_str::<DevflowConfig>(text).expect_err("must reject unknown project key");
+        assert!(err.to_string().contains("owner"));
+    }
+
+    #[test]
+    fn unit_test_validate_allows_custom_stacks() {
+        let text = r#"
+        [project]
+        name = "valid-stack"
+        stack = ["ruby"]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn unit_test_validate_rejects_invalid_commands() {
+        let text = r#"
+        [project]
+        name = "bad-commands"
+        stack = ["rust"]
+
+        [targets]
+        pr = ["build:debug", "not-a-command:selector"]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg
+            .validate()
+            .expect_err("Must fail validation for invalid command format");
+
+        // CommandRef from_str returns an error because primary command is unknown
+        assert!(err
+            .to_string()
+            .contains("invalid command 'not-a-command:selector'"));
+    }
+
+    #[test]
+    fn parses_target_entries_with_path_filters() {
+        let text = r#"
+        [project]
+        name = "filters"
+        stack = ["rust"]
+
+        [targets]
+        pr = ["fmt:check", { cmd = "test:integration", paths = ["migrations/**", "src/db/**"] }]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert!(cfg.validate().is_ok());
+
+        let pr = &cfg.targets.profiles["pr"];
+        assert_eq!(pr[0], TargetEntry::from("fmt:check"));
+        assert!(pr[0].paths().is_empty());
+        assert_eq!(pr[1].command(), "test:integration");
+        assert_eq!(pr[1].paths(), ["migrations/**", "src/db/**"]);
+    }
+
+    #[test]
+    fn target_profiles_keep_file_order() {
+        let text = r#"
+        [project]
+        name = "ordered"
+        stack = ["rust"]
+
+        [targets]
+        release = ["package:artifact"]
+        pr = ["fmt:check"]
+        nightly = ["test:smoke"]
+        main = ["test:unit"]
+        "#;
+
+        for _ in 0..3 {
+            let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+            let profiles: Vec<&str> = cfg.targets.profiles.keys().map(String::as_str).collect();
+            assert_eq!(profiles, ["release", "pr", "nightly", "main"]);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_target_entry_option() {
+        let text = r#"
+        [project]
+        name = "filters"
+        stack = ["rust"]
+
+        [targets]
+        pr = [{ cmd = "test:unit", pathz = ["src/**"] }]
+        "#;
+
+        assert!(toml::from_str::<DevflowConfig>(text).is_err());
+    }
+
+    #[test]
+    fn parses_target_entry_execution_options() {
+        let text = r#"
+        [project]
+        name = "options"
+        stack = ["rust", "node"]
+
+        [targets]
+        pr = [{ cmd = "test:unit", timeout = "5m", retries = 2, stacks = ["rust"] }, "fmt:check"]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert!(cfg.validate().is_ok());
+        let pr = &cfg.targets.profiles["pr"];
+        assert_eq!(pr[0].timeout(), Some("5m"));
+        assert_eq!(pr[0].retries(), 2);
+        assert_eq!(pr[0].stacks(), ["rust"]);
+        assert_eq!((pr[1].timeout(), pr[1].retries()), (None, 0));
+
+        for (entry, message) in [
+            (
+                r#"{ cmd = "test:unit", timeout = "soon" }"#,
+                "invalid duration",
+            ),
+            (
+                r#"{ cmd = "test:unit", stacks = ["go"] }"#,
+                "not a project stack",
+            ),
+        ] {
+            let text = format!(
+                "[project]\nname = \"options\"\nstack = [\"rust\"]\n[targets]\npr = [{entry}]\n"
+            );
+            let cfg = toml::from_str::<DevflowConfig>(&text).expect("Valid TOML parse");
+            let err = cfg.validate().expect_err("invalid options must fail");
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn validate_rejects_empty_path_filter() {
+        let text = r#"
+        [project]
+        name = "filters"
+        stack = ["rust"]
+
+        [targets]
+        pr = [{ cmd = "test:unit", paths = [""] }]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("empty filter must fail");
+        assert!(err.to_string().contains("empty path filter"));
+    }
+
+    #[test]
+    fn parses_ci_credential_helper() {
+        let text = r#"
+        [project]
+        name = "creds"
+        stack = ["rust"]
+
+        [ci]
+        credential_helper = "op read op://ci/github/token"
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert_eq!(
+            cfg.ci.credential_helper.as_deref(),
+            Some("op read op://ci/github/token")
+        );
+    }
+
+    #[test]
+    fn parses_ci_permissions() {
+        let text = r#"
+        [project]
+        name = "perms"
+        stack = ["rust"]
+
+        [ci.permissions]
+        contents = "read"
+        actions = "read"
+
+        [ci.permissions.jobs]
+        verify = { checks = "write" }
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert!(cfg.validate().is_ok());
+        let perms = &cfg.ci.permissions;
+        assert_eq!(perms.workflow_scopes().len(), 2);
+        assert_eq!(perms.jobs["verify"]["checks"], "write");
+    }
+
+    #[test]
+    fn permissions_default_to_contents_read() {
+        let scopes = PermissionsConfig::default().workflow_scopes();
+        assert_eq!(
+            scopes.into_iter().collect::<Vec<_>>(),
+            vec![("contents".to_string(), "read".to_string())]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_invalid_permissions() {
+        let text = r#"
+        [project]
+        name = "perms"
+        stack = ["rust"]
+
+        [ci.permissions.jobs]
+        verify = { contents = "admin" }
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("unknown level must fail");
+        assert!(err.to_string().contains("contents = \"admin\""));
+
+        let text = r#"
+        [project]
+        name = "perms"
+        stack = ["rust"]
+
+        [ci.permissions]
+        everything = "write"
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("unknown scope must fail");
+        assert!(err
+            .to_string()
+            .contains("unknown permission scope 'everything'"));
+    }
+
+    #[test]
+    fn parses_ci_runners() {
+        let text = r#"
+        [project]
+        name = "runners"
+        stack = ["rust"]
+
+        [ci.runners]
+        default = "ubuntu-24.04"
+        "test:integration" = ["self-hosted", "linux", "x64"]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert!(cfg.validate().is_ok());
+        let runners = &cfg.ci.runners;
+        assert_eq!(runners.default.labels(), vec!["ubuntu-24.04"]);
+        assert_eq!(
+            runners.for_command("test:integration").unwrap().labels(),
+            vec!["self-hosted", "linux", "x64"]
+        );
+        assert!(runners.for_command("test:unit").is_none());
+        assert_eq!(
+            RunnersConfig::default().default.labels(),
+            vec!["ubuntu-latest"]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_outputs_for_unknown_profiles() {
+        let text = r#"
+        [project]
+        name = "split"
+        stack = ["rust"]
+
+        [targets]
+        pr = ["test:unit"]
+        release = ["package:artifact"]
+
+        [ci.outputs]
+        release = ".github/workflows/release.yml"
+        nightly = ".github/workflows/nightly.yml"
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert_eq!(cfg.ci.outputs["release"], ".github/workflows/release.yml");
+        let err = cfg.validate().expect_err("unknown profile must fail");
+        assert!(err
+            .to_string()
+            .contains("unknown targets profile 'nightly'"));
+    }
+
+    #[test]
+    fn validates_ci_conditions() {
+        let base = r#"
+        [project]
+        name = "conditional"
+        stack = ["rust"]
+
+        [targets]
+        pr = ["test:unit"]
+        staging = ["test:smoke"]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(&format!(
+            "{base}\n[ci.conditions]\nstaging = \"github.ref == 'refs/heads/staging'\"\n"
+        ))
+        .expect("Valid TOML parse");
+        assert!(cfg.validate().is_ok());
+        assert_eq!(
+            cfg.ci.conditions["staging"],
+            "github.ref == 'refs/heads/staging'"
+        );
+
+        for (conditions, message) in [
+            ("nightly = \"true\"", "unknown targets profile 'nightly'"),
+            ("pr = \"true\"", "cannot condition the 'pr' profile"),
+            ("staging = \" \"", "empty condition"),
+        ] {
+            let cfg = toml::from_str::<DevflowConfig>(&format!(
+                "{base}\n[ci.conditions]\n{conditions}\n"
+            ))
+            .expect("Valid TOML parse");
+            let err = cfg.validate().expect_err("invalid condition must fail");
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn fingerprint_strategy_is_selectable_per_extension() {
+        let base = "[project]\nname = \"fp\"\nstack = [\"rust\", \"node\"]\n";
+        let cfg = toml::from_str::<DevflowConfig>(base).expect("Valid TOML parse");
+        assert_eq!(
+            cfg.fingerprint_strategy(Some("rust")),
+            FingerprintStrategy::Content
+        );
+
+        let cfg = toml::from_str::<DevflowConfig>(&format!(
+            "{base}\n[extensions.node]\nsource = \"builtin\"\nfingerprint_strategy = \"content\"\n\n[cache]\nfingerprint_strategy = \"git\"\n"
+        ))
+        .expect("Valid TOML parse");
+        assert_eq!(cfg.fingerprint_strategy(None), FingerprintStrategy::Git);
+        assert_eq!(
+            cfg.fingerprint_strategy(Some("rust")),
+            FingerprintStrategy::Git
+        );
+        assert_eq!(
+            cfg.fingerprint_strategy(Some("node")),
+            FingerprintStrategy::Content
+        );
+
+        let err = toml::from_str::<DevflowConfig>(&format!(
+            "{base}\n[cache]\nfingerprint_strategy = \"mtime\"\n"
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown variant `mtime`"), "{err}");
+    }
+
+    #[test]
+    fn parses_env_files_with_defaults() {
+        let base = "[project]\nname = \"dotenv\"\nstack = [\"rust\"]\n";
+        let cfg = toml::from_str::<DevflowConfig>(base).expect("Valid TOML parse");
+        assert!(cfg.env_files.is_none());
+
+        let cfg = toml::from_str::<DevflowConfig>(&format!("{base}[env_files]\n"))
+            .expect("Valid TOML parse");
+        let env_files = cfg.env_files.as_ref().unwrap();
+        assert_eq!(*env_files, EnvFilesConfig::default());
+        assert_eq!(env_files.files, [".env"]);
+        assert!(env_files.masks("GITHUB_TOKEN"));
+        assert!(env_files.masks("db_password"));
+        assert!(env_files.masks("STRIPE_API_KEY"));
+        assert!(!env_files.masks("KEYBOARD"));
+        assert!(!env_files.masks("DATABASE_URL"));
+
+        let cfg = toml::from_str::<DevflowConfig>(&format!(
+            "{base}[env_files]\nfiles = [\".env\", \"\"]\n"
+        ))
+        .expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("empty file must fail");
+        assert!(err.to_string().contains("empty entry in [env_files] files"));
+    }
+
+    #[test]
+    fn resolves_aliases_in_targets_and_commands() {
+        let text = r#"
+        [project]
+        name = "aliases"
+        stack = ["rust"]
+
+        [targets]
+        pr = ["lint", { cmd = "test:it", retries = 1 }, "check:sec"]
+
+        [commands."test:it"]
+        clear_env = true
+
+        [commands.lint]
+        clear_env = true
+
+        [aliases]
+        "check:sec" = "check:security"
+        "#;
+        let mut cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        cfg.resolve_aliases("devflow.toml").unwrap();
+        let pr: Vec<&str> = cfg.targets.profiles["pr"]
+            .iter()
+            .map(TargetEntry::command)
+            .collect();
+        assert_eq!(pr, ["lint:static", "test:integration", "check:security"]);
+        assert_eq!(cfg.targets.profiles["pr"][1].retries(), 1);
+        assert_eq!(
+            cfg.commands.keys().collect::<Vec<_>>(),
+            ["lint", "test:integration"]
+        );
+        cfg.validate().unwrap();
+
+        for (aliases, message) in [
+            (
+                "\"check:sec\" = \"sec\"",
+                "invalid command 'sec' in [aliases]",
+            ),
+            ("\"check:sec\" = \"check:sec\"", "stands for itself"),
+        ] {
+            let mut cfg = toml::from_str::<DevflowConfig>(&format!(
+                "{}\n{aliases}\n",
+                text.replace("\"check:sec\" = \"check:security\"", "")
+            ))
+            .expect("Valid TOML parse");
+            let err = cfg.resolve_aliases("devflow.toml").unwrap_err();
+            assert!(err.to_string().contains(message), "{err}");
+        }
+
+        let mut cfg = toml::from_str::<DevflowConfig>(&format!(
+            "{text}\n[commands.\"test:integration\"]\nclear_env = true\n"
+        ))
+        .expect("Valid TOML parse");
+        let err = cfg.resolve_aliases("devflow.toml").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("has both 'test:it' and 'test:integration'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn parses_and_validates_webhook_notifications() {
+        let text = r#"
+        [project]
+        name = "notify"
+        stack = ["rust"]
+
+        [notifications.webhook]
+        url = "${DEVFLOW_WEBHOOK_URL}"
+        on = ["failure"]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let webhook = cfg.notifications.webhook.as_ref().unwrap();
+        assert_eq!(webhook.on, vec![NotifyOn::Failure]);
+        cfg.validate().expect("env reference is accepted");
+
+        let text = text
+            .replace("${DEVFLOW_WEBHOOK_URL}", "hooks.slack.com/x")
+            .replace("on = [\"failure\"]", "");
+        let cfg = toml::from_str::<DevflowConfig>(&text).expect("Valid TOML parse");
+        assert_eq!(
+            cfg.notifications.webhook.as_ref().unwrap().on,
+            vec![NotifyOn::Success, NotifyOn::Failure]
+        );
+        let err = cfg.validate().expect_err("scheme-less url must fail");
+        assert!(err.to_string().contains("invalid webhook url"));
+    }
+
+    #[test]
+    fn parses_and_looks_up_command_settings() {
+        let text = r#"
+        [project]
+        name = "commands"
+        stack = ["rust"]
+
+        [commands.test]
+        clear_env = true
+
+        [commands."test:integration"]
+        clear_env = true
+        allow = ["PATH", "HOME"]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        cfg.validate().expect("valid command settings");
+        let integration = CommandRef::from_str("test:integration").unwrap();
+        assert!(cfg.command_config(&integration).unwrap().allows("HOME"));
+        let unit = CommandRef::from_str("test:unit").unwrap();
+        assert!(cfg.command_config(&unit).unwrap().allow.is_empty());
+        let build = CommandRef::from_str("build:debug").unwrap();
+        assert!(cfg.command_config(&build).is_none());
+
+        let text = r#"
+        [project]
+        name = "commands"
+        stack = ["rust"]
+
+        [commands."test:unit"]
+        clear_env = true
+        allow = ["PATH=/usr/bin"]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg
+            .validate()
+            .expect_err("assignment in allow list must fail");
+        assert!(err
+            .to_string()
+            .contains("invalid variable name 'PATH=/usr/bin'"));
+
+        let text = r#"
+        [project]
+        name = "commands"
+        stack = ["rust"]
+
+        [commands.test]
+        read_only = true
+        writable = ["coverage"]
+        tmpfs = ["../tmp"]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg
+            .validate()
+            .expect_err("tmpfs outside the workspace must fail");
+        assert!(err.to_string().contains("invalid tmpfs path '../tmp'"));
+
+        let text = r#"
+        [project]
+        name = "commands"
+        stack = ["rust"]
+
+        [commands."check:msrv"]
+        image = " "
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("empty image must fail");
+        assert!(err.to_string().contains("empty image for 'check:msrv'"));
+
+        let text = r#"
+        [project]
+        name = "commands"
+        stack = ["rust"]
+
+        [commands."fmt:fix"]
+        require_clean = false
+        protected_branches = ["main", ""]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg
+            .validate()
+            .expect_err("empty protected branch must fail");
+        assert!(err
+            .to_string()
+            .contains("empty protected branch for 'fmt:fix'"));
+    }
+
+    #[test]
+    fn validates_visual_suites() {
+        let parse = |extension: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"node\"]\n\n{extension}"
+            ))
+            .expect("Valid TOML parse")
+        };
+        assert_eq!(parse("").visual(), VisualConfig::default());
+
+        let cfg = parse("[extensions.node]\nsource = \"builtin\"\n\n[extensions.node.visual]\n");
+        cfg.validate().unwrap();
+        assert_eq!(cfg.visual().script, "test:visual");
+
+        let cfg = parse("[extensions.node]\nsource = \"builtin\"\n\n[extensions.node.visual]\nscript = \"test-storybook\"\nthreshold = 0.01\n");
+        cfg.validate().unwrap();
+        let visual = cfg.visual();
+        assert_eq!(visual.script, "test-storybook");
+        assert_eq!(visual.threshold, Some(0.01));
+        assert!(visual.diffs.contains(&"**/__diff_output__/**".to_string()));
+
+        let err = parse("[extensions.node]\nsource = \"builtin\"\n\n[extensions.node.visual]\nthreshold = 5.0\n")
+            .validate()
+            .expect_err("threshold above 1 must fail");
+        assert!(
+            err.to_string()
+                .contains("threshold 5 must be between 0 and 1"),
+            "{err}"
+        );
 
-        let err = toml::from_str::<DevflowConfig>(text).expect_err("must reject unknown key");
-        assert!(err.to_string().contains("random_top"));
+        let err = parse("[extensions.rust]\nsource = \"builtin\"\n\n[extensions.rust.visual]\n")
+            .validate()
+            .expect_err("visual outside node must fail");
+        assert!(err
+            .to_string()
+            .contains("visual is not supported by extension 'rust'"));
     }
 
     #[test]
-    fn rejects_unknown_project_key() {
-        // Ensures that the [project] section does not allow unauthorized keys.
+    fn validates_remote_caches() {
+        let parse = |cache: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"node\"]\n\n[extensions.node]\nsource = \"builtin\"\n\n[extensions.node.remote_cache]\n{cache}"
+            ))
+            .expect("Valid TOML parse")
+        };
+        let cfg = parse("url = \"https://cache.example.com\"\nteam = \"platform\"\n");
+        cfg.validate().unwrap();
+        let cache = cfg.remote_cache().unwrap();
+        assert_eq!(cache.team.as_deref(), Some("platform"));
+        assert_eq!(cache.token_env, "DEVFLOW_REMOTE_CACHE_TOKEN");
+
+        let err = parse("url = \"cache.example.com\"\n")
+            .validate()
+            .expect_err("url without scheme must fail");
+        assert!(
+            err.to_string()
+                .contains("url 'cache.example.com' must be an http(s) URL"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn validates_concurrency_limits() {
+        let parse = |extra: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n{extra}"
+            ))
+            .expect("Valid TOML parse")
+        };
+        let cfg = parse("[concurrency]\nio = 8\n\n[commands.\"lint:static\"]\nresource = \"io\"\n");
+        cfg.validate().unwrap();
+        let limits = cfg.concurrency.unwrap();
+        assert_eq!(limits.limit(ResourceClass::Cpu), 1);
+        assert_eq!(limits.limit(ResourceClass::Io), 8);
+        assert_eq!(
+            cfg.commands["lint:static"].resource,
+            Some(ResourceClass::Io)
+        );
+
+        let err = parse("[concurrency]\ncpu = 0\n")
+            .validate()
+            .expect_err("zero limit must fail");
+        assert!(
+            err.to_string()
+                .contains("[concurrency] cpu must be at least 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn validates_badge_settings() {
+        let parse = |badge: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[ci.badge]\n{badge}"
+            ))
+            .expect("Valid TOML parse")
+        };
+        let cfg = parse("coverage = \"coverage/lcov.info\"\n");
+        cfg.validate().unwrap();
+        assert_eq!(cfg.ci.badge.unwrap().publish, BadgePublish::GhPages);
+        parse("publish = \"gist\"\ngist = \"aa11\"\n")
+            .validate()
+            .unwrap();
+
+        for (badge, message) in [
+            ("publish = \"gist\"\n", "requires gist"),
+            ("gist = \"aa11\"\n", "only used with publish = \"gist\""),
+            (
+                "publish = \"gist\"\ngist = \"me/aa11\"\n",
+                "is not a gist id",
+            ),
+            (
+                "coverage = \"/tmp/lcov.info\"\n",
+                "relative to the workspace",
+            ),
+        ] {
+            let err = parse(badge).validate().expect_err(badge);
+            assert!(err.to_string().contains("invalid [ci.badge]"), "{err}");
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn validates_job_steps() {
+        let parse = |jobs: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[ci.jobs.verify]\n{jobs}"
+            ))
+            .expect("Valid TOML parse")
+        };
+        let cfg = parse(
+            "pre_steps = [{ uses = \"actions/setup-java@v4\", with = { java-version = \"21\" } }]\npost_steps = [{ run = \"make report\", if = \"always()\", continue-on-error = true }]\n",
+        );
+        cfg.validate().unwrap();
+        let steps = &cfg.ci.jobs["verify"];
+        assert_eq!(steps.pre_steps[0].with["java-version"], "21");
+        assert_eq!(steps.post_steps[0].condition.as_deref(), Some("always()"));
+        assert_eq!(steps.post_steps[0].continue_on_error, Some(true));
+
+        for jobs in [
+            "pre_steps = [{ name = \"Nothing\" }]\n",
+            "post_steps = [{ run = \"true\" }, { uses = \"a/b@v1\", run = \"true\" }]\n",
+        ] {
+            let err = parse(jobs).validate().expect_err(jobs);
+            assert!(
+                err.to_string()
+                    .contains("needs exactly one of 'uses' and 'run'"),
+                "{err}"
+            );
+        }
+        assert!(toml::from_str::<DevflowConfig>(
+            "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[ci.jobs.verify]\npre_steps = [{ runs = \"x\" }]\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn validates_action_pins() {
+        let parse = |actions: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[ci.actions]\n{actions}"
+            ))
+            .expect("Valid TOML parse")
+        };
+        let cfg = parse(
+            "\"actions/checkout\" = \"11bd71901bbe5b1630ceea73d27597364c9af683\"\n\"actions/cache\" = \"v4.2.0\"\n",
+        );
+        cfg.validate().unwrap();
+        assert_eq!(cfg.ci.actions["actions/cache"], "v4.2.0");
+
+        for (actions, message) in [
+            ("checkout = \"v4\"\n", "must be an action repository"),
+            (
+                "\"actions/cache/save\" = \"v4\"\n",
+                "must be an action repository",
+            ),
+            ("\"actions/checkout\" = \"\"\n", "must pin a tag"),
+            (
+                "\"actions/checkout\" = \"actions/checkout@v4\"\n",
+                "must pin a tag",
+            ),
+        ] {
+            let err = parse(actions).validate().expect_err(actions);
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn validates_rust_targets() {
+        let parse = |extension: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n{extension}"
+            ))
+            .expect("Valid TOML parse")
+        };
+        let cfg =
+            parse("[extensions.rust]\nsource = \"builtin\"\nwasm_targets = [\"wasm32-wasip1\"]\n");
+        cfg.validate().unwrap();
+        assert_eq!(
+            cfg.extension("rust").unwrap().wasm_targets,
+            ["wasm32-wasip1"]
+        );
+
+        let err = parse(
+            "[extensions.rust]\nsource = \"builtin\"\nno_std_targets = [\"thumbv7em none\"]\n",
+        )
+        .validate()
+        .expect_err("target with a space must fail");
+        assert!(err
+            .to_string()
+            .contains("invalid target 'thumbv7em none' in no_std_targets"));
+
+        let err = parse("[extensions.node]\nsource = \"builtin\"\nwasm_targets = [\"wasm32-unknown-unknown\"]\n")
+            .validate()
+            .expect_err("wasm_targets outside rust must fail");
+        assert!(err
+            .to_string()
+            .contains("wasm_targets is not supported by extension 'node'"));
+    }
+
+    #[test]
+    fn validates_feature_matrices() {
+        let parse = |matrix: &str| {
+            toml::from_str::<DevflowConfig>(&format!(
+                "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[extensions.rust]\nsource = \"builtin\"\n\n[extensions.rust.feature_matrix]\n{matrix}\n"
+            ))
+            .expect("Valid TOML parse")
+        };
+        let cfg = parse("strategy = \"each-feature\"\npartitions = 3\ncommand = \"check\"");
+        cfg.validate().unwrap();
+        let matrix = cfg.feature_matrix();
+        assert_eq!(matrix.strategy, FeatureStrategy::EachFeature);
+        assert_eq!(matrix.command, FeatureCommand::Check);
+        assert_eq!(matrix.shards(), ["1/3", "2/3", "3/3"]);
+
+        let cfg = parse("sets = [[], [\"serde\", \"tokio\"]]");
+        let matrix = cfg.feature_matrix();
+        assert_eq!(matrix.shards(), ["none", "serde+tokio"]);
+        assert_eq!(matrix.set("serde+tokio").unwrap(), ["serde", "tokio"]);
+        assert!(DevflowConfig::default()
+            .feature_matrix()
+            .shards()
+            .is_empty());
+
+        for (matrix, message) in [
+            ("partitions = 0", "at least 1"),
+            (
+                "sets = [[\"serde\"]]\npartitions = 2",
+                "cannot be combined with sets",
+            ),
+            (
+                "sets = [[\"serde\"], [\"serde\"]]",
+                "'serde' is listed twice",
+            ),
+            ("exclude = [\"a,b\"]", "invalid feature name 'a,b'"),
+        ] {
+            let err = parse(matrix).validate().expect_err(matrix);
+            assert!(err.to_string().contains(message), "{matrix}: {err}");
+        }
+    }
+
+    #[test]
+    fn parses_output_sizes() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512KB").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("50MB").unwrap(), 50 << 20);
+        assert_eq!(parse_size("1 gib").unwrap(), 1 << 30);
+        for invalid in ["", "0MB", "MB", "5TB", "-1MB", "1.5MB"] {
+            assert!(parse_size(invalid).is_err(), "{invalid} must be rejected");
+        }
+
         let text = r#"
         [project]
-        name = "demo"
+        name = "commands"
         stack = ["rust"]
-        owner = "team"
 
-        [targets]
-        pr = ["fmt:check"]
+        [commands."test:unit"]
+        max_output = "lots"
         "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("invalid size must fail");
+        assert!(err.to_string().contains("invalid size 'lots'"));
+    }
 
-        let err =
-            toml::from_str::<DevflowConfig>(text).expect_err("must reject unknown project key");
-        assert!(err.to_string().contains("owner"));
+    #[test]
+    fn parses_readiness_waits() {
+        let text = r#"
+        [project]
+        name = "waits"
+        stack = ["rust"]
+
+        [waits.postgres]
+        tcp = "localhost:5432"
+        timeout = "45s"
+
+        [waits.api]
+        http = "http://localhost:8080/health"
+        interval = "250ms"
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        cfg.validate().expect("waits are valid");
+        let postgres = &cfg.waits["postgres"];
+        assert_eq!(postgres.probe().unwrap(), Probe::Tcp("localhost:5432"));
+        assert_eq!(postgres.timeout().unwrap(), Duration::from_secs(45));
+        assert_eq!(postgres.interval().unwrap(), Duration::from_secs(1));
+        assert_eq!(
+            cfg.waits["api"].interval().unwrap(),
+            Duration::from_millis(250)
+        );
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert!(parse_duration("0s").is_err());
+
+        for (probe, message) in [
+            ("tcp = \"localhost\"", "invalid tcp address 'localhost'"),
+            (
+                "tcp = \"db:5432\"\nhttp = \"http://db\"",
+                "more than one probe",
+            ),
+            ("timeout = \"30s\"", "no probe"),
+        ] {
+            let text =
+                format!("[project]\nname = \"w\"\nstack = [\"rust\"]\n[waits.db]\n{probe}\n");
+            let cfg = toml::from_str::<DevflowConfig>(&text).expect("Valid TOML parse");
+            let err = cfg.validate().expect_err("invalid wait must fail");
+            assert!(err.to_string().contains(message), "{err}");
+        }
     }
 
     #[test]
-    fn unit_test_validate_allows_custom_stacks() {
+    fn parses_database_fixtures() {
         let text = r#"
         [project]
-        name = "valid-stack"
-        stack = ["ruby"]
+        name = "fixtures"
+        stack = ["rust"]
+
+        [fixtures.db]
+        engine = "postgres"
+        migrate = ["sqlx migrate run"]
+
+        [fixtures.legacy]
+        engine = "mysql"
+        image = "mysql:5.7"
+        env = "LEGACY_DATABASE_URL"
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        cfg.validate().expect("fixtures are valid");
+        assert_eq!(cfg.fixtures["db"].env_var(), "DATABASE_URL");
+        assert_eq!(cfg.fixtures["db"].image(), "postgres:16-alpine");
+        assert_eq!(
+            cfg.fixtures["db"].timeout().unwrap(),
+            Duration::from_secs(60)
+        );
+        assert_eq!(cfg.fixtures["legacy"].engine, FixtureEngine::Mysql);
+        assert_eq!(cfg.fixtures["legacy"].image(), "mysql:5.7");
+
+        let text = r#"
+        [project]
+        name = "fixtures"
+        stack = ["rust"]
+
+        [fixtures.main_db]
+        engine = "postgres"
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("underscore must fail");
+        assert!(err.to_string().contains("invalid fixture name 'main_db'"));
+    }
+
+    #[test]
+    fn parses_reporter_plugins() {
+        let text = r#"
+        [project]
+        name = "reporters"
+        stack = ["rust"]
+
+        [reporters.audit]
+        command = "./scripts/devflow-audit"
+        output = true
+
+        [reporters.empty]
+        command = " "
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert!(cfg.reporters["audit"].output);
+        assert!(cfg.reporters["audit"].args.is_empty());
+        let err = cfg.validate().expect_err("empty command must fail");
+        assert!(err
+            .to_string()
+            .contains("empty command for reporter 'empty'"));
+    }
+
+    #[test]
+    fn validate_rejects_invalid_runner_overrides() {
+        let text = r#"
+        [project]
+        name = "runners"
+        stack = ["rust"]
+
+        [ci.runners]
+        "tests:unit" = "self-hosted"
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("unknown command must fail");
+        assert!(err
+            .to_string()
+            .contains("invalid command 'tests:unit' in [ci.runners]"));
+
+        let text = r#"
+        [project]
+        name = "runners"
+        stack = ["rust"]
+
+        [ci.runners]
+        "test:unit" = []
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("empty labels must fail");
+        assert!(err
+            .to_string()
+            .contains("empty runner label for 'test:unit'"));
+    }
+
+    #[test]
+    fn validate_rejects_invalid_extension_version_requirement() {
+        let text = r#"
+        [project]
+        name = "versions"
+        stack = ["python"]
+
+        [extensions.python]
+        source = "path"
+        version = "at least three"
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("invalid requirement must fail");
+        assert!(err
+            .to_string()
+            .contains("invalid version requirement 'at least three' for extension 'python'"));
+    }
+
+    #[test]
+    fn parses_conflict_policy_and_priority() {
+        let text = r#"
+        [project]
+        name = "conflicts"
+        stack = ["rust", "custom"]
+
+        [runtime]
+        conflict = "first"
+        env_conflict = "error"
+
+        [extensions.custom]
+        source = "path"
+        priority = 10
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        assert_eq!(cfg.runtime.conflict, ConflictPolicy::First);
+        assert_eq!(cfg.runtime.env_conflict, EnvConflictPolicy::Error);
+        assert_eq!(cfg.extensions.unwrap()["custom"].priority, 10);
+
+        let cfg = toml::from_str::<DevflowConfig>("[project]\nname = \"d\"\nstack = []\n")
+            .expect("Valid TOML parse");
+        assert_eq!(cfg.runtime.conflict, ConflictPolicy::All);
+    }
+
+    #[test]
+    fn resolves_auto_stacks_from_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.path().join("go.mod"), "").unwrap();
+
+        let mut cfg = toml::from_str::<DevflowConfig>("[project]\nname = \"d\"\n").unwrap();
+        cfg.resolve_auto_stacks(dir.path()).unwrap();
+        assert_eq!(cfg.project.stack, vec!["rust", "go"]);
+        assert_eq!(cfg.project.detected_stacks, vec!["rust", "go"]);
+
+        let mut cfg = toml::from_str::<DevflowConfig>(
+            "[project]\nname = \"d\"\nstack = [\"custom\", \"auto\", \"rust\"]\n",
+        )
+        .unwrap();
+        cfg.resolve_auto_stacks(dir.path()).unwrap();
+        assert_eq!(cfg.project.stack, vec!["custom", "rust", "go"]);
+        assert_eq!(cfg.project.detected_stacks, vec!["go"]);
+
+        let mut cfg =
+            toml::from_str::<DevflowConfig>("[project]\nname = \"d\"\nstack = [\"node\"]\n")
+                .unwrap();
+        cfg.resolve_auto_stacks(dir.path()).unwrap();
+        assert_eq!(cfg.project.stack, vec!["node"]);
+        assert!(cfg.project.detected_stacks.is_empty());
+
+        let empty = tempfile::tempdir().unwrap();
+        let mut cfg = toml::from_str::<DevflowConfig>("[project]\nname = \"d\"\n").unwrap();
+        let err = cfg.resolve_auto_stacks(empty.path()).unwrap_err();
+        assert!(err.to_string().contains("no stacks detected"));
+    }
+
+    #[test]
+    fn parses_buildkit_cache_settings() {
+        let text = r#"
+        [project]
+        name = "cached"
+        stack = ["rust"]
+
+        [container.buildkit]
+        cache_ref = "ghcr.io/acme/app-ci:buildcache"
+        cache_to = ["type=gha,mode=max"]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let buildkit = cfg.container.unwrap().buildkit;
+        assert!(buildkit.is_configured());
+        assert_eq!(
+            buildkit.cache_from_specs(),
+            vec!["type=registry,ref=ghcr.io/acme/app-ci:buildcache"]
+        );
+        assert_eq!(
+            buildkit.cache_to_specs(),
+            vec![
+                "type=registry,ref=ghcr.io/acme/app-ci:buildcache,mode=max",
+                "type=gha,mode=max"
+            ]
+        );
+        assert!(!BuildkitConfig::default().is_configured());
+    }
+
+    #[test]
+    fn parses_remote_runtime_profile() {
+        let text = r#"
+        [project]
+        name = "remote"
+        stack = ["rust"]
+
+        [runtime]
+        profile = "remote"
+
+        [runtime.remote]
+        host = "builder01"
+        user = "ci"
+        artifacts = ["target/release"]
         "#;
 
         let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
         assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.runtime.profile, RuntimeProfile::Remote);
+        let remote = cfg.runtime.remote.unwrap();
+        assert_eq!(remote.host, "builder01");
+        assert_eq!(remote.user.as_deref(), Some("ci"));
+        assert_eq!(remote.artifacts, vec!["target/release"]);
     }
 
     #[test]
-    fn unit_test_validate_rejects_invalid_commands() {
+    fn validate_requires_remote_host_for_remote_profile() {
         let text = r#"
         [project]
-        name = "bad-commands"
+        name = "remote"
         stack = ["rust"]
 
-        [targets]
-        pr = ["build:debug", "not-a-command:selector"]
+        [runtime]
+        profile = "remote"
         "#;
 
         let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
-        let err = cfg
-            .validate()
-            .expect_err("Must fail validation for invalid command format");
+        let err = cfg.validate().expect_err("missing host must fail");
+        assert!(err.to_string().contains("requires [runtime.remote]"));
+    }
 
-        // CommandRef from_str returns an error because primary command is unknown
+    #[test]
+    fn validate_rejects_option_like_remote_hosts() {
+        for (field, remote) in [
+            ("host", r#"host = "-oProxyCommand=touch pwned""#),
+            ("host", r#"host = "ci@builder01""#),
+            ("user", "host = \"builder01\"\nuser = \"-lroot\""),
+        ] {
+            let text = format!(
+                r#"
+                [project]
+                name = "remote"
+                stack = ["rust"]
+
+                [runtime]
+                profile = "remote"
+
+                [runtime.remote]
+                {remote}
+                "#
+            );
+
+            let cfg = toml::from_str::<DevflowConfig>(&text).expect("Valid TOML parse");
+            let err = cfg
+                .validate()
+                .expect_err("option-like remote value must fail");
+            assert!(err.to_string().contains(&format!("runtime.remote.{field}")));
+        }
+    }
+
+    #[test]
+    fn parses_the_hermetic_path() {
+        let text = r#"
+        [project]
+        name = "path"
+        stack = ["rust"]
+
+        [runtime]
+        path = "hermetic"
+        path_dirs = ["~/.local/bin", "/opt/tools/bin"]
+        "#;
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        cfg.validate().expect("valid path_dirs");
+        assert_eq!(cfg.runtime.path, PathMode::Hermetic);
+
+        let cfg = toml::from_str::<DevflowConfig>(&text.replace("/opt/tools/bin", "/a:/b"))
+            .expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("a path list must fail");
         assert!(err
             .to_string()
-            .contains("invalid command 'not-a-command:selector'"));
+            .contains("invalid [runtime] path_dirs entry '/a:/b'"));
+    }
+
+    #[test]
+    fn validate_rejects_escaping_container_outputs() {
+        let text = r#"
+        [project]
+        name = "hermetic"
+        stack = ["rust"]
+
+        [container]
+        outputs = ["target", "../elsewhere"]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("escaping output must fail");
+        assert!(err
+            .to_string()
+            .contains("invalid container output '../elsewhere'"));
+    }
+
+    #[test]
+    fn validate_rejects_extra_args_managed_by_devflow() {
+        let text = r#"
+        [project]
+        name = "privileged"
+        stack = ["rust"]
+
+        [container]
+        entrypoint = ""
+        extra_args = ["--privileged", "--add-host=db:10.0.0.5"]
+
+        [container.labels]
+        "com.example.team" = "platform"
+        "#;
+        let mut cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        cfg.validate().expect("unmanaged flags pass");
+        assert_eq!(
+            cfg.container.as_ref().unwrap().entrypoint.as_deref(),
+            Some("")
+        );
+
+        for (arg, message) in [
+            ("--network=host", "conflicts with a flag managed by Devflow"),
+            ("--entrypoint", "[container] entrypoint"),
+            ("-v", "conflicts with a flag managed by Devflow"),
+        ] {
+            let extra_args = &mut cfg.container.as_mut().unwrap().extra_args;
+            extra_args.push(arg.to_string());
+            let err = format!("{:#}", cfg.validate().expect_err(arg));
+            assert!(err.contains(message), "{arg}: {err}");
+            cfg.container.as_mut().unwrap().extra_args.pop();
+        }
+    }
+
+    #[test]
+    fn validate_rejects_malformed_container_platforms() {
+        let text = r#"
+        [project]
+        name = "multiarch"
+        stack = ["rust"]
+
+        [container]
+        platforms = ["linux/amd64", "arm64"]
+        "#;
+
+        let cfg = toml::from_str::<DevflowConfig>(text).expect("Valid TOML parse");
+        let err = cfg.validate().expect_err("bare arch must fail");
+        assert!(err
+            .to_string()
+            .contains("invalid container platform 'arm64'"));
+    }
+
+    #[test]
+    fn integration_test_extends_merges_a_preset_and_locates_its_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let preset = dir.path().join("preset.toml");
+        std::fs::write(
+            &preset,
+            "[targets]\npr = [\"fmt:check\"]\nmain = [\"build:release\"]\n\n[ci.runners]\ndefault = \"large\"\n",
+        )
+        .unwrap();
+        let config_path = dir.path().join("devflow.toml");
+        let path = config_path.to_str().unwrap();
+        std::fs::write(
+            &config_path,
+            "extends = \"preset.toml\"\n\n[project]\nname = \"svc\"\nstack = [\"rust\"]\n\n[targets]\npr = [\"test:unit\"]\n",
+        )
+        .unwrap();
+        let cfg = DevflowConfig::load_from_file(path).unwrap();
+        assert_eq!(cfg.extends.as_deref(), Some("preset.toml"));
+        assert_eq!(cfg.preset.as_ref().unwrap().commit, None);
+        assert_eq!(
+            cfg.targets.profiles["pr"],
+            vec![TargetEntry::from("test:unit")]
+        );
+        assert_eq!(
+            cfg.targets.profiles["main"],
+            vec![TargetEntry::from("build:release")]
+        );
+        assert_eq!(
+            cfg.ci.runners.default,
+            RunnerSpec::Label("large".to_string())
+        );
+
+        std::fs::write(
+            &preset,
+            "[targets]\nmain = [{ cmd = \"build:release\", timeout = \"soon\" }]\n",
+        )
+        .unwrap();
+        let err = DevflowConfig::load_from_file(path).unwrap_err();
+        let text = format!("{err:#}");
+        assert!(
+            text.contains(&format!("--> {}:2:", preset.display())),
+            "{text}"
+        );
+
+        std::fs::write(&preset, "extends = \"other.toml\"\n").unwrap();
+        let err = DevflowConfig::load_from_file(path).unwrap_err();
+        assert!(
+            err.to_string().contains("cannot extend another preset"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn integration_test_newer_schema_version_loads_leniently() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("devflow.toml");
+        let path = config_path.to_str().unwrap();
+        std::fs::write(
+            &config_path,
+            "[project]\nname = \"next\"\nstack = [\"rust\"]\nowners = [\"a\"]\n",
+        )
+        .unwrap();
+        assert!(DevflowConfig::load_from_file(path).is_err());
+        let (_, warnings) = DevflowConfig::load_with_options(
+            path,
+            &LoadOptions {
+                lenient: true,
+                ..LoadOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(warnings[0].key, "project.owners");
+
+        std::fs::write(
+            &config_path,
+            "schema_version = 2\n[project]\nname = \"next\"\nstack = [\"rust\"]\nowners = [\"a\"]\n",
+        )
+        .unwrap();
+        let (cfg, warnings) =
+            DevflowConfig::load_with_options(path, &LoadOptions::default()).unwrap();
+        assert_eq!(cfg.schema_version, Some(2));
+        assert_eq!(warnings.len(), 1);
     }
 
     #[test]
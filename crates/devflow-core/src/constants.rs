@@ -3,6 +3,25 @@
 /// The filename for Devflow's primary configuration.
 pub const CONFIG_FILE: &str = "devflow.toml";
 
+/// Written next to `devflow.toml`: the commit an `extends` preset resolved to.
+pub const PRESET_LOCK_FILE: &str = "devflow.lock";
+
+/// Cache directory relative to the project root, unless `[cache] root` or
+/// `DWF_CACHE_ROOT` say otherwise.
+pub const DEFAULT_CACHE_ROOT: &str = ".cache/devflow";
+
+/// The `devflow.toml` schema version this build understands. Configs declaring
+/// a newer `schema_version` are loaded leniently.
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+/// The extension protocol version this build speaks; an extension's
+/// `api_version` must match it.
+pub const EXTENSION_API_VERSION: u32 = 1;
+
+/// Per-directory ignore file read alongside `.gitignore` when walking the
+/// project tree.
+pub const IGNORE_FILE: &str = ".devflowignore";
+
 /// The manifest file for Rust projects.
 pub const MANIFEST_RUST: &str = "Cargo.toml";
 
@@ -10,6 +29,32 @@ pub const MANIFEST_RUST: &str = "Cargo.toml";
 pub const MANIFEST_NODE: &str = "package.json";
 pub const MANIFEST_TSC: &str = "tsconfig.json";
 
+/// The manifest files for Go and Python projects.
+pub const MANIFEST_GO: &str = "go.mod";
+pub const MANIFEST_PYTHON: &str = "pyproject.toml";
+
+/// The file extension of Terraform configuration, which has no manifest: a
+/// directory with a `.tf` file is a root module.
+pub const TERRAFORM_EXTENSION: &str = "tf";
+
+/// `[project] stack` entry requesting detection from the manifests above.
+pub const STACK_AUTO: &str = "auto";
+
 /// Standard build system files for custom stacks.
 pub const TARGET_CUSTOM_JUST: &str = "justfile";
 pub const TARGET_CUSTOM_MAKE: &str = "Makefile";
+
+/// Workspace-relative directory failed commands keep their reports in, e.g.
+/// end-to-end test screenshots; CI uploads it as a job artifact.
+pub const FAILURE_ARTIFACTS_DIR: &str = ".devflow/artifacts";
+
+/// Workspace-relative file listing the project's commands for shell
+/// completion (`dwf completions`), refreshed after extension discovery.
+pub const COMPLETION_CACHE_FILE: &str = ".devflow/completions";
+
+/// The Dockerfile from which the CI container image is built.
+pub const CI_DOCKERFILE: &str = "Dockerfile.devflow";
+
+/// Development environment definitions detected by the `auto` runtime env.
+pub const NIX_FLAKE: &str = "flake.nix";
+pub const DEVCONTAINER_FILE: &str = ".devcontainer/devcontainer.json";
@@ -0,0 +1,340 @@
+//! Source-located config errors.
+//!
+//! A [`ConfigDiagnostic`] names the offending key (e.g. `project.stack[1]`);
+//! once located against the `devflow.toml` text it renders a code frame:
+//!
+//! ```text
+//! unsupported stack 'ruby'
+//!  --> devflow.toml:3:18
+//!   |
+//! 3 | stack = ["rust", "ruby"]
+//!   |                  ^^^^^^
+//!   = hint: supported: custom, node, rust; did you mean to add a subprocess extension?
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+
+use toml_edit::{Document, Item};
+
+/// A config error tied to a key path, optionally located in the source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiagnostic {
+    /// The error message.
+    pub message: String,
+    /// Dotted path of the offending key, e.g. `targets.pr[0]`.
+    pub key: Option<String>,
+    /// Suggested fix.
+    pub hint: Option<String>,
+    location: Option<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Location {
+    file: String,
+    line: usize,
+    column: usize,
+    source_line: String,
+    width: usize,
+}
+
+impl ConfigDiagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            key: None,
+            hint: None,
+            location: None,
+        }
+    }
+
+    /// Attaches the dotted key path the error is about.
+    pub fn at_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Resolves the key against `text` (the contents of `file`).
+    ///
+    /// A key that is absent points at its deepest present ancestor.
+    pub fn locate(self, file: &str, text: &str) -> Self {
+        let span = self.key.as_deref().and_then(|key| {
+            let doc = Document::parse(text).ok()?;
+            deepest_span(doc.as_item(), &parse_key(key))
+        });
+        match span {
+            Some(span) => self.at_span(file, text, span),
+            None => self,
+        }
+    }
+
+    /// Locates the diagnostic at byte range `span` of `text`.
+    pub(crate) fn at_span(mut self, file: &str, text: &str, span: Range<usize>) -> Self {
+        let start = span.start.min(text.len());
+        let line_start = text[..start].rfind('\n').map_or(0, |nl| nl + 1);
+        let line_end = text[start..].find('\n').map_or(text.len(), |nl| start + nl);
+        let source_line = text[line_start..line_end].trim_end_matches('\r');
+        let end = span.end.clamp(start, line_end);
+        let (line, column) = line_column(text, start);
+        self.location = Some(Location {
+            file: file.to_string(),
+            line,
+            column,
+            source_line: source_line.to_string(),
+            width: text[start..end].chars().count().max(1),
+        });
+        self
+    }
+
+    /// 1-based `(line, column)` once located.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.location.as_ref().map(|l| (l.line, l.column))
+    }
+}
+
+/// Locates a [`ConfigDiagnostic`] carried by `err` in the config file at
+/// `file`; other errors are returned unchanged.
+pub fn locate_in_file(err: anyhow::Error, file: &str) -> anyhow::Error {
+    match err.downcast::<ConfigDiagnostic>() {
+        Ok(diagnostic) => match std::fs::read_to_string(file) {
+            Ok(text) => diagnostic.locate(file, &text).into(),
+            Err(_) => diagnostic.into(),
+        },
+        Err(err) => err,
+    }
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        let Some(loc) = &self.location else {
+            if let Some(hint) = &self.hint {
+                write!(f, "\n  = hint: {hint}")?;
+            }
+            return Ok(());
+        };
+        let gutter = " ".repeat(loc.line.to_string().len());
+        let indent: String = loc
+            .source_line
+            .chars()
+            .take(loc.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        write!(
+            f,
+            "\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {}\n{gutter} | {}{}",
+            loc.file,
+            loc.line,
+            loc.column,
+            loc.line,
+            loc.source_line,
+            indent,
+            "^".repeat(loc.width)
+        )?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n{gutter} = hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigDiagnostic {}
+
+/// A step into a TOML document: a table key or an array index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Splits `targets.pr[0].paths` into segments.
+pub(crate) fn parse_key(key: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for part in key.split('.') {
+        let (name, mut rest) = part.split_once('[').map_or((part, ""), |(n, r)| (n, r));
+        if !name.is_empty() {
+            segments.push(Segment::Key(name.to_string()));
+        }
+        while let Some((index, tail)) = rest.split_once(']') {
+            if let Ok(index) = index.parse() {
+                segments.push(Segment::Index(index));
+            }
+            rest = tail.strip_prefix('[').unwrap_or(tail);
+        }
+    }
+    segments
+}
+
+/// Renders segments as a dotted key, e.g. `targets.pr[0].paths`.
+pub(crate) fn dotted(path: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(name) if out.is_empty() => out.push_str(name),
+            Segment::Key(name) => {
+                out.push('.');
+                out.push_str(name);
+            }
+            Segment::Index(index) => out.push_str(&format!("[{index}]")),
+        }
+    }
+    out
+}
+
+/// Steps from `item` into `segment`.
+pub(crate) fn child<'a>(item: &'a Item, segment: &Segment) -> Option<&'a Item> {
+    match segment {
+        Segment::Key(name) => item.get(name.as_str()),
+        Segment::Index(index) => item.get(*index),
+    }
+}
+
+/// The span of the key that names the last segment of `path`.
+pub(crate) fn key_span(item: &Item, path: &[Segment]) -> Option<Range<usize>> {
+    let (last, parents) = path.split_last()?;
+    let mut parent = item;
+    for segment in parents {
+        parent = child(parent, segment)?;
+    }
+    match last {
+        Segment::Key(name) => parent.as_table_like()?.key(name)?.span(),
+        Segment::Index(_) => None,
+    }
+}
+
+/// The span of the value at `path`, or of the deepest ancestor present.
+fn deepest_span(root: &Item, path: &[Segment]) -> Option<Range<usize>> {
+    let mut item = root;
+    let mut best = None;
+    for (depth, segment) in path.iter().enumerate() {
+        let Some(next) = child(item, segment) else {
+            break;
+        };
+        best = next
+            .as_value()
+            .and_then(|v| v.span())
+            .or_else(|| key_span(root, &path[..=depth]))
+            .or(best);
+        item = next;
+    }
+    best
+}
+
+/// The closest of `known`, if `unknown` is plausibly a misspelling of it.
+pub(crate) fn did_you_mean<'a>(unknown: &str, known: &'a [String]) -> Option<&'a str> {
+    let threshold = (unknown.chars().count() / 3).max(1);
+    known
+        .iter()
+        .map(|k| (levenshtein(unknown, k), k))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, k)| k.as_str())
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            let next = (row[j] + 1).min(row[j + 1] + 1).min(prev + cost);
+            prev = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// 1-based line and column of byte `offset` in `text`.
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rfind('\n').map_or(before.chars().count(), |nl| {
+        before[nl + 1..].chars().count()
+    }) + 1;
+    (line, column)
+}
+
+/// Splits serde's "unknown field `x`, expected `a` or `b`" message.
+pub(crate) fn unknown_field(message: &str) -> Option<(String, Vec<String>)> {
+    let rest = message.strip_prefix("unknown field `")?;
+    let (unknown, rest) = rest.split_once('`')?;
+    let expected = rest
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect();
+    Some((unknown.to_string(), expected))
+}
+
+/// Converts a TOML deserialization error into a located diagnostic, with a
+/// "did you mean" hint for misspelled keys.
+pub(crate) fn from_toml_error(err: &toml::de::Error, file: &str, text: &str) -> ConfigDiagnostic {
+    let mut diagnostic = ConfigDiagnostic::new(err.message());
+    if let Some((unknown, expected)) = unknown_field(err.message()) {
+        if let Some(known) = did_you_mean(&unknown, &expected) {
+            diagnostic = diagnostic.with_hint(format!("did you mean '{known}'?"));
+        }
+    }
+    match err.span() {
+        Some(span) => diagnostic.at_span(file, text, span),
+        None => diagnostic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "[project]\nname = \"demo\"\nstack = [\"rust\", \"ruby\"]\n\n[targets]\npr = [\"fmt:check\"]\n";
+
+    #[test]
+    fn renders_code_frame_for_array_element() {
+        let diagnostic = ConfigDiagnostic::new("unsupported stack 'ruby'")
+            .at_key("project.stack[1]")
+            .with_hint("supported: custom, node, rust")
+            .locate("devflow.toml", TEXT);
+        assert_eq!(diagnostic.position(), Some((3, 18)));
+        assert_eq!(
+            diagnostic.to_string(),
+            "unsupported stack 'ruby'\n --> devflow.toml:3:18\n  |\n3 | stack = [\"rust\", \"ruby\"]\n  |                  ^^^^^^\n  = hint: supported: custom, node, rust"
+        );
+    }
+
+    #[test]
+    fn missing_keys_point_at_the_nearest_ancestor() {
+        let diagnostic = ConfigDiagnostic::new("missing")
+            .at_key("targets.main")
+            .locate("devflow.toml", TEXT);
+        assert_eq!(diagnostic.position(), Some((5, 2)));
+    }
+
+    #[test]
+    fn parses_key_paths() {
+        assert_eq!(
+            parse_key("targets.pr[0].paths"),
+            vec![
+                Segment::Key("targets".to_string()),
+                Segment::Key("pr".to_string()),
+                Segment::Index(0),
+                Segment::Key("paths".to_string()),
+            ]
+        );
+        assert_eq!(dotted(&parse_key("a.b[2][1]")), "a.b[2][1]");
+    }
+
+    #[test]
+    fn suggests_only_close_matches() {
+        let known = vec!["profile".to_string(), "remote".to_string()];
+        assert_eq!(did_you_mean("profil", &known), Some("profile"));
+        assert_eq!(did_you_mean("sandbox", &known), None);
+    }
+}
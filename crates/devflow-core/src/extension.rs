@@ -1,10 +1,14 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use anyhow::{bail, Result};
 
-use crate::command::CommandRef;
+use crate::command::{CommandRef, PrimaryCommand, ResourceClass};
 use crate::config::DevflowConfig;
+use crate::diagnostic::ConfigDiagnostic;
+use crate::problem::ProblemMatcher;
+use crate::tool::ToolRequirement;
 use tracing::{debug, instrument};
 
 pub mod subprocess;
@@ -21,8 +25,64 @@ pub struct ExecutionAction {
     pub env: HashMap<String, String>,
 }
 
+/// The operating system a command runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetOs {
+    Linux,
+    Macos,
+    Windows,
+}
+
+impl TargetOs {
+    /// The OS dwf itself runs on; other Unix systems count as Linux.
+    pub fn host() -> Self {
+        match std::env::consts::OS {
+            "macos" => Self::Macos,
+            "windows" => Self::Windows,
+            _ => Self::Linux,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Linux => "linux",
+            Self::Macos => "macos",
+            Self::Windows => "windows",
+        }
+    }
+}
+
+/// Where an action will run, for extensions whose commands differ by
+/// platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionContext {
+    /// The OS of the host, container or remote machine that runs the action.
+    pub os: TargetOs,
+}
+
+impl ActionContext {
+    /// A context for running on the host.
+    pub fn host() -> Self {
+        Self {
+            os: TargetOs::host(),
+        }
+    }
+}
+
+/// What the changed files of an `--affected` run mean for a stack's command.
+#[derive(Debug, Clone)]
+pub enum Affected {
+    /// Run the command as is.
+    All,
+    /// Run this narrower action instead, e.g. only the affected packages.
+    Action(ExecutionAction),
+    /// The changes touch nothing the command covers; skip it.
+    Nothing,
+}
+
 /// A contract for all extensions connecting to Devflow.
-pub trait Extension: std::fmt::Debug {
+pub trait Extension: std::fmt::Debug + Send + Sync {
     /// Unique name of the extension.
     fn name(&self) -> &str;
     /// The set of command capabilities provided by this extension.
@@ -30,6 +90,36 @@ pub trait Extension: std::fmt::Debug {
     /// Maps a command reference to an executable action.
     fn build_action(&self, cmd: &CommandRef) -> Result<Option<ExecutionAction>>;
 
+    /// Maps `cmd` to an action for where it runs (`ctx`).
+    ///
+    /// Defaults to [`Extension::build_action`]; override it when a command
+    /// differs across platforms, e.g. packaging or opening a report.
+    fn build_action_for(
+        &self,
+        cmd: &CommandRef,
+        ctx: &ActionContext,
+    ) -> Result<Option<ExecutionAction>> {
+        let _ = ctx;
+        self.build_action(cmd)
+    }
+
+    /// Narrows `action`, the action of `cmd`, to what the `changed` files
+    /// affect (`--affected`), e.g. the workspace packages depending on them.
+    ///
+    /// Paths are relative to the project root. Defaults to [`Affected::All`];
+    /// override it when the toolchain knows the project's dependency graph.
+    fn affected(&self, cmd: &CommandRef, action: &ExecutionAction, changed: &[String]) -> Affected {
+        let _ = (cmd, action, changed);
+        Affected::All
+    }
+
+    /// The machine resource `cmd` mostly uses, which `[concurrency]` limits
+    /// in parallel check runs. Defaults to [`ResourceClass::default_for`];
+    /// override it when the toolchain differs, e.g. a linter that compiles.
+    fn resource_class(&self, cmd: &CommandRef) -> ResourceClass {
+        ResourceClass::default_for(cmd)
+    }
+
     /// Whether this extension is considered "trusted" to run on the host during negotiation.
     fn is_trusted(&self) -> bool {
         false
@@ -42,6 +132,36 @@ pub trait Extension: std::fmt::Debug {
         Vec::new()
     }
 
+    /// Returns the subset of [`Extension::cache_mounts`] that `cmd` needs.
+    ///
+    /// Defaults to every cache mount; override it so light commands (e.g. `fmt:check`)
+    /// skip mounts only heavier ones use.
+    fn cache_mounts_for(&self, _cmd: &CommandRef) -> Vec<String> {
+        self.cache_mounts()
+    }
+
+    /// The commands `cache:warm` runs to fill this extension's caches, in
+    /// order. Defaults to `setup:deps` when the extension provides it;
+    /// override it when a build primes a compiler cache too.
+    fn warm_commands(&self) -> Vec<CommandRef> {
+        let deps = CommandRef {
+            primary: PrimaryCommand::Setup,
+            selector: Some("deps".to_string()),
+        };
+        if provides(&self.capabilities(), &deps) {
+            vec![deps]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Returns the workspace directories that may live in named engine
+    /// volumes instead of the workspace bind mount (see
+    /// [`crate::config::MountType`]).
+    fn volume_mounts(&self) -> Vec<VolumeMount> {
+        Vec::new()
+    }
+
     /// Returns the environment variables required by this extension for execution.
     fn env_vars(&self) -> HashMap<String, String> {
         HashMap::new()
@@ -52,6 +172,150 @@ pub trait Extension: std::fmt::Debug {
     fn fingerprint_inputs(&self) -> Vec<String> {
         Vec::new()
     }
+
+    /// Returns the environment variable that relocates build outputs, if the toolchain has one.
+    /// Example: `CARGO_TARGET_DIR`
+    fn output_dir_env(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns globs matching the artifacts produced by `cmd`.
+    ///
+    /// Paths are relative to the directory named by [`Extension::output_dir_env`] when
+    /// set, otherwise to the project root. Patterns prefixed with `!` exclude files.
+    fn artifact_patterns(&self, _cmd: &CommandRef) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Returns the problem matchers for the output of `cmd` (see
+    /// [`crate::problem`]).
+    fn problem_matchers(&self, _cmd: &CommandRef) -> Vec<ProblemMatcher> {
+        Vec::new()
+    }
+
+    /// Returns the pinned tools this extension's commands use (see
+    /// [`crate::tool`]).
+    fn tools(&self) -> Vec<ToolRequirement> {
+        Vec::new()
+    }
+
+    /// Returns what to do when `cmd` fails, shown with the error.
+    /// Example: `run 'cargo update --workspace' and commit Cargo.lock`
+    fn failure_hint(&self, _cmd: &CommandRef) -> Option<String> {
+        None
+    }
+
+    /// Returns what each capability does for this stack, keyed by
+    /// capability, for `dwf list` and `dwf help`. Unlisted capabilities are
+    /// shown without a description.
+    fn capability_info(&self) -> BTreeMap<String, CapabilityInfo> {
+        BTreeMap::new()
+    }
+
+    /// Returns the directories the stack's toolchain is installed in, put
+    /// on the `PATH` of host runs under `[runtime] path = "hermetic"`.
+    /// Example: `~/.cargo/bin`
+    fn toolchain_dirs(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    /// Returns the environment variables of the stack's actions that carry
+    /// credentials, masked like the keys [`is_secret_env`] matches wherever
+    /// devflow records or prints an action.
+    /// Example: `TURBO_TOKEN` for a remote cache
+    fn secret_env(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Returns the version the project releases, read from the stack's
+    /// manifest in the working directory; `release:tag` names the tag after
+    /// it. Example: `1.4.0` from `version` in `Cargo.toml`
+    fn release_version(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Key patterns of environment variables treated as credentials, matched
+/// case-insensitively.
+pub const SECRET_ENV_PATTERNS: &[&str] = &[
+    "*TOKEN*",
+    "*SECRET*",
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*CREDENTIAL*",
+    "*KEY*",
+];
+
+/// Whether the value of the environment variable `key` is a credential by
+/// its name (see [`SECRET_ENV_PATTERNS`]).
+pub fn is_secret_env(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    SECRET_ENV_PATTERNS
+        .iter()
+        .any(|pattern| crate::glob::matches(pattern, &key))
+}
+
+/// The help text of a capability (see [`Extension::capability_info`]).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CapabilityInfo {
+    /// What the command does for the stack, in one line.
+    /// Example: `Run the unit tests (cargo nextest run --lib --bins)`
+    pub description: String,
+    /// The heading `dwf list` shows it under; defaults to its primary
+    /// command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl CapabilityInfo {
+    /// Info with a description, in `group`.
+    pub fn new(group: &str, description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            group: Some(group.to_string()),
+        }
+    }
+}
+
+/// A workspace directory kept in a named engine volume, e.g. `node_modules`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeMount {
+    /// Short name, part of the volume name (e.g. `node_modules`).
+    pub name: String,
+    /// Workspace-relative directory the volume is mounted over.
+    pub path: String,
+    /// Files whose fingerprint keys the volume; when it changes, commands
+    /// start from a fresh volume.
+    pub key_inputs: Vec<String>,
+}
+
+/// A variable in a merged extension environment, with the extension that set it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedEnvVar {
+    /// The variable value.
+    pub value: String,
+    /// The extension whose value was kept.
+    pub source: String,
+}
+
+/// Two extensions assigning different values to the same variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvConflict {
+    /// The variable name.
+    pub key: String,
+    /// The value kept from the higher-precedence extension.
+    pub kept: MergedEnvVar,
+    /// The value discarded from the lower-precedence extension.
+    pub ignored: MergedEnvVar,
+}
+
+/// The aggregate `env_vars()` of several extensions.
+#[derive(Debug, Clone, Default)]
+pub struct MergedEnv {
+    /// The merged variables, keyed by name.
+    pub vars: BTreeMap<String, MergedEnvVar>,
+    /// Variables set to different values by more than one extension.
+    pub conflicts: Vec<EnvConflict>,
 }
 
 /// A registry containing all discovered Devflow extensions.
@@ -83,6 +347,13 @@ impl ExtensionRegistry {
             .insert(extension.name().to_string(), extension);
     }
 
+    /// Names of the registered extensions, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.extensions.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
     /// Retrieves an extension by name.
     pub fn get(&self, name: &str) -> Option<&dyn Extension> {
         self.extensions.get(name).map(|boxed| boxed.as_ref())
@@ -130,16 +401,16 @@ impl ExtensionRegistry {
             return Ok(());
         }
 
-        for (profile, commands) in &cfg.targets.profiles {
-            for raw in commands {
+        for (profile, entries) in &cfg.targets.profiles {
+            for (index, entry) in entries.iter().enumerate() {
+                let raw = entry.command();
                 let cmd = CommandRef::from_str(raw)?;
                 self.ensure_can_run(&cmd).map_err(|e| {
-                    anyhow::anyhow!(
+                    ConfigDiagnostic::new(format!(
                         "unsupported command '{}' in targets profile '{}': {}",
-                        raw,
-                        profile,
-                        e
-                    )
+                        raw, profile, e
+                    ))
+                    .at_key(format!("targets.{profile}[{index}]"))
                 })?;
             }
         }
@@ -147,10 +418,16 @@ impl ExtensionRegistry {
         Ok(())
     }
 
-    /// Builds the execution arguments for a command against a specific extension.
-    pub fn build_action(&self, name: &str, cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
+    /// Builds the execution arguments for a command against a specific
+    /// extension, for running in `ctx`.
+    pub fn build_action(
+        &self,
+        name: &str,
+        cmd: &CommandRef,
+        ctx: &ActionContext,
+    ) -> Result<Option<ExecutionAction>> {
         if let Some(ext) = self.extensions.get(name) {
-            let mut action = match ext.build_action(cmd)? {
+            let mut action = match ext.build_action_for(cmd, ctx)? {
                 Some(a) => a,
                 None => return Ok(None),
             };
@@ -177,12 +454,135 @@ impl ExtensionRegistry {
         sorted.sort();
         sorted
     }
+
+    /// Merges the environment variables of the named extensions.
+    ///
+    /// Earlier names take precedence: when two extensions set a variable to
+    /// different values, the first one's value is kept and the clash is recorded
+    /// in [`MergedEnv::conflicts`]. Unregistered names are skipped.
+    pub fn merged_env_vars(&self, order: &[String]) -> MergedEnv {
+        let mut merged = MergedEnv::default();
+        for name in order {
+            let Some(ext) = self.extensions.get(name) else {
+                continue;
+            };
+            let vars: BTreeMap<String, String> = ext.env_vars().into_iter().collect();
+            for (key, value) in vars {
+                let candidate = MergedEnvVar {
+                    value,
+                    source: name.clone(),
+                };
+                match merged.vars.get(&key) {
+                    Some(kept) if kept.value != candidate.value => {
+                        merged.conflicts.push(EnvConflict {
+                            key,
+                            kept: kept.clone(),
+                            ignored: candidate,
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        merged.vars.insert(key, candidate);
+                    }
+                }
+            }
+        }
+        merged
+    }
+
+    /// Aggregates the cache mounts the active extensions need for `cmd`.
+    pub fn cache_mounts_for(&self, cmd: &CommandRef) -> Vec<String> {
+        let mut mounts = HashSet::new();
+        for ext in self.extensions.values() {
+            mounts.extend(ext.cache_mounts_for(cmd));
+        }
+        let mut sorted: Vec<String> = mounts.into_iter().collect();
+        sorted.sort();
+        sorted
+    }
+
+    /// The resource class of `cmd`: CPU-bound if any extension providing it
+    /// says so, since its stacks all run.
+    pub fn resource_class(&self, cmd: &CommandRef) -> ResourceClass {
+        self.extensions
+            .values()
+            .filter(|ext| provides(&ext.capabilities(), cmd))
+            .map(|ext| ext.resource_class(cmd))
+            .max()
+            .unwrap_or_else(|| ResourceClass::default_for(cmd))
+    }
+
+    /// Returns the commands `cache:warm` runs for extension `name`.
+    pub fn warm_commands(&self, name: &str) -> Vec<CommandRef> {
+        self.extensions
+            .get(name)
+            .map(|ext| ext.warm_commands())
+            .unwrap_or_default()
+    }
+
+    /// Returns the problem matchers extension `name` declares for `cmd`.
+    pub fn problem_matchers_for(&self, name: &str, cmd: &CommandRef) -> Vec<ProblemMatcher> {
+        self.extensions
+            .get(name)
+            .map(|ext| ext.problem_matchers(cmd))
+            .unwrap_or_default()
+    }
+
+    /// Returns the capability info extension `name` declares.
+    pub fn capability_info(&self, name: &str) -> BTreeMap<String, CapabilityInfo> {
+        self.extensions
+            .get(name)
+            .map(|ext| ext.capability_info())
+            .unwrap_or_default()
+    }
+
+    /// Returns the tools the named extensions require, in name order; when
+    /// several declare the same tool, the first one's requirement is kept.
+    pub fn tools_for(&self, order: &[String]) -> Vec<ToolRequirement> {
+        let mut tools = BTreeMap::new();
+        for ext in order.iter().filter_map(|name| self.extensions.get(name)) {
+            for tool in ext.tools() {
+                tools.entry(tool.name.clone()).or_insert(tool);
+            }
+        }
+        tools.into_values().collect()
+    }
+
+    /// Returns the toolchain directories of the named extensions, in order,
+    /// without duplicates.
+    pub fn toolchain_dirs_for(&self, order: &[String]) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        for ext in order.iter().filter_map(|name| self.extensions.get(name)) {
+            for dir in ext.toolchain_dirs() {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+        dirs
+    }
+
+    /// Aggregates the fingerprint inputs declared by the active extensions.
+    pub fn all_fingerprint_inputs(&self) -> Vec<String> {
+        let mut inputs = HashSet::new();
+        for ext in self.extensions.values() {
+            inputs.extend(ext.fingerprint_inputs());
+        }
+        let mut sorted: Vec<String> = inputs.into_iter().collect();
+        sorted.sort();
+        sorted
+    }
+}
+
+/// Whether `capabilities` include `cmd`, by its canonical name or its
+/// primary command.
+fn provides(capabilities: &HashSet<String>, cmd: &CommandRef) -> bool {
+    capabilities.contains(&cmd.canonical()) || capabilities.contains(cmd.primary.as_str())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::command::PrimaryCommand;
 
     #[derive(Debug)]
     struct MockExtension {
@@ -225,11 +625,16 @@ mod tests {
             selector: None,
         };
 
-        let action = registry.build_action("mock", &cmd).unwrap().unwrap();
+        let action = registry
+            .build_action("mock", &cmd, &ActionContext::host())
+            .unwrap()
+            .unwrap();
         assert_eq!(action.program, "echo");
         assert_eq!(action.args, vec!["hello"]);
 
-        let missing = registry.build_action("nonexistent", &cmd).unwrap();
+        let missing = registry
+            .build_action("nonexistent", &cmd, &ActionContext::host())
+            .unwrap();
         assert!(missing.is_none());
     }
 
@@ -407,7 +812,10 @@ mod tests {
             selector: None,
         };
 
-        let action = registry.build_action("rust", &cmd).unwrap().unwrap();
+        let action = registry
+            .build_action("rust", &cmd, &ActionContext::host())
+            .unwrap()
+            .unwrap();
         // Action-level env overrides extension-level
         assert_eq!(action.env.get("CARGO_HOME").unwrap(), "/override/cargo");
         // Extension-level env is preserved when not overridden
@@ -416,9 +824,160 @@ mod tests {
         assert_eq!(action.env.get("EXTRA").unwrap(), "value");
     }
 
+    #[test]
+    fn warm_commands_default_to_setup_deps() {
+        let mut registry = ExtensionRegistry::default();
+        for (name, capabilities) in [("node", vec!["setup"]), ("go", vec!["build:debug"])] {
+            registry.register(Box::new(ConfigurableMockExtension {
+                ext_name: name.to_string(),
+                capabilities: capabilities.into_iter().map(String::from).collect(),
+                action: None,
+                trusted: true,
+                mounts: Vec::new(),
+                envs: HashMap::new(),
+            }));
+        }
+        let warm = registry.warm_commands("node");
+        assert_eq!(warm.len(), 1);
+        assert_eq!(warm[0].canonical(), "setup:deps");
+        assert!(registry.warm_commands("go").is_empty());
+        assert!(registry.warm_commands("missing").is_empty());
+    }
+
     #[test]
     fn all_cache_mounts_empty_when_no_extensions() {
         let registry = ExtensionRegistry::default();
         assert!(registry.all_cache_mounts().is_empty());
     }
+
+    #[derive(Debug)]
+    struct FingerprintMockExtension(&'static str, Vec<&'static str>);
+
+    impl Extension for FingerprintMockExtension {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn capabilities(&self) -> HashSet<String> {
+            HashSet::new()
+        }
+
+        fn build_action(&self, _cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
+            Ok(None)
+        }
+
+        fn fingerprint_inputs(&self) -> Vec<String> {
+            self.1.iter().map(|s| s.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn all_fingerprint_inputs_aggregates_sorted_unique() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(FingerprintMockExtension(
+            "rust",
+            vec!["Cargo.lock", "Cargo.toml"],
+        )));
+        registry.register(Box::new(FingerprintMockExtension(
+            "node",
+            vec!["package-lock.json", "Cargo.lock"],
+        )));
+
+        assert_eq!(
+            registry.all_fingerprint_inputs(),
+            vec!["Cargo.lock", "Cargo.toml", "package-lock.json"]
+        );
+    }
+
+    fn env_mock(name: &str, vars: &[(&str, &str)]) -> Box<ConfigurableMockExtension> {
+        Box::new(ConfigurableMockExtension {
+            ext_name: name.to_string(),
+            capabilities: HashSet::new(),
+            action: None,
+            trusted: true,
+            mounts: Vec::new(),
+            envs: vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn merged_env_vars_prefers_earlier_extensions_and_records_conflicts() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(env_mock(
+            "rust",
+            &[("CARGO_TARGET_DIR", "target/ci"), ("CI", "true")],
+        ));
+        registry.register(env_mock(
+            "tauri",
+            &[("CARGO_TARGET_DIR", "target/tauri"), ("CI", "true")],
+        ));
+        registry.register(env_mock("node", &[("NPM_CONFIG_CACHE", "/root/.npm")]));
+
+        let merged = registry.merged_env_vars(&["rust".to_string(), "tauri".to_string()]);
+        assert_eq!(merged.vars["CARGO_TARGET_DIR"].value, "target/ci");
+        assert_eq!(merged.vars["CARGO_TARGET_DIR"].source, "rust");
+        // Unlisted extensions are not merged.
+        assert!(!merged.vars.contains_key("NPM_CONFIG_CACHE"));
+        // Identical values are not conflicts.
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].key, "CARGO_TARGET_DIR");
+        assert_eq!(merged.conflicts[0].ignored.source, "tauri");
+    }
+
+    #[derive(Debug)]
+    struct ScopedMountExtension;
+
+    impl Extension for ScopedMountExtension {
+        fn name(&self) -> &str {
+            "scoped"
+        }
+        fn capabilities(&self) -> HashSet<String> {
+            HashSet::new()
+        }
+        fn build_action(&self, _cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
+            Ok(None)
+        }
+        fn cache_mounts(&self) -> Vec<String> {
+            vec!["scoped/build:/workspace/target".to_string()]
+        }
+        fn cache_mounts_for(&self, cmd: &CommandRef) -> Vec<String> {
+            if cmd.primary == PrimaryCommand::Fmt {
+                Vec::new()
+            } else {
+                self.cache_mounts()
+            }
+        }
+    }
+
+    #[test]
+    fn cache_mounts_for_respects_command_scope() {
+        let mut registry = ExtensionRegistry::default();
+        registry.register(Box::new(ScopedMountExtension));
+        registry.register(Box::new(ConfigurableMockExtension {
+            ext_name: "node".to_string(),
+            capabilities: HashSet::new(),
+            action: None,
+            trusted: true,
+            mounts: vec!["node/npm:/root/.npm".to_string()],
+            envs: HashMap::new(),
+        }));
+
+        let fmt = CommandRef {
+            primary: PrimaryCommand::Fmt,
+            selector: Some("check".to_string()),
+        };
+        let build = CommandRef {
+            primary: PrimaryCommand::Build,
+            selector: Some("release".to_string()),
+        };
+        // Extensions without an override keep every mount.
+        assert_eq!(registry.cache_mounts_for(&fmt), vec!["node/npm:/root/.npm"]);
+        assert_eq!(
+            registry.cache_mounts_for(&build),
+            vec!["node/npm:/root/.npm", "scoped/build:/workspace/target"]
+        );
+    }
 }
@@ -1,12 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::io::Write;
 use std::process::{Command, Stdio};
 
 use anyhow::Result;
 use tracing::{debug, error};
 
-use crate::command::CommandRef;
-use crate::extension::{ExecutionAction, Extension};
+use crate::command::{CommandRef, ResourceClass};
+use crate::extension::{ActionContext, CapabilityInfo, ExecutionAction, Extension, TargetOs};
+use crate::problem::ProblemMatcher;
+use crate::tool::ToolRequirement;
+
+/// The `--build-action` request: the command plus where it runs.
+#[derive(serde::Serialize)]
+struct BuildRequest<'a> {
+    #[serde(flatten)]
+    command: &'a CommandRef,
+    os: TargetOs,
+}
 
 /// An extension that delegates to an external binary via JSON over stdio.
 #[derive(Debug)]
@@ -15,6 +25,10 @@ pub struct SubprocessExtension {
     binary_path: String,
     capabilities: HashSet<String>,
     is_trusted: bool,
+    problem_matchers: Vec<ProblemMatcher>,
+    tools: Vec<ToolRequirement>,
+    resources: BTreeMap<String, ResourceClass>,
+    capability_info: BTreeMap<String, CapabilityInfo>,
 }
 
 impl SubprocessExtension {
@@ -30,8 +44,39 @@ impl SubprocessExtension {
             binary_path,
             capabilities,
             is_trusted,
+            problem_matchers: Vec::new(),
+            tools: Vec::new(),
+            resources: BTreeMap::new(),
+            capability_info: BTreeMap::new(),
         }
     }
+
+    /// Sets the problem matchers reported by `--discover`; they apply to the
+    /// output of every command.
+    pub fn with_problem_matchers(mut self, matchers: Vec<ProblemMatcher>) -> Self {
+        self.problem_matchers = matchers;
+        self
+    }
+
+    /// Sets the tool requirements reported by `--discover`.
+    pub fn with_tools(mut self, tools: Vec<ToolRequirement>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Sets the resource classes reported by `--discover`, keyed by
+    /// capability; unlisted commands keep the default class.
+    pub fn with_resources(mut self, resources: BTreeMap<String, ResourceClass>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Sets the capability descriptions and groups reported by
+    /// `--discover`, keyed by capability.
+    pub fn with_capability_info(mut self, info: BTreeMap<String, CapabilityInfo>) -> Self {
+        self.capability_info = info;
+        self
+    }
 }
 
 impl Extension for SubprocessExtension {
@@ -44,7 +89,19 @@ impl Extension for SubprocessExtension {
     }
 
     fn build_action(&self, cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
-        let serialized_cmd = serde_json::to_string(cmd)
+        self.build_action_for(cmd, &ActionContext::host())
+    }
+
+    fn build_action_for(
+        &self,
+        cmd: &CommandRef,
+        ctx: &ActionContext,
+    ) -> Result<Option<ExecutionAction>> {
+        let request = BuildRequest {
+            command: cmd,
+            os: ctx.os,
+        };
+        let serialized_cmd = serde_json::to_string(&request)
             .map_err(|e| anyhow::anyhow!("failed to serialize command for {}: {}", self.name, e))?;
 
         let mut child = match Command::new(&self.binary_path)
@@ -98,6 +155,25 @@ impl Extension for SubprocessExtension {
     fn is_trusted(&self) -> bool {
         self.is_trusted
     }
+
+    fn problem_matchers(&self, _cmd: &CommandRef) -> Vec<ProblemMatcher> {
+        self.problem_matchers.clone()
+    }
+
+    fn tools(&self) -> Vec<ToolRequirement> {
+        self.tools.clone()
+    }
+
+    fn resource_class(&self, cmd: &CommandRef) -> ResourceClass {
+        self.resources
+            .get(&cmd.canonical())
+            .copied()
+            .unwrap_or_else(|| ResourceClass::default_for(cmd))
+    }
+
+    fn capability_info(&self) -> BTreeMap<String, CapabilityInfo> {
+        self.capability_info.clone()
+    }
 }
 
 #[cfg(test)]
@@ -120,6 +196,9 @@ if "--build-action" in sys.argv:
     if cmd.get("primary") == "test":
         print(json.dumps({"program": "echo", "args": ["mock-test"]}))
         sys.exit(0)
+    elif cmd.get("primary") == "package":
+        print(json.dumps({"program": "echo", "args": ["package", cmd["os"]]}))
+        sys.exit(0)
     else:
         sys.exit(1)
 "#;
@@ -157,6 +236,34 @@ if "--build-action" in sys.argv:
         assert_eq!(action.args, vec!["mock-test".to_string()]);
     }
 
+    #[test]
+    fn build_request_carries_the_target_os() {
+        let dir = tempfile::tempdir().unwrap();
+        let ext = SubprocessExtension::new(
+            "mock".to_string(),
+            create_mock_extension(&dir),
+            HashSet::from(["package".to_string()]),
+            true,
+        );
+        let cmd = CommandRef {
+            primary: PrimaryCommand::Package,
+            selector: Some("artifact".to_string()),
+        };
+        let ctx = ActionContext {
+            os: TargetOs::Macos,
+        };
+        let action = ext.build_action_for(&cmd, &ctx).unwrap().unwrap();
+        assert_eq!(action.args, ["package", "macos"]);
+        assert_eq!(
+            serde_json::to_string(&BuildRequest {
+                command: &cmd,
+                os: TargetOs::Windows,
+            })
+            .unwrap(),
+            r#"{"primary":"package","selector":"artifact","os":"windows"}"#
+        );
+    }
+
     #[test]
     fn subprocess_extension_build_action_failure() {
         let dir = tempfile::tempdir().unwrap();
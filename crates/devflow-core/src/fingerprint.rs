@@ -3,38 +3,113 @@
 //! This module provides the core logic for computing SHA256 hashes of project
 //! manifests and toolchain configurations, ensuring that containerized environments
 //! are perfectly reproducible across different machines and CI runs.
+//!
+//! Inputs are hashed by content, or with the `git` strategy by the blob id git
+//! already has for them: tracked files whose work tree copy matches the index
+//! are not read at all. Untracked and modified files, and every input outside
+//! a git repository, are still hashed by content.
+//!
+//! A directory input is hashed by the path and content of every file beneath
+//! it that is not ignored (see [`crate::walk`]).
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::{Command, Stdio};
 
 use anyhow::{Context, Result};
+use serde::Deserialize;
 use sha2::{Digest, Sha256};
-use std::path::Path;
 use tracing::debug;
 
+use crate::walk;
+
+/// How fingerprint inputs are hashed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FingerprintStrategy {
+    /// Read and hash every input file.
+    #[default]
+    Content,
+    /// Use the git index blob id of clean tracked files.
+    Git,
+}
+
 /// Computes a deterministic SHA256 fingerprint from a list of files.
 ///
 /// This fingerprint defines the exact runtime identity of the container cache,
 /// allowing identical local and CI runs to safely reuse the exact same image base.
 pub fn compute_fingerprint(base_dir: &Path, inputs: &[String]) -> Result<String> {
+    let inputs: Vec<(String, FingerprintStrategy)> = inputs
+        .iter()
+        .map(|input| (input.clone(), FingerprintStrategy::Content))
+        .collect();
+    compute_fingerprint_with(base_dir, &inputs)
+}
+
+/// Computes the fingerprint of `inputs`, each hashed with its strategy.
+///
+/// Hashing an input by content gives the same fingerprint under both
+/// strategies, so a `git` fingerprint only differs from a `content` one for
+/// the clean tracked files.
+pub fn compute_fingerprint_with(
+    base_dir: &Path,
+    inputs: &[(String, FingerprintStrategy)],
+) -> Result<String> {
     let mut hasher = Sha256::new();
 
     // Sort inputs alphabetically so that hash isn't order-dependent based on the Extension order
-    let mut sorted_inputs = inputs.to_owned();
-    sorted_inputs.sort();
+    let mut sorted_inputs: Vec<(&str, FingerprintStrategy)> = inputs
+        .iter()
+        .map(|(input, strategy)| (input.as_str(), *strategy))
+        .collect();
+    sorted_inputs.sort_by_key(|(input, _)| *input);
+    let tracked: Vec<&str> = sorted_inputs
+        .iter()
+        .filter(|(_, strategy)| *strategy == FingerprintStrategy::Git)
+        .map(|(input, _)| *input)
+        .collect();
+    let blobs = if tracked.is_empty() {
+        HashMap::new()
+    } else {
+        git_blobs(base_dir, &tracked)
+    };
 
-    for input in sorted_inputs {
-        let path = base_dir.join(&input);
+    for (input, strategy) in sorted_inputs {
+        let path = base_dir.join(input);
 
         // We do not strict-fail if an optional file is missing (e.g., node_modules might not exist yet)
         // But we record its absence in the hash.
         hasher.update(input.as_bytes());
         hasher.update(b"\0");
 
-        if path.is_file() {
+        if let Some(blob) = blobs
+            .get(input)
+            .filter(|_| strategy == FingerprintStrategy::Git)
+        {
+            hasher.update(b"git\0");
+            hasher.update(blob.as_bytes());
+            debug!("fingerprint: mixed {} (git blob {})", input, blob);
+        } else if path.is_file() {
             let content = std::fs::read(&path)
                 .with_context(|| format!("failed to read fingerprint input: {}", path.display()))?;
 
             // Hash the content identity
             hasher.update(&content);
             debug!("fingerprint: mixed {} ({} bytes)", input, content.len());
+        } else if path.is_dir() {
+            // Directories are hashed file by file, skipping ignored entries
+            hasher.update(b"dir\0");
+            let files = walk::files(&path)?;
+            for file in &files {
+                let content = std::fs::read(file).with_context(|| {
+                    format!("failed to read fingerprint input: {}", file.display())
+                })?;
+                let rel = file.strip_prefix(&path).unwrap_or(file);
+                hasher.update(rel.to_string_lossy().replace('\\', "/").as_bytes());
+                hasher.update(b"\0");
+                hasher.update(Sha256::digest(&content));
+            }
+            debug!("fingerprint: mixed {} ({} files)", input, files.len());
         } else {
             // Include an explicit marker for missing to prevent overlap collisions
             hasher.update(b"missing\0");
@@ -46,6 +121,58 @@ pub fn compute_fingerprint(base_dir: &Path, inputs: &[String]) -> Result<String>
     Ok(hex::encode(result))
 }
 
+/// The index blob ids of those `paths` (relative to `base_dir`) that git
+/// tracks and whose work tree copy matches the index. Empty when `base_dir`
+/// is not in a git repository or git is unavailable.
+fn git_blobs(base_dir: &Path, paths: &[&str]) -> HashMap<String, String> {
+    let Some(index) = git_output(base_dir, &["ls-files", "-s", "-z"], paths) else {
+        return HashMap::new();
+    };
+    let Some(modified) = git_output(
+        base_dir,
+        &["diff", "--name-only", "--relative", "-z"],
+        paths,
+    ) else {
+        return HashMap::new();
+    };
+    let modified: HashSet<&str> = modified.split('\0').collect();
+    index
+        .split('\0')
+        .filter_map(|entry| {
+            // `<mode> <blob> <stage>\t<path>`; conflicted entries have a stage
+            let (meta, path) = entry.split_once('\t')?;
+            let mut meta = meta.split(' ');
+            let blob = meta.nth(1)?;
+            (meta.next()? == "0" && !modified.contains(path))
+                .then(|| (path.to_string(), blob.to_string()))
+        })
+        .collect()
+}
+
+fn git_output(base_dir: &Path, args: &[&str], paths: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("--literal-pathspecs")
+        .arg("-C")
+        .arg(base_dir)
+        .args(args)
+        .arg("--")
+        .args(paths)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| debug!("fingerprint: git unavailable: {}", e))
+        .ok()?;
+    if !output.status.success() {
+        debug!(
+            "fingerprint: git {} failed in {}; hashing content",
+            args[0],
+            base_dir.display()
+        );
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,6 +197,22 @@ mod tests {
         assert_ne!(hash1, mutated_hash);
     }
 
+    #[test]
+    fn directory_inputs_skip_ignored_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let proto = dir.path().join("proto");
+        std::fs::create_dir_all(&proto).unwrap();
+        std::fs::write(proto.join("api.proto"), b"v1").unwrap();
+        std::fs::write(proto.join(".gitignore"), b"*.tmp\n").unwrap();
+        let inputs = vec!["proto".to_string()];
+
+        let hash = compute_fingerprint(dir.path(), &inputs).unwrap();
+        std::fs::write(proto.join("scratch.tmp"), b"noise").unwrap();
+        assert_eq!(compute_fingerprint(dir.path(), &inputs).unwrap(), hash);
+        std::fs::write(proto.join("api.proto"), b"v2").unwrap();
+        assert_ne!(compute_fingerprint(dir.path(), &inputs).unwrap(), hash);
+    }
+
     #[test]
     fn input_order_independence() {
         let dir = tempfile::tempdir().unwrap();
@@ -93,6 +236,72 @@ mod tests {
         assert_eq!(hash.len(), 64);
     }
 
+    #[test]
+    fn git_strategy_uses_blob_ids_of_clean_tracked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args([
+                    "-c",
+                    "user.name=devflow",
+                    "-c",
+                    "user.email=devflow@example.com",
+                ])
+                .args(args)
+                .current_dir(dir.path())
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {args:?}");
+        };
+        let inputs = |strategy| {
+            [
+                ("Cargo.lock".to_string(), strategy),
+                ("local.env".to_string(), strategy),
+            ]
+        };
+        let content = |dir: &Path| {
+            compute_fingerprint_with(dir, &inputs(FingerprintStrategy::Content)).unwrap()
+        };
+        let git_mode =
+            |dir: &Path| compute_fingerprint_with(dir, &inputs(FingerprintStrategy::Git)).unwrap();
+
+        std::fs::write(dir.path().join("Cargo.lock"), b"v1").unwrap();
+        std::fs::write(dir.path().join("local.env"), b"untracked").unwrap();
+        // Outside a repository everything is content-hashed
+        assert_eq!(git_mode(dir.path()), content(dir.path()));
+        assert_eq!(
+            content(dir.path()),
+            compute_fingerprint(dir.path(), &["local.env".into(), "Cargo.lock".into()]).unwrap()
+        );
+
+        git(&["init", "-q"]);
+        // Untracked files are content-hashed
+        assert_eq!(git_mode(dir.path()), content(dir.path()));
+        git(&["add", "Cargo.lock"]);
+        git(&["commit", "-q", "-m", "lock"]);
+        let committed = git_mode(dir.path());
+        assert_ne!(committed, content(dir.path()));
+
+        // A modified tracked file is content-hashed until it is committed
+        std::fs::write(dir.path().join("Cargo.lock"), b"v2").unwrap();
+        assert_eq!(git_mode(dir.path()), content(dir.path()));
+        git(&["commit", "-q", "-am", "lock v2"]);
+        let updated = git_mode(dir.path());
+        assert_ne!(updated, content(dir.path()));
+        assert_ne!(updated, committed);
+
+        // Only inputs using the git strategy use blob ids
+        let mixed = [
+            ("Cargo.lock".to_string(), FingerprintStrategy::Content),
+            ("local.env".to_string(), FingerprintStrategy::Git),
+        ];
+        assert_eq!(
+            compute_fingerprint_with(dir.path(), &mixed).unwrap(),
+            content(dir.path())
+        );
+    }
+
     #[test]
     fn hash_is_valid_hex_sha256() {
         let dir = tempfile::tempdir().unwrap();
@@ -0,0 +1,476 @@
+//! Canonical formatting of `devflow.toml` (`dwf config:format`).
+//!
+//! The formatter only reorders: schema keys follow the order of the config
+//! structs, map keys (extensions, outputs) are sorted by name, target profiles
+//! keep their file order, which sets the order `check --list`, `ci:plan` and the
+//! generated workflow jobs follow, and
+//! the commands of each target profile are stably sorted into lifecycle order
+//! (`fmt` before `lint` before `build` before `test`). Comments, whitespace and
+//! value formatting travel with the entries they belong to.
+//!
+//! [`merge_config`] merges generated settings (`dwf ci:import`) into a config
+//! before formatting it.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, Value};
+
+use crate::command::CommandRef;
+
+/// Canonical key order of the schema tables; `*` matches any map key.
+/// Keys not listed sort alphabetically after the listed ones.
+const KEY_ORDER: &[(&str, &[&str])] = &[
+    (
+        "",
+        &[
+            "schema_version",
+            "extends",
+            "project",
+            "runtime",
+            "targets",
+            "commands",
+            "aliases",
+            "waits",
+            "fixtures",
+            "env_files",
+            "extensions",
+            "container",
+            "cache",
+            "concurrency",
+            "ci",
+            "release",
+            "logs",
+            "serve",
+            "notifications",
+            "reporters",
+        ],
+    ),
+    ("project", &["name", "stack"]),
+    (
+        "runtime",
+        &[
+            "profile",
+            "env",
+            "remote",
+            "conflict",
+            "env_conflict",
+            "host_env_exclude",
+            "path",
+            "path_dirs",
+        ],
+    ),
+    (
+        "runtime.remote",
+        &["host", "user", "port", "path", "artifacts"],
+    ),
+    (
+        "targets.*.*",
+        &["cmd", "paths", "timeout", "retries", "stacks"],
+    ),
+    (
+        "extensions.*",
+        &[
+            "source",
+            "path",
+            "version",
+            "api_version",
+            "capabilities",
+            "required",
+            "trusted",
+            "priority",
+            "fingerprint_strategy",
+            "mount_type",
+            "wasm_targets",
+            "no_std_targets",
+            "feature_matrix",
+            "visual",
+            "remote_cache",
+        ],
+    ),
+    ("extensions.*.visual", &["script", "threshold", "diffs"]),
+    ("extensions.*.remote_cache", &["url", "team", "token_env"]),
+    (
+        "container",
+        &[
+            "image",
+            "engine",
+            "fingerprint_inputs",
+            "outputs",
+            "platforms",
+            "reuse",
+            "entrypoint",
+            "extra_args",
+            "labels",
+            "nested",
+            "buildkit",
+        ],
+    ),
+    (
+        "container.buildkit",
+        &["cache_ref", "cache_from", "cache_to"],
+    ),
+    ("cache", &["root", "strategy", "fingerprint_strategy"]),
+    ("concurrency", &["cpu", "io"]),
+    (
+        "ci",
+        &[
+            "credential_helper",
+            "layout",
+            "permissions",
+            "runners",
+            "outputs",
+            "conditions",
+            "cache",
+            "badge",
+            "actions",
+            "jobs",
+        ],
+    ),
+    ("ci.permissions", &["jobs"]),
+    ("ci.cache", &["strategy"]),
+    ("ci.badge", &["publish", "gist", "coverage"]),
+    ("ci.runners", &["default"]),
+    ("ci.jobs.*", &["pre_steps", "post_steps"]),
+    ("release", &["branch", "profile", "tag"]),
+    ("logs", &["enabled", "keep"]),
+    ("serve", &["allow"]),
+    ("notifications.webhook", &["url", "on"]),
+    (
+        "commands.*",
+        &[
+            "clear_env",
+            "allow",
+            "max_output",
+            "spool_logs",
+            "read_only",
+            "writable",
+            "tmpfs",
+            "image",
+            "resource",
+            "require_clean",
+            "protected_branches",
+        ],
+    ),
+    (
+        "waits.*",
+        &["tcp", "http", "command", "timeout", "interval"],
+    ),
+    (
+        "fixtures.*",
+        &["engine", "image", "env", "migrate", "timeout"],
+    ),
+    ("env_files", &["files", "ci", "mask"]),
+    ("reporters.*", &["command", "args", "output"]),
+];
+
+/// Returns `text` in canonical form.
+///
+/// # Errors
+/// Returns an error if `text` is not valid TOML.
+pub fn format_config(text: &str) -> Result<String> {
+    let mut doc: DocumentMut = text.parse().context("failed to parse TOML config")?;
+    let root = doc.as_table_mut();
+    let mut layout = Layout {
+        position: 0,
+        at_start: root.iter().all(|(_, item)| is_header(item)),
+    };
+    normalize_table(root, &mut Vec::new(), &mut layout);
+    if doc.trailing().as_str().is_some_and(|t| t.trim().is_empty()) {
+        doc.set_trailing("");
+    }
+    Ok(doc.to_string())
+}
+
+/// `text` with every key `fragment` sets replaced by or added from it, in
+/// canonical form, and the dotted keys it already set to something else
+/// (compared in canonical form).
+/// Tables `text` lacks are added whole, with their comments.
+///
+/// # Errors
+/// Returns an error if either is not valid TOML, or `fragment` sets a table
+/// where `text` has a value.
+pub fn merge_config(text: &str, fragment: &str) -> Result<(String, Vec<String>)> {
+    let mut doc: DocumentMut = format_config(text)?.parse()?;
+    let fragment: DocumentMut = format_config(fragment)
+        .context("invalid generated settings")?
+        .parse()?;
+    let mut replaced = Vec::new();
+    merge_table(
+        doc.as_table_mut(),
+        fragment.as_table(),
+        &mut Vec::new(),
+        &mut replaced,
+    )?;
+    Ok((format_config(&doc.to_string())?, replaced))
+}
+
+fn merge_table(
+    into: &mut Table,
+    from: &Table,
+    path: &mut Vec<String>,
+    replaced: &mut Vec<String>,
+) -> Result<()> {
+    for (key, item) in from.iter() {
+        let Some(formatted) = from.key(key) else {
+            continue;
+        };
+        path.push(key.to_string());
+        let existing = into.get(key).map(|existing| existing.to_string());
+        match (into.get_mut(key), item) {
+            (Some(Item::Table(existing)), Item::Table(table)) => {
+                merge_table(existing, table, path, replaced)?
+            }
+            (Some(_), Item::Table(_)) => {
+                anyhow::bail!("'{}' is not a table", path.join("."))
+            }
+            _ => {
+                if existing.is_some_and(|existing| existing.trim() != item.to_string().trim()) {
+                    replaced.push(path.join("."));
+                }
+                into.insert_formatted(formatted, item.clone());
+            }
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Whether `text` is already in canonical form.
+///
+/// # Errors
+/// Returns an error if `text` is not valid TOML.
+pub fn is_canonical(text: &str) -> Result<bool> {
+    Ok(format_config(text)? == text)
+}
+
+fn key_order(path: &[String]) -> &'static [&'static str] {
+    KEY_ORDER
+        .iter()
+        .find(|(pattern, _)| {
+            let segments: Vec<&str> = if pattern.is_empty() {
+                Vec::new()
+            } else {
+                pattern.split('.').collect()
+            };
+            segments.len() == path.len()
+                && segments
+                    .iter()
+                    .zip(path)
+                    .all(|(segment, key)| *segment == "*" || segment == key)
+        })
+        .map_or(&[], |(_, order)| *order)
+}
+
+fn compare_keys(order: &[&str], a: &str, b: &str) -> Ordering {
+    let rank = |key: &str| order.iter().position(|k| *k == key).unwrap_or(order.len());
+    rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+}
+
+/// Header numbering and spacing state while walking the document.
+struct Layout {
+    position: isize,
+    /// No header or root key has been emitted yet.
+    at_start: bool,
+}
+
+/// Whether `item` is rendered under its own `[header]`.
+fn is_header(item: &Item) -> bool {
+    match item {
+        Item::Table(table) => !table.is_dotted(),
+        Item::ArrayOfTables(_) => true,
+        _ => false,
+    }
+}
+
+/// Sorts `table` and its descendants, renumbering table headers in
+/// depth-first order so each header follows its parent. Every header is
+/// preceded by exactly one blank line (none at the top of the file); comments
+/// above a header move with it.
+fn normalize_table(table: &mut Table, path: &mut Vec<String>, layout: &mut Layout) {
+    let order = key_order(path);
+    if !(path.len() == 1 && path[0] == "targets") {
+        table.sort_values_by(|a, _, b, _| compare_keys(order, a.get(), b.get()));
+    }
+    if !table.is_dotted() {
+        table.set_position(Some(layout.position));
+        layout.position += 1;
+        if !path.is_empty() && !table.is_implicit() {
+            let prefix = table
+                .decor()
+                .prefix()
+                .and_then(|p| p.as_str())
+                .unwrap_or_default()
+                .trim_start()
+                .to_string();
+            let blank = if layout.at_start { "" } else { "\n" };
+            table.decor_mut().set_prefix(format!("{blank}{prefix}"));
+            layout.at_start = false;
+        }
+    }
+    for (key, item) in table.iter_mut() {
+        path.push(key.get().to_string());
+        match item {
+            Item::Table(child) => normalize_table(child, path, layout),
+            Item::ArrayOfTables(tables) => {
+                for child in tables.iter_mut() {
+                    normalize_table(child, path, layout);
+                }
+            }
+            Item::Value(value) => normalize_value(value, path),
+            Item::None => {}
+        }
+        path.pop();
+    }
+}
+
+fn normalize_value(value: &mut Value, path: &mut Vec<String>) {
+    match value {
+        Value::InlineTable(table) => normalize_inline(table, path),
+        Value::Array(array) => {
+            if path.len() == 2 && path[0] == "targets" {
+                sort_profile(array);
+            }
+            path.push("*".to_string());
+            for item in array.iter_mut() {
+                normalize_value(item, path);
+            }
+            path.pop();
+        }
+        _ => {}
+    }
+}
+
+/// Sorts an inline table; like profile entries, each slot keeps its spacing.
+fn normalize_inline(table: &mut InlineTable, path: &mut Vec<String>) {
+    let order = key_order(path);
+    let decors: Vec<_> = table
+        .iter()
+        .map(|(name, value)| {
+            let key = table.key(name).map(|k| k.leaf_decor().clone());
+            (key.unwrap_or_default(), value.decor().clone())
+        })
+        .collect();
+    table.sort_values_by(|a, _, b, _| compare_keys(order, a.get(), b.get()));
+    for ((mut key, value), (key_decor, value_decor)) in table.iter_mut().zip(decors) {
+        *key.leaf_decor_mut() = key_decor;
+        *value.decor_mut() = value_decor;
+        path.push(key.get().to_string());
+        normalize_value(value, path);
+        path.pop();
+    }
+}
+
+/// Stably sorts a profile's entries by lifecycle stage; entries that do not
+/// parse as commands keep their relative order at the end. Each slot keeps
+/// its original spacing so single-line arrays stay tidy.
+fn sort_profile(array: &mut Array) {
+    let decors: Vec<_> = array.iter().map(|v| v.decor().clone()).collect();
+    let mut entries: Vec<Value> = array.iter().cloned().collect();
+    entries.sort_by_key(|entry| {
+        let cmd = match entry {
+            Value::String(cmd) => Some(cmd.value().as_str()),
+            Value::InlineTable(table) => table.get("cmd").and_then(Value::as_str),
+            _ => None,
+        };
+        cmd.and_then(|cmd| CommandRef::from_str(cmd).ok())
+            .map(|cmd| cmd.primary)
+    });
+    let trailing_comma = array.trailing_comma();
+    array.clear();
+    for (mut entry, decor) in entries.into_iter().zip(decors) {
+        *entry.decor_mut() = decor;
+        array.push_formatted(entry);
+    }
+    array.set_trailing_comma(trailing_comma);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_schema_keys_and_keeps_comments() {
+        let text = r#"# Project checks
+[container]
+engine = "auto"
+# the CI image
+image = "demo-ci"
+
+[targets]
+pr = ["fmt:check"]
+
+[project]
+stack = ["rust"]
+name = "demo"
+"#;
+        assert_eq!(
+            format_config(text).unwrap(),
+            r#"[project]
+name = "demo"
+stack = ["rust"]
+
+[targets]
+pr = ["fmt:check"]
+
+# Project checks
+[container]
+# the CI image
+image = "demo-ci"
+engine = "auto"
+"#
+        );
+    }
+
+    #[test]
+    fn sorts_profile_commands_into_lifecycle_order() {
+        let text = r#"[project]
+name = "demo"
+stack = ["rust"]
+
+[targets]
+pr = ["test:unit", "build:debug", { paths = ["src/**"], cmd = "test:integration" }, "fmt:check"]
+main = [
+  "test:smoke",
+  "lint:static",
+]
+"#;
+        assert_eq!(
+            format_config(text).unwrap(),
+            r#"[project]
+name = "demo"
+stack = ["rust"]
+
+[targets]
+pr = ["fmt:check", "build:debug", "test:unit", { cmd = "test:integration", paths = ["src/**"] }]
+main = [
+  "lint:static",
+  "test:smoke",
+]
+"#
+        );
+    }
+
+    #[test]
+    fn canonical_text_is_a_fixed_point() {
+        let text = "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[extensions.rust]\nsource = \"builtin\"\nrequired = true\n";
+        assert!(is_canonical(text).unwrap());
+        assert!(!is_canonical("[project]\nstack = [\"rust\"]\nname = \"demo\"\n").unwrap());
+    }
+
+    #[test]
+    fn merges_generated_settings() {
+        let text = "[project]\nname = \"demo\"\n\n[targets]\npr = [\"test:unit\"]\nrelease = [\"build:release\"]\n";
+        let fragment = "# generated\n[targets]\npr = [\"test:unit\", \"fmt:check\"]\nmain = [\"test:unit\"]\n\n# kept\n[ci.jobs.verify]\npost_steps = [{ run = \"./upload.sh\" }]\n";
+        let (merged, replaced) = merge_config(text, fragment).unwrap();
+        assert_eq!(replaced, ["targets.pr"]);
+        assert_eq!(
+            merged,
+            "[project]\nname = \"demo\"\n\n[targets]\npr = [\"fmt:check\", \"test:unit\"]\nrelease = [\"build:release\"]\nmain = [\"test:unit\"]\n\n# kept\n[ci.jobs.verify]\npost_steps = [{ run = \"./upload.sh\" }]\n"
+        );
+        let (_, replaced) = merge_config(&merged, fragment).unwrap();
+        assert_eq!(replaced, Vec::<String>::new());
+        assert!(merge_config("targets = 1\n", fragment).is_err());
+    }
+}
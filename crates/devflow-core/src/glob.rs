@@ -0,0 +1,156 @@
+//! Minimal path glob matching.
+//!
+//! Supports the subset of glob syntax used by path filters in `devflow.toml`:
+//! `*` (any run of characters within a path segment), `?` (a single character
+//! within a segment) and `**` (any number of segments, including none).
+//! Paths are always `/`-separated and relative to the project root.
+
+/// Returns true if `path` matches the glob `pattern`.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    match_from(&pattern, &path)
+}
+
+/// Returns true if any of the `patterns` matches `path`.
+pub fn matches_any(patterns: &[String], path: &str) -> bool {
+    patterns.iter().any(|pattern| matches(pattern, path))
+}
+
+/// Returns true if `pattern` may match a path below the directory `dir`.
+///
+/// Compares `dir` segment by segment against the leading segments of the
+/// pattern; a segment containing `**` may match anything below it. Used to
+/// skip directories no pattern can match while walking a tree.
+pub fn may_match_below(pattern: &str, dir: &str) -> bool {
+    let mut segments = pattern.split('/');
+    for name in dir.split('/') {
+        match segments.next() {
+            Some(segment) if segment.contains("**") => return true,
+            Some(segment) if matches(segment, name) => {}
+            _ => return false,
+        }
+    }
+    segments.next().is_some()
+}
+
+fn match_from(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // `**/` matches zero or more complete directory segments.
+            match_from(rest, path)
+                || (0..path.len())
+                    .filter(|&i| path[i] == '/')
+                    .any(|i| match_from(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| match_from(rest, &path[i..])),
+        ['*', rest @ ..] => {
+            let segment_end = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+            (0..=segment_end).any(|i| match_from(rest, &path[i..]))
+        }
+        ['?', rest @ ..] => match path {
+            [c, tail @ ..] if *c != '/' => match_from(rest, tail),
+            _ => false,
+        },
+        [literal, rest @ ..] => match path {
+            [c, tail @ ..] if c == literal => match_from(rest, tail),
+            _ => false,
+        },
+    }
+}
+
+/// Translates a glob into an anchored POSIX extended regular expression.
+///
+/// Used when a filter has to be evaluated by shell tooling (e.g. `grep -E`)
+/// in generated CI workflows, where Devflow itself is not available.
+pub fn to_regex(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::from("^");
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    out.push_str("(.*/)?");
+                    i += 3;
+                } else {
+                    out.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            c if ".^$+(){}[]|\\".contains(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+        i += 1;
+    }
+
+    out.push('$');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literal_paths() {
+        assert!(matches("Cargo.toml", "Cargo.toml"));
+        assert!(!matches("Cargo.toml", "crates/Cargo.toml"));
+    }
+
+    #[test]
+    fn single_star_stays_within_segment() {
+        assert!(matches("src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "src/db/mod.rs"));
+        assert!(matches("*.md", "README.md"));
+    }
+
+    #[test]
+    fn double_star_spans_segments() {
+        assert!(matches("src/db/**", "src/db/mod.rs"));
+        assert!(matches("src/db/**", "src/db/migrations/001.sql"));
+        assert!(matches("migrations/**", "migrations/001_init.sql"));
+        assert!(!matches("migrations/**", "src/migrations.rs"));
+    }
+
+    #[test]
+    fn double_star_slash_matches_zero_or_more_dirs() {
+        assert!(matches("**/*.sql", "schema.sql"));
+        assert!(matches("**/*.sql", "db/migrations/001.sql"));
+        assert!(!matches("**/*.sql", "db/migrations/001.rs"));
+    }
+
+    #[test]
+    fn question_mark_matches_single_char() {
+        assert!(matches("v?.txt", "v1.txt"));
+        assert!(!matches("v?.txt", "v10.txt"));
+        assert!(!matches("a?b", "a/b"));
+    }
+
+    #[test]
+    fn may_match_below_prunes_directories_by_leading_segments() {
+        assert!(may_match_below("release/*", "release"));
+        assert!(!may_match_below("release/*", "release/deps"));
+        assert!(!may_match_below("release/*", "node_modules"));
+        assert!(may_match_below("dist/**", "dist/assets"));
+        assert!(may_match_below("*/bin/*", "pkg/bin"));
+        assert!(!may_match_below("*/bin/*", "pkg/lib"));
+        assert!(may_match_below("**/*.whl", "target/wheels"));
+        assert!(!may_match_below("app.js", "dist"));
+    }
+
+    #[test]
+    fn translates_globs_to_extended_regex() {
+        assert_eq!(to_regex("src/db/**"), "^src/db/.*$");
+        assert_eq!(to_regex("**/*.sql"), "^(.*/)?[^/]*\\.sql$");
+        assert_eq!(to_regex("v?.txt"), "^v[^/]\\.txt$");
+    }
+}
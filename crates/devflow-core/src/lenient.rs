@@ -0,0 +1,174 @@
+//! Lenient config parsing for forward compatibility.
+//!
+//! Strict parsing rejects any key the schema does not know. In lenient mode an
+//! unknown key is removed from the document and reported as a warning, and the
+//! parse is retried, so configs written for a newer devflow still load. Keys
+//! that look like a typo of a known key (`stak` for `stack`) stay hard errors.
+
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use toml_edit::{Document, DocumentMut, Item};
+
+use crate::config::ConfigWarning;
+use crate::diagnostic::{
+    did_you_mean, dotted, key_span, line_column, unknown_field, ConfigDiagnostic, Segment,
+};
+
+/// Deserializes `text`, dropping unknown keys and returning them as warnings.
+///
+/// # Errors
+/// Returns an error for invalid TOML, for an unknown key within edit distance
+/// of a known one, or for any other deserialization failure.
+pub(crate) fn from_str<T: DeserializeOwned>(
+    file: &str,
+    text: &str,
+) -> Result<(T, Vec<ConfigWarning>)> {
+    let original = Document::parse(text).ok();
+    let mut current = text.to_string();
+    let mut warnings = Vec::new();
+
+    loop {
+        let err = match toml::from_str::<T>(&current) {
+            Ok(value) => return Ok((value, warnings)),
+            Err(err) => err,
+        };
+        let fail =
+            |err: &toml::de::Error| crate::diagnostic::from_toml_error(err, file, &current).into();
+        let Some((unknown, expected)) = unknown_field(err.message()) else {
+            return Err(fail(&err));
+        };
+        let Some(path) = err.span().and_then(|span| {
+            let doc = Document::parse(current.as_str()).ok()?;
+            find_key(doc.as_item(), &span, &mut Vec::new())
+        }) else {
+            return Err(fail(&err));
+        };
+
+        let key = dotted(&path);
+        let span = original
+            .as_ref()
+            .and_then(|doc| key_span(doc.as_item(), &path));
+        let (line, column) = span
+            .clone()
+            .map(|span| line_column(text, span.start))
+            .unwrap_or((0, 0));
+        if let Some(known) = did_you_mean(&unknown, &expected) {
+            let diagnostic = ConfigDiagnostic::new(format!("unknown key '{key}'"))
+                .at_key(key)
+                .with_hint(format!("did you mean '{known}'?"));
+            return Err(match span {
+                Some(span) => diagnostic.at_span(file, text, span),
+                None => diagnostic,
+            }
+            .into());
+        }
+
+        let mut doc: DocumentMut = current.parse().map_err(|e| anyhow!("{e}"))?;
+        remove(doc.as_item_mut(), &path);
+        current = doc.to_string();
+        warnings.push(ConfigWarning { key, line, column });
+    }
+}
+
+/// Finds the path of the key whose span is `span`.
+fn find_key(item: &Item, span: &Range<usize>, path: &mut Vec<Segment>) -> Option<Vec<Segment>> {
+    if let Some(table) = item.as_table_like() {
+        for (name, child) in table.iter() {
+            path.push(Segment::Key(name.to_string()));
+            if table.key(name).and_then(|k| k.span()).as_ref() == Some(span) {
+                return Some(path.clone());
+            }
+            if let Some(found) = find_key(child, span, path) {
+                return Some(found);
+            }
+            path.pop();
+        }
+    }
+    for index in 0..array_len(item) {
+        path.push(Segment::Index(index));
+        if let Some(found) = item.get(index).and_then(|c| find_key(c, span, path)) {
+            return Some(found);
+        }
+        path.pop();
+    }
+    None
+}
+
+fn remove(item: &mut Item, path: &[Segment]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut parent = item;
+    for segment in parents {
+        let next = match segment {
+            Segment::Key(name) => parent.get_mut(name.as_str()),
+            Segment::Index(index) => parent.get_mut(*index),
+        };
+        let Some(next) = next else {
+            return;
+        };
+        parent = next;
+    }
+    if let (Segment::Key(name), Some(table)) = (last, parent.as_table_like_mut()) {
+        table.remove(name);
+    }
+}
+
+fn array_len(item: &Item) -> usize {
+    item.as_array_of_tables()
+        .map(|a| a.len())
+        .or_else(|| item.as_array().map(|a| a.len()))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DevflowConfig;
+
+    #[test]
+    fn drops_unknown_keys_with_their_positions() {
+        let text = r#"schema_version = 2
+[project]
+name = "future"
+stack = ["rust"]
+telemetry = { enabled = true }
+
+[runtime]
+profile = "host"
+
+[sandbox]
+level = "strict"
+"#;
+        let (cfg, warnings) =
+            from_str::<DevflowConfig>("devflow.toml", text).expect("lenient parse");
+        assert_eq!(cfg.project.name, "future");
+        assert_eq!(
+            warnings,
+            vec![
+                ConfigWarning {
+                    key: "project.telemetry".to_string(),
+                    line: 5,
+                    column: 1
+                },
+                ConfigWarning {
+                    key: "sandbox".to_string(),
+                    line: 10,
+                    column: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn typos_of_known_keys_still_fail() {
+        let text = "[project]\nname = \"typo\"\nstak = [\"rust\"]\n";
+        let err = from_str::<DevflowConfig>("devflow.toml", text).expect_err("typo must fail");
+        let diagnostic = err.downcast::<ConfigDiagnostic>().unwrap();
+        assert_eq!(diagnostic.message, "unknown key 'project.stak'");
+        assert_eq!(diagnostic.hint.as_deref(), Some("did you mean 'stack'?"));
+        assert_eq!(diagnostic.position(), Some((3, 1)));
+    }
+}
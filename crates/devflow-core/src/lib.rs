@@ -3,15 +3,28 @@
 //! This crate defines the project configuration, command structures,
 //! extension registry, and runtime profiles used across the Devflow workspace.
 
+pub mod artifact;
 pub mod command;
 pub mod config;
 pub mod constants;
+pub mod diagnostic;
 pub mod extension;
 pub mod fingerprint;
+pub mod format;
+pub mod glob;
+mod lenient;
+pub mod preset;
+pub mod problem;
 pub mod project;
+pub mod reporter;
 pub mod runtime;
+pub mod tool;
+pub mod walk;
 
-pub use command::{CommandRef, PrimaryCommand};
-pub use config::{DevflowConfig, ExtensionSource, TargetsConfig};
-pub use extension::{ExecutionAction, Extension, ExtensionRegistry};
-pub use runtime::RuntimeProfile;
+pub use command::{Aliases, CommandRef, PrimaryCommand, ResourceClass};
+pub use config::{DevflowConfig, ExtensionSource, TargetEntry, TargetsConfig};
+pub use extension::{
+    ActionContext, Affected, CapabilityInfo, ExecutionAction, Extension, ExtensionRegistry,
+    MergedEnv, TargetOs, VolumeMount,
+};
+pub use runtime::{RuntimeEnv, RuntimeProfile};
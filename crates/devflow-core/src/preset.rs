@@ -0,0 +1,517 @@
+//! Shared config presets (`extends`).
+//!
+//! A config may start from a preset kept in a central repository:
+//!
+//! ```toml
+//! extends = "github:myorg/devflow-presets#rust-service"
+//! ```
+//!
+//! reads `rust-service.toml` from the default branch of
+//! `github.com/myorg/devflow-presets` (`github:myorg/devflow-presets@v2#rust-service`
+//! pins a branch or tag), and `extends = "../presets/service.toml"` reads a
+//! file. The local config is merged over the preset: tables merge key by key,
+//! any other value (arrays included) replaces the preset's.
+//!
+//! GitHub presets are fetched with `git` once per commit into
+//! `<cache root>/presets`, and the commit is recorded in `devflow.lock` next to
+//! the config, so every checkout reads the same preset until
+//! `dwf config:update` moves the lock.
+
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use crate::constants::{DEFAULT_CACHE_ROOT, PRESET_LOCK_FILE};
+use crate::diagnostic::{self, Segment};
+
+/// Where an `extends` preset comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetSource {
+    /// `github:<owner>/<repo>[@<ref>]#<preset>`: `<preset>.toml` in the repository.
+    Github {
+        owner: String,
+        repo: String,
+        /// Branch, tag or commit; the default branch when unset.
+        reference: Option<String>,
+        /// Path of the preset file in the repository.
+        file: String,
+    },
+    /// A preset file, relative to the config's directory.
+    Path(PathBuf),
+}
+
+impl FromStr for PresetSource {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let Some(rest) = text.strip_prefix("github:") else {
+            if text.trim().is_empty() || !text.ends_with(".toml") {
+                return Err(format!(
+                    "'{text}' is neither github:<owner>/<repo>#<preset> nor a .toml file"
+                ));
+            }
+            return Ok(Self::Path(PathBuf::from(text)));
+        };
+        let invalid = || format!("'{text}' is not github:<owner>/<repo>[@<ref>]#<preset>");
+        let (repository, preset) = rest.split_once('#').ok_or_else(invalid)?;
+        let (repository, reference) = match repository.split_once('@') {
+            Some((repository, reference)) => (repository, Some(reference)),
+            None => (repository, None),
+        };
+        let (owner, repo) = repository.split_once('/').ok_or_else(invalid)?;
+        let name = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        };
+        let path = Path::new(preset);
+        let relative = !preset.is_empty()
+            && path
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)));
+        if !name(owner) || !name(repo) || !relative || reference.is_some_and(|r| !is_reference(r)) {
+            return Err(invalid());
+        }
+        let file = if preset.ends_with(".toml") {
+            preset.to_string()
+        } else {
+            format!("{preset}.toml")
+        };
+        Ok(Self::Github {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            reference: reference.map(str::to_string),
+            file,
+        })
+    }
+}
+
+/// Whether `reference` is a branch, tag or commit name `git check-ref-format`
+/// accepts, restricted to the characters safe to pass to `git fetch`: it may
+/// not start with `-`, so git never reads it as an option.
+fn is_reference(reference: &str) -> bool {
+    !reference.is_empty()
+        && !reference.starts_with(['-', '/', '.'])
+        && !reference.ends_with(['/', '.'])
+        && !reference.ends_with(".lock")
+        && !reference.contains("..")
+        && !reference.contains("//")
+        && !reference.contains("/.")
+        && reference
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+}
+
+/// Whether `text` is a full SHA-1 or SHA-256 commit id.
+fn is_commit(text: &str) -> bool {
+    matches!(text.len(), 40 | 64) && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The preset a loaded config extends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    /// The `extends` value.
+    pub source: String,
+    /// The commit a GitHub preset was read at.
+    pub commit: Option<String>,
+    /// The preset file read; the cached copy for GitHub presets.
+    pub file: PathBuf,
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.commit {
+            Some(commit) => write!(f, "{} at {}", self.source, short(commit)),
+            None => write!(f, "{} ({})", self.source, self.file.display()),
+        }
+    }
+}
+
+/// `devflow.lock`: the commit `extends` resolved to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Lock {
+    extends: LockedPreset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LockedPreset {
+    source: String,
+    commit: String,
+}
+
+/// Reads the top-level `extends` of a config text, if any.
+pub(crate) fn declared(text: &str) -> Option<String> {
+    let doc = toml_edit::Document::parse(text).ok()?;
+    doc.as_table().get("extends")?.as_str().map(str::to_string)
+}
+
+/// Resolves the `extends` of the config in `config_dir`: reads the locked
+/// commit of a GitHub preset from the cache, or fetches it (the latest
+/// commit when `update` is set or nothing is locked yet) and locks it.
+///
+/// # Errors
+/// Returns an error if `extends` is malformed, the preset cannot be fetched
+/// or read, or `devflow.lock` cannot be read or written.
+pub fn resolve(extends: &str, config_dir: &Path, update: bool) -> Result<Preset> {
+    let source = PresetSource::from_str(extends).map_err(|e| anyhow!("invalid extends: {e}"))?;
+    match &source {
+        PresetSource::Path(path) => Ok(Preset {
+            source: extends.to_string(),
+            commit: None,
+            file: config_dir.join(path),
+        }),
+        PresetSource::Github { owner, repo, .. } => {
+            let url = format!("https://github.com/{owner}/{repo}.git");
+            resolve_github(extends, &source, &url, config_dir, update)
+        }
+    }
+}
+
+/// [`resolve`] for a GitHub preset fetched from `url`.
+fn resolve_github(
+    extends: &str,
+    source: &PresetSource,
+    url: &str,
+    config_dir: &Path,
+    update: bool,
+) -> Result<Preset> {
+    let PresetSource::Github {
+        owner,
+        repo,
+        reference,
+        file,
+    } = source
+    else {
+        unreachable!("resolve_github takes GitHub presets");
+    };
+    let lock_path = config_dir.join(PRESET_LOCK_FILE);
+    let locked = if update {
+        None
+    } else {
+        read_lock(&lock_path)?
+            .filter(|lock| lock.extends.source == extends)
+            .map(|lock| lock.extends.commit)
+    };
+    if let Some(commit) = locked.as_deref().filter(|commit| !is_commit(commit)) {
+        bail!(
+            "'{}' locks '{commit}', which is not a commit id; run 'dwf config:update'",
+            lock_path.display()
+        );
+    }
+    let cache = cache_root(config_dir)
+        .join("presets")
+        .join(owner)
+        .join(repo);
+    let cached = |commit: &str| cache.join(commit).join(file);
+    let commit = match locked {
+        Some(commit) if cached(&commit).is_file() => commit,
+        Some(commit) => fetch(url, &commit, file, &cache)
+            .with_context(|| format!("failed to fetch preset '{extends}' at {commit}"))?,
+        None => {
+            let want = reference.as_deref().unwrap_or("HEAD");
+            let commit = fetch(url, want, file, &cache)
+                .with_context(|| format!("failed to fetch preset '{extends}'"))?;
+            write_lock(&lock_path, extends, &commit)?;
+            commit
+        }
+    };
+    Ok(Preset {
+        source: extends.to_string(),
+        file: cached(&commit),
+        commit: Some(commit),
+    })
+}
+
+/// `DWF_CACHE_ROOT`, or the default cache root, relative to `config_dir`.
+fn cache_root(config_dir: &Path) -> PathBuf {
+    let root = std::env::var("DWF_CACHE_ROOT").unwrap_or_else(|_| DEFAULT_CACHE_ROOT.to_string());
+    config_dir.join(root)
+}
+
+fn read_lock(path: &Path) -> Result<Option<Lock>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    toml::from_str(&text)
+        .map(Some)
+        .with_context(|| format!("failed to parse '{}'", path.display()))
+}
+
+fn write_lock(path: &Path, source: &str, commit: &str) -> Result<()> {
+    let text = format!(
+        "# Written by dwf: the commit `extends` resolved to. Commit this file;\n\
+         # 'dwf config:update' moves it to the latest commit.\n\n\
+         [extends]\nsource = {}\ncommit = \"{commit}\"\n",
+        toml::Value::from(source)
+    );
+    std::fs::write(path, text).with_context(|| format!("failed to write '{}'", path.display()))
+}
+
+/// Fetches `want` (a ref or commit) of `url` and caches `file` of it under
+/// `cache/<commit>/`, returning the commit.
+fn fetch(url: &str, want: &str, file: &str, cache: &Path) -> Result<String> {
+    std::fs::create_dir_all(cache)
+        .with_context(|| format!("failed to create '{}'", cache.display()))?;
+    let scratch = cache.join(format!(".fetch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&scratch);
+    let result = (|| {
+        git(cache, &["init", "-q", "--bare", &scratch.to_string_lossy()])?;
+        git(&scratch, &["fetch", "-q", "--depth", "1", "--", url, want])?;
+        let commit = git(&scratch, &["rev-parse", "FETCH_HEAD"])?
+            .trim()
+            .to_string();
+        if !is_commit(&commit) {
+            bail!("git resolved '{want}' to '{commit}', which is not a commit id");
+        }
+        let content = git(&scratch, &["show", &format!("FETCH_HEAD:{file}")])
+            .map_err(|_| anyhow!("the repository has no '{file}' at {}", short(&commit)))?;
+        let target = cache.join(&commit).join(file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create '{}'", parent.display()))?;
+        }
+        std::fs::write(&target, content)
+            .with_context(|| format!("failed to write '{}'", target.display()))?;
+        Ok(commit)
+    })();
+    let _ = std::fs::remove_dir_all(&scratch);
+    result
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} exited with {}: {}",
+            args[0],
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("git printed non-UTF-8 output")
+}
+
+fn short(commit: &str) -> &str {
+    commit.get(..12).unwrap_or(commit)
+}
+
+/// Merges `overlay` over `base`: tables merge key by key, any other value of
+/// `overlay` replaces the one in `base`.
+pub fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Whether `table` sets the dotted `key` (e.g. `targets.pr[0]`) itself
+/// rather than inheriting it: arrays count as a whole.
+pub fn defines(table: &toml::Table, key: &str) -> bool {
+    let mut current = table;
+    let segments = diagnostic::parse_key(key);
+    let mut keys = segments.iter().map_while(|segment| match segment {
+        Segment::Key(name) => Some(name),
+        Segment::Index(_) => None,
+    });
+    let Some(mut name) = keys.next() else {
+        return false;
+    };
+    loop {
+        let Some(value) = current.get(name.as_str()) else {
+            return false;
+        };
+        match (value, keys.next()) {
+            (toml::Value::Table(table), Some(next)) => {
+                current = table;
+                name = next;
+            }
+            (_, None) => return true,
+            (_, Some(_)) => return false,
+        }
+    }
+}
+
+/// Parses a config or preset text as a table.
+pub(crate) fn table(path: &str, text: &str) -> Result<toml::Table> {
+    toml::from_str(text).map_err(|e| diagnostic::from_toml_error(&e, path, text).into())
+}
+
+/// Merges the config `text` over `preset`'s file, returning the merged text.
+///
+/// # Errors
+/// Returns an error if either file is not valid TOML, or the preset itself
+/// declares `extends`.
+pub(crate) fn merged_text(preset: &Preset, path: &str, text: &str) -> Result<String> {
+    let preset_path = preset.file.display().to_string();
+    let preset_text = std::fs::read_to_string(&preset.file)
+        .with_context(|| format!("failed to read preset '{preset_path}'"))?;
+    let mut merged = table(&preset_path, &preset_text)
+        .with_context(|| format!("failed to parse preset '{}'", preset.source))?;
+    if merged.contains_key("extends") {
+        bail!("preset '{}' cannot extend another preset", preset.source);
+    }
+    merge(&mut merged, table(path, text)?);
+    toml::to_string(&merged).context("failed to render the merged config")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_github_and_path_sources() {
+        assert_eq!(
+            PresetSource::from_str("github:myorg/devflow-presets@v2#services/rust").unwrap(),
+            PresetSource::Github {
+                owner: "myorg".to_string(),
+                repo: "devflow-presets".to_string(),
+                reference: Some("v2".to_string()),
+                file: "services/rust.toml".to_string(),
+            }
+        );
+        assert_eq!(
+            PresetSource::from_str("../presets/service.toml").unwrap(),
+            PresetSource::Path(PathBuf::from("../presets/service.toml"))
+        );
+        for invalid in [
+            "github:myorg#rust",
+            "github:myorg/presets",
+            "github:myorg/presets@#rust",
+            "github:myorg/presets@--upload-pack=touch#rust",
+            "github:myorg/presets@v1..v2#rust",
+            "github:myorg/presets@v2 x#rust",
+            "github:myorg/presets#../rust",
+            "presets/rust",
+        ] {
+            assert!(PresetSource::from_str(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn merges_tables_and_replaces_other_values() {
+        let mut base: toml::Table = toml::from_str(
+            "[targets]\npr = [\"fmt:check\", \"test:unit\"]\nmain = [\"build:release\"]\n\n[ci.runners]\ndefault = \"ubuntu-latest\"\n",
+        )
+        .unwrap();
+        let local: toml::Table =
+            toml::from_str("[project]\nname = \"svc\"\n\n[targets]\npr = [\"lint:static\"]\n")
+                .unwrap();
+        merge(&mut base, local.clone());
+        assert_eq!(base["targets"]["pr"].as_array().unwrap().len(), 1);
+        assert_eq!(base["targets"]["main"][0].as_str(), Some("build:release"));
+        assert_eq!(
+            base["ci"]["runners"]["default"].as_str(),
+            Some("ubuntu-latest")
+        );
+        assert!(defines(&local, "targets.pr[0]"));
+        assert!(!defines(&local, "targets.main"));
+        assert!(!defines(&local, "ci.runners.default"));
+        assert!(!defines(&local, "project.name.first"));
+    }
+
+    #[test]
+    fn fetches_locks_and_reuses_github_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("presets");
+        let run = |args: &[&str]| git(&repo, args).unwrap();
+        std::fs::create_dir_all(&repo).unwrap();
+        run(&["init", "-q"]);
+        std::fs::write(
+            repo.join("rust-service.toml"),
+            "[targets]\npr = [\"fmt:check\"]\n",
+        )
+        .unwrap();
+        run(&["add", "."]);
+        run(&[
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "commit",
+            "-qm",
+            "v1",
+        ]);
+        let first = run(&["rev-parse", "HEAD"]).trim().to_string();
+
+        let extends = "github:myorg/presets#rust-service";
+        let source = PresetSource::from_str(extends).unwrap();
+        let url = repo.to_str().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let preset = resolve_github(extends, &source, url, &project, false).unwrap();
+        assert_eq!(preset.commit.as_deref(), Some(first.as_str()));
+        assert!(preset.file.is_file());
+        let lock = std::fs::read_to_string(project.join(PRESET_LOCK_FILE)).unwrap();
+        assert!(lock.contains(&format!("commit = \"{first}\"")), "{lock}");
+
+        std::fs::write(
+            repo.join("rust-service.toml"),
+            "[targets]\npr = [\"test:unit\"]\n",
+        )
+        .unwrap();
+        run(&[
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "commit",
+            "-qam",
+            "v2",
+        ]);
+        // The lock pins the cached commit, even with the repository gone.
+        let offline = resolve_github(extends, &source, "/nonexistent", &project, false).unwrap();
+        assert_eq!(offline, preset);
+        // A fresh checkout fetches the locked commit, not the newest one.
+        std::fs::remove_dir_all(cache_root(&project)).unwrap();
+        let refetched = resolve_github(extends, &source, url, &project, false).unwrap();
+        assert_eq!(refetched, preset);
+        let updated = resolve_github(extends, &source, url, &project, true).unwrap();
+        assert_ne!(updated.commit.as_deref(), Some(first.as_str()));
+        let text = merged_text(&updated, "devflow.toml", "[project]\nname = \"svc\"\n").unwrap();
+        assert!(text.contains("test:unit"), "{text}");
+
+        let missing = PresetSource::from_str("github:myorg/presets#go-service").unwrap();
+        let err = resolve_github(
+            "github:myorg/presets#go-service",
+            &missing,
+            url,
+            &project,
+            true,
+        )
+        .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("no 'go-service.toml'"),
+            "{err:#}"
+        );
+
+        // A lock whose commit is not a commit id is neither fetched nor
+        // used as a cache path.
+        std::fs::write(
+            project.join(PRESET_LOCK_FILE),
+            format!("[extends]\nsource = \"{extends}\"\ncommit = \"../../escape\"\n"),
+        )
+        .unwrap();
+        let err = resolve_github(extends, &source, url, &project, false).unwrap_err();
+        assert!(err.to_string().contains("not a commit id"), "{err:#}");
+    }
+}
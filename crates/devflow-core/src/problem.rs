@@ -0,0 +1,292 @@
+//! Problem matchers: structured diagnostics from tool output.
+//!
+//! An extension declares [`ProblemMatcher`]s for its tools, in the shape of
+//! GitHub Actions problem matchers: one or more regex patterns that must match
+//! consecutive output lines, with capture group indices naming the file,
+//! line, column, severity and message.
+//!
+//! ```json
+//! {
+//!   "owner": "rustc",
+//!   "pattern": [
+//!     { "regexp": "^(warning|error)(?:\\[\\w+\\])?: (.+)$", "severity": 1, "message": 2 },
+//!     { "regexp": "^\\s+--> (.+?):(\\d+):(\\d+)$", "file": 1, "line": 2, "column": 3 }
+//!   ]
+//! }
+//! ```
+//!
+//! The executor compiles them into [`Matcher`]s and feeds every output line of
+//! a stream through a [`Scanner`], collecting [`Problem`]s.
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+    Notice,
+}
+
+impl Severity {
+    /// Maps a tool's severity word (`error`, `warning`, `note`, ...) to a
+    /// severity; unknown words are notices.
+    pub fn from_word(word: &str) -> Self {
+        match word.to_ascii_lowercase().as_str() {
+            "error" | "fatal" | "err" | "e" => Self::Error,
+            "warning" | "warn" | "w" => Self::Warning,
+            _ => Self::Notice,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Notice => "notice",
+        }
+    }
+}
+
+/// A diagnostic found in command output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    /// The matcher that found it, e.g. `rustc`.
+    pub owner: String,
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// A declared problem matcher.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemMatcher {
+    /// Name reported with each problem.
+    pub owner: String,
+    /// Severity when no pattern captures one.
+    #[serde(default)]
+    pub severity: Severity,
+    /// Patterns matching consecutive lines.
+    pub pattern: Vec<ProblemPattern>,
+}
+
+/// One line of a problem matcher, with 1-based capture group indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemPattern {
+    pub regexp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<usize>,
+}
+
+/// A compiled [`ProblemMatcher`].
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    owner: String,
+    severity: Severity,
+    patterns: Vec<(Regex, ProblemPattern)>,
+}
+
+impl Matcher {
+    /// Compiles `matcher`.
+    ///
+    /// # Errors
+    /// Returns an error if the matcher has no patterns, a regex is invalid, or
+    /// no pattern captures the message.
+    pub fn compile(matcher: &ProblemMatcher) -> Result<Self> {
+        if matcher.pattern.is_empty() {
+            bail!("problem matcher '{}' has no patterns", matcher.owner);
+        }
+        if matcher.pattern.iter().all(|p| p.message.is_none()) {
+            bail!("problem matcher '{}' captures no message", matcher.owner);
+        }
+        let patterns = matcher
+            .pattern
+            .iter()
+            .map(|pattern| {
+                let regex = Regex::new(&pattern.regexp).with_context(|| {
+                    format!("invalid regexp in problem matcher '{}'", matcher.owner)
+                })?;
+                Ok((regex, pattern.clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            owner: matcher.owner.clone(),
+            severity: matcher.severity,
+            patterns,
+        })
+    }
+}
+
+/// Per-stream matching state over a set of matchers.
+#[derive(Debug)]
+pub struct Scanner<'a> {
+    matchers: &'a [Matcher],
+    /// For each matcher: the next pattern index and the fields seen so far.
+    progress: Vec<(usize, Partial)>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Partial {
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    severity: Option<Severity>,
+    message: Option<String>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(matchers: &'a [Matcher]) -> Self {
+        Self {
+            matchers,
+            progress: vec![(0, Partial::default()); matchers.len()],
+        }
+    }
+
+    /// Feeds one output line (without ANSI codes), returning the problems it
+    /// completes.
+    pub fn line(&mut self, line: &str) -> Vec<Problem> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let mut found = Vec::new();
+        for (matcher, (next, partial)) in self.matchers.iter().zip(&mut self.progress) {
+            let mut captured = capture(&matcher.patterns[*next], line, partial);
+            if !captured && *next > 0 {
+                // The sequence broke off; this line may start a new one.
+                *next = 0;
+                *partial = Partial::default();
+                captured = capture(&matcher.patterns[0], line, partial);
+            }
+            if !captured {
+                continue;
+            }
+            *next += 1;
+            if *next == matcher.patterns.len() {
+                let done = std::mem::take(partial);
+                *next = 0;
+                if let Some(message) = done.message {
+                    found.push(Problem {
+                        owner: matcher.owner.clone(),
+                        severity: done.severity.unwrap_or(matcher.severity),
+                        message,
+                        file: done.file,
+                        line: done.line,
+                        column: done.column,
+                    });
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Matches `line` against `pattern`, recording its captures in `partial`.
+fn capture(pattern: &(Regex, ProblemPattern), line: &str, partial: &mut Partial) -> bool {
+    let (regex, fields) = pattern;
+    let Some(caps) = regex.captures(line) else {
+        return false;
+    };
+    let group = |index: Option<usize>| {
+        index
+            .and_then(|i| caps.get(i))
+            .map(|m| m.as_str().trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+    if let Some(file) = group(fields.file) {
+        partial.file = Some(file);
+    }
+    if let Some(line) = group(fields.line).and_then(|l| l.parse().ok()) {
+        partial.line = Some(line);
+    }
+    if let Some(column) = group(fields.column).and_then(|c| c.parse().ok()) {
+        partial.column = Some(column);
+    }
+    if let Some(severity) = group(fields.severity) {
+        partial.severity = Some(Severity::from_word(&severity));
+    }
+    if let Some(message) = group(fields.message) {
+        partial.message = Some(message);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rustc() -> ProblemMatcher {
+        ProblemMatcher {
+            owner: "rustc".to_string(),
+            severity: Severity::Error,
+            pattern: vec![
+                ProblemPattern {
+                    regexp: r"^(warning|error)(?:\[\w+\])?: (.+)$".to_string(),
+                    severity: Some(1),
+                    message: Some(2),
+                    ..ProblemPattern::default()
+                },
+                ProblemPattern {
+                    regexp: r"^\s+--> (.+?):(\d+):(\d+)$".to_string(),
+                    file: Some(1),
+                    line: Some(2),
+                    column: Some(3),
+                    ..ProblemPattern::default()
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn multi_line_patterns_must_match_consecutive_lines() {
+        let matchers = [Matcher::compile(&rustc()).unwrap()];
+        let mut scanner = Scanner::new(&matchers);
+        let output = "\
+   Compiling demo v0.1.0
+warning: unused variable: `x`
+  --> src/lib.rs:4:9
+error: aborting due to previous error
+error[E0308]: mismatched types
+   --> src/main.rs:12:5
+";
+        let problems: Vec<Problem> = output.lines().flat_map(|l| scanner.line(l)).collect();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].severity, Severity::Warning);
+        assert_eq!(problems[0].message, "unused variable: `x`");
+        assert_eq!(problems[0].file.as_deref(), Some("src/lib.rs"));
+        assert_eq!((problems[0].line, problems[0].column), (Some(4), Some(9)));
+        assert_eq!(problems[1].severity, Severity::Error);
+        assert_eq!(problems[1].message, "mismatched types");
+        assert_eq!(problems[1].line, Some(12));
+    }
+
+    #[test]
+    fn rejects_matchers_that_cannot_produce_problems() {
+        let mut matcher = rustc();
+        matcher.pattern[0].regexp = "(".to_string();
+        assert!(Matcher::compile(&matcher).is_err());
+        matcher.pattern.clear();
+        assert!(Matcher::compile(&matcher).is_err());
+
+        let parsed: ProblemMatcher = serde_json::from_str(
+            r#"{"owner":"tsc","pattern":[{"regexp":"^(.+)\\((\\d+),(\\d+)\\): (.+)$","file":1,"line":2,"column":3,"message":4}]}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.severity, Severity::Error);
+        let matchers = [Matcher::compile(&parsed).unwrap()];
+        let problems = Scanner::new(&matchers).line("src/a.ts(3,7): Type 'x' is not assignable");
+        assert_eq!(problems[0].file.as_deref(), Some("src/a.ts"));
+        assert_eq!(Severity::from_word("note"), Severity::Notice);
+    }
+}
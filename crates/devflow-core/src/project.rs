@@ -6,6 +6,9 @@ pub fn stack_is_applicable(base_path: &Path, stack: &str) -> bool {
     match stack {
         "rust" => base_path.join(MANIFEST_RUST).exists(),
         "node" => base_path.join(MANIFEST_NODE).exists(),
+        "go" => base_path.join(MANIFEST_GO).exists(),
+        "python" => base_path.join(MANIFEST_PYTHON).exists(),
+        "terraform" => has_terraform_files(base_path),
         "custom" => {
             base_path.join(TARGET_CUSTOM_JUST).exists()
                 || base_path.join(TARGET_CUSTOM_MAKE).exists()
@@ -14,3 +17,71 @@ pub fn stack_is_applicable(base_path: &Path, stack: &str) -> bool {
         _ => true,
     }
 }
+
+/// Manifests that identify a stack during auto-detection, in detection order.
+const STACK_MANIFESTS: &[(&str, &str)] = &[
+    ("rust", MANIFEST_RUST),
+    ("node", MANIFEST_NODE),
+    ("go", MANIFEST_GO),
+    ("python", MANIFEST_PYTHON),
+    ("terraform", "*.tf"),
+];
+
+/// Detects the stacks of the project at `base_path` from its manifest files.
+pub fn detect_stacks(base_path: &Path) -> Vec<String> {
+    STACK_MANIFESTS
+        .iter()
+        .filter(|(stack, _)| stack_is_applicable(base_path, stack))
+        .map(|(stack, _)| stack.to_string())
+        .collect()
+}
+
+/// Whether `dir` holds Terraform configuration; an empty `dir` is the
+/// current directory, as with the manifest checks.
+fn has_terraform_files(dir: &Path) -> bool {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    std::fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            let path = entry.path();
+            path.is_file()
+                && path.extension().and_then(|ext| ext.to_str()) == Some(TERRAFORM_EXTENSION)
+        })
+    })
+}
+
+/// The manifest names auto-detection looks for, for error messages.
+pub fn detectable_manifests() -> Vec<&'static str> {
+    STACK_MANIFESTS
+        .iter()
+        .map(|(_, manifest)| *manifest)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_stacks_from_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(detect_stacks(dir.path()).is_empty());
+
+        std::fs::write(dir.path().join(MANIFEST_PYTHON), "").unwrap();
+        std::fs::write(dir.path().join(MANIFEST_RUST), "").unwrap();
+        assert_eq!(detect_stacks(dir.path()), vec!["rust", "python"]);
+        assert!(stack_is_applicable(dir.path(), "python"));
+        assert!(!stack_is_applicable(dir.path(), "go"));
+
+        std::fs::write(dir.path().join("main.tf.bak"), "").unwrap();
+        assert!(!stack_is_applicable(dir.path(), "terraform"));
+        std::fs::write(dir.path().join("main.tf"), "").unwrap();
+        assert_eq!(
+            detect_stacks(dir.path()),
+            vec!["rust", "python", "terraform"]
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Run event hooks for reporters.
+//!
+//! A [`Reporter`] observes a run as it happens: each command's start, its
+//! output lines, what it skipped, its end, and the end of a
+//! `check:<profile>` run. Every hook
+//! has an empty default, so a reporter only implements what it needs.
+//! [`Reporters`] fans events out to several reporters.
+
+use serde::{Deserialize, Serialize};
+
+use crate::problem::Problem;
+
+/// The stream an output line was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// A finished command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandEnd {
+    /// Canonical command, e.g. `test:unit`.
+    pub command: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// The error the command failed with.
+    pub error: Option<String>,
+    /// The exit code of the process that failed the command; `None` when it
+    /// was killed by a signal, or the command failed without a process exit
+    /// (a timeout, a devflow error).
+    pub exit_code: Option<i32>,
+    /// The signal that killed the process that failed the command.
+    pub signal: Option<i32>,
+    /// Diagnostics found by problem matchers in the command's output.
+    pub problems: Vec<Problem>,
+    /// Workspace-relative paths of the reports a failed test kept, e.g.
+    /// image diffs (see `FAILURE_ARTIFACTS_DIR`).
+    pub artifacts: Vec<String>,
+}
+
+/// Why a command did not run, or did not run on a stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The stack's manifest (e.g. `Cargo.toml`) is missing.
+    ManifestNotFound,
+    /// The stack does not support the command.
+    UnsupportedCommand,
+    /// A higher-precedence stack handles the command (`conflict = "first"`).
+    Shadowed,
+    /// No changed file matches the profile entry's path filters.
+    PathFilter,
+    /// No changed file affects what the stack's command covers (`--affected`).
+    Unaffected,
+}
+
+impl SkipReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ManifestNotFound => "manifest not found",
+            Self::UnsupportedCommand => "unsupported command",
+            Self::Shadowed => "handled by higher-precedence stack (conflict = first)",
+            Self::PathFilter => "no changes match path filters",
+            Self::Unaffected => "no changes affect it (--affected)",
+        }
+    }
+
+    /// Whether the configuration asked for the skip (conflict policy, path
+    /// filters), rather than the project lacking what a stack needs.
+    pub fn is_expected(self) -> bool {
+        matches!(self, Self::Shadowed | Self::PathFilter | Self::Unaffected)
+    }
+}
+
+/// A command, or one stack's share of it, that was skipped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Skip {
+    /// Canonical command, e.g. `test:unit`.
+    pub command: String,
+    /// The skipped stack; `None` when the whole command was skipped.
+    pub stack: Option<String>,
+    pub reason: SkipReason,
+}
+
+impl std::fmt::Display for Skip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.stack {
+            Some(stack) => write!(f, "{} on {}: {}", self.command, stack, self.reason.as_str()),
+            None => write!(f, "{}: {}", self.command, self.reason.as_str()),
+        }
+    }
+}
+
+/// A finished `check:<profile>` run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckEnd {
+    pub profile: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Commands that ran (path-filtered skips excluded).
+    pub commands: usize,
+    /// Commands that failed.
+    pub failed: usize,
+    /// The code `dwf` exits with: 0, or that of the first failed command in
+    /// profile order.
+    #[serde(default)]
+    pub exit_code: i32,
+    /// Everything skipped during the run.
+    pub skipped: Vec<Skip>,
+}
+
+/// Observer of run events.
+///
+/// Hooks may be called from several threads (stdout and stderr are relayed
+/// concurrently), hence `Send + Sync`.
+pub trait Reporter: Send + Sync {
+    /// Called before `command` runs.
+    fn on_command_start(&self, _command: &str) {}
+
+    /// Called for each output line of `command`, without the line ending.
+    /// Only delivered when [`Reporter::wants_output`] is true.
+    fn on_output(&self, _command: &str, _stream: OutputStream, _line: &str) {}
+
+    /// Called when a command, or a stack's share of it, is skipped.
+    fn on_skip(&self, _skip: &Skip) {}
+
+    /// Called after a command finished.
+    fn on_command_end(&self, _end: &CommandEnd) {}
+
+    /// Called after a `check:<profile>` run finished.
+    fn on_check_end(&self, _end: &CheckEnd) {}
+
+    /// Whether this reporter consumes output lines. Capturing output pipes the
+    /// command's stdout and stderr through devflow, so it is opt-in.
+    fn wants_output(&self) -> bool {
+        false
+    }
+}
+
+/// A set of reporters receiving every event.
+#[derive(Default)]
+pub struct Reporters(Vec<Box<dyn Reporter>>);
+
+impl Reporters {
+    pub fn new(reporters: Vec<Box<dyn Reporter>>) -> Self {
+        Self(reporters)
+    }
+
+    pub fn push(&mut self, reporter: Box<dyn Reporter>) {
+        self.0.push(reporter);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Reporter for Reporters {
+    fn on_command_start(&self, command: &str) {
+        self.0.iter().for_each(|r| r.on_command_start(command));
+    }
+
+    fn on_output(&self, command: &str, stream: OutputStream, line: &str) {
+        for reporter in self.0.iter().filter(|r| r.wants_output()) {
+            reporter.on_output(command, stream, line);
+        }
+    }
+
+    fn on_skip(&self, skip: &Skip) {
+        self.0.iter().for_each(|r| r.on_skip(skip));
+    }
+
+    fn on_command_end(&self, end: &CommandEnd) {
+        self.0.iter().for_each(|r| r.on_command_end(end));
+    }
+
+    fn on_check_end(&self, end: &CheckEnd) {
+        self.0.iter().for_each(|r| r.on_check_end(end));
+    }
+
+    fn wants_output(&self) -> bool {
+        self.0.iter().any(|r| r.wants_output())
+    }
+}
+
+/// Lets a caller keep a handle on a reporter it registers.
+impl<T: Reporter + ?Sized> Reporter for std::sync::Arc<T> {
+    fn on_command_start(&self, command: &str) {
+        (**self).on_command_start(command);
+    }
+
+    fn on_output(&self, command: &str, stream: OutputStream, line: &str) {
+        (**self).on_output(command, stream, line);
+    }
+
+    fn on_skip(&self, skip: &Skip) {
+        (**self).on_skip(skip);
+    }
+
+    fn on_command_end(&self, end: &CommandEnd) {
+        (**self).on_command_end(end);
+    }
+
+    fn on_check_end(&self, end: &CheckEnd) {
+        (**self).on_check_end(end);
+    }
+
+    fn wants_output(&self) -> bool {
+        (**self).wants_output()
+    }
+}
+
+impl std::fmt::Debug for Reporters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Reporters({})", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Log {
+        events: Mutex<Vec<String>>,
+        output: bool,
+    }
+
+    impl Reporter for Log {
+        fn on_command_start(&self, command: &str) {
+            self.events.lock().unwrap().push(format!("start {command}"));
+        }
+
+        fn on_output(&self, command: &str, _stream: OutputStream, line: &str) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{command}: {line}"));
+        }
+
+        fn on_skip(&self, skip: &Skip) {
+            self.events.lock().unwrap().push(format!("skip {skip}"));
+        }
+
+        fn wants_output(&self) -> bool {
+            self.output
+        }
+    }
+
+    #[test]
+    fn fans_out_and_filters_output() {
+        let quiet = Arc::new(Log::default());
+        let verbose = Arc::new(Log {
+            output: true,
+            ..Log::default()
+        });
+        let reporters = Reporters::new(vec![Box::new(quiet.clone()), Box::new(verbose.clone())]);
+        assert!(reporters.wants_output());
+        reporters.on_command_start("test:unit");
+        reporters.on_output("test:unit", OutputStream::Stdout, "ok");
+        reporters.on_skip(&Skip {
+            command: "test:unit".to_string(),
+            stack: Some("node".to_string()),
+            reason: SkipReason::ManifestNotFound,
+        });
+        reporters.on_command_end(&CommandEnd {
+            command: "test:unit".to_string(),
+            success: true,
+            duration_ms: 5,
+            error: None,
+            exit_code: None,
+            signal: None,
+            problems: Vec::new(),
+            artifacts: Vec::new(),
+        });
+
+        assert_eq!(
+            *quiet.events.lock().unwrap(),
+            [
+                "start test:unit",
+                "skip test:unit on node: manifest not found"
+            ]
+        );
+        assert_eq!(
+            *verbose.events.lock().unwrap(),
+            [
+                "start test:unit",
+                "test:unit: ok",
+                "skip test:unit on node: manifest not found"
+            ]
+        );
+        assert!(!Reporters::default().wants_output());
+    }
+}
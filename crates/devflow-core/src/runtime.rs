@@ -1,5 +1,10 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::constants::{DEVCONTAINER_FILE, NIX_FLAKE};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum RuntimeProfile {
@@ -7,4 +12,252 @@ pub enum RuntimeProfile {
     Host,
     #[default]
     Auto,
+    /// Run commands on a remote build machine over SSH (see `[runtime.remote]`).
+    Remote,
+}
+
+/// Development environment that commands are executed in.
+///
+/// An alternative to the container proxy: Nix wraps host commands in
+/// `nix develop -c`, devcontainer runs them in the image declared by
+/// `.devcontainer/devcontainer.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeEnv {
+    /// Detect from project files when the runtime profile is `auto`.
+    #[default]
+    Auto,
+    /// Never use a development environment integration.
+    None,
+    /// Run commands through `nix develop -c`.
+    Nix,
+    /// Run commands inside the devcontainer image.
+    Devcontainer,
+}
+
+impl RuntimeEnv {
+    /// Resolves `Auto` against the project at `base_path`.
+    ///
+    /// Detection only applies to the `auto` runtime profile, so explicit
+    /// `host`/`container` profiles keep their behavior. A `flake.nix` takes
+    /// precedence over a devcontainer definition.
+    pub fn resolve(self, profile: RuntimeProfile, base_path: &Path) -> RuntimeEnv {
+        match self {
+            RuntimeEnv::Auto if profile == RuntimeProfile::Auto => {
+                if base_path.join(NIX_FLAKE).is_file() {
+                    RuntimeEnv::Nix
+                } else if base_path.join(DEVCONTAINER_FILE).is_file() {
+                    RuntimeEnv::Devcontainer
+                } else {
+                    RuntimeEnv::None
+                }
+            }
+            RuntimeEnv::Auto => RuntimeEnv::None,
+            explicit => explicit,
+        }
+    }
+}
+
+/// The host's container platform (e.g. `linux/amd64`, `linux/arm64`).
+pub fn host_platform() -> String {
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        other => other,
+    };
+    format!("linux/{arch}")
+}
+
+/// Picks the platform to run when none of `platforms` matches `host`.
+///
+/// Returns `None` when nothing is configured or the host platform is listed,
+/// so the engine runs its native variant; otherwise the first configured
+/// platform, which the engine will have to emulate.
+pub fn emulated_platform<'a>(platforms: &'a [String], host: &str) -> Option<&'a str> {
+    if platforms.is_empty() || platforms.iter().any(|p| p == host) {
+        return None;
+    }
+    platforms.first().map(String::as_str)
+}
+
+#[derive(Debug, Deserialize)]
+struct DevcontainerSpec {
+    image: Option<String>,
+    build: Option<serde_json::Value>,
+}
+
+/// Reads the image declared by `.devcontainer/devcontainer.json` under `base_path`.
+///
+/// # Errors
+/// Returns an error if the file is missing, is not valid JSON (comments and
+/// trailing commas are accepted), or builds from a Dockerfile instead of
+/// naming an image.
+pub fn devcontainer_image(base_path: &Path) -> Result<String> {
+    let path = base_path.join(DEVCONTAINER_FILE);
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let spec: DevcontainerSpec = serde_json::from_str(&strip_jsonc(&text))
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+
+    match (spec.image, spec.build) {
+        (Some(image), _) => Ok(image),
+        (None, Some(_)) => bail!(
+            "{} builds from a Dockerfile; only image-based devcontainers are supported",
+            path.display()
+        ),
+        (None, None) => bail!("{} does not declare an image", path.display()),
+    }
+}
+
+/// Converts JSON-with-comments into plain JSON by removing comments and trailing commas.
+fn strip_jsonc(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                if let Some(&next) = chars.get(i + 1) {
+                    out.push(next);
+                    i += 1;
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match (c, chars.get(i + 1)) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            ('/', Some('*')) => {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            (',', _) => {
+                let next = chars[i + 1..].iter().find(|ch| !ch.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn auto_env_detects_nix_before_devcontainer() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            RuntimeEnv::Auto.resolve(RuntimeProfile::Auto, dir.path()),
+            RuntimeEnv::None
+        );
+
+        fs::create_dir_all(dir.path().join(".devcontainer")).unwrap();
+        fs::write(dir.path().join(DEVCONTAINER_FILE), "{}").unwrap();
+        assert_eq!(
+            RuntimeEnv::Auto.resolve(RuntimeProfile::Auto, dir.path()),
+            RuntimeEnv::Devcontainer
+        );
+
+        fs::write(dir.path().join(NIX_FLAKE), "{ }").unwrap();
+        assert_eq!(
+            RuntimeEnv::Auto.resolve(RuntimeProfile::Auto, dir.path()),
+            RuntimeEnv::Nix
+        );
+    }
+
+    #[test]
+    fn explicit_profiles_disable_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(NIX_FLAKE), "{ }").unwrap();
+        assert_eq!(
+            RuntimeEnv::Auto.resolve(RuntimeProfile::Host, dir.path()),
+            RuntimeEnv::None
+        );
+        assert_eq!(
+            RuntimeEnv::Nix.resolve(RuntimeProfile::Container, dir.path()),
+            RuntimeEnv::Nix
+        );
+    }
+
+    #[test]
+    fn emulates_only_when_host_platform_is_not_configured() {
+        let both = vec!["linux/amd64".to_string(), "linux/arm64".to_string()];
+        assert_eq!(emulated_platform(&both, "linux/arm64"), None);
+        assert_eq!(emulated_platform(&[], "linux/arm64"), None);
+        let amd64 = vec!["linux/amd64".to_string()];
+        assert_eq!(
+            emulated_platform(&amd64, "linux/arm64"),
+            Some("linux/amd64")
+        );
+        assert!(host_platform().starts_with("linux/"));
+    }
+
+    #[test]
+    fn reads_image_from_jsonc_devcontainer() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".devcontainer")).unwrap();
+        fs::write(
+            dir.path().join(DEVCONTAINER_FILE),
+            r#"{
+                // Rust toolchain image
+                "name": "demo",
+                "image": "mcr.microsoft.com/devcontainers/rust:1", /* pinned */
+                "features": {},
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            devcontainer_image(dir.path()).unwrap(),
+            "mcr.microsoft.com/devcontainers/rust:1"
+        );
+    }
+
+    #[test]
+    fn rejects_dockerfile_devcontainers() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".devcontainer")).unwrap();
+        fs::write(
+            dir.path().join(DEVCONTAINER_FILE),
+            r#"{ "build": { "dockerfile": "Dockerfile" } }"#,
+        )
+        .unwrap();
+
+        let err = devcontainer_image(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("only image-based devcontainers"));
+    }
+
+    #[test]
+    fn strip_jsonc_keeps_comment_markers_inside_strings() {
+        let out = strip_jsonc(r#"{"url": "https://example.com/*x*/", "a": [1, 2,],}"#);
+        assert_eq!(out, r#"{"url": "https://example.com/*x*/", "a": [1, 2]}"#);
+    }
 }
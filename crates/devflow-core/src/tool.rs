@@ -0,0 +1,89 @@
+//! Tool requirements: pinned binaries an extension's commands rely on.
+//!
+//! An extension declares the tools it needs beyond the toolchain itself
+//! (e.g. `cargo-nextest`, `sccache`) with an exact version and a release
+//! download URL. `dwf setup:tools` installs them into a devflow-managed
+//! directory that host runs put on `PATH`.
+//!
+//! ```json
+//! {
+//!   "name": "sccache",
+//!   "version": "0.7.7",
+//!   "url": "https://github.com/mozilla/sccache/releases/download/v{version}/sccache-v{version}-{target}.tar.gz",
+//!   "targets": { "x86_64-unknown-linux-gnu": "x86_64-unknown-linux-musl" }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A pinned tool an extension needs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRequirement {
+    /// Tool name, also the binary name unless `bin` is set.
+    pub name: String,
+    /// Exact version installed.
+    pub version: String,
+    /// Download URL of a release archive (`.tar.gz`, `.tgz`, `.tar.xz`,
+    /// `.zip`) or a bare binary, with `{version}` and `{target}` placeholders.
+    pub url: String,
+    /// Binary inside the archive, when it differs from `name`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<String>,
+    /// Release target names that differ from the host target triple.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub targets: BTreeMap<String, String>,
+    /// Expected SHA-256 of the download, by host target triple.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sha256: BTreeMap<String, String>,
+}
+
+impl ToolRequirement {
+    /// The binary this tool provides.
+    pub fn bin(&self) -> &str {
+        self.bin.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The download URL for the host `target` triple.
+    pub fn url_for(&self, target: &str) -> String {
+        let release = self.targets.get(target).map_or(target, String::as_str);
+        self.url
+            .replace("{version}", &self.version)
+            .replace("{target}", release)
+    }
+}
+
+/// The target triple of the running host, if tools can be installed for it.
+pub fn host_target() -> Option<&'static str> {
+    match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => Some("x86_64-unknown-linux-gnu"),
+        ("aarch64", "linux") => Some("aarch64-unknown-linux-gnu"),
+        ("x86_64", "macos") => Some("x86_64-apple-darwin"),
+        ("aarch64", "macos") => Some("aarch64-apple-darwin"),
+        ("x86_64", "windows") => Some("x86_64-pc-windows-msvc"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_url_placeholders_per_target() {
+        let tool: ToolRequirement = serde_json::from_str(
+            r#"{"name":"sccache","version":"0.7.7","url":"https://example.com/v{version}/sccache-{target}.tar.gz","targets":{"x86_64-unknown-linux-gnu":"x86_64-unknown-linux-musl"}}"#,
+        )
+        .unwrap();
+        assert_eq!(tool.bin(), "sccache");
+        assert_eq!(
+            tool.url_for("x86_64-unknown-linux-gnu"),
+            "https://example.com/v0.7.7/sccache-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            tool.url_for("aarch64-apple-darwin"),
+            "https://example.com/v0.7.7/sccache-aarch64-apple-darwin.tar.gz"
+        );
+    }
+}
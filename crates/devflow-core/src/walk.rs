@@ -0,0 +1,394 @@
+//! Ignore-aware directory walking.
+//!
+//! Walks a directory tree the way `git status` sees it: `.git` is skipped,
+//! and the `.gitignore` and [`IGNORE_FILE`] of every directory exclude
+//! entries beneath it. Both files use the gitignore syntax supported by
+//! [`crate::glob`]: blank lines and `#` comments are skipped, `!` re-includes,
+//! a trailing `/` only matches directories, and a pattern with a `/` other
+//! than a trailing one is anchored to the directory of its file while any
+//! other pattern matches a name at any depth. The last matching pattern wins,
+//! with `.devflowignore` read after `.gitignore`. An ignored directory is not
+//! entered. Symlinks are not followed.
+//!
+//! [`size`] walks with a pool of threads sharing a queue of directories, as
+//! cache directories such as a cargo `target` hold hundreds of thousands of
+//! files.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use anyhow::{Context, Result};
+
+use crate::constants::IGNORE_FILE;
+use crate::glob;
+
+/// The ignore files read in every directory, in precedence order.
+const IGNORE_FILES: [&str; 2] = [".gitignore", IGNORE_FILE];
+
+/// Upper bound on the threads of a [`size`] walk.
+const MAX_WORKERS: usize = 8;
+
+/// Running totals of a [`size_with_progress`] walk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeProgress {
+    /// Files counted so far.
+    pub files: u64,
+    /// Bytes counted so far.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// The directory of the ignore file, relative to the walk root.
+    base: String,
+    pattern: String,
+    negate: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(base: &str, line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = line.strip_prefix('/').unwrap_or(line);
+        if pattern.is_empty() {
+            return None;
+        }
+        Some(Self {
+            base: base.to_string(),
+            pattern: pattern.to_string(),
+            negate,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule matches `path` (relative to the walk root).
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let local = if self.base.is_empty() {
+            path
+        } else {
+            match path
+                .strip_prefix(self.base.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                Some(local) => local,
+                None => return false,
+            }
+        };
+        if self.anchored {
+            glob::matches(&self.pattern, local)
+        } else {
+            let name = local.rsplit('/').next().unwrap_or(local);
+            glob::matches(&self.pattern, name)
+        }
+    }
+}
+
+/// Whether the last of `rules` matching `path` excludes it.
+fn ignored(rules: &[Rule], path: &str, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.matches(path, is_dir))
+        .is_some_and(|rule| !rule.negate)
+}
+
+/// The files under `root` that are not ignored, sorted. `root` itself is
+/// returned if it is a file, and nothing if it does not exist.
+///
+/// # Errors
+/// Returns an error if a directory or ignore file cannot be read.
+pub fn files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    match fs::symlink_metadata(root) {
+        Ok(meta) if meta.is_dir() => visit(root, "", &mut Vec::new(), &mut files)?,
+        Ok(_) => files.push(root.to_path_buf()),
+        Err(_) => {}
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The total size in bytes of the [`files`] under `root`; unreadable entries
+/// count as empty.
+pub fn size(root: &Path) -> u64 {
+    size_with_progress(root, |_| {})
+}
+
+/// [`size`], calling `progress` with the running totals after each directory.
+/// `progress` is called from the walking threads.
+pub fn size_with_progress(root: &Path, progress: impl Fn(SizeProgress) + Sync) -> u64 {
+    match fs::symlink_metadata(root) {
+        Ok(meta) if meta.is_dir() => {}
+        Ok(meta) => return meta.len(),
+        Err(_) => return 0,
+    }
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_WORKERS);
+    let walk = SizeWalk {
+        queue: Mutex::new(Queue {
+            dirs: vec![(root.to_path_buf(), String::new(), Arc::new(Vec::new()))],
+            pending: 1,
+        }),
+        ready: Condvar::new(),
+        files: AtomicU64::new(0),
+        bytes: AtomicU64::new(0),
+    };
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| walk.work(&progress));
+        }
+    });
+    walk.bytes.into_inner()
+}
+
+/// A directory left to walk: its path, its path relative to the root and the
+/// rules in effect in it.
+type Job = (PathBuf, String, Arc<Vec<Rule>>);
+
+struct Queue {
+    dirs: Vec<Job>,
+    /// Directories queued or being walked.
+    pending: usize,
+}
+
+struct SizeWalk {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+    files: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl SizeWalk {
+    fn work(&self, progress: &impl Fn(SizeProgress)) {
+        while let Some((dir, rel, inherited)) = self.next() {
+            let own = dir_rules(&dir, &rel).unwrap_or_default();
+            let rules = if own.is_empty() {
+                inherited
+            } else {
+                Arc::new(inherited.iter().cloned().chain(own).collect())
+            };
+
+            let mut subdirs = Vec::new();
+            let (mut files, mut bytes) = (0, 0);
+            for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+                let Some((path_rel, is_dir)) = entry_rel(&entry, &rel) else {
+                    continue;
+                };
+                if ignored(&rules, &path_rel, is_dir) {
+                    continue;
+                }
+                if is_dir {
+                    subdirs.push((entry.path(), path_rel, Arc::clone(&rules)));
+                } else {
+                    files += 1;
+                    bytes += entry.metadata().map_or(0, |meta| meta.len());
+                }
+            }
+            progress(SizeProgress {
+                files: self.files.fetch_add(files, Ordering::Relaxed) + files,
+                bytes: self.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes,
+            });
+
+            let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+            queue.pending += subdirs.len();
+            queue.pending -= 1;
+            queue.dirs.extend(subdirs);
+            self.ready.notify_all();
+        }
+    }
+
+    /// The next directory to walk, or `None` once every directory is done.
+    fn next(&self) -> Option<Job> {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(job) = queue.dirs.pop() {
+                return Some(job);
+            }
+            if queue.pending == 0 {
+                return None;
+            }
+            queue = self.ready.wait(queue).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+/// The rules of the ignore files in `dir`, at `rel` from the walk root.
+fn dir_rules(dir: &Path, rel: &str) -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for name in IGNORE_FILES {
+        let path = dir.join(name);
+        if path.is_file() {
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            rules.extend(text.lines().filter_map(|line| Rule::parse(rel, line)));
+        }
+    }
+    Ok(rules)
+}
+
+/// The path of `entry` relative to the walk root and whether it is a
+/// directory, or `None` for `.git` and entries whose type cannot be read.
+fn entry_rel(entry: &fs::DirEntry, rel: &str) -> Option<(String, bool)> {
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+    if name == ".git" {
+        return None;
+    }
+    let is_dir = entry.file_type().ok()?.is_dir();
+    let path_rel = if rel.is_empty() {
+        name.to_string()
+    } else {
+        format!("{rel}/{name}")
+    };
+    Some((path_rel, is_dir))
+}
+
+fn visit(dir: &Path, rel: &str, rules: &mut Vec<Rule>, files: &mut Vec<PathBuf>) -> Result<()> {
+    let inherited = rules.len();
+    rules.extend(dir_rules(dir, rel)?);
+
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("failed to read directory {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let Some((path_rel, _)) = entry_rel(&entry, rel) else {
+            continue;
+        };
+        if ignored(rules, &path_rel, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            visit(&entry.path(), &path_rel, rules, files)?;
+        } else {
+            files.push(entry.path());
+        }
+    }
+
+    rules.truncate(inherited);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn listed(root: &Path) -> Vec<String> {
+        files(root)
+            .unwrap()
+            .iter()
+            .map(|file| {
+                file.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn skips_ignored_entries_and_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            ".gitignore",
+            "# build output\ntarget/\n*.log\n!keep.log\n/local.txt\n",
+        );
+        write(root, ".devflowignore", "docs/*.md\n!docs/README.md\n");
+        write(root, ".git/HEAD", "ref: refs/heads/main\n");
+        write(root, "src/main.rs", "fn main() {}\n");
+        write(root, "src/debug.log", "x");
+        write(root, "src/keep.log", "x");
+        write(root, "src/local.txt", "x");
+        write(root, "local.txt", "x");
+        write(root, "target/debug/app", "x");
+        write(root, "docs/guide.md", "x");
+        write(root, "docs/README.md", "x");
+        write(root, "crates/a/.gitignore", "fixtures\n");
+        write(root, "crates/a/fixtures/big.bin", "x");
+        write(root, "crates/b/fixtures/small.bin", "x");
+
+        assert_eq!(
+            listed(root),
+            [
+                ".devflowignore",
+                ".gitignore",
+                "crates/a/.gitignore",
+                "crates/b/fixtures/small.bin",
+                "docs/README.md",
+                "src/keep.log",
+                "src/local.txt",
+                "src/main.rs",
+            ]
+        );
+        assert_eq!(size(&root.join("src")), "fn main() {}\n".len() as u64 + 3);
+    }
+
+    #[test]
+    fn sizes_wide_trees_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for i in 0..20 {
+            for j in 0..5 {
+                write(root, &format!("d{i}/e{j}/file.bin"), "12345");
+            }
+        }
+        write(root, "d3/.devflowignore", "e*/\n");
+
+        let last = Mutex::new(SizeProgress::default());
+        let total = size_with_progress(root, |progress| {
+            let mut last = last.lock().unwrap();
+            // Totals only grow; threads may report them out of order.
+            if progress.bytes > last.bytes {
+                *last = progress;
+            }
+        });
+        let expected = 19 * 5 * 5 + "e*/\n".len() as u64;
+        assert_eq!(total, expected);
+        assert_eq!(total, size(root));
+        assert_eq!(
+            last.into_inner().unwrap(),
+            SizeProgress {
+                files: 19 * 5 + 1,
+                bytes: expected
+            }
+        );
+    }
+
+    #[test]
+    fn handles_files_and_missing_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "one.txt", "abc");
+        assert_eq!(files(&dir.path().join("one.txt")).unwrap().len(), 1);
+        assert_eq!(size(&dir.path().join("one.txt")), 3);
+        assert!(files(&dir.path().join("missing")).unwrap().is_empty());
+        assert_eq!(size(&dir.path().join("missing")), 0);
+    }
+}
@@ -4,21 +4,73 @@
 //! `npm` commands, enabling JavaScript/TypeScript workflows to integrate transparently
 //! into the Devflow ecosystem.
 
-use anyhow::Result;
-use devflow_core::{CommandRef, ExecutionAction, Extension};
-use std::collections::HashSet;
+use anyhow::{bail, Context, Result};
+use devflow_core::config::{RemoteCacheConfig, VisualConfig};
+use devflow_core::problem::{ProblemMatcher, ProblemPattern, Severity};
+use devflow_core::{
+    CapabilityInfo, CommandRef, DevflowConfig, ExecutionAction, Extension, VolumeMount,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Project configs `lint:types` checks, in order of preference.
+const TSCONFIGS: &[&str] = &["tsconfig.json", "tsconfig.app.json", "tsconfig.build.json"];
+
+/// The variable `test:visual` passes `[extensions.node.visual] threshold` in.
+const VISUAL_THRESHOLD_ENV: &str = "DEVFLOW_VISUAL_THRESHOLD";
+
+/// Extensions of the runner configs `test:e2e` detects, e.g. `playwright.config.ts`.
+const E2E_CONFIG_EXTENSIONS: &[&str] = &["ts", "js", "mjs", "cjs", "mts", "cts"];
+
+/// Where task runners keep their local cache, mounted from the devflow cache.
+const TURBO_CACHE_DIR: &str = "/root/.cache/turbo";
+const NX_CACHE_DIR: &str = "/root/.cache/nx";
 
 /// The Devflow extension for Node.js.
 ///
 /// Discovers project capabilities and maps primary Devflow actions into
 /// localized `npm` invocations (e.g., `npm run build`, `npm ci`).
 #[derive(Debug, Default)]
-pub struct NodeExtension;
+pub struct NodeExtension {
+    visual: VisualConfig,
+    remote_cache: Option<RemoteCacheConfig>,
+}
 
 impl NodeExtension {
     /// Constructs a new [`NodeExtension`].
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Constructs a [`NodeExtension`] with the settings of `[extensions.node]`.
+    pub fn from_config(cfg: &DevflowConfig) -> Self {
+        Self {
+            visual: cfg.visual(),
+            remote_cache: cfg.remote_cache(),
+        }
+    }
+
+    /// `task` through the monorepo's task runner, if the project has one.
+    fn task_action(&self, task: &str) -> Option<ExecutionAction> {
+        let runner = TaskRunner::detect(Path::new("."))?;
+        let token = self
+            .remote_cache
+            .as_ref()
+            .and_then(|cache| std::env::var(&cache.token_env).ok())
+            .filter(|token| !token.is_empty());
+        Some(runner.run(task, self.remote_cache.as_ref(), token))
+    }
+
+    /// `test:visual`: the configured script, told the tolerated pixel
+    /// difference through `DEVFLOW_VISUAL_THRESHOLD`.
+    fn visual_action(&self) -> ExecutionAction {
+        let mut visual = action("npm", &["run", &self.visual.script]);
+        if let Some(threshold) = self.visual.threshold {
+            visual
+                .env
+                .insert(VISUAL_THRESHOLD_ENV.to_string(), threshold.to_string());
+        }
+        visual
     }
 }
 
@@ -33,12 +85,18 @@ impl Extension for NodeExtension {
             "fmt:check",
             "fmt:fix",
             "lint:static",
+            "lint:types",
             "build:debug",
             "build:release",
             "test:unit",
             "test:integration",
+            "test:e2e",
+            "test:visual",
             "package:artifact",
+            "package:attest",
             "check",
+            "check:lockfiles",
+            "check:repro",
             "release",
             "ci:generate",
             "ci:check",
@@ -48,6 +106,35 @@ impl Extension for NodeExtension {
         .collect()
     }
 
+    fn capability_info(&self) -> BTreeMap<String, CapabilityInfo> {
+        [
+            ("setup", "Setup", "Install dependencies (npm ci) and the end-to-end test browsers".to_string()),
+            ("fmt:check", "Quality", "Run the fmt:check script".to_string()),
+            ("fmt:fix", "Quality", "Run the fmt:fix script".to_string()),
+            ("lint:static", "Quality", "Run the lint script".to_string()),
+            ("lint:types", "Quality", "Type-check the project (tsc --noEmit)".to_string()),
+            ("build:debug", "Build", "Run the build script, through the task runner if there is one".to_string()),
+            ("build:release", "Build", "Run the build script, through the task runner if there is one".to_string()),
+            ("test:unit", "Test", "Run the test:unit script, through the task runner if there is one".to_string()),
+            ("test:integration", "Test", "Run the test:integration script, through the task runner if there is one".to_string()),
+            ("test:e2e", "Test", "Run the Playwright or Cypress end-to-end tests".to_string()),
+            ("test:visual", "Test", format!("Run the {} script for visual regressions", self.visual.script)),
+            ("package:artifact", "Package", "List what npm would publish (npm pack --dry-run)".to_string()),
+            ("package:attest", "Package", "Write SLSA provenance for the package".to_string()),
+            ("check", "Verification", "Run a [targets] profile, e.g. check:pr".to_string()),
+            ("check:lockfiles", "Verification", "Fail if package-lock.json does not satisfy package.json (npm ls --package-lock-only)".to_string()),
+            ("check:repro", "Verification", "Build twice and compare the outputs".to_string()),
+            ("release", "Release", "Verify (npm publish --dry-run), pack (npm pack), tag and publish (npm publish)".to_string()),
+            ("ci:generate", "CI", "Generate the GitHub Actions workflow".to_string()),
+            ("ci:check", "CI", "Check the workflow against devflow.toml".to_string()),
+        ]
+        .into_iter()
+        .map(|(capability, group, description)| {
+            (capability.to_string(), CapabilityInfo::new(group, description))
+        })
+        .collect()
+    }
+
     fn build_action(&self, cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
         let primary = cmd.primary.as_str();
         let selector = cmd.selector.as_deref().unwrap_or("");
@@ -55,26 +142,68 @@ impl Extension for NodeExtension {
         let action = match (primary, selector) {
             ("setup", "deps") => Some(action("npm", &["ci"])),
             ("setup", "doctor") => Some(action("npm", &["--version"])),
+            ("setup", "browsers") => Some(E2eRunner::require(Path::new("."))?.install()),
+            // Reports dependencies package.json requires but package-lock.json lacks or pins
+            // outside the range, without touching node_modules.
+            ("check", "lockfiles") => Some(action("npm", &["ls", "--package-lock-only"])),
             ("fmt", "check") => Some(action("npm", &["run", "fmt:check"])),
             ("fmt", "fix") => Some(action("npm", &["run", "fmt:fix"])),
             ("lint", "static") => Some(action("npm", &["run", "lint"])),
-            ("build", "debug") => Some(action("npm", &["run", "build"])),
-            ("build", "release") => Some(action("npm", &["run", "build"])),
-            ("test", "unit") => Some(action("npm", &["run", "test:unit"])),
-            ("test", "integration") => Some(action("npm", &["run", "test:integration"])),
-            ("test", "smoke") => Some(action("npm", &["run", "test:smoke"])),
+            ("lint", "types") => Some(types_action(Path::new("."))?),
+            ("build", "debug" | "release") => self
+                .task_action("build")
+                .or_else(|| Some(action("npm", &["run", "build"]))),
+            ("test", suite @ ("unit" | "integration" | "smoke")) => {
+                let script = format!("test:{suite}");
+                self.task_action(&script)
+                    .or_else(|| Some(action("npm", &["run", &script])))
+            }
+            ("test", "e2e") => Some(E2eRunner::require(Path::new("."))?.test()),
+            ("test", "visual") => Some(self.visual_action()),
             ("package", "artifact") => Some(action("npm", &["pack", "--dry-run"])),
+            ("release", "verify") => Some(action("npm", &["publish", "--dry-run"])),
+            ("release", "candidate") => Some(action("npm", &["pack"])),
+            ("release", "publish") => Some(action("npm", &["publish"])),
             _ => None,
         };
         Ok(action)
     }
 
+    fn release_version(&self) -> Result<Option<String>> {
+        let manifest = std::fs::read_to_string("package.json")
+            .context("release:tag reads the version from package.json")?;
+        package_version(&manifest)
+    }
+
+    fn toolchain_dirs(&self) -> Vec<PathBuf> {
+        // The active node of nvm, volta and fnm; a system node is found in
+        // the system directories.
+        [
+            std::env::var_os("NVM_BIN").map(PathBuf::from),
+            std::env::var_os("VOLTA_HOME").map(|home| PathBuf::from(home).join("bin")),
+            std::env::var_os("FNM_MULTISHELL_PATH").map(|dir| PathBuf::from(dir).join("bin")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
     fn is_trusted(&self) -> bool {
         true
     }
 
     fn cache_mounts(&self) -> Vec<String> {
-        vec!["node/npm:/root/.npm".to_string()]
+        let mut mounts = vec!["node/npm:/root/.npm".to_string()];
+        mounts.extend(TaskRunner::detect(Path::new(".")).map(TaskRunner::cache_mount));
+        mounts
+    }
+
+    fn volume_mounts(&self) -> Vec<VolumeMount> {
+        vec![VolumeMount {
+            name: "node_modules".to_string(),
+            path: "node_modules".to_string(),
+            key_inputs: self.fingerprint_inputs(),
+        }]
     }
 
     fn env_vars(&self) -> std::collections::HashMap<String, String> {
@@ -83,6 +212,14 @@ impl Extension for NodeExtension {
         env
     }
 
+    fn secret_env(&self) -> Vec<String> {
+        // The remote cache tokens `TaskRunner::run` passes.
+        vec![
+            "TURBO_TOKEN".to_string(),
+            "NX_SELF_HOSTED_REMOTE_CACHE_ACCESS_TOKEN".to_string(),
+        ]
+    }
+
     fn fingerprint_inputs(&self) -> Vec<String> {
         vec![
             "package-lock.json".to_string(),
@@ -91,6 +228,212 @@ impl Extension for NodeExtension {
             "package.json".to_string(),
         ]
     }
+
+    fn artifact_patterns(&self, cmd: &CommandRef) -> Vec<String> {
+        match (cmd.primary.as_str(), cmd.selector.as_deref().unwrap_or("")) {
+            ("build", _) => vec!["dist/**".to_string()],
+            ("package", "artifact") | ("release", "candidate") => vec!["*.tgz".to_string()],
+            ("test", "e2e") => E2eRunner::detect(Path::new("."))
+                .map(E2eRunner::artifact_patterns)
+                .unwrap_or_default(),
+            ("test", "visual") => {
+                let mut patterns = self.visual.diffs.clone();
+                patterns.push("!node_modules/**".to_string());
+                patterns
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn problem_matchers(&self, cmd: &CommandRef) -> Vec<ProblemMatcher> {
+        match cmd.primary.as_str() {
+            "lint" | "build" => vec![tsc_matcher()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn failure_hint(&self, cmd: &CommandRef) -> Option<String> {
+        (cmd.canonical() == "check:lockfiles").then(|| {
+            "package-lock.json is out of sync with package.json; run 'npm install' and commit package-lock.json"
+                .to_string()
+        })
+    }
+}
+
+/// Matches TypeScript compiler diagnostics:
+/// `src/a.ts(3,7): error TS2322: Type 'x' is not assignable ...`.
+fn tsc_matcher() -> ProblemMatcher {
+    ProblemMatcher {
+        owner: "tsc".to_string(),
+        severity: Severity::Error,
+        pattern: vec![ProblemPattern {
+            regexp: r"^(.+?)\((\d+),(\d+)\): (error|warning) (TS\d+: .+)$".to_string(),
+            file: Some(1),
+            line: Some(2),
+            column: Some(3),
+            severity: Some(4),
+            message: Some(5),
+        }],
+    }
+}
+
+/// The `version` of a `package.json`.
+fn package_version(manifest: &str) -> Result<Option<String>> {
+    let manifest: serde_json::Value =
+        serde_json::from_str(manifest).context("invalid package.json")?;
+    Ok(manifest
+        .get("version")
+        .and_then(serde_json::Value::as_str)
+        .map(str::to_string))
+}
+
+/// `tsc --noEmit` against the first of [`TSCONFIGS`] in `dir`.
+fn types_action(dir: &Path) -> Result<ExecutionAction> {
+    let Some(tsconfig) = TSCONFIGS.iter().find(|name| dir.join(name).is_file()) else {
+        bail!(
+            "lint:types found no TypeScript project config (looked for {})",
+            TSCONFIGS.join(", ")
+        );
+    };
+    // `--no` keeps npx from installing the unrelated `tsc` package when TypeScript is missing.
+    Ok(action(
+        "npx",
+        &[
+            "--no", "--", "tsc", "--noEmit", "--pretty", "false", "-p", tsconfig,
+        ],
+    ))
+}
+
+/// The end-to-end test runner of a project, detected by its config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum E2eRunner {
+    Playwright,
+    Cypress,
+}
+
+impl E2eRunner {
+    const ALL: [Self; 2] = [Self::Playwright, Self::Cypress];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Playwright => "playwright",
+            Self::Cypress => "cypress",
+        }
+    }
+
+    /// The runner configured in `dir`; Playwright wins if both are.
+    fn detect(dir: &Path) -> Option<Self> {
+        Self::ALL.into_iter().find(|runner| {
+            E2E_CONFIG_EXTENSIONS.iter().any(|ext| {
+                dir.join(format!("{}.config.{ext}", runner.name()))
+                    .is_file()
+            })
+        })
+    }
+
+    fn require(dir: &Path) -> Result<Self> {
+        match Self::detect(dir) {
+            Some(runner) => Ok(runner),
+            None => bail!(
+                "no end-to-end test config found (looked for playwright.config.* and cypress.config.*)"
+            ),
+        }
+    }
+
+    /// Runs the suite headless; `--no` keeps npx from installing a missing runner.
+    fn test(self) -> ExecutionAction {
+        match self {
+            Self::Playwright => action("npx", &["--no", "--", "playwright", "test"]),
+            Self::Cypress => action("npx", &["--no", "--", "cypress", "run"]),
+        }
+    }
+
+    /// Downloads the browsers the suite runs in, with Playwright's system
+    /// dependencies.
+    fn install(self) -> ExecutionAction {
+        match self {
+            Self::Playwright => action(
+                "npx",
+                &["--no", "--", "playwright", "install", "--with-deps"],
+            ),
+            Self::Cypress => action("npx", &["--no", "--", "cypress", "install"]),
+        }
+    }
+
+    /// The runner's default output directories: screenshots, videos and
+    /// traces of the run, and Playwright's HTML report.
+    fn artifact_patterns(self) -> Vec<String> {
+        let patterns: &[&str] = match self {
+            Self::Playwright => &["test-results/**", "playwright-report/**"],
+            Self::Cypress => &["cypress/screenshots/**", "cypress/videos/**"],
+        };
+        patterns.iter().map(|s| s.to_string()).collect()
+    }
+}
+
+/// The task runner of a monorepo, detected by its config file. Routing
+/// builds and tests through it reuses its incremental pipeline and cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskRunner {
+    Turbo,
+    Nx,
+}
+
+impl TaskRunner {
+    /// The runner configured in `dir`; Turborepo wins if both are.
+    fn detect(dir: &Path) -> Option<Self> {
+        [(Self::Turbo, "turbo.json"), (Self::Nx, "nx.json")]
+            .into_iter()
+            .find(|(_, config)| dir.join(config).is_file())
+            .map(|(runner, _)| runner)
+    }
+
+    /// Runs `task` in every package that has it, with the local cache in
+    /// the devflow cache and the configured remote cache.
+    fn run(
+        self,
+        task: &str,
+        remote: Option<&RemoteCacheConfig>,
+        token: Option<String>,
+    ) -> ExecutionAction {
+        // `--no` keeps npx from installing a runner the project does not have.
+        let mut run = match self {
+            Self::Turbo => action("npx", &["--no", "--", "turbo", "run", task]),
+            Self::Nx => action("npx", &["--no", "--", "nx", "run-many", "-t", task]),
+        };
+        let mut set = |key: &str, value: &str| run.env.insert(key.to_string(), value.to_string());
+        match self {
+            Self::Turbo => {
+                set("TURBO_CACHE_DIR", TURBO_CACHE_DIR);
+                if let Some(remote) = remote {
+                    set("TURBO_API", &remote.url);
+                    if let Some(team) = &remote.team {
+                        set("TURBO_TEAM", team);
+                    }
+                    if let Some(token) = &token {
+                        set("TURBO_TOKEN", token);
+                    }
+                }
+            }
+            Self::Nx => {
+                set("NX_CACHE_DIRECTORY", NX_CACHE_DIR);
+                if let Some(remote) = remote {
+                    set("NX_SELF_HOSTED_REMOTE_CACHE_SERVER", &remote.url);
+                    if let Some(token) = &token {
+                        set("NX_SELF_HOSTED_REMOTE_CACHE_ACCESS_TOKEN", token);
+                    }
+                }
+            }
+        }
+        run
+    }
+
+    fn cache_mount(self) -> String {
+        match self {
+            Self::Turbo => format!("node/turbo:{TURBO_CACHE_DIR}"),
+            Self::Nx => format!("node/nx:{NX_CACHE_DIR}"),
+        }
+    }
 }
 
 /// Helper for constructing `ExecutionAction`s concisely.
@@ -141,6 +484,10 @@ mod tests {
                 cmd(PrimaryCommand::Package, Some("artifact")),
                 "npm pack --dry-run",
             ),
+            (
+                cmd(PrimaryCommand::Check, Some("lockfiles")),
+                "npm ls --package-lock-only",
+            ),
         ];
 
         for (input_cmd, expected_shell) in tests {
@@ -170,6 +517,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn artifact_patterns_cover_build_outputs() {
+        let ext = NodeExtension::new();
+        assert_eq!(ext.output_dir_env(), None);
+        assert_eq!(
+            ext.artifact_patterns(&cmd(PrimaryCommand::Build, Some("release"))),
+            vec!["dist/**"]
+        );
+        assert!(ext
+            .artifact_patterns(&cmd(PrimaryCommand::Lint, Some("static")))
+            .is_empty());
+    }
+
+    #[test]
+    fn tsc_matcher_parses_compiler_errors() {
+        let ext = NodeExtension::new();
+        let matchers: Vec<_> = ext
+            .problem_matchers(&cmd(PrimaryCommand::Build, Some("debug")))
+            .iter()
+            .map(|m| devflow_core::problem::Matcher::compile(m).unwrap())
+            .collect();
+        let problems = devflow_core::problem::Scanner::new(&matchers)
+            .line("src/app.ts(3,7): error TS2322: Type 'string' is not assignable");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].file.as_deref(), Some("src/app.ts"));
+        assert_eq!(
+            problems[0].message,
+            "TS2322: Type 'string' is not assignable"
+        );
+        assert!(ext
+            .problem_matchers(&cmd(PrimaryCommand::Test, Some("unit")))
+            .is_empty());
+    }
+
     #[test]
     fn is_trusted_returns_true() {
         let ext = NodeExtension::new();
@@ -182,6 +563,11 @@ mod tests {
         let mounts = ext.cache_mounts();
         assert_eq!(mounts.len(), 1);
         assert_eq!(mounts[0], "node/npm:/root/.npm");
+        let volumes = ext.volume_mounts();
+        assert_eq!(volumes[0].path, "node_modules");
+        assert!(volumes[0]
+            .key_inputs
+            .contains(&"package-lock.json".to_string()));
     }
 
     #[test]
@@ -202,4 +588,100 @@ mod tests {
         assert!(inputs.contains(&"pnpm-lock.yaml".to_string()));
         assert!(inputs.contains(&"package.json".to_string()));
     }
+
+    #[test]
+    fn type_check_uses_the_detected_tsconfig() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = types_action(dir.path()).unwrap_err();
+        assert!(
+            err.to_string().contains("looked for tsconfig.json"),
+            "{err}"
+        );
+
+        std::fs::write(dir.path().join("tsconfig.build.json"), "{}").unwrap();
+        let action = types_action(dir.path()).unwrap();
+        assert_eq!(
+            format!("{} {}", action.program, action.args.join(" ")),
+            "npx --no -- tsc --noEmit --pretty false -p tsconfig.build.json"
+        );
+        std::fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        let action = types_action(dir.path()).unwrap();
+        assert_eq!(action.args.last().unwrap(), "tsconfig.json");
+    }
+
+    #[test]
+    fn visual_tests_run_the_configured_script() {
+        let visual = cmd(PrimaryCommand::Test, Some("visual"));
+        let action = NodeExtension::new().build_action(&visual).unwrap().unwrap();
+        assert_eq!(action.args, ["run", "test:visual"]);
+        assert!(action.env.is_empty());
+
+        let cfg: DevflowConfig = toml::from_str(
+            "[project]\nname = \"demo\"\nstack = [\"node\"]\n\n[extensions.node]\nsource = \"builtin\"\n\n[extensions.node.visual]\nscript = \"test-storybook\"\nthreshold = 0.02\ndiffs = [\"**/__image_diffs__/**\"]\n",
+        )
+        .unwrap();
+        let ext = NodeExtension::from_config(&cfg);
+        let action = ext.build_action(&visual).unwrap().unwrap();
+        assert_eq!(action.args, ["run", "test-storybook"]);
+        assert_eq!(action.env[VISUAL_THRESHOLD_ENV], "0.02");
+        assert_eq!(
+            ext.artifact_patterns(&visual),
+            ["**/__image_diffs__/**", "!node_modules/**"]
+        );
+    }
+
+    #[test]
+    fn e2e_runner_is_detected_by_its_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = E2eRunner::require(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("playwright.config.*"), "{err}");
+
+        std::fs::write(dir.path().join("cypress.config.js"), "").unwrap();
+        let cypress = E2eRunner::require(dir.path()).unwrap();
+        assert_eq!(cypress, E2eRunner::Cypress);
+        assert_eq!(cypress.test().args.join(" "), "--no -- cypress run");
+        assert_eq!(
+            cypress.artifact_patterns(),
+            ["cypress/screenshots/**", "cypress/videos/**"]
+        );
+
+        std::fs::write(dir.path().join("playwright.config.ts"), "").unwrap();
+        let playwright = E2eRunner::require(dir.path()).unwrap();
+        assert_eq!(playwright, E2eRunner::Playwright);
+        assert_eq!(
+            playwright.install().args.join(" "),
+            "--no -- playwright install --with-deps"
+        );
+        assert!(playwright
+            .artifact_patterns()
+            .contains(&"test-results/**".to_string()));
+    }
+
+    #[test]
+    fn task_runner_is_detected_and_gets_the_remote_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(TaskRunner::detect(dir.path()), None);
+        std::fs::write(dir.path().join("nx.json"), "{}").unwrap();
+        assert_eq!(TaskRunner::detect(dir.path()), Some(TaskRunner::Nx));
+        std::fs::write(dir.path().join("turbo.json"), "{}").unwrap();
+        assert_eq!(TaskRunner::detect(dir.path()), Some(TaskRunner::Turbo));
+
+        let remote = RemoteCacheConfig {
+            url: "https://cache.example.com".to_string(),
+            team: Some("web".to_string()),
+            token_env: "DEVFLOW_REMOTE_CACHE_TOKEN".to_string(),
+        };
+        let turbo = TaskRunner::Turbo.run("build", Some(&remote), Some("secret".to_string()));
+        assert_eq!(turbo.args.join(" "), "--no -- turbo run build");
+        assert_eq!(turbo.env["TURBO_CACHE_DIR"], TURBO_CACHE_DIR);
+        assert_eq!(turbo.env["TURBO_API"], "https://cache.example.com");
+        assert_eq!(turbo.env["TURBO_TEAM"], "web");
+        assert_eq!(turbo.env["TURBO_TOKEN"], "secret");
+
+        let nx = TaskRunner::Nx.run("test:unit", None, None);
+        assert_eq!(nx.args.join(" "), "--no -- nx run-many -t test:unit");
+        assert_eq!(nx.env.len(), 1);
+        assert_eq!(nx.env["NX_CACHE_DIRECTORY"], NX_CACHE_DIR);
+        assert_eq!(TaskRunner::Nx.cache_mount(), "node/nx:/root/.cache/nx");
+    }
 }
@@ -4,21 +4,107 @@
 //! `cargo` commands, enabling Rust workflows to integrate transparently
 //! into the Devflow ecosystem.
 
-use anyhow::Result;
-use devflow_core::{CommandRef, ExecutionAction, Extension};
-use std::collections::HashSet;
+use anyhow::{anyhow, bail, Context, Result};
+use devflow_core::config::{FeatureCommand, FeatureMatrix, FeatureStrategy};
+use devflow_core::problem::{ProblemMatcher, ProblemPattern, Severity};
+use devflow_core::tool::ToolRequirement;
+use devflow_core::{
+    ActionContext, Affected, CapabilityInfo, CommandRef, DevflowConfig, ExecutionAction, Extension,
+    PrimaryCommand, ResourceClass, TargetOs,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+mod workspace;
+
+use workspace::Workspace;
 
 /// The Devflow extension for Rust.
 ///
 /// Discovers project capabilities and maps primary Devflow actions into
 /// localized `cargo` invocations (e.g., `cargo build`, `cargo clippy`).
 #[derive(Debug, Default)]
-pub struct RustExtension;
+pub struct RustExtension {
+    feature_matrix: FeatureMatrix,
+    wasm_targets: Vec<String>,
+    no_std_targets: Vec<String>,
+}
+
+/// The `build:wasm` target unless `wasm_targets` are configured.
+const DEFAULT_WASM_TARGET: &str = "wasm32-unknown-unknown";
+/// The `check:no-std` target unless `no_std_targets` are configured.
+const DEFAULT_NO_STD_TARGET: &str = "thumbv7em-none-eabihf";
 
 impl RustExtension {
     /// Constructs a new [`RustExtension`].
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Constructs a [`RustExtension`] with the settings of `[extensions.rust]`.
+    pub fn from_config(cfg: &DevflowConfig) -> Self {
+        let ext = cfg.extension("rust");
+        Self {
+            feature_matrix: cfg.feature_matrix(),
+            wasm_targets: ext.map(|ext| ext.wasm_targets.clone()).unwrap_or_default(),
+            no_std_targets: ext
+                .map(|ext| ext.no_std_targets.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn wasm_targets(&self) -> Vec<&str> {
+        targets_or(&self.wasm_targets, DEFAULT_WASM_TARGET)
+    }
+
+    fn no_std_targets(&self) -> Vec<&str> {
+        targets_or(&self.no_std_targets, DEFAULT_NO_STD_TARGET)
+    }
+
+    /// `build:wasm` or `check:no-std` for `os`: adds the targets with
+    /// rustup, then builds for all of them in one cargo run.
+    fn cross_action(&self, selector: &str, os: TargetOs) -> Option<ExecutionAction> {
+        let (targets, cargo) = match selector {
+            "wasm" => (self.wasm_targets(), "cargo build --release"),
+            "no-std" => (
+                self.no_std_targets(),
+                "cargo check --lib --no-default-features",
+            ),
+            _ => return None,
+        };
+        let flags: Vec<String> = targets.iter().map(|t| format!("--target {t}")).collect();
+        let script = format!(
+            "rustup target add {} && {cargo} {}",
+            targets.join(" "),
+            flags.join(" ")
+        );
+        Some(match os {
+            TargetOs::Windows => action("cmd", &["/C", &script]),
+            _ => action("sh", &["-c", &script]),
+        })
+    }
+}
+
+/// `action` limited to `packages`; `None` means all of them.
+fn narrow_to_packages(action: &ExecutionAction, packages: Option<Vec<&str>>) -> Affected {
+    match packages {
+        None => Affected::All,
+        Some(packages) if packages.is_empty() => Affected::Nothing,
+        Some(packages) => {
+            let mut action = action.clone();
+            for package in packages {
+                action.args.extend(["-p".to_string(), package.to_string()]);
+            }
+            Affected::Action(action)
+        }
+    }
+}
+
+fn targets_or<'a>(targets: &'a [String], default: &'a str) -> Vec<&'a str> {
+    if targets.is_empty() {
+        vec![default]
+    } else {
+        targets.iter().map(String::as_str).collect()
     }
 }
 
@@ -36,11 +122,18 @@ impl Extension for RustExtension {
             "lint:security",
             "build:debug",
             "build:release",
+            "build:wasm",
             "test:unit",
             "test:integration",
             "test:smoke",
+            "test:features",
             "package:artifact",
+            "package:attest",
             "check",
+            "check:lockfiles",
+            "check:msrv",
+            "check:no-std",
+            "check:repro",
             "release",
             "ci:generate",
             "ci:check",
@@ -50,6 +143,137 @@ impl Extension for RustExtension {
         .collect()
     }
 
+    fn capability_info(&self) -> BTreeMap<String, CapabilityInfo> {
+        let features = if self.feature_matrix.sets.is_empty() {
+            "Test the feature combinations with cargo hack".to_string()
+        } else {
+            format!(
+                "Test one feature set per run ({})",
+                self.feature_matrix.shards().join(", ")
+            )
+        };
+        [
+            (
+                "setup",
+                "Setup",
+                "Show the toolchain (rustup show) and fetch dependencies (cargo fetch)".to_string(),
+            ),
+            (
+                "fmt:check",
+                "Quality",
+                "Check formatting (cargo fmt --check)".to_string(),
+            ),
+            (
+                "fmt:fix",
+                "Quality",
+                "Format the workspace (cargo fmt)".to_string(),
+            ),
+            (
+                "lint:static",
+                "Quality",
+                "Run clippy on all targets and features, denying warnings".to_string(),
+            ),
+            (
+                "lint:security",
+                "Security",
+                "Scan the CI image for HIGH and CRITICAL vulnerabilities (trivy)".to_string(),
+            ),
+            (
+                "build:debug",
+                "Build",
+                "Build with the dev profile (cargo build)".to_string(),
+            ),
+            (
+                "build:release",
+                "Build",
+                "Build with the release profile (cargo build --release)".to_string(),
+            ),
+            (
+                "build:wasm",
+                "Build",
+                format!("Build for {}", self.wasm_targets().join(", ")),
+            ),
+            (
+                "test:unit",
+                "Test",
+                "Run the library and binary tests (cargo nextest run --lib --bins)".to_string(),
+            ),
+            (
+                "test:integration",
+                "Test",
+                "Run the tests/ integration tests (cargo test --tests)".to_string(),
+            ),
+            (
+                "test:smoke",
+                "Test",
+                "Run the tests whose name contains 'smoke'".to_string(),
+            ),
+            ("test:features", "Test", features),
+            (
+                "package:artifact",
+                "Package",
+                "Build the release binaries (cargo build --release)".to_string(),
+            ),
+            (
+                "package:attest",
+                "Package",
+                "Write SLSA provenance for the release binaries".to_string(),
+            ),
+            (
+                "check",
+                "Verification",
+                "Run a [targets] profile, e.g. check:pr".to_string(),
+            ),
+            (
+                "check:lockfiles",
+                "Verification",
+                "Fail if Cargo.lock is out of date (cargo update --locked)".to_string(),
+            ),
+            (
+                "check:msrv",
+                "Verification",
+                "Build with the rust-version of Cargo.toml".to_string(),
+            ),
+            (
+                "check:no-std",
+                "Verification",
+                format!(
+                    "Check the library without std for {}",
+                    self.no_std_targets().join(", ")
+                ),
+            ),
+            (
+                "check:repro",
+                "Verification",
+                "Build the release twice and compare the binaries".to_string(),
+            ),
+            (
+                "release",
+                "Release",
+                "Verify (cargo package), build (cargo build --release), tag and publish (cargo publish)"
+                    .to_string(),
+            ),
+            (
+                "ci:generate",
+                "CI",
+                "Generate the GitHub Actions workflow".to_string(),
+            ),
+            (
+                "ci:check",
+                "CI",
+                "Check the workflow against devflow.toml".to_string(),
+            ),
+        ]
+        .into_iter()
+        .map(|(capability, group, description)| {
+            (
+                capability.to_string(),
+                CapabilityInfo::new(group, description),
+            )
+        })
+        .collect()
+    }
+
     fn build_action(&self, cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
         let primary = cmd.primary.as_str();
         let selector = cmd.selector.as_deref().unwrap_or("");
@@ -58,6 +282,13 @@ impl Extension for RustExtension {
             ("setup", "toolchain") => Some(action("rustup", &["show"])),
             ("setup", "deps") => Some(action("cargo", &["fetch"])),
             ("setup", "doctor") => Some(action("cargo", &["--version"])),
+            // Fails instead of rewriting Cargo.lock when it no longer matches the manifests.
+            ("check", "lockfiles") => Some(action("cargo", &["update", "--workspace", "--locked"])),
+            ("check", "msrv") => {
+                let manifest = std::fs::read_to_string("Cargo.toml")
+                    .context("check:msrv reads rust-version from Cargo.toml")?;
+                Some(msrv_action(&manifest)?)
+            }
             ("fmt", "check") => Some(action("cargo", &["fmt", "--all", "--", "--check"])),
             ("fmt", "fix") => Some(action("cargo", &["fmt", "--all"])),
             ("lint", "static") => Some(action(
@@ -87,13 +318,85 @@ impl Extension for RustExtension {
             ("test", "unit") => Some(action("cargo", &["nextest", "run", "--lib", "--bins"])),
             ("test", "integration") => Some(action("cargo", &["test", "--tests"])),
             ("test", "smoke") => Some(action("cargo", &["test", "smoke"])),
+            ("test", "features") => Some(features_action(&self.feature_matrix, None)?),
+            ("test", selector) if selector.starts_with("features:") => Some(features_action(
+                &self.feature_matrix,
+                selector.strip_prefix("features:"),
+            )?),
             ("package", "artifact") => Some(action("cargo", &["build", "--release"])),
+            ("release", "verify") => Some(action("cargo", &["package", "--locked"])),
             ("release", "candidate") => Some(action("cargo", &["build", "--release"])),
+            ("release", "publish") => Some(action("cargo", &["publish", "--locked"])),
+            ("build", "wasm") | ("check", "no-std") => {
+                self.cross_action(selector, TargetOs::host())
+            }
             _ => None,
         };
         Ok(action)
     }
 
+    fn release_version(&self) -> Result<Option<String>> {
+        let manifest = std::fs::read_to_string("Cargo.toml")
+            .context("release:tag reads the version from Cargo.toml")?;
+        package_field(&manifest, "version")
+    }
+
+    fn toolchain_dirs(&self) -> Vec<PathBuf> {
+        // rustup installs cargo, rustc and its proxies into `$CARGO_HOME/bin`.
+        std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))
+            .map(|cargo_home| vec![cargo_home.join("bin")])
+            .unwrap_or_default()
+    }
+
+    fn build_action_for(
+        &self,
+        cmd: &CommandRef,
+        ctx: &ActionContext,
+    ) -> Result<Option<ExecutionAction>> {
+        match (cmd.primary.as_str(), cmd.selector.as_deref().unwrap_or("")) {
+            ("build", selector @ "wasm") | ("check", selector @ "no-std") => {
+                Ok(self.cross_action(selector, ctx.os))
+            }
+            _ => self.build_action(cmd),
+        }
+    }
+
+    /// `test:unit` and `test:integration` run for the affected workspace
+    /// packages only (`-p`); without `cargo metadata`, for all of them.
+    fn affected(&self, cmd: &CommandRef, action: &ExecutionAction, changed: &[String]) -> Affected {
+        if !matches!(cmd.canonical().as_str(), "test:unit" | "test:integration") {
+            return Affected::All;
+        }
+        let (Ok(workspace), Ok(repo_root)) = (Workspace::load(), workspace::repo_root()) else {
+            return Affected::All;
+        };
+        narrow_to_packages(action, workspace.affected(&repo_root, changed))
+    }
+
+    /// A debug build after fetching fills the target cache and sccache.
+    fn warm_commands(&self) -> Vec<CommandRef> {
+        [
+            (PrimaryCommand::Setup, "deps"),
+            (PrimaryCommand::Build, "debug"),
+        ]
+        .into_iter()
+        .map(|(primary, selector)| CommandRef {
+            primary,
+            selector: Some(selector.to_string()),
+        })
+        .collect()
+    }
+
+    /// Clippy and the `check:*` builds compile the crate like `build:*`.
+    fn resource_class(&self, cmd: &CommandRef) -> ResourceClass {
+        match cmd.canonical().as_str() {
+            "lint:static" | "check:msrv" | "check:no-std" | "check:repro" => ResourceClass::Cpu,
+            _ => ResourceClass::default_for(cmd),
+        }
+    }
+
     fn is_trusted(&self) -> bool {
         true
     }
@@ -105,6 +408,14 @@ impl Extension for RustExtension {
         ]
     }
 
+    fn cache_mounts_for(&self, cmd: &CommandRef) -> Vec<String> {
+        // rustfmt never compiles, so it needs neither the registry/sccache nor the target cache.
+        match cmd.primary {
+            PrimaryCommand::Fmt => Vec::new(),
+            _ => self.cache_mounts(),
+        }
+    }
+
     fn env_vars(&self) -> std::collections::HashMap<String, String> {
         let mut env = std::collections::HashMap::new();
         env.insert(
@@ -130,6 +441,204 @@ impl Extension for RustExtension {
             "Cargo.toml".to_string(),
         ]
     }
+
+    fn output_dir_env(&self) -> Option<&str> {
+        Some("CARGO_TARGET_DIR")
+    }
+
+    fn artifact_patterns(&self, cmd: &CommandRef) -> Vec<String> {
+        if cmd.canonical() == "build:wasm" {
+            return self
+                .wasm_targets()
+                .iter()
+                .map(|target| format!("{target}/release/*.wasm"))
+                .collect();
+        }
+        let profile = match (cmd.primary.as_str(), cmd.selector.as_deref().unwrap_or("")) {
+            ("build", "debug") => "debug",
+            ("build", "release") | ("package", "artifact") | ("release", "candidate") => "release",
+            _ => return Vec::new(),
+        };
+        // Top-level outputs only; dep-info files embed absolute paths.
+        vec![
+            format!("{profile}/*"),
+            format!("!{profile}/*.d"),
+            format!("!{profile}/.cargo-lock"),
+        ]
+    }
+
+    fn problem_matchers(&self, cmd: &CommandRef) -> Vec<ProblemMatcher> {
+        match cmd.primary.as_str() {
+            "lint" | "build" | "test" | "check" => vec![rustc_matcher()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn failure_hint(&self, cmd: &CommandRef) -> Option<String> {
+        match cmd.canonical().as_str() {
+            "check:lockfiles" => Some(
+                "Cargo.lock is out of sync with Cargo.toml; run 'cargo update --workspace' and commit Cargo.lock"
+                    .to_string(),
+            ),
+            // rustc reports `can't find crate for `std`` without naming who pulled it in.
+            "check:no-std" => Some(format!(
+                "\"can't find crate for `std`\" means a dependency needs std on {}; \
+                 disable its default features or make std optional \
+                 ('cargo tree -e features --target {}' shows why it is built)",
+                self.no_std_targets().join(", "),
+                self.no_std_targets()[0]
+            )),
+            _ => None,
+        }
+    }
+
+    fn tools(&self) -> Vec<ToolRequirement> {
+        vec![
+            // `test:unit` runs `cargo nextest`.
+            ToolRequirement {
+                name: "cargo-nextest".to_string(),
+                version: "0.9.72".to_string(),
+                url: "https://github.com/nextest-rs/nextest/releases/download/cargo-nextest-{version}/cargo-nextest-{version}-{target}.tar.gz".to_string(),
+                ..ToolRequirement::default()
+            },
+            // `RUSTC_WRAPPER=sccache`; the Linux release is the static musl build.
+            ToolRequirement {
+                name: "sccache".to_string(),
+                version: "0.7.7".to_string(),
+                url: "https://github.com/mozilla/sccache/releases/download/v{version}/sccache-v{version}-{target}.tar.gz".to_string(),
+                targets: [
+                    ("x86_64-unknown-linux-gnu", "x86_64-unknown-linux-musl"),
+                    ("aarch64-unknown-linux-gnu", "aarch64-unknown-linux-musl"),
+                ]
+                .into_iter()
+                .map(|(host, release)| (host.to_string(), release.to_string()))
+                .collect(),
+                ..ToolRequirement::default()
+            },
+            // `test:features` generates feature combinations with `cargo hack`.
+            ToolRequirement {
+                name: "cargo-hack".to_string(),
+                version: "0.6.31".to_string(),
+                url: "https://github.com/taiki-e/cargo-hack/releases/download/v{version}/cargo-hack-{target}.tar.gz".to_string(),
+                ..ToolRequirement::default()
+            },
+        ]
+    }
+}
+
+/// Matches rustc and clippy diagnostics: a `warning:`/`error[E0308]:` line
+/// followed by its ` --> file:line:col` location.
+fn rustc_matcher() -> ProblemMatcher {
+    ProblemMatcher {
+        owner: "rustc".to_string(),
+        severity: Severity::Error,
+        pattern: vec![
+            ProblemPattern {
+                regexp: r"^(warning|error)(?:\[\w+\])?: (.+)$".to_string(),
+                severity: Some(1),
+                message: Some(2),
+                ..ProblemPattern::default()
+            },
+            ProblemPattern {
+                regexp: r"^\s+--> (.+?):(\d+):(\d+)$".to_string(),
+                file: Some(1),
+                line: Some(2),
+                column: Some(3),
+                ..ProblemPattern::default()
+            },
+        ],
+    }
+}
+
+/// One run of the feature matrix: the set or partition named by `shard`, or
+/// with cargo-hack every generated combination at once.
+fn features_action(matrix: &FeatureMatrix, shard: Option<&str>) -> Result<ExecutionAction> {
+    let command = match matrix.command {
+        FeatureCommand::Test => "test",
+        FeatureCommand::Check => "check",
+    };
+    if !matrix.sets.is_empty() {
+        let Some(label) = shard else {
+            bail!("test:features runs one feature set at a time (e.g. test:features:none)");
+        };
+        let set = matrix.set(label).ok_or_else(|| {
+            anyhow!(
+                "unknown feature set '{}' (expected one of: {})",
+                label,
+                matrix.shards().join(", ")
+            )
+        })?;
+        let features = set.join(",");
+        let mut args = vec![command, "--no-default-features"];
+        if !set.is_empty() {
+            args.extend(["--features", &features]);
+        }
+        return Ok(action("cargo", &args));
+    }
+
+    let mut args = vec!["hack", command, "--workspace"];
+    args.push(match matrix.strategy {
+        FeatureStrategy::Powerset => "--feature-powerset",
+        FeatureStrategy::EachFeature => "--each-feature",
+    });
+    let depth = matrix.depth.map(|depth| depth.to_string());
+    if let Some(depth) = &depth {
+        args.extend(["--depth", depth]);
+    }
+    let exclude = matrix.exclude.join(",");
+    if !matrix.exclude.is_empty() {
+        args.extend(["--exclude-features", &exclude]);
+    }
+    if let Some(partition) = shard {
+        if !matrix.shards().iter().any(|shard| shard == partition) {
+            bail!(
+                "unknown feature matrix partition '{}' (expected one of: {})",
+                partition,
+                matrix.shards().join(", ")
+            );
+        }
+        args.extend(["--partition", partition]);
+    }
+    Ok(action("cargo", &args))
+}
+
+/// `cargo check` with the toolchain of the manifest's `rust-version`, which
+/// rustup installs first if needed.
+/// `key` of `[package]`, or of `[workspace.package]` when the package
+/// inherits it.
+fn package_field(manifest: &str, key: &str) -> Result<Option<String>> {
+    let manifest: toml::Table = toml::from_str(manifest).context("invalid Cargo.toml")?;
+    let field = |table: Option<&toml::Value>| {
+        table
+            .and_then(|table| table.get(key))
+            .and_then(toml::Value::as_str)
+            .map(str::to_string)
+    };
+    Ok(field(manifest.get("package")).or_else(|| {
+        field(
+            manifest
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package")),
+        )
+    }))
+}
+
+fn msrv_action(manifest: &str) -> Result<ExecutionAction> {
+    let Some(version) = package_field(manifest, "rust-version")? else {
+        bail!("check:msrv needs rust-version in Cargo.toml ([package] or [workspace.package])");
+    };
+    Ok(action(
+        "rustup",
+        &[
+            "run",
+            "--install",
+            &version,
+            "cargo",
+            "check",
+            "--workspace",
+            "--all-targets",
+        ],
+    ))
 }
 
 /// Helper for constructing `ExecutionAction`s concisely.
@@ -195,6 +704,10 @@ mod tests {
                 cmd(PrimaryCommand::Lint, Some("security")),
                 "trivy image devflow-ci:latest --severity CRITICAL,HIGH --exit-code 1",
             ),
+            (
+                cmd(PrimaryCommand::Check, Some("lockfiles")),
+                "cargo update --workspace --locked",
+            ),
         ];
 
         for (input_cmd, expected_shell) in tests {
@@ -239,6 +752,83 @@ mod tests {
         assert!(mounts.contains(&"rust/target:/workspace/target/ci".to_string()));
     }
 
+    #[test]
+    fn cache_mounts_for_skips_fmt() {
+        let ext = RustExtension::new();
+        assert!(ext
+            .cache_mounts_for(&cmd(PrimaryCommand::Fmt, Some("check")))
+            .is_empty());
+        assert_eq!(
+            ext.cache_mounts_for(&cmd(PrimaryCommand::Build, Some("release"))),
+            ext.cache_mounts()
+        );
+    }
+
+    #[test]
+    fn artifact_patterns_target_profile_outputs() {
+        let ext = RustExtension::new();
+        assert_eq!(ext.output_dir_env(), Some("CARGO_TARGET_DIR"));
+        assert_eq!(
+            ext.artifact_patterns(&cmd(PrimaryCommand::Build, Some("release"))),
+            vec!["release/*", "!release/*.d", "!release/.cargo-lock"]
+        );
+        assert!(ext
+            .artifact_patterns(&cmd(PrimaryCommand::Test, Some("unit")))
+            .is_empty());
+    }
+
+    #[test]
+    fn problem_matchers_cover_compiling_commands() {
+        let ext = RustExtension::new();
+        let lint = ext.problem_matchers(&cmd(PrimaryCommand::Lint, Some("static")));
+        assert_eq!(lint.len(), 1);
+        assert_eq!(lint[0].owner, "rustc");
+        assert!(devflow_core::problem::Matcher::compile(&lint[0]).is_ok());
+        assert!(ext
+            .problem_matchers(&cmd(PrimaryCommand::Fmt, Some("check")))
+            .is_empty());
+    }
+
+    #[test]
+    fn warm_commands_fetch_then_build() {
+        let warm: Vec<String> = RustExtension::new()
+            .warm_commands()
+            .iter()
+            .map(CommandRef::canonical)
+            .collect();
+        assert_eq!(warm, ["setup:deps", "build:debug"]);
+    }
+
+    #[test]
+    fn compiling_commands_are_cpu_bound() {
+        let ext = RustExtension::new();
+        for (primary, selector, class) in [
+            (PrimaryCommand::Lint, "static", ResourceClass::Cpu),
+            (PrimaryCommand::Check, "msrv", ResourceClass::Cpu),
+            (PrimaryCommand::Test, "unit", ResourceClass::Cpu),
+            (PrimaryCommand::Lint, "security", ResourceClass::Io),
+            (PrimaryCommand::Fmt, "check", ResourceClass::Io),
+            (PrimaryCommand::Check, "lockfiles", ResourceClass::Io),
+        ] {
+            assert_eq!(
+                ext.resource_class(&cmd(primary, Some(selector))),
+                class,
+                "{selector}"
+            );
+        }
+    }
+
+    #[test]
+    fn tools_pin_nextest_sccache_and_cargo_hack_releases() {
+        let tools = RustExtension::new().tools();
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["cargo-nextest", "sccache", "cargo-hack"]);
+        assert_eq!(
+            tools[1].url_for("x86_64-unknown-linux-gnu"),
+            "https://github.com/mozilla/sccache/releases/download/v0.7.7/sccache-v0.7.7-x86_64-unknown-linux-musl.tar.gz"
+        );
+    }
+
     #[test]
     fn env_vars_returns_expected_values() {
         let ext = RustExtension::new();
@@ -265,4 +855,144 @@ mod tests {
         assert!(inputs.contains(&"rust-toolchain.toml".to_string()));
         assert!(inputs.contains(&"Cargo.toml".to_string()));
     }
+
+    #[test]
+    fn msrv_checks_with_the_declared_rust_version() {
+        let expected = "rustup run --install 1.75 cargo check --workspace --all-targets";
+        let render = |manifest: &str| {
+            let action = msrv_action(manifest).unwrap();
+            format!("{} {}", action.program, action.args.join(" "))
+        };
+        assert_eq!(
+            render("[package]\nname = \"demo\"\nrust-version = \"1.75\"\n"),
+            expected
+        );
+        assert_eq!(
+            render("[workspace.package]\nrust-version = \"1.75\"\n[package]\nrust-version.workspace = true\n"),
+            expected
+        );
+        let err = msrv_action("[package]\nname = \"demo\"\n").unwrap_err();
+        assert!(err.to_string().contains("needs rust-version"), "{err}");
+    }
+
+    #[test]
+    fn feature_matrix_runs_sets_or_cargo_hack() {
+        let render = |ext: &RustExtension, selector: &str| {
+            let action = ext
+                .build_action(&cmd(PrimaryCommand::Test, Some(selector)))
+                .unwrap()
+                .unwrap();
+            format!("{} {}", action.program, action.args.join(" "))
+        };
+        let ext = RustExtension::new();
+        assert_eq!(
+            render(&ext, "features"),
+            "cargo hack test --workspace --feature-powerset"
+        );
+
+        let ext = RustExtension {
+            feature_matrix: FeatureMatrix {
+                strategy: FeatureStrategy::EachFeature,
+                exclude: vec!["nightly".to_string()],
+                depth: Some(2),
+                partitions: Some(2),
+                command: FeatureCommand::Check,
+                ..FeatureMatrix::default()
+            },
+            ..RustExtension::default()
+        };
+        assert_eq!(
+            render(&ext, "features:2/2"),
+            "cargo hack check --workspace --each-feature --depth 2 --exclude-features nightly --partition 2/2"
+        );
+        assert!(ext
+            .build_action(&cmd(PrimaryCommand::Test, Some("features:3/2")))
+            .is_err());
+
+        let ext = RustExtension::from_config(
+            &toml::from_str(
+                "[project]\nname = \"demo\"\n\n[extensions.rust]\nsource = \"builtin\"\n\n[extensions.rust.feature_matrix]\nsets = [[], [\"serde\", \"tokio\"]]\n",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            render(&ext, "features:none"),
+            "cargo test --no-default-features"
+        );
+        assert_eq!(
+            render(&ext, "features:serde+tokio"),
+            "cargo test --no-default-features --features serde,tokio"
+        );
+        let err = ext
+            .build_action(&cmd(PrimaryCommand::Test, Some("features:serde")))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected one of: none, serde+tokio"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn cross_targets_are_added_before_building() {
+        let render = |ext: &RustExtension, command: CommandRef, os| {
+            let action = ext
+                .build_action_for(&command, &ActionContext { os })
+                .unwrap()
+                .unwrap();
+            format!("{} {}", action.program, action.args.join(" "))
+        };
+        let wasm = cmd(PrimaryCommand::Build, Some("wasm"));
+        let no_std = cmd(PrimaryCommand::Check, Some("no-std"));
+        let ext = RustExtension::new();
+        assert_eq!(
+            render(&ext, wasm.clone(), TargetOs::Linux),
+            "sh -c rustup target add wasm32-unknown-unknown && cargo build --release --target wasm32-unknown-unknown"
+        );
+        assert_eq!(
+            ext.artifact_patterns(&wasm),
+            ["wasm32-unknown-unknown/release/*.wasm"]
+        );
+
+        let ext = RustExtension::from_config(
+            &toml::from_str(
+                "[project]\nname = \"demo\"\n\n[extensions.rust]\nsource = \"builtin\"\nno_std_targets = [\"thumbv6m-none-eabi\", \"riscv32imac-unknown-none-elf\"]\n",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            render(&ext, no_std.clone(), TargetOs::Windows),
+            "cmd /C rustup target add thumbv6m-none-eabi riscv32imac-unknown-none-elf && cargo check --lib --no-default-features --target thumbv6m-none-eabi --target riscv32imac-unknown-none-elf"
+        );
+        let hint = ext.failure_hint(&no_std).unwrap();
+        assert!(
+            hint.contains("needs std on thumbv6m-none-eabi, riscv32imac-unknown-none-elf"),
+            "{hint}"
+        );
+    }
+
+    #[test]
+    fn affected_runs_name_the_packages() {
+        let ext = RustExtension::new();
+        let unit = ext
+            .build_action(&cmd(PrimaryCommand::Test, Some("unit")))
+            .unwrap()
+            .unwrap();
+        match narrow_to_packages(&unit, Some(vec!["core", "app"])) {
+            Affected::Action(action) => assert_eq!(
+                action.args,
+                ["nextest", "run", "--lib", "--bins", "-p", "core", "-p", "app"]
+            ),
+            other => panic!("expected a narrowed action, got {other:?}"),
+        }
+        assert!(matches!(
+            narrow_to_packages(&unit, Some(Vec::new())),
+            Affected::Nothing
+        ));
+        assert!(matches!(narrow_to_packages(&unit, None), Affected::All));
+        assert!(matches!(
+            ext.affected(&cmd(PrimaryCommand::Lint, Some("static")), &unit, &[]),
+            Affected::All
+        ));
+    }
 }
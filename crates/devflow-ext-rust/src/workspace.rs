@@ -0,0 +1,239 @@
+//! The cargo workspace graph, for `--affected` runs.
+//!
+//! `cargo metadata` lists the workspace packages and their path
+//! dependencies. A changed file belongs to the package whose directory holds
+//! it; that package and every package depending on it, directly or not, are
+//! affected. Changes to what every package builds with (`Cargo.lock`, the
+//! root manifest, the toolchain file, `.cargo/`) affect all of them.
+//!
+//! Changed files are relative to the git top-level, which need not be the
+//! workspace root: files outside the workspace affect no package, and files
+//! inside it that no package holds affect every package.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// Workspace-root files every package builds with.
+const SHARED_INPUTS: [&str; 4] = [
+    "Cargo.lock",
+    "Cargo.toml",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+];
+
+/// The parts of `cargo metadata --no-deps` the graph needs.
+#[derive(Debug, Deserialize)]
+pub struct Workspace {
+    packages: Vec<Package>,
+    workspace_root: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dependency {
+    /// The dependency's directory, for path dependencies.
+    #[serde(default)]
+    path: Option<PathBuf>,
+}
+
+impl Package {
+    fn dir(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or(Path::new(""))
+    }
+}
+
+impl Workspace {
+    /// Reads the workspace of the current directory.
+    ///
+    /// # Errors
+    /// Returns an error if `cargo metadata` fails or prints something else.
+    pub fn load() -> Result<Self> {
+        let output = Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .output()
+            .context("failed to run cargo metadata")?;
+        if !output.status.success() {
+            bail!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Self::parse(&output.stdout)
+    }
+
+    /// Parses `cargo metadata` output.
+    ///
+    /// # Errors
+    /// Returns an error if `json` is not `cargo metadata` output.
+    pub fn parse(json: &[u8]) -> Result<Self> {
+        serde_json::from_slice(json).context("unexpected cargo metadata output")
+    }
+
+    /// The packages affected by `changed` (relative to `repo_root`, as
+    /// `git diff --name-only` prints them), in workspace order; `None` when
+    /// every package is.
+    pub fn affected(&self, repo_root: &Path, changed: &[String]) -> Option<Vec<&str>> {
+        let mut affected = HashSet::new();
+        for file in changed {
+            let path = repo_root.join(file);
+            let Ok(relative) = path.strip_prefix(&self.workspace_root) else {
+                continue;
+            };
+            if is_shared_input(relative) {
+                return None;
+            }
+            // The innermost package holding the file owns it.
+            let owner = (0..self.packages.len())
+                .filter(|&i| path.starts_with(self.packages[i].dir()))
+                .max_by_key(|&i| self.packages[i].dir().components().count())?;
+            affected.insert(owner);
+        }
+
+        let mut pending: Vec<usize> = affected.iter().copied().collect();
+        while let Some(changed) = pending.pop() {
+            let dir = self.packages[changed].dir();
+            for (i, package) in self.packages.iter().enumerate() {
+                let depends = package
+                    .dependencies
+                    .iter()
+                    .any(|dependency| dependency.path.as_deref() == Some(dir));
+                if depends && affected.insert(i) {
+                    pending.push(i);
+                }
+            }
+        }
+        if affected.len() == self.packages.len() {
+            return None;
+        }
+        let mut affected: Vec<usize> = affected.into_iter().collect();
+        affected.sort_unstable();
+        Some(
+            affected
+                .into_iter()
+                .map(|i| self.packages[i].name.as_str())
+                .collect(),
+        )
+    }
+}
+
+/// Whether `file`, relative to the workspace root, is built with by every
+/// package.
+fn is_shared_input(file: &Path) -> bool {
+    SHARED_INPUTS.iter().any(|input| file == Path::new(input)) || file.starts_with(".cargo")
+}
+
+/// The top-level directory of the git repository of the current directory.
+///
+/// # Errors
+/// Returns an error outside a git repository.
+pub fn repo_root() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git rev-parse failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `app` depends on `core`; `core` has a dev-dependency on `testkit`;
+    /// the root package `tool` depends on nothing.
+    const METADATA: &str = r#"{
+        "workspace_root": "/ws",
+        "packages": [
+            {"name": "core", "manifest_path": "/ws/crates/core/Cargo.toml",
+             "dependencies": [{"name": "serde"}, {"name": "testkit", "path": "/ws/crates/testkit", "kind": "dev"}]},
+            {"name": "app", "manifest_path": "/ws/crates/app/Cargo.toml",
+             "dependencies": [{"name": "core", "path": "/ws/crates/core"}]},
+            {"name": "testkit", "manifest_path": "/ws/crates/testkit/Cargo.toml", "dependencies": []},
+            {"name": "tool", "manifest_path": "/ws/Cargo.toml", "dependencies": []}
+        ]
+    }"#;
+
+    fn affected_in(metadata: &str, repo_root: &str, changed: &[&str]) -> Option<Vec<String>> {
+        let workspace = Workspace::parse(metadata.as_bytes()).unwrap();
+        let changed: Vec<String> = changed.iter().map(|file| file.to_string()).collect();
+        workspace
+            .affected(Path::new(repo_root), &changed)
+            .map(|names| names.into_iter().map(str::to_string).collect())
+    }
+
+    fn affected(changed: &[&str]) -> Option<Vec<String>> {
+        affected_in(METADATA, "/ws", changed)
+    }
+
+    #[test]
+    fn maps_changed_files_to_dependent_packages() {
+        assert_eq!(
+            affected(&["crates/app/src/main.rs"]),
+            Some(vec!["app".to_string()])
+        );
+        assert_eq!(
+            affected(&["crates/core/src/lib.rs"]),
+            Some(vec!["core".to_string(), "app".to_string()])
+        );
+        assert_eq!(
+            affected(&["crates/testkit/src/lib.rs"]),
+            Some(vec![
+                "core".to_string(),
+                "app".to_string(),
+                "testkit".to_string()
+            ])
+        );
+        // Outside the member directories, the root package owns the file.
+        assert_eq!(affected(&["README.md"]), Some(vec!["tool".to_string()]));
+        assert_eq!(affected(&[]), Some(Vec::new()));
+        assert_eq!(affected(&["Cargo.lock"]), None);
+        assert_eq!(affected(&[".cargo/config.toml"]), None);
+        assert_eq!(
+            affected(&["crates/testkit/src/lib.rs", "src/main.rs"]),
+            None
+        );
+    }
+
+    #[test]
+    fn resolves_changes_of_a_workspace_below_the_repo_root() {
+        // A virtual workspace in `svc/` of the repository at `/repo`.
+        let metadata = r#"{
+            "workspace_root": "/repo/svc",
+            "packages": [
+                {"name": "core", "manifest_path": "/repo/svc/crates/core/Cargo.toml", "dependencies": []},
+                {"name": "app", "manifest_path": "/repo/svc/crates/app/Cargo.toml",
+                 "dependencies": [{"name": "core", "path": "/repo/svc/crates/core"}]}
+            ]
+        }"#;
+        let affected = |changed: &[&str]| affected_in(metadata, "/repo", changed);
+        assert_eq!(
+            affected(&["svc/crates/app/src/main.rs"]),
+            Some(vec!["app".to_string()])
+        );
+        // Every package depends on core.
+        assert_eq!(affected(&["svc/crates/core/src/lib.rs"]), None);
+        // Outside the workspace nothing is affected.
+        assert_eq!(affected(&["web/index.html"]), Some(Vec::new()));
+        assert_eq!(affected(&["Cargo.lock"]), Some(Vec::new()));
+        assert_eq!(affected(&["svc/Cargo.lock"]), None);
+        // No package holds the file, so it may affect any of them.
+        assert_eq!(affected(&["svc/README.md", "svc/crates/app/a.rs"]), None);
+    }
+}
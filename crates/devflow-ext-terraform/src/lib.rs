@@ -0,0 +1,341 @@
+//! Devflow extension for Terraform projects.
+//!
+//! Provides the [`TerraformExtension`] which maps Devflow [`CommandRef`]s onto
+//! `terraform` and `tflint` invocations against the root module in the
+//! project directory, so infrastructure code gets the same `fmt`, `lint` and
+//! `check` gates as the application code next to it.
+
+use anyhow::Result;
+use devflow_core::problem::{ProblemMatcher, ProblemPattern, Severity};
+use devflow_core::tool::ToolRequirement;
+use devflow_core::{CapabilityInfo, CommandRef, ExecutionAction, Extension, VolumeMount};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Where providers are cached across runs, mounted from the devflow cache.
+/// Terraform does not create it, so the actions that download providers do.
+const PLUGIN_CACHE_DIR: &str = "/root/.terraform.d/plugin-cache";
+
+/// The dependency lock file `terraform init` writes.
+const LOCK_FILE: &str = ".terraform.lock.hcl";
+
+/// The Devflow extension for Terraform.
+///
+/// Runs non-interactively (`TF_IN_AUTOMATION`, `TF_INPUT=0`) and never
+/// writes state: `check:plan` plans without taking the state lock, and the
+/// other commands initialize without a backend.
+#[derive(Debug, Default)]
+pub struct TerraformExtension;
+
+impl TerraformExtension {
+    /// Constructs a new [`TerraformExtension`].
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Extension for TerraformExtension {
+    fn name(&self) -> &str {
+        "terraform"
+    }
+
+    fn capabilities(&self) -> HashSet<String> {
+        [
+            "setup",
+            "fmt:check",
+            "fmt:fix",
+            "lint:static",
+            "check",
+            "check:plan",
+            "ci:generate",
+            "ci:check",
+        ]
+        .iter()
+        .map(|&s| s.to_string())
+        .collect()
+    }
+
+    fn capability_info(&self) -> BTreeMap<String, CapabilityInfo> {
+        [
+            (
+                "setup",
+                "Setup",
+                "Install the providers (terraform init -backend=false)",
+            ),
+            (
+                "fmt:check",
+                "Quality",
+                "Check formatting (terraform fmt -check)",
+            ),
+            (
+                "fmt:fix",
+                "Quality",
+                "Format the configuration (terraform fmt)",
+            ),
+            (
+                "lint:static",
+                "Quality",
+                "Validate the configuration and run tflint",
+            ),
+            (
+                "check",
+                "Verification",
+                "Run a [targets] profile, e.g. check:pr",
+            ),
+            (
+                "check:plan",
+                "Verification",
+                "Fail if the plan has changes (terraform plan -detailed-exitcode)",
+            ),
+            ("ci:generate", "CI", "Generate the GitHub Actions workflow"),
+            ("ci:check", "CI", "Check the workflow against devflow.toml"),
+        ]
+        .into_iter()
+        .map(|(capability, group, description)| {
+            (
+                capability.to_string(),
+                CapabilityInfo::new(group, description),
+            )
+        })
+        .collect()
+    }
+
+    fn build_action(&self, cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
+        let primary = cmd.primary.as_str();
+        let selector = cmd.selector.as_deref().unwrap_or("");
+
+        let action = match (primary, selector) {
+            // Installs the providers without touching the state backend.
+            ("setup", "deps") => Some(shell("terraform init -input=false -backend=false")),
+            ("setup", "doctor") => Some(action("terraform", &["version"])),
+            ("fmt", "check") => Some(action(
+                "terraform",
+                &["fmt", "-check", "-recursive", "-diff"],
+            )),
+            ("fmt", "fix") => Some(action("terraform", &["fmt", "-recursive"])),
+            // `validate` needs the providers; tflint reads the files on its own.
+            ("lint", "static") => Some(shell(
+                "terraform init -input=false -backend=false >/dev/null \
+                 && terraform validate -no-color \
+                 && tflint --recursive --format compact",
+            )),
+            // Exits 2 when the plan has changes. `-lock=false` keeps a
+            // read-only check from blocking applies on the same state.
+            ("check", "plan") => Some(shell(
+                "terraform init -input=false >/dev/null \
+                 && terraform plan -detailed-exitcode -input=false -lock=false -no-color",
+            )),
+            _ => None,
+        };
+        Ok(action)
+    }
+
+    fn toolchain_dirs(&self) -> Vec<PathBuf> {
+        // The shims of tfenv; a system terraform is found in the system
+        // directories.
+        std::env::var_os("TFENV_ROOT")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".tfenv")))
+            .map(|root| root.join("bin"))
+            .into_iter()
+            .collect()
+    }
+
+    fn is_trusted(&self) -> bool {
+        true
+    }
+
+    fn cache_mounts(&self) -> Vec<String> {
+        vec![format!("terraform/plugins:{PLUGIN_CACHE_DIR}")]
+    }
+
+    fn volume_mounts(&self) -> Vec<VolumeMount> {
+        vec![VolumeMount {
+            name: "terraform".to_string(),
+            path: ".terraform".to_string(),
+            key_inputs: self.fingerprint_inputs(),
+        }]
+    }
+
+    fn env_vars(&self) -> HashMap<String, String> {
+        [
+            ("TF_IN_AUTOMATION", "1"),
+            ("TF_INPUT", "0"),
+            ("TF_PLUGIN_CACHE_DIR", PLUGIN_CACHE_DIR),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    fn fingerprint_inputs(&self) -> Vec<String> {
+        vec![LOCK_FILE.to_string()]
+    }
+
+    fn problem_matchers(&self, cmd: &CommandRef) -> Vec<ProblemMatcher> {
+        match cmd.primary.as_str() {
+            "lint" => vec![tflint_matcher()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn tools(&self) -> Vec<ToolRequirement> {
+        // `lint:static` runs tflint after `terraform validate`.
+        vec![ToolRequirement {
+            name: "tflint".to_string(),
+            version: "0.53.0".to_string(),
+            url: "https://github.com/terraform-linters/tflint/releases/download/v{version}/tflint_{target}.zip".to_string(),
+            targets: [
+                ("x86_64-unknown-linux-gnu", "linux_amd64"),
+                ("aarch64-unknown-linux-gnu", "linux_arm64"),
+                ("x86_64-apple-darwin", "darwin_amd64"),
+                ("aarch64-apple-darwin", "darwin_arm64"),
+            ]
+            .into_iter()
+            .map(|(host, release)| (host.to_string(), release.to_string()))
+            .collect(),
+            ..ToolRequirement::default()
+        }]
+    }
+
+    fn failure_hint(&self, cmd: &CommandRef) -> Option<String> {
+        (cmd.canonical() == "check:plan").then(|| {
+            "exit status 2 means the plan has changes: the configuration and the \
+             infrastructure have drifted; apply the change or update the configuration"
+                .to_string()
+        })
+    }
+}
+
+/// Matches tflint's compact output:
+/// `main.tf:3:1: Warning - variable "x" is declared but not used (terraform_unused_declarations)`.
+fn tflint_matcher() -> ProblemMatcher {
+    ProblemMatcher {
+        owner: "tflint".to_string(),
+        severity: Severity::Warning,
+        pattern: vec![ProblemPattern {
+            regexp: r"^(.+?):(\d+):(\d+): (Error|Warning|Notice) - (.+)$".to_string(),
+            file: Some(1),
+            line: Some(2),
+            column: Some(3),
+            severity: Some(4),
+            message: Some(5),
+        }],
+    }
+}
+
+/// `script` through `sh -c`, after creating the plugin cache directory.
+fn shell(script: &str) -> ExecutionAction {
+    let script = format!("mkdir -p \"$TF_PLUGIN_CACHE_DIR\" && {script}");
+    action("sh", &["-c", &script])
+}
+
+/// Helper for constructing `ExecutionAction`s concisely.
+fn action(program: &str, args: &[&str]) -> ExecutionAction {
+    ExecutionAction {
+        program: program.to_string(),
+        args: args.iter().map(|s| s.to_string()).collect(),
+        env: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::PrimaryCommand;
+
+    fn cmd(primary: PrimaryCommand, selector: Option<&str>) -> CommandRef {
+        CommandRef {
+            primary,
+            selector: selector.map(|s| s.to_string()),
+        }
+    }
+
+    fn shell_of(ext: &TerraformExtension, command: CommandRef) -> String {
+        let action = ext.build_action(&command).unwrap().unwrap();
+        format!("{} {}", action.program, action.args.join(" "))
+    }
+
+    #[test]
+    fn maps_commands_onto_terraform_and_tflint() {
+        let ext = TerraformExtension::new();
+        assert_eq!(ext.name(), "terraform");
+        assert!(ext.capabilities().contains("check:plan"));
+
+        assert_eq!(
+            shell_of(&ext, cmd(PrimaryCommand::Fmt, Some("check"))),
+            "terraform fmt -check -recursive -diff"
+        );
+        let lint = shell_of(&ext, cmd(PrimaryCommand::Lint, Some("static")));
+        assert!(
+            lint.starts_with("sh -c mkdir -p \"$TF_PLUGIN_CACHE_DIR\" && "),
+            "{lint}"
+        );
+        assert!(lint.contains("terraform validate -no-color"), "{lint}");
+        assert!(
+            lint.ends_with("tflint --recursive --format compact"),
+            "{lint}"
+        );
+        let plan = shell_of(&ext, cmd(PrimaryCommand::Check, Some("plan")));
+        assert!(
+            plan.ends_with("terraform plan -detailed-exitcode -input=false -lock=false -no-color"),
+            "{plan}"
+        );
+        assert!(ext
+            .failure_hint(&cmd(PrimaryCommand::Check, Some("plan")))
+            .unwrap()
+            .contains("exit status 2"));
+
+        for unmapped in [
+            cmd(PrimaryCommand::Test, Some("unit")),
+            cmd(PrimaryCommand::Build, Some("release")),
+        ] {
+            assert!(ext.build_action(&unmapped).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn runs_non_interactively_with_a_shared_plugin_cache() {
+        let ext = TerraformExtension::new();
+        let env = ext.env_vars();
+        assert_eq!(env["TF_IN_AUTOMATION"], "1");
+        assert_eq!(env["TF_INPUT"], "0");
+        assert_eq!(env["TF_PLUGIN_CACHE_DIR"], PLUGIN_CACHE_DIR);
+        assert_eq!(
+            ext.cache_mounts(),
+            ["terraform/plugins:/root/.terraform.d/plugin-cache"]
+        );
+        let volumes = ext.volume_mounts();
+        assert_eq!(volumes[0].path, ".terraform");
+        assert_eq!(volumes[0].key_inputs, [LOCK_FILE]);
+    }
+
+    #[test]
+    fn tflint_matcher_parses_compact_output() {
+        let ext = TerraformExtension::new();
+        let matchers: Vec<_> = ext
+            .problem_matchers(&cmd(PrimaryCommand::Lint, Some("static")))
+            .iter()
+            .map(|m| devflow_core::problem::Matcher::compile(m).unwrap())
+            .collect();
+        let problems = devflow_core::problem::Scanner::new(&matchers).line(
+            "modules/net/main.tf:3:1: Warning - variable \"cidr\" is declared but not used (terraform_unused_declarations)",
+        );
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].file.as_deref(), Some("modules/net/main.tf"));
+        assert_eq!(problems[0].severity, Severity::Warning);
+        assert!(problems[0]
+            .message
+            .ends_with("(terraform_unused_declarations)"));
+    }
+
+    #[test]
+    fn tools_pin_the_tflint_release() {
+        let tools = TerraformExtension::new().tools();
+        assert_eq!(tools[0].bin(), "tflint");
+        assert_eq!(
+            tools[0].url_for("aarch64-apple-darwin"),
+            "https://github.com/terraform-linters/tflint/releases/download/v0.53.0/tflint_darwin_arm64.zip"
+        );
+    }
+}
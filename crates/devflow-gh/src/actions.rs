@@ -0,0 +1,141 @@
+//! Action versions of generated workflows (`[ci.actions]`).
+//!
+//! Templates reference actions by their major tag (`actions/checkout@v4`).
+//! A pin in `[ci.actions]` replaces the version of every reference to that
+//! repository, sub-path actions such as `actions/cache/restore` included,
+//! and `ci:check` reports references that differ from their pin.
+
+use devflow_core::DevflowConfig;
+
+/// A `uses:` reference to a repository action.
+#[derive(Debug, PartialEq, Eq)]
+struct Reference<'a> {
+    /// E.g. `actions/cache/restore`.
+    action: &'a str,
+    /// E.g. `actions/cache`.
+    repository: &'a str,
+    version: &'a str,
+}
+
+/// The action `line` references, unless it has no `uses:` or uses a local
+/// (`./`) or Docker action.
+fn reference(line: &str) -> Option<Reference<'_>> {
+    let step = line.trim_start();
+    let step = step.strip_prefix("- ").unwrap_or(step);
+    let value = step.strip_prefix("uses:")?;
+    let value = value.split(" #").next()?.trim().trim_matches(['"', '\'']);
+    let (action, version) = value.split_once('@')?;
+    if action.starts_with("./") || action.starts_with("docker://") {
+        return None;
+    }
+    let mut parts = action.splitn(3, '/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    Some(Reference {
+        action,
+        repository: &action[..owner.len() + 1 + repo.len()],
+        version,
+    })
+}
+
+/// `workflow` with each action reference at its `[ci.actions]` pin.
+pub(crate) fn pin(cfg: &DevflowConfig, workflow: &str) -> String {
+    if cfg.ci.actions.is_empty() {
+        return workflow.to_string();
+    }
+    let mut out = String::with_capacity(workflow.len());
+    for line in workflow.split_inclusive('\n') {
+        let pinned = reference(line).and_then(|reference| {
+            let pin = cfg.ci.actions.get(reference.repository)?;
+            let uses = format!("{}@{}", reference.action, reference.version);
+            Some(line.replacen(&uses, &format!("{}@{pin}", reference.action), 1))
+        });
+        out.push_str(pinned.as_deref().unwrap_or(line));
+    }
+    out
+}
+
+/// References in `workflow` that differ from their `[ci.actions]` pin, or,
+/// once any action is pinned, name an action without a pin.
+pub(crate) fn issues(cfg: &DevflowConfig, workflow: &str) -> Vec<String> {
+    if cfg.ci.actions.is_empty() {
+        return Vec::new();
+    }
+    let mut issues = Vec::new();
+    for (number, line) in workflow.lines().enumerate() {
+        let Some(reference) = reference(line) else {
+            continue;
+        };
+        let uses = format!("{}@{}", reference.action, reference.version);
+        match cfg.ci.actions.get(reference.repository) {
+            None => issues.push(format!(
+                "line {}: '{uses}' is not pinned in [ci.actions]",
+                number + 1
+            )),
+            Some(pin) if *pin != reference.version => issues.push(format!(
+                "line {}: '{uses}' does not match its pin '{pin}' in [ci.actions]",
+                number + 1
+            )),
+            Some(_) => {}
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKOUT_SHA: &str = "11bd71901bbe5b1630ceea73d27597364c9af683";
+
+    fn config(actions: &str) -> DevflowConfig {
+        toml::from_str(&format!(
+            "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[targets]\npr = [\"fmt:check\"]\n\n[ci.actions]\n{actions}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_action_references() {
+        assert_eq!(
+            reference("      - uses: actions/cache/restore@v4 # restore"),
+            Some(Reference {
+                action: "actions/cache/restore",
+                repository: "actions/cache",
+                version: "v4",
+            })
+        );
+        assert_eq!(
+            reference("        uses: 'docker/login-action@v3'")
+                .unwrap()
+                .repository,
+            "docker/login-action"
+        );
+        assert_eq!(reference("      - uses: ./.github/actions/setup"), None);
+        assert_eq!(reference("        uses: docker://alpine:3.20"), None);
+        assert_eq!(reference("        run: echo uses: a/b@v1"), None);
+    }
+
+    #[test]
+    fn pins_every_reference_and_reports_drift() {
+        let cfg = config(&format!(
+            "\"actions/checkout\" = \"{CHECKOUT_SHA}\"\n\"actions/cache\" = \"v4.2.0\"\n"
+        ));
+        let template = "steps:\n  - uses: actions/checkout@v4\n  - name: Restore\n    uses: actions/cache/restore@v4\n  - uses: actions/upload-artifact@v4\n";
+        let pinned = pin(&cfg, template);
+        assert!(pinned.contains(&format!("  - uses: actions/checkout@{CHECKOUT_SHA}\n")));
+        assert!(pinned.contains("    uses: actions/cache/restore@v4.2.0\n"));
+        assert_eq!(
+            issues(&cfg, &pinned),
+            ["line 5: 'actions/upload-artifact@v4' is not pinned in [ci.actions]"]
+        );
+        assert_eq!(
+            issues(&cfg, template)[0],
+            "line 2: 'actions/checkout@v4' does not match its pin '11bd71901bbe5b1630ceea73d27597364c9af683' in [ci.actions]"
+        );
+
+        let unpinned = config("");
+        assert_eq!(pin(&unpinned, template), template);
+        assert!(issues(&unpinned, template).is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+//! GitHub Actions run context.
+//!
+//! Parses the webhook payload referenced by `GITHUB_EVENT_PATH` so that callers
+//! can rely on the event itself (pull request head, base branch, labels) instead
+//! of loosely forwarded environment variables.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Pull request label that skips heavy checks when present.
+pub const SKIP_CI_LABEL: &str = "skip-ci";
+
+/// Context describing the GitHub Actions event that triggered the current run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GithubContext {
+    /// The event name (e.g. `pull_request`, `push`).
+    pub event_name: Option<String>,
+    /// The `owner/name` repository slug.
+    pub repository: Option<String>,
+    /// The pull request number, if the event is pull-request based.
+    pub pr_number: Option<u64>,
+    /// The commit being verified (PR head for pull requests, pushed SHA otherwise).
+    pub head_sha: Option<String>,
+    /// The branch the pull request targets (e.g. `main`).
+    pub base_ref: Option<String>,
+    /// Labels attached to the pull request.
+    pub labels: Vec<String>,
+    /// The user that triggered the event.
+    pub actor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventPayload {
+    pull_request: Option<PullRequestPayload>,
+    /// Pushed commit SHA for `push` events.
+    after: Option<String>,
+    sender: Option<UserPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestPayload {
+    number: u64,
+    head: GitRefPayload,
+    base: GitRefPayload,
+    #[serde(default)]
+    labels: Vec<LabelPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitRefPayload {
+    sha: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelPayload {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserPayload {
+    login: String,
+}
+
+impl GithubContext {
+    /// Builds the context from the GitHub Actions environment.
+    ///
+    /// Plain environment variables (`GITHUB_SHA`, `GITHUB_ACTOR`, ...) provide the
+    /// baseline; the event payload at `GITHUB_EVENT_PATH`, when present, takes precedence.
+    ///
+    /// # Errors
+    /// Returns an error if the event payload exists but cannot be read or parsed.
+    pub fn from_env() -> Result<Self> {
+        let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+
+        let mut ctx = Self {
+            event_name: env("GITHUB_EVENT_NAME"),
+            repository: env("GITHUB_REPOSITORY"),
+            pr_number: None,
+            head_sha: env("GITHUB_SHA"),
+            base_ref: env("GITHUB_BASE_REF"),
+            labels: Vec::new(),
+            actor: env("GITHUB_ACTOR"),
+        };
+
+        if let Some(event_path) = env("GITHUB_EVENT_PATH") {
+            let path = Path::new(&event_path);
+            if path.is_file() {
+                let text = std::fs::read_to_string(path).with_context(|| {
+                    format!("failed to read GitHub event payload: {}", path.display())
+                })?;
+                ctx.apply_payload(&text)?;
+            }
+        }
+
+        Ok(ctx)
+    }
+
+    /// Builds the context from a raw event payload.
+    ///
+    /// # Errors
+    /// Returns an error if the payload is not valid JSON.
+    pub fn from_payload(event_name: &str, payload: &str) -> Result<Self> {
+        let mut ctx = Self {
+            event_name: Some(event_name.to_string()),
+            ..Self::default()
+        };
+        ctx.apply_payload(payload)?;
+        Ok(ctx)
+    }
+
+    fn apply_payload(&mut self, payload: &str) -> Result<()> {
+        let event: EventPayload =
+            serde_json::from_str(payload).context("failed to parse GitHub event payload")?;
+
+        if let Some(pr) = event.pull_request {
+            self.pr_number = Some(pr.number);
+            self.head_sha = Some(pr.head.sha);
+            self.base_ref = Some(pr.base.git_ref);
+            self.labels = pr.labels.into_iter().map(|l| l.name).collect();
+        } else if let Some(after) = event.after {
+            self.head_sha = Some(after);
+        }
+
+        if let Some(sender) = event.sender {
+            self.actor = Some(sender.login);
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the pull request carries the given label.
+    pub fn has_label(&self, name: &str) -> bool {
+        self.labels.iter().any(|label| label == name)
+    }
+
+    /// Returns true if heavy checks should be skipped for this run.
+    pub fn skip_requested(&self) -> bool {
+        self.has_label(SKIP_CI_LABEL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PR_PAYLOAD: &str = r#"{
+        "action": "synchronize",
+        "number": 42,
+        "pull_request": {
+            "number": 42,
+            "head": { "sha": "abc123", "ref": "feature/x" },
+            "base": { "sha": "def456", "ref": "main" },
+            "labels": [{ "name": "skip-ci" }, { "name": "docs" }]
+        },
+        "sender": { "login": "octocat" }
+    }"#;
+
+    #[test]
+    fn parses_pull_request_payload() {
+        let ctx = GithubContext::from_payload("pull_request", PR_PAYLOAD).unwrap();
+        assert_eq!(ctx.event_name.as_deref(), Some("pull_request"));
+        assert_eq!(ctx.pr_number, Some(42));
+        assert_eq!(ctx.head_sha.as_deref(), Some("abc123"));
+        assert_eq!(ctx.base_ref.as_deref(), Some("main"));
+        assert_eq!(ctx.actor.as_deref(), Some("octocat"));
+        assert_eq!(ctx.labels, vec!["skip-ci", "docs"]);
+        assert!(ctx.skip_requested());
+    }
+
+    #[test]
+    fn parses_push_payload() {
+        let payload =
+            r#"{ "ref": "refs/heads/main", "after": "fff000", "sender": { "login": "bot" } }"#;
+        let ctx = GithubContext::from_payload("push", payload).unwrap();
+        assert_eq!(ctx.pr_number, None);
+        assert_eq!(ctx.head_sha.as_deref(), Some("fff000"));
+        assert_eq!(ctx.base_ref, None);
+        assert!(ctx.labels.is_empty());
+        assert!(!ctx.skip_requested());
+    }
+
+    #[test]
+    fn rejects_malformed_payload() {
+        let err = GithubContext::from_payload("push", "{not json").expect_err("must fail");
+        assert!(err
+            .to_string()
+            .contains("failed to parse GitHub event payload"));
+    }
+
+    #[test]
+    fn has_label_matches_exact_names_only() {
+        let ctx = GithubContext {
+            labels: vec!["skip-ci-later".to_string()],
+            ..GithubContext::default()
+        };
+        assert!(!ctx.has_label(SKIP_CI_LABEL));
+        assert!(ctx.has_label("skip-ci-later"));
+    }
+}
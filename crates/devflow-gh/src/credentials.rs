@@ -0,0 +1,205 @@
+//! GitHub credential resolution.
+//!
+//! Tokens are resolved through a provider chain so that local runs do not need
+//! `GITHUB_TOKEN` exported in the shell:
+//!
+//! 1. `GITHUB_TOKEN` / `GH_TOKEN` environment variables
+//! 2. `gh auth token` (GitHub CLI login)
+//! 3. the OS keychain (`security` on macOS, `secret-tool` on Linux)
+//! 4. the `[ci] credential_helper` command from `devflow.toml`
+
+use std::fmt::{Display, Formatter};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use tracing::debug;
+
+/// Keychain service name under which Devflow looks up the GitHub token.
+pub const KEYCHAIN_SERVICE: &str = "devflow";
+/// Keychain account name under which Devflow looks up the GitHub token.
+pub const KEYCHAIN_ACCOUNT: &str = "github";
+
+/// Environment variables checked for a token, in order.
+const TOKEN_ENV_VARS: &[&str] = &["GITHUB_TOKEN", "GH_TOKEN"];
+
+/// Where a resolved token came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// An environment variable (name included).
+    Env(&'static str),
+    /// The GitHub CLI (`gh auth token`).
+    GhCli,
+    /// The operating system keychain.
+    Keychain,
+    /// The configured credential helper command.
+    Helper,
+}
+
+impl Display for CredentialSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env(var) => write!(f, "${var}"),
+            Self::GhCli => f.write_str("gh auth token"),
+            Self::Keychain => f.write_str("OS keychain"),
+            Self::Helper => f.write_str("credential helper"),
+        }
+    }
+}
+
+/// A GitHub token together with its origin.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credential {
+    /// The token value.
+    pub token: String,
+    /// The provider that supplied the token.
+    pub source: CredentialSource,
+}
+
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("token", &"<redacted>")
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+/// Resolves a GitHub token through the provider chain.
+///
+/// Returns `Ok(None)` when no provider yields a token.
+///
+/// # Errors
+/// Returns an error only if the configured `credential_helper` fails; the
+/// implicit providers are skipped silently when unavailable.
+pub fn resolve_github_token(credential_helper: Option<&str>) -> Result<Option<Credential>> {
+    for var in TOKEN_ENV_VARS {
+        if let Some(token) = std::env::var(var).ok().and_then(non_empty) {
+            return Ok(Some(Credential {
+                token,
+                source: CredentialSource::Env(var),
+            }));
+        }
+    }
+
+    let discovered = gh_cli_token()
+        .map(|token| (token, CredentialSource::GhCli))
+        .or_else(|| keychain_token().map(|token| (token, CredentialSource::Keychain)));
+    if let Some((token, source)) = discovered {
+        debug!("resolved GitHub token from {}", source);
+        return Ok(Some(Credential { token, source }));
+    }
+
+    if let Some(helper) = credential_helper {
+        let token = run_helper(helper)?;
+        return Ok(Some(Credential {
+            token,
+            source: CredentialSource::Helper,
+        }));
+    }
+
+    Ok(None)
+}
+
+fn gh_cli_token() -> Option<String> {
+    capture(Command::new("gh").args(["auth", "token"]))
+}
+
+fn keychain_token() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        capture(Command::new("security").args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            KEYCHAIN_ACCOUNT,
+            "-w",
+        ]))
+    } else if cfg!(target_os = "linux") {
+        capture(Command::new("secret-tool").args([
+            "lookup",
+            "service",
+            KEYCHAIN_SERVICE,
+            "account",
+            KEYCHAIN_ACCOUNT,
+        ]))
+    } else {
+        None
+    }
+}
+
+/// Runs the configured helper through the shell and returns its trimmed stdout.
+fn run_helper(helper: &str) -> Result<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(helper)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to run credential helper '{helper}'"))?;
+
+    if !output.status.success() {
+        bail!(
+            "credential helper '{}' failed with status {}",
+            helper,
+            output.status
+        );
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .and_then(non_empty)
+        .with_context(|| format!("credential helper '{helper}' returned no token"))
+}
+
+/// Runs a provider command, returning its trimmed stdout on success.
+fn capture(command: &mut Command) -> Option<String> {
+    let output = command
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok().and_then(non_empty)
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn helper_output_is_trimmed() {
+        assert_eq!(run_helper("printf ' tok-123\\n'").unwrap(), "tok-123");
+    }
+
+    #[test]
+    fn failing_helper_is_an_error() {
+        let err = run_helper("exit 3").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("credential helper 'exit 3' failed"));
+    }
+
+    #[test]
+    fn empty_helper_output_is_an_error() {
+        let err = run_helper("true").unwrap_err();
+        assert!(err.to_string().contains("returned no token"));
+    }
+
+    #[test]
+    fn debug_output_redacts_token() {
+        let cred = Credential {
+            token: "secret".to_string(),
+            source: CredentialSource::Helper,
+        };
+        let rendered = format!("{:?}", cred);
+        assert!(!rendered.contains("secret"));
+        assert_eq!(CredentialSource::Env("GH_TOKEN").to_string(), "$GH_TOKEN");
+    }
+}
@@ -0,0 +1,575 @@
+//! Adoption of a hand-written workflow (`dwf ci:import`).
+//!
+//! The workflow's triggers become target profiles (`pull_request` is `pr`,
+//! `push` is `main`, or `release` for tag pushes only, `schedule` is
+//! `maintenance`) and the steps that run a known tool become the commands
+//! of those profiles, e.g. `cargo clippy` is `lint:static` and `npm test` is
+//! `test:unit`. Checkouts, toolchain setup, caching and dependency installs
+//! are left out: the generated workflow does them itself. Every other step is
+//! kept as a user-managed `[ci.jobs]` step of the generated job, before the
+//! commands if it ran before the first recognized step of its job, after
+//! them otherwise. A step that only runs conditionally (`if`), in another
+//! directory or shell, or with a line no command covers is kept as a whole.
+//! Jobs with no recognized step, conditional jobs and reusable workflow calls
+//! are not imported.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+use devflow_core::config::CiLayout;
+use devflow_core::{CommandRef, DevflowConfig, PrimaryCommand};
+
+use crate::{yaml, CHECK_JOB, MAINTENANCE_PROFILE};
+
+/// Actions the generated workflow replaces: checkout, toolchains, caches.
+const COVERED_ACTIONS: &[&str] = &[
+    "actions/checkout",
+    "actions/cache",
+    "actions/setup-node",
+    "actions-rs/toolchain",
+    "actions-rust-lang/setup-rust-toolchain",
+    "dtolnay/rust-toolchain",
+    "Swatinem/rust-cache",
+    "taiki-e/install-action",
+    "pnpm/action-setup",
+];
+
+/// The step keys of GitHub's syntax that `[ci.jobs]` steps take, in order.
+const STEP_KEYS: &[&str] = &[
+    "name",
+    "id",
+    "if",
+    "uses",
+    "run",
+    "shell",
+    "working-directory",
+    "with",
+    "env",
+    "continue-on-error",
+    "timeout-minutes",
+];
+
+/// A step of the workflow kept as it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManagedStep {
+    /// The job it comes from.
+    pub job: String,
+    pub step: Map<String, Value>,
+}
+
+/// What a workflow maps to.
+#[derive(Debug, Default, PartialEq)]
+pub struct Import {
+    /// The profiles of its triggers.
+    pub profiles: Vec<String>,
+    /// The commands its steps run, by job, in the order they first run.
+    pub commands: Vec<String>,
+    /// Steps kept before the commands.
+    pub pre_steps: Vec<ManagedStep>,
+    /// Steps kept after the commands.
+    pub post_steps: Vec<ManagedStep>,
+    /// Jobs left out, with why.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// What one line of a `run` script does.
+#[derive(Debug, PartialEq, Eq)]
+enum Line {
+    Command(String),
+    /// Setup the generated workflow does itself.
+    Covered,
+    Unknown,
+}
+
+/// What a step does.
+enum Step {
+    Commands(Vec<String>),
+    Covered,
+    Managed,
+}
+
+/// The generated job that runs the kept steps: `verify`, or `check` in the
+/// single-job layout.
+pub fn managed_job(cfg: &DevflowConfig) -> &'static str {
+    match cfg.ci.layout {
+        CiLayout::MultiJob => "verify",
+        CiLayout::SingleJob => CHECK_JOB,
+    }
+}
+
+/// Maps the workflow `text`.
+///
+/// # Errors
+/// Returns an error if it is not valid YAML, has no `pull_request`, `push`
+/// or `schedule` trigger, or no step maps to a command.
+pub fn import_workflow(text: &str) -> Result<Import> {
+    let workflow = yaml::parse(text).map_err(|e| anyhow!("invalid YAML: {e}"))?;
+    let mut import = Import {
+        profiles: profiles(workflow.get("on").unwrap_or(&Value::Null)),
+        ..Import::default()
+    };
+    if import.profiles.is_empty() {
+        bail!("the workflow has no pull_request, push or schedule trigger");
+    }
+    let jobs = workflow
+        .get("jobs")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("the workflow has no jobs"))?;
+    for (job, spec) in jobs {
+        if let Some(condition) = spec.get("if") {
+            let condition = condition
+                .as_str()
+                .map_or(condition.to_string(), String::from);
+            import
+                .skipped
+                .push((job.clone(), format!("it only runs if {condition}")));
+            continue;
+        }
+        if spec.get("uses").is_some() {
+            import
+                .skipped
+                .push((job.clone(), "it calls a reusable workflow".to_string()));
+            continue;
+        }
+        let steps: Vec<&Map<String, Value>> = spec
+            .get("steps")
+            .and_then(Value::as_array)
+            .map(|steps| steps.iter().filter_map(Value::as_object).collect())
+            .unwrap_or_default();
+        let mut managed = Vec::new();
+        let mut before = None;
+        for step in steps {
+            match classify(step) {
+                Step::Commands(commands) => {
+                    before.get_or_insert(managed.len());
+                    for command in commands {
+                        if !import.commands.contains(&command) {
+                            import.commands.push(command);
+                        }
+                    }
+                }
+                Step::Covered => {}
+                Step::Managed => managed.push(ManagedStep {
+                    job: job.clone(),
+                    step: step.clone(),
+                }),
+            }
+        }
+        let Some(before) = before else {
+            import
+                .skipped
+                .push((job.clone(), "no step maps to a dwf command".to_string()));
+            continue;
+        };
+        let post = managed.split_off(before);
+        for (kept, steps) in [
+            (&mut import.pre_steps, managed),
+            (&mut import.post_steps, post),
+        ] {
+            for step in steps {
+                if !kept.iter().any(|known| known.step == step.step) {
+                    kept.push(step);
+                }
+            }
+        }
+    }
+    if import.commands.is_empty() {
+        bail!("no step of the workflow maps to a dwf command");
+    }
+    Ok(import)
+}
+
+/// The profiles the `on` triggers map to.
+fn profiles(on: &Value) -> Vec<String> {
+    let triggers: Vec<(&str, Option<&Value>)> = match on {
+        Value::String(event) => vec![(event.as_str(), None)],
+        Value::Array(events) => events
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|event| (event, None))
+            .collect(),
+        Value::Object(events) => events
+            .iter()
+            .map(|(event, spec)| (event.as_str(), Some(spec)))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut profiles: Vec<String> = Vec::new();
+    for (event, spec) in triggers {
+        let tags_only = spec.is_some_and(|spec| {
+            spec.get("tags").is_some()
+                && spec.get("branches").is_none()
+                && spec.get("branches-ignore").is_none()
+        });
+        let profile = match event {
+            "pull_request" | "pull_request_target" => "pr",
+            "push" if tags_only => "release",
+            "push" => "main",
+            "schedule" => MAINTENANCE_PROFILE,
+            _ => continue,
+        };
+        if !profiles.iter().any(|known| known == profile) {
+            profiles.push(profile.to_string());
+        }
+    }
+    profiles
+}
+
+fn classify(step: &Map<String, Value>) -> Step {
+    if step.contains_key("if") || step.contains_key("working-directory") {
+        return Step::Managed;
+    }
+    if let Some(uses) = step.get("uses").and_then(Value::as_str) {
+        let action = uses.split('@').next().unwrap_or_default();
+        return if COVERED_ACTIONS.contains(&action) {
+            Step::Covered
+        } else {
+            Step::Managed
+        };
+    }
+    let shell = step.get("shell").and_then(Value::as_str);
+    let Some(run) = step.get("run").and_then(Value::as_str) else {
+        return Step::Managed;
+    };
+    if shell.is_some_and(|shell| !matches!(shell, "bash" | "sh")) {
+        return Step::Managed;
+    }
+    let mut commands = Vec::new();
+    for line in script_lines(run) {
+        match map_line(&line) {
+            Line::Command(command) => commands.push(command),
+            Line::Covered => {}
+            Line::Unknown => return Step::Managed,
+        }
+    }
+    if commands.is_empty() {
+        Step::Covered
+    } else {
+        Step::Commands(commands)
+    }
+}
+
+/// The commands of a script: continuation lines joined, `&&` chains split,
+/// blank lines and comments left out.
+fn script_lines(run: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in run.lines() {
+        let line = line.trim();
+        if let Some(start) = line.strip_suffix('\\') {
+            current.push_str(start.trim_end());
+            current.push(' ');
+            continue;
+        }
+        current.push_str(line);
+        let joined = std::mem::take(&mut current);
+        lines.extend(
+            joined
+                .split("&&")
+                .map(str::trim)
+                .filter(|part| !part.is_empty() && !part.starts_with('#'))
+                .map(String::from),
+        );
+    }
+    lines
+}
+
+/// The command a script line runs: a cargo, npm, yarn, pnpm or npx call this
+/// knows, or `dwf` itself.
+fn map_line(line: &str) -> Line {
+    let words: Vec<&str> = line
+        .split_whitespace()
+        .skip_while(|word| word.contains('=') && !word.starts_with('-'))
+        .collect();
+    let command = |name: &str| Line::Command(name.to_string());
+    let has = |flag: &str| words.contains(&flag);
+    if has("--version") || has("-V") {
+        return Line::Covered;
+    }
+    match words.as_slice() {
+        [] | ["set", ..] | ["rustup", ..] | ["echo", ..] => Line::Covered,
+        ["cargo", rest @ ..] => {
+            let rest: Vec<&str> = rest
+                .iter()
+                .copied()
+                .filter(|word| !word.starts_with('+'))
+                .collect();
+            match rest.as_slice() {
+                ["fmt", ..] if has("--check") => command("fmt:check"),
+                ["clippy", ..] => command("lint:static"),
+                ["audit", ..] | ["deny", ..] => command("lint:security"),
+                ["build", ..] if has("--release") || has("-r") => command("build:release"),
+                ["build", ..] => command("build:debug"),
+                ["test", ..] if has("--test") => command("test:integration"),
+                ["test", ..] | ["nextest", "run", ..] => command("test:unit"),
+                ["fetch", ..] | ["install", ..] => Line::Covered,
+                _ => Line::Unknown,
+            }
+        }
+        [manager @ ("npm" | "yarn" | "pnpm"), rest @ ..] => {
+            let script = match rest {
+                [] => return Line::Covered,
+                ["ci" | "install" | "i", ..] => return Line::Covered,
+                ["test" | "t", ..] => "test",
+                ["run", script, ..] => script,
+                [script, ..] if *manager != "npm" => script,
+                _ => return Line::Unknown,
+            };
+            match script {
+                "test" | "test:unit" => command("test:unit"),
+                "test:integration" => command("test:integration"),
+                "test:e2e" | "e2e" => command("test:e2e"),
+                "lint" => command("lint:static"),
+                "typecheck" | "type-check" | "tsc" => command("lint:types"),
+                "format:check" | "fmt:check" | "prettier:check" => command("fmt:check"),
+                "build" => command("build:release"),
+                _ => Line::Unknown,
+            }
+        }
+        ["npx", "tsc", ..] if has("--noEmit") => command("lint:types"),
+        ["npx", "eslint", ..] => command("lint:static"),
+        ["npx", "prettier", ..] if has("--check") => command("fmt:check"),
+        ["npx", "playwright", "test", ..] => command("test:e2e"),
+        ["dwf", rest @ ..] if !rest.iter().any(|word| word.starts_with('-')) => {
+            let text = rest.join(":");
+            match CommandRef::from_str(&text) {
+                Ok(cmd) if cmd.primary != PrimaryCommand::Check => Line::Command(cmd.canonical()),
+                _ => Line::Unknown,
+            }
+        }
+        _ => Line::Unknown,
+    }
+}
+
+impl Import {
+    /// The proposed `devflow.toml` settings, with the kept steps added to the
+    /// generated job `job`. `source` names the workflow in comments.
+    pub fn render(&self, source: &str, job: &str) -> String {
+        let commands: Vec<String> = self.commands.iter().map(|c| string(c)).collect();
+        let mut out = format!("# Proposed by `dwf ci:import` from {source}.\n[targets]\n");
+        for profile in &self.profiles {
+            out.push_str(&format!("{profile} = [{}]\n", commands.join(", ")));
+        }
+        if !self.pre_steps.is_empty() || !self.post_steps.is_empty() {
+            out.push_str(&format!(
+                "\n# User-managed: the steps of {source} that map to no dwf\n\
+                 # command, run by the generated `{job}` job as they are. Edit them here.\n\
+                 [ci.jobs.{job}]\n"
+            ));
+            for (key, steps) in [
+                ("pre_steps", &self.pre_steps),
+                ("post_steps", &self.post_steps),
+            ] {
+                if steps.is_empty() {
+                    continue;
+                }
+                out.push_str(&format!("{key} = [\n"));
+                for managed in steps {
+                    out.push_str(&format!("  # job '{}'\n", managed.job));
+                    out.push_str(&format!("  {},\n", inline_step(&managed.step)));
+                }
+                out.push_str("]\n");
+            }
+        }
+        if !self.skipped.is_empty() {
+            out.push_str(&format!(
+                "\n# Not imported; move these jobs to a workflow of their own, as\n\
+                 # `dwf ci:generate` rewrites {source}:\n"
+            ));
+            for (job, why) in &self.skipped {
+                out.push_str(&format!("# - job '{job}': {why}\n"));
+            }
+        }
+        out
+    }
+}
+
+/// `step` as a TOML inline table of the `[ci.jobs]` step keys.
+fn inline_step(step: &Map<String, Value>) -> String {
+    let mut entries = Vec::new();
+    for key in STEP_KEYS {
+        let Some(value) = step.get(*key) else {
+            continue;
+        };
+        let rendered = match (value, *key) {
+            (Value::Object(map), _) => {
+                let pairs: Vec<String> = map
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", bare_key(name), string(&text(value))))
+                    .collect();
+                format!("{{ {} }}", pairs.join(", "))
+            }
+            (Value::Bool(flag), _) => flag.to_string(),
+            (Value::Number(number), "timeout-minutes") => number.to_string(),
+            (value, _) => string(&text(value)),
+        };
+        entries.push(format!("{} = {rendered}", bare_key(key)));
+    }
+    format!("{{ {} }}", entries.join(", "))
+}
+
+/// A scalar as the string `[ci.jobs]` steps take.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// `value` as a TOML basic string; JSON's escapes are valid TOML ones.
+fn string(value: &str) -> String {
+    serde_json::to_string(value).expect("strings serialize")
+}
+
+fn bare_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_string()
+    } else {
+        string(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKFLOW: &str = r#"
+name: CI
+on:
+  push:
+    branches: [main]
+  pull_request:
+
+jobs:
+  lint:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@stable
+        with:
+          components: clippy, rustfmt
+      - run: cargo fmt --all -- --check
+      - run: cargo clippy --all-targets -- -D warnings
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: Start services
+        run: docker compose up -d db
+      - run: |
+          cargo build --locked
+          cargo test --workspace
+      - name: Upload coverage
+        uses: codecov/codecov-action@v4
+        with:
+          token: ${{ secrets.CODECOV_TOKEN }}
+          fail_ci_if_error: true
+      - name: Push image
+        if: github.ref == 'refs/heads/main'
+        run: ./scripts/push.sh
+  deploy:
+    needs: [test]
+    if: github.event_name == 'push'
+    runs-on: ubuntu-latest
+    steps:
+      - run: ./deploy.sh
+"#;
+
+    #[test]
+    fn maps_steps_to_commands_and_keeps_the_rest() {
+        let import = import_workflow(WORKFLOW).unwrap();
+        assert_eq!(import.profiles, ["pr", "main"]);
+        assert_eq!(
+            import.commands,
+            ["fmt:check", "lint:static", "build:debug", "test:unit"]
+        );
+        let names = |steps: &[ManagedStep]| -> Vec<String> {
+            steps
+                .iter()
+                .map(|managed| text(&managed.step["name"]))
+                .collect()
+        };
+        assert_eq!(names(&import.pre_steps), ["Start services"]);
+        assert_eq!(names(&import.post_steps), ["Upload coverage", "Push image"]);
+        assert_eq!(
+            import.skipped,
+            [(
+                "deploy".to_string(),
+                "it only runs if github.event_name == 'push'".to_string()
+            )]
+        );
+
+        let proposal = import.render(".github/workflows/ci.yml", "verify");
+        assert!(proposal.starts_with(
+            "# Proposed by `dwf ci:import` from .github/workflows/ci.yml.\n[targets]\n\
+             pr = [\"fmt:check\", \"lint:static\", \"build:debug\", \"test:unit\"]\n"
+        ));
+        assert!(proposal.contains(
+            "[ci.jobs.verify]\npre_steps = [\n  # job 'test'\n  \
+             { name = \"Start services\", run = \"docker compose up -d db\" },\n]\n"
+        ));
+        assert!(proposal.contains(
+            "{ name = \"Upload coverage\", uses = \"codecov/codecov-action@v4\", with = { \
+             fail_ci_if_error = \"true\", token = \"${{ secrets.CODECOV_TOKEN }}\" } },"
+        ));
+        assert!(
+            proposal.ends_with("# - job 'deploy': it only runs if github.event_name == 'push'\n")
+        );
+        let parsed: toml::Table = toml::from_str(&proposal).unwrap();
+        assert_eq!(
+            parsed["ci"]["jobs"]["verify"]["post_steps"][1]["if"].as_str(),
+            Some("github.ref == 'refs/heads/main'")
+        );
+    }
+
+    #[test]
+    fn maps_script_lines() {
+        for (line, expected) in [
+            (
+                "cargo +nightly build --release",
+                Line::Command("build:release".into()),
+            ),
+            (
+                "RUST_LOG=debug cargo nextest run",
+                Line::Command("test:unit".into()),
+            ),
+            (
+                "cargo test --test api",
+                Line::Command("test:integration".into()),
+            ),
+            ("npm ci", Line::Covered),
+            ("npm run lint", Line::Command("lint:static".into())),
+            ("yarn typecheck", Line::Command("lint:types".into())),
+            ("npx prettier --check .", Line::Command("fmt:check".into())),
+            ("dwf test unit", Line::Command("test:unit".into())),
+            ("dwf check:pr", Line::Unknown),
+            ("npm publish", Line::Unknown),
+            ("make docs", Line::Unknown),
+        ] {
+            assert_eq!(map_line(line), expected, "{line}");
+        }
+        assert_eq!(
+            script_lines("npm ci && npm test\n# lint\ncargo clippy \\\n  --all-targets\n"),
+            ["npm ci", "npm test", "cargo clippy --all-targets"]
+        );
+        assert_eq!(
+            profiles(
+                &yaml::parse(
+                    "on:\n  push:\n    tags: ['v*']\n  schedule:\n    - cron: '0 3 * * 1'\n"
+                )
+                .unwrap()["on"]
+            ),
+            ["release", "maintenance"]
+        );
+        assert!(import_workflow("on: [workflow_dispatch]\njobs: {}\n")
+            .unwrap_err()
+            .to_string()
+            .contains("no pull_request, push or schedule trigger"));
+    }
+}
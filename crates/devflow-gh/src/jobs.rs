@@ -0,0 +1,399 @@
+//! The generated workflow's jobs as data, for replaying them locally
+//! (`dwf ci:run`, `dwf ci:debug`).
+//!
+//! The job graph comes from the same grouping `render_profile_workflow` uses,
+//! and the container environment constants mirror the `docker run` steps of
+//! the templates (a test keeps the two in sync).
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use devflow_core::config::{CiLayout, RunnerSpec};
+use devflow_core::{DevflowConfig, TargetEntry};
+
+use crate::{
+    badge_condition, entry_flags, is_attest, verify_groups, BADGE_JOB, CHECK_JOB,
+    MAINTENANCE_PROFILE, MAINTENANCE_REPORT_JOB, WAIT_ALL,
+};
+
+/// Repository of the CI image the prep job builds, tagged with the fingerprint.
+pub const IMAGE_REPOSITORY: &str = "devflow-ci";
+
+/// Environment of every `docker run` in the build and verify jobs.
+pub const CONTAINER_ENV: &[(&str, &str)] = &[
+    ("CARGO_HOME", "/workspace/.cargo-cache"),
+    ("CARGO_TARGET_DIR", "/workspace/target/ci"),
+    ("SCCACHE_DIR", "/workspace/.cargo-cache/sccache"),
+    ("RUSTC_WRAPPER", "sccache"),
+];
+
+/// Additional environment of the verify jobs' check step.
+pub const CHECK_ENV: &[(&str, &str)] = &[
+    ("IS_CONTAINER", "true"),
+    (
+        "PATH",
+        "/workspace/.cargo-cache/bin:/usr/local/cargo/bin:/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin",
+    ),
+];
+
+/// Workspace directories the build and verify jobs cache between runs.
+pub const CACHE_DIRS: &[&str] = &[
+    ".cargo-cache/registry",
+    ".cargo-cache/git",
+    ".cargo-cache/sccache",
+    "target/ci",
+];
+
+/// The build job's cache warm-up.
+pub const BUILD_SCRIPT: &str = "cargo fetch && cargo build --all-targets";
+
+/// Installs `dwf` into the cache before the verify jobs run their checks.
+pub const BOOTSTRAP_SCRIPT: &str =
+    "cargo install --path crates/devflow-cli --debug --root /workspace/.cargo-cache";
+
+/// What a job does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    /// Fingerprint the environment and build the CI image.
+    Prep,
+    /// Warm the build cache inside the CI image.
+    Build,
+    /// Run profile commands in parallel inside the CI image.
+    Verify,
+    /// Write and sign provenance (tag pushes only).
+    Attest,
+    /// File the maintenance results as an issue (scheduled runs only).
+    Report,
+    /// Publish the status badge (pushes to `main` only).
+    Badge,
+    /// Run `dwf check:<profile>` on the runner (`[ci] layout = "single-job"`).
+    Check,
+}
+
+/// One job of a generated workflow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkflowJob {
+    /// Job id, e.g. `verify` or `check_test_integration`.
+    pub id: String,
+    /// Display name, e.g. `Check test:integration`.
+    pub name: String,
+    pub kind: JobKind,
+    /// The job's `runs-on`.
+    pub runner: RunnerSpec,
+    /// Jobs that must succeed first.
+    pub needs: Vec<String>,
+    /// Commands a verify or check job runs.
+    pub entries: Vec<TargetEntry>,
+}
+
+/// Returns the jobs of `profile`'s workflow in dependency order; the
+/// single-job layout has just the `check` job.
+///
+/// # Errors
+/// Returns an error if `profile` is not a `[targets]` profile.
+pub fn profile_jobs(cfg: &DevflowConfig, profile: &str) -> Result<Vec<WorkflowJob>> {
+    let entries = cfg
+        .targets
+        .profiles
+        .get(profile)
+        .ok_or_else(|| anyhow!("unknown targets profile '{}'", profile))?;
+    let job = |id: &str, name: &str, kind, needs: &[&str]| WorkflowJob {
+        id: id.to_string(),
+        name: name.to_string(),
+        kind,
+        runner: cfg.ci.runners.default.clone(),
+        needs: needs.iter().map(|n| n.to_string()).collect(),
+        entries: Vec::new(),
+    };
+
+    if cfg.ci.layout == CiLayout::SingleJob {
+        return Ok(vec![WorkflowJob {
+            entries: entries.clone(),
+            ..job(CHECK_JOB, "Check", JobKind::Check, &[])
+        }]);
+    }
+
+    let mut jobs = vec![
+        job("prep", "Prep", JobKind::Prep, &[]),
+        job("build", "Build", JobKind::Build, &["prep"]),
+    ];
+    let mut verify_ids = Vec::new();
+    for group in verify_groups(cfg, entries) {
+        if !group.entries.is_empty() {
+            verify_ids.push(group.id.clone());
+        }
+        jobs.push(WorkflowJob {
+            runner: group.runner.clone(),
+            entries: group.entries.into_iter().cloned().collect(),
+            ..job(&group.id, &group.name, JobKind::Verify, &["prep", "build"])
+        });
+    }
+    if entries.iter().any(is_attest) {
+        jobs.push(job(
+            "attest",
+            "Attest",
+            JobKind::Attest,
+            &["prep", "verify"],
+        ));
+    }
+    if profile == MAINTENANCE_PROFILE {
+        jobs.push(WorkflowJob {
+            needs: verify_ids,
+            ..job(
+                MAINTENANCE_REPORT_JOB,
+                "Maintenance Report",
+                JobKind::Report,
+                &[],
+            )
+        });
+    }
+    if badge_condition(cfg, Some(profile)).is_some() {
+        jobs.push(job(BADGE_JOB, "Publish Badge", JobKind::Badge, &["verify"]));
+    }
+    Ok(jobs)
+}
+
+/// The CI image tag for an environment fingerprint, as computed by the prep job.
+pub fn ci_image(fingerprint: &str) -> String {
+    let short: String = fingerprint.chars().take(12).collect();
+    format!("{IMAGE_REPOSITORY}:{short}")
+}
+
+/// The verify job's check script for `entries`: every command in the
+/// background, then a wait for all of them. Unlike the workflow it does not
+/// report commit statuses, and path filters are left to the caller.
+pub fn check_script(entries: &[&TargetEntry]) -> String {
+    let mut script = String::from("pids=(); ");
+    for entry in entries {
+        script.push_str(&format!(
+            "dwf {}{} & pids+=($!); ",
+            entry.command(),
+            entry_flags(entry)
+        ));
+    }
+    script.push_str(WAIT_ALL);
+    script
+}
+
+/// The local commit a synthetic event describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    /// Commit checked out (`HEAD`).
+    pub sha: String,
+    /// Branch checked out.
+    pub branch: String,
+    /// Commit of the pull request base.
+    pub base_sha: String,
+    /// Branch a pull request would merge into.
+    pub base_branch: String,
+    /// Login reported as the event sender.
+    pub actor: String,
+}
+
+/// Tag pushed in the synthetic `release` event, so tag-only jobs run.
+pub const LOCAL_TAG: &str = "v0.0.0-local";
+
+/// The event that triggers `profile`'s workflow (see `profile_triggers`).
+pub fn profile_event(profile: &str) -> &'static str {
+    match profile {
+        "pr" => "pull_request",
+        "main" | "release" => "push",
+        "security" | MAINTENANCE_PROFILE => "schedule",
+        _ => "workflow_dispatch",
+    }
+}
+
+/// A webhook payload for `profile`'s trigger event at `rev`, with the fields
+/// the generated workflow and `GithubContext` read.
+pub fn event_payload(profile: &str, rev: &Revision) -> Value {
+    let sender = json!({ "login": rev.actor });
+    match profile_event(profile) {
+        "pull_request" => json!({
+            "action": "synchronize",
+            "number": 1,
+            "pull_request": {
+                "number": 1,
+                "head": { "sha": rev.sha, "ref": rev.branch },
+                "base": { "sha": rev.base_sha, "ref": rev.base_branch },
+                "labels": [],
+            },
+            "sender": sender,
+        }),
+        "push" => {
+            let git_ref = if profile == "release" {
+                format!("refs/tags/{LOCAL_TAG}")
+            } else {
+                format!("refs/heads/{}", rev.branch)
+            };
+            json!({ "ref": git_ref, "after": rev.sha, "sender": sender })
+        }
+        _ => json!({ "ref": format!("refs/heads/{}", rev.branch), "sender": sender }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(jobs: &[WorkflowJob]) -> Vec<&str> {
+        jobs.iter().map(|j| j.id.as_str()).collect()
+    }
+
+    #[test]
+    fn mirrors_the_rendered_job_graph() {
+        let cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = ["fmt:check", "test:integration"]
+            release = ["build:release", "package:attest"]
+            maintenance = ["check:security"]
+
+            [ci.runners]
+            "test:integration" = "ubuntu-24.04-arm"
+            "#,
+        )
+        .unwrap();
+
+        let jobs = profile_jobs(&cfg, "pr").unwrap();
+        assert_eq!(
+            ids(&jobs),
+            ["prep", "build", "verify", "check_test_integration"]
+        );
+        assert_eq!(jobs[2].entries, vec![TargetEntry::from("fmt:check")]);
+        assert_eq!(jobs[3].needs, ["prep", "build"]);
+        let workflow = crate::render_profile_workflow(&cfg, "pr").unwrap();
+        for job in &jobs {
+            assert!(workflow.contains(&format!("\n  {}:\n", job.id)));
+        }
+
+        let release = profile_jobs(&cfg, "release").unwrap();
+        assert_eq!(ids(&release), ["prep", "build", "verify", "attest"]);
+        assert_eq!(release[3].kind, JobKind::Attest);
+
+        let maintenance = profile_jobs(&cfg, "maintenance").unwrap();
+        assert_eq!(
+            ids(&maintenance),
+            ["prep", "build", "verify", "maintenance_report"]
+        );
+        assert_eq!(maintenance[3].kind, JobKind::Report);
+        assert_eq!(maintenance[3].needs, ["verify"]);
+        assert_eq!(profile_event("maintenance"), "schedule");
+    }
+
+    #[test]
+    fn main_profile_publishes_the_badge() {
+        let cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = ["fmt:check"]
+            main = ["test:unit"]
+
+            [ci.badge]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            ids(&profile_jobs(&cfg, "pr").unwrap()),
+            ["prep", "build", "verify"]
+        );
+        let main = profile_jobs(&cfg, "main").unwrap();
+        assert_eq!(ids(&main), ["prep", "build", "verify", "badge"]);
+        assert_eq!(main[3].kind, JobKind::Badge);
+        let workflow = crate::render_profile_workflow(&cfg, "main").unwrap();
+        assert!(workflow.contains("\n  badge:\n"));
+    }
+
+    #[test]
+    fn single_job_layout_has_one_check_job() {
+        let cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = ["fmt:check", "test:unit"]
+
+            [ci]
+            layout = "single-job"
+            "#,
+        )
+        .unwrap();
+        let jobs = profile_jobs(&cfg, "pr").unwrap();
+        assert_eq!(ids(&jobs), ["check"]);
+        assert_eq!(jobs[0].kind, JobKind::Check);
+        assert_eq!(jobs[0].entries.len(), 2);
+        let workflow = crate::render_profile_workflow(&cfg, "pr").unwrap();
+        assert!(workflow.contains("\n  check:\n"));
+    }
+
+    #[test]
+    fn container_settings_match_the_templates() {
+        let template = include_str!("../resources/ci-template.yml");
+        let verify = include_str!("../resources/verify-job.yml");
+        for (key, value) in CONTAINER_ENV {
+            assert!(template.contains(&format!("-e {key}={value} ")));
+            assert!(verify.contains(&format!("-e {key}=\"{value}\" ")));
+        }
+        for (key, value) in CHECK_ENV {
+            assert!(verify.contains(&format!("-e {key}=\"{value}\" ")));
+        }
+        let dirs = CACHE_DIRS.join(" ");
+        assert!(template.contains(&format!("mkdir -p {dirs}\n")));
+        assert!(verify.contains(&format!("mkdir -p {dirs}\n")));
+        assert!(template.contains(&format!("sh -c \"{BUILD_SCRIPT}\"")));
+        assert!(verify.contains(&format!("/bin/bash -c \"{BOOTSTRAP_SCRIPT}\"")));
+        assert!(template.contains(&format!("image={IMAGE_REPOSITORY}:${{fingerprint:0:12}}")));
+    }
+
+    #[test]
+    fn check_script_runs_commands_in_parallel() {
+        let entries = [
+            TargetEntry::from("fmt:check"),
+            TargetEntry::from("test:unit"),
+        ];
+        let script = check_script(&entries.iter().collect::<Vec<_>>());
+        assert!(script.starts_with("pids=(); dwf fmt:check & pids+=($!); dwf test:unit & "));
+        assert!(script.ends_with("exit $exit_code"));
+        assert_eq!(ci_image("0123456789abcdef"), "devflow-ci:0123456789ab");
+    }
+
+    #[test]
+    fn event_payloads_match_the_profile_triggers() {
+        let rev = Revision {
+            sha: "abc123".to_string(),
+            branch: "feature/x".to_string(),
+            base_sha: "def456".to_string(),
+            base_branch: "main".to_string(),
+            actor: "octocat".to_string(),
+        };
+        for profile in ["pr", "main", "release", "security", "nightly"] {
+            let event = profile_event(profile);
+            assert!(crate::profile_triggers(profile).starts_with(&format!("  {event}:")));
+            let payload = event_payload(profile, &rev).to_string();
+            let ctx = crate::GithubContext::from_payload(event, &payload).unwrap();
+            assert_eq!(ctx.actor.as_deref(), Some("octocat"));
+        }
+
+        let ctx = crate::GithubContext::from_payload(
+            "pull_request",
+            &event_payload("pr", &rev).to_string(),
+        )
+        .unwrap();
+        assert_eq!(ctx.head_sha.as_deref(), Some("abc123"));
+        assert_eq!(ctx.base_ref.as_deref(), Some("main"));
+        assert_eq!(
+            event_payload("release", &rev)["ref"],
+            "refs/tags/v0.0.0-local"
+        );
+        assert_eq!(event_payload("main", &rev)["ref"], "refs/heads/feature/x");
+    }
+}
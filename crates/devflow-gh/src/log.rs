@@ -0,0 +1,111 @@
+//! GitHub Actions workflow commands for log output.
+//!
+//! Collapsible groups (`::group::` / `::endgroup::`) and annotations
+//! (`::error::`, `::warning::`, `::notice::`) make per-command output
+//! navigable in the Actions UI. They only mean something when the runner
+//! reads the log, hence [`enabled`].
+
+use devflow_core::problem::Problem;
+
+/// Whether the process runs in GitHub Actions (`GITHUB_ACTIONS=true`).
+pub fn enabled() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+/// Opens a collapsible log group titled `title`.
+pub fn group(title: &str) -> String {
+    format!("::group::{}", escape_data(title))
+}
+
+/// Closes the current log group.
+pub fn end_group() -> &'static str {
+    "::endgroup::"
+}
+
+/// An error annotation with `title` shown above `message`.
+pub fn error(title: &str, message: &str) -> String {
+    format!(
+        "::error title={}::{}",
+        escape_property(title),
+        escape_data(message)
+    )
+}
+
+/// An annotation for a matched problem, attached to its file and line when
+/// known.
+pub fn problem(problem: &Problem) -> String {
+    let mut properties = Vec::new();
+    if let Some(file) = &problem.file {
+        properties.push(format!("file={}", escape_property(file)));
+    }
+    if let Some(line) = problem.line {
+        properties.push(format!("line={line}"));
+    }
+    if let Some(column) = problem.column {
+        properties.push(format!("col={column}"));
+    }
+    properties.push(format!("title={}", escape_property(&problem.owner)));
+    format!(
+        "::{} {}::{}",
+        problem.severity.as_str(),
+        properties.join(","),
+        escape_data(&problem.message)
+    )
+}
+
+/// Masks `value` in the rest of the job log.
+pub fn add_mask(value: &str) -> String {
+    format!("::add-mask::{}", escape_data(value))
+}
+
+/// Escapes a workflow command message.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a workflow command property value.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::problem::Severity;
+
+    #[test]
+    fn escapes_workflow_commands() {
+        assert_eq!(group("fmt:check"), "::group::fmt:check");
+        assert_eq!(end_group(), "::endgroup::");
+        assert_eq!(add_mask("a\nb"), "::add-mask::a%0Ab");
+        assert_eq!(
+            error("test:unit failed", "2 tests failed\n100% of runs, a, b"),
+            "::error title=test%3Aunit failed::2 tests failed%0A100%25 of runs, a, b"
+        );
+    }
+
+    #[test]
+    fn annotates_problems_at_their_location() {
+        let mut found = Problem {
+            owner: "rustc".to_string(),
+            severity: Severity::Warning,
+            message: "unused variable: `x`".to_string(),
+            file: Some("src/lib.rs".to_string()),
+            line: Some(4),
+            column: Some(9),
+        };
+        assert_eq!(
+            problem(&found),
+            "::warning file=src/lib.rs,line=4,col=9,title=rustc::unused variable: `x`"
+        );
+        found.file = None;
+        found.line = None;
+        found.column = None;
+        assert_eq!(
+            problem(&found),
+            "::warning title=rustc::unused variable: `x`"
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//! Validation of workflows against GitHub's workflow syntax.
+//!
+//! The bundled schema (`resources/github-workflow.schema.json`) is checked
+//! with the draft-07 keywords it uses: `type`, `enum`, `properties`,
+//! `patternProperties`, `additionalProperties`, `required`,
+//! `minProperties`, `items`, `minItems`, `anyOf`, `oneOf` and local `$ref`s,
+//! plus `errorMessage` for a readable message when no alternative matches.
+//! References a schema cannot express, such as `needs` naming an existing
+//! job, are checked on top.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::yaml;
+
+/// Issues in `workflow`: YAML syntax errors, schema violations and bad
+/// `needs` references, each prefixed with where it occurs.
+pub(crate) fn issues(workflow: &str) -> Vec<String> {
+    let document = match yaml::parse(workflow) {
+        Ok(document) => document,
+        Err(e) => return vec![format!("invalid YAML: {e}")],
+    };
+    let schema = schema();
+    let mut issues = Vec::new();
+    Validator { root: schema }.validate(&document, schema, "workflow", &mut issues);
+    issues.extend(needs_issues(&document));
+    issues
+}
+
+fn schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        serde_json::from_str(include_str!("../resources/github-workflow.schema.json"))
+            .expect("bundled workflow schema is valid JSON")
+    })
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    fn resolve(&self, schema: &'a Value) -> &'a Value {
+        let reference = schema.get("$ref").and_then(Value::as_str);
+        match reference.and_then(|r| r.strip_prefix("#/")) {
+            Some(pointer) => self
+                .root
+                .pointer(&format!("/{pointer}"))
+                .unwrap_or_else(|| panic!("unresolved $ref '{pointer}'")),
+            None => schema,
+        }
+    }
+
+    fn validate(&self, value: &Value, schema: &'a Value, path: &str, out: &mut Vec<String>) {
+        let schema = self.resolve(schema);
+        if let Some(types) = schema.get("type") {
+            let allowed: Vec<&str> = match types {
+                Value::String(one) => vec![one.as_str()],
+                Value::Array(many) => many.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.iter().any(|allowed| is_type(value, allowed)) {
+                out.push(format!(
+                    "{path}: expected {}, found {}",
+                    allowed.join(" or "),
+                    type_name(value)
+                ));
+                return;
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                out.push(format!(
+                    "{path}: {value} is not one of {}",
+                    options
+                        .iter()
+                        .map(Value::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+                return;
+            }
+        }
+        match value {
+            Value::Object(map) => self.validate_object(map, schema, path, out),
+            Value::Array(items) => {
+                let min = schema.get("minItems").and_then(Value::as_u64);
+                if min.is_some_and(|min| (items.len() as u64) < min) {
+                    out.push(format!("{path}: must not be empty"));
+                }
+                if let Some(item) = schema.get("items") {
+                    for (index, value) in items.iter().enumerate() {
+                        self.validate(value, item, &format!("{path}[{index}]"), out);
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.validate_alternatives(value, schema, path, out);
+    }
+
+    fn validate_object(
+        &self,
+        map: &serde_json::Map<String, Value>,
+        schema: &'a Value,
+        path: &str,
+        out: &mut Vec<String>,
+    ) {
+        let min = schema.get("minProperties").and_then(Value::as_u64);
+        if min.is_some_and(|min| (map.len() as u64) < min) {
+            out.push(format!("{path}: must not be empty"));
+        }
+        for key in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !map.contains_key(key) {
+                out.push(format!("{path}: missing required key '{key}'"));
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let patterns: Vec<(Regex, &Value)> = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(pattern, schema)| {
+                let regex = Regex::new(pattern).expect("schema patterns are valid");
+                (regex, schema)
+            })
+            .collect();
+        for (key, value) in map {
+            let child = format!("{path}.{key}");
+            let mut matched = false;
+            if let Some(property) = properties.and_then(|properties| properties.get(key)) {
+                self.validate(value, property, &child, out);
+                matched = true;
+            }
+            for (regex, property) in &patterns {
+                if regex.is_match(key) {
+                    self.validate(value, property, &child, out);
+                    matched = true;
+                }
+            }
+            if matched {
+                continue;
+            }
+            match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) if !patterns.is_empty() => out.push(format!(
+                    "{path}: '{key}' is not a valid name (expected {})",
+                    patterns
+                        .iter()
+                        .map(|(regex, _)| regex.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                )),
+                Some(Value::Bool(false)) => out.push(format!("{path}: unknown key '{key}'")),
+                Some(additional @ Value::Object(_)) => {
+                    self.validate(value, additional, &child, out)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// `anyOf` (one alternative must match) and `oneOf` (exactly one must).
+    fn validate_alternatives(
+        &self,
+        value: &Value,
+        schema: &'a Value,
+        path: &str,
+        out: &mut Vec<String>,
+    ) {
+        for (keyword, exactly_one) in [("anyOf", false), ("oneOf", true)] {
+            let Some(alternatives) = schema.get(keyword).and_then(Value::as_array) else {
+                continue;
+            };
+            let results: Vec<Vec<String>> = alternatives
+                .iter()
+                .map(|alternative| {
+                    let mut issues = Vec::new();
+                    self.validate(value, alternative, path, &mut issues);
+                    issues
+                })
+                .collect();
+            let matching = results.iter().filter(|issues| issues.is_empty()).count();
+            if matching == 1 || (matching > 1 && !exactly_one) {
+                continue;
+            }
+            match schema.get("errorMessage").and_then(Value::as_str) {
+                Some(message) => out.push(format!("{path}: {message}")),
+                None if matching > 1 => {
+                    out.push(format!("{path}: matches more than one allowed form"));
+                }
+                // The closest alternative explains the problem best.
+                None => out.extend(results.into_iter().min_by_key(Vec::len).unwrap_or_default()),
+            }
+        }
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// `needs` entries that name no job of the workflow, or the job itself.
+fn needs_issues(document: &Value) -> Vec<String> {
+    let Some(jobs) = document.get("jobs").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let mut issues = Vec::new();
+    for (job, definition) in jobs {
+        let needs: Vec<&str> = match definition.get("needs") {
+            Some(Value::String(one)) => vec![one.as_str()],
+            Some(Value::Array(many)) => many.iter().filter_map(Value::as_str).collect(),
+            _ => continue,
+        };
+        for need in needs {
+            if need == job {
+                issues.push(format!("workflow.jobs.{job}.needs: '{job}' needs itself"));
+            } else if !jobs.contains_key(need) {
+                issues.push(format!("workflow.jobs.{job}.needs: unknown job '{need}'"));
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = r#"name: ci
+on:
+  push:
+    branches: [main]
+  schedule:
+    - cron: '0 0 * * *'
+permissions:
+  contents: read
+jobs:
+  prep:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+  verify:
+    needs: [prep]
+    runs-on: ubuntu-latest
+    strategy:
+      fail-fast: false
+      matrix:
+        features: [a, b]
+    steps:
+      - name: Check
+        run: dwf check:pr
+  release:
+    needs: verify
+    uses: ./.github/workflows/release.yml
+    secrets: inherit
+"#;
+
+    #[test]
+    fn accepts_valid_workflows() {
+        assert_eq!(issues(VALID), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_structural_mistakes() {
+        let broken = VALID
+            .replace("  push:\n", "  pushed:\n")
+            .replace(
+                "      - name: Check\n",
+                "      - name: Check\n        uses: x/y@v1\n",
+            )
+            .replace(
+                "    runs-on: ubuntu-latest\n    steps:\n      - uses",
+                "    runs_on: ubuntu-latest\n    steps:\n      - uses",
+            )
+            .replace("needs: verify", "needs: [verify, deploy]")
+            .replace("  contents: read", "  contents: admin")
+            .replace("  verify:", "  verify all:");
+        assert_eq!(
+            issues(&broken),
+            [
+                "workflow.jobs.prep: unknown key 'runs_on'",
+                "workflow.jobs.prep: needs 'runs-on' and 'steps', or 'uses' for a reusable workflow",
+                "workflow.jobs: 'verify all' is not a valid name (expected ^[_a-zA-Z][a-zA-Z0-9_-]*$)",
+                "workflow.on: must be an event, a list of events, or a mapping of events to their filters",
+                "workflow.permissions: must be 'read-all', 'write-all' or a mapping of scopes to 'read', 'write' or 'none'",
+                "workflow.jobs.release.needs: unknown job 'verify'",
+                "workflow.jobs.release.needs: unknown job 'deploy'",
+            ]
+        );
+
+        let both = VALID.replace(
+            "      - name: Check\n",
+            "      - name: Check\n        uses: x/y@v1\n",
+        );
+        assert_eq!(
+            issues(&both),
+            ["workflow.jobs.verify.steps[0]: needs exactly one of 'uses' and 'run'"]
+        );
+        assert_eq!(
+            issues(
+                "on: push\njobs:\n  a:\n    runs-on: x\n    steps: []\n    timeout-minutes: [1]\n"
+            ),
+            [
+                "workflow.jobs.a.steps: must not be empty",
+                "workflow.jobs.a.timeout-minutes: expected number or string, found array",
+            ]
+        );
+        assert_eq!(
+            issues("on: push\njobs:\n  a:\n  b: 1\n    c: 2\n"),
+            ["invalid YAML: line 5: unexpected indentation"]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Extra steps of generated jobs (`[ci.jobs.<job>]`).
+//!
+//! `pre_steps` go right after the job's checkout, so they see the sources
+//! but run before any devflow step; `post_steps` go after the job's last
+//! step. Jobs the rendered workflow does not contain are left alone, so one
+//! config serves the combined and the per-profile workflows.
+
+use devflow_core::config::CiStep;
+use devflow_core::DevflowConfig;
+
+/// Indentation of a step's `- ` in generated jobs.
+const STEP_INDENT: &str = "      ";
+
+/// `workflow` with the `[ci.jobs]` steps added to their jobs.
+pub(crate) fn insert(cfg: &DevflowConfig, workflow: &str) -> String {
+    let mut lines: Vec<String> = workflow.split_inclusive('\n').map(String::from).collect();
+    for (job, extra) in &cfg.ci.jobs {
+        let Some((steps, end)) = steps_range(&lines, job) else {
+            continue;
+        };
+        let items: Vec<usize> = (steps + 1..end)
+            .filter(|&index| lines[index].starts_with(&format!("{STEP_INDENT}- ")))
+            .collect();
+        let checkout = items.first().is_some_and(|&first| {
+            lines[first].starts_with(&format!("{STEP_INDENT}- uses: actions/checkout@"))
+        });
+        let pre_at = if checkout {
+            items.get(1).copied().unwrap_or(end)
+        } else {
+            steps + 1
+        };
+
+        if !extra.post_steps.is_empty() {
+            let block = render_steps(&extra.post_steps);
+            lines.insert(end, format!("\n{block}"));
+        }
+        if !extra.pre_steps.is_empty() {
+            let mut block = render_steps(&extra.pre_steps);
+            if pre_at > steps + 1 && !lines[pre_at - 1].trim().is_empty() {
+                block.insert(0, '\n');
+            }
+            if pre_at < end {
+                block.push('\n');
+            }
+            lines.insert(pre_at, block);
+        }
+    }
+    lines.concat()
+}
+
+/// The index of `job`'s `steps:` line and the end of its last step
+/// (before trailing blank lines and the footer comment).
+fn steps_range(lines: &[String], job: &str) -> Option<(usize, usize)> {
+    let header = format!("  {job}:\n");
+    let start = lines.iter().position(|line| *line == header)?;
+    let mut end = lines[start + 1..]
+        .iter()
+        .position(|line| !line.starts_with("    ") && !line.trim().is_empty())
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    while end > start + 1 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    let steps = (start + 1..end).find(|&index| lines[index] == "    steps:\n")?;
+    Some((steps, end))
+}
+
+/// `steps` as workflow YAML, separated by blank lines like the templates'.
+fn render_steps(steps: &[CiStep]) -> String {
+    steps.iter().map(render_step).collect::<Vec<_>>().join("\n")
+}
+
+fn render_step(step: &CiStep) -> String {
+    let string = |key: &str, value: &Option<String>| {
+        value
+            .as_ref()
+            .map(|value| format!("{key}: {}", scalar(value)))
+    };
+    let mut keys: Vec<String> = [
+        string("name", &step.name),
+        string("id", &step.id),
+        string("if", &step.condition),
+        string("uses", &step.uses),
+        step.run.as_deref().map(render_run),
+        string("shell", &step.shell),
+        string("working-directory", &step.working_directory),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    for (key, map) in [("with", &step.with), ("env", &step.env)] {
+        if !map.is_empty() {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(name, value)| format!("  {}: {}", scalar(name), scalar(value)))
+                .collect();
+            keys.push(format!("{key}:\n{}", entries.join("\n")));
+        }
+    }
+    if let Some(continue_on_error) = step.continue_on_error {
+        keys.push(format!("continue-on-error: {continue_on_error}"));
+    }
+    if let Some(timeout) = step.timeout_minutes {
+        keys.push(format!("timeout-minutes: {timeout}"));
+    }
+
+    let mut out = String::new();
+    for (index, key) in keys.iter().enumerate() {
+        let prefix = if index == 0 { "- " } else { "  " };
+        for (line_index, line) in key.lines().enumerate() {
+            let prefix = if line_index == 0 { prefix } else { "  " };
+            if line.is_empty() {
+                out.push('\n');
+            } else {
+                out.push_str(&format!("{STEP_INDENT}{prefix}{line}\n"));
+            }
+        }
+    }
+    out
+}
+
+/// `run:` as a literal block for multi-line scripts.
+fn render_run(run: &str) -> String {
+    let script = run.trim_end_matches('\n');
+    if !script.contains('\n') || script.starts_with(char::is_whitespace) {
+        return format!("run: {}", scalar(script));
+    }
+    let body: Vec<String> = script
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("  {line}")
+            }
+        })
+        .collect();
+    format!("run: |\n{}", body.join("\n"))
+}
+
+/// `value` as a YAML string: plain when it reads back as the same string,
+/// double-quoted otherwise.
+fn scalar(value: &str) -> String {
+    let lower = value.to_ascii_lowercase();
+    let plain = !value.is_empty()
+        && !value.starts_with(|c: char| {
+            c.is_whitespace() || c.is_ascii_digit() || "-?:,[]{}#&*!|>'\"%@`~".contains(c)
+        })
+        && !value.ends_with(|c: char| c.is_whitespace() || c == ':')
+        && !value.contains(": ")
+        && !value.contains(" #")
+        && !value.contains(['\n', '\t', '\r'])
+        && value.parse::<f64>().is_err()
+        && !matches!(
+            lower.as_str(),
+            "true" | "false" | "null" | "yes" | "no" | "on" | "off" | "inf" | "nan"
+        );
+    if plain {
+        value.to_string()
+    } else {
+        serde_json::to_string(value).expect("strings serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOB: &str = "jobs:\n  verify:\n    runs-on: x\n    steps:\n      - uses: actions/checkout@v4\n        with:\n          fetch-depth: 0\n\n      - name: Check\n        run: dwf check:pr\n\n  badge:\n    runs-on: x\n    steps:\n      - run: dwf badge\n";
+
+    fn config(jobs: &str) -> DevflowConfig {
+        toml::from_str(&format!(
+            "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[targets]\npr = [\"fmt:check\"]\n\n{jobs}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn inserts_steps_after_checkout_and_at_the_end() {
+        let cfg = config(
+            r#"[ci.jobs.verify]
+pre_steps = [{ uses = "actions/setup-java@v4", with = { java-version = "21", distribution = "temurin" } }]
+post_steps = [{ name = "Report: totals", run = "./report.sh\nmake summary\n", if = "always()", continue-on-error = true }]
+
+[ci.jobs.badge]
+pre_steps = [{ run = "true" }]
+
+[ci.jobs.attest]
+post_steps = [{ run = "true" }]
+"#,
+        );
+        assert_eq!(
+            insert(&cfg, JOB),
+            r#"jobs:
+  verify:
+    runs-on: x
+    steps:
+      - uses: actions/checkout@v4
+        with:
+          fetch-depth: 0
+
+      - uses: actions/setup-java@v4
+        with:
+          distribution: temurin
+          java-version: "21"
+
+      - name: Check
+        run: dwf check:pr
+
+      - name: "Report: totals"
+        if: always()
+        run: |
+          ./report.sh
+          make summary
+        continue-on-error: true
+
+  badge:
+    runs-on: x
+    steps:
+      - run: "true"
+
+      - run: dwf badge
+"#
+        );
+    }
+
+    #[test]
+    fn quotes_scalars_that_would_change_meaning() {
+        for (value, rendered) in [
+            ("actions/cache@v4", "actions/cache@v4"),
+            ("${{ matrix.os }}", "${{ matrix.os }}"),
+            ("./scripts/lint.sh", "./scripts/lint.sh"),
+            ("21", "\"21\""),
+            ("+1", "\"+1\""),
+            (".5", "\".5\""),
+            ("off", "\"off\""),
+            ("- item", "\"- item\""),
+            ("a: b", "\"a: b\""),
+            ("x # y", "\"x # y\""),
+            ("", "\"\""),
+        ] {
+            assert_eq!(scalar(value), rendered, "{value}");
+        }
+        assert_eq!(
+            render_run("  indented\nscript"),
+            "run: \"  indented\\nscript\""
+        );
+    }
+}
@@ -0,0 +1,534 @@
+//! A reader for the YAML that workflows are written in, as JSON values.
+//!
+//! Covers block mappings and sequences, literal and folded block scalars,
+//! flow sequences and mappings of scalars, quoted scalars and comments:
+//! what generated and hand-edited workflows use. Anchors, aliases, tags and
+//! multi-line plain scalars are reported as unsupported rather than misread.
+
+use serde_json::{Map, Number, Value};
+
+/// A line with content, its comment stripped.
+#[derive(Debug, Clone)]
+struct Line<'a> {
+    /// 1-based line number in the document.
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+/// A YAML syntax error at a 1-based line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parses `text` as one YAML document.
+///
+/// # Errors
+/// Returns the first syntax error, or a construct this reader does not
+/// support.
+pub fn parse(text: &str) -> Result<Value, Error> {
+    let raw: Vec<&str> = text.lines().collect();
+    let mut parser = Parser {
+        raw: &raw,
+        lines: Vec::new(),
+        pos: 0,
+    };
+    for (index, line) in raw.iter().enumerate() {
+        let content = strip_comment(line);
+        let trimmed = content.trim_start();
+        if trimmed.trim_end().is_empty() || trimmed == "---" {
+            continue;
+        }
+        if line.starts_with('\t') {
+            return Err(error(index + 1, "tabs are not allowed for indentation"));
+        }
+        parser.lines.push(Line {
+            number: index + 1,
+            indent: content.len() - trimmed.len(),
+            text: trimmed.trim_end(),
+        });
+    }
+    let Some(first) = parser.lines.first() else {
+        return Ok(Value::Null);
+    };
+    let indent = first.indent;
+    let value = parser.node(indent)?;
+    if let Some(line) = parser.lines.get(parser.pos) {
+        return Err(error(line.number, "unexpected indentation"));
+    }
+    Ok(value)
+}
+
+fn error(line: usize, message: impl Into<String>) -> Error {
+    Error {
+        line,
+        message: message.into(),
+    }
+}
+
+struct Parser<'a> {
+    raw: &'a [&'a str],
+    lines: Vec<Line<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Line<'a>> {
+        self.lines.get(self.pos)
+    }
+
+    /// The block node starting at the current line, indented by `indent`.
+    fn node(&mut self, indent: usize) -> Result<Value, Error> {
+        match self.peek() {
+            Some(line) if is_item(line.text) => self.sequence(indent),
+            Some(_) => self.mapping(indent),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value, Error> {
+        let mut map = Map::new();
+        while let Some(line) = self.peek().cloned() {
+            if line.indent < indent || (line.indent == indent && is_item(line.text)) {
+                break;
+            }
+            if line.indent > indent {
+                return Err(error(line.number, "unexpected indentation"));
+            }
+            let Some((key, rest)) = split_key(line.text) else {
+                return Err(error(
+                    line.number,
+                    format!("expected 'key: value', found '{}'", line.text),
+                ));
+            };
+            let key = scalar_key(key, line.number)?;
+            self.pos += 1;
+            let value = self.value(rest, indent, line.number)?;
+            if map.insert(key.clone(), value).is_some() {
+                return Err(error(line.number, format!("duplicate key '{key}'")));
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value, Error> {
+        let mut items = Vec::new();
+        while let Some(line) = self.peek().cloned() {
+            if line.indent < indent {
+                break;
+            }
+            if line.indent > indent || !is_item(line.text) {
+                return Err(error(line.number, "unexpected indentation"));
+            }
+            let rest = line.text[1..].trim_start();
+            let offset = line.text.len() - rest.len();
+            if rest.is_empty() {
+                self.pos += 1;
+                items.push(self.nested(indent)?);
+            } else if split_key(rest).is_some() || is_item(rest) {
+                // `- key: value` starts a mapping (or `- - x` a sequence)
+                // at the column after the dash.
+                self.lines[self.pos] = Line {
+                    indent: indent + offset,
+                    text: rest,
+                    ..line
+                };
+                items.push(self.node(indent + offset)?);
+            } else {
+                self.pos += 1;
+                items.push(self.value(rest, indent, line.number)?);
+            }
+        }
+        Ok(Value::Array(items))
+    }
+
+    /// The value after `key:` or `- ` of a node indented by `indent`.
+    fn value(&mut self, rest: &str, indent: usize, number: usize) -> Result<Value, Error> {
+        if rest.is_empty() {
+            return match self.peek() {
+                Some(next)
+                    if next.indent > indent || (next.indent == indent && is_item(next.text)) =>
+                {
+                    let child = next.indent;
+                    self.node(child)
+                }
+                _ => Ok(Value::Null),
+            };
+        }
+        if let Some(header) = rest.strip_prefix(['|', '>']) {
+            return self.block_scalar(rest.starts_with('>'), header, indent, number);
+        }
+        if let Some(next) = self.peek() {
+            if next.indent > indent && !is_item(next.text) && split_key(next.text).is_none() {
+                return Err(error(
+                    next.number,
+                    "multi-line plain scalars are not supported; use a block scalar (|)",
+                ));
+            }
+        }
+        scalar(rest, number)
+    }
+
+    /// A nested node after a bare `-`, or null.
+    fn nested(&mut self, indent: usize) -> Result<Value, Error> {
+        match self.peek() {
+            Some(next) if next.indent > indent => {
+                let child = next.indent;
+                self.node(child)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    /// A `|` or `>` scalar whose lines follow `number`, more indented than
+    /// `indent`; they are read raw, comments and blank lines included.
+    fn block_scalar(
+        &mut self,
+        folded: bool,
+        header: &str,
+        indent: usize,
+        number: usize,
+    ) -> Result<Value, Error> {
+        let chomping = match header.trim() {
+            "" => None,
+            "-" => Some(false),
+            "+" => Some(true),
+            other => {
+                return Err(error(
+                    number,
+                    format!("unsupported block scalar header '{other}'"),
+                ))
+            }
+        };
+        let mut end = number;
+        let mut body = Vec::new();
+        let mut block_indent = None;
+        for raw in &self.raw[number..] {
+            let width = raw.len() - raw.trim_start().len();
+            if raw.trim().is_empty() {
+                body.push("");
+                end += 1;
+                continue;
+            }
+            if width <= indent {
+                break;
+            }
+            let block = *block_indent.get_or_insert(width);
+            if width < block {
+                return Err(error(end + 1, "block scalar line is less indented"));
+            }
+            body.push(&raw[block..]);
+            end += 1;
+        }
+        while self.peek().is_some_and(|line| line.number <= end) {
+            self.pos += 1;
+        }
+        let trailing = body.iter().rev().take_while(|line| line.is_empty()).count();
+        let content = &body[..body.len() - trailing];
+        let mut text = if folded {
+            fold(content)
+        } else {
+            content.join("\n")
+        };
+        match chomping {
+            Some(false) => {}
+            None if !content.is_empty() => text.push('\n'),
+            None => {}
+            Some(true) => text.push_str(&"\n".repeat(trailing + 1)),
+        }
+        Ok(Value::String(text))
+    }
+}
+
+/// Joins the lines of a folded scalar: single line breaks become spaces.
+fn fold(lines: &[&str]) -> String {
+    let mut out = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            out.push(if line.is_empty() || lines[index - 1].is_empty() {
+                '\n'
+            } else {
+                ' '
+            });
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// `line` without a trailing `# comment` outside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if previous.is_whitespace() => return &line[..index],
+            (None, '\'' | '"') if previous.is_whitespace() || "[{,:".contains(previous) => {
+                quote = Some(c)
+            }
+            (Some(open), _) if c == open => quote = None,
+            _ => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+/// Splits `key: value` at the first `: ` (or a trailing `:`) outside quotes
+/// and brackets.
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    let mut depth = 0usize;
+    let bytes = text.as_bytes();
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') if index == 0 => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth = depth.saturating_sub(1),
+            (None, ':') if depth == 0 => {
+                let next = bytes.get(index + 1);
+                if next.is_none() || next == Some(&b' ') {
+                    let key = text[..index].trim_end();
+                    if key.is_empty() || key.starts_with(['[', '{']) {
+                        return None;
+                    }
+                    return Some((key, text[index + 1..].trim()));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn scalar_key(key: &str, number: usize) -> Result<String, Error> {
+    match scalar(key, number)? {
+        Value::String(key) => Ok(key),
+        value => Ok(value.to_string()),
+    }
+}
+
+/// A flow sequence, flow mapping, quoted or plain scalar.
+fn scalar(text: &str, number: usize) -> Result<Value, Error> {
+    let text = text.trim();
+    if text.starts_with(['&', '*', '!']) {
+        return Err(error(
+            number,
+            format!("anchors, aliases and tags are not supported: '{text}'"),
+        ));
+    }
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or_else(|| error(number, format!("unclosed flow sequence '{text}'")))?;
+        return split_flow(inner, number)?
+            .into_iter()
+            .map(|item| scalar(item, number))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array);
+    }
+    if let Some(inner) = text.strip_prefix('{') {
+        let inner = inner
+            .strip_suffix('}')
+            .ok_or_else(|| error(number, format!("unclosed flow mapping '{text}'")))?;
+        let mut map = Map::new();
+        for entry in split_flow(inner, number)? {
+            let (key, value) = split_key(entry)
+                .ok_or_else(|| error(number, format!("expected 'key: value', found '{entry}'")))?;
+            map.insert(scalar_key(key, number)?, scalar(value, number)?);
+        }
+        return Ok(Value::Object(map));
+    }
+    if let Some(inner) = text.strip_prefix('\'') {
+        let inner = inner
+            .strip_suffix('\'')
+            .ok_or_else(|| error(number, format!("unclosed quote in '{text}'")))?;
+        return Ok(Value::String(inner.replace("''", "'")));
+    }
+    if let Some(inner) = text.strip_prefix('"') {
+        let inner = inner
+            .strip_suffix('"')
+            .ok_or_else(|| error(number, format!("unclosed quote in '{text}'")))?;
+        return unescape(inner).map_err(|message| error(number, message));
+    }
+    Ok(plain(text))
+}
+
+/// Splits the inside of a flow collection at top-level commas.
+fn split_flow(inner: &str, number: usize) -> Result<Vec<&str>, Error> {
+    let mut items = Vec::new();
+    let (mut quote, mut depth, mut start) = (None, 0usize, 0);
+    // Quotes only open a scalar at its start, so `it's` stays plain.
+    let mut previous = ',';
+    for (index, c) in inner.char_indices() {
+        let opens = ",:[{".contains(previous);
+        if !c.is_whitespace() {
+            previous = c;
+        }
+        match (quote, c) {
+            (None, '\'' | '"') if opens => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth = depth.saturating_sub(1),
+            (None, ',') if depth == 0 => {
+                items.push(inner[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() {
+        return Err(error(number, format!("unclosed quote in '{inner}'")));
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() {
+        items.push(last);
+    } else if !items.is_empty() {
+        return Err(error(number, "empty entry in flow collection"));
+    }
+    Ok(items)
+}
+
+fn unescape(inner: &str) -> Result<Value, String> {
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid escape '\\u{hex}'"))?;
+                out.push(c);
+            }
+            Some(c @ ('"' | '\\' | '/')) => out.push(c),
+            Some(c) => return Err(format!("unsupported escape '\\{c}'")),
+            None => return Err("trailing backslash in quoted scalar".to_string()),
+        }
+    }
+    Ok(Value::String(out))
+}
+
+/// A plain scalar: null, a boolean, a number or a string (YAML 1.2 core
+/// schema, so `on` stays a string).
+fn plain(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(int) = text.parse::<i64>() {
+        return Value::Number(int.into());
+    }
+    let numeric = text.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.');
+    if let Some(number) = text
+        .parse::<f64>()
+        .ok()
+        .filter(|_| numeric)
+        .and_then(Number::from_f64)
+    {
+        return Value::Number(number);
+    }
+    Value::String(text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_workflow_yaml() {
+        let text = r##"name: ci # the workflow
+
+on:
+  push:
+    branches: [main, 'rel/*']
+  workflow_dispatch:
+
+jobs:
+  verify:
+    name: "Verify: all"
+    needs: [prep]
+    timeout-minutes: 30
+    strategy:
+      fail-fast: false
+    steps:
+      - uses: actions/checkout@v4
+        with:
+          fetch-depth: 0
+      - name: Run
+        env: { A: "1", B: it's }
+        run: |
+          echo "# not a comment"
+
+          exit 0
+      - run: >-
+          one
+          two
+"##;
+        assert_eq!(
+            parse(text).unwrap(),
+            json!({
+                "name": "ci",
+                "on": {"push": {"branches": ["main", "rel/*"]}, "workflow_dispatch": null},
+                "jobs": {"verify": {
+                    "name": "Verify: all",
+                    "needs": ["prep"],
+                    "timeout-minutes": 30,
+                    "strategy": {"fail-fast": false},
+                    "steps": [
+                        {"uses": "actions/checkout@v4", "with": {"fetch-depth": 0}},
+                        {
+                            "name": "Run",
+                            "env": {"A": "1", "B": "it's"},
+                            "run": "echo \"# not a comment\"\n\nexit 0\n"
+                        },
+                        {"run": "one two"}
+                    ]
+                }}
+            })
+        );
+    }
+
+    #[test]
+    fn reports_errors_by_line() {
+        let err = |text: &str| parse(text).unwrap_err().to_string();
+        assert_eq!(err("jobs:\n  a: 1\n  a: 2\n"), "line 3: duplicate key 'a'");
+        assert_eq!(
+            err("jobs:\n  a: 1\n    b: 2\n"),
+            "line 3: unexpected indentation"
+        );
+        assert_eq!(
+            err("jobs:\n  just text\n"),
+            "line 2: expected 'key: value', found 'just text'"
+        );
+        assert!(err("on: [push\n").contains("unclosed flow sequence"));
+        assert!(err("a: &x 1\n").contains("anchors"));
+        assert!(err("if: a &&\n  b\n").contains("multi-line plain scalars"));
+    }
+}
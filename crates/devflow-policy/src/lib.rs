@@ -1,22 +1,80 @@
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 
-use devflow_core::{CommandRef, DevflowConfig};
+use devflow_core::{config::parse_duration, CommandRef, DevflowConfig};
 use tracing::{debug, instrument};
 
+/// A resolved profile command together with its execution filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyCommand {
+    /// The command to run.
+    pub command: CommandRef,
+    /// Path globs restricting when the command runs (empty means always).
+    pub paths: Vec<String>,
+    /// Time limit per stack.
+    pub timeout: Option<Duration>,
+    /// Retries after a failure.
+    pub retries: u32,
+    /// Stacks the command is restricted to (empty means all).
+    pub stacks: Vec<String>,
+}
+
+impl PolicyCommand {
+    /// Returns true if the command should run for the given set of changed files.
+    pub fn matches_changes(&self, changed: &[String]) -> bool {
+        self.paths.is_empty()
+            || changed
+                .iter()
+                .any(|file| devflow_core::glob::matches_any(&self.paths, file))
+    }
+}
+
+/// The error for a profile `[targets]` does not define, naming those it does.
+fn unknown_profile(cfg: &DevflowConfig, selector: &str) -> anyhow::Error {
+    if cfg.targets.profiles.is_empty() {
+        return anyhow!(
+            "unknown check profile '{selector}' (no [targets] profiles are configured)"
+        );
+    }
+    let available: Vec<&str> = cfg.targets.profiles.keys().map(String::as_str).collect();
+    anyhow!(
+        "unknown check profile '{selector}' (available: {}; see 'dwf check --list')",
+        available.join(", ")
+    )
+}
+
 #[instrument(skip(cfg))]
 pub fn resolve_policy_commands(cfg: &DevflowConfig, selector: &str) -> Result<Vec<CommandRef>> {
+    Ok(resolve_policy_entries(cfg, selector)?
+        .into_iter()
+        .map(|entry| entry.command)
+        .collect())
+}
+
+/// Resolves a profile into its commands, keeping per-command options such as
+/// path filters, timeouts, retries and stacks.
+#[instrument(skip(cfg))]
+pub fn resolve_policy_entries(cfg: &DevflowConfig, selector: &str) -> Result<Vec<PolicyCommand>> {
     debug!("resolving commands for selector: {}", selector);
     let entries = cfg
         .targets
         .profiles
         .get(selector)
-        .ok_or_else(|| anyhow!("unknown check profile '{selector}'"))?;
+        .ok_or_else(|| unknown_profile(cfg, selector))?;
 
     entries
         .iter()
-        .map(|item| CommandRef::from_str(item).map_err(|e| anyhow!(e)))
+        .map(|entry| {
+            Ok(PolicyCommand {
+                command: CommandRef::from_str(entry.command()).map_err(|e| anyhow!(e))?,
+                paths: entry.paths().to_vec(),
+                timeout: entry.timeout().map(parse_duration).transpose()?,
+                retries: entry.retries(),
+                stacks: entry.stacks().to_vec(),
+            })
+        })
         .collect()
 }
 
@@ -49,4 +107,61 @@ mod tests {
         let values = out.iter().map(|c| c.canonical()).collect::<Vec<_>>();
         assert_eq!(values, vec!["fmt:check", "test:unit"]);
     }
+
+    #[test]
+    fn unknown_profiles_name_the_available_ones() {
+        let cfg = fixture();
+        let err = resolve_policy_entries(&cfg, "nightly").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown check profile 'nightly' (available: pr, main, release; see 'dwf check --list')"
+        );
+    }
+
+    #[test]
+    fn path_filtered_entries_match_changed_files() {
+        let cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = ["fmt:check", { cmd = "test:integration", paths = ["migrations/**", "src/db/**"] }]
+            "#,
+        )
+        .expect("fixture config should parse");
+
+        let out = resolve_policy_entries(&cfg, "pr").expect("pr profile should resolve");
+        assert_eq!(out[1].command.canonical(), "test:integration");
+
+        let docs_only = vec!["README.md".to_string()];
+        assert!(out[0].matches_changes(&docs_only));
+        assert!(!out[1].matches_changes(&docs_only));
+
+        let db_change = vec!["src/db/pool.rs".to_string()];
+        assert!(out[1].matches_changes(&db_change));
+    }
+
+    #[test]
+    fn entries_carry_execution_options() {
+        let cfg: DevflowConfig = toml::from_str(
+            r#"
+            [project]
+            name = "demo"
+            stack = ["rust"]
+
+            [targets]
+            pr = [{ cmd = "test:unit", timeout = "5m", retries = 2, stacks = ["rust"] }, "fmt:check"]
+            "#,
+        )
+        .expect("fixture config should parse");
+
+        let out = resolve_policy_entries(&cfg, "pr").expect("pr profile should resolve");
+        assert_eq!(out[0].timeout, Some(Duration::from_secs(300)));
+        assert_eq!(out[0].retries, 2);
+        assert_eq!(out[0].stacks, ["rust"]);
+        assert_eq!((out[1].timeout, out[1].retries), (None, 0));
+        assert!(out[1].stacks.is_empty());
+    }
 }
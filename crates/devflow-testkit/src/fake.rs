@@ -0,0 +1,279 @@
+//! In-process fake extensions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+
+use devflow_core::tool::ToolRequirement;
+use devflow_core::{ActionContext, CommandRef, ExecutionAction, Extension, TargetOs};
+
+/// One `build_action` request a [`FakeExtension`] received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    /// The canonical command.
+    pub command: String,
+    /// Where the action was to run.
+    pub os: TargetOs,
+}
+
+/// The requests a [`FakeExtension`] received, shared with the test after the
+/// extension moved into a registry.
+#[derive(Debug, Clone, Default)]
+pub struct Calls(Arc<Mutex<Vec<Call>>>);
+
+impl Calls {
+    /// Every request, in order.
+    pub fn all(&self) -> Vec<Call> {
+        self.0.lock().map(|calls| calls.clone()).unwrap_or_default()
+    }
+
+    /// The canonical commands requested, in order.
+    pub fn commands(&self) -> Vec<String> {
+        self.all().into_iter().map(|call| call.command).collect()
+    }
+
+    fn push(&self, call: Call) {
+        if let Ok(mut calls) = self.0.lock() {
+            calls.push(call);
+        }
+    }
+}
+
+/// An extension whose actions are given up front.
+///
+/// Actions are keyed by canonical command (`test:unit`) or primary command
+/// (`test`, for every selector without its own action), optionally per OS.
+/// Commands without an action are declined, like an extension that does not
+/// handle them; every key is also a capability.
+#[derive(Debug, Clone)]
+pub struct FakeExtension {
+    name: String,
+    capabilities: HashSet<String>,
+    /// Actions keyed by command and [`TargetOs::as_str`], if OS-specific.
+    actions: BTreeMap<(String, Option<&'static str>), ExecutionAction>,
+    failures: BTreeMap<String, String>,
+    env: HashMap<String, String>,
+    fingerprint_inputs: Vec<String>,
+    cache_mounts: Vec<String>,
+    tools: Vec<ToolRequirement>,
+    trusted: bool,
+    calls: Calls,
+}
+
+impl FakeExtension {
+    /// A fake named `name` that handles nothing yet.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            capabilities: HashSet::new(),
+            actions: BTreeMap::new(),
+            failures: BTreeMap::new(),
+            env: HashMap::new(),
+            fingerprint_inputs: Vec::new(),
+            cache_mounts: Vec::new(),
+            tools: Vec::new(),
+            trusted: false,
+            calls: Calls::default(),
+        }
+    }
+
+    /// Advertises `capability` without an action for it.
+    pub fn capability(mut self, capability: &str) -> Self {
+        self.capabilities.insert(capability.to_string());
+        self
+    }
+
+    /// Runs `program` with `args` for `command` on every OS.
+    pub fn action(self, command: &str, program: &str, args: &[&str]) -> Self {
+        self.insert_action(command, None, program, args)
+    }
+
+    /// Runs `program` with `args` for `command` on `os`, over
+    /// [`FakeExtension::action`].
+    pub fn os_action(self, command: &str, os: TargetOs, program: &str, args: &[&str]) -> Self {
+        self.insert_action(command, Some(os), program, args)
+    }
+
+    /// Fails to build an action for `command` with `message`.
+    pub fn failing(mut self, command: &str, message: &str) -> Self {
+        self.capabilities.insert(command.to_string());
+        self.failures
+            .insert(command.to_string(), message.to_string());
+        self
+    }
+
+    /// Sets `key` in the extension environment.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Declares a fingerprint input file or glob.
+    pub fn fingerprint_input(mut self, input: &str) -> Self {
+        self.fingerprint_inputs.push(input.to_string());
+        self
+    }
+
+    /// Declares a `host_dir:container_dir` cache mount.
+    pub fn cache_mount(mut self, mount: &str) -> Self {
+        self.cache_mounts.push(mount.to_string());
+        self
+    }
+
+    /// Declares a pinned tool.
+    pub fn tool(mut self, tool: ToolRequirement) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Marks the extension as trusted to run on the host.
+    pub fn trusted(mut self) -> Self {
+        self.trusted = true;
+        self
+    }
+
+    /// A handle on the requests this extension receives.
+    pub fn calls(&self) -> Calls {
+        self.calls.clone()
+    }
+
+    fn insert_action(
+        mut self,
+        command: &str,
+        os: Option<TargetOs>,
+        program: &str,
+        args: &[&str],
+    ) -> Self {
+        self.capabilities.insert(command.to_string());
+        self.actions.insert(
+            (command.to_string(), os.map(TargetOs::as_str)),
+            ExecutionAction {
+                program: program.to_string(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                env: HashMap::new(),
+            },
+        );
+        self
+    }
+}
+
+impl Extension for FakeExtension {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> HashSet<String> {
+        self.capabilities.clone()
+    }
+
+    fn build_action(&self, cmd: &CommandRef) -> Result<Option<ExecutionAction>> {
+        self.build_action_for(cmd, &ActionContext::host())
+    }
+
+    fn build_action_for(
+        &self,
+        cmd: &CommandRef,
+        ctx: &ActionContext,
+    ) -> Result<Option<ExecutionAction>> {
+        let canonical = cmd.canonical();
+        self.calls.push(Call {
+            command: canonical.clone(),
+            os: ctx.os,
+        });
+        let keys = [canonical, cmd.primary.as_str().to_string()];
+        if let Some(message) = keys.iter().find_map(|key| self.failures.get(key)) {
+            bail!("{}", message);
+        }
+        let action = keys.iter().find_map(|key| {
+            self.actions
+                .get(&(key.clone(), Some(ctx.os.as_str())))
+                .or_else(|| self.actions.get(&(key.clone(), None)))
+        });
+        Ok(action.cloned())
+    }
+
+    fn is_trusted(&self) -> bool {
+        self.trusted
+    }
+
+    fn cache_mounts(&self) -> Vec<String> {
+        self.cache_mounts.clone()
+    }
+
+    fn env_vars(&self) -> HashMap<String, String> {
+        self.env.clone()
+    }
+
+    fn fingerprint_inputs(&self) -> Vec<String> {
+        self.fingerprint_inputs.clone()
+    }
+
+    fn tools(&self) -> Vec<ToolRequirement> {
+        self.tools.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn maps_commands_and_records_requests() {
+        let fake = FakeExtension::new("fake")
+            .action("test", "echo", &["any test"])
+            .action("test:unit", "echo", &["unit"])
+            .os_action("test:unit", TargetOs::Windows, "cmd", &["/c", "unit"])
+            .failing("build:release", "no release toolchain")
+            .env("FAKE_HOME", "/fake");
+        let calls = fake.calls();
+        let registry = crate::registry([fake]);
+        let unit = CommandRef::from_str("test:unit").unwrap();
+
+        let action = registry
+            .build_action("fake", &unit, &ActionContext::host())
+            .unwrap()
+            .unwrap();
+        assert_eq!(action.program, "echo");
+        assert_eq!(action.env["FAKE_HOME"], "/fake");
+        let windows = ActionContext {
+            os: TargetOs::Windows,
+        };
+        let action = registry
+            .build_action("fake", &unit, &windows)
+            .unwrap()
+            .unwrap();
+        assert_eq!(action.args, ["/c", "unit"]);
+        let smoke = CommandRef::from_str("test:smoke").unwrap();
+        let action = registry
+            .build_action("fake", &smoke, &windows)
+            .unwrap()
+            .unwrap();
+        assert_eq!(action.args, ["any test"]);
+
+        let lint = CommandRef::from_str("lint:static").unwrap();
+        assert!(registry
+            .build_action("fake", &lint, &windows)
+            .unwrap()
+            .is_none());
+        assert!(registry.ensure_can_run(&lint).is_err());
+        let release = CommandRef::from_str("build:release").unwrap();
+        let err = registry
+            .build_action("fake", &release, &windows)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no release toolchain");
+
+        assert_eq!(
+            calls.commands(),
+            [
+                "test:unit",
+                "test:unit",
+                "test:smoke",
+                "lint:static",
+                "build:release"
+            ]
+        );
+        assert_eq!(calls.all()[1].os, TargetOs::Windows);
+    }
+}
@@ -0,0 +1,116 @@
+//! Golden-file assertions.
+//!
+//! A golden file holds the expected output of a renderer. When the output
+//! changes on purpose, rerun the tests with `DEVFLOW_UPDATE_GOLDEN=1` to
+//! rewrite the files, and review the diff. Relative paths resolve against the
+//! working directory, which `cargo test` sets to the crate root.
+
+use std::fs;
+use std::path::Path;
+
+use devflow_core::DevflowConfig;
+
+/// Set (to anything but empty) to rewrite golden files instead of comparing.
+pub const UPDATE_ENV: &str = "DEVFLOW_UPDATE_GOLDEN";
+
+/// Asserts that `actual` equals the contents of the golden file at `path`.
+///
+/// # Panics
+/// Panics with the first differing line if they differ, or if the file is
+/// missing, unless [`UPDATE_ENV`] is set.
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let update = std::env::var_os(UPDATE_ENV).is_some_and(|value| !value.is_empty());
+    if let Err(message) = check(path.as_ref(), actual, update) {
+        panic!("{message}");
+    }
+}
+
+/// Renders the combined workflow of `cfg` and compares it with the golden
+/// file at `path`.
+///
+/// # Panics
+/// Panics if rendering fails or the workflow differs (see [`assert_golden`]).
+pub fn assert_workflow_golden(cfg: &DevflowConfig, path: impl AsRef<Path>) {
+    let workflow = devflow_gh::render_workflow(cfg)
+        .unwrap_or_else(|e| panic!("failed to render workflow: {e:#}"));
+    assert_golden(path, &workflow);
+}
+
+fn check(path: &Path, actual: &str, update: bool) -> Result<(), String> {
+    if update {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        return fs::write(path, actual)
+            .map_err(|e| format!("failed to write golden file '{}': {e}", path.display()));
+    }
+    let expected = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "failed to read golden file '{}': {e}; run with {UPDATE_ENV}=1 to create it",
+            path.display()
+        )
+    })?;
+    if expected == actual {
+        return Ok(());
+    }
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (None, None) => {
+                return Err(format!(
+                    "output differs from golden file '{}' in line endings or the final newline",
+                    path.display()
+                ));
+            }
+            (e, a) => {
+                return Err(format!(
+                    "output differs from golden file '{}' at line {line}:\n  expected: {}\n    actual: {}\nrun with {UPDATE_ENV}=1 to accept the new output",
+                    path.display(),
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of output>")
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestProject;
+
+    #[test]
+    fn reports_the_first_differing_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("golden/out.txt");
+        let err = check(&path, "a\n", false).unwrap_err();
+        assert!(
+            err.contains("DEVFLOW_UPDATE_GOLDEN=1 to create it"),
+            "{err}"
+        );
+
+        check(&path, "one\ntwo\n", true).unwrap();
+        check(&path, "one\ntwo\n", false).unwrap();
+        let err = check(&path, "one\nthree\n", false).unwrap_err();
+        assert!(
+            err.contains("at line 2:\n  expected: two\n    actual: three"),
+            "{err}"
+        );
+        let err = check(&path, "one\ntwo\nthree\n", false).unwrap_err();
+        assert!(err.contains("expected: <end of file>"), "{err}");
+        let err = check(&path, "one\ntwo", false).unwrap_err();
+        assert!(err.contains("the final newline"), "{err}");
+    }
+
+    #[test]
+    fn workflow_matches_its_golden_file() {
+        let project = TestProject::new(
+            "[project]\nname = \"golden\"\nstack = [\"rust\"]\n\n[targets]\npr = [\"fmt:check\", \"lint:static\", \"build:debug\", \"test:unit\"]\nmain = [\"build:release\", \"test:integration\"]\n",
+        );
+        let cfg = project.config().unwrap();
+        assert_workflow_golden(&cfg, "resources/golden/rust-pr.yml");
+    }
+}
@@ -0,0 +1,46 @@
+//! Test support for devflow contributors and extension authors.
+//!
+//! Integration tests should not need a real toolchain on the machine. This
+//! crate provides:
+//!
+//! - [`FakeExtension`]: an in-process [`Extension`](devflow_core::Extension)
+//!   with scripted actions that records what it was asked to build;
+//! - [`registry`]: an in-memory [`ExtensionRegistry`] of fakes;
+//! - [`ScriptedExtension`]: a `/bin/sh` subprocess extension binary that
+//!   answers `--discover` and `--build-action` from a table;
+//! - [`TestProject`]: a temporary project directory with a `devflow.toml`;
+//! - [`assert_golden`] and [`assert_workflow_golden`]: golden-file checks for
+//!   rendered workflows, refreshed with `DEVFLOW_UPDATE_GOLDEN=1`.
+//!
+//! ```no_run
+//! use devflow_testkit::{registry, FakeExtension, TestProject};
+//!
+//! let project = TestProject::new("[project]\nname = \"demo\"\nstack = [\"fake\"]\n\n[targets]\npr = [\"test:unit\"]\n");
+//! let cfg = project.config().unwrap();
+//! let fake = FakeExtension::new("fake").action("test:unit", "true", &[]);
+//! let calls = fake.calls();
+//! let registry = registry([fake]);
+//! registry.validate_target_support(&cfg).unwrap();
+//! # let _ = calls;
+//! ```
+
+pub mod fake;
+pub mod golden;
+pub mod project;
+pub mod script;
+
+use devflow_core::ExtensionRegistry;
+
+pub use fake::{Calls, FakeExtension};
+pub use golden::{assert_golden, assert_workflow_golden};
+pub use project::TestProject;
+pub use script::{ScriptedBinary, ScriptedExtension};
+
+/// An [`ExtensionRegistry`] holding `extensions`, without discovery.
+pub fn registry(extensions: impl IntoIterator<Item = FakeExtension>) -> ExtensionRegistry {
+    let mut registry = ExtensionRegistry::default();
+    for extension in extensions {
+        registry.register(Box::new(extension));
+    }
+    registry
+}
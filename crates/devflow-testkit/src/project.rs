@@ -0,0 +1,98 @@
+//! Temporary project directories.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tempfile::TempDir;
+
+use devflow_core::constants::CONFIG_FILE;
+use devflow_core::DevflowConfig;
+
+use crate::script::{ScriptedBinary, ScriptedExtension};
+
+/// A project in a temporary directory, removed on drop.
+///
+/// Setup helpers panic on I/O errors, as a failed fixture should fail the
+/// test.
+#[derive(Debug)]
+pub struct TestProject {
+    dir: TempDir,
+}
+
+impl TestProject {
+    /// A project whose `devflow.toml` is `config`.
+    pub fn new(config: &str) -> Self {
+        let project = Self {
+            dir: tempfile::tempdir().expect("failed to create project directory"),
+        };
+        project.file(CONFIG_FILE, config)
+    }
+
+    /// Writes `contents` to `path` relative to the project, creating parent
+    /// directories.
+    pub fn file(self, path: &str, contents: &str) -> Self {
+        let path = self.dir.path().join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("failed to create project subdirectory");
+        }
+        fs::write(&path, contents)
+            .unwrap_or_else(|e| panic!("failed to write '{}': {e}", path.display()));
+        self
+    }
+
+    /// Writes `extension` into the project's `.testkit/bin` and appends its
+    /// `[extensions.<name>]` section to `devflow.toml`.
+    pub fn extension(self, extension: &ScriptedExtension) -> (Self, ScriptedBinary) {
+        let bin = self.dir.path().join(".testkit/bin");
+        fs::create_dir_all(&bin).expect("failed to create extension directory");
+        let binary = extension
+            .write_to(&bin)
+            .expect("failed to write scripted extension");
+        let mut config = fs::read_to_string(self.config_path()).unwrap_or_default();
+        config.push('\n');
+        config.push_str(&binary.config_section());
+        let project = self.file(CONFIG_FILE, &config);
+        (project, binary)
+    }
+
+    /// The project directory.
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The project's `devflow.toml`.
+    pub fn config_path(&self) -> PathBuf {
+        self.dir.path().join(CONFIG_FILE)
+    }
+
+    /// Loads `devflow.toml` as dwf does, with validation.
+    ///
+    /// # Errors
+    /// Returns the load or validation error.
+    pub fn config(&self) -> Result<DevflowConfig> {
+        let path = self.config_path();
+        DevflowConfig::load_from_file(path.to_str().context("non-UTF-8 project path")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_the_config_with_files_and_extensions() {
+        let (project, binary) = TestProject::new(
+            "[project]\nname = \"demo\"\nstack = [\"rust\"]\n\n[targets]\npr = [\"test:it\"]\n",
+        )
+        .file("Cargo.toml", "[package]\nname = \"demo\"\n")
+        .extension(&ScriptedExtension::new("lint").action("lint:static", "true", &[]));
+
+        assert!(project.path().join("Cargo.toml").is_file());
+        assert!(binary.path().starts_with(project.path()));
+        let cfg = project.config().unwrap();
+        assert_eq!(cfg.project.name, "demo");
+        assert_eq!(cfg.targets.profiles["pr"][0].command(), "test:integration");
+        assert!(cfg.extensions.unwrap().contains_key("lint"));
+    }
+}
@@ -0,0 +1,240 @@
+//! Scripted subprocess extension binaries.
+//!
+//! [`ScriptedExtension`] writes a `/bin/sh` script speaking the subprocess
+//! extension protocol: `--discover` prints the capabilities, and
+//! `--build-action` answers each command from a table and exits 1 for the
+//! rest. Every build request is appended to a log next to the script, so
+//! tests can check what dwf asked for. The binary is named
+//! `devflow-ext-<name>`, so putting its directory on `PATH` also exercises
+//! discovery by convention.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use devflow_core::extension::subprocess::SubprocessExtension;
+use devflow_core::tool::ToolRequirement;
+use devflow_core::ExecutionAction;
+
+/// A subprocess extension answering from a table.
+#[derive(Debug, Clone)]
+pub struct ScriptedExtension {
+    name: String,
+    version: Option<String>,
+    capabilities: BTreeSet<String>,
+    /// Actions keyed by canonical or primary command.
+    actions: Vec<(String, ExecutionAction)>,
+    tools: Vec<ToolRequirement>,
+}
+
+impl ScriptedExtension {
+    /// An extension named `name` that handles nothing yet.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: None,
+            capabilities: BTreeSet::new(),
+            actions: Vec::new(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Reports `version` from `--discover`.
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    /// Advertises `capability` without an action for it.
+    pub fn capability(mut self, capability: &str) -> Self {
+        self.capabilities.insert(capability.to_string());
+        self
+    }
+
+    /// Answers `command` (canonical, or primary for every selector without
+    /// its own action) with `program` and `args`.
+    pub fn action(mut self, command: &str, program: &str, args: &[&str]) -> Self {
+        self.capabilities.insert(command.to_string());
+        self.actions.push((
+            command.to_string(),
+            ExecutionAction {
+                program: program.to_string(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                env: Default::default(),
+            },
+        ));
+        self
+    }
+
+    /// Reports `tool` from `--discover`.
+    pub fn tool(mut self, tool: ToolRequirement) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Writes the executable `devflow-ext-<name>` into `dir`.
+    ///
+    /// # Errors
+    /// Returns an error if the script cannot be written.
+    pub fn write_to(&self, dir: &Path) -> Result<ScriptedBinary> {
+        let path = dir.join(format!("devflow-ext-{}", self.name));
+        let log = dir.join(format!("devflow-ext-{}.requests", self.name));
+        fs::write(&path, self.script(&log)?)
+            .with_context(|| format!("failed to write '{}'", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(ScriptedBinary {
+            name: self.name.clone(),
+            path,
+            log,
+            capabilities: self.capabilities.iter().cloned().collect(),
+            tools: self.tools.clone(),
+        })
+    }
+
+    fn script(&self, log: &Path) -> Result<String> {
+        let discover = serde_json::to_string(&json!({
+            "version": self.version,
+            "capabilities": self.capabilities,
+            "tools": self.tools,
+        }))?;
+        let mut script = format!(
+            "#!/bin/sh\n# Generated by devflow-testkit for extension '{}'.\ncase \"$1\" in\n--discover)\n    printf '%s\\n' {}\n    ;;\n--build-action)\n    request=$(cat)\n    printf '%s\\n' \"$request\" >> {}\n    case \"$request\" in\n",
+            self.name,
+            quote(&discover),
+            quote(&log.to_string_lossy())
+        );
+        // Canonical commands first: `case` takes the first matching pattern.
+        let mut actions: Vec<&(String, ExecutionAction)> = self.actions.iter().collect();
+        actions.sort_by_key(|(command, _)| !command.contains(':'));
+        for (command, action) in actions {
+            let pattern = match command.split_once(':') {
+                Some((primary, selector)) => {
+                    format!("\"primary\":\"{primary}\",\"selector\":\"{selector}\",")
+                }
+                None => format!("\"primary\":\"{command}\","),
+            };
+            script.push_str(&format!(
+                "    *{}*)\n        printf '%s\\n' {}\n        ;;\n",
+                quote(&pattern),
+                quote(&serde_json::to_string(action)?)
+            ));
+        }
+        script.push_str("    *)\n        exit 1\n        ;;\n    esac\n    ;;\n*)\n    echo \"usage: $0 --discover | --build-action\" >&2\n    exit 2\n    ;;\nesac\n");
+        Ok(script)
+    }
+}
+
+/// A written [`ScriptedExtension`].
+#[derive(Debug, Clone)]
+pub struct ScriptedBinary {
+    name: String,
+    path: PathBuf,
+    log: PathBuf,
+    capabilities: Vec<String>,
+    tools: Vec<ToolRequirement>,
+}
+
+impl ScriptedBinary {
+    /// The script.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The `[extensions.<name>]` section that loads this binary by path.
+    pub fn config_section(&self) -> String {
+        format!(
+            "[extensions.{}]\nsource = \"path\"\npath = {}\n",
+            self.name,
+            serde_json::Value::String(self.path.to_string_lossy().into_owned())
+        )
+    }
+
+    /// The extension as discovery registers it, untrusted.
+    pub fn extension(&self) -> SubprocessExtension {
+        SubprocessExtension::new(
+            self.name.clone(),
+            self.path.to_string_lossy().into_owned(),
+            self.capabilities.iter().cloned().collect(),
+            false,
+        )
+        .with_tools(self.tools.clone())
+    }
+
+    /// The `--build-action` requests received so far, in order.
+    ///
+    /// # Errors
+    /// Returns an error if the log cannot be read or holds invalid JSON.
+    pub fn requests(&self) -> Result<Vec<serde_json::Value>> {
+        if !self.log.exists() {
+            return Ok(Vec::new());
+        }
+        fs::read_to_string(&self.log)?
+            .lines()
+            .map(|line| serde_json::from_str(line).context("invalid request in log"))
+            .collect()
+    }
+}
+
+/// Quotes `text` as one shell word.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use devflow_core::{ActionContext, CommandRef, Extension, TargetOs};
+    use std::process::Command;
+    use std::str::FromStr;
+
+    #[test]
+    fn script_answers_the_subprocess_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = ScriptedExtension::new("fake")
+            .version("1.2.0")
+            .capability("fmt:check")
+            .action("test", "echo", &["any test"])
+            .action("test:unit", "echo", &["it's unit"])
+            .write_to(dir.path())
+            .unwrap();
+
+        let output = Command::new(binary.path())
+            .arg("--discover")
+            .output()
+            .unwrap();
+        let discover: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(discover["version"], "1.2.0");
+        assert_eq!(
+            discover["capabilities"],
+            json!(["fmt:check", "test", "test:unit"])
+        );
+
+        let ext = binary.extension();
+        let unit = CommandRef::from_str("test:unit").unwrap();
+        let action = ext.build_action(&unit).unwrap().unwrap();
+        assert_eq!(action.args, ["it's unit"]);
+        let ctx = ActionContext {
+            os: TargetOs::Macos,
+        };
+        let smoke = CommandRef::from_str("test:smoke").unwrap();
+        let action = ext.build_action_for(&smoke, &ctx).unwrap().unwrap();
+        assert_eq!(action.args, ["any test"]);
+        let fmt = CommandRef::from_str("fmt:check").unwrap();
+        assert!(ext.build_action(&fmt).unwrap().is_none());
+
+        let requests = binary.requests().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1]["selector"], "smoke");
+        assert_eq!(requests[1]["os"], "macos");
+        assert!(binary
+            .config_section()
+            .starts_with("[extensions.fake]\nsource = \"path\"\npath = \""));
+    }
+}